@@ -0,0 +1,268 @@
+use base64::Engine;
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::clipper::{self, LocalizedImage};
+use crate::error::CommandError;
+use crate::import::{self, ImportFailure};
+use crate::links::{NameLookup, Resolver};
+use crate::note_index::NoteIndexRegistry;
+use crate::rename;
+use crate::settings::{self, SettingsStore};
+use crate::vault_config;
+use crate::WriteResult;
+
+/// File contents as a byte array or a base64 string, which may be a
+/// `data:` URL
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub enum AttachmentData {
+    Bytes(Vec<u8>),
+    Base64(String),
+}
+
+impl AttachmentData {
+    fn into_bytes(self) -> Result<Vec<u8>, String> {
+        match self {
+            AttachmentData::Bytes(bytes) => Ok(bytes),
+            AttachmentData::Base64(text) => {
+                let encoded = match text.split_once(',') {
+                    Some((prefix, data)) if prefix.starts_with("data:") => data,
+                    _ => text.as_str(),
+                };
+                let encoded: String = encoded.chars().filter(|c| !c.is_whitespace()).collect();
+                base64::engine::general_purpose::STANDARD
+                    .decode(encoded)
+                    .map_err(|e| format!("Failed to decode attachment: {}", e))
+            }
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct SavedAttachment {
+    /// Path of the attachment
+    pub path: String,
+    /// Link target for the attachment, relative to the note
+    pub link: String,
+    /// An identical file was already in the attachments folder and was
+    /// reused
+    pub existing: bool,
+}
+
+/// The folder attachments of `note` go in, per the vault's setting
+pub fn attachments_dir(
+    note: &Path,
+    registry: &NoteIndexRegistry,
+    settings: &Mutex<SettingsStore>,
+) -> Result<PathBuf, String> {
+    let root = registry.root_for(note);
+    let dir = vault_config::resolve(&root, settings)?.attachments_dir;
+    let note_dir = note.parent().unwrap_or(&root);
+    Ok(match dir.strip_prefix("./") {
+        Some(relative) => note_dir.join(relative),
+        None if dir == "." => note_dir.to_path_buf(),
+        None if dir == "/" => root,
+        None => root.join(dir),
+    })
+}
+
+/// Extension for file contents recognized by their leading bytes
+fn sniff_extension(data: &[u8]) -> &'static str {
+    match data {
+        [0x89, b'P', b'N', b'G', ..] => "png",
+        [0xFF, 0xD8, 0xFF, ..] => "jpg",
+        [b'G', b'I', b'F', b'8', ..] => "gif",
+        [b'R', b'I', b'F', b'F', _, _, _, _, b'W', b'E', b'B', b'P', ..] => "webp",
+        [b'%', b'P', b'D', b'F', ..] => "pdf",
+        _ if data.starts_with(b"<svg") || data.starts_with(b"<?xml") => "svg",
+        _ => "bin",
+    }
+}
+
+/// A file in `dir` whose contents are `data`
+pub fn find_identical(dir: &Path, data: &[u8]) -> Option<PathBuf> {
+    let hash = crate::content_hash(data);
+    fs::read_dir(dir)
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.metadata().is_ok_and(|m| m.is_file() && m.len() == data.len() as u64))
+        .map(|entry| entry.path())
+        .find(|path| fs::read(path).is_ok_and(|existing| crate::content_hash(&existing) == hash))
+}
+
+/// Save a file pasted or dropped into `note_path` in the vault's attachments
+/// folder and return the link to insert. A file with the same contents
+/// already there is reused instead of saving a copy.
+#[tauri::command(async)]
+pub fn save_attachment(
+    data: AttachmentData,
+    suggested_name: Option<String>,
+    note_path: String,
+    registry: tauri::State<'_, NoteIndexRegistry>,
+    settings: tauri::State<'_, Mutex<SettingsStore>>,
+) -> Result<SavedAttachment, CommandError> {
+    let data = data.into_bytes()?;
+    let note = PathBuf::from(&note_path);
+    let dir = attachments_dir(&note, &registry, &settings)?;
+    settings::check_writable(&settings, &dir)?;
+
+    let (path, existing) = match find_identical(&dir, &data) {
+        Some(path) => (path, true),
+        None => {
+            let name = match suggested_name.as_deref().map(str::trim) {
+                Some(name) if !name.is_empty() => import::safe_file_name(name),
+                _ => format!("Pasted image {}", Local::now().format("%Y%m%d%H%M%S")),
+            };
+            let name = if Path::new(&name).extension().is_some() {
+                name
+            } else {
+                format!("{}.{}", name, sniff_extension(&data))
+            };
+            fs::create_dir_all(&dir).map_err(|e| format!("Failed to create directory: {}", e))?;
+            let path = import::unique_path(&dir, &name);
+            crate::write_atomic(&path, &data).map_err(|e| format!("Failed to write file: {}", e))?;
+            if let Some(index) = registry.for_path(&path) {
+                index.update_path(&path);
+            }
+            (path, false)
+        }
+    };
+
+    let note_dir = note.parent().unwrap_or(Path::new(""));
+    let link = import::link_target(&rename::relative_path(note_dir, &path));
+    Ok(SavedAttachment {
+        path: path.to_string_lossy().to_string(),
+        link,
+        existing,
+    })
+}
+
+#[derive(Debug, Serialize)]
+pub struct LocalizeReport {
+    /// Images downloaded, in the order they appear in the note
+    pub localized: Vec<LocalizedImage>,
+    /// Images that couldn't be downloaded; their links are left as they were
+    pub failed: Vec<ImportFailure>,
+    /// The note as written, or `None` if no link changed
+    pub written: Option<WriteResult>,
+}
+
+/// Download the remote (`http(s)`) images a note embeds into the vault's
+/// attachments folder and rewrite their links to the local copies
+#[tauri::command(async)]
+pub fn localize_images(
+    path: String,
+    registry: tauri::State<'_, NoteIndexRegistry>,
+    settings: tauri::State<'_, Mutex<SettingsStore>>,
+) -> Result<LocalizeReport, CommandError> {
+    let path_buf = PathBuf::from(&path);
+    settings::check_writable(&settings, &path_buf)?;
+    let content = fs::read_to_string(&path_buf).map_err(|e| format!("Failed to read file: {}", e))?;
+    let dir = attachments_dir(&path_buf, &registry, &settings)?;
+    let note_dir = path_buf.parent().unwrap_or(Path::new(""));
+
+    let mut failed = Vec::new();
+    let (updated, localized) = clipper::localize(&content, note_dir, &dir, &clipper::agent(), &mut failed);
+    let written = if updated == content {
+        None
+    } else {
+        crate::write_atomic(&path_buf, updated.as_bytes()).map_err(|e| format!("Failed to write file: {}", e))?;
+        if let Some(index) = registry.for_path(&path_buf) {
+            index.update_path(&path_buf);
+        }
+        Some(WriteResult {
+            mtime: fs::metadata(&path_buf).ok().as_ref().and_then(crate::mtime_millis),
+            hash: crate::content_hash(updated.as_bytes()),
+        })
+    };
+    Ok(LocalizeReport {
+        localized,
+        failed,
+        written,
+    })
+}
+
+#[derive(Debug, Serialize)]
+pub struct OrphanAttachment {
+    pub path: String,
+    /// Size in bytes
+    pub size: u64,
+}
+
+/// Whether `path` is in an attachments folder of the vault at `root`, per
+/// its `attachments_dir` setting. Note-relative settings match a folder of
+/// that name anywhere in the vault.
+fn in_attachments_folder(path: &Path, root: &Path, dir: &str) -> bool {
+    if dir == "." || dir == "/" {
+        return true;
+    }
+    match dir.strip_prefix("./") {
+        Some(relative) => path
+            .ancestors()
+            .skip(1)
+            .take_while(|ancestor| ancestor.starts_with(root))
+            .any(|ancestor| ancestor.ends_with(relative)),
+        None => path.starts_with(root.join(dir)),
+    }
+}
+
+/// Attachments in the vault's attachments folder that no note links to or
+/// embeds. With `trash` set they are also moved to the system trash.
+#[tauri::command(async)]
+pub fn find_orphan_attachments(
+    root: String,
+    trash: Option<bool>,
+    registry: tauri::State<'_, NoteIndexRegistry>,
+    settings: tauri::State<'_, Mutex<SettingsStore>>,
+) -> Result<Vec<OrphanAttachment>, CommandError> {
+    let root = PathBuf::from(&root);
+    if trash.unwrap_or(false) {
+        settings::check_writable(&settings, &root)?;
+    }
+    let index = registry.for_vault(&root, &settings)?;
+    let dir = vault_config::resolve(index.root(), &settings)?.attachments_dir;
+
+    let orphans: Vec<PathBuf> = {
+        let contents = index.contents()?;
+        let names = NameLookup::new(&contents);
+        let resolver = Resolver {
+            root: index.root(),
+            note_extensions: index.note_extensions(),
+            names: &names,
+        };
+        let referenced: HashSet<PathBuf> = contents
+            .notes
+            .iter()
+            .flat_map(|(source, note)| note.links.iter().filter_map(|link| resolver.resolve(source, link)))
+            .collect();
+        let mut orphans: Vec<PathBuf> = contents
+            .attachments
+            .iter()
+            .filter(|path| path.starts_with(&root) && in_attachments_folder(path, index.root(), &dir))
+            .filter(|path| !referenced.contains(*path))
+            .cloned()
+            .collect();
+        orphans.sort();
+        orphans
+    };
+
+    let found = orphans
+        .iter()
+        .map(|path| OrphanAttachment {
+            path: path.to_string_lossy().to_string(),
+            size: fs::metadata(path).map(|m| m.len()).unwrap_or(0),
+        })
+        .collect();
+    if trash.unwrap_or(false) && !orphans.is_empty() {
+        trash::delete_all(&orphans).map_err(|e| format!("Failed to move to trash: {}", e))?;
+        for path in &orphans {
+            index.update_path(path);
+        }
+    }
+    Ok(found)
+}
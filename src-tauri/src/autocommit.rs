@@ -0,0 +1,169 @@
+use chrono::Local;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::error::CommandError;
+use crate::git::git;
+use crate::settings::{self, SettingsStore};
+
+/// How often vaults with unsaved-to-git changes are checked
+const TICK: Duration = Duration::from_secs(15);
+
+/// Files named in a `{files}` commit message before the rest are counted
+const MAX_LISTED_FILES: usize = 10;
+
+/// Vaults with saves since their last auto-commit
+#[derive(Default)]
+pub struct AutocommitState {
+    /// Vault root to the time of its latest save
+    pending: Mutex<HashMap<PathBuf, Instant>>,
+}
+
+impl AutocommitState {
+    /// Note a save in the vault at `root`, restarting its quiet period
+    pub fn note_write(&self, root: &Path) {
+        if let Ok(mut pending) = self.pending.lock() {
+            pending.insert(root.to_path_buf(), Instant::now());
+        }
+    }
+
+    /// Take the vaults that are due a commit: those quiet for their interval,
+    /// or every one with auto-commit on when `all` is set. Vaults with
+    /// auto-commit off are dropped.
+    fn take_due(&self, settings: &Mutex<SettingsStore>, all: bool) -> Vec<PathBuf> {
+        let Ok(mut pending) = self.pending.lock() else {
+            return Vec::new();
+        };
+        let mut due = Vec::new();
+        pending.retain(|root, saved| {
+            let Ok(vault) = settings::vault_settings(settings, root) else {
+                return true;
+            };
+            if !vault.autocommit.enabled {
+                return false;
+            }
+            let interval = Duration::from_secs(vault.autocommit.interval_minutes.max(1) * 60);
+            if all || saved.elapsed() >= interval {
+                due.push(root.clone());
+                return false;
+            }
+            true
+        });
+        due
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AutocommitEvent {
+    pub root: String,
+    /// Hash of the new commit; `None` if there was nothing to commit or the
+    /// commit failed
+    pub commit: Option<String>,
+    /// Number of files committed
+    pub files: usize,
+    pub message: Option<String>,
+    pub error: Option<String>,
+}
+
+/// Paths in `git status --porcelain -z` output
+fn changed_paths(status: &str) -> Vec<String> {
+    let mut paths = Vec::new();
+    let mut records = status.split('\0').filter(|record| record.len() > 3);
+    while let Some(record) = records.next() {
+        paths.push(record[3..].to_string());
+        // Renames and copies are followed by the original path
+        if matches!(record.as_bytes()[0], b'R' | b'C') {
+            records.next();
+        }
+    }
+    paths
+}
+
+/// `template` with `{date}`, `{count}` and `{files}` filled in
+fn commit_message(template: &str, paths: &[String]) -> String {
+    let mut files = paths.iter().take(MAX_LISTED_FILES).cloned().collect::<Vec<_>>().join(", ");
+    if paths.len() > MAX_LISTED_FILES {
+        files.push_str(&format!(" and {} more", paths.len() - MAX_LISTED_FILES));
+    }
+    template
+        .replace("{date}", &Local::now().format("%Y-%m-%d %H:%M:%S").to_string())
+        .replace("{count}", &paths.len().to_string())
+        .replace("{files}", &files)
+}
+
+/// Commit every change under `root` with a message from `template`.
+/// Returns the commit hash, or `None` if there was nothing to commit.
+fn commit_vault(root: &Path, template: &str) -> Result<(Option<String>, usize, String), CommandError> {
+    let status = git(root, &["status", "--porcelain", "-z", "--untracked-files=all", "--", "."])?;
+    let paths = changed_paths(&status);
+    let message = commit_message(template, &paths);
+    if paths.is_empty() {
+        return Ok((None, 0, message));
+    }
+    git(root, &["add", "--all", "--", "."])?;
+    // Limited to the vault, so changes staged elsewhere in the repository
+    // aren't swept in
+    git(root, &["commit", "--quiet", "--no-verify", "--message", &message, "--", "."])?;
+    let hash = git(root, &["rev-parse", "HEAD"])?.trim().to_string();
+    Ok((Some(hash), paths.len(), message))
+}
+
+/// Commit the vault at `root` and report the result as a
+/// `git-autocommit` event
+fn run(app: &AppHandle, root: &Path) -> AutocommitEvent {
+    let settings = app.state::<Mutex<SettingsStore>>();
+    let template = settings::vault_settings(&settings, root)
+        .map(|vault| vault.autocommit.message)
+        .unwrap_or_default();
+    let event = match commit_vault(root, &template) {
+        Ok((commit, files, message)) => AutocommitEvent {
+            root: root.to_string_lossy().to_string(),
+            commit,
+            files,
+            message: Some(message),
+            error: None,
+        },
+        Err(e) => AutocommitEvent {
+            root: root.to_string_lossy().to_string(),
+            commit: None,
+            files: 0,
+            message: None,
+            error: Some(e.to_string()),
+        },
+    };
+    let _ = app.emit("git-autocommit", event.clone());
+    event
+}
+
+/// Start the thread that commits vaults once they have been quiet for their
+/// auto-commit interval
+pub fn spawn(app: AppHandle) {
+    thread::spawn(move || loop {
+        thread::sleep(TICK);
+        let due = {
+            let state = app.state::<AutocommitState>();
+            let settings = app.state::<Mutex<SettingsStore>>();
+            state.take_due(&settings, false)
+        };
+        for root in due {
+            run(&app, &root);
+        }
+    });
+}
+
+/// Commit the pending changes of every vault with auto-commit on right
+/// away, e.g. when the app loses focus
+#[tauri::command(async)]
+pub fn flush_autocommit(
+    app: AppHandle,
+    state: tauri::State<'_, AutocommitState>,
+    settings: tauri::State<'_, Mutex<SettingsStore>>,
+) -> Result<Vec<AutocommitEvent>, String> {
+    let due = state.take_due(&settings, true);
+    Ok(due.iter().map(|root| run(&app, root)).collect())
+}
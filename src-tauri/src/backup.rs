@@ -0,0 +1,379 @@
+use chrono::{Datelike, Local, NaiveDateTime, TimeZone, Timelike};
+use serde::Serialize;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Read};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, SystemTime};
+use tauri::{AppHandle, Emitter, Manager};
+use zip::write::SimpleFileOptions;
+
+use crate::error::CommandError;
+use crate::metadata_cache::MetadataCache;
+use crate::note_index::NoteIndexRegistry;
+use crate::sandbox::Sandbox;
+use crate::settings::{self, BackupSchedule, SettingsStore};
+
+/// How often vaults with daily backups are checked
+const TICK: Duration = Duration::from_secs(10 * 60);
+
+const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Timestamp in archive names, e.g. `Notes-20240315-093000.zip`
+const NAME_FORMAT: &str = "%Y%m%d-%H%M%S";
+
+/// Where backups go when a vault doesn't choose a folder
+pub struct Backups {
+    data_dir: PathBuf,
+}
+
+impl Backups {
+    pub fn new(data_dir: PathBuf) -> Self {
+        Backups { data_dir }
+    }
+
+    /// The folder backups of the vault at `root` are written to: `out_dir`
+    /// if given, else the vault's backup folder, else one of its own in the
+    /// app's data folder
+    fn dir(&self, root: &Path, out_dir: Option<&str>, settings: &Mutex<SettingsStore>) -> Result<PathBuf, String> {
+        if let Some(dir) = out_dir {
+            return Ok(PathBuf::from(dir));
+        }
+        if let Some(dir) = settings::vault_settings(settings, root)?.backup.dir {
+            return Ok(PathBuf::from(dir));
+        }
+        let key = crate::content_hash(root.to_string_lossy().as_bytes());
+        Ok(self.data_dir.join(format!("{}-{}", vault_name(root), &key[..16])))
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BackupInfo {
+    pub path: String,
+    pub name: String,
+    /// When the backup was made, in milliseconds since the Unix epoch
+    pub created: u64,
+    /// Size of the archive in bytes
+    pub size: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BackupEvent {
+    pub root: String,
+    /// The backup made; `None` if nothing changed since the last one or it
+    /// failed
+    pub backup: Option<BackupInfo>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RestoreSummary {
+    /// Files written from the backup
+    pub restored: usize,
+    /// Files already as they are in the backup
+    pub unchanged: usize,
+}
+
+fn vault_name(root: &Path) -> String {
+    root.file_name().map_or_else(|| "vault".to_string(), |name| name.to_string_lossy().to_string())
+}
+
+/// The backup at `path` if it is one of the vault at `root`, by its name
+fn backup_info(root: &Path, path: &Path) -> Option<BackupInfo> {
+    let name = path.file_name()?.to_string_lossy().to_string();
+    let stamp = name
+        .strip_prefix(&vault_name(root))?
+        .strip_prefix('-')?
+        .strip_suffix(".zip")?;
+    let created = NaiveDateTime::parse_from_str(stamp, NAME_FORMAT).ok()?;
+    let created = Local.from_local_datetime(&created).earliest()?.timestamp_millis();
+    Some(BackupInfo {
+        path: path.to_string_lossy().to_string(),
+        name,
+        created: created.max(0) as u64,
+        size: fs::metadata(path).ok()?.len(),
+    })
+}
+
+/// Backups of the vault at `root` in `dir`, newest first
+fn list(root: &Path, dir: &Path) -> Result<Vec<BackupInfo>, String> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(format!("Failed to read backup folder: {}", e)),
+    };
+    let mut backups: Vec<BackupInfo> = entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| backup_info(root, &entry.path()))
+        .collect();
+    backups.sort_by(|a, b| b.created.cmp(&a.created).then_with(|| b.name.cmp(&a.name)));
+    Ok(backups)
+}
+
+/// Files of the vault at `root` to back up, skipping ignored ones and
+/// anything in `dir`
+fn files(root: &Path, dir: &Path, ignore_globs: &[String]) -> Result<Vec<PathBuf>, String> {
+    let walker = crate::ignore_rules::walker(root, ignore_globs)?;
+    let mut files: Vec<PathBuf> = walker
+        .build()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_some_and(|t| t.is_file()))
+        .map(|entry| entry.into_path())
+        .filter(|path| !path.starts_with(dir))
+        .collect();
+    files.sort();
+    Ok(files)
+}
+
+fn zip_time(time: SystemTime) -> Option<zip::DateTime> {
+    let time = chrono::DateTime::<Local>::from(time);
+    zip::DateTime::from_date_and_time(
+        u16::try_from(time.year()).ok()?,
+        time.month() as u8,
+        time.day() as u8,
+        time.hour() as u8,
+        time.minute() as u8,
+        time.second() as u8,
+    )
+    .ok()
+}
+
+/// Write `files` under `root` to a new timestamped archive in `dir`
+fn create(root: &Path, dir: &Path, files: &[PathBuf]) -> Result<BackupInfo, String> {
+    fs::create_dir_all(dir).map_err(|e| format!("Failed to create backup folder: {}", e))?;
+    // Written under a temporary name, so a backup cut short isn't listed
+    let temp = tempfile::Builder::new()
+        .prefix(".readmark-")
+        .suffix(".zip")
+        .tempfile_in(dir)
+        .map_err(|e| format!("Failed to create backup: {}", e))?;
+    let mut archive = zip::ZipWriter::new(BufWriter::new(temp.as_file()));
+    for path in files {
+        let Ok(relative) = path.strip_prefix(root) else {
+            continue;
+        };
+        let name = relative.to_string_lossy().replace('\\', "/");
+        let metadata = fs::metadata(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        let mut options = SimpleFileOptions::default()
+            .compression_method(zip::CompressionMethod::Deflated)
+            .large_file(metadata.len() >= u32::MAX as u64);
+        if let Some(time) = metadata.modified().ok().and_then(zip_time) {
+            options = options.last_modified_time(time);
+        }
+        let mut file = File::open(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        archive.start_file(name, options).map_err(|e| format!("Failed to write backup: {}", e))?;
+        io::copy(&mut file, &mut archive).map_err(|e| format!("Failed to back up {}: {}", path.display(), e))?;
+    }
+    archive
+        .finish()
+        .map_err(|e| format!("Failed to write backup: {}", e))?
+        .into_inner()
+        .map_err(|e| format!("Failed to write backup: {}", e.error()))?;
+
+    let name = format!("{}-{}.zip", vault_name(root), Local::now().format(NAME_FORMAT));
+    let path = dir.join(&name);
+    temp.persist(&path).map_err(|e| format!("Failed to save backup: {}", e.error))?;
+    backup_info(root, &path).ok_or_else(|| format!("Failed to read backup: {}", path.display()))
+}
+
+/// Remove all but the newest `keep` backups of the vault at `root` in `dir`
+fn prune(root: &Path, dir: &Path, keep: usize) -> Result<(), String> {
+    if keep == 0 {
+        return Ok(());
+    }
+    for old in list(root, dir)?.iter().skip(keep) {
+        fs::remove_file(&old.path).map_err(|e| format!("Failed to remove old backup {}: {}", old.name, e))?;
+    }
+    Ok(())
+}
+
+/// Back up the vault at `root` to `dir` and prune old backups. With
+/// `only_if_changed`, nothing is written when no file changed since the
+/// newest backup.
+fn back_up(
+    root: &Path,
+    dir: &Path,
+    only_if_changed: bool,
+    settings: &Mutex<SettingsStore>,
+) -> Result<Option<BackupInfo>, String> {
+    let ignore_globs = settings::ignore_globs(settings, &[])?;
+    let files = files(root, dir, &ignore_globs)?;
+    if only_if_changed {
+        // The archive's own time is finer than the one in its name
+        let newest = list(root, dir)?.first().map(|newest| {
+            let written = fs::metadata(&newest.path).ok().as_ref().and_then(crate::mtime_millis);
+            written.unwrap_or(newest.created)
+        });
+        if let Some(newest) = newest {
+            let changed = files.iter().any(|path| {
+                fs::metadata(path).ok().as_ref().and_then(crate::mtime_millis).is_some_and(|mtime| mtime > newest)
+            });
+            if !changed {
+                return Ok(None);
+            }
+        }
+    }
+    let backup = create(root, dir, &files)?;
+    prune(root, dir, settings::vault_settings(settings, root)?.backup.keep)?;
+    Ok(Some(backup))
+}
+
+/// Back up the vault at `root` on schedule and report the result as a
+/// `vault-backup` event
+fn run(app: &AppHandle, root: &Path) {
+    let settings = app.state::<Mutex<SettingsStore>>();
+    let result = app
+        .state::<Backups>()
+        .dir(root, None, &settings)
+        .and_then(|dir| back_up(root, &dir, true, &settings));
+    let (backup, error) = match result {
+        Ok(backup) => (backup, None),
+        Err(e) => (None, Some(e)),
+    };
+    let event = BackupEvent {
+        root: root.to_string_lossy().to_string(),
+        backup,
+        error,
+    };
+    let _ = app.emit("vault-backup", event);
+}
+
+/// Roots of the vaults backed up on `schedule`
+fn scheduled(app: &AppHandle, schedule: BackupSchedule) -> Vec<PathBuf> {
+    let settings = app.state::<Mutex<SettingsStore>>();
+    let Ok(store) = settings.lock() else {
+        return Vec::new();
+    };
+    store
+        .settings()
+        .vaults
+        .iter()
+        .filter(|(_, vault)| vault.backup.schedule == schedule)
+        .map(|(root, _)| PathBuf::from(root))
+        .filter(|root| root.is_dir())
+        .collect()
+}
+
+/// Start the thread that backs up vaults with daily backups once their
+/// newest backup is a day old
+pub fn spawn(app: AppHandle) {
+    thread::spawn(move || loop {
+        for root in scheduled(&app, BackupSchedule::Daily) {
+            let settings = app.state::<Mutex<SettingsStore>>();
+            let Ok(dir) = app.state::<Backups>().dir(&root, None, &settings) else {
+                continue;
+            };
+            let newest = list(&root, &dir).ok().and_then(|backups| backups.first().map(|backup| backup.created));
+            let now = Local::now().timestamp_millis().max(0) as u64;
+            if newest.is_none_or(|newest| now.saturating_sub(newest) >= DAY.as_millis() as u64) {
+                run(&app, &root);
+            }
+        }
+        thread::sleep(TICK);
+    });
+}
+
+/// Back up vaults with backups on close, as the app quits
+pub fn on_exit(app: &AppHandle) {
+    for root in scheduled(app, BackupSchedule::OnClose) {
+        run(app, &root);
+    }
+}
+
+/// Back up the vault at `root` to a timestamped zip archive in `out_dir`,
+/// or the vault's backup folder, leaving out ignored files. Old backups
+/// beyond the vault's limit are removed.
+#[tauri::command(async)]
+pub fn backup_vault(
+    root: String,
+    out_dir: Option<String>,
+    backups: tauri::State<'_, Backups>,
+    settings: tauri::State<'_, Mutex<SettingsStore>>,
+) -> Result<BackupInfo, String> {
+    let root = PathBuf::from(&root);
+    crate::ensure_dir(&root)?;
+    let dir = backups.dir(&root, out_dir.as_deref(), &settings)?;
+    back_up(&root, &dir, false, &settings)?.ok_or_else(|| "Nothing was backed up".to_string())
+}
+
+/// Backups of the vault at `root` in `out_dir`, or the vault's backup
+/// folder, newest first
+#[tauri::command]
+pub fn list_backups(
+    root: String,
+    out_dir: Option<String>,
+    backups: tauri::State<'_, Backups>,
+    sandbox: tauri::State<'_, Sandbox>,
+    settings: tauri::State<'_, Mutex<SettingsStore>>,
+) -> Result<Vec<BackupInfo>, String> {
+    let root = PathBuf::from(&root);
+    let dir = backups.dir(&root, out_dir.as_deref(), &settings)?;
+    let listed = list(&root, &dir)?;
+    // Listed backups can be restored wherever they are kept
+    for backup in &listed {
+        sandbox.allow_file(Path::new(&backup.path));
+    }
+    Ok(listed)
+}
+
+/// Restore the files in the backup at `zip_path` into the vault at `root`.
+/// Files that differ from the backup are overwritten, with their current
+/// contents kept in the file history; files the backup doesn't have are
+/// left alone.
+#[tauri::command(async)]
+pub fn restore_backup(
+    zip_path: String,
+    root: String,
+    registry: tauri::State<'_, NoteIndexRegistry>,
+    settings: tauri::State<'_, Mutex<SettingsStore>>,
+    cache: tauri::State<'_, MetadataCache>,
+) -> Result<RestoreSummary, CommandError> {
+    let root = PathBuf::from(&root);
+    crate::ensure_dir(&root)?;
+    settings::check_writable(&settings, &root)?;
+    let file = File::open(&zip_path).map_err(|e| format!("Failed to open backup: {}", e))?;
+    let mut archive = zip::ZipArchive::new(file).map_err(|e| format!("Failed to open backup: {}", e))?;
+
+    let note_extensions = settings::note_extensions(&settings)?;
+    let mut summary = RestoreSummary {
+        restored: 0,
+        unchanged: 0,
+    };
+    for i in 0..archive.len() {
+        let mut entry = archive.by_index(i).map_err(|e| format!("Failed to read backup: {}", e))?;
+        if entry.is_dir() {
+            continue;
+        }
+        // Names that would escape the vault are skipped
+        let Some(relative) = entry.enclosed_name() else {
+            continue;
+        };
+        let mut content = Vec::new();
+        entry
+            .read_to_end(&mut content)
+            .map_err(|e| format!("Failed to read {} from the backup: {}", relative.display(), e))?;
+
+        let path = root.join(&relative);
+        let previous = fs::read(&path).ok();
+        if previous.as_deref() == Some(content.as_slice()) {
+            summary.unchanged += 1;
+            continue;
+        }
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|e| format!("Failed to create directory: {}", e))?;
+        }
+        crate::write_atomic(&path, &content).map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+        if settings::has_note_extension(&path, &note_extensions) {
+            crate::note_written(&path, previous.as_deref(), &content, &registry, &settings, &cache);
+        } else {
+            // Attachments aren't kept in the file history
+            if let Some(index) = registry.for_path(&path) {
+                index.update_path(&path);
+            }
+            cache.invalidate(&path);
+        }
+        summary.restored += 1;
+    }
+    Ok(summary)
+}
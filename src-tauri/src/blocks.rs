@@ -0,0 +1,103 @@
+use aes_gcm::aead::rand_core::RngCore;
+use aes_gcm::aead::OsRng;
+use regex::Regex;
+use serde::Serialize;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Mutex, OnceLock};
+
+use crate::error::CommandError;
+use crate::markdown;
+use crate::metadata_cache::MetadataCache;
+use crate::note_index::NoteIndexRegistry;
+use crate::search::line_starts;
+use crate::settings::{self, SettingsStore};
+
+/// Length of generated block ids, as in Obsidian
+const BLOCK_ID_LEN: usize = 6;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BlockId {
+    /// The id, without the `^`
+    pub id: String,
+    /// 1-based line the id ends
+    pub line: usize,
+}
+
+/// A `^block-id` at the end of a line, alone or after a space
+pub fn block_id_regex() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| Regex::new(r"(?:^|\s)\^([A-Za-z0-9_-]+)\s*$").expect("valid block id regex"))
+}
+
+/// Every `^block-id` anchor in `content`, in order. The frontmatter is
+/// skipped.
+pub fn block_ids(content: &str) -> Vec<BlockId> {
+    let body_line = content[..markdown::body_start(content)].matches('\n').count();
+    content
+        .lines()
+        .enumerate()
+        .skip(body_line)
+        .filter_map(|(i, line)| {
+            let id = block_id_regex().captures(line)?.get(1)?.as_str().to_string();
+            Some(BlockId { id, line: i + 1 })
+        })
+        .collect()
+}
+
+/// 1-based line of the block `^id` among `blocks`
+pub fn find(blocks: &[BlockId], id: &str) -> Option<usize> {
+    blocks.iter().find(|block| block.id == id).map(|block| block.line)
+}
+
+/// A random id of lowercase letters and digits not in `taken`
+fn generate_id(taken: &[BlockId]) -> String {
+    const CHARS: &[u8] = b"abcdefghijklmnopqrstuvwxyz0123456789";
+    loop {
+        let mut bytes = [0u8; BLOCK_ID_LEN];
+        OsRng.fill_bytes(&mut bytes);
+        let id: String = bytes.iter().map(|b| CHARS[*b as usize % CHARS.len()] as char).collect();
+        if find(taken, &id).is_none() {
+            return id;
+        }
+    }
+}
+
+/// Give `line` (1-based) of the note at `path` a `^block-id`, unless it has
+/// one, and return a link to it such as `[[Note#^f3k9x2]]`
+#[tauri::command(async)]
+pub fn ensure_block_id(
+    path: String,
+    line: usize,
+    registry: tauri::State<'_, NoteIndexRegistry>,
+    settings: tauri::State<'_, Mutex<SettingsStore>>,
+    cache: tauri::State<'_, MetadataCache>,
+) -> Result<String, CommandError> {
+    let path_buf = PathBuf::from(&path);
+    let previous = fs::read_to_string(&path_buf).map_err(|e| format!("Failed to read file: {}", e))?;
+    let name = path_buf.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+    let blocks = block_ids(&previous);
+    if let Some(block) = blocks.iter().find(|block| block.line == line) {
+        return Ok(format!("[[{}#^{}]]", name, block.id));
+    }
+
+    settings::check_writable(&settings, &path_buf)?;
+    let starts = line_starts(&previous);
+    let start = *line
+        .checked_sub(1)
+        .and_then(|i| starts.get(i))
+        .ok_or_else(|| format!("Line {} is out of range", line))?;
+    if start < markdown::body_start(&previous) {
+        return Err(format!("Line {} is in the frontmatter", line).into());
+    }
+    let text = previous[start..].split('\n').next().unwrap_or_default().trim_end_matches('\r');
+    if text.trim().is_empty() {
+        return Err(format!("Line {} is empty", line).into());
+    }
+
+    let id = generate_id(&blocks);
+    let mut content = previous.clone();
+    content.insert_str(start + text.trim_end().len(), &format!(" ^{}", id));
+    crate::write_note(&path_buf, Some(previous.as_bytes()), content.as_bytes(), &registry, &settings, &cache)?;
+    Ok(format!("[[{}#^{}]]", name, id))
+}
@@ -0,0 +1,201 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::fs;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use zip::write::SimpleFileOptions;
+
+use crate::encryption;
+use crate::link_style;
+use crate::links::{self, LinkKind, NameLookup, Resolver};
+use crate::note_index::{NoteIndexRegistry, VaultContents};
+use crate::rename;
+use crate::sandbox::Sandbox;
+use crate::settings;
+
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct ZipExportOptions {
+    /// Also bundle the notes the selection links to, the notes those link
+    /// to, and so on
+    pub include_linked_notes: bool,
+    /// Bundle the images and other files the notes embed or link to
+    pub include_attachments: bool,
+}
+
+impl Default for ZipExportOptions {
+    fn default() -> Self {
+        ZipExportOptions {
+            include_linked_notes: false,
+            include_attachments: true,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct ZipExport {
+    pub notes: usize,
+    pub attachments: usize,
+}
+
+/// The notes and attachments of a bundle, and how to resolve their links
+struct Bundle<'a> {
+    resolver: Resolver<'a>,
+    contents: &'a VaultContents,
+    notes: BTreeSet<PathBuf>,
+    attachments: BTreeSet<PathBuf>,
+}
+
+impl Bundle<'_> {
+    /// Add what the notes link to, per `options`
+    fn add_linked(&mut self, options: &ZipExportOptions) {
+        let mut pending: Vec<PathBuf> = self.notes.iter().cloned().collect();
+        while let Some(source) = pending.pop() {
+            let Some(note) = self.contents.notes.get(&source) else {
+                continue;
+            };
+            for target in note.links.iter().filter_map(|link| self.resolver.resolve(&source, link)) {
+                if self.contents.notes.contains_key(&target) {
+                    if options.include_linked_notes && self.notes.insert(target.clone()) {
+                        pending.push(target);
+                    }
+                } else if options.include_attachments && self.contents.attachments.contains(&target) {
+                    self.attachments.insert(target);
+                }
+            }
+        }
+    }
+
+    fn contains(&self, path: &Path) -> bool {
+        self.notes.contains(path) || self.attachments.contains(path)
+    }
+
+    /// `content` of the note at `source` with its links to bundled files as
+    /// markdown links relative to the note, which work wherever the archive
+    /// is unpacked. Links to files left out stay as they are.
+    fn rewrite(&self, content: &str, source: &Path) -> String {
+        let mut rewritten = content.to_string();
+        for link in links::extract(content).iter().rev() {
+            if link.target.is_empty() || links::is_external(&link.target) {
+                continue;
+            }
+            let Some(target) = self.resolver.resolve(source, link).filter(|target| self.contains(target)) else {
+                continue;
+            };
+            let replacement = match link.kind {
+                LinkKind::Wiki => link_style::wiki_to_markdown(&self.resolver, content, source, link),
+                LinkKind::Markdown => {
+                    let Some(dir) = source.parent() else {
+                        continue;
+                    };
+                    let relative = rename::relative_path(dir, &target);
+                    let span = &content[link.start..link.end];
+                    // Already relative, or a reference-style link defined elsewhere
+                    match span.rfind(&link.target) {
+                        Some(_) if links::percent_decode(&link.target) == relative => None,
+                        Some(at) => {
+                            let mut span = span.to_string();
+                            span.replace_range(at..at + link.target.len(), &link_style::encode_target(&relative));
+                            Some(span)
+                        }
+                        None => None,
+                    }
+                }
+            };
+            if let Some(replacement) = replacement {
+                rewritten.replace_range(link.start..link.end, &replacement);
+            }
+        }
+        rewritten
+    }
+}
+
+/// Package the notes at `paths`, and every note under the folders among
+/// them, into a zip archive at `out_path` together with the attachments
+/// they embed or link to. Files keep their place relative to the vault
+/// root, and links between bundled files are rewritten as relative
+/// markdown links, so the archive can be handed off on its own.
+#[tauri::command(async)]
+pub fn export_zip(
+    paths: Vec<String>,
+    out_path: String,
+    options: Option<ZipExportOptions>,
+    registry: tauri::State<'_, NoteIndexRegistry>,
+    sandbox: tauri::State<'_, Sandbox>,
+) -> Result<ZipExport, String> {
+    let options = options.unwrap_or_default();
+    for path in &paths {
+        sandbox.check(path)?;
+    }
+    let selected: Vec<PathBuf> = paths.iter().map(|path| links::normalize_path(Path::new(path))).collect();
+    let first = selected.first().ok_or_else(|| "Nothing to export".to_string())?;
+    let index = registry
+        .for_path(first)
+        .ok_or_else(|| format!("No open vault contains {}", first.display()))?;
+    let root = index.root().to_path_buf();
+    if let Some(outside) = selected.iter().find(|path| !path.starts_with(&root)) {
+        return Err(format!("{} is not in the vault", outside.display()));
+    }
+
+    let contents = index.contents()?;
+    let names = NameLookup::new(&contents);
+    let mut bundle = Bundle {
+        resolver: Resolver {
+            root: &root,
+            note_extensions: index.note_extensions(),
+            names: &names,
+        },
+        contents: &contents,
+        notes: BTreeSet::new(),
+        attachments: BTreeSet::new(),
+    };
+    for path in &selected {
+        if path.is_dir() {
+            bundle.notes.extend(contents.notes.keys().filter(|note| note.starts_with(path)).cloned());
+        } else if contents.notes.contains_key(path) {
+            bundle.notes.insert(path.clone());
+        } else if contents.attachments.contains(path) {
+            bundle.attachments.insert(path.clone());
+        } else {
+            return Err(format!("File does not exist: {}", path.display()));
+        }
+    }
+    bundle.add_linked(&options);
+
+    let out = Path::new(&out_path);
+    if let Some(parent) = out.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("Failed to create directory: {}", e))?;
+    }
+    let temp = tempfile::Builder::new()
+        .prefix(".readmark-")
+        .suffix(".zip")
+        .tempfile_in(out.parent().unwrap_or(Path::new(".")))
+        .map_err(|e| format!("Failed to create archive: {}", e))?;
+    let mut archive = zip::ZipWriter::new(BufWriter::new(temp.as_file()));
+    let file_options = SimpleFileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+    for path in bundle.notes.iter().chain(&bundle.attachments) {
+        let bytes = fs::read(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        let is_note = settings::has_note_extension(path, index.note_extensions());
+        let bytes = match String::from_utf8(bytes) {
+            Ok(content) if is_note && !encryption::is_encrypted(content.as_bytes()) => {
+                bundle.rewrite(&content, path).into_bytes()
+            }
+            Ok(content) => content.into_bytes(),
+            Err(e) => e.into_bytes(),
+        };
+        let name = rename::relative_path(&root, path);
+        archive.start_file(name, file_options).map_err(|e| format!("Failed to write archive: {}", e))?;
+        archive.write_all(&bytes).map_err(|e| format!("Failed to write archive: {}", e))?;
+    }
+    archive
+        .finish()
+        .map_err(|e| format!("Failed to write archive: {}", e))?
+        .into_inner()
+        .map_err(|e| format!("Failed to write archive: {}", e.error()))?;
+    temp.persist(out).map_err(|e| format!("Failed to save archive: {}", e.error))?;
+
+    Ok(ZipExport {
+        notes: bundle.notes.len(),
+        attachments: bundle.attachments.len(),
+    })
+}
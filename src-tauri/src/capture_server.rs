@@ -0,0 +1,282 @@
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::fs;
+use std::io::ErrorKind;
+use std::net::{Ipv4Addr, SocketAddr, TcpListener};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager};
+
+use crate::encoding;
+use crate::encryption;
+use crate::http_server::{self, Request, Response, ServerHandle};
+use crate::links;
+use crate::metadata_cache::MetadataCache;
+use crate::note_index::NoteIndexRegistry;
+use crate::settings::{self, CaptureServerSettings, SettingsStore};
+
+struct Running {
+    server: ServerHandle,
+    root: PathBuf,
+}
+
+/// The capture server, if it is running
+#[derive(Default)]
+pub struct CaptureServer {
+    running: Mutex<Option<Running>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CaptureServerStatus {
+    pub running: bool,
+    pub port: u16,
+    /// Root of the vault served
+    pub vault: Option<String>,
+    /// Bearer token clients must send
+    pub token: String,
+    /// Base URL, e.g. `http://127.0.0.1:27183`
+    pub url: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct CaptureRequest {
+    text: String,
+    /// Note to append to instead of the inbox, relative to the vault root
+    note: Option<String>,
+}
+
+/// `relative` inside the vault at `root`, or `None` if it leaves it
+fn vault_file(root: &Path, relative: &str) -> Option<PathBuf> {
+    let relative = settings::normalize_vault_path(relative, "Note").ok()?;
+    (relative != ".").then(|| root.join(relative))
+}
+
+/// Append `text` to the note at `path`, creating it if needed, as a
+/// paragraph of its own
+fn append(app: &AppHandle, path: &Path, text: &str) -> Result<(), String> {
+    let settings = app.state::<Mutex<SettingsStore>>();
+    let registry = app.state::<NoteIndexRegistry>();
+    settings::check_writable(&settings, path).map_err(|e| e.to_string())?;
+    let previous = match fs::read(path) {
+        Ok(previous) => Some(previous),
+        Err(e) if e.kind() == ErrorKind::NotFound => None,
+        Err(e) => return Err(format!("Failed to read file: {}", e)),
+    };
+    if previous.as_deref().is_some_and(encryption::is_encrypted) {
+        return Err("Can't append to an encrypted note".to_string());
+    }
+    // Decoded to `\n` line breaks, and written back in the note's own format
+    let existing = previous.as_deref().map(|bytes| encoding::decode(bytes).content).unwrap_or_default();
+    let format = previous.as_deref().map(encoding::format_of).unwrap_or_default();
+    let separator = match existing.trim_end_matches([' ', '\t']) {
+        "" => "",
+        content if content.ends_with("\n\n") => "",
+        content if content.ends_with('\n') => "\n",
+        _ => "\n\n",
+    };
+    let content = format!("{}{}{}\n", existing.trim_end_matches([' ', '\t']), separator, text.trim_end());
+    let content = encoding::encode(&content, format)?;
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("Failed to create directory: {}", e))?;
+    }
+    let cache = app.state::<MetadataCache>();
+    crate::write_note(path, previous.as_deref(), &content, &registry, &settings, &cache)?;
+    Ok(())
+}
+
+fn capture(app: &AppHandle, root: &Path, config: &CaptureServerSettings, request: &Request) -> Response {
+    let is_json = request.header("content-type").is_some_and(|mime| mime.starts_with("application/json"));
+    let capture = if is_json {
+        match serde_json::from_slice::<CaptureRequest>(&request.body) {
+            Ok(capture) => capture,
+            Err(e) => return Response::error(400, &format!("Invalid JSON: {}", e)),
+        }
+    } else {
+        CaptureRequest {
+            text: String::from_utf8_lossy(&request.body).to_string(),
+            note: None,
+        }
+    };
+    if capture.text.trim().is_empty() {
+        return Response::error(400, "Nothing to capture");
+    }
+    let note_extensions = match settings::note_extensions(&app.state::<Mutex<SettingsStore>>()) {
+        Ok(extensions) => extensions,
+        Err(e) => return Response::error(500, &e),
+    };
+    let path = match vault_file(root, capture.note.as_deref().unwrap_or(&config.inbox)) {
+        Some(path) if settings::has_note_extension(&path, &note_extensions) => path,
+        _ => return Response::error(400, "The note must be a note inside the vault"),
+    };
+    match append(app, &path, &capture.text) {
+        Ok(()) => Response::json(201, json!({ "path": path.to_string_lossy() })),
+        Err(e) => Response::error(500, &e),
+    }
+}
+
+fn read_note(app: &AppHandle, root: &Path, relative: &str) -> Response {
+    let note_extensions = match settings::note_extensions(&app.state::<Mutex<SettingsStore>>()) {
+        Ok(extensions) => extensions,
+        Err(e) => return Response::error(500, &e),
+    };
+    let path = match vault_file(root, &links::percent_decode(relative)) {
+        Some(path) if settings::has_note_extension(&path, &note_extensions) => path,
+        _ => return Response::error(404, "No such note"),
+    };
+    match fs::read(&path) {
+        Ok(bytes) if encryption::is_encrypted(&bytes) => Response::error(403, "The note is encrypted"),
+        Ok(bytes) => Response::new(200, "text/markdown; charset=utf-8", bytes),
+        Err(e) if e.kind() == ErrorKind::NotFound => Response::error(404, "No such note"),
+        Err(e) => Response::error(500, &format!("Failed to read file: {}", e)),
+    }
+}
+
+fn handle(app: &AppHandle, root: &Path, port: u16, request: &Request) -> Response {
+    if !request.is_local_host(port) {
+        return Response::error(403, "Unexpected Host header");
+    }
+    if request.method == "OPTIONS" {
+        return Response::new(204, "text/plain", Vec::new());
+    }
+
+    let config = match app.state::<Mutex<SettingsStore>>().lock() {
+        Ok(store) => store.settings().capture_server.clone(),
+        Err(e) => return Response::error(500, &format!("Lock error: {}", e)),
+    };
+    let token = request
+        .header("authorization")
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .or_else(|| request.header("x-readmark-token"));
+    if !token.is_some_and(|token| http_server::tokens_match(token, &config.token)) {
+        return Response::error(401, "Missing or wrong token");
+    }
+    match (request.method.as_str(), request.path.as_str()) {
+        ("POST", "/capture") => capture(app, root, &config, request),
+        ("GET", path) if path.starts_with("/notes/") => read_note(app, root, &path["/notes/".len()..]),
+        (_, "/capture") => Response::error(405, "Use POST"),
+        (_, path) if path.starts_with("/notes/") => Response::error(405, "Use GET"),
+        _ => Response::error(404, "No such route"),
+    }
+}
+
+fn status(server: &CaptureServer, config: &CaptureServerSettings) -> Result<CaptureServerStatus, String> {
+    let running = server.running.lock().map_err(|e| format!("Lock error: {}", e))?;
+    let port = running.as_ref().map_or(config.port, |running| running.server.port);
+    Ok(CaptureServerStatus {
+        running: running.is_some(),
+        port,
+        vault: running
+            .as_ref()
+            .map(|running| running.root.to_string_lossy().to_string())
+            .or_else(|| config.vault.clone()),
+        token: config.token.clone(),
+        url: format!("http://127.0.0.1:{}", port),
+    })
+}
+
+fn stop(server: &CaptureServer) -> Result<(), String> {
+    if let Some(running) = server.running.lock().map_err(|e| format!("Lock error: {}", e))?.take() {
+        running.server.stop();
+    }
+    Ok(())
+}
+
+/// Serve the vault at `root` on `port` of the loopback interface
+fn start(app: &AppHandle, root: PathBuf, port: u16) -> Result<(), String> {
+    let server = app.state::<CaptureServer>();
+    stop(&server)?;
+    let listener = TcpListener::bind(SocketAddr::from((Ipv4Addr::LOCALHOST, port)))
+        .map_err(|e| format!("Failed to listen on port {}: {}", port, e))?;
+    let (handle_app, handle_root) = (app.clone(), root.clone());
+    let handle = http_server::serve(listener, move |request| {
+        // Browser extensions call from their own origin
+        handle(&handle_app, &handle_root, port, request)
+            .with_header("Access-Control-Allow-Origin", "*")
+            .with_header("Access-Control-Allow-Headers", "Authorization, Content-Type")
+            .with_header("Access-Control-Allow-Methods", "GET, POST, OPTIONS")
+    })?;
+    *server.running.lock().map_err(|e| format!("Lock error: {}", e))? = Some(Running { server: handle, root });
+    Ok(())
+}
+
+/// Start the capture server at launch if it was left on
+pub fn restore(app: &AppHandle) {
+    let config = match app.state::<Mutex<SettingsStore>>().lock() {
+        Ok(store) => store.settings().capture_server.clone(),
+        Err(_) => return,
+    };
+    if let (true, Some(vault)) = (config.enabled, config.vault) {
+        if let Err(e) = start(app, PathBuf::from(vault), config.port) {
+            eprintln!("Capture server error: {}", e);
+        }
+    }
+}
+
+/// Start the local capture server for the vault at `root`, on `port` or the
+/// last one used, and keep it on across launches. It listens on 127.0.0.1
+/// only and every request needs the token: `POST /capture` appends the
+/// markdown body (or JSON `{ text, note? }`) to the inbox note, and
+/// `GET /notes/<path>` returns a note.
+#[tauri::command]
+pub fn start_capture_server(
+    root: String,
+    port: Option<u16>,
+    app: AppHandle,
+    server: tauri::State<'_, CaptureServer>,
+    settings: tauri::State<'_, Mutex<SettingsStore>>,
+) -> Result<CaptureServerStatus, String> {
+    let root = PathBuf::from(&root);
+    crate::ensure_dir(&root)?;
+    let current = settings.lock().map_err(|e| format!("Lock error: {}", e))?.settings().capture_server.clone();
+    let port = port.unwrap_or(current.port);
+    if port == 0 {
+        return Err("Capture server port must be between 1 and 65535".to_string());
+    }
+    start(&app, root.clone(), port)?;
+
+    let mut store = settings.lock().map_err(|e| format!("Lock error: {}", e))?;
+    let updated = store.update(|settings| {
+        let capture = &mut settings.capture_server;
+        capture.enabled = true;
+        capture.vault = Some(root.to_string_lossy().to_string());
+        capture.port = port;
+        if capture.token.is_empty() {
+            capture.token = http_server::random_token();
+        }
+    })?;
+    status(&server, &updated.capture_server)
+}
+
+/// Stop the capture server and keep it off across launches
+#[tauri::command]
+pub fn stop_capture_server(
+    server: tauri::State<'_, CaptureServer>,
+    settings: tauri::State<'_, Mutex<SettingsStore>>,
+) -> Result<CaptureServerStatus, String> {
+    stop(&server)?;
+    let mut store = settings.lock().map_err(|e| format!("Lock error: {}", e))?;
+    let updated = store.update(|settings| settings.capture_server.enabled = false)?;
+    status(&server, &updated.capture_server)
+}
+
+/// Whether the capture server is running, where, and its token
+#[tauri::command]
+pub fn get_capture_server_status(
+    server: tauri::State<'_, CaptureServer>,
+    settings: tauri::State<'_, Mutex<SettingsStore>>,
+) -> Result<CaptureServerStatus, String> {
+    let config = settings.lock().map_err(|e| format!("Lock error: {}", e))?.settings().capture_server.clone();
+    status(&server, &config)
+}
+
+/// Replace the capture server's token, locking out clients using the old one
+#[tauri::command]
+pub fn reset_capture_token(
+    server: tauri::State<'_, CaptureServer>,
+    settings: tauri::State<'_, Mutex<SettingsStore>>,
+) -> Result<CaptureServerStatus, String> {
+    let mut store = settings.lock().map_err(|e| format!("Lock error: {}", e))?;
+    let updated = store.update(|settings| settings.capture_server.token = http_server::random_token())?;
+    status(&server, &updated.capture_server)
+}
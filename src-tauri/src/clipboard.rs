@@ -0,0 +1,410 @@
+use pulldown_cmark::{Event, LinkType, Parser, Tag, TagEnd};
+use serde::Deserialize;
+use std::fs;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::sync::Mutex;
+
+use crate::error::{CommandError, ToolError};
+use crate::export::{self, WikiLinkStyle};
+use crate::links;
+use crate::markdown;
+use crate::note_index::NoteIndexRegistry;
+use crate::settings::SettingsStore;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CopyFormat {
+    /// HTML, for mail clients, chat apps and web editors
+    Html,
+    /// HTML and RTF, for word processors. Linux clipboards get HTML only.
+    RichText,
+}
+
+/// Escape `text` for RTF, with non-ASCII characters as `\uN?` escapes
+fn escape_rtf(text: &str, out: &mut String) {
+    for c in text.chars() {
+        match c {
+            '\\' | '{' | '}' => {
+                out.push('\\');
+                out.push(c);
+            }
+            '\n' => out.push(' '),
+            '\t' => out.push_str("\\tab "),
+            c if c.is_ascii() => out.push(c),
+            c => {
+                let mut units = [0u16; 2];
+                for unit in c.encode_utf16(&mut units) {
+                    out.push_str(&format!("\\u{}?", *unit as i16));
+                }
+            }
+        }
+    }
+}
+
+/// Writes RTF for a stream of markdown events. Paragraphs are `\pard` blocks
+/// indented by list and quote depth; inline formatting uses groups.
+struct RtfWriter {
+    out: String,
+    /// Whether a block has been started, to be ended with `\par`
+    in_block: bool,
+    /// Next number of each open list, `None` for bullet lists
+    lists: Vec<Option<u64>>,
+    /// An item whose marker hasn't been written yet
+    item_pending: bool,
+    quote_depth: usize,
+    code_block: bool,
+    /// Whether each open link was written as a hyperlink field
+    links: Vec<bool>,
+    /// Inside an image, whose alt text is dropped
+    image_depth: usize,
+}
+
+impl RtfWriter {
+    fn start_block(&mut self, format: &str) {
+        if self.in_block {
+            self.out.push_str("\\par\n");
+        }
+        let indent = 720 * (self.lists.len() + self.quote_depth);
+        // `\plain` drops the code font or heading size of the last block
+        self.out.push_str(&format!("\\pard\\plain\\sa180\\li{}{} ", indent, format));
+        self.in_block = true;
+    }
+
+    /// Start the pending list item's paragraph with its marker
+    fn ensure_item(&mut self) {
+        if !self.item_pending {
+            return;
+        }
+        self.item_pending = false;
+        let marker = match self.lists.last_mut() {
+            Some(Some(number)) => {
+                *number += 1;
+                format!("{}.", *number - 1)
+            }
+            _ => "\\bullet".to_string(),
+        };
+        self.start_block("\\fi-360");
+        self.out.push_str(&format!("{}\\tab ", marker));
+    }
+
+    /// Start a paragraph for inline content outside one, as in tight lists
+    fn ensure_block(&mut self) {
+        self.ensure_item();
+        if !self.in_block {
+            self.start_block("");
+        }
+    }
+
+    fn text(&mut self, text: &str) {
+        if self.image_depth > 0 {
+            return;
+        }
+        self.ensure_block();
+        if self.code_block {
+            let mut lines = text.split('\n').peekable();
+            while let Some(line) = lines.next() {
+                escape_rtf(line, &mut self.out);
+                if lines.peek().is_some_and(|next| !next.is_empty()) {
+                    self.out.push_str("\\line ");
+                }
+            }
+        } else {
+            escape_rtf(text, &mut self.out);
+        }
+    }
+
+    fn event(&mut self, event: Event) {
+        match event {
+            Event::Start(Tag::Paragraph) => {
+                if self.item_pending {
+                    self.ensure_item();
+                } else {
+                    self.start_block("");
+                }
+            }
+            Event::Start(Tag::Heading { level, .. }) => {
+                let size = match level as u8 {
+                    1 => 36,
+                    2 => 30,
+                    3 => 26,
+                    _ => 24,
+                };
+                self.start_block("\\keepn");
+                self.out.push_str(&format!("{{\\b\\fs{} ", size));
+            }
+            Event::End(TagEnd::Heading(_)) => self.out.push('}'),
+            Event::Start(Tag::BlockQuote(_)) => self.quote_depth += 1,
+            Event::End(TagEnd::BlockQuote(_)) => self.quote_depth -= 1,
+            Event::Start(Tag::CodeBlock(_)) => {
+                self.start_block("\\f1\\fs20");
+                self.code_block = true;
+            }
+            Event::End(TagEnd::CodeBlock) => {
+                self.code_block = false;
+                self.out.push_str("\\par\n");
+                self.in_block = false;
+            }
+            Event::Start(Tag::List(first)) => {
+                self.ensure_item();
+                self.lists.push(first);
+            }
+            Event::End(TagEnd::List(_)) => {
+                self.lists.pop();
+            }
+            Event::Start(Tag::Item) => self.item_pending = true,
+            Event::End(TagEnd::Item) => self.ensure_item(),
+            Event::TaskListMarker(checked) => self.text(if checked { "\u{2611} " } else { "\u{2610} " }),
+            Event::Start(Tag::TableHead) | Event::Start(Tag::TableRow) => self.start_block(""),
+            Event::End(TagEnd::TableCell) => self.out.push_str("\\tab "),
+            Event::Start(Tag::Emphasis) | Event::Start(Tag::Strong) | Event::Start(Tag::Strikethrough) => {
+                self.ensure_block();
+                self.out.push_str(match event {
+                    Event::Start(Tag::Emphasis) => "{\\i ",
+                    Event::Start(Tag::Strong) => "{\\b ",
+                    _ => "{\\strike ",
+                });
+            }
+            Event::End(TagEnd::Emphasis) | Event::End(TagEnd::Strong) | Event::End(TagEnd::Strikethrough) => {
+                self.out.push('}')
+            }
+            Event::Start(Tag::Link { link_type, dest_url, .. }) => {
+                // Only links that work outside the vault stay links
+                let field = !matches!(link_type, LinkType::WikiLink { .. }) && links::is_external(&dest_url);
+                self.ensure_block();
+                if field {
+                    self.out.push_str("{\\field{\\*\\fldinst{HYPERLINK \"");
+                    escape_rtf(&dest_url.replace('"', "%22"), &mut self.out);
+                    self.out.push_str("\"}}{\\fldrslt{\\ul ");
+                }
+                self.links.push(field);
+            }
+            Event::End(TagEnd::Link) if self.links.pop().unwrap_or(false) => self.out.push_str("}}}"),
+            Event::Start(Tag::Image { .. }) => self.image_depth += 1,
+            Event::End(TagEnd::Image) => self.image_depth -= 1,
+            Event::Start(Tag::FootnoteDefinition(label)) => {
+                self.start_block("\\fs20");
+                self.text(&format!("[{}] ", label));
+            }
+            Event::Text(text) => self.text(&text),
+            Event::Code(code) => {
+                self.ensure_block();
+                self.out.push_str("{\\f1 ");
+                self.text(&code);
+                self.out.push('}');
+            }
+            Event::FootnoteReference(label) => self.text(&format!("[{}]", label)),
+            Event::SoftBreak => self.text(" "),
+            Event::HardBreak => self.out.push_str("\\line "),
+            Event::Rule => {
+                self.start_block("\\brdrb\\brdrs\\brdrw10\\brsp20");
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Fonts are `\f0` for text and `\f1` for code
+const RTF_HEADER: &str = r"{\rtf1\ansi\ansicpg1252\deff0\uc1{\fonttbl{\f0\fswiss Helvetica;}{\f1\fmodern Courier New;}}";
+
+/// The markdown `content` as an RTF document
+pub fn to_rtf(content: &str) -> String {
+    let mut writer = RtfWriter {
+        out: String::from(RTF_HEADER),
+        in_block: false,
+        lists: Vec::new(),
+        item_pending: false,
+        quote_depth: 0,
+        code_block: false,
+        links: Vec::new(),
+        image_depth: 0,
+    };
+    let body = &content[markdown::body_start(content)..];
+    for event in Parser::new_ext(body, markdown::parser_options()) {
+        writer.event(event);
+    }
+    if writer.in_block {
+        writer.out.push_str("\\par");
+    }
+    writer.out.push_str("\n}");
+    writer.out
+}
+
+/// `fragment` wrapped in the `CF_HTML` header Windows expects, which gives
+/// the byte offsets of the document and the fragment
+#[cfg(target_os = "windows")]
+fn cf_html(fragment: &str) -> String {
+    let header = |start_html: usize, end_html: usize, start_fragment: usize, end_fragment: usize| {
+        format!(
+            "Version:0.9\r\nStartHTML:{:010}\r\nEndHTML:{:010}\r\nStartFragment:{:010}\r\nEndFragment:{:010}\r\n",
+            start_html, end_html, start_fragment, end_fragment
+        )
+    };
+    let prefix = "<html><body>\r\n<!--StartFragment-->";
+    let suffix = "<!--EndFragment-->\r\n</body></html>";
+    // The offsets have a fixed width, so the header's length doesn't vary
+    let start_html = header(0, 0, 0, 0).len();
+    let start_fragment = start_html + prefix.len();
+    let end_fragment = start_fragment + fragment.len();
+    let end_html = end_fragment + suffix.len();
+    let header = header(start_html, end_html, start_fragment, end_fragment);
+    format!("{}{}{}{}", header, prefix, fragment, suffix)
+}
+
+/// Contents for each format, in temporary files the clipboard helper reads
+#[cfg(any(target_os = "macos", target_os = "windows"))]
+struct ClipboardFiles {
+    html: tempfile::NamedTempFile,
+    rtf: Option<tempfile::NamedTempFile>,
+    text: tempfile::NamedTempFile,
+}
+
+#[cfg(any(target_os = "macos", target_os = "windows"))]
+fn temp_file(contents: &str) -> Result<tempfile::NamedTempFile, String> {
+    use std::io::Write;
+    let mut file = tempfile::Builder::new()
+        .prefix(".readmark-clipboard-")
+        .tempfile()
+        .map_err(|e| format!("Failed to create temporary file: {}", e))?;
+    file.write_all(contents.as_bytes())
+        .map_err(|e| format!("Failed to write temporary file: {}", e))?;
+    Ok(file)
+}
+
+/// Run a helper that reads the formats' files from the environment
+#[cfg(any(target_os = "macos", target_os = "windows"))]
+fn run_helper(mut command: Command, files: &ClipboardFiles, tool: &str) -> Result<(), CommandError> {
+    command
+        .env("READMARK_CLIPBOARD_HTML", files.html.path())
+        .env("READMARK_CLIPBOARD_TEXT", files.text.path())
+        .env(
+            "READMARK_CLIPBOARD_RTF",
+            files.rtf.as_ref().map(|rtf| rtf.path().as_os_str()).unwrap_or_default(),
+        );
+    let output = command
+        .stdin(Stdio::null())
+        .output()
+        .map_err(|e| format!("Failed to run {}: {}", tool, e))?;
+    if !output.status.success() {
+        return Err(ToolError::ToolFailed {
+            tool: tool.to_string(),
+            status: output.status.code(),
+            stderr: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        }
+        .into());
+    }
+    Ok(())
+}
+
+#[cfg(target_os = "macos")]
+fn set_clipboard(html: &str, rtf: Option<&str>, text: &str) -> Result<(), CommandError> {
+    const SCRIPT: &str = r#"
+ObjC.import('AppKit');
+const env = $.NSProcessInfo.processInfo.environment;
+const read = (name) => $.NSString.stringWithContentsOfFileEncodingError(
+  env.objectForKey(name), $.NSUTF8StringEncoding, null);
+const pasteboard = $.NSPasteboard.generalPasteboard;
+pasteboard.clearContents;
+pasteboard.setStringForType(read('READMARK_CLIPBOARD_HTML'), 'public.html');
+if (env.objectForKey('READMARK_CLIPBOARD_RTF').js) {
+  pasteboard.setStringForType(read('READMARK_CLIPBOARD_RTF'), 'public.rtf');
+}
+pasteboard.setStringForType(read('READMARK_CLIPBOARD_TEXT'), 'public.utf8-plain-text');
+"#;
+    let files = ClipboardFiles {
+        html: temp_file(html)?,
+        rtf: rtf.map(temp_file).transpose()?,
+        text: temp_file(text)?,
+    };
+    let mut command = Command::new("osascript");
+    command.args(["-l", "JavaScript", "-e", SCRIPT]);
+    run_helper(command, &files, "osascript")
+}
+
+#[cfg(target_os = "windows")]
+fn set_clipboard(html: &str, rtf: Option<&str>, text: &str) -> Result<(), CommandError> {
+    use std::os::windows::process::CommandExt;
+    const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+    const SCRIPT: &str = r#"
+Add-Type -AssemblyName System.Windows.Forms
+$utf8 = New-Object System.Text.UTF8Encoding $false
+$data = New-Object System.Windows.Forms.DataObject
+$data.SetData([System.Windows.Forms.DataFormats]::Html, [IO.File]::ReadAllText($env:READMARK_CLIPBOARD_HTML, $utf8))
+if ($env:READMARK_CLIPBOARD_RTF) {
+  $data.SetData([System.Windows.Forms.DataFormats]::Rtf, [IO.File]::ReadAllText($env:READMARK_CLIPBOARD_RTF, $utf8))
+}
+$text = [IO.File]::ReadAllText($env:READMARK_CLIPBOARD_TEXT, $utf8)
+$data.SetData([System.Windows.Forms.DataFormats]::UnicodeText, $text)
+[System.Windows.Forms.Clipboard]::SetDataObject($data, $true)
+"#;
+    let files = ClipboardFiles {
+        html: temp_file(&cf_html(html))?,
+        rtf: rtf.map(temp_file).transpose()?,
+        text: temp_file(text)?,
+    };
+    let mut command = Command::new("powershell");
+    command
+        .args(["-NoProfile", "-NonInteractive", "-STA", "-Command", SCRIPT])
+        .creation_flags(CREATE_NO_WINDOW);
+    run_helper(command, &files, "powershell")
+}
+
+/// Wayland and X11 clipboard tools take one format at a time, so only the
+/// HTML is set
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+fn set_clipboard(html: &str, _rtf: Option<&str>, _text: &str) -> Result<(), CommandError> {
+    use std::io::Write;
+    let wayland = std::env::var_os("WAYLAND_DISPLAY").is_some();
+    let (tool, args, install_url): (&str, &[&str], &str) = if wayland {
+        ("wl-copy", &["--type", "text/html"], "https://github.com/bugaevc/wl-clipboard")
+    } else {
+        ("xclip", &["-selection", "clipboard", "-t", "text/html"], "https://github.com/astrand/xclip")
+    };
+    let program = export::find_program(&[tool], &[]).ok_or_else(|| ToolError::ToolMissing {
+        tool: tool.to_string(),
+        install_url: install_url.to_string(),
+    })?;
+    // Both tools stay running in the background to serve the clipboard, so
+    // their output isn't waited on
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| format!("Failed to run {}: {}", tool, e))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin
+            .write_all(html.as_bytes())
+            .map_err(|e| format!("Failed to send the note to {}: {}", tool, e))?;
+    }
+    let status = child.wait().map_err(|e| format!("Failed to run {}: {}", tool, e))?;
+    if !status.success() {
+        return Err(ToolError::ToolFailed {
+            tool: tool.to_string(),
+            status: status.code(),
+            stderr: String::new(),
+        }
+        .into());
+    }
+    Ok(())
+}
+
+/// Render the note at `path` and put it on the system clipboard as HTML,
+/// plus RTF for `rich_text` on macOS and Windows, so pasting into mail,
+/// chat or a word processor keeps the formatting. The markdown goes along
+/// as plain text for apps that take neither.
+#[tauri::command(async)]
+pub fn copy_note_as(
+    path: String,
+    format: CopyFormat,
+    registry: tauri::State<'_, NoteIndexRegistry>,
+    settings: tauri::State<'_, Mutex<SettingsStore>>,
+) -> Result<(), CommandError> {
+    let source = links::normalize_path(Path::new(&path));
+    let content = fs::read_to_string(&source).map_err(|e| format!("Failed to read file: {}", e))?;
+    let html = export::embedded_body(&source, &content, WikiLinkStyle::Text, &registry, &settings)?;
+    let rtf = (format == CopyFormat::RichText).then(|| to_rtf(&content));
+    let text = &content[markdown::body_start(&content)..];
+    set_clipboard(&html, rtf.as_deref(), text)
+}
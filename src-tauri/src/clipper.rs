@@ -0,0 +1,362 @@
+use chrono::{Local, SecondsFormat};
+use dom_smoothie::Readability;
+use regex::Regex;
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+use ureq::ResponseExt;
+
+use crate::attachments;
+use crate::encoding;
+use crate::error::CommandError;
+use crate::frontmatter;
+use crate::import::{self, ImportFailure};
+use crate::links::{self, LinkKind};
+use crate::rename;
+use crate::settings::{self, SettingsStore};
+
+const USER_AGENT: &str = concat!("Mozilla/5.0 (compatible; Readmark/", env!("CARGO_PKG_VERSION"), ")");
+/// Largest page that is clipped
+const MAX_PAGE_BYTES: u64 = 20 * 1024 * 1024;
+/// Largest image that is downloaded with a clipped page
+const MAX_IMAGE_BYTES: u64 = 50 * 1024 * 1024;
+
+#[derive(Debug, Serialize)]
+pub struct ClippedNote {
+    /// Path of the note written
+    pub path: String,
+    pub title: String,
+    /// Number of images downloaded
+    pub images: usize,
+    /// Images that couldn't be downloaded; the note keeps linking to them
+    pub failed: Vec<ImportFailure>,
+}
+
+struct Download {
+    data: Vec<u8>,
+    mime: Option<String>,
+    /// URL after redirects
+    url: String,
+}
+
+pub fn agent() -> ureq::Agent {
+    ureq::Agent::config_builder()
+        .timeout_global(Some(Duration::from_secs(30)))
+        .user_agent(USER_AGENT)
+        .build()
+        .into()
+}
+
+fn fetch(agent: &ureq::Agent, url: &str, limit: u64) -> Result<Download, String> {
+    let mut response = agent.get(url).call().map_err(|e| format!("Failed to fetch {}: {}", url, e))?;
+    let final_url = response.get_uri().to_string();
+    let body = response.body_mut();
+    let mime = body.mime_type().map(str::to_lowercase);
+    let data = body
+        .with_config()
+        .limit(limit)
+        .read_to_vec()
+        .map_err(|e| format!("Failed to fetch {}: {}", url, e))?;
+    Ok(Download { data, mime, url: final_url })
+}
+
+fn is_http(url: &str) -> bool {
+    let lower = url.to_ascii_lowercase();
+    lower.starts_with("http://") || lower.starts_with("https://")
+}
+
+/// File name for an image downloaded from `url`: the last path segment,
+/// with an extension from the MIME type if it has none
+fn image_file_name(url: &str, mime: Option<&str>) -> String {
+    let path = url.split(['?', '#']).next().unwrap_or_default();
+    let segment = path.trim_end_matches('/').rsplit('/').next().unwrap_or_default();
+    let name = import::safe_file_name(&links::percent_decode(segment));
+    if Path::new(&name).extension().is_some() {
+        name
+    } else {
+        format!("{}.{}", name, import::extension_for(mime.unwrap_or_default()))
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct LocalizedImage {
+    /// Remote URL the image was linked by
+    pub url: String,
+    /// Path of the downloaded copy
+    pub path: String,
+}
+
+/// Download every remote image in `markdown` (the content of a note in
+/// `note_dir`) into `attachments_dir` and point the image links at the local
+/// copies. A file with the same contents already there is reused.
+pub fn localize(
+    markdown: &str,
+    note_dir: &Path,
+    attachments_dir: &Path,
+    agent: &ureq::Agent,
+    failed: &mut Vec<ImportFailure>,
+) -> (String, Vec<LocalizedImage>) {
+    let mut localized = Vec::new();
+    let first_failure = failed.len();
+    // Remote URL to the link target of its local copy, or `None` if it failed
+    let mut saved: HashMap<String, Option<String>> = HashMap::new();
+    let mut markdown = markdown.to_string();
+    for link in links::extract(&markdown).iter().rev() {
+        if link.kind != LinkKind::Markdown || !link.embed || !is_http(&link.target) {
+            continue;
+        }
+        let local = saved.entry(link.target.clone()).or_insert_with(|| {
+            let result = fetch(agent, &link.target, MAX_IMAGE_BYTES).and_then(|download| {
+                if let Some(mime) = download.mime.as_deref().filter(|mime| !mime.starts_with("image/")) {
+                    return Err(format!("Not an image: {}", mime));
+                }
+                if let Some(existing) = attachments::find_identical(attachments_dir, &download.data) {
+                    return Ok(existing);
+                }
+                fs::create_dir_all(attachments_dir).map_err(|e| format!("Failed to create directory: {}", e))?;
+                let name = image_file_name(&download.url, download.mime.as_deref());
+                let path = import::unique_path(attachments_dir, &name);
+                fs::write(&path, &download.data).map_err(|e| format!("Failed to save image: {}", e))?;
+                Ok(path)
+            });
+            match result {
+                Ok(path) => {
+                    localized.push(LocalizedImage {
+                        url: link.target.clone(),
+                        path: path.to_string_lossy().to_string(),
+                    });
+                    Some(import::link_target(&rename::relative_path(note_dir, &path)))
+                }
+                Err(error) => {
+                    failed.push(ImportFailure {
+                        item: link.target.clone(),
+                        error,
+                    });
+                    None
+                }
+            }
+        });
+        if let (Some(local), Some(range)) = (local.clone(), rename::target_range(&markdown, link)) {
+            markdown.replace_range(range, &local);
+        }
+    }
+    // Links were visited last to first
+    localized.reverse();
+    failed[first_failure..].reverse();
+    (markdown, localized)
+}
+
+/// Save a web page as a note in `dest_dir`: the article is extracted from
+/// the page, converted to markdown and its images downloaded, with the
+/// source URL and clip date in the frontmatter
+#[tauri::command(async)]
+pub fn clip_url(
+    url: String,
+    dest_dir: String,
+    settings: tauri::State<'_, Mutex<SettingsStore>>,
+) -> Result<ClippedNote, CommandError> {
+    if !is_http(&url) {
+        return Err(format!("Not an http(s) URL: {}", url).into());
+    }
+    let dest_dir = PathBuf::from(&dest_dir);
+    settings::check_writable(&settings, &dest_dir)?;
+    fs::create_dir_all(&dest_dir).map_err(|e| format!("Failed to create directory: {}", e))?;
+
+    let agent = agent();
+    let page = fetch(&agent, &url, MAX_PAGE_BYTES)?;
+    if page.mime.as_deref().is_some_and(|mime| !mime.contains("html")) {
+        return Err(format!("Not an HTML page: {}", page.mime.unwrap_or_default()).into());
+    }
+    let html = String::from_utf8_lossy(&page.data).to_string();
+    let article = Readability::new(html, Some(&page.url), None)
+        .and_then(|mut readability| readability.parse())
+        .map_err(|e| format!("Failed to extract article: {}", e))?;
+
+    let title = match article.title.trim() {
+        "" => page.url.clone(),
+        title => title.to_string(),
+    };
+    let mut failed = Vec::new();
+    let markdown = import::markdown_from_html(&article.content);
+    let attachments_dir = dest_dir.join(import::ATTACHMENTS_DIR);
+    let (markdown, images) = localize(&markdown, &dest_dir, &attachments_dir, &agent, &mut failed);
+
+    let mut fields = Map::new();
+    fields.insert("title".to_string(), Value::String(title.clone()));
+    fields.insert("source".to_string(), Value::String(page.url.clone()));
+    if let Some(author) = article.byline.filter(|author| !author.trim().is_empty()) {
+        fields.insert("author".to_string(), Value::String(author.trim().to_string()));
+    }
+    if let Some(published) = article.published_time {
+        fields.insert("published".to_string(), Value::String(published));
+    }
+    fields.insert(
+        "clipped".to_string(),
+        Value::String(Local::now().to_rfc3339_opts(SecondsFormat::Secs, true)),
+    );
+
+    let body = format!("# {}\n\n{}\n", title, markdown.trim());
+    let content = frontmatter::replace(&body, &fields)?;
+    let path = import::unique_path(&dest_dir, &format!("{}.md", import::safe_file_name(&title)));
+    crate::write_atomic(&path, content.as_bytes()).map_err(|e| format!("Failed to write file: {}", e))?;
+    Ok(ClippedNote {
+        path: path.to_string_lossy().to_string(),
+        title,
+        images: images.len(),
+        failed,
+    })
+}
+
+/// How long `fetch_url_title` waits for a page
+const TITLE_TIMEOUT: Duration = Duration::from_secs(5);
+/// Bytes of a page read for its title, which is in the `<head>`
+const MAX_HEAD_BYTES: u64 = 512 * 1024;
+
+#[derive(Debug, Default, Serialize)]
+pub struct UrlTitle {
+    /// URL after redirects
+    pub url: String,
+    /// OpenGraph or Twitter title, else the `<title>`; `None` if the page
+    /// has neither
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub site_name: Option<String>,
+    /// Preview image URL
+    pub image: Option<String>,
+}
+
+/// `text` with HTML character references decoded: numeric ones and the
+/// named ones common in titles
+fn decode_entities(text: &str) -> String {
+    let mut decoded = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(at) = rest.find('&') {
+        decoded.push_str(&rest[..at]);
+        rest = &rest[at..];
+        let end = rest.find(';').filter(|end| *end <= 10);
+        let entity = end.map(|end| &rest[1..end]).unwrap_or_default();
+        let c = match entity {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            "nbsp" => Some(' '),
+            "ndash" => Some('–'),
+            "mdash" => Some('—'),
+            "hellip" => Some('…'),
+            "lsquo" => Some('‘'),
+            "rsquo" => Some('’'),
+            "ldquo" => Some('“'),
+            "rdquo" => Some('”'),
+            "laquo" => Some('«'),
+            "raquo" => Some('»'),
+            "middot" => Some('·'),
+            "bull" => Some('•'),
+            "copy" => Some('©'),
+            "reg" => Some('®'),
+            "trade" => Some('™'),
+            _ => entity
+                .strip_prefix("#x")
+                .or_else(|| entity.strip_prefix("#X"))
+                .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+                .or_else(|| entity.strip_prefix('#').and_then(|n| n.parse().ok()))
+                .and_then(char::from_u32),
+        };
+        match (c, end) {
+            (Some(c), Some(end)) => {
+                decoded.push(c);
+                rest = &rest[end + 1..];
+            }
+            _ => {
+                decoded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    decoded.push_str(rest);
+    decoded
+}
+
+/// Whitespace runs as single spaces, entities decoded; `None` if empty
+fn clean_text(text: &str) -> Option<String> {
+    let text = decode_entities(text).split_whitespace().collect::<Vec<_>>().join(" ");
+    Some(text).filter(|text| !text.is_empty())
+}
+
+/// Title, description, site name and image of an HTML page from its
+/// `<title>` and OpenGraph, Twitter and description `<meta>` tags
+fn page_title(url: &str, html: &str) -> UrlTitle {
+    static TITLE: OnceLock<Regex> = OnceLock::new();
+    static META: OnceLock<Regex> = OnceLock::new();
+    static ATTRIBUTE: OnceLock<Regex> = OnceLock::new();
+    let title_regex =
+        TITLE.get_or_init(|| Regex::new(r"(?is)<title\b[^>]*>(.*?)</title>").expect("valid title regex"));
+    let meta_regex = META.get_or_init(|| Regex::new(r"(?i)<meta\b[^>]*>").expect("valid meta regex"));
+    let attribute_regex = ATTRIBUTE.get_or_init(|| {
+        Regex::new(r#"([A-Za-z:_-]+)\s*=\s*(?:"([^"]*)"|'([^']*)'|([^\s"'>]+))"#).expect("valid attribute regex")
+    });
+
+    // Meta `property` or `name` to `content`, first one winning
+    let mut meta: HashMap<String, String> = HashMap::new();
+    for tag in meta_regex.find_iter(html) {
+        let mut key = None;
+        let mut content = None;
+        for attribute in attribute_regex.captures_iter(tag.as_str()) {
+            let value = attribute.get(2).or(attribute.get(3)).or(attribute.get(4)).map_or("", |m| m.as_str());
+            match attribute[1].to_ascii_lowercase().as_str() {
+                "property" | "name" => key = Some(value.to_ascii_lowercase()),
+                "content" => content = Some(value.to_string()),
+                _ => {}
+            }
+        }
+        if let (Some(key), Some(content)) = (key, content) {
+            meta.entry(key).or_insert(content);
+        }
+    }
+    let field = |keys: &[&str]| keys.iter().find_map(|key| meta.get(*key).and_then(|value| clean_text(value)));
+
+    let title = field(&["og:title", "twitter:title"])
+        .or_else(|| title_regex.captures(html).and_then(|captures| clean_text(&captures[1])));
+    UrlTitle {
+        url: url.to_string(),
+        title,
+        description: field(&["og:description", "twitter:description", "description"]),
+        site_name: field(&["og:site_name", "application-name"]),
+        image: field(&["og:image", "og:image:url", "twitter:image"]),
+    }
+}
+
+/// Fetch the page at `url` with a short timeout and read its title and
+/// OpenGraph data, so a pasted URL can become a `[Title](url)` link
+#[tauri::command(async)]
+pub fn fetch_url_title(url: String) -> Result<UrlTitle, String> {
+    if !is_http(&url) {
+        return Err(format!("Not an http(s) URL: {}", url));
+    }
+    let agent: ureq::Agent = ureq::Agent::config_builder()
+        .timeout_global(Some(TITLE_TIMEOUT))
+        .user_agent(USER_AGENT)
+        .build()
+        .into();
+    let mut response = agent.get(&url).call().map_err(|e| format!("Failed to fetch {}: {}", url, e))?;
+    let final_url = response.get_uri().to_string();
+    let body = response.body_mut();
+    if body.mime_type().is_some_and(|mime| !mime.to_lowercase().contains("html")) {
+        return Ok(UrlTitle {
+            url: final_url,
+            ..Default::default()
+        });
+    }
+    let mut data = Vec::new();
+    body.as_reader()
+        .take(MAX_HEAD_BYTES)
+        .read_to_end(&mut data)
+        .map_err(|e| format!("Failed to fetch {}: {}", url, e))?;
+    Ok(page_title(&final_url, &encoding::decode(&data).content))
+}
@@ -0,0 +1,275 @@
+use rusqlite::Connection;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::search_index::IndexRegistry;
+
+/// Completions returned when no limit is given
+const DEFAULT_LIMIT: usize = 20;
+
+/// Headings returned by `search_headings` when no limit is given
+const DEFAULT_HEADING_LIMIT: usize = 50;
+
+/// Age in days at which a note's recency counts half
+const RECENCY_HALF_LIFE_DAYS: f64 = 7.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CompletionKind {
+    Note,
+    Alias,
+    Heading,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LinkCompletion {
+    pub kind: CompletionKind,
+    /// The note name, alias or heading that matched
+    pub label: String,
+    /// What goes between `[[` and `]]`, e.g. `Note`, `Note|Alias` or
+    /// `Note#Heading`
+    pub target: String,
+    pub path: String,
+    pub title: String,
+}
+
+struct Note {
+    path: String,
+    name: String,
+    title: String,
+    /// `name`, or the path from the vault root if another note has the name
+    link: String,
+    score: f64,
+}
+
+fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+/// How well `text` matches `prefix` (lowercased): 2 if it starts with it, 1
+/// if one of its words does
+fn match_quality(text: &str, prefix: &str) -> Option<u8> {
+    let text = text.to_lowercase();
+    if text.starts_with(prefix) {
+        return Some(2);
+    }
+    let mut words = text.match_indices(|c: char| !c.is_alphanumeric()).map(|(i, s)| i + s.len());
+    words.any(|start| text[start..].starts_with(prefix)).then_some(1)
+}
+
+/// Every note of the index, scored by how many notes link to it and how
+/// recently it changed
+fn load_notes(conn: &Connection, root: &Path) -> rusqlite::Result<HashMap<i64, Note>> {
+    let mut stmt = conn.prepare("SELECT target_name, COUNT(DISTINCT file_id) FROM note_links GROUP BY target_name")?;
+    let inbound: HashMap<String, i64> = stmt
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<Result<_, _>>()?;
+
+    let now = now_millis();
+    let mut stmt = conn.prepare(
+        "SELECT files.id, files.path, files.mtime, m.name, m.title
+         FROM files JOIN note_meta m ON m.file_id = files.id",
+    )?;
+    let rows = stmt.query_map([], |row| {
+        let path: String = row.get(1)?;
+        let name: String = row.get(3)?;
+        let age_days = now.saturating_sub(row.get::<_, i64>(2)? as u64) as f64 / 86_400_000.0;
+        let links = inbound.get(&name.to_lowercase()).copied().unwrap_or(0);
+        let score = (links as f64).ln_1p() + 1.0 / (1.0 + age_days / RECENCY_HALF_LIFE_DAYS);
+        let note = Note {
+            link: name.clone(),
+            path,
+            name,
+            title: row.get(4)?,
+            score,
+        };
+        Ok((row.get(0)?, note))
+    })?;
+    let mut notes: HashMap<i64, Note> = rows.collect::<Result<_, _>>()?;
+
+    let mut names: HashMap<String, usize> = HashMap::new();
+    for note in notes.values() {
+        *names.entry(note.name.to_lowercase()).or_default() += 1;
+    }
+    for note in notes.values_mut() {
+        if names[&note.name.to_lowercase()] > 1 {
+            let relative = Path::new(&note.path).strip_prefix(root).unwrap_or(Path::new(&note.path));
+            let relative = relative.with_extension("");
+            note.link = relative.to_string_lossy().replace('\\', "/");
+        }
+    }
+    Ok(notes)
+}
+
+fn complete(conn: &Connection, root: &Path, prefix: &str, limit: usize) -> rusqlite::Result<Vec<LinkCompletion>> {
+    let notes = load_notes(conn, root)?;
+    let mut found: Vec<(f64, LinkCompletion)> = Vec::new();
+    let completion = |kind, label: String, target: String, note: &Note| LinkCompletion {
+        kind,
+        label,
+        target,
+        path: note.path.clone(),
+        title: note.title.clone(),
+    };
+
+    // `Note#Head` completes headings of that note only
+    if let Some((name, heading_prefix)) = prefix.split_once('#') {
+        let name = name.trim().to_lowercase();
+        let heading_prefix = heading_prefix.trim().to_lowercase();
+        let mut stmt = conn.prepare("SELECT file_id, text FROM note_headings ORDER BY rowid")?;
+        let mut rows = stmt.query([])?;
+        while let Some(row) = rows.next()? {
+            let Some(note) = notes.get(&row.get(0)?) else {
+                continue;
+            };
+            if note.name.to_lowercase() != name && note.link.to_lowercase() != name {
+                continue;
+            }
+            let text: String = row.get(1)?;
+            if let Some(quality) = match_quality(&text, &heading_prefix) {
+                let target = format!("{}#{}", note.link, text);
+                found.push((quality as f64, completion(CompletionKind::Heading, text, target, note)));
+            }
+        }
+        found.truncate(limit);
+        return Ok(found.into_iter().map(|(_, c)| c).collect());
+    }
+
+    let prefix = prefix.trim().to_lowercase();
+    for note in notes.values() {
+        let quality = match_quality(&note.name, &prefix).max(match_quality(&note.title, &prefix));
+        if let Some(quality) = quality {
+            let entry = completion(CompletionKind::Note, note.name.clone(), note.link.clone(), note);
+            found.push((quality as f64 * 10.0 + note.score, entry));
+        }
+    }
+    let mut stmt = conn.prepare("SELECT file_id, alias FROM note_aliases")?;
+    let mut rows = stmt.query([])?;
+    while let Some(row) = rows.next()? {
+        let Some(note) = notes.get(&row.get(0)?) else {
+            continue;
+        };
+        let alias: String = row.get(1)?;
+        if let Some(quality) = match_quality(&alias, &prefix) {
+            let target = format!("{}|{}", note.link, alias);
+            let entry = completion(CompletionKind::Alias, alias, target, note);
+            found.push((quality as f64 * 10.0 + note.score, entry));
+        }
+    }
+    // Headings of any note, below notes and aliases matching as well
+    if !prefix.is_empty() {
+        let mut stmt = conn.prepare("SELECT file_id, text FROM note_headings")?;
+        let mut rows = stmt.query([])?;
+        while let Some(row) = rows.next()? {
+            let Some(note) = notes.get(&row.get(0)?) else {
+                continue;
+            };
+            let text: String = row.get(1)?;
+            // The note itself already stands for its title
+            if text.eq_ignore_ascii_case(&note.title) {
+                continue;
+            }
+            if let Some(quality) = match_quality(&text, &prefix) {
+                let target = format!("{}#{}", note.link, text);
+                let score = quality as f64 * 10.0 + note.score - 5.0;
+                found.push((score, completion(CompletionKind::Heading, text, target, note)));
+            }
+        }
+    }
+
+    found.sort_by(|(a, x), (b, y)| b.total_cmp(a).then_with(|| x.label.cmp(&y.label)));
+    found.truncate(limit);
+    Ok(found.into_iter().map(|(_, c)| c).collect())
+}
+
+#[derive(Debug, Serialize)]
+pub struct HeadingMatch {
+    pub path: String,
+    /// Title of the note
+    pub title: String,
+    pub heading: String,
+    /// 1 for `#` through 6 for `######`
+    pub level: u8,
+    /// Anchor for `#slug` links
+    pub slug: String,
+    /// 1-based line number
+    pub line: usize,
+    /// What goes between `[[` and `]]`, e.g. `Note#Heading`
+    pub target: String,
+}
+
+/// Headings containing every word of `query`, starting with it first, then
+/// higher level headings and notes linked to often and changed recently
+fn headings(conn: &Connection, root: &Path, query: &str, limit: usize) -> rusqlite::Result<Vec<HeadingMatch>> {
+    let notes = load_notes(conn, root)?;
+    let query = query.trim().to_lowercase();
+    let words: Vec<&str> = query.split_whitespace().collect();
+    let mut found: Vec<(f64, HeadingMatch)> = Vec::new();
+    let mut stmt = conn.prepare("SELECT file_id, level, text, slug, line FROM note_headings")?;
+    let mut rows = stmt.query([])?;
+    while let Some(row) = rows.next()? {
+        let Some(note) = notes.get(&row.get(0)?) else {
+            continue;
+        };
+        let text: String = row.get(2)?;
+        let lower = text.to_lowercase();
+        if !words.iter().all(|word| lower.contains(word)) {
+            continue;
+        }
+        let level: u8 = row.get(1)?;
+        let quality = match_quality(&text, &query).unwrap_or(0);
+        let score = quality as f64 * 10.0 - level as f64 + note.score;
+        found.push((
+            score,
+            HeadingMatch {
+                path: note.path.clone(),
+                title: note.title.clone(),
+                target: format!("{}#{}", note.link, text),
+                heading: text,
+                level,
+                slug: row.get(3)?,
+                line: row.get::<_, i64>(4)? as usize,
+            },
+        ));
+    }
+    found.sort_by(|(a, x), (b, y)| b.total_cmp(a).then_with(|| (&x.path, x.line).cmp(&(&y.path, y.line))));
+    found.truncate(limit);
+    Ok(found.into_iter().map(|(_, h)| h).collect())
+}
+
+/// Note names, aliases and headings of a vault matching `prefix`, for `[[`
+/// autocompletion. Matches at the start come first, then notes linked to
+/// often and changed recently. A prefix such as `Note#Sec` completes the
+/// headings of that note.
+#[tauri::command(async)]
+pub fn get_link_completions(
+    root: String,
+    prefix: String,
+    limit: Option<usize>,
+    registry: tauri::State<'_, IndexRegistry>,
+) -> Result<Vec<LinkCompletion>, String> {
+    let root = Path::new(&root);
+    let index = registry.get(root)?;
+    let conn = index.lock_conn()?;
+    complete(&conn, root, &prefix, limit.unwrap_or(DEFAULT_LIMIT)).map_err(|e| format!("Query failed: {}", e))
+}
+
+/// Headings across a vault containing the words of `query`, with their note
+/// and slug, for `[[Note#Heading]]` completion and jumping to a heading
+#[tauri::command(async)]
+pub fn search_headings(
+    root: String,
+    query: String,
+    limit: Option<usize>,
+    registry: tauri::State<'_, IndexRegistry>,
+) -> Result<Vec<HeadingMatch>, String> {
+    let root = Path::new(&root);
+    let index = registry.get(root)?;
+    let conn = index.lock_conn()?;
+    headings(&conn, root, &query, limit.unwrap_or(DEFAULT_HEADING_LIMIT)).map_err(|e| format!("Query failed: {}", e))
+}
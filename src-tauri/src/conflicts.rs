@@ -0,0 +1,173 @@
+use regex::Regex;
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use crate::diff::{self, TextDiff};
+use crate::history;
+use crate::ignore_rules;
+use crate::note_index::NoteIndexRegistry;
+
+/// Unchanged lines shown around each change in the diff of a conflict
+const DIFF_CONTEXT: usize = 3;
+
+/// Naming scheme a conflict file follows
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictKind {
+    /// `name (conflicted copy 2024-01-31).md`, as written by Dropbox,
+    /// Nextcloud and WebDAV sync
+    ConflictedCopy,
+    /// `name.sync-conflict-20240131-120000-ABCDEFG.md`
+    Syncthing,
+    /// `name_conflict-20240131-120000.md`, from older ownCloud clients
+    Owncloud,
+}
+
+/// A copy a sync client made of a file that changed on two devices
+#[derive(Debug, Serialize)]
+pub struct ConflictFile {
+    pub path: String,
+    /// The file it is a conflicting version of
+    pub original: String,
+    pub original_exists: bool,
+    pub kind: ConflictKind,
+    pub size: u64,
+    /// Milliseconds since the Unix epoch
+    pub modified: Option<u64>,
+}
+
+/// A conflict file merged into its original
+#[derive(Debug, Serialize)]
+pub struct ConflictMerge {
+    /// The merged text, with conflict markers where both versions changed
+    /// the same lines differently
+    pub merged: String,
+    /// Regions left with markers; 0 if the merge is clean
+    pub conflicts: usize,
+    /// History version of the original used as the common ancestor;
+    /// `None` if there was none, which makes every difference a conflict
+    pub base: Option<String>,
+    /// Line diff from the original to the conflict file, for resolving by
+    /// hand
+    pub diff: TextDiff,
+}
+
+fn patterns() -> &'static [(ConflictKind, Regex)] {
+    static PATTERNS: OnceLock<Vec<(ConflictKind, Regex)>> = OnceLock::new();
+    PATTERNS.get_or_init(|| {
+        [
+            (
+                ConflictKind::ConflictedCopy,
+                r"(?i)^(.+?) \([^()]*conflicted copy[^()]*(?:\(\d+\)[^()]*)?\)(\.[^.]+)?$",
+            ),
+            (
+                ConflictKind::Syncthing,
+                r"^(.+?)\.sync-conflict-\d{8}-\d{6}(?:-[A-Z0-9]{7})?(\.[^.]+)?$",
+            ),
+            (ConflictKind::Owncloud, r"^(.+?)_conflict-\d{8}-\d{6}(\.[^.]+)?$"),
+        ]
+        .into_iter()
+        .map(|(kind, pattern)| (kind, Regex::new(pattern).expect("conflict regex is valid")))
+        .collect()
+    })
+}
+
+/// The kind of conflict file `name` is and the name of its original, if it
+/// is one
+fn parse_conflict_name(name: &str) -> Option<(ConflictKind, String)> {
+    patterns().iter().find_map(|(kind, pattern)| {
+        let captures = pattern.captures(name)?;
+        let extension = captures.get(2).map_or("", |m| m.as_str());
+        Some((*kind, format!("{}{}", &captures[1], extension)))
+    })
+}
+
+/// Conflict files sync clients left in the vault at `root`
+#[tauri::command(async)]
+pub fn find_sync_conflicts(root: String) -> Result<Vec<ConflictFile>, String> {
+    let root = PathBuf::from(&root);
+    crate::ensure_dir(&root)?;
+
+    let mut conflicts = Vec::new();
+    for entry in ignore_rules::walker(&root, &[])?.build().filter_map(|entry| entry.ok()) {
+        if !entry.file_type().is_some_and(|t| t.is_file()) {
+            continue;
+        }
+        let name = entry.file_name().to_string_lossy();
+        let Some((kind, original_name)) = parse_conflict_name(&name) else {
+            continue;
+        };
+        let original = entry.path().with_file_name(original_name);
+        let metadata = entry.metadata().ok();
+        conflicts.push(ConflictFile {
+            path: entry.path().to_string_lossy().to_string(),
+            original_exists: original.is_file(),
+            original: original.to_string_lossy().to_string(),
+            kind,
+            size: metadata.as_ref().map_or(0, |m| m.len()),
+            modified: metadata.as_ref().and_then(crate::mtime_millis),
+        });
+    }
+    conflicts.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(conflicts)
+}
+
+/// The newest history version of `original` saved before either file was
+/// last modified and matching neither, as the likely common ancestor
+fn guess_base(
+    original: &Path,
+    conflict: &Path,
+    contents: (&str, &str),
+    registry: &NoteIndexRegistry,
+) -> Option<(String, String)> {
+    let modified = |path: &Path| fs::metadata(path).ok().as_ref().and_then(crate::mtime_millis);
+    let before = modified(original)?.min(modified(conflict)?);
+    history::versions(original, registry)
+        .into_iter()
+        .filter(|version| version.timestamp < before)
+        .filter_map(|version| {
+            let content = history::version_content(original, &version.id, registry).ok()?;
+            Some((version.id, content))
+        })
+        .find(|(_, content)| content != contents.0 && content != contents.1)
+}
+
+/// Merge the conflict file `conflict` into `original`. The common ancestor
+/// is history version `base` of the original, or the likeliest one when not
+/// given. Nothing is written; the result is for the editor to save.
+#[tauri::command(async)]
+pub fn merge_conflict(
+    original: String,
+    conflict: String,
+    base: Option<String>,
+    registry: tauri::State<'_, NoteIndexRegistry>,
+) -> Result<ConflictMerge, String> {
+    let original_path = Path::new(&original);
+    let conflict_path = Path::new(&conflict);
+    let ours = fs::read_to_string(original_path).map_err(|e| format!("Failed to read {}: {}", original, e))?;
+    let theirs = fs::read_to_string(conflict_path).map_err(|e| format!("Failed to read {}: {}", conflict, e))?;
+
+    let base = match base {
+        Some(id) => {
+            let content = history::version_content(original_path, &id, &registry)?;
+            Some((id, content))
+        }
+        None => guess_base(original_path, conflict_path, (&ours, &theirs), &registry),
+    };
+    let label = |path: &Path| path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+    let merge = diff::merge(
+        base.as_ref().map(|(_, content)| content.as_str()),
+        &ours,
+        &theirs,
+        (&label(original_path), &label(conflict_path)),
+    );
+
+    Ok(ConflictMerge {
+        merged: merge.text,
+        conflicts: merge.conflicts,
+        base: base.map(|(id, _)| id),
+        diff: diff::diff(&ours, &theirs, DIFF_CONTEXT),
+    })
+}
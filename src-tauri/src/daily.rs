@@ -0,0 +1,66 @@
+use chrono::{Local, NaiveDate, NaiveTime};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::dates;
+use crate::error::CommandError;
+use crate::metadata_cache::MetadataCache;
+use crate::settings::{self, SettingsStore};
+use crate::templates;
+use crate::vault_config;
+
+/// Path of the daily note of `date` in the vault at `root`, creating it from
+/// the vault's daily note template if it doesn't exist yet
+pub fn daily_note(
+    root: &Path,
+    date: NaiveDate,
+    settings: &Mutex<SettingsStore>,
+    cache: &MetadataCache,
+) -> Result<PathBuf, CommandError> {
+    let config = vault_config::resolve(root, settings)?;
+    let relative = templates::render(&config.daily_notes_format, &HashMap::new(), "", date, NaiveTime::MIN);
+    let path = root.join(relative);
+    if path.exists() {
+        return Ok(path);
+    }
+    settings::check_writable(settings, &path)?;
+
+    let content = match &config.daily_notes_template {
+        Some(template) => {
+            let template_path = root.join(template);
+            let template = fs::read_to_string(&template_path)
+                .map_err(|e| format!("Failed to read template {}: {}", template_path.display(), e))?;
+            let title = path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+            templates::render(&template, &HashMap::new(), &title, date, Local::now().time())
+        }
+        None => String::new(),
+    };
+
+    // Created in the meantime is fine, e.g. by the tray and the window at once
+    templates::create_file(&path, &content, true, cache)?;
+    Ok(path)
+}
+
+/// Path of the daily note of `date` (`YYYY-MM-DD` or words such as
+/// `yesterday` or `last friday`, read like `parse_date`; today if not given)
+/// in the vault at `root`, following its `daily_notes_format`. A missing note
+/// is created from the vault's `daily_notes_template`, or empty if it has
+/// none.
+#[tauri::command(async)]
+pub fn open_daily_note(
+    root: String,
+    date: Option<String>,
+    settings: tauri::State<'_, Mutex<SettingsStore>>,
+    cache: tauri::State<'_, MetadataCache>,
+) -> Result<String, CommandError> {
+    let root = PathBuf::from(&root);
+    crate::ensure_dir(&root)?;
+    let date = match date {
+        Some(date) => dates::parse(&date, Local::now().date_naive())?.0,
+        None => Local::now().date_naive(),
+    };
+    let path = daily_note(&root, date, &settings, &cache)?;
+    Ok(path.to_string_lossy().to_string())
+}
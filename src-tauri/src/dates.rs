@@ -0,0 +1,331 @@
+use chrono::{Datelike, Days, Local, Month, Months, NaiveDate, NaiveTime, Weekday};
+use serde::Serialize;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ParsedDate {
+    /// `YYYY-MM-DD`
+    pub date: String,
+    /// `HH:MM`, if the text has a time of day
+    pub time: Option<String>,
+}
+
+fn number(word: &str) -> Option<u32> {
+    const WORDS: &[&str] = &[
+        "one", "two", "three", "four", "five", "six", "seven", "eight", "nine", "ten", "eleven", "twelve",
+    ];
+    match word {
+        "a" | "an" => Some(1),
+        _ => word
+            .parse()
+            .ok()
+            .or_else(|| WORDS.iter().position(|w| *w == word).map(|i| i as u32 + 1)),
+    }
+}
+
+/// A day of the month such as `1`, `1st` or `22nd`
+fn day_of_month(word: &str) -> Option<u32> {
+    let digits = word.trim_end_matches(|c: char| c.is_ascii_alphabetic());
+    let suffix = &word[digits.len()..];
+    if !matches!(suffix, "" | "st" | "nd" | "rd" | "th") {
+        return None;
+    }
+    digits.parse().ok().filter(|day| (1..=31).contains(day))
+}
+
+fn month(word: &str) -> Option<u32> {
+    let word = if word == "sept" { "sep" } else { word };
+    word.parse::<Month>().ok().map(|month| month.number_from_month())
+}
+
+fn weekday(word: &str) -> Option<Weekday> {
+    let word = match word {
+        "tues" => "tue",
+        "thur" | "thurs" => "thu",
+        word => word,
+    };
+    word.parse().ok()
+}
+
+/// A time of day such as `14:30`, `9am`, `9:30pm` or `noon`
+fn time_of_day(word: &str) -> Option<NaiveTime> {
+    match word {
+        "noon" | "midday" => return NaiveTime::from_hms_opt(12, 0, 0),
+        "midnight" => return Some(NaiveTime::MIN),
+        _ => {}
+    }
+    let (clock, offset) = match word.strip_suffix("am").or_else(|| word.strip_suffix("a.m.")) {
+        Some(clock) => (clock, Some(0)),
+        None => match word.strip_suffix("pm").or_else(|| word.strip_suffix("p.m.")) {
+            Some(clock) => (clock, Some(12)),
+            None => (word, None),
+        },
+    };
+    let (hour, minute) = match clock.split_once(':') {
+        Some((hour, minute)) if minute.len() == 2 => (hour.parse::<u32>().ok()?, minute.parse::<u32>().ok()?),
+        // A bare number is only a time with am or pm
+        None if offset.is_some() => (clock.parse::<u32>().ok()?, 0),
+        _ => return None,
+    };
+    let hour = match offset {
+        Some(_) if !(1..=12).contains(&hour) => return None,
+        Some(offset) => hour % 12 + offset,
+        None => hour,
+    };
+    NaiveTime::from_hms_opt(hour, minute, 0)
+}
+
+/// `date` moved by `count` of `unit` (day, week, fortnight, month or year)
+fn shift(date: NaiveDate, count: i64, unit: &str) -> Option<NaiveDate> {
+    let unit = unit.strip_suffix('s').unwrap_or(unit);
+    let days = match unit {
+        "day" => count,
+        "week" => count * 7,
+        "fortnight" => count * 14,
+        "month" | "year" => {
+            let months = if unit == "year" { count * 12 } else { count };
+            let months = Months::new(u32::try_from(months.unsigned_abs()).ok()?);
+            return match count < 0 {
+                true => date.checked_sub_months(months),
+                false => date.checked_add_months(months),
+            };
+        }
+        _ => return None,
+    };
+    let shifted = Days::new(days.unsigned_abs());
+    match days < 0 {
+        true => date.checked_sub_days(shifted),
+        false => date.checked_add_days(shifted),
+    }
+}
+
+/// The first `weekday` after `date`, or on it if `inclusive`
+fn next_weekday(date: NaiveDate, weekday: Weekday, inclusive: bool) -> Option<NaiveDate> {
+    let ahead = (weekday.num_days_from_monday() + 7 - date.weekday().num_days_from_monday()) % 7;
+    let ahead = if ahead == 0 && !inclusive { 7 } else { ahead };
+    date.checked_add_days(Days::new(ahead as u64))
+}
+
+/// The first `month`/`day` on or after `date`, or in `year` if given
+fn month_day(date: NaiveDate, month: u32, day: u32, year: Option<&str>) -> Option<NaiveDate> {
+    if let Some(year) = year {
+        return NaiveDate::from_ymd_opt(year.parse().ok().filter(|year| *year >= 1000)?, month, day);
+    }
+    (date.year()..date.year() + 8)
+        .filter_map(|year| NaiveDate::from_ymd_opt(year, month, day))
+        .find(|candidate| *candidate >= date)
+}
+
+/// The start or end of the week (Monday to Sunday), month or year `date` is in
+fn period_edge(date: NaiveDate, edge: &str, period: &str) -> Option<NaiveDate> {
+    let start = match period {
+        "week" => date.checked_sub_days(Days::new(date.weekday().num_days_from_monday() as u64))?,
+        "month" => date.with_day(1)?,
+        "year" => NaiveDate::from_ymd_opt(date.year(), 1, 1)?,
+        _ => return None,
+    };
+    match edge {
+        "start" | "beginning" => Some(start),
+        "end" => shift(start, 1, period)?.pred_opt(),
+        _ => None,
+    }
+}
+
+/// The date `words` name, counting from `today`
+fn parse_words(words: &[&str], today: NaiveDate) -> Option<NaiveDate> {
+    match words {
+        [] | ["today"] | ["now"] => Some(today),
+        ["tomorrow"] | ["tmrw"] => today.succ_opt(),
+        ["yesterday"] => today.pred_opt(),
+        ["day", "after", "tomorrow"] => today.checked_add_days(Days::new(2)),
+        ["day", "before", "yesterday"] => today.checked_sub_days(Days::new(2)),
+        [date] if date.contains(['-', '/']) => {
+            let date = date.replace('/', "-");
+            NaiveDate::parse_from_str(&date, "%Y-%m-%d").ok()
+        }
+        ["in", count, unit] => shift(today, number(count)? as i64, unit),
+        [count, unit, "from", "now" | "today"] | [count, unit, "later"] => {
+            shift(today, number(count)? as i64, unit)
+        }
+        [count, unit, "ago"] => shift(today, -(number(count)? as i64), unit),
+        ["next", unit @ ("day" | "week" | "fortnight" | "month" | "year")] => shift(today, 1, unit),
+        ["last", unit @ ("day" | "week" | "fortnight" | "month" | "year")] => shift(today, -1, unit),
+        [edge @ ("start" | "beginning" | "end"), "of", rest @ ..] => {
+            let (base, period) = match rest {
+                ["the" | "this", period] | [period] => (today, *period),
+                ["next", period] => (shift(today, 1, period)?, *period),
+                ["last", period] => (shift(today, -1, period)?, *period),
+                _ => return None,
+            };
+            period_edge(base, edge, period)
+        }
+        [day] if weekday(day).is_some() => next_weekday(today, weekday(day)?, false),
+        ["next", day] => next_weekday(today, weekday(day)?, false),
+        ["this", day] => next_weekday(today, weekday(day)?, true),
+        ["last", day] => next_weekday(today.checked_sub_days(Days::new(8))?, weekday(day)?, false),
+        ["the", day] | [day] => {
+            let day = day_of_month(day)?;
+            (0..12)
+                .filter_map(|months| today.with_day(1)?.checked_add_months(Months::new(months))?.with_day(day))
+                .find(|candidate| *candidate >= today)
+        }
+        [count, unit] if number(count).is_some() && shift(today, 0, unit).is_some() => {
+            shift(today, number(count)? as i64, unit)
+        }
+        [first, second, year @ ..] if year.len() <= 1 => {
+            // `june 1`, `1 june`, `1st of june`, with an optional year
+            let (first, second, year) = match (*second, year) {
+                ("of", [name]) => (*first, *name, None),
+                (_, year) => (*first, *second, year.first().copied()),
+            };
+            let (month, day) = match (month(first), month(second)) {
+                (Some(month), None) => (month, day_of_month(second)?),
+                (None, Some(month)) => (month, day_of_month(first)?),
+                _ => return None,
+            };
+            month_day(today, month, day, year)
+        }
+        _ => None,
+    }
+}
+
+/// The date and time of day `text` names, such as `2024-06-01`, `tomorrow`,
+/// `next friday`, `in 2 weeks`, `3 days ago`, `june 1st`, `end of month` or
+/// `monday at 9am`, counting from `today`. A weekday alone is the next one
+/// after today; `this friday` may be today and `last friday` is the one
+/// before. A day without a year is the next one on or after today.
+pub fn parse(text: &str, today: NaiveDate) -> Result<(NaiveDate, Option<NaiveTime>), String> {
+    let lower = text.trim().to_lowercase().replace(',', " ");
+    let mut words: Vec<&str> = lower.split_whitespace().collect();
+    if words.last().is_some_and(|word| word.ends_with('.') && !word.ends_with(".m.")) {
+        if let Some(last) = words.last_mut() {
+            *last = last.trim_end_matches('.');
+        }
+    }
+
+    // A time at the end, as in `friday 14:30` or `tomorrow at 9 am`
+    let mut time = None;
+    let spaced = matches!(words.as_slice(), [.., hour, "am" | "pm" | "a.m." | "p.m."]
+        if hour.parse::<u32>().is_ok() || hour.contains(':'));
+    if spaced {
+        let suffix = words.pop().unwrap_or_default();
+        let hour = words.pop().unwrap_or_default();
+        time = Some(time_of_day(&format!("{}{}", hour, suffix)).ok_or_else(|| invalid(text))?);
+    } else {
+        if let Some(found) = words.last().and_then(|word| time_of_day(word)) {
+            words.pop();
+            time = Some(found);
+        }
+    }
+    if time.is_some() && words.last() == Some(&"at") {
+        words.pop();
+    }
+    if words.first() == Some(&"on") {
+        words.remove(0);
+    }
+
+    let date = parse_words(&words, today).ok_or_else(|| invalid(text))?;
+    Ok((date, time))
+}
+
+fn invalid(text: &str) -> String {
+    format!("Couldn't read a date from \"{}\"; try YYYY-MM-DD, \"tomorrow\" or \"next friday\"", text.trim())
+}
+
+/// The date `text` names, in words such as `next friday` or `in 2 weeks` or
+/// as `YYYY-MM-DD`, counting from `reference` (`YYYY-MM-DD`, today if not
+/// given), with the time of day if it has one (`friday at 9am`). Daily note
+/// navigation and task due dates read dates the same way.
+#[tauri::command]
+pub fn parse_date(text: String, reference: Option<String>) -> Result<ParsedDate, String> {
+    let today = match reference {
+        Some(reference) => NaiveDate::parse_from_str(reference.trim(), "%Y-%m-%d")
+            .map_err(|_| format!("Invalid reference date, expected YYYY-MM-DD: {}", reference))?,
+        None => Local::now().date_naive(),
+    };
+    let (date, time) = parse(&text, today)?;
+    Ok(ParsedDate {
+        date: date.format("%Y-%m-%d").to_string(),
+        time: time.map(|time| time.format("%H:%M").to_string()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A Wednesday
+    fn today() -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 6, 5).unwrap()
+    }
+
+    fn date(text: &str) -> NaiveDate {
+        parse(text, today()).unwrap().0
+    }
+
+    fn ymd(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    #[test]
+    fn reads_iso_dates() {
+        assert_eq!(date("2024-06-01"), ymd(2024, 6, 1));
+        assert_eq!(date("2024/06/01"), ymd(2024, 6, 1));
+        assert!(parse("2024-02-30", today()).is_err());
+    }
+
+    #[test]
+    fn reads_relative_days() {
+        assert_eq!(date("today"), today());
+        assert_eq!(date("Tomorrow"), ymd(2024, 6, 6));
+        assert_eq!(date("yesterday"), ymd(2024, 6, 4));
+        assert_eq!(date("day after tomorrow"), ymd(2024, 6, 7));
+        assert_eq!(date("in 2 weeks"), ymd(2024, 6, 19));
+        assert_eq!(date("in three days"), ymd(2024, 6, 8));
+        assert_eq!(date("3 days ago"), ymd(2024, 6, 2));
+        assert_eq!(date("a month from now"), ymd(2024, 7, 5));
+        assert_eq!(date("next year"), ymd(2025, 6, 5));
+    }
+
+    #[test]
+    fn reads_weekdays() {
+        assert_eq!(date("friday"), ymd(2024, 6, 7));
+        assert_eq!(date("next fri"), ymd(2024, 6, 7));
+        // A weekday alone is never today, but `this` one may be
+        assert_eq!(date("wednesday"), ymd(2024, 6, 12));
+        assert_eq!(date("this wednesday"), today());
+        assert_eq!(date("last friday"), ymd(2024, 5, 31));
+        assert_eq!(date("last wednesday"), ymd(2024, 5, 29));
+    }
+
+    #[test]
+    fn reads_days_of_the_year() {
+        // Without a year, the next one on or after today
+        assert_eq!(date("june 1st"), ymd(2025, 6, 1));
+        assert_eq!(date("june 5"), today());
+        assert_eq!(date("1st of july"), ymd(2024, 7, 1));
+        assert_eq!(date("1 july 2023"), ymd(2023, 7, 1));
+        assert_eq!(date("the 3rd"), ymd(2024, 7, 3));
+        assert_eq!(date("end of month"), ymd(2024, 6, 30));
+        assert_eq!(date("start of next week"), ymd(2024, 6, 10));
+        assert_eq!(date("end of the year"), ymd(2024, 12, 31));
+    }
+
+    #[test]
+    fn reads_times_of_day() {
+        let at = |hour, minute| NaiveTime::from_hms_opt(hour, minute, 0);
+        assert_eq!(parse("tomorrow at 9am", today()).unwrap(), (ymd(2024, 6, 6), at(9, 0)));
+        assert_eq!(parse("friday 14:30", today()).unwrap(), (ymd(2024, 6, 7), at(14, 30)));
+        assert_eq!(parse("on monday at 9 pm", today()).unwrap(), (ymd(2024, 6, 10), at(21, 0)));
+        assert_eq!(parse("12am", today()).unwrap(), (today(), at(0, 0)));
+        assert_eq!(parse("noon", today()).unwrap(), (today(), at(12, 0)));
+        assert_eq!(parse("2024-06-01", today()).unwrap().1, None);
+    }
+
+    #[test]
+    fn rejects_what_it_cannot_read() {
+        for text in ["someday", "in many days", "friday at 25:00", "13pm", "june 32"] {
+            let error = parse(text, today()).unwrap_err();
+            assert!(error.contains("Couldn't read a date"), "{}: {}", text, error);
+        }
+    }
+}
@@ -0,0 +1,95 @@
+use serde::Serialize;
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::links;
+
+const SCHEME: &str = "readmark://";
+
+/// What a `readmark://` link asks the app to do
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "action", rename_all = "lowercase")]
+pub enum DeepLink {
+    /// `readmark://open?path=/notes/Todo.md`
+    Open { path: String },
+    /// `readmark://new?title=Idea&content=...`; nothing is written until the
+    /// frontend saves it
+    New { title: Option<String>, content: Option<String> },
+    /// `readmark://search?q=tag:work`
+    Search { query: String },
+}
+
+/// Links received before the frontend was ready to handle them
+#[derive(Default)]
+pub struct DeepLinks {
+    ready: bool,
+    pending: Vec<DeepLink>,
+}
+
+/// `key=value` pairs of a URL query, decoded
+fn query_pairs(query: &str) -> Vec<(String, String)> {
+    query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            let decode = |text: &str| links::percent_decode(&text.replace('+', " "));
+            (decode(key), decode(value))
+        })
+        .collect()
+}
+
+/// Parse a `readmark://` URL
+pub fn parse(url: &str) -> Result<DeepLink, String> {
+    let rest = url
+        .get(..SCHEME.len())
+        .filter(|scheme| scheme.eq_ignore_ascii_case(SCHEME))
+        .map(|_| &url[SCHEME.len()..])
+        .ok_or_else(|| format!("Not a readmark link: {}", url))?;
+    let (action, query) = rest.split_once('?').unwrap_or((rest, ""));
+    let pairs = query_pairs(query.split('#').next().unwrap_or(""));
+    let param = |name: &str| pairs.iter().find(|(key, _)| key == name).map(|(_, value)| value.clone());
+
+    match action.trim_end_matches('/').to_ascii_lowercase().as_str() {
+        "open" => param("path")
+            .filter(|path| !path.is_empty())
+            .map(|path| DeepLink::Open { path })
+            .ok_or_else(|| "Missing path in readmark://open link".to_string()),
+        "new" => Ok(DeepLink::New {
+            title: param("title"),
+            content: param("content"),
+        }),
+        "search" => Ok(DeepLink::Search {
+            query: param("q").unwrap_or_default(),
+        }),
+        other => Err(format!("Unknown readmark link action: {}", other)),
+    }
+}
+
+/// Hand `urls` to the frontend as `deep-link` events, or queue them until
+/// it asks for them with `take_deep_links`
+pub fn handle_urls<'a>(app: &AppHandle, urls: impl IntoIterator<Item = &'a str>) {
+    let state = app.state::<Mutex<DeepLinks>>();
+    let Ok(mut links) = state.lock() else {
+        return;
+    };
+    for url in urls {
+        match parse(url) {
+            Ok(link) if links.ready => {
+                let _ = app.emit("deep-link", link);
+            }
+            Ok(link) => links.pending.push(link),
+            Err(e) => eprintln!("{}", e),
+        }
+    }
+}
+
+/// Links the app was opened with before the frontend was listening. Call
+/// once the `deep-link` listener is registered; later links arrive as
+/// events.
+#[tauri::command]
+pub fn take_deep_links(state: tauri::State<'_, Mutex<DeepLinks>>) -> Result<Vec<DeepLink>, String> {
+    let mut links = state.lock().map_err(|e| format!("Lock error: {}", e))?;
+    links.ready = true;
+    Ok(std::mem::take(&mut links.pending))
+}
@@ -0,0 +1,103 @@
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::thread;
+
+/// Start `command` without waiting for it, reaping it once it exits
+fn launch(mut command: Command) -> Result<(), String> {
+    let program = command.get_program().to_string_lossy().to_string();
+    let mut child = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| format!("Failed to run {}: {}", program, e))?;
+    thread::spawn(move || child.wait());
+    Ok(())
+}
+
+fn existing(path: &str) -> Result<PathBuf, String> {
+    let path_buf = PathBuf::from(path);
+    if !path_buf.exists() {
+        return Err(format!("File does not exist: {}", path));
+    }
+    Ok(path_buf)
+}
+
+#[cfg(target_os = "macos")]
+fn reveal(path: &Path) -> Result<(), String> {
+    let mut command = Command::new("open");
+    command.arg("-R").arg(path);
+    launch(command)
+}
+
+#[cfg(target_os = "windows")]
+fn reveal(path: &Path) -> Result<(), String> {
+    use std::os::windows::process::CommandExt;
+    // Explorer parses its own command line, and doesn't accept `/select,`
+    // and the path as separately quoted arguments
+    let mut command = Command::new("explorer");
+    command.raw_arg(format!("/select,\"{}\"", path.display()));
+    launch(command)
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+fn reveal(path: &Path) -> Result<(), String> {
+    // File managers implementing the FileManager1 D-Bus interface (Nautilus,
+    // Dolphin, Nemo, Caja...) can select the item; others just get the folder
+    let uri = format!("file://{}", crate::sync::encode_path(&path.to_string_lossy()));
+    let shown = Command::new("dbus-send")
+        .args([
+            "--session",
+            "--print-reply",
+            "--dest=org.freedesktop.FileManager1",
+            "--type=method_call",
+            "/org/freedesktop/FileManager1",
+            "org.freedesktop.FileManager1.ShowItems",
+        ])
+        .arg(format!("array:string:{}", uri))
+        .arg("string:")
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .is_ok_and(|status| status.success());
+    if shown {
+        return Ok(());
+    }
+    let folder = if path.is_dir() { path } else { path.parent().unwrap_or(path) };
+    open(folder)
+}
+
+#[cfg(target_os = "macos")]
+fn open(path: &Path) -> Result<(), String> {
+    let mut command = Command::new("open");
+    command.arg(path);
+    launch(command)
+}
+
+#[cfg(target_os = "windows")]
+fn open(path: &Path) -> Result<(), String> {
+    use std::os::windows::process::CommandExt;
+    let mut command = Command::new("explorer");
+    command.raw_arg(format!("\"{}\"", path.display()));
+    launch(command)
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+fn open(path: &Path) -> Result<(), String> {
+    let mut command = Command::new("xdg-open");
+    command.arg(path);
+    launch(command)
+}
+
+/// Show `path` selected in Finder, Explorer or the desktop's file manager
+#[tauri::command(async)]
+pub fn reveal_in_file_manager(path: String) -> Result<(), String> {
+    reveal(&existing(&path)?)
+}
+
+/// Open `path` in the app the OS associates with it, e.g. a PDF attachment
+/// in the system PDF viewer
+#[tauri::command(async)]
+pub fn open_with_default_app(path: String) -> Result<(), String> {
+    open(&existing(&path)?)
+}
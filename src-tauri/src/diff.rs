@@ -0,0 +1,343 @@
+use serde::Serialize;
+use similar::{Algorithm, ChangeTag, DiffTag, TextDiff as LineDiff};
+use std::fs;
+use std::ops::Range;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use crate::encoding;
+use crate::encryption::EncryptionKeys;
+use crate::error::CommandError;
+use crate::history;
+use crate::note_index::NoteIndexRegistry;
+
+/// Unchanged lines shown around each change, if not overridden
+const DEFAULT_CONTEXT: usize = 3;
+
+/// Time after which the diff falls back to a coarser result rather than
+/// keep searching for the smallest one
+const DIFF_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Version id that stands for the file as it is on disk
+const CURRENT_VERSION: &str = "current";
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum LineKind {
+    Context,
+    Added,
+    Removed,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DiffLine {
+    pub kind: LineKind,
+    /// The line without its line ending
+    pub text: String,
+    /// 1-based line number in the old text; `None` for added lines
+    pub old_line: Option<usize>,
+    /// 1-based line number in the new text; `None` for removed lines
+    pub new_line: Option<usize>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Hunk {
+    /// 1-based first line of the hunk in the old text
+    pub old_start: usize,
+    pub old_lines: usize,
+    /// 1-based first line of the hunk in the new text
+    pub new_start: usize,
+    pub new_lines: usize,
+    pub lines: Vec<DiffLine>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TextDiff {
+    pub hunks: Vec<Hunk>,
+    /// Total lines added and removed
+    pub added: usize,
+    pub removed: usize,
+}
+
+/// Line diff of `old` and `new`, grouped into hunks with `context`
+/// unchanged lines around the changes
+pub fn diff(old: &str, new: &str, context: usize) -> TextDiff {
+    let line_diff = LineDiff::configure().timeout(DIFF_TIMEOUT).diff_lines(old, new);
+    let mut result = TextDiff {
+        hunks: Vec::new(),
+        added: 0,
+        removed: 0,
+    };
+
+    for group in line_diff.grouped_ops(context) {
+        let (Some(first), Some(last)) = (group.first(), group.last()) else {
+            continue;
+        };
+        let old_range = first.old_range().start..last.old_range().end;
+        let new_range = first.new_range().start..last.new_range().end;
+        let mut lines = Vec::new();
+        for op in &group {
+            for change in line_diff.iter_changes(op) {
+                let kind = match change.tag() {
+                    ChangeTag::Equal => LineKind::Context,
+                    ChangeTag::Insert => {
+                        result.added += 1;
+                        LineKind::Added
+                    }
+                    ChangeTag::Delete => {
+                        result.removed += 1;
+                        LineKind::Removed
+                    }
+                };
+                lines.push(DiffLine {
+                    kind,
+                    text: change.value().trim_end_matches(['\n', '\r']).to_string(),
+                    old_line: change.old_index().map(|i| i + 1),
+                    new_line: change.new_index().map(|i| i + 1),
+                });
+            }
+        }
+        result.hunks.push(Hunk {
+            old_start: old_range.start + 1,
+            old_lines: old_range.len(),
+            new_start: new_range.start + 1,
+            new_lines: new_range.len(),
+            lines,
+        });
+    }
+    result
+}
+
+/// Result of a line-based merge
+#[derive(Debug)]
+pub struct Merge {
+    /// The merged text, with conflict markers where both sides changed the
+    /// same lines differently
+    pub text: String,
+    /// Number of regions left with conflict markers
+    pub conflicts: usize,
+}
+
+/// A change one side made to the base: lines `base` replaced with lines
+/// `side` of that side
+struct Edit {
+    base: Range<usize>,
+    side: Range<usize>,
+}
+
+/// Changes from `base` to `side`, with adjacent ones combined
+fn edits(base: &[&str], side: &[&str]) -> Vec<Edit> {
+    let deadline = Some(Instant::now() + DIFF_TIMEOUT);
+    let mut edits: Vec<Edit> = Vec::new();
+    for op in similar::capture_diff_slices_deadline(Algorithm::Myers, base, side, deadline) {
+        let (tag, old, new) = op.as_tag_tuple();
+        if tag == DiffTag::Equal {
+            continue;
+        }
+        match edits.last_mut() {
+            Some(last) if last.base.end == old.start && last.side.end == new.start => {
+                last.base.end = old.end;
+                last.side.end = new.end;
+            }
+            _ => edits.push(Edit { base: old, side: new }),
+        }
+    }
+    edits
+}
+
+/// What one side has in place of base lines `region`, given its `edits`
+/// within the region
+fn side_region(base: &[&str], side: &[&str], edits: &[Edit], region: &Range<usize>) -> String {
+    match (edits.first(), edits.last()) {
+        (Some(first), Some(last)) => {
+            let start = first.side.start - (first.base.start - region.start);
+            let end = last.side.end + (region.end - last.base.end);
+            side[start..end].concat()
+        }
+        _ => base[region.clone()].concat(),
+    }
+}
+
+/// Push `text` to `out` on lines of its own
+fn push_lines(out: &mut String, text: &str) {
+    out.push_str(text);
+    if !text.is_empty() && !text.ends_with('\n') {
+        out.push('\n');
+    }
+}
+
+/// Line-based three-way merge of `ours` and `theirs`, which both derive
+/// from `base`. Changes to overlapping or adjacent lines are left as
+/// conflicts, marked with `labels`. Without a base, every difference is a
+/// conflict.
+pub fn merge(base: Option<&str>, ours: &str, theirs: &str, labels: (&str, &str)) -> Merge {
+    let ours_lines: Vec<&str> = ours.split_inclusive('\n').collect();
+    let theirs_lines: Vec<&str> = theirs.split_inclusive('\n').collect();
+    // With no base, the lines both sides share stand in for one
+    let base_lines: Vec<&str> = match base {
+        Some(base) => base.split_inclusive('\n').collect(),
+        None => {
+            let deadline = Some(Instant::now() + DIFF_TIMEOUT);
+            similar::capture_diff_slices_deadline(Algorithm::Myers, &ours_lines, &theirs_lines, deadline)
+                .iter()
+                .filter(|op| op.tag() == DiffTag::Equal)
+                .flat_map(|op| ours_lines[op.old_range()].iter().copied())
+                .collect()
+        }
+    };
+    let ours_edits = edits(&base_lines, &ours_lines);
+    let theirs_edits = edits(&base_lines, &theirs_lines);
+
+    let mut merged = Merge {
+        text: String::new(),
+        conflicts: 0,
+    };
+    let (mut copied, mut i, mut j) = (0, 0, 0);
+    while i < ours_edits.len() || j < theirs_edits.len() {
+        // Start at the first change, then take in every change from either
+        // side that overlaps or touches the region so far
+        let start = match (ours_edits.get(i), theirs_edits.get(j)) {
+            (Some(a), Some(b)) => a.base.start.min(b.base.start),
+            (Some(a), None) => a.base.start,
+            (None, Some(b)) => b.base.start,
+            (None, None) => break,
+        };
+        let mut region = start..start;
+        let (first_ours, first_theirs) = (i, j);
+        loop {
+            if let Some(edit) = ours_edits.get(i).filter(|e| e.base.start <= region.end) {
+                region.end = region.end.max(edit.base.end);
+                i += 1;
+            } else if let Some(edit) = theirs_edits.get(j).filter(|e| e.base.start <= region.end) {
+                region.end = region.end.max(edit.base.end);
+                j += 1;
+            } else {
+                break;
+            }
+        }
+
+        merged.text.push_str(&base_lines[copied..region.start].concat());
+        copied = region.end;
+        let ours_text = side_region(&base_lines, &ours_lines, &ours_edits[first_ours..i], &region);
+        let theirs_text = side_region(&base_lines, &theirs_lines, &theirs_edits[first_theirs..j], &region);
+        if base.is_some() && first_theirs == j {
+            merged.text.push_str(&ours_text);
+        } else if base.is_some() && first_ours == i || ours_text == theirs_text {
+            merged.text.push_str(&theirs_text);
+        } else {
+            merged.conflicts += 1;
+            merged.text.push_str(&format!("<<<<<<< {}\n", labels.0));
+            push_lines(&mut merged.text, &ours_text);
+            merged.text.push_str("=======\n");
+            push_lines(&mut merged.text, &theirs_text);
+            merged.text.push_str(&format!(">>>>>>> {}\n", labels.1));
+        }
+    }
+    merged.text.push_str(&base_lines[copied..].concat());
+    merged
+}
+
+/// Line diff of two texts
+#[tauri::command(async)]
+pub fn diff_text(a: String, b: String, context: Option<usize>) -> Result<TextDiff, String> {
+    Ok(diff(&a, &b, context.unwrap_or(DEFAULT_CONTEXT)))
+}
+
+/// Contents of version `id` of `path`, or of the file itself for `current`,
+/// decoded and decrypted as `read_version` does
+fn version_or_current(
+    path: &Path,
+    id: &str,
+    registry: &NoteIndexRegistry,
+    keys: &EncryptionKeys,
+) -> Result<String, CommandError> {
+    let bytes = if id == CURRENT_VERSION {
+        fs::read(path).map_err(|e| format!("Failed to read file: {}", e))?
+    } else {
+        history::version_bytes(path, id, registry)?
+    };
+    Ok(encoding::decode(&keys.decrypt(path, bytes)?).content)
+}
+
+/// Line diff between two saved versions of a file. Either id can be
+/// `current` for the file as it is now. Encrypted notes are compared
+/// decrypted, and need their vault unlocked.
+#[tauri::command(async)]
+pub fn diff_versions(
+    path: String,
+    id_a: String,
+    id_b: String,
+    context: Option<usize>,
+    registry: tauri::State<'_, NoteIndexRegistry>,
+    keys: tauri::State<'_, EncryptionKeys>,
+) -> Result<TextDiff, CommandError> {
+    let path = Path::new(&path);
+    let a = version_or_current(path, &id_a, &registry, &keys)?;
+    let b = version_or_current(path, &id_b, &registry, &keys)?;
+    Ok(diff(&a, &b, context.unwrap_or(DEFAULT_CONTEXT)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LABELS: (&str, &str) = ("ours", "theirs");
+
+    #[test]
+    fn merges_changes_apart_from_each_other() {
+        let merged = merge(Some("a\nb\nc\nd\ne\n"), "a\nB\nc\nd\ne\n", "a\nb\nc\nD\ne\n", LABELS);
+        assert_eq!(merged.conflicts, 0);
+        assert_eq!(merged.text, "a\nB\nc\nD\ne\n");
+    }
+
+    #[test]
+    fn takes_a_change_made_on_one_side_only() {
+        let base = "a\nb\nc\n";
+        let merged = merge(Some(base), base, "a\nb\nc\nd\n", LABELS);
+        assert_eq!((merged.text.as_str(), merged.conflicts), ("a\nb\nc\nd\n", 0));
+        let merged = merge(Some(base), "a\nc\n", base, LABELS);
+        assert_eq!((merged.text.as_str(), merged.conflicts), ("a\nc\n", 0));
+    }
+
+    #[test]
+    fn takes_the_same_change_from_both_sides_once() {
+        let merged = merge(Some("a\nb\nc\n"), "a\nB\nc\n", "a\nB\nc\n", LABELS);
+        assert_eq!((merged.text.as_str(), merged.conflicts), ("a\nB\nc\n", 0));
+    }
+
+    #[test]
+    fn marks_overlapping_changes_as_a_conflict() {
+        let merged = merge(Some("a\nb\nc\n"), "a\nB1\nc\n", "a\nB2\nc\n", LABELS);
+        assert_eq!(merged.conflicts, 1);
+        assert_eq!(merged.text, "a\n<<<<<<< ours\nB1\n=======\nB2\n>>>>>>> theirs\nc\n");
+    }
+
+    #[test]
+    fn marks_changes_to_adjacent_lines_as_a_conflict() {
+        let merged = merge(Some("a\nb\nc\nd\n"), "a\nB\nc\nd\n", "a\nb\nC\nd\n", LABELS);
+        assert_eq!(merged.conflicts, 1);
+        assert_eq!(merged.text, "a\n<<<<<<< ours\nB\nc\n=======\nb\nC\n>>>>>>> theirs\nd\n");
+    }
+
+    #[test]
+    fn counts_each_conflicting_region() {
+        let merged = merge(Some("a\nb\nc\nd\ne\n"), "A1\nb\nc\nd\nE1\n", "A2\nb\nc\nd\nE2\n", LABELS);
+        assert_eq!(merged.conflicts, 2);
+        assert!(merged.text.contains("\nb\nc\nd\n"));
+    }
+
+    #[test]
+    fn puts_conflict_markers_on_lines_of_their_own() {
+        let merged = merge(Some("a\nb"), "a\nX", "a\nY", LABELS);
+        assert_eq!(merged.text, "a\n<<<<<<< ours\nX\n=======\nY\n>>>>>>> theirs\n");
+    }
+
+    #[test]
+    fn without_a_base_every_difference_conflicts() {
+        let merged = merge(None, "a\nb\n", "a\n", LABELS);
+        assert_eq!(merged.conflicts, 1);
+        assert_eq!(merged.text, "a\n<<<<<<< ours\nb\n=======\n>>>>>>> theirs\n");
+        let merged = merge(None, "a\nb\n", "a\nb\n", LABELS);
+        assert_eq!((merged.text.as_str(), merged.conflicts), ("a\nb\n", 0));
+    }
+}
@@ -0,0 +1,185 @@
+use chardetng::{EncodingDetector, Iso2022JpDetection, Utf8Detection};
+use encoding_rs::{Encoding, UTF_16BE, UTF_16LE, UTF_8};
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+
+/// Bytes looked at when guessing whether a file without a BOM is UTF-16
+const UTF16_SNIFF_LEN: usize = 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LineEnding {
+    Lf,
+    Crlf,
+    Cr,
+}
+
+impl LineEnding {
+    fn as_str(self) -> &'static str {
+        match self {
+            LineEnding::Lf => "\n",
+            LineEnding::Crlf => "\r\n",
+            LineEnding::Cr => "\r",
+        }
+    }
+}
+
+/// A text file decoded for the editor
+#[derive(Debug, Clone, Serialize)]
+pub struct TextFile {
+    /// The text, with line endings turned into `\n`
+    pub content: String,
+    /// Name of the encoding, e.g. `UTF-8`, `windows-1252` or `UTF-16LE`
+    pub encoding: String,
+    /// Whether the file starts with a byte order mark
+    pub bom: bool,
+    /// The most common line ending of the file, `lf` if it has no line breaks
+    pub line_ending: LineEnding,
+    /// Whether the file is an encrypted note, decrypted for reading
+    pub encrypted: bool,
+    /// SHA-256 of the file's bytes on disk, for `write_text_file`'s
+    /// `expected_hash`; only set by `read_text_file`
+    pub hash: Option<String>,
+    /// Modification time of the file when it was read, in milliseconds since
+    /// the Unix epoch, for `expected_mtime`; only set by `read_text_file`
+    pub mtime: Option<u64>,
+}
+
+/// How a text file is written back
+#[derive(Debug, Clone, Copy)]
+pub struct TextFormat {
+    pub encoding: &'static Encoding,
+    pub bom: bool,
+    pub line_ending: LineEnding,
+}
+
+impl Default for TextFormat {
+    fn default() -> Self {
+        TextFormat {
+            encoding: UTF_8,
+            bom: false,
+            line_ending: LineEnding::Lf,
+        }
+    }
+}
+
+/// The encoding named `label`, accepting the usual aliases such as `latin1`
+pub fn encoding_for_label(label: &str) -> Result<&'static Encoding, String> {
+    Encoding::for_label(label.trim().as_bytes()).ok_or_else(|| format!("Unknown encoding: {}", label))
+}
+
+/// UTF-16 without a BOM, recognized by the zero bytes of mostly-ASCII text
+fn sniff_utf16(bytes: &[u8]) -> Option<&'static Encoding> {
+    let sample = &bytes[..bytes.len().min(UTF16_SNIFF_LEN) & !1];
+    if sample.is_empty() {
+        return None;
+    }
+    let pairs = sample.len() / 2;
+    let zero_high = sample.chunks(2).filter(|pair| pair[0] != 0 && pair[1] == 0).count();
+    let zero_low = sample.chunks(2).filter(|pair| pair[0] == 0 && pair[1] != 0).count();
+    if zero_high * 10 >= pairs * 9 {
+        Some(UTF_16LE)
+    } else if zero_low * 10 >= pairs * 9 {
+        Some(UTF_16BE)
+    } else {
+        None
+    }
+}
+
+/// The encoding of `bytes` and the length of its BOM, if any
+fn detect(bytes: &[u8]) -> (&'static Encoding, usize) {
+    if let Some(found) = Encoding::for_bom(bytes) {
+        return found;
+    }
+    // Before UTF-8, which ASCII text in UTF-16 also is
+    if let Some(encoding) = sniff_utf16(bytes) {
+        return (encoding, 0);
+    }
+    if std::str::from_utf8(bytes).is_ok() {
+        return (UTF_8, 0);
+    }
+    let mut detector = EncodingDetector::new(Iso2022JpDetection::Deny);
+    detector.feed(bytes, true);
+    (detector.guess(None, Utf8Detection::Allow), 0)
+}
+
+fn detect_line_ending(text: &str) -> LineEnding {
+    let crlf = text.matches("\r\n").count();
+    let lf = text.matches('\n').count() - crlf;
+    let cr = text.matches('\r').count() - crlf;
+    if crlf > lf && crlf >= cr {
+        LineEnding::Crlf
+    } else if cr > lf && cr > crlf {
+        LineEnding::Cr
+    } else {
+        LineEnding::Lf
+    }
+}
+
+/// The text of `bytes` as is, and how it is encoded
+fn decode_raw(bytes: &[u8]) -> (Cow<'_, str>, TextFormat) {
+    let (encoding, bom_len) = detect(bytes);
+    let (text, _) = encoding.decode_without_bom_handling(&bytes[bom_len..]);
+    let format = TextFormat {
+        encoding,
+        bom: bom_len > 0,
+        line_ending: detect_line_ending(&text),
+    };
+    (text, format)
+}
+
+/// Decode the bytes of a text file in whatever encoding they appear to be in
+pub fn decode(bytes: &[u8]) -> TextFile {
+    let (text, format) = decode_raw(bytes);
+    TextFile {
+        content: text.replace("\r\n", "\n").replace('\r', "\n"),
+        encoding: format.encoding.name().to_string(),
+        bom: format.bom,
+        line_ending: format.line_ending,
+        encrypted: false,
+        hash: None,
+        mtime: None,
+    }
+}
+
+/// How the file `bytes` were read from is encoded, for writing it back the
+/// same way
+pub fn format_of(bytes: &[u8]) -> TextFormat {
+    decode_raw(bytes).1
+}
+
+/// Encode `content` for disk, with its line breaks in `format`'s style.
+/// Fails if the encoding can't represent some of the text.
+pub fn encode(content: &str, format: TextFormat) -> Result<Vec<u8>, String> {
+    let text = content.replace("\r\n", "\n");
+    let text = match format.line_ending {
+        LineEnding::Lf => text,
+        ending => text.replace('\n', ending.as_str()),
+    };
+
+    let mut bytes = Vec::with_capacity(text.len() + 3);
+    // encoding_rs only decodes UTF-16, so it is encoded by hand
+    if format.encoding == UTF_16LE || format.encoding == UTF_16BE {
+        let little_endian = format.encoding == UTF_16LE;
+        if format.bom {
+            bytes.extend_from_slice(if little_endian { &[0xFF, 0xFE] } else { &[0xFE, 0xFF] });
+        }
+        for unit in text.encode_utf16() {
+            bytes.extend_from_slice(&if little_endian { unit.to_le_bytes() } else { unit.to_be_bytes() });
+        }
+        return Ok(bytes);
+    }
+
+    if format.bom && format.encoding == UTF_8 {
+        bytes.extend_from_slice(&[0xEF, 0xBB, 0xBF]);
+    }
+    let (encoded, _, unmappable) = format.encoding.encode(&text);
+    if unmappable {
+        return Err(format!(
+            "The text has characters that can't be saved as {}; save it as UTF-8 instead",
+            format.encoding.name()
+        ));
+    }
+    bytes.extend_from_slice(&encoded);
+    Ok(bytes)
+}
@@ -0,0 +1,230 @@
+use aes_gcm::aead::rand_core::RngCore;
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Nonce};
+use argon2::Argon2;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::error::{CommandError, NoteLocked};
+use crate::history;
+use crate::note_index::NoteIndexRegistry;
+use crate::settings::{self, SettingsStore};
+use crate::WriteResult;
+
+/// Start of every encrypted note, followed by the nonce and the AES-256-GCM
+/// ciphertext
+const MAGIC: &[u8] = b"READMARK-ENCRYPTED-1\n";
+
+const NONCE_LEN: usize = 12;
+
+const SALT_LEN: usize = 16;
+
+/// Name of the file in a vault's `.readmark` folder holding what is needed
+/// to check a password; never the key itself
+const KEY_FILE: &str = "encryption.json";
+
+/// Encrypted into the key file, so a wrong password is told apart from a
+/// right one
+const CHECK_TEXT: &[u8] = b"readmark";
+
+#[derive(Serialize, Deserialize)]
+struct KeyFile {
+    /// Base64 Argon2id salt the key is derived with
+    salt: String,
+    /// Base64 `CHECK_TEXT`, encrypted with the key
+    check: String,
+}
+
+/// Keys of the unlocked vaults, held in memory only
+#[derive(Default)]
+pub struct EncryptionKeys {
+    unlocked: Mutex<HashMap<PathBuf, Aes256Gcm>>,
+}
+
+/// Whether `bytes` are an encrypted note
+pub fn is_encrypted(bytes: &[u8]) -> bool {
+    bytes.starts_with(MAGIC)
+}
+
+fn seal(cipher: &Aes256Gcm, plaintext: &[u8]) -> Result<Vec<u8>, String> {
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+    let ciphertext = cipher.encrypt(&nonce, plaintext).map_err(|_| "Failed to encrypt note".to_string())?;
+    Ok([MAGIC, nonce.as_slice(), &ciphertext].concat())
+}
+
+fn open(cipher: &Aes256Gcm, sealed: &[u8]) -> Result<Vec<u8>, String> {
+    let body = sealed.strip_prefix(MAGIC).ok_or("Not an encrypted note")?;
+    if body.len() < NONCE_LEN {
+        return Err("Encrypted note is truncated".to_string());
+    }
+    let (nonce, ciphertext) = body.split_at(NONCE_LEN);
+    cipher
+        .decrypt(Nonce::from_slice(nonce), ciphertext)
+        .map_err(|_| "Failed to decrypt note: wrong key or damaged file".to_string())
+}
+
+fn derive(password: &str, salt: &[u8]) -> Result<Aes256Gcm, String> {
+    let mut key = [0u8; 32];
+    Argon2::default()
+        .hash_password_into(password.as_bytes(), salt, &mut key)
+        .map_err(|e| format!("Failed to derive key: {}", e))?;
+    Aes256Gcm::new_from_slice(&key).map_err(|e| format!("Invalid key: {}", e))
+}
+
+fn key_file_path(root: &Path) -> PathBuf {
+    root.join(crate::DATA_DIR).join(KEY_FILE)
+}
+
+impl EncryptionKeys {
+    /// The key of the unlocked vault `path` is in
+    fn cipher_for(&self, path: &Path) -> Result<Option<Aes256Gcm>, String> {
+        let unlocked = self.unlocked.lock().map_err(|e| format!("Lock error: {}", e))?;
+        Ok(unlocked
+            .iter()
+            .filter(|(root, _)| path.starts_with(root))
+            .max_by_key(|(root, _)| root.as_os_str().len())
+            .map(|(_, cipher)| cipher.clone()))
+    }
+
+    fn locked(path: &Path) -> CommandError {
+        NoteLocked {
+            path: path.to_string_lossy().to_string(),
+        }
+        .into()
+    }
+
+    /// The plaintext of the file `path` read as `bytes`: decrypted if it is
+    /// an encrypted note, as is otherwise
+    pub fn decrypt(&self, path: &Path, bytes: Vec<u8>) -> Result<Vec<u8>, CommandError> {
+        if !is_encrypted(&bytes) {
+            return Ok(bytes);
+        }
+        let cipher = self.cipher_for(path)?.ok_or_else(|| Self::locked(path))?;
+        Ok(open(&cipher, &bytes)?)
+    }
+
+    /// `plaintext` encrypted for the file `path`, whose vault must be
+    /// unlocked
+    pub fn encrypt(&self, path: &Path, plaintext: &[u8]) -> Result<Vec<u8>, CommandError> {
+        let cipher = self.cipher_for(path)?.ok_or_else(|| Self::locked(path))?;
+        Ok(seal(&cipher, plaintext)?)
+    }
+}
+
+/// Unlock the encrypted notes of the vault at `root` with `password`. The
+/// first unlock of a vault sets its password. The key stays in memory until
+/// `lock_vault` or the app quits.
+#[tauri::command(async)]
+pub fn unlock_vault(root: String, password: String, keys: tauri::State<'_, EncryptionKeys>) -> Result<(), String> {
+    if password.is_empty() {
+        return Err("A password is required".to_string());
+    }
+    let root = PathBuf::from(&root);
+    crate::ensure_dir(&root)?;
+    let path = key_file_path(&root);
+    let engine = base64::engine::general_purpose::STANDARD;
+
+    let cipher = match fs::read_to_string(&path) {
+        Ok(raw) => {
+            let file: KeyFile = serde_json::from_str(&raw).map_err(|e| format!("Invalid key file: {}", e))?;
+            let salt = engine.decode(&file.salt).map_err(|e| format!("Invalid key file: {}", e))?;
+            let check = engine.decode(&file.check).map_err(|e| format!("Invalid key file: {}", e))?;
+            let cipher = derive(&password, &salt)?;
+            if open(&cipher, &check).ok().as_deref() != Some(CHECK_TEXT) {
+                return Err("Wrong password".to_string());
+            }
+            cipher
+        }
+        Err(e) if e.kind() == ErrorKind::NotFound => {
+            let mut salt = [0u8; SALT_LEN];
+            OsRng.fill_bytes(&mut salt);
+            let cipher = derive(&password, &salt)?;
+            let file = KeyFile {
+                salt: engine.encode(salt),
+                check: engine.encode(seal(&cipher, CHECK_TEXT)?),
+            };
+            let json = serde_json::to_string_pretty(&file).map_err(|e| format!("Failed to serialize key file: {}", e))?;
+            fs::create_dir_all(root.join(crate::DATA_DIR)).map_err(|e| format!("Failed to create directory: {}", e))?;
+            crate::write_atomic(&path, json.as_bytes()).map_err(|e| format!("Failed to write key file: {}", e))?;
+            cipher
+        }
+        Err(e) => return Err(format!("Failed to read key file: {}", e)),
+    };
+
+    let mut unlocked = keys.unlocked.lock().map_err(|e| format!("Lock error: {}", e))?;
+    unlocked.insert(root, cipher);
+    Ok(())
+}
+
+/// Forget the key of the vault at `root`, so its encrypted notes can't be
+/// read until it is unlocked again
+#[tauri::command]
+pub fn lock_vault(root: String, keys: tauri::State<'_, EncryptionKeys>) -> Result<(), String> {
+    let mut unlocked = keys.unlocked.lock().map_err(|e| format!("Lock error: {}", e))?;
+    unlocked.remove(Path::new(&root));
+    Ok(())
+}
+
+/// Whether the vault at `root` is unlocked
+#[tauri::command]
+pub fn is_vault_unlocked(root: String, keys: tauri::State<'_, EncryptionKeys>) -> Result<bool, String> {
+    let unlocked = keys.unlocked.lock().map_err(|e| format!("Lock error: {}", e))?;
+    Ok(unlocked.contains_key(Path::new(&root)))
+}
+
+/// Replace the note at `path` with `bytes`, keeping its index up to date
+fn replace_note(path: &Path, bytes: &[u8], registry: &NoteIndexRegistry) -> Result<WriteResult, String> {
+    crate::write_atomic(path, bytes).map_err(|e| format!("Failed to write file: {}", e))?;
+    if let Some(index) = registry.for_path(path) {
+        index.update_path(path);
+    }
+    Ok(WriteResult {
+        mtime: fs::metadata(path).ok().as_ref().and_then(crate::mtime_millis),
+        hash: crate::content_hash(bytes),
+    })
+}
+
+/// Encrypt the note at `path` in place; its vault must be unlocked. From
+/// then on `read_text_file` and `write_text_file` decrypt and encrypt it.
+/// Its history, which holds the plaintext, is deleted.
+#[tauri::command(async)]
+pub fn encrypt_note(
+    path: String,
+    keys: tauri::State<'_, EncryptionKeys>,
+    registry: tauri::State<'_, NoteIndexRegistry>,
+    settings: tauri::State<'_, Mutex<SettingsStore>>,
+) -> Result<WriteResult, CommandError> {
+    let path_buf = PathBuf::from(&path);
+    settings::check_writable(&settings, &path_buf)?;
+    let bytes = fs::read(&path_buf).map_err(|e| format!("Failed to read file: {}", e))?;
+    if is_encrypted(&bytes) {
+        return Err(format!("Note is already encrypted: {}", path).into());
+    }
+    let sealed = keys.encrypt(&path_buf, &bytes)?;
+    let written = replace_note(&path_buf, &sealed, &registry)?;
+    history::forget(&path_buf, &registry)?;
+    Ok(written)
+}
+
+/// Store the encrypted note at `path` as plaintext again
+#[tauri::command(async)]
+pub fn decrypt_note(
+    path: String,
+    keys: tauri::State<'_, EncryptionKeys>,
+    registry: tauri::State<'_, NoteIndexRegistry>,
+    settings: tauri::State<'_, Mutex<SettingsStore>>,
+) -> Result<WriteResult, CommandError> {
+    let path_buf = PathBuf::from(&path);
+    settings::check_writable(&settings, &path_buf)?;
+    let bytes = fs::read(&path_buf).map_err(|e| format!("Failed to read file: {}", e))?;
+    if !is_encrypted(&bytes) {
+        return Err(format!("Note is not encrypted: {}", path).into());
+    }
+    let plaintext = keys.decrypt(&path_buf, bytes)?;
+    Ok(replace_note(&path_buf, &plaintext, &registry)?)
+}
@@ -0,0 +1,143 @@
+use serde::{Serialize, Serializer};
+
+/// Error type for commands that need to hand structured data back to the
+/// frontend. Plain messages serialize as a bare string, matching the
+/// `Result<_, String>` commands, while the other variants serialize as
+/// objects tagged with a `kind` field.
+#[derive(Debug)]
+pub enum CommandError {
+    Message(String),
+    Conflict(ConflictError),
+    Tool(ToolError),
+    TooLarge(TooLargeError),
+    ReadOnly(ReadOnlyVault),
+    Locked(NoteLocked),
+}
+
+/// The file on disk changed since the frontend last read it
+#[derive(Debug, Serialize)]
+#[serde(tag = "kind", rename = "conflict")]
+pub struct ConflictError {
+    pub path: String,
+    /// `None` if the file has been deleted in the meantime
+    pub current_mtime: Option<u64>,
+    /// SHA-256 of the bytes on disk, as `TextFile::hash`
+    pub current_hash: Option<String>,
+    /// Current on-disk text, decoded as `read_text_file` does; never given
+    /// for encrypted notes
+    pub current_content: Option<String>,
+}
+
+/// The file is bigger than the read size limit; `read_file_range` reads it
+/// piece by piece instead
+#[derive(Debug, Serialize)]
+#[serde(tag = "kind", rename = "too_large")]
+pub struct TooLargeError {
+    pub path: String,
+    /// Size of the file in bytes
+    pub size: u64,
+    /// The limit in bytes
+    pub limit: u64,
+}
+
+/// The change would modify a vault that is set to read-only
+#[derive(Debug, Serialize)]
+#[serde(tag = "kind", rename = "read_only_vault")]
+pub struct ReadOnlyVault {
+    pub root: String,
+    /// The file or folder that would have changed
+    pub path: String,
+}
+
+/// The note is encrypted and its vault hasn't been unlocked
+#[derive(Debug, Serialize)]
+#[serde(tag = "kind", rename = "note_locked")]
+pub struct NoteLocked {
+    pub path: String,
+}
+
+/// An external program a command relies on is missing or failed
+#[derive(Debug, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ToolError {
+    /// Not installed, or not anywhere it is looked for
+    ToolMissing {
+        tool: String,
+        /// Where to get it
+        install_url: String,
+    },
+    /// Ran but exited unsuccessfully
+    ToolFailed {
+        tool: String,
+        /// Exit code, if it wasn't killed by a signal
+        status: Option<i32>,
+        stderr: String,
+    },
+}
+
+impl From<String> for CommandError {
+    fn from(message: String) -> Self {
+        CommandError::Message(message)
+    }
+}
+
+impl From<ConflictError> for CommandError {
+    fn from(conflict: ConflictError) -> Self {
+        CommandError::Conflict(conflict)
+    }
+}
+
+impl From<ToolError> for CommandError {
+    fn from(error: ToolError) -> Self {
+        CommandError::Tool(error)
+    }
+}
+
+impl From<TooLargeError> for CommandError {
+    fn from(error: TooLargeError) -> Self {
+        CommandError::TooLarge(error)
+    }
+}
+
+impl From<ReadOnlyVault> for CommandError {
+    fn from(error: ReadOnlyVault) -> Self {
+        CommandError::ReadOnly(error)
+    }
+}
+
+impl From<NoteLocked> for CommandError {
+    fn from(error: NoteLocked) -> Self {
+        CommandError::Locked(error)
+    }
+}
+
+impl std::fmt::Display for CommandError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CommandError::Message(message) => f.write_str(message),
+            CommandError::Conflict(conflict) => {
+                write!(f, "File changed on disk: {}", conflict.path)
+            }
+            CommandError::Tool(ToolError::ToolMissing { tool, .. }) => write!(f, "{} is not installed", tool),
+            CommandError::Tool(ToolError::ToolFailed { tool, stderr, .. }) => write!(f, "{} failed: {}", tool, stderr),
+            CommandError::Locked(error) => write!(f, "Note is encrypted and its vault is locked: {}", error.path),
+            CommandError::ReadOnly(error) => write!(f, "Vault is read-only: {}", error.root),
+            CommandError::TooLarge(error) => {
+                write!(f, "File is too large to open ({} bytes, limit {}): {}", error.size, error.limit, error.path)
+            }
+        }
+    }
+}
+
+impl Serialize for CommandError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            CommandError::Message(message) => serializer.serialize_str(message),
+            CommandError::Conflict(conflict) => conflict.serialize(serializer),
+            CommandError::Tool(error) => error.serialize(serializer),
+            CommandError::TooLarge(error) => error.serialize(serializer),
+            CommandError::ReadOnly(error) => error.serialize(serializer),
+            CommandError::Locked(error) => error.serialize(serializer),
+        }
+    }
+}
@@ -0,0 +1,754 @@
+use base64::Engine;
+use pulldown_cmark::{CowStr, Event, LinkType, Tag, TagEnd};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::env;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::error::{CommandError, ToolError};
+use crate::frontmatter;
+use crate::jobs::{self, Job};
+use crate::links::{self, Link, LinkKind, NameLookup, Resolver};
+use crate::markdown::{self, Heading};
+use crate::note_index::NoteIndexRegistry;
+use crate::render;
+use crate::settings::SettingsStore;
+use crate::tags;
+
+/// Stylesheet embedded in exported pages
+const EXPORT_CSS: &str = r#"
+body { margin: 0; background: #fff; color: #1f2328; }
+article { max-width: 46rem; margin: 0 auto; padding: 2rem 1.5rem 4rem;
+  font: 16px/1.6 -apple-system, BlinkMacSystemFont, "Segoe UI", Helvetica, Arial, sans-serif; }
+h1, h2, h3, h4, h5, h6 { line-height: 1.25; margin: 1.5em 0 0.5em; }
+h1, h2 { border-bottom: 1px solid #d1d9e0; padding-bottom: 0.3em; }
+a { color: #0969da; text-decoration: none; }
+a:hover { text-decoration: underline; }
+img { max-width: 100%; }
+code { font: 0.875em ui-monospace, SFMono-Regular, Menlo, Consolas, monospace;
+  background: #f6f8fa; padding: 0.2em 0.4em; border-radius: 4px; }
+pre { background: #f6f8fa; padding: 1em; overflow: auto; border-radius: 6px; }
+pre code { background: none; padding: 0; }
+blockquote { margin: 0; padding: 0 1em; color: #59636e; border-left: 0.25em solid #d1d9e0; }
+table { border-collapse: collapse; }
+th, td { border: 1px solid #d1d9e0; padding: 0.4em 0.8em; }
+li:has(> input[type="checkbox"]) { list-style: none; }
+.footnote-definition { font-size: 0.875em; }
+nav { font-size: 0.875em; margin-bottom: 1.5rem; }
+@media (prefers-color-scheme: dark) {
+  body { background: #0d1117; color: #e6edf3; }
+  a { color: #4493f8; }
+  code, pre { background: #151b23; }
+  h1, h2, th, td, blockquote { border-color: #3d444d; }
+  blockquote { color: #9198a1; }
+}
+"#;
+
+/// How `[[wiki links]]` to other notes appear in an export. Links to
+/// headings in the same note always become `#anchor` links.
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum WikiLinkStyle {
+    /// The link text, without a link
+    #[default]
+    Text,
+    /// A relative link to the note's `.html` export. Markdown links to
+    /// notes are pointed at the `.html` file too.
+    Anchor,
+}
+
+#[derive(Debug, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct HtmlExportOptions {
+    pub wiki_links: WikiLinkStyle,
+    /// Page title; defaults to the note's first heading, then its file name
+    pub title: Option<String>,
+    /// Extra CSS added after the built-in stylesheet
+    pub css: Option<String>,
+}
+
+/// Escape `text` for use in HTML content or attribute values
+pub fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// MIME type of an image, by file extension
+pub fn image_mime(path: &Path) -> Option<&'static str> {
+    let extension = path.extension()?.to_string_lossy().to_lowercase();
+    Some(match extension.as_str() {
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "svg" => "image/svg+xml",
+        "bmp" => "image/bmp",
+        "ico" => "image/x-icon",
+        "avif" => "image/avif",
+        _ => return None,
+    })
+}
+
+/// `data:` URL with the contents of an image file
+pub fn data_url(path: &Path) -> Option<String> {
+    let mime = image_mime(path)?;
+    let bytes = fs::read(path).ok()?;
+    Some(format!("data:{};base64,{}", mime, base64::engine::general_purpose::STANDARD.encode(bytes)))
+}
+
+/// A complete HTML page around rendered note `body`
+pub fn html_document(title: &str, body: &str, extra_css: Option<&str>) -> String {
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+         <meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n\
+         <title>{}</title>\n<style>{}{}</style>\n</head>\n<body>\n<article>\n{}</article>\n</body>\n</html>\n",
+        escape_html(title),
+        EXPORT_CSS,
+        extra_css.unwrap_or_default(),
+        body
+    )
+}
+
+/// Title for an exported note: its first heading, else the file name
+pub fn note_title(path: &Path, headings: &[Heading]) -> String {
+    match headings.first() {
+        Some(heading) => heading.text.clone(),
+        None => path.file_stem().unwrap_or_default().to_string_lossy().to_string(),
+    }
+}
+
+/// Renders one note for export, with heading ids for anchors and links and
+/// images rewritten to work outside the app
+pub struct NoteExporter<'a> {
+    pub resolver: &'a Resolver<'a>,
+    /// The note being exported
+    pub source: &'a Path,
+    pub wiki_links: WikiLinkStyle,
+    /// Rewrites a local image file into the `src` to use, e.g. a `data:` URL
+    pub image_src: &'a dyn Fn(&Path) -> Option<String>,
+}
+
+impl NoteExporter<'_> {
+    /// `href` for a link to `target` in the exported pages, or `None` if it
+    /// doesn't point at a note that is exported
+    fn note_href(&self, kind: LinkKind, target: &str, fragment: Option<&str>, headings: &[Heading]) -> Option<String> {
+        let anchor = |headings: &[Heading], fragment: &str| match links::find_heading(headings, fragment) {
+            Some(heading) => heading.slug.clone(),
+            None => markdown::slugify(fragment.rsplit('#').next().unwrap_or_default()),
+        };
+        if target.is_empty() {
+            return fragment.map(|fragment| format!("#{}", anchor(headings, fragment)));
+        }
+        if self.wiki_links == WikiLinkStyle::Text {
+            return None;
+        }
+
+        let link = Link {
+            kind,
+            embed: false,
+            target: target.to_string(),
+            fragment: None,
+            text: String::new(),
+            line: 0,
+            start: 0,
+            end: 0,
+        };
+        let resolved = self.resolver.resolve(self.source, &link)?;
+        if !crate::settings::has_note_extension(&resolved, self.resolver.note_extensions) {
+            return None;
+        }
+        let relative = crate::rename::relative_path(self.source.parent()?, &resolved.with_extension("html"));
+        let href = relative.replace(' ', "%20");
+        Some(match fragment {
+            Some(fragment) => {
+                let headings = fs::read_to_string(&resolved).map(|c| markdown::headings(&c)).unwrap_or_default();
+                format!("{}#{}", href, anchor(&headings, fragment))
+            }
+            None => href,
+        })
+    }
+
+    pub fn render(&self, content: &str) -> String {
+        let headings = markdown::headings(content);
+        let mut slugs = headings.iter().map(|heading| heading.slug.clone());
+        // Whether each open link is kept, so a dropped link's end is too
+        let mut open_links: Vec<bool> = Vec::new();
+
+        render::render(content, |event| match event {
+            Event::Start(Tag::Heading { level, id, classes, attrs }) => {
+                let id = slugs.next().map(CowStr::from).or(id);
+                Event::Start(Tag::Heading { level, id, classes, attrs })
+            }
+            Event::Start(Tag::Link {
+                link_type: link_type @ LinkType::WikiLink { .. },
+                dest_url,
+                title,
+                id,
+            }) => {
+                let (target, fragment) = match dest_url.split_once('#') {
+                    Some((target, fragment)) => (target, Some(fragment)),
+                    None => (&*dest_url, None),
+                };
+                match self.note_href(LinkKind::Wiki, target, fragment, &headings) {
+                    Some(href) => {
+                        open_links.push(true);
+                        Event::Start(Tag::Link {
+                            link_type,
+                            dest_url: href.into(),
+                            title,
+                            id,
+                        })
+                    }
+                    None => {
+                        open_links.push(false);
+                        Event::InlineHtml(CowStr::Borrowed(""))
+                    }
+                }
+            }
+            Event::Start(Tag::Link { link_type, dest_url, title, id }) => {
+                open_links.push(true);
+                let (target, fragment) = match dest_url.split_once('#') {
+                    Some((target, fragment)) => (target, Some(fragment)),
+                    None => (&*dest_url, None),
+                };
+                // Only links to other notes change; `#heading` links already work
+                let href = (!target.is_empty())
+                    .then(|| self.note_href(LinkKind::Markdown, target, fragment, &headings))
+                    .flatten();
+                Event::Start(Tag::Link {
+                    link_type,
+                    dest_url: href.map_or(dest_url, CowStr::from),
+                    title,
+                    id,
+                })
+            }
+            Event::End(TagEnd::Link) if !open_links.pop().unwrap_or(true) => Event::InlineHtml(CowStr::Borrowed("")),
+            Event::Start(Tag::Image { link_type, dest_url, title, id }) => {
+                let dest_url = render::local_image(self.resolver, self.source, link_type, &dest_url)
+                    .and_then(|path| (self.image_src)(&path))
+                    .map_or(dest_url, CowStr::from);
+                Event::Start(Tag::Image { link_type, dest_url, title, id })
+            }
+            event => event,
+        })
+    }
+}
+
+/// The HTML body of the note `content` at `source`, with local images
+/// embedded as data URLs
+pub fn embedded_body(
+    source: &Path,
+    content: &str,
+    wiki_links: WikiLinkStyle,
+    registry: &NoteIndexRegistry,
+    settings: &Mutex<SettingsStore>,
+) -> Result<String, String> {
+    let (root, note_extensions, names) = links::lookup_for(source, registry, settings)?;
+    let resolver = Resolver {
+        root: &root,
+        note_extensions: &note_extensions,
+        names: &names,
+    };
+    let exporter = NoteExporter {
+        resolver: &resolver,
+        source,
+        wiki_links,
+        image_src: &data_url,
+    };
+    Ok(exporter.render(content))
+}
+
+/// The note at `path` as one HTML page with the stylesheet and images
+/// embedded. `page_css` goes after the user's CSS.
+fn standalone_html(
+    path: &str,
+    options: &HtmlExportOptions,
+    page_css: &str,
+    registry: &NoteIndexRegistry,
+    settings: &Mutex<SettingsStore>,
+) -> Result<String, String> {
+    let source = links::normalize_path(Path::new(path));
+    let content = fs::read_to_string(&source).map_err(|e| format!("Failed to read file: {}", e))?;
+    let body = embedded_body(&source, &content, options.wiki_links, registry, settings)?;
+    let title = options
+        .title
+        .clone()
+        .unwrap_or_else(|| note_title(&source, &markdown::headings(&content)));
+    let css = format!("{}{}", options.css.as_deref().unwrap_or_default(), page_css);
+    Ok(html_document(&title, &body, Some(&css)))
+}
+
+fn create_parent(out: &Path) -> Result<(), String> {
+    match out.parent() {
+        Some(parent) => fs::create_dir_all(parent).map_err(|e| format!("Failed to create directory: {}", e)),
+        None => Ok(()),
+    }
+}
+
+pub fn write_output(out: &Path, contents: &[u8]) -> Result<(), String> {
+    create_parent(out)?;
+    crate::write_atomic(out, contents).map_err(|e| format!("Failed to write file: {}", e))
+}
+
+/// Export a note as a single HTML file with the stylesheet and local images
+/// embedded, for sharing with people who don't use the app
+#[tauri::command(async)]
+pub fn export_html(
+    path: String,
+    out_path: String,
+    options: Option<HtmlExportOptions>,
+    registry: tauri::State<'_, NoteIndexRegistry>,
+    settings: tauri::State<'_, Mutex<SettingsStore>>,
+) -> Result<(), String> {
+    let html = standalone_html(&path, &options.unwrap_or_default(), "", &registry, &settings)?;
+    write_output(Path::new(&out_path), html.as_bytes())
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct PdfExportOptions {
+    #[serde(flatten)]
+    pub html: HtmlExportOptions,
+    /// CSS page size such as `A4`, `Letter` or `210mm 297mm` (default A4)
+    pub page_size: String,
+    pub landscape: bool,
+    /// CSS margin around each page, e.g. `20mm` or `1in 0.75in` (default 20mm)
+    pub margin: String,
+}
+
+impl Default for PdfExportOptions {
+    fn default() -> Self {
+        PdfExportOptions {
+            html: HtmlExportOptions::default(),
+            page_size: "A4".to_string(),
+            landscape: false,
+            margin: "20mm".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ExportProgress {
+    /// The file or directory being written
+    pub out_path: String,
+    /// What the export is doing, e.g. `rendering` or `printing`
+    pub stage: String,
+    pub completed: usize,
+    pub total: usize,
+}
+
+fn emit_progress(app: &AppHandle, out_path: &str, stage: &str, completed: usize, total: usize) {
+    let _ = app.emit(
+        "export-progress",
+        ExportProgress {
+            out_path: out_path.to_string(),
+            stage: stage.to_string(),
+            completed,
+            total,
+        },
+    );
+}
+
+/// The first of `names` found on `PATH`, else the first of `installs` that
+/// exists. GUI apps on macOS don't inherit the shell's `PATH`, so the usual
+/// install locations are worth listing.
+pub fn find_program(names: &[&str], installs: &[&str]) -> Option<PathBuf> {
+    let path = env::var_os("PATH").unwrap_or_default();
+    let exe_suffix = if cfg!(windows) { ".exe" } else { "" };
+    env::split_paths(&path)
+        .flat_map(|dir| names.iter().map(move |name| dir.join(format!("{}{}", name, exe_suffix))))
+        .chain(installs.iter().map(PathBuf::from))
+        .find(|candidate| candidate.is_file())
+}
+
+/// A Chromium-based browser to print with
+fn find_browser() -> Option<PathBuf> {
+    const NAMES: &[&str] = &[
+        "chromium",
+        "chromium-browser",
+        "google-chrome",
+        "google-chrome-stable",
+        "chrome",
+        "microsoft-edge",
+        "msedge",
+        "brave-browser",
+    ];
+    const INSTALLS: &[&str] = &[
+        "/Applications/Google Chrome.app/Contents/MacOS/Google Chrome",
+        "/Applications/Chromium.app/Contents/MacOS/Chromium",
+        "/Applications/Microsoft Edge.app/Contents/MacOS/Microsoft Edge",
+        "/Applications/Brave Browser.app/Contents/MacOS/Brave Browser",
+        r"C:\Program Files\Google\Chrome\Application\chrome.exe",
+        r"C:\Program Files (x86)\Google\Chrome\Application\chrome.exe",
+        r"C:\Program Files (x86)\Microsoft\Edge\Application\msedge.exe",
+        r"C:\Program Files\Microsoft\Edge\Application\msedge.exe",
+    ];
+    find_program(NAMES, INSTALLS)
+}
+
+/// Export a note to PDF by printing its standalone HTML with a headless
+/// Chromium-based browser. Emits `export-progress` as it goes.
+#[tauri::command(async)]
+pub fn export_pdf(
+    path: String,
+    out_path: String,
+    options: Option<PdfExportOptions>,
+    app: AppHandle,
+    registry: tauri::State<'_, NoteIndexRegistry>,
+    settings: tauri::State<'_, Mutex<SettingsStore>>,
+) -> Result<(), String> {
+    const STEPS: usize = 3;
+    let options = options.unwrap_or_default();
+    let browser = find_browser().ok_or("No Chrome, Chromium or Edge installation found to print the PDF with")?;
+
+    emit_progress(&app, &out_path, "rendering", 0, STEPS);
+    let orientation = if options.landscape { " landscape" } else { "" };
+    let page_css = format!(
+        "@page {{ size: {}{}; margin: {}; }}\narticle {{ max-width: none; padding: 0; }}\n",
+        options.page_size, orientation, options.margin
+    );
+    let html = standalone_html(&path, &options.html, &page_css, &registry, &settings)?;
+    let mut page = tempfile::Builder::new()
+        .prefix(".readmark-")
+        .suffix(".html")
+        .tempfile()
+        .map_err(|e| format!("Failed to create temporary file: {}", e))?;
+    page.write_all(html.as_bytes())
+        .map_err(|e| format!("Failed to write temporary file: {}", e))?;
+
+    emit_progress(&app, &out_path, "printing", 1, STEPS);
+    let out = Path::new(&out_path);
+    create_parent(out)?;
+    let output = Command::new(&browser)
+        .arg("--headless")
+        .arg("--disable-gpu")
+        .arg("--no-pdf-header-footer")
+        .arg(format!("--print-to-pdf={}", out.display()))
+        .arg(page.path())
+        .output()
+        .map_err(|e| format!("Failed to run {}: {}", browser.display(), e))?;
+    if !output.status.success() || !out.is_file() {
+        return Err(format!(
+            "Failed to print PDF: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    emit_progress(&app, &out_path, "done", STEPS, STEPS);
+    Ok(())
+}
+
+/// Document formats produced through pandoc
+#[derive(Debug, Deserialize, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum PandocFormat {
+    Docx,
+    Odt,
+    Epub,
+    Rtf,
+}
+
+impl PandocFormat {
+    fn writer(self) -> &'static str {
+        match self {
+            PandocFormat::Docx => "docx",
+            PandocFormat::Odt => "odt",
+            PandocFormat::Epub => "epub3",
+            PandocFormat::Rtf => "rtf",
+        }
+    }
+}
+
+fn find_pandoc() -> Option<PathBuf> {
+    let local_install = env::var("LOCALAPPDATA")
+        .map(|dir| format!(r"{}\Pandoc\pandoc.exe", dir))
+        .unwrap_or_default();
+    find_program(
+        &["pandoc"],
+        &[
+            "/opt/homebrew/bin/pandoc",
+            "/usr/local/bin/pandoc",
+            "/usr/bin/pandoc",
+            r"C:\Program Files\Pandoc\pandoc.exe",
+            &local_install,
+        ],
+    )
+}
+
+/// Pandoc metadata from a note's frontmatter: every field is passed through,
+/// with a `title` filled in and `tags` doubling as `keywords`
+fn pandoc_metadata(content: &str, title: String) -> Map<String, Value> {
+    let mut metadata = frontmatter::fields(content);
+    if !metadata.contains_key("title") {
+        metadata.insert("title".to_string(), Value::String(title));
+    }
+    if !metadata.contains_key("keywords") {
+        let tags = tags::frontmatter_tags(&metadata);
+        if !tags.is_empty() {
+            metadata.insert("keywords".to_string(), Value::from(tags));
+        }
+    }
+    if !metadata.contains_key("date") {
+        if let Some(created) = metadata.get("created").cloned() {
+            metadata.insert("date".to_string(), created);
+        }
+    }
+    metadata
+}
+
+/// Convert a note to DOCX, ODT, EPUB or RTF with pandoc. Frontmatter becomes
+/// document metadata. Fails with a `tool_missing` error when pandoc isn't
+/// installed.
+#[tauri::command(async)]
+pub fn export_with_pandoc(
+    path: String,
+    format: PandocFormat,
+    out_path: String,
+    registry: tauri::State<'_, NoteIndexRegistry>,
+    settings: tauri::State<'_, Mutex<SettingsStore>>,
+) -> Result<(), CommandError> {
+    let pandoc = find_pandoc().ok_or_else(|| ToolError::ToolMissing {
+        tool: "pandoc".to_string(),
+        install_url: "https://pandoc.org/installing.html".to_string(),
+    })?;
+
+    let source = links::normalize_path(Path::new(&path));
+    let content = fs::read_to_string(&source).map_err(|e| format!("Failed to read file: {}", e))?;
+    let (root, note_extensions, names) = links::lookup_for(&source, &registry, &settings)?;
+    let resolver = Resolver {
+        root: &root,
+        note_extensions: &note_extensions,
+        names: &names,
+    };
+    // Pandoc reads our rendering rather than the markdown, which it would
+    // parse without wiki links and with different extensions
+    let exporter = NoteExporter {
+        resolver: &resolver,
+        source: &source,
+        wiki_links: WikiLinkStyle::Text,
+        image_src: &|path| Some(path.to_string_lossy().to_string()),
+    };
+    let body = exporter.render(&content);
+
+    let title = note_title(&source, &markdown::headings(&content));
+    let metadata = serde_yaml::to_string(&pandoc_metadata(&content, title))
+        .map_err(|e| format!("Failed to write metadata: {}", e))?;
+    let mut metadata_file = tempfile::Builder::new()
+        .prefix(".readmark-")
+        .suffix(".yaml")
+        .tempfile()
+        .map_err(|e| format!("Failed to create temporary file: {}", e))?;
+    metadata_file
+        .write_all(metadata.as_bytes())
+        .map_err(|e| format!("Failed to write temporary file: {}", e))?;
+
+    let out = Path::new(&out_path);
+    create_parent(out)?;
+    let mut child = Command::new(&pandoc)
+        .args(["--from", "html", "--to", format.writer(), "--standalone"])
+        .arg(format!("--metadata-file={}", metadata_file.path().display()))
+        .arg(format!("--resource-path={}", source.parent().unwrap_or(&root).display()))
+        .arg("--output")
+        .arg(out)
+        .stdin(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Failed to run pandoc: {}", e))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin
+            .write_all(body.as_bytes())
+            .map_err(|e| format!("Failed to send the note to pandoc: {}", e))?;
+    }
+    let output = child
+        .wait_with_output()
+        .map_err(|e| format!("Failed to run pandoc: {}", e))?;
+
+    if !output.status.success() {
+        return Err(ToolError::ToolFailed {
+            tool: "pandoc".to_string(),
+            status: output.status.code(),
+            stderr: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        }
+        .into());
+    }
+    Ok(())
+}
+
+#[derive(Debug, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct SiteExportOptions {
+    /// Site name for the index page and navigation; defaults to the vault's
+    /// folder name
+    pub title: Option<String>,
+    /// Extra CSS added to every page
+    pub css: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SiteExport {
+    pub pages: usize,
+    pub attachments: usize,
+}
+
+/// `path` as an `href` relative to the directory `from_dir`
+pub fn relative_href(from_dir: &Path, path: &Path) -> String {
+    crate::rename::relative_path(from_dir, path).replace(' ', "%20")
+}
+
+/// Render every note in the vault at `root` to a page under `out_dir`,
+/// mirroring the folder layout. Links between notes point at the pages,
+/// attachments are copied alongside, and an `index.html` lists every page
+/// unless the vault has its own index note. Emits `export-progress` per file.
+#[tauri::command(async)]
+pub fn export_site(
+    root: String,
+    out_dir: String,
+    options: Option<SiteExportOptions>,
+    app: AppHandle,
+    registry: tauri::State<'_, NoteIndexRegistry>,
+    settings: tauri::State<'_, Mutex<SettingsStore>>,
+) -> Result<SiteExport, String> {
+    site(&root, &out_dir, &options.unwrap_or_default(), &app, &registry, &settings, &Job::detached())
+}
+
+/// `export_site` as a job that can be cancelled. It reports the files
+/// written; the summary is the result of its `job-done` event.
+#[tauri::command]
+pub fn start_export_site(
+    root: String,
+    out_dir: String,
+    options: Option<SiteExportOptions>,
+    app: AppHandle,
+) -> Result<u64, String> {
+    let handle = app.clone();
+    jobs::spawn(&app, "export_site", move |job| {
+        let registry = handle.state::<NoteIndexRegistry>();
+        let settings = handle.state::<Mutex<SettingsStore>>();
+        site(&root, &out_dir, &options.unwrap_or_default(), &handle, &registry, &settings, job)
+    })
+}
+
+fn site(
+    root: &str,
+    out_dir: &str,
+    options: &SiteExportOptions,
+    app: &AppHandle,
+    registry: &NoteIndexRegistry,
+    settings: &Mutex<SettingsStore>,
+    job: &Job,
+) -> Result<SiteExport, String> {
+    let root = links::normalize_path(Path::new(root));
+    let out_dir = links::normalize_path(Path::new(out_dir));
+    let index = registry.for_vault(&root, settings)?;
+    let contents = index.contents()?;
+
+    // A site exported into the vault shouldn't be exported again next time
+    let included = |path: &&PathBuf| path.starts_with(&root) && !path.starts_with(&out_dir);
+    let mut notes: Vec<&PathBuf> = contents.notes.keys().filter(included).collect();
+    let mut attachments: Vec<&PathBuf> = contents.attachments.iter().filter(included).collect();
+    notes.sort();
+    attachments.sort();
+
+    let site_title = options.title.clone().unwrap_or_else(|| {
+        root.file_name()
+            .map_or_else(|| root.to_string_lossy().to_string(), |name| name.to_string_lossy().to_string())
+    });
+    let names = NameLookup::new(&contents);
+    let resolver = Resolver {
+        root: index.root(),
+        note_extensions: index.note_extensions(),
+        names: &names,
+    };
+
+    let total = notes.len() + attachments.len();
+    let mut pages: Vec<(PathBuf, String)> = Vec::new();
+    for (done, source) in notes.iter().enumerate() {
+        job.check()?;
+        job.progress(done, Some(total), Some(&source.to_string_lossy()));
+        emit_progress(app, &out_dir.to_string_lossy(), "rendering", done, total);
+        let content = fs::read_to_string(source).map_err(|e| format!("Failed to read {}: {}", source.display(), e))?;
+        let relative = source.strip_prefix(&root).unwrap_or(source).with_extension("html");
+        let page_path = out_dir.join(&relative);
+        let page_dir = page_path.parent().unwrap_or(&out_dir).to_path_buf();
+
+        // Attachments end up at the same place relative to the page
+        let note_dir = source.parent().unwrap_or(&root).to_path_buf();
+        let image_src = |image: &Path| -> Option<String> {
+            if image.starts_with(&root) && !image.starts_with(&out_dir) {
+                Some(relative_href(&note_dir, image))
+            } else {
+                data_url(image)
+            }
+        };
+        let exporter = NoteExporter {
+            resolver: &resolver,
+            source,
+            wiki_links: WikiLinkStyle::Anchor,
+            image_src: &image_src,
+        };
+
+        let nav = format!(
+            "<nav><a href=\"{}\">{}</a></nav>\n",
+            relative_href(&page_dir, &out_dir.join("index.html")),
+            escape_html(&site_title)
+        );
+        let title = note_title(source, &markdown::headings(&content));
+        let html = html_document(&title, &(nav + &exporter.render(&content)), options.css.as_deref());
+        write_output(&page_path, html.as_bytes())?;
+        pages.push((relative, title));
+    }
+
+    for (done, attachment) in attachments.iter().enumerate() {
+        job.check()?;
+        job.progress(notes.len() + done, Some(total), Some(&attachment.to_string_lossy()));
+        emit_progress(app, &out_dir.to_string_lossy(), "copying", notes.len() + done, total);
+        let target = out_dir.join(attachment.strip_prefix(&root).unwrap_or(attachment));
+        create_parent(&target)?;
+        crate::copy_preserving(attachment, &target)
+            .map_err(|e| format!("Failed to copy {}: {}", attachment.display(), e))?;
+    }
+
+    let has_index = pages.iter().any(|(page, _)| page == Path::new("index.html"));
+    if !has_index {
+        let mut list = String::new();
+        for (page, title) in &pages {
+            let folder = page.parent().map(|dir| dir.to_string_lossy().to_string()).unwrap_or_default();
+            let folder = if folder.is_empty() {
+                String::new()
+            } else {
+                format!(" <small>{}</small>", escape_html(&folder))
+            };
+            list.push_str(&format!(
+                "<li><a href=\"{}\">{}</a>{}</li>\n",
+                relative_href(&out_dir, &out_dir.join(page)),
+                escape_html(title),
+                folder
+            ));
+        }
+        let body = format!("<h1>{}</h1>\n<ul>\n{}</ul>\n", escape_html(&site_title), list);
+        let html = html_document(&site_title, &body, options.css.as_deref());
+        write_output(&out_dir.join("index.html"), html.as_bytes())?;
+    }
+
+    emit_progress(app, &out_dir.to_string_lossy(), "done", total, total);
+    job.progress(total, Some(total), None);
+    Ok(SiteExport {
+        pages: pages.len(),
+        attachments: attachments.len(),
+    })
+}
@@ -0,0 +1,104 @@
+use serde::Serialize;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+
+/// Most bytes `read_file_range` returns at once
+const MAX_RANGE_LEN: u64 = 16 * 1024 * 1024;
+
+/// Bytes read at a time when counting lines
+const COUNT_CHUNK: usize = 64 * 1024;
+
+#[derive(Debug, Serialize)]
+pub struct FileRange {
+    pub content: String,
+    /// Byte offset the content starts at. Moved past the rest of a character
+    /// the requested offset fell inside of.
+    pub offset: u64,
+    /// Byte offset just past the content, where the next range starts
+    pub end: u64,
+    /// Size of the whole file in bytes
+    pub size: u64,
+}
+
+fn is_continuation(byte: u8) -> bool {
+    byte & 0xC0 == 0x80
+}
+
+/// Length of the UTF-8 sequence `lead` starts
+fn sequence_len(lead: u8) -> usize {
+    match lead {
+        0xF0.. => 4,
+        0xE0.. => 3,
+        0xC0.. => 2,
+        _ => 1,
+    }
+}
+
+/// Where `bytes` stops holding whole characters: before a sequence that is
+/// cut off at the end
+fn whole_chars_end(bytes: &[u8]) -> usize {
+    let tail_start = bytes.len().saturating_sub(4);
+    let lead = (tail_start..bytes.len()).rev().find(|&i| !is_continuation(bytes[i]));
+    match lead {
+        Some(i) if i + sequence_len(bytes[i]) > bytes.len() => i,
+        _ => bytes.len(),
+    }
+}
+
+/// Read up to `len` bytes of a text file from byte `offset`, for viewing files
+/// too large for `read_text_file` a window at a time. The range is widened
+/// or narrowed to whole UTF-8 characters; invalid bytes come back as U+FFFD.
+#[tauri::command(async)]
+pub fn read_file_range(path: String, offset: u64, len: u64) -> Result<FileRange, String> {
+    let mut file = File::open(&path).map_err(|e| format!("Failed to open file: {}", e))?;
+    let size = file.metadata().map_err(|e| format!("Failed to read file: {}", e))?.len();
+    let offset = offset.min(size);
+    let len = len.min(MAX_RANGE_LEN).min(size - offset);
+
+    file.seek(SeekFrom::Start(offset)).map_err(|e| format!("Failed to read file: {}", e))?;
+    let mut bytes = Vec::with_capacity(len as usize);
+    file.by_ref()
+        .take(len)
+        .read_to_end(&mut bytes)
+        .map_err(|e| format!("Failed to read file: {}", e))?;
+
+    let start = if offset == 0 {
+        0
+    } else {
+        bytes.iter().take(3).take_while(|b| is_continuation(**b)).count()
+    };
+    let stop = if offset + bytes.len() as u64 >= size {
+        bytes.len()
+    } else {
+        start + whole_chars_end(&bytes[start..])
+    };
+
+    Ok(FileRange {
+        content: String::from_utf8_lossy(&bytes[start..stop]).into_owned(),
+        offset: offset + start as u64,
+        end: offset + stop as u64,
+        size,
+    })
+}
+
+/// Number of lines in a file, without loading it whole. A last line without
+/// a trailing newline counts; an empty file has none.
+#[tauri::command(async)]
+pub fn get_line_count(path: String) -> Result<u64, String> {
+    let mut file = File::open(&path).map_err(|e| format!("Failed to open file: {}", e))?;
+    let mut buffer = vec![0u8; COUNT_CHUNK];
+    let mut lines = 0u64;
+    let mut last = None;
+    loop {
+        let read = file.read(&mut buffer).map_err(|e| format!("Failed to read file: {}", e))?;
+        if read == 0 {
+            break;
+        }
+        lines += buffer[..read].iter().filter(|&&b| b == b'\n').count() as u64;
+        last = Some(buffer[read - 1]);
+    }
+    if last.is_some_and(|b| b != b'\n') {
+        lines += 1;
+    }
+    Ok(lines)
+}
@@ -0,0 +1,391 @@
+use pulldown_cmark::{Alignment, Event, Parser, Tag, TagEnd};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::ops::Range;
+use std::path::PathBuf;
+use std::sync::{Mutex, OnceLock};
+
+use crate::encoding;
+use crate::encryption;
+use crate::error::CommandError;
+use crate::markdown;
+use crate::metadata_cache::MetadataCache;
+use crate::note_index::NoteIndexRegistry;
+use crate::render::MarkdownSource;
+use crate::search::line_starts;
+use crate::settings::{self, SettingsStore};
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct FormatStyle {
+    /// Marker of bullet list items: `-`, `*` or `+`
+    pub bullet: char,
+    /// Pad table cells so the columns line up
+    pub align_tables: bool,
+    /// Drop spaces at line ends, except two-space line breaks
+    pub trim_trailing_whitespace: bool,
+    /// Blank lines kept in a row
+    pub max_blank_lines: usize,
+    /// Move `[label]: url` definitions to the end of the note
+    pub references_at_end: bool,
+}
+
+impl Default for FormatStyle {
+    fn default() -> Self {
+        FormatStyle {
+            bullet: '-',
+            align_tables: true,
+            trim_trailing_whitespace: true,
+            max_blank_lines: 1,
+            references_at_end: true,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct FormatResult {
+    pub content: String,
+    pub changed: bool,
+}
+
+/// End of the line containing `offset`, before the `\n`
+fn line_end(content: &str, offset: usize) -> usize {
+    content[offset..].find('\n').map_or(content.len(), |i| offset + i)
+}
+
+/// Whether `offset` starts a line, allowing up to three spaces of indent
+fn at_line_start(content: &str, offset: usize) -> bool {
+    let start = content[..offset].rfind('\n').map_or(0, |i| i + 1);
+    offset - start <= 3 && content[start..offset].chars().all(|c| c == ' ')
+}
+
+/// A heading written the ATX way: `#`s, one space, the text
+fn atx_heading(source: &str, level: u8) -> String {
+    let text = if source.trim_start().starts_with('#') {
+        let text = source.trim().trim_start_matches('#');
+        // A closing sequence needs a space before it, `# C#` keeps its `#`
+        let trimmed = text.trim_end_matches('#');
+        match trimmed.ends_with([' ', '\t']) || trimmed.is_empty() {
+            true => trimmed.trim().to_string(),
+            false => text.trim().to_string(),
+        }
+    } else {
+        // Setext: text lines and an underline of `=` or `-`
+        let lines: Vec<&str> = source.trim_end().lines().collect();
+        lines[..lines.len().saturating_sub(1)].iter().map(|line| line.trim()).collect::<Vec<_>>().join(" ")
+    };
+    match text.is_empty() {
+        true => "#".repeat(level as usize),
+        false => format!("{} {}", "#".repeat(level as usize), text),
+    }
+}
+
+/// Cells of a table row, split at unescaped pipes, which as in GFM includes
+/// pipes inside code spans
+fn table_cells(row: &str) -> Vec<String> {
+    let row = row.trim();
+    let row = row.strip_prefix('|').unwrap_or(row);
+    let mut cells = Vec::new();
+    let mut cell = String::new();
+    let mut chars = row.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                cell.push(c);
+                if let Some(next) = chars.next() {
+                    cell.push(next);
+                }
+            }
+            '|' => cells.push(std::mem::take(&mut cell)),
+            _ => cell.push(c),
+        }
+    }
+    if !cell.trim().is_empty() {
+        cells.push(cell);
+    }
+    cells.into_iter().map(|cell| cell.trim().to_string()).collect()
+}
+
+fn pad(text: &str, width: usize, alignment: Alignment) -> String {
+    let space = width.saturating_sub(text.chars().count());
+    let (left, right) = match alignment {
+        Alignment::Right => (space, 0),
+        Alignment::Center => (space / 2, space - space / 2),
+        Alignment::Left | Alignment::None => (0, space),
+    };
+    format!("{}{}{}", " ".repeat(left), text, " ".repeat(right))
+}
+
+/// The table `source` with its columns padded to line up
+pub fn align_table(source: &str, alignments: &[Alignment]) -> String {
+    let rows: Vec<Vec<String>> = source.lines().map(table_cells).collect();
+    let columns = rows.iter().map(Vec::len).max().unwrap_or(0);
+    let alignment = |i: usize| alignments.get(i).copied().unwrap_or(Alignment::None);
+    let widths: Vec<usize> = (0..columns)
+        .map(|i| {
+            let widest = rows
+                .iter()
+                .enumerate()
+                .filter(|(r, _)| *r != 1)
+                .filter_map(|(_, row)| row.get(i))
+                .map(|cell| cell.chars().count())
+                .max()
+                .unwrap_or(0);
+            widest.max(3)
+        })
+        .collect();
+
+    let mut lines = Vec::with_capacity(rows.len());
+    for (r, row) in rows.iter().enumerate() {
+        let cells: Vec<String> = if r == 1 {
+            (0..row.len())
+                .map(|i| {
+                    let width = widths[i];
+                    match alignment(i) {
+                        Alignment::Left => format!(":{}", "-".repeat(width - 1)),
+                        Alignment::Right => format!("{}:", "-".repeat(width - 1)),
+                        Alignment::Center => format!(":{}:", "-".repeat(width - 2)),
+                        Alignment::None => "-".repeat(width),
+                    }
+                })
+                .collect()
+        } else {
+            row.iter().enumerate().map(|(i, cell)| pad(cell, widths[i], alignment(i))).collect()
+        };
+        lines.push(format!("| {} |", cells.join(" | ")));
+    }
+    lines.join("\n")
+}
+
+/// Edits to headings, bullets and tables, found by parsing
+fn block_edits(content: &str, style: &FormatStyle) -> Vec<(Range<usize>, String)> {
+    let offset = markdown::body_start(content);
+    let mut edits = Vec::new();
+    // Whether each open list is a bullet list
+    let mut lists: Vec<bool> = Vec::new();
+    let mut table: Option<(usize, Vec<Alignment>)> = None;
+
+    for (event, range) in Parser::new_ext(&content[offset..], markdown::parser_options()).into_offset_iter() {
+        let range = offset + range.start..offset + range.end;
+        match event {
+            Event::Start(Tag::Heading { level, .. }) if at_line_start(content, range.start) => {
+                let source = &content[range.clone()];
+                let end = range.start + source.trim_end().len();
+                let heading = atx_heading(&content[range.start..end], level as u8);
+                if heading != content[range.start..end] {
+                    edits.push((range.start..end, heading));
+                }
+            }
+            Event::Start(Tag::List(first)) => lists.push(first.is_none()),
+            Event::End(TagEnd::List(_)) => {
+                lists.pop();
+            }
+            Event::Start(Tag::Item) if lists.last() == Some(&true) => {
+                let marker = content[range.start..].chars().next();
+                if matches!(marker, Some('-' | '*' | '+')) && marker != Some(style.bullet) {
+                    edits.push((range.start..range.start + 1, style.bullet.to_string()));
+                }
+            }
+            Event::Start(Tag::Table(alignments)) if style.align_tables && at_line_start(content, range.start) => {
+                table = Some((range.start, alignments));
+            }
+            Event::End(TagEnd::Table) => {
+                if let Some((start, alignments)) = table.take() {
+                    let end = line_end(content, range.end.saturating_sub(1).max(start));
+                    let source = &content[start..end];
+                    // Tables inside quotes or lists are left alone
+                    if source.lines().all(|line| line.trim_start().starts_with('|') || !line.starts_with(' ')) {
+                        let aligned = align_table(source, &alignments);
+                        if aligned != source {
+                            edits.push((start..end, aligned));
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    edits
+}
+
+/// Byte ranges of code blocks and HTML blocks, which are never reformatted
+fn verbatim_ranges(content: &str) -> Vec<Range<usize>> {
+    let offset = markdown::body_start(content);
+    // The frontmatter too
+    let mut ranges: Vec<Range<usize>> = Vec::new();
+    ranges.push(0..offset);
+    let mut html_start = None;
+    for (event, range) in Parser::new_ext(&content[offset..], markdown::parser_options()).into_offset_iter() {
+        match event {
+            Event::Start(Tag::CodeBlock(_)) => ranges.push(offset + range.start..offset + range.end),
+            Event::Start(Tag::HtmlBlock) => html_start = Some(offset + range.start),
+            Event::End(TagEnd::HtmlBlock) => {
+                if let Some(start) = html_start.take() {
+                    ranges.push(start..offset + range.end);
+                }
+            }
+            _ => {}
+        }
+    }
+    ranges
+}
+
+fn reference_regex() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| {
+        Regex::new(r#"^ {0,3}\[([^\]^][^\]]*)\]:[ \t]*(<[^>]*>|\S+)(?:[ \t]+("[^"]*"|'[^']*'|\([^)]*\)))?[ \t]*$"#)
+            .expect("valid reference regex")
+    })
+}
+
+/// `[label]: url "title"`
+fn normalize_reference(line: &str) -> Option<(String, String)> {
+    let captures = reference_regex().captures(line)?;
+    let label = captures[1].split_whitespace().collect::<Vec<_>>().join(" ");
+    let mut definition = format!("[{}]: {}", label, &captures[2]);
+    if let Some(title) = captures.get(3) {
+        let inner = &title.as_str()[1..title.as_str().len() - 1];
+        match inner.contains('"') {
+            true => definition.push_str(&format!(" {}", title.as_str())),
+            false => definition.push_str(&format!(" \"{}\"", inner)),
+        }
+    }
+    Some((label.to_lowercase(), definition))
+}
+
+/// Whether `line` can continue the paragraph before it, rather than being
+/// blank or starting another block, so that a two-space break before it
+/// means something
+fn continues_paragraph(line: &str) -> bool {
+    let line = line.trim_start();
+    let ordered = line
+        .split_once(['.', ')'])
+        .is_some_and(|(number, _)| !number.is_empty() && number.bytes().all(|b| b.is_ascii_digit()));
+    !line.is_empty() && !ordered && !line.starts_with(['-', '*', '+', '#', '>', '|'])
+}
+
+/// Whitespace, blank lines and reference definitions, line by line
+fn format_lines(content: &str, style: &FormatStyle) -> String {
+    let verbatim = verbatim_ranges(content);
+    let starts = line_starts(content);
+    let lines: Vec<&str> = content.split('\n').collect();
+    let is_verbatim = |i: usize| verbatim.iter().any(|range| range.contains(&starts[i]));
+
+    let mut out: Vec<String> = Vec::with_capacity(lines.len());
+    let mut references: Vec<String> = Vec::new();
+    let mut labels: HashSet<String> = HashSet::new();
+    let mut blank_run = 0;
+    // Definitions can't interrupt a paragraph, so one must follow a blank
+    // line or another definition
+    let mut can_define = true;
+    for (i, line) in lines.iter().enumerate() {
+        if is_verbatim(i) {
+            blank_run = 0;
+            can_define = false;
+            out.push(line.to_string());
+            continue;
+        }
+        if can_define {
+            if let Some((label, definition)) = normalize_reference(line) {
+                // Later definitions of a label are ignored by renderers
+                if labels.insert(label) {
+                    match style.references_at_end {
+                        true => references.push(definition),
+                        false => out.push(definition),
+                    }
+                }
+                continue;
+            }
+        }
+        can_define = line.trim().is_empty();
+
+        let mut line = line.to_string();
+        if style.trim_trailing_whitespace {
+            let trimmed = line.trim_end();
+            let continues = lines.get(i + 1).is_some_and(|next| continues_paragraph(next));
+            let hard_break = line.ends_with("  ") && !trimmed.is_empty() && continues;
+            line = match hard_break {
+                true => format!("{}  ", trimmed),
+                false => trimmed.to_string(),
+            };
+        }
+        if line.trim().is_empty() {
+            blank_run += 1;
+            if blank_run > style.max_blank_lines {
+                continue;
+            }
+        } else {
+            blank_run = 0;
+        }
+        out.push(line);
+    }
+
+    while out.last().is_some_and(|line| line.trim().is_empty()) {
+        out.pop();
+    }
+    if !references.is_empty() {
+        out.push(String::new());
+        out.extend(references);
+    }
+    if out.is_empty() {
+        return String::new();
+    }
+    format!("{}\n", out.join("\n"))
+}
+
+/// `content` formatted per `style`. Frontmatter, code blocks and HTML are
+/// left as they are.
+pub fn format(content: &str, style: &FormatStyle) -> String {
+    let content = content.replace("\r\n", "\n");
+    let mut formatted = content.clone();
+    let mut edits = block_edits(&content, style);
+    edits.sort_by_key(|(range, _)| std::cmp::Reverse(range.start));
+    for (range, text) in edits {
+        formatted.replace_range(range, &text);
+    }
+    format_lines(&formatted, style)
+}
+
+/// Format markdown the same way every time: ATX headings, one bullet
+/// marker, aligned tables, no trailing whitespace or runs of blank lines,
+/// and reference definitions normalized and gathered at the end. Formats
+/// the content given, or a note, which with `write` is saved back.
+#[tauri::command(async)]
+pub fn format_markdown(
+    content_or_path: MarkdownSource,
+    style: Option<FormatStyle>,
+    write: Option<bool>,
+    registry: tauri::State<'_, NoteIndexRegistry>,
+    settings: tauri::State<'_, Mutex<SettingsStore>>,
+    cache: tauri::State<'_, MetadataCache>,
+) -> Result<FormatResult, CommandError> {
+    let style = style.unwrap_or_default();
+    if !matches!(style.bullet, '-' | '*' | '+') {
+        return Err(format!("Not a bullet marker: {}", style.bullet).into());
+    }
+    let (content, path) = match content_or_path {
+        MarkdownSource::Content(content) => (content, None),
+        MarkdownSource::Path(path) => {
+            let path = PathBuf::from(path);
+            let bytes = fs::read(&path).map_err(|e| format!("Failed to read file: {}", e))?;
+            if encryption::is_encrypted(&bytes) {
+                return Err(format!("Can't format an encrypted note: {}", path.display()).into());
+            }
+            (encoding::decode(&bytes).content, Some((path, bytes)))
+        }
+    };
+
+    let formatted = format(&content, &style);
+    let changed = formatted != content;
+    if let (Some((path, previous)), Some(true), true) = (&path, write, changed) {
+        settings::check_writable(&settings, path)?;
+        let bytes = encoding::encode(&formatted, encoding::format_of(previous))?;
+        crate::write_note(path, Some(previous), &bytes, &registry, &settings, &cache)?;
+    }
+    Ok(FormatResult {
+        content: formatted,
+        changed,
+    })
+}
@@ -0,0 +1,282 @@
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use crate::encoding;
+use crate::error::CommandError;
+use crate::metadata_cache::MetadataCache;
+use crate::note_index::NoteIndexRegistry;
+use crate::settings::{self, SettingsStore};
+use crate::WriteResult;
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum FrontmatterFormat {
+    /// Delimited by `---`
+    Yaml,
+    /// Delimited by `+++`
+    Toml,
+}
+
+impl FrontmatterFormat {
+    fn fence(self) -> &'static str {
+        match self {
+            FrontmatterFormat::Yaml => "---",
+            FrontmatterFormat::Toml => "+++",
+        }
+    }
+}
+
+/// The frontmatter block at the start of a note
+pub struct Block<'a> {
+    pub format: FrontmatterFormat,
+    /// Text between the fences
+    pub raw: &'a str,
+    /// Byte offset of the first line after the closing fence
+    pub body_start: usize,
+}
+
+/// Locate the frontmatter block at the start of `content`, if there is one
+pub fn split(content: &str) -> Option<Block<'_>> {
+    let format = if content.starts_with("---") {
+        FrontmatterFormat::Yaml
+    } else if content.starts_with("+++") {
+        FrontmatterFormat::Toml
+    } else {
+        return None;
+    };
+    let fence = format.fence();
+
+    // The opening fence must be on a line of its own
+    let first_line_end = content.find('\n')?;
+    if content[..first_line_end].trim_end() != fence {
+        return None;
+    }
+
+    let raw_start = first_line_end + 1;
+    let mut pos = raw_start;
+    loop {
+        let line_end = content[pos..].find('\n').map(|i| pos + i);
+        let line = content[pos..line_end.unwrap_or(content.len())].trim_end();
+        // YAML also allows `...` as the document end marker
+        if line == fence || (format == FrontmatterFormat::Yaml && line == "...") {
+            return Some(Block {
+                format,
+                raw: &content[raw_start..pos],
+                body_start: line_end.map_or(content.len(), |end| end + 1),
+            });
+        }
+        pos = line_end? + 1;
+    }
+}
+
+/// Parse a frontmatter block into a JSON object
+pub fn parse(block: &Block) -> Result<Map<String, Value>, String> {
+    if block.raw.trim().is_empty() {
+        return Ok(Map::new());
+    }
+
+    let value = match block.format {
+        FrontmatterFormat::Yaml => {
+            serde_yaml::from_str(block.raw).map_err(|e| format!("Invalid YAML frontmatter: {}", e))?
+        }
+        FrontmatterFormat::Toml => {
+            let table: toml::Table =
+                toml::from_str(block.raw).map_err(|e| format!("Invalid TOML frontmatter: {}", e))?;
+            toml_to_json(toml::Value::Table(table))
+        }
+    };
+
+    match value {
+        Value::Object(map) => Ok(map),
+        Value::Null => Ok(Map::new()),
+        _ => Err("Frontmatter is not a set of key/value pairs".to_string()),
+    }
+}
+
+/// The frontmatter fields of `content`, or an empty map if it has none or
+/// they can't be parsed. For indexers that shouldn't fail on one bad note.
+pub fn fields(content: &str) -> Map<String, Value> {
+    split(content)
+        .and_then(|block| parse(&block).ok())
+        .unwrap_or_default()
+}
+
+fn toml_to_json(value: toml::Value) -> Value {
+    match value {
+        toml::Value::String(s) => Value::String(s),
+        toml::Value::Integer(i) => Value::from(i),
+        toml::Value::Float(f) => Value::from(f),
+        toml::Value::Boolean(b) => Value::Bool(b),
+        // Dates are exposed as their TOML text, e.g. "2024-05-01"
+        toml::Value::Datetime(d) => Value::String(d.to_string()),
+        toml::Value::Array(items) => Value::Array(items.into_iter().map(toml_to_json).collect()),
+        toml::Value::Table(table) => Value::Object(table.into_iter().map(|(k, v)| (k, toml_to_json(v))).collect()),
+    }
+}
+
+/// Inverse of `toml_to_json`. Strings are written back as dates only where
+/// `original`, the value they replace, was a date and they still are one, so
+/// a quoted `"2024-05-01"` stays a string. Nulls (which TOML can't represent)
+/// are dropped.
+fn json_to_toml(value: &Value, original: Option<&toml::Value>) -> Option<toml::Value> {
+    Some(match value {
+        Value::Null => return None,
+        Value::Bool(b) => toml::Value::Boolean(*b),
+        Value::Number(n) => match n.as_i64() {
+            Some(i) => toml::Value::Integer(i),
+            None => toml::Value::Float(n.as_f64()?),
+        },
+        Value::String(s) => match (original, s.parse::<toml::value::Datetime>()) {
+            (Some(toml::Value::Datetime(_)), Ok(date)) => toml::Value::Datetime(date),
+            _ => toml::Value::String(s.clone()),
+        },
+        Value::Array(items) => {
+            let original = |i: usize| original.and_then(toml::Value::as_array).and_then(|items| items.get(i));
+            toml::Value::Array(items.iter().enumerate().filter_map(|(i, v)| json_to_toml(v, original(i))).collect())
+        }
+        Value::Object(map) => {
+            let original = original.and_then(toml::Value::as_table);
+            toml::Value::Table(
+                map.iter()
+                    .filter_map(|(k, v)| {
+                        let value = json_to_toml(v, original.and_then(|table| table.get(k)))?;
+                        Some((k.clone(), value))
+                    })
+                    .collect(),
+            )
+        }
+    })
+}
+
+/// Apply a JSON merge patch (RFC 7386): objects merge recursively, `null`
+/// removes a key, anything else replaces the value
+pub fn merge_patch(target: &mut Value, patch: &Value) {
+    let Value::Object(patch) = patch else {
+        *target = patch.clone();
+        return;
+    };
+    if !target.is_object() {
+        *target = Value::Object(Map::new());
+    }
+    if let Value::Object(map) = target {
+        for (key, value) in patch {
+            if value.is_null() {
+                // Keeps the remaining keys in their original order
+                map.shift_remove(key);
+            } else {
+                merge_patch(map.entry(key.clone()).or_insert(Value::Null), value);
+            }
+        }
+    }
+}
+
+/// Serialize `fields` as a complete frontmatter block, fences included.
+/// TOML dates are kept where `original` has them.
+pub fn render(
+    format: FrontmatterFormat,
+    fields: &Map<String, Value>,
+    original: Option<&toml::Value>,
+) -> Result<String, String> {
+    let mut text = match format {
+        FrontmatterFormat::Yaml => serde_yaml::to_string(fields)
+            .map_err(|e| format!("Failed to serialize frontmatter: {}", e))?,
+        FrontmatterFormat::Toml => {
+            let table = match json_to_toml(&Value::Object(fields.clone()), original) {
+                Some(toml::Value::Table(table)) => table,
+                _ => toml::Table::new(),
+            };
+            toml::to_string(&table).map_err(|e| format!("Failed to serialize frontmatter: {}", e))?
+        }
+    };
+    if !text.ends_with('\n') {
+        text.push('\n');
+    }
+    let fence = format.fence();
+    Ok(format!("{}\n{}{}\n", fence, text, fence))
+}
+
+/// Replace the frontmatter of `content` with `fields`, leaving the body
+/// byte-for-byte intact. An empty `fields` removes the block entirely.
+pub fn replace(content: &str, fields: &Map<String, Value>) -> Result<String, String> {
+    let block = split(content);
+    let (format, body) = match &block {
+        Some(block) => (block.format, &content[block.body_start..]),
+        None => (FrontmatterFormat::Yaml, content),
+    };
+    if fields.is_empty() {
+        return Ok(body.to_string());
+    }
+
+    // What the block was, for the TOML dates in it
+    let original = block
+        .as_ref()
+        .filter(|block| block.format == FrontmatterFormat::Toml)
+        .and_then(|block| toml::from_str::<toml::Table>(block.raw).ok())
+        .map(toml::Value::Table);
+    let mut rendered = render(format, fields, original.as_ref())?;
+    if content.starts_with(&format!("{}\r\n", format.fence())) {
+        rendered = rendered.replace('\n', "\r\n");
+    }
+    Ok(rendered + body)
+}
+
+#[derive(Debug, Serialize)]
+pub struct Frontmatter {
+    /// `None` when the note has no frontmatter
+    pub format: Option<FrontmatterFormat>,
+    /// The parsed fields; an empty object when there is no frontmatter
+    pub data: Map<String, Value>,
+    /// Byte offset where the body starts
+    pub body_offset: usize,
+}
+
+/// Read the YAML or TOML frontmatter of a note as JSON
+#[tauri::command(async)]
+pub fn read_frontmatter(path: String) -> Result<Frontmatter, String> {
+    let content = fs::read_to_string(&path).map_err(|e| format!("Failed to read file: {}", e))?;
+    match split(&content) {
+        Some(block) => Ok(Frontmatter {
+            format: Some(block.format),
+            data: parse(&block)?,
+            body_offset: block.body_start,
+        }),
+        None => Ok(Frontmatter {
+            format: None,
+            data: Map::new(),
+            body_offset: 0,
+        }),
+    }
+}
+
+/// Apply a JSON merge patch to a note's frontmatter and save it.
+///
+/// Key order is preserved, new keys are appended, and the body is left
+/// untouched. Notes without frontmatter get a YAML block. Takes the same
+/// `expected_mtime` / `expected_hash` conflict guard as `write_text_file`.
+#[tauri::command(async)]
+pub fn update_frontmatter(
+    path: String,
+    patch: Value,
+    expected_mtime: Option<u64>,
+    expected_hash: Option<String>,
+    registry: tauri::State<'_, NoteIndexRegistry>,
+    settings: tauri::State<'_, Mutex<SettingsStore>>,
+    cache: tauri::State<'_, MetadataCache>,
+) -> Result<WriteResult, CommandError> {
+    let path_buf = PathBuf::from(&path);
+    settings::check_writable(&settings, &path_buf)?;
+    crate::check_for_conflict(&path_buf, expected_mtime, expected_hash.as_deref())?;
+
+    let (previous, content) = crate::read_note(&path_buf)?;
+    let mut data = Value::Object(split(&content).map(|block| parse(&block)).transpose()?.unwrap_or_default());
+    merge_patch(&mut data, &patch);
+    let Value::Object(fields) = data else {
+        return Err("Frontmatter patch must be an object".to_string().into());
+    };
+
+    let updated = encoding::encode(&replace(&content, &fields)?, encoding::format_of(&previous))?;
+    Ok(crate::write_note(&path_buf, Some(&previous), &updated, &registry, &settings, &cache)?)
+}
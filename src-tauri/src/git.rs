@@ -0,0 +1,511 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter};
+
+use crate::diff::{self, TextDiff};
+use crate::error::{CommandError, ToolError};
+use crate::export::find_program;
+use crate::settings::{self, SettingsStore};
+
+/// Lines of context in `git_diff` hunks
+const DIFF_CONTEXT: usize = 3;
+
+/// Commits listed by `git_log` if not overridden
+const DEFAULT_LOG_LIMIT: usize = 100;
+
+fn find_git() -> Option<PathBuf> {
+    find_program(
+        &["git"],
+        &[
+            "/usr/bin/git",
+            "/usr/local/bin/git",
+            "/opt/homebrew/bin/git",
+            r"C:\Program Files\Git\cmd\git.exe",
+        ],
+    )
+}
+
+/// Run git in `dir` and return its stdout
+pub fn git(dir: &Path, args: &[&str]) -> Result<String, CommandError> {
+    let git = find_git().ok_or_else(|| ToolError::ToolMissing {
+        tool: "git".to_string(),
+        install_url: "https://git-scm.com/downloads".to_string(),
+    })?;
+    let output = Command::new(git)
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        // Never wait on a credential prompt nobody can see
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .env("LC_ALL", "C")
+        .output()
+        .map_err(|e| format!("Failed to run git: {}", e))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+        if stderr.contains("not a git repository") {
+            return Err(format!("Not in a git repository: {}", dir.display()).into());
+        }
+        return Err(ToolError::ToolFailed {
+            tool: "git".to_string(),
+            status: output.status.code(),
+            stderr,
+        }
+        .into());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// Top-level directory of the repository containing `dir`
+pub fn repo_root(dir: &Path) -> Result<PathBuf, CommandError> {
+    Ok(PathBuf::from(git(dir, &["rev-parse", "--show-toplevel"])?.trim_end()))
+}
+
+/// Directory to run git in for `path`, and `path` as a pathspec from there
+fn location(path: &Path) -> (PathBuf, String) {
+    if path.is_dir() {
+        return (path.to_path_buf(), ".".to_string());
+    }
+    let dir = path.parent().map(Path::to_path_buf).unwrap_or_default();
+    let name = path.file_name().unwrap_or_default().to_string_lossy().to_string();
+    (dir, name)
+}
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum FileState {
+    Unmodified,
+    Modified,
+    Added,
+    Deleted,
+    Renamed,
+    Copied,
+    TypeChanged,
+    Untracked,
+    Conflicted,
+}
+
+impl FileState {
+    /// State for one column of a `git status` XY code
+    fn from_code(code: u8) -> Self {
+        match code {
+            b'M' => FileState::Modified,
+            b'A' => FileState::Added,
+            b'D' => FileState::Deleted,
+            b'R' => FileState::Renamed,
+            b'C' => FileState::Copied,
+            b'T' => FileState::TypeChanged,
+            _ => FileState::Unmodified,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct GitFileStatus {
+    pub path: String,
+    /// Previous path, for renames and copies
+    pub from: Option<String>,
+    /// Change staged in the index
+    pub index: FileState,
+    /// Change in the working tree that isn't staged
+    pub worktree: FileState,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct GitStatus {
+    /// Checked-out branch; `None` for a detached HEAD
+    pub branch: Option<String>,
+    pub upstream: Option<String>,
+    /// Commits not on the upstream yet, and upstream commits not pulled
+    pub ahead: usize,
+    pub behind: usize,
+    /// Changed files under the vault root
+    pub files: Vec<GitFileStatus>,
+}
+
+/// Parse `git status --porcelain=v2 --branch -z` output, with paths made
+/// absolute against `repo`
+fn parse_status(output: &str, repo: &Path) -> GitStatus {
+    let mut status = GitStatus::default();
+    let absolute = |path: &str| repo.join(path).to_string_lossy().to_string();
+    let mut records = output.split('\0');
+    while let Some(record) = records.next() {
+        if let Some(header) = record.strip_prefix("# ") {
+            let (key, value) = header.split_once(' ').unwrap_or((header, ""));
+            match key {
+                "branch.head" if value != "(detached)" => status.branch = Some(value.to_string()),
+                "branch.upstream" => status.upstream = Some(value.to_string()),
+                "branch.ab" => {
+                    for count in value.split(' ') {
+                        if let Some(ahead) = count.strip_prefix('+') {
+                            status.ahead = ahead.parse().unwrap_or(0);
+                        } else if let Some(behind) = count.strip_prefix('-') {
+                            status.behind = behind.parse().unwrap_or(0);
+                        }
+                    }
+                }
+                _ => {}
+            }
+            continue;
+        }
+
+        // Changed entries have a fixed number of fields before the path,
+        // which may itself contain spaces
+        let fields = match record.as_bytes().first() {
+            Some(b'1') => 9,
+            Some(b'2') => 10,
+            Some(b'u') => 11,
+            Some(b'?') => {
+                status.files.push(GitFileStatus {
+                    path: absolute(&record[2..]),
+                    from: None,
+                    index: FileState::Unmodified,
+                    worktree: FileState::Untracked,
+                });
+                continue;
+            }
+            _ => continue,
+        };
+        let parts: Vec<&str> = record.splitn(fields, ' ').collect();
+        let (Some(xy), Some(path)) = (parts.get(1).map(|xy| xy.as_bytes()), parts.last()) else {
+            continue;
+        };
+        let (index, worktree) = if record.starts_with('u') {
+            (FileState::Conflicted, FileState::Conflicted)
+        } else {
+            (FileState::from_code(xy[0]), FileState::from_code(xy[1]))
+        };
+        // Renames and copies are followed by the original path
+        let from = record.starts_with('2').then(|| records.next().map(absolute)).flatten();
+        status.files.push(GitFileStatus {
+            path: absolute(path),
+            from,
+            index,
+            worktree,
+        });
+    }
+    status
+}
+
+/// Branch, upstream and changed files of the git repository the vault at
+/// `root` is in
+#[tauri::command(async)]
+pub fn git_status(root: String) -> Result<GitStatus, CommandError> {
+    let root = PathBuf::from(&root);
+    let repo = repo_root(&root)?;
+    let output = git(
+        &root,
+        &["status", "--porcelain=v2", "--branch", "-z", "--untracked-files=all", "--", "."],
+    )?;
+    Ok(parse_status(&output, &repo))
+}
+
+#[derive(Debug, Serialize)]
+pub struct GitCommit {
+    pub hash: String,
+    pub short_hash: String,
+    pub author: String,
+    pub email: String,
+    /// Author date, in milliseconds since the Unix epoch
+    pub timestamp: u64,
+    pub subject: String,
+}
+
+/// Commits that touched a note (following renames) or anything under a
+/// folder, newest first
+#[tauri::command(async)]
+pub fn git_log(path: String, limit: Option<usize>) -> Result<Vec<GitCommit>, CommandError> {
+    let path = PathBuf::from(&path);
+    let (dir, pathspec) = location(&path);
+    let limit = format!("--max-count={}", limit.unwrap_or(DEFAULT_LOG_LIMIT));
+    // Fields separated by US, records by RS, as neither appears in them
+    let mut args = vec!["log", &limit, "--format=%H%x1f%h%x1f%an%x1f%ae%x1f%at%x1f%s%x1e"];
+    if path.is_file() {
+        args.push("--follow");
+    }
+    args.extend(["--", &pathspec]);
+
+    let output = git(&dir, &args)?;
+    Ok(output
+        .split('\x1e')
+        .filter_map(|record| {
+            let fields: Vec<&str> = record.trim_start_matches('\n').split('\x1f').collect();
+            let [hash, short_hash, author, email, timestamp, subject] = fields[..] else {
+                return None;
+            };
+            Some(GitCommit {
+                hash: hash.to_string(),
+                short_hash: short_hash.to_string(),
+                author: author.to_string(),
+                email: email.to_string(),
+                timestamp: timestamp.parse::<u64>().unwrap_or(0) * 1000,
+                subject: subject.to_string(),
+            })
+        })
+        .collect())
+}
+
+/// Line diff of a note between a commit (`HEAD` if not given) and the
+/// working copy. Notes the commit doesn't have diff against an empty text.
+#[tauri::command(async)]
+pub fn git_diff(path: String, rev: Option<String>) -> Result<TextDiff, CommandError> {
+    let path = PathBuf::from(&path);
+    let (dir, name) = location(&path);
+    let rev = rev.unwrap_or_else(|| "HEAD".to_string());
+    if rev.starts_with('-') || rev.contains(':') {
+        return Err(format!("Invalid revision: {}", rev).into());
+    }
+    // Fail early, and with the usual message, outside a repository
+    repo_root(&dir)?;
+
+    let old = match git(&dir, &["show", &format!("{}:./{}", rev, name)]) {
+        Ok(old) => old,
+        Err(CommandError::Tool(ToolError::ToolFailed { .. })) => String::new(),
+        Err(e) => return Err(e),
+    };
+    let new = match fs::read_to_string(&path) {
+        Ok(new) => new,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(format!("Failed to read file: {}", e).into()),
+    };
+    Ok(diff::diff(&old, &new, DIFF_CONTEXT))
+}
+
+/// Steps of `git_sync` reported in `git-sync-progress` events
+const SYNC_STEPS: usize = 3;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SyncProgress {
+    pub root: String,
+    /// `fetching`, `integrating`, `pushing` or `done`
+    pub stage: String,
+    pub completed: usize,
+    pub total: usize,
+}
+
+fn emit_sync_progress(app: &AppHandle, root: &Path, stage: &str, completed: usize) {
+    let _ = app.emit(
+        "git-sync-progress",
+        SyncProgress {
+            root: root.to_string_lossy().to_string(),
+            stage: stage.to_string(),
+            completed,
+            total: SYNC_STEPS,
+        },
+    );
+}
+
+#[derive(Debug, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct SyncOptions {
+    /// Merge the upstream changes instead of rebasing local commits onto
+    /// them
+    pub merge: bool,
+}
+
+/// A file both sides changed, left with conflict markers in the working copy
+#[derive(Debug, Serialize)]
+pub struct GitConflict {
+    pub path: String,
+    /// The file as of the common ancestor; `None` if it didn't exist yet
+    pub base: Option<String>,
+    /// This device's version; `None` if it was deleted here
+    pub local: Option<String>,
+    /// The remote's version; `None` if it was deleted there
+    pub remote: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct GitSyncResult {
+    /// Commits brought in from the remote
+    pub pulled: usize,
+    /// Commits pushed to the remote
+    pub pushed: usize,
+    /// Files to resolve before `git_sync_continue`; the sync stopped short
+    /// of pushing when this isn't empty
+    pub conflicts: Vec<GitConflict>,
+}
+
+/// A rebase or merge stopped part-way in the repository at `repo`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Integration {
+    Rebase,
+    Merge,
+}
+
+fn integration_in_progress(repo: &Path) -> Result<Option<Integration>, CommandError> {
+    let git_dir = PathBuf::from(git(repo, &["rev-parse", "--absolute-git-dir"])?.trim_end());
+    Ok(if git_dir.join("rebase-merge").exists() || git_dir.join("rebase-apply").exists() {
+        Some(Integration::Rebase)
+    } else if git_dir.join("MERGE_HEAD").exists() {
+        Some(Integration::Merge)
+    } else {
+        None
+    })
+}
+
+/// Commits on HEAD but not its upstream, and the other way round
+fn ahead_behind(dir: &Path) -> Result<(usize, usize), CommandError> {
+    let counts = git(dir, &["rev-list", "--left-right", "--count", "HEAD...@{upstream}"])?;
+    let mut counts = counts.split_whitespace().map(|count| count.parse().unwrap_or(0));
+    Ok((counts.next().unwrap_or(0), counts.next().unwrap_or(0)))
+}
+
+/// Contents of `path` at index `stage` (1 base, 2 ours, 3 theirs)
+fn staged_content(repo: &Path, stage: u8, path: &str) -> Option<String> {
+    git(repo, &["show", &format!(":{}:{}", stage, path)]).ok()
+}
+
+/// The conflicted files of a stopped rebase or merge. During a rebase
+/// "ours" is the upstream the local commits are replayed onto.
+fn conflicts(repo: &Path, integration: Integration) -> Result<Vec<GitConflict>, CommandError> {
+    let (local_stage, remote_stage) = match integration {
+        Integration::Rebase => (3, 2),
+        Integration::Merge => (2, 3),
+    };
+    let output = git(repo, &["diff", "--name-only", "--diff-filter=U", "-z"])?;
+    Ok(output
+        .split('\0')
+        .filter(|path| !path.is_empty())
+        .map(|path| GitConflict {
+            path: repo.join(path).to_string_lossy().to_string(),
+            base: staged_content(repo, 1, path),
+            local: staged_content(repo, local_stage, path),
+            remote: staged_content(repo, remote_stage, path),
+        })
+        .collect())
+}
+
+/// Push what the upstream doesn't have yet, once any rebase or merge has
+/// completed, or report the conflicts holding it up
+fn finish_sync(app: &AppHandle, root: &Path, repo: &Path, pulled: usize) -> Result<GitSyncResult, CommandError> {
+    if let Some(integration) = integration_in_progress(repo)? {
+        let conflicts = conflicts(repo, integration)?;
+        if !conflicts.is_empty() {
+            return Ok(GitSyncResult {
+                pulled,
+                pushed: 0,
+                conflicts,
+            });
+        }
+    }
+
+    emit_sync_progress(app, root, "pushing", 2);
+    let (ahead, _) = ahead_behind(repo)?;
+    if ahead > 0 {
+        git(repo, &["push", "--quiet"])?;
+    }
+    emit_sync_progress(app, root, "done", SYNC_STEPS);
+    Ok(GitSyncResult {
+        pulled,
+        pushed: ahead,
+        conflicts: Vec::new(),
+    })
+}
+
+/// Sync the repository the vault at `root` is in with its upstream: fetch,
+/// rebase (or merge) onto the remote changes and push. Uncommitted changes
+/// are stashed around the rebase. Conflicts stop the sync and are returned
+/// for `git_sync_continue` or `git_sync_abort`.
+#[tauri::command(async)]
+pub fn git_sync(
+    root: String,
+    options: Option<SyncOptions>,
+    app: AppHandle,
+    settings: tauri::State<'_, Mutex<SettingsStore>>,
+) -> Result<GitSyncResult, CommandError> {
+    let options = options.unwrap_or_default();
+    let root = PathBuf::from(&root);
+    settings::check_writable(&settings, &root)?;
+    let repo = repo_root(&root)?;
+    if integration_in_progress(&repo)?.is_some() {
+        return Err("A rebase or merge is already in progress".to_string().into());
+    }
+    git(&repo, &["rev-parse", "--abbrev-ref", "--symbolic-full-name", "@{upstream}"])
+        .map_err(|_| CommandError::from("The current branch has no upstream to sync with".to_string()))?;
+
+    emit_sync_progress(&app, &root, "fetching", 0);
+    git(&repo, &["fetch", "--quiet", "--prune"])?;
+
+    emit_sync_progress(&app, &root, "integrating", 1);
+    let (_, behind) = ahead_behind(&repo)?;
+    if behind > 0 {
+        let integrate: &[&str] = if options.merge {
+            &["merge", "--autostash", "--no-edit", "@{upstream}"]
+        } else {
+            &["rebase", "--autostash", "@{upstream}"]
+        };
+        match git(&repo, integrate) {
+            Ok(_) => {}
+            // A stopped rebase or merge is reported through its conflicts
+            Err(CommandError::Tool(ToolError::ToolFailed { .. })) if integration_in_progress(&repo)?.is_some() => {}
+            Err(e) => return Err(e),
+        }
+    }
+    finish_sync(&app, &root, &repo, behind)
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ResolvedFile {
+    pub path: String,
+    /// The merged contents to keep
+    pub content: String,
+}
+
+/// Carry on with a sync that stopped on conflicts, with the files resolved
+/// to `resolved`. Returns the next conflicts if the rebase stops again.
+#[tauri::command(async)]
+pub fn git_sync_continue(
+    root: String,
+    resolved: Vec<ResolvedFile>,
+    app: AppHandle,
+    settings: tauri::State<'_, Mutex<SettingsStore>>,
+) -> Result<GitSyncResult, CommandError> {
+    let root = PathBuf::from(&root);
+    settings::check_writable(&settings, &root)?;
+    for file in &resolved {
+        settings::check_writable(&settings, Path::new(&file.path))?;
+    }
+    let repo = repo_root(&root)?;
+    let Some(integration) = integration_in_progress(&repo)? else {
+        return Err("No rebase or merge is in progress".to_string().into());
+    };
+
+    for file in &resolved {
+        let path = Path::new(&file.path);
+        crate::write_atomic(path, file.content.as_bytes()).map_err(|e| format!("Failed to write file: {}", e))?;
+        git(&repo, &["add", "--", &path.to_string_lossy()])?;
+    }
+    if !conflicts(&repo, integration)?.is_empty() {
+        return finish_sync(&app, &root, &repo, 0);
+    }
+
+    emit_sync_progress(&app, &root, "integrating", 1);
+    // No editor to confirm the commit messages in
+    let next = match integration {
+        Integration::Rebase => git(&repo, &["-c", "core.editor=true", "rebase", "--continue"]),
+        Integration::Merge => git(&repo, &["commit", "--no-edit", "--quiet"]),
+    };
+    match next {
+        Ok(_) => {}
+        Err(CommandError::Tool(ToolError::ToolFailed { .. })) if integration_in_progress(&repo)?.is_some() => {}
+        Err(e) => return Err(e),
+    }
+    finish_sync(&app, &root, &repo, 0)
+}
+
+/// Give up on a sync that stopped on conflicts, putting the repository back
+/// as it was before
+#[tauri::command(async)]
+pub fn git_sync_abort(root: String) -> Result<(), CommandError> {
+    let repo = repo_root(Path::new(&root))?;
+    match integration_in_progress(&repo)? {
+        Some(Integration::Rebase) => git(&repo, &["rebase", "--abort"])?,
+        Some(Integration::Merge) => git(&repo, &["merge", "--abort"])?,
+        None => return Err("No rebase or merge is in progress".to_string().into()),
+    };
+    Ok(())
+}
@@ -0,0 +1,166 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use crate::links::{normalize_path, NameLookup, Resolver};
+use crate::note_index::NoteIndexRegistry;
+use crate::settings::SettingsStore;
+
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct GraphOptions {
+    /// Add a node per tag, connected to the notes that use it
+    pub include_tags: bool,
+    /// Keep notes without any edges (default true)
+    pub include_orphans: bool,
+    /// Only include nodes within `depth` edges of this note
+    pub focus: Option<String>,
+    /// Maximum distance from `focus` (default 1)
+    pub depth: Option<usize>,
+}
+
+impl Default for GraphOptions {
+    fn default() -> Self {
+        GraphOptions {
+            include_tags: false,
+            include_orphans: true,
+            focus: None,
+            depth: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum NodeKind {
+    Note,
+    Tag,
+}
+
+#[derive(Debug, Serialize)]
+pub struct GraphNode {
+    /// The note's path, or `#tag` for tags
+    pub id: String,
+    pub kind: NodeKind,
+    pub label: String,
+    /// Number of edges to or from the node
+    pub degree: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct GraphEdge {
+    pub source: String,
+    pub target: String,
+    /// Number of links from `source` to `target`; 1 for tag edges
+    pub weight: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Graph {
+    pub nodes: Vec<GraphNode>,
+    pub edges: Vec<GraphEdge>,
+}
+
+/// Nodes and edges for the graph view of the vault at `root`: one node per
+/// note, edges for the links between them, and optionally tags
+#[tauri::command(async)]
+pub fn get_graph(
+    root: String,
+    options: Option<GraphOptions>,
+    registry: tauri::State<'_, NoteIndexRegistry>,
+    settings: tauri::State<'_, Mutex<SettingsStore>>,
+) -> Result<Graph, String> {
+    let options = options.unwrap_or_default();
+    let root = PathBuf::from(&root);
+    let index = registry.for_vault(&root, &settings)?;
+    let contents = index.contents()?;
+    let names = NameLookup::new(&contents);
+    let resolver = Resolver {
+        root: index.root(),
+        note_extensions: index.note_extensions(),
+        names: &names,
+    };
+
+    // Node id to (kind, label); a BTreeMap keeps the output stable
+    let mut nodes: BTreeMap<String, (NodeKind, String)> = BTreeMap::new();
+    let mut edges: BTreeMap<(String, String), usize> = BTreeMap::new();
+    for (path, note) in contents.notes.iter().filter(|(path, _)| path.starts_with(&root)) {
+        let id = path.to_string_lossy().to_string();
+        let label = path.file_stem().map_or_else(|| id.clone(), |stem| stem.to_string_lossy().to_string());
+        nodes.insert(id.clone(), (NodeKind::Note, label));
+
+        for link in &note.links {
+            let Some(target) = resolver.resolve(path, link) else {
+                continue;
+            };
+            if target == *path || !target.starts_with(&root) || !contents.notes.contains_key(&target) {
+                continue;
+            }
+            *edges.entry((id.clone(), target.to_string_lossy().to_string())).or_insert(0) += 1;
+        }
+
+        if options.include_tags {
+            for tag in &note.tags {
+                let tag_id = format!("#{}", tag.to_lowercase());
+                nodes.entry(tag_id.clone()).or_insert((NodeKind::Tag, tag.clone()));
+                edges.insert((id.clone(), tag_id), 1);
+            }
+        }
+    }
+
+    // Undirected adjacency, for degrees and the distance from the focus
+    let mut neighbours: HashMap<&str, Vec<&str>> = HashMap::new();
+    for (source, target) in edges.keys() {
+        neighbours.entry(source).or_default().push(target);
+        neighbours.entry(target).or_default().push(source);
+    }
+
+    let keep: Option<HashSet<String>> = match &options.focus {
+        Some(focus) => {
+            let focus = normalize_path(&PathBuf::from(focus)).to_string_lossy().to_string();
+            if !nodes.contains_key(&focus) {
+                return Err(format!("Note is not in the vault: {}", focus));
+            }
+            let depth = options.depth.unwrap_or(1);
+            let mut seen = HashSet::from([focus.clone()]);
+            let mut queue = VecDeque::from([(focus, 0)]);
+            while let Some((id, distance)) = queue.pop_front() {
+                if distance == depth {
+                    continue;
+                }
+                for next in neighbours.get(id.as_str()).into_iter().flatten() {
+                    if seen.insert(next.to_string()) {
+                        queue.push_back((next.to_string(), distance + 1));
+                    }
+                }
+            }
+            Some(seen)
+        }
+        None => None,
+    };
+    let kept = |id: &str| keep.as_ref().is_none_or(|keep| keep.contains(id));
+
+    let edges: Vec<GraphEdge> = edges
+        .into_iter()
+        .filter(|((source, target), _)| kept(source) && kept(target))
+        .map(|((source, target), weight)| GraphEdge { source, target, weight })
+        .collect();
+    let mut degrees: HashMap<&str, usize> = HashMap::new();
+    for edge in &edges {
+        *degrees.entry(&edge.source).or_insert(0) += 1;
+        *degrees.entry(&edge.target).or_insert(0) += 1;
+    }
+
+    let nodes = nodes
+        .into_iter()
+        .filter(|(id, _)| kept(id))
+        .map(|(id, (kind, label))| {
+            let degree = degrees.get(id.as_str()).copied().unwrap_or(0);
+            GraphNode { id, kind, label, degree }
+        })
+        .filter(|node| options.include_orphans || node.degree > 0)
+        .collect();
+
+    Ok(Graph { nodes, edges })
+}
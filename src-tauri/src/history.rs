@@ -0,0 +1,220 @@
+use serde::Serialize;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::encryption::{self, EncryptionKeys};
+use crate::error::CommandError;
+use crate::note_index::NoteIndexRegistry;
+use crate::rename;
+use crate::settings::{self, SettingsStore};
+use crate::WriteResult;
+
+/// Extension of snapshot files, which are named by their id
+const SNAPSHOT_EXTENSION: &str = "snap";
+
+const DAY_MILLIS: u64 = 24 * 60 * 60 * 1000;
+
+#[derive(Debug, Serialize)]
+pub struct Version {
+    pub id: String,
+    /// When the snapshot was taken, in milliseconds since the Unix epoch
+    pub timestamp: u64,
+    /// Size in bytes
+    pub size: u64,
+}
+
+fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+/// Folder holding the snapshots of `path`: `.readmark/history/` in the vault
+/// root, followed by the file's path within the vault
+fn history_dir(path: &Path, registry: &NoteIndexRegistry) -> PathBuf {
+    let root = registry.root_for(path);
+    root.join(crate::DATA_DIR)
+        .join("history")
+        .join(rename::relative_path(&root, path))
+}
+
+/// Snapshots of the file with history in `dir`, oldest first. Ids are the
+/// snapshot times in milliseconds.
+fn snapshots(dir: &Path) -> Vec<(u64, PathBuf)> {
+    let mut snapshots: Vec<(u64, PathBuf)> = fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|e| e == SNAPSHOT_EXTENSION))
+        .filter_map(|path| Some((path.file_stem()?.to_str()?.parse().ok()?, path)))
+        .collect();
+    snapshots.sort();
+    snapshots
+}
+
+/// Store `content` as the newest version of the file at `path`, unless it
+/// already is, then drop snapshots per the retention settings
+fn snapshot(
+    path: &Path,
+    content: &[u8],
+    registry: &NoteIndexRegistry,
+    settings: &Mutex<SettingsStore>,
+) -> Result<(), String> {
+    let retention = {
+        let store = settings.lock().map_err(|e| format!("Lock error: {}", e))?;
+        store.settings().history.clone()
+    };
+    if !retention.enabled {
+        return Ok(());
+    }
+
+    let dir = history_dir(path, registry);
+    let mut existing = snapshots(&dir);
+    if let Some((_, latest)) = existing.last() {
+        if fs::read(latest).is_ok_and(|latest| latest == content) {
+            return Ok(());
+        }
+    }
+
+    fs::create_dir_all(&dir).map_err(|e| format!("Failed to create history directory: {}", e))?;
+    // Ids stay increasing even for writes within the same millisecond
+    let now = now_millis();
+    let id = existing.last().map_or(now, |(latest, _)| now.max(latest + 1));
+    let snapshot_path = dir.join(format!("{}.{}", id, SNAPSHOT_EXTENSION));
+    crate::write_atomic(&snapshot_path, content).map_err(|e| format!("Failed to write snapshot: {}", e))?;
+    existing.push((id, snapshot_path));
+
+    // The newest snapshot is kept whatever its age
+    let cutoff = now.saturating_sub(retention.max_age_days.saturating_mul(DAY_MILLIS));
+    let kept_by_count = match retention.max_versions {
+        0 => existing.len(),
+        max => max,
+    };
+    let count = existing.len();
+    for (index, (id, path)) in existing.iter().enumerate() {
+        let newest = index + 1 == count;
+        let too_old = retention.max_age_days > 0 && *id < cutoff;
+        let too_many = count - index > kept_by_count;
+        if !newest && (too_old || too_many) {
+            fs::remove_file(path).map_err(|e| format!("Failed to remove snapshot: {}", e))?;
+        }
+    }
+    Ok(())
+}
+
+/// Record a write of `content` to `path` in its history. The content it
+/// replaced is snapshotted first if the history doesn't have it yet, so
+/// edits made outside the app can be restored too.
+pub fn record_write(
+    path: &Path,
+    previous: Option<&[u8]>,
+    content: &[u8],
+    registry: &NoteIndexRegistry,
+    settings: &Mutex<SettingsStore>,
+) -> Result<(), String> {
+    if let Some(previous) = previous {
+        snapshot(path, previous, registry, settings)?;
+    }
+    snapshot(path, content, registry, settings)
+}
+
+/// The snapshot file of version `id` of `path`
+fn version_path(path: &Path, id: &str, registry: &NoteIndexRegistry) -> Result<PathBuf, String> {
+    if id.is_empty() || !id.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(format!("Invalid version id: {}", id));
+    }
+    let version = history_dir(path, registry).join(format!("{}.{}", id, SNAPSHOT_EXTENSION));
+    if !version.is_file() {
+        return Err(format!("Version not found: {}", id));
+    }
+    Ok(version)
+}
+
+/// Raw bytes of version `id` of `path`
+pub fn version_bytes(path: &Path, id: &str, registry: &NoteIndexRegistry) -> Result<Vec<u8>, String> {
+    let version = version_path(path, id, registry)?;
+    fs::read(version).map_err(|e| format!("Failed to read version: {}", e))
+}
+
+/// Contents of version `id` of `path`. Versions of encrypted notes are
+/// only read through `read_version`.
+pub fn version_content(path: &Path, id: &str, registry: &NoteIndexRegistry) -> Result<String, String> {
+    let bytes = version_bytes(path, id, registry)?;
+    if encryption::is_encrypted(&bytes) {
+        return Err(format!("Version {} is encrypted", id));
+    }
+    Ok(crate::encoding::decode(&bytes).content)
+}
+
+/// Delete every saved version of `path`
+pub fn forget(path: &Path, registry: &NoteIndexRegistry) -> Result<(), String> {
+    match fs::remove_dir_all(history_dir(path, registry)) {
+        Err(e) if e.kind() != ErrorKind::NotFound => Err(format!("Failed to remove history: {}", e)),
+        _ => Ok(()),
+    }
+}
+
+/// Saved versions of the file at `path`, newest first
+pub fn versions(path: &Path, registry: &NoteIndexRegistry) -> Vec<Version> {
+    snapshots(&history_dir(path, registry))
+        .into_iter()
+        .rev()
+        .map(|(id, snapshot)| Version {
+            id: id.to_string(),
+            timestamp: id,
+            size: fs::metadata(&snapshot).map(|m| m.len()).unwrap_or(0),
+        })
+        .collect()
+}
+
+/// Saved versions of a file, newest first
+#[tauri::command(async)]
+pub fn list_versions(path: String, registry: tauri::State<'_, NoteIndexRegistry>) -> Result<Vec<Version>, String> {
+    Ok(versions(Path::new(&path), &registry))
+}
+
+/// Contents of a saved version of a file, decrypted if the note is
+/// encrypted
+#[tauri::command(async)]
+pub fn read_version(
+    path: String,
+    id: String,
+    registry: tauri::State<'_, NoteIndexRegistry>,
+    keys: tauri::State<'_, EncryptionKeys>,
+) -> Result<String, CommandError> {
+    let path = Path::new(&path);
+    let bytes = keys.decrypt(path, version_bytes(path, &id, &registry)?)?;
+    Ok(crate::encoding::decode(&bytes).content)
+}
+
+/// Replace a file with a saved version of it. The current contents are
+/// kept in the history, so a restore can be undone.
+#[tauri::command(async)]
+pub fn restore_version(
+    path: String,
+    id: String,
+    registry: tauri::State<'_, NoteIndexRegistry>,
+    settings: tauri::State<'_, Mutex<SettingsStore>>,
+) -> Result<WriteResult, CommandError> {
+    let path_buf = PathBuf::from(&path);
+    settings::check_writable(&settings, &path_buf)?;
+    let version = version_path(&path_buf, &id, &registry)?;
+    let content = fs::read(version).map_err(|e| format!("Failed to read version: {}", e))?;
+    let previous = fs::read(&path_buf).ok();
+
+    crate::write_atomic(&path_buf, &content).map_err(|e| format!("Failed to write file: {}", e))?;
+    record_write(&path_buf, previous.as_deref(), &content, &registry, &settings)?;
+    if let Some(index) = registry.for_path(&path_buf) {
+        index.update_path(&path_buf);
+    }
+
+    Ok(WriteResult {
+        mtime: fs::metadata(&path_buf).ok().as_ref().and_then(crate::mtime_millis),
+        hash: crate::content_hash(&content),
+    })
+}
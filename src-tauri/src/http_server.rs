@@ -0,0 +1,239 @@
+use aes_gcm::aead::rand_core::RngCore;
+use aes_gcm::aead::OsRng;
+use serde_json::json;
+use std::io::{Read, Write};
+use std::net::{Ipv4Addr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use crate::links;
+
+/// Largest request head read
+const MAX_HEAD_BYTES: usize = 16 * 1024;
+/// Largest request body accepted
+const MAX_BODY_BYTES: usize = 1024 * 1024;
+/// How long a client may take to send its request
+const READ_TIMEOUT: Duration = Duration::from_secs(10);
+
+pub struct Request {
+    pub method: String,
+    /// The path, without the query string
+    pub path: String,
+    pub query: String,
+    headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl Request {
+    /// Value of the header `name`, matched case-insensitively
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(header, _)| header.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Percent-decoded value of the query parameter `name`
+    pub fn query_param(&self, name: &str) -> Option<String> {
+        self.query.split('&').find_map(|pair| {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            (key == name).then(|| links::percent_decode(&value.replace('+', " ")))
+        })
+    }
+
+    /// Value of the cookie `name`
+    pub fn cookie(&self, name: &str) -> Option<&str> {
+        self.header("cookie")?.split(';').find_map(|pair| {
+            let (key, value) = pair.trim().split_once('=')?;
+            (key == name).then_some(value)
+        })
+    }
+
+    /// Whether the Host header names the loopback interface on `port`.
+    /// Another host name pointing at 127.0.0.1 means a web page trying its
+    /// luck through DNS rebinding.
+    pub fn is_local_host(&self, port: u16) -> bool {
+        let local_hosts = [format!("127.0.0.1:{}", port), format!("localhost:{}", port)];
+        self.header("host").is_some_and(|host| local_hosts.iter().any(|local| local == host))
+    }
+}
+
+pub struct Response {
+    pub status: u16,
+    pub content_type: &'static str,
+    pub headers: Vec<(&'static str, String)>,
+    pub body: Vec<u8>,
+}
+
+impl Response {
+    pub fn new(status: u16, content_type: &'static str, body: impl Into<Vec<u8>>) -> Self {
+        Response {
+            status,
+            content_type,
+            headers: Vec::new(),
+            body: body.into(),
+        }
+    }
+
+    pub fn json(status: u16, value: serde_json::Value) -> Self {
+        Response::new(status, "application/json", value.to_string())
+    }
+
+    /// `{ "error": message }`
+    pub fn error(status: u16, message: &str) -> Self {
+        Response::json(status, json!({ "error": message }))
+    }
+
+    pub fn with_header(mut self, name: &'static str, value: impl Into<String>) -> Self {
+        self.headers.push((name, value.into()));
+        self
+    }
+}
+
+fn status_text(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        201 => "Created",
+        204 => "No Content",
+        302 => "Found",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        403 => "Forbidden",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        413 => "Payload Too Large",
+        _ => "Internal Server Error",
+    }
+}
+
+/// A random token of 48 hex digits
+pub fn random_token() -> String {
+    let mut bytes = [0u8; 24];
+    OsRng.fill_bytes(&mut bytes);
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// `a == b` in time independent of where they differ
+pub fn tokens_match(a: &str, b: &str) -> bool {
+    a.len() == b.len() && a.bytes().zip(b.bytes()).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+fn read_request(stream: &mut TcpStream) -> Result<Request, Response> {
+    let mut buffer = Vec::new();
+    let mut chunk = [0u8; 4096];
+    let mut read_more = |buffer: &mut Vec<u8>| -> Result<(), Response> {
+        match stream.read(&mut chunk) {
+            Ok(0) | Err(_) => Err(Response::error(400, "Incomplete request")),
+            Ok(read) => {
+                buffer.extend_from_slice(&chunk[..read]);
+                Ok(())
+            }
+        }
+    };
+
+    let (head_len, mut request) = loop {
+        read_more(&mut buffer)?;
+        let mut headers = [httparse::EMPTY_HEADER; 64];
+        let mut parsed = httparse::Request::new(&mut headers);
+        match parsed.parse(&buffer) {
+            Ok(httparse::Status::Complete(head_len)) => {
+                let target = parsed.path.unwrap_or_default();
+                let (path, query) = target.split_once('?').unwrap_or((target, ""));
+                let request = Request {
+                    method: parsed.method.unwrap_or_default().to_string(),
+                    path: path.to_string(),
+                    query: query.to_string(),
+                    headers: parsed
+                        .headers
+                        .iter()
+                        .map(|header| {
+                            let value = String::from_utf8_lossy(header.value).trim().to_string();
+                            (header.name.to_string(), value)
+                        })
+                        .collect(),
+                    body: Vec::new(),
+                };
+                break (head_len, request);
+            }
+            Ok(httparse::Status::Partial) if buffer.len() < MAX_HEAD_BYTES => continue,
+            Ok(httparse::Status::Partial) => return Err(Response::error(413, "Request head too large")),
+            Err(_) => return Err(Response::error(400, "Malformed request")),
+        }
+    };
+
+    let length: usize = request.header("content-length").and_then(|n| n.parse().ok()).unwrap_or(0);
+    if length > MAX_BODY_BYTES {
+        return Err(Response::error(413, "Request body too large"));
+    }
+    let mut body = buffer.split_off(head_len);
+    while body.len() < length {
+        read_more(&mut body)?;
+    }
+    body.truncate(length);
+    request.body = body;
+    Ok(request)
+}
+
+fn write_response(stream: &mut TcpStream, response: &Response) {
+    let mut head = format!(
+        "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n",
+        response.status,
+        status_text(response.status),
+        response.content_type,
+        response.body.len()
+    );
+    for (name, value) in &response.headers {
+        head.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    head.push_str("\r\n");
+    let _ = stream.write_all(head.as_bytes());
+    let _ = stream.write_all(&response.body);
+}
+
+/// A server started by `serve`; it runs until `stop` is called
+pub struct ServerHandle {
+    pub port: u16,
+    stop: Arc<AtomicBool>,
+}
+
+impl ServerHandle {
+    pub fn stop(self) {
+        self.stop.store(true, Ordering::SeqCst);
+        // Wake the accept loop so it sees the flag
+        let _ = TcpStream::connect((Ipv4Addr::LOCALHOST, self.port));
+    }
+}
+
+/// Answer every connection to `listener` with `handler`, one request per
+/// connection, each on a thread of its own
+pub fn serve<F>(listener: TcpListener, handler: F) -> Result<ServerHandle, String>
+where
+    F: Fn(&Request) -> Response + Send + Sync + 'static,
+{
+    let port = listener.local_addr().map_err(|e| format!("Failed to start server: {}", e))?.port();
+    let stop = Arc::new(AtomicBool::new(false));
+    let stopped = stop.clone();
+    let handler = Arc::new(handler);
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            if stopped.load(Ordering::SeqCst) {
+                break;
+            }
+            let Ok(mut stream) = stream else {
+                continue;
+            };
+            let handler = handler.clone();
+            thread::spawn(move || {
+                let _ = stream.set_read_timeout(Some(READ_TIMEOUT));
+                let response = match read_request(&mut stream) {
+                    Ok(request) => handler(&request),
+                    Err(response) => response,
+                };
+                write_response(&mut stream, &response);
+            });
+        }
+    });
+    Ok(ServerHandle { port, stop })
+}
@@ -0,0 +1,163 @@
+use chrono::{Days, NaiveDate, Utc};
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::encoding;
+use crate::encryption;
+use crate::export;
+use crate::markdown;
+use crate::note_index::NoteIndexRegistry;
+use crate::rename;
+use crate::settings::SettingsStore;
+use crate::tasks;
+use crate::templates;
+use crate::vault_config;
+
+/// Longest a line of an iCalendar file may be, in bytes
+const LINE_LIMIT: usize = 75;
+
+/// How much of a daily note goes in its event's description
+const DESCRIPTION_LIMIT: usize = 1000;
+
+#[derive(Debug, Serialize)]
+pub struct IcsExport {
+    /// Events for daily notes
+    pub daily_notes: usize,
+    /// To-dos for tasks with a due date
+    pub tasks: usize,
+}
+
+/// `text` as an iCalendar value
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace("\r\n", "\\n")
+        .replace('\n', "\\n")
+}
+
+/// `line` folded into lines of at most `LINE_LIMIT` bytes, continuation
+/// lines starting with a space, each ended by CRLF
+fn push_line(ics: &mut String, line: &str) {
+    let mut length = 0;
+    for c in line.chars() {
+        if length + c.len_utf8() > LINE_LIMIT {
+            ics.push_str("\r\n ");
+            length = 1;
+        }
+        ics.push(c);
+        length += c.len_utf8();
+    }
+    ics.push_str("\r\n");
+}
+
+fn ics_date(date: NaiveDate) -> String {
+    date.format("%Y%m%d").to_string()
+}
+
+/// An id for the event of `kind` from `key` that stays the same from one
+/// export to the next, so calendars that subscribe to the file update their
+/// events rather than adding new ones
+fn uid(kind: &str, key: &str) -> String {
+    format!("{}-{}@readmark", kind, &crate::content_hash(key.as_bytes())[..32])
+}
+
+/// The title of the daily note at `path` and the start of its text; just its
+/// name if it can't be read
+fn daily_note_text(path: &Path) -> (String, String) {
+    let name = path.file_stem().map(|stem| stem.to_string_lossy().to_string()).unwrap_or_default();
+    let Ok(bytes) = fs::read(path) else {
+        return (name, String::new());
+    };
+    if encryption::is_encrypted(&bytes) {
+        return (name, String::new());
+    }
+    let content = encoding::decode(&bytes).content;
+    let title = export::note_title(path, &markdown::headings(&content));
+    let body = content[markdown::body_start(&content)..].trim();
+    let description = match body.char_indices().nth(DESCRIPTION_LIMIT) {
+        Some((end, _)) => format!("{}…", &body[..end]),
+        None => body.to_string(),
+    };
+    (title, description)
+}
+
+/// Export the vault at `root` as an iCalendar file at `out_path`: an
+/// all-day event for each daily note, found by the vault's
+/// `daily_notes_format`, and a to-do for each task with a due date
+/// (`📅 2024-06-01`, `due:2024-06-01` or `@due(2024-06-01)`)
+#[tauri::command(async)]
+pub fn export_ics(
+    root: String,
+    out_path: String,
+    registry: tauri::State<'_, NoteIndexRegistry>,
+    settings: tauri::State<'_, Mutex<SettingsStore>>,
+) -> Result<IcsExport, String> {
+    let root = PathBuf::from(&root);
+    let index = registry.for_vault(&root, &settings)?;
+    let daily_notes_format = vault_config::resolve(&root, &settings)?.daily_notes_format;
+    let contents = index.contents()?;
+    let mut notes: Vec<_> = contents.notes.iter().filter(|(path, _)| path.starts_with(&root)).collect();
+    notes.sort_by(|a, b| a.0.cmp(b.0));
+
+    let stamp = Utc::now().format("%Y%m%dT%H%M%SZ").to_string();
+    let mut ics = String::new();
+    for line in ["BEGIN:VCALENDAR", "VERSION:2.0", "PRODID:-//readmark//readmark//EN", "CALSCALE:GREGORIAN"] {
+        push_line(&mut ics, line);
+    }
+    let name = root.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+    push_line(&mut ics, &format!("X-WR-CALNAME:{}", escape(&name)));
+
+    let mut export = IcsExport {
+        daily_notes: 0,
+        tasks: 0,
+    };
+    for (path, note) in notes {
+        let relative = rename::relative_path(&root, path);
+        let location = path.to_string_lossy();
+        if let Some(date) = templates::path_date(&daily_notes_format, &relative) {
+            let (title, description) = daily_note_text(path);
+            push_line(&mut ics, "BEGIN:VEVENT");
+            push_line(&mut ics, &format!("UID:{}", uid("note", &relative)));
+            push_line(&mut ics, &format!("DTSTAMP:{}", stamp));
+            push_line(&mut ics, &format!("DTSTART;VALUE=DATE:{}", ics_date(date)));
+            if let Some(next) = date.checked_add_days(Days::new(1)) {
+                push_line(&mut ics, &format!("DTEND;VALUE=DATE:{}", ics_date(next)));
+            }
+            push_line(&mut ics, &format!("SUMMARY:{}", escape(&title)));
+            if !description.is_empty() {
+                push_line(&mut ics, &format!("DESCRIPTION:{}", escape(&description)));
+            }
+            push_line(&mut ics, &format!("LOCATION:{}", escape(&location)));
+            push_line(&mut ics, "TRANSP:TRANSPARENT");
+            push_line(&mut ics, "END:VEVENT");
+            export.daily_notes += 1;
+        }
+
+        for task in &note.tasks {
+            let Some(due) = task.due.as_deref().and_then(|due| NaiveDate::parse_from_str(due, "%Y-%m-%d").ok())
+            else {
+                continue;
+            };
+            let summary = tasks::strip_due(&task.text);
+            push_line(&mut ics, "BEGIN:VTODO");
+            push_line(&mut ics, &format!("UID:{}", uid("task", &format!("{}\n{}", relative, summary))));
+            push_line(&mut ics, &format!("DTSTAMP:{}", stamp));
+            // A time is local to wherever the calendar is
+            match task.due_time.as_deref() {
+                Some(time) => push_line(&mut ics, &format!("DUE:{}T{}00", ics_date(due), time.replace(':', ""))),
+                None => push_line(&mut ics, &format!("DUE;VALUE=DATE:{}", ics_date(due))),
+            }
+            push_line(&mut ics, &format!("SUMMARY:{}", escape(&summary)));
+            push_line(&mut ics, &format!("DESCRIPTION:{}", escape(&format!("{}:{}", location, task.line))));
+            push_line(&mut ics, &format!("STATUS:{}", if task.completed { "COMPLETED" } else { "NEEDS-ACTION" }));
+            push_line(&mut ics, "END:VTODO");
+            export.tasks += 1;
+        }
+    }
+    push_line(&mut ics, "END:VCALENDAR");
+    export::write_output(Path::new(&out_path), ics.as_bytes())?;
+    Ok(export)
+}
@@ -0,0 +1,109 @@
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use ignore::WalkBuilder;
+use std::cmp::Reverse;
+use std::path::Path;
+
+/// Directories that are never useful in a notes listing, gitignored or not,
+/// including the app's own per-vault data
+pub const DEFAULT_IGNORES: &[&str] = &[".git/", "node_modules/", ".readmark/"];
+
+/// Gitignore-style rules for deciding which paths to hide from listings and
+/// watcher events.
+///
+/// Combines the `.gitignore` files from `root` up to the enclosing repository
+/// root (and, with `for_tree`, those below it), the built-in
+/// `DEFAULT_IGNORES`, and any extra globs supplied by the user (gitignore
+/// syntax, so `build/` or `*.tmp`).
+pub struct IgnoreMatcher {
+    /// The built-in and user rules
+    rules: Gitignore,
+    /// `.gitignore` files, deepest folder first
+    gitignores: Vec<Gitignore>,
+}
+
+impl IgnoreMatcher {
+    pub fn new(root: &Path, globs: &[String], respect_gitignore: bool) -> Result<Self, String> {
+        let mut builder = GitignoreBuilder::new(root);
+        for line in DEFAULT_IGNORES.iter().copied().chain(globs.iter().map(String::as_str)) {
+            builder
+                .add_line(None, line)
+                .map_err(|e| format!("Invalid ignore pattern '{}': {}", line, e))?;
+        }
+        let rules = builder.build().map_err(|e| format!("Invalid ignore patterns: {}", e))?;
+
+        let mut gitignores = Vec::new();
+        if respect_gitignore {
+            for dir in root.ancestors() {
+                let gitignore = dir.join(".gitignore");
+                if gitignore.is_file() {
+                    // A partially invalid .gitignore still yields the valid rules
+                    let (matcher, _) = Gitignore::new(&gitignore);
+                    gitignores.push(matcher);
+                }
+                if dir.join(".git").exists() {
+                    break;
+                }
+            }
+        }
+
+        Ok(IgnoreMatcher { rules, gitignores })
+    }
+
+    /// Like `new` with `.gitignore` files respected, but also taking those
+    /// in the folders under `root` that `walker` goes into, so a whole tree
+    /// is matched as its listings are
+    pub fn for_tree(root: &Path, globs: &[String]) -> Result<Self, String> {
+        let mut matcher = IgnoreMatcher::new(root, globs, true)?;
+        let mut nested = Vec::new();
+        for entry in walker(root, globs)?.build().filter_map(|entry| entry.ok()) {
+            let gitignore = entry.path().join(".gitignore");
+            if entry.depth() > 0 && entry.file_type().is_some_and(|t| t.is_dir()) && gitignore.is_file() {
+                nested.push(Gitignore::new(&gitignore).0);
+            }
+        }
+        nested.sort_by_key(|matcher| Reverse(matcher.path().components().count()));
+        nested.append(&mut matcher.gitignores);
+        matcher.gitignores = nested;
+        Ok(matcher)
+    }
+
+    pub fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+        // matched_path_or_any_parents panics for paths outside the matcher root
+        if path.starts_with(self.rules.path()) && self.rules.matched_path_or_any_parents(path, is_dir).is_ignore() {
+            return true;
+        }
+        // As in git, the closest .gitignore with a rule for the path decides,
+        // so a nested one can re-include what a parent ignores
+        self.gitignores
+            .iter()
+            .filter(|matcher| path.starts_with(matcher.path()))
+            .map(|matcher| matcher.matched_path_or_any_parents(path, is_dir))
+            .find(|matched| !matched.is_none())
+            .is_some_and(|matched| matched.is_ignore())
+    }
+}
+
+/// A recursive walker over `root` that honors nested `.gitignore` files as
+/// well as the rules in `IgnoreMatcher`, and nothing else: like
+/// `IgnoreMatcher`, it skips the global gitignore, `.git/info/exclude`,
+/// `.ignore` files and `.gitignore` files above `root`
+pub fn walker(root: &Path, globs: &[String]) -> Result<WalkBuilder, String> {
+    let matcher = IgnoreMatcher::new(root, globs, false)?;
+
+    let mut builder = WalkBuilder::new(root);
+    builder
+        .follow_links(true)
+        .hidden(true)
+        .git_ignore(true)
+        .git_global(false)
+        .git_exclude(false)
+        .ignore(false)
+        .parents(false)
+        .require_git(false)
+        .filter_entry(move |entry| {
+            let is_dir = entry.file_type().is_some_and(|t| t.is_dir());
+            !matcher.is_ignored(entry.path(), is_dir)
+        });
+
+    Ok(builder)
+}
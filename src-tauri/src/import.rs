@@ -0,0 +1,1693 @@
+use base64::Engine;
+use chrono::{Datelike, NaiveDate};
+use htmd::options::{BulletListMarker, HrStyle, Options};
+use htmd::HtmlToMarkdown;
+use md5::{Digest, Md5};
+use quick_xml::events::Event;
+use quick_xml::Reader;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::{BufReader, Cursor, Read, Seek};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+
+use crate::attachments;
+use crate::error::CommandError;
+use crate::export::escape_html;
+use crate::frontmatter;
+use crate::links::{self, LinkKind};
+use crate::note_index::NoteIndexRegistry;
+use crate::rename;
+use crate::settings::{self, SettingsStore};
+use crate::templates;
+use crate::vault_config;
+
+/// Folder, next to the imported notes, that their attachments are saved in
+pub const ATTACHMENTS_DIR: &str = "attachments";
+
+#[derive(Debug, Serialize)]
+pub struct ImportFailure {
+    /// Title or path of the item that couldn't be imported
+    pub item: String,
+    pub error: String,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct ImportSummary {
+    /// Paths of the notes written
+    pub imported: Vec<String>,
+    /// Number of attachments saved
+    pub attachments: usize,
+    pub failed: Vec<ImportFailure>,
+}
+
+/// Markdown for a fragment or document of HTML, with `-` bullets and
+/// `---` rules to match what the editor writes
+pub fn markdown_from_html(html: &str) -> String {
+    let converter = HtmlToMarkdown::builder()
+        .options(Options {
+            hr_style: HrStyle::Dashes,
+            bullet_list_marker: BulletListMarker::Dash,
+            ul_bullet_spacing: 1,
+            ol_number_spacing: 1,
+            ..Default::default()
+        })
+        .skip_tags(vec!["head", "script", "style", "noscript", "template"])
+        .build();
+    converter.convert(html).unwrap_or_default()
+}
+
+/// Convert HTML, such as rich text from the clipboard, to markdown. Only the
+/// marked fragment of clipboard HTML with `<!--StartFragment-->` comments is
+/// converted.
+#[tauri::command(async)]
+pub fn html_to_markdown(html: String) -> Result<String, String> {
+    let fragment = match html.split_once("<!--StartFragment-->") {
+        Some((_, rest)) => rest.split("<!--EndFragment-->").next().unwrap_or(rest),
+        None => &html,
+    };
+    Ok(markdown_from_html(fragment).trim().to_string())
+}
+
+/// `name` with the characters that are invalid in file names, or that break
+/// `[[wiki links]]`, replaced
+pub fn safe_file_name(name: &str) -> String {
+    let replaced: String = name
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' | '#' | '^' | '[' | ']' => ' ',
+            c if c.is_control() => ' ',
+            c => c,
+        })
+        .collect();
+    let collapsed = replaced.split_whitespace().collect::<Vec<_>>().join(" ");
+    let trimmed: String = collapsed.trim_matches('.').trim().chars().take(150).collect();
+    if trimmed.is_empty() {
+        "Untitled".to_string()
+    } else {
+        trimmed
+    }
+}
+
+/// `name`, or `name 1`, `name 2`, ... (before the extension), whichever is
+/// the first not `taken`
+fn unique_name(name: &str, taken: impl Fn(&str) -> bool) -> String {
+    if !taken(name) {
+        return name.to_string();
+    }
+    let (stem, extension) = match name.rsplit_once('.') {
+        Some((stem, extension)) if !stem.is_empty() => (stem, format!(".{}", extension)),
+        _ => (name, String::new()),
+    };
+    (1..)
+        .map(|n| format!("{} {}{}", stem, n, extension))
+        .find(|candidate| !taken(candidate))
+        .expect("some numbered name is free")
+}
+
+/// `dir/name`, or `dir/name 1`, `dir/name 2`, ... if that is taken
+pub fn unique_path(dir: &Path, name: &str) -> PathBuf {
+    dir.join(unique_name(name, |candidate| dir.join(candidate).exists()))
+}
+
+/// A path relative to the note for use as a markdown link target
+pub fn link_target(relative: &str) -> String {
+    relative.replace(' ', "%20")
+}
+
+/// Evernote's `20130730T205204Z` as `2013-07-30T20:52:04Z`
+fn enex_date(date: &str) -> String {
+    let date = date.trim();
+    if date.len() == 16 && date.is_char_boundary(8) && &date[8..9] == "T" {
+        format!(
+            "{}-{}-{}T{}:{}:{}Z",
+            &date[0..4],
+            &date[4..6],
+            &date[6..8],
+            &date[9..11],
+            &date[11..13],
+            &date[13..15]
+        )
+    } else {
+        date.to_string()
+    }
+}
+
+#[derive(Debug, Default)]
+struct EnexResource {
+    data: Vec<u8>,
+    mime: String,
+    file_name: Option<String>,
+}
+
+#[derive(Debug, Default)]
+struct EnexNote {
+    title: String,
+    /// ENML, Evernote's XHTML dialect
+    content: String,
+    created: Option<String>,
+    updated: Option<String>,
+    tags: Vec<String>,
+    source_url: Option<String>,
+    resources: Vec<EnexResource>,
+}
+
+fn attribute_regex() -> &'static Regex {
+    static ATTRIBUTE: OnceLock<Regex> = OnceLock::new();
+    ATTRIBUTE.get_or_init(|| Regex::new(r#"([\w-]+)\s*=\s*"([^"]*)""#).expect("attribute regex is valid"))
+}
+
+fn attributes(tag: &str) -> HashMap<String, String> {
+    attribute_regex()
+        .captures_iter(tag)
+        .map(|captures| (captures[1].to_lowercase(), captures[2].to_string()))
+        .collect()
+}
+
+/// Checkbox placeholder kept through the HTML conversion, which would escape
+/// a literal `[ ]`
+const TODO_OPEN: char = '\u{E000}';
+const TODO_CLOSE: char = '\u{E001}';
+
+/// Markdown for an ENML note body. `media` maps resource hashes to the saved
+/// attachment (relative path, is image).
+fn enml_to_markdown(enml: &str, media: &HashMap<String, (String, bool)>) -> String {
+    static MEDIA: OnceLock<Regex> = OnceLock::new();
+    static TODO: OnceLock<Regex> = OnceLock::new();
+    static TASK: OnceLock<Regex> = OnceLock::new();
+    // Self-closing custom tags aren't self-closing to an HTML parser, so
+    // swap them out before parsing
+    let media_regex = MEDIA.get_or_init(|| {
+        Regex::new(r"(?s)<en-media\b([^>]*?)/?>(?:\s*</en-media>)?").expect("en-media regex is valid")
+    });
+    let todo_regex =
+        TODO.get_or_init(|| Regex::new(r"(?s)<en-todo\b([^>]*?)/?>(?:\s*</en-todo>)?").expect("en-todo regex is valid"));
+
+    let html = media_regex.replace_all(enml, |captures: &regex::Captures| {
+        let attrs = attributes(&captures[1]);
+        let hash = attrs.get("hash").map(|hash| hash.to_lowercase()).unwrap_or_default();
+        match media.get(&hash) {
+            Some((path, true)) => format!("<img src=\"{}\" alt=\"\">", escape_html(&link_target(path))),
+            Some((path, false)) => {
+                let name = path.rsplit('/').next().unwrap_or(path);
+                format!("<a href=\"{}\">{}</a>", escape_html(&link_target(path)), escape_html(name))
+            }
+            None => String::new(),
+        }
+    });
+    let html = todo_regex.replace_all(&html, |captures: &regex::Captures| {
+        let checked = attributes(&captures[1]).get("checked").is_some_and(|value| value == "true");
+        format!("{}{}{}", TODO_OPEN, if checked { 'x' } else { ' ' }, TODO_CLOSE)
+    });
+
+    let markdown = markdown_from_html(&html);
+    // A checkbox at the start of a line becomes a task list item
+    let task_regex = TASK.get_or_init(|| {
+        Regex::new(&format!(r"(?m)^([ \t]*)(?:[-*+][ \t]+)?{}(.){}[ \t]*", TODO_OPEN, TODO_CLOSE))
+            .expect("task regex is valid")
+    });
+    let markdown = task_regex.replace_all(&markdown, "$1- [$2] ");
+    markdown
+        .replace(&format!("{}x{}", TODO_OPEN, TODO_CLOSE), "[x]")
+        .replace(&format!("{} {}", TODO_OPEN, TODO_CLOSE), "[ ]")
+}
+
+/// File extension for a MIME type, for resources saved without a file name
+pub fn extension_for(mime: &str) -> &'static str {
+    match mime {
+        "image/png" => "png",
+        "image/jpeg" => "jpg",
+        "image/gif" => "gif",
+        "image/webp" => "webp",
+        "image/svg+xml" => "svg",
+        "application/pdf" => "pdf",
+        "audio/mpeg" => "mp3",
+        "audio/wav" | "audio/x-wav" => "wav",
+        "video/mp4" => "mp4",
+        _ => "bin",
+    }
+}
+
+/// Save the note's resources and write the note itself into `dest_dir`
+fn write_enex_note(note: &EnexNote, dest_dir: &Path, summary: &mut ImportSummary) -> Result<PathBuf, String> {
+    let attachments_dir = dest_dir.join(ATTACHMENTS_DIR);
+    let mut media = HashMap::new();
+    for resource in &note.resources {
+        let hash: String = Md5::digest(&resource.data).iter().map(|b| format!("{:02x}", b)).collect();
+        let name = match &resource.file_name {
+            Some(name) => safe_file_name(name),
+            None => format!("{}.{}", &hash[..12], extension_for(&resource.mime)),
+        };
+        fs::create_dir_all(&attachments_dir).map_err(|e| format!("Failed to create directory: {}", e))?;
+        let path = unique_path(&attachments_dir, &name);
+        fs::write(&path, &resource.data).map_err(|e| format!("Failed to save attachment {}: {}", name, e))?;
+        summary.attachments += 1;
+
+        let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+        let relative = format!("{}/{}", ATTACHMENTS_DIR, file_name);
+        media.insert(hash, (relative, resource.mime.starts_with("image/")));
+    }
+
+    let mut fields = Map::new();
+    if let Some(created) = &note.created {
+        fields.insert("created".to_string(), Value::String(enex_date(created)));
+    }
+    if let Some(updated) = &note.updated {
+        fields.insert("updated".to_string(), Value::String(enex_date(updated)));
+    }
+    if !note.tags.is_empty() {
+        fields.insert("tags".to_string(), Value::from(note.tags.clone()));
+    }
+    if let Some(source) = &note.source_url {
+        fields.insert("source".to_string(), Value::String(source.clone()));
+    }
+
+    let body = enml_to_markdown(&note.content, &media);
+    let content = frontmatter::replace(&format!("{}\n", body.trim_end()), &fields)?;
+    let path = unique_path(dest_dir, &format!("{}.md", safe_file_name(&note.title)));
+    crate::write_atomic(&path, content.as_bytes()).map_err(|e| format!("Failed to write file: {}", e))?;
+    Ok(path)
+}
+
+/// Import an Evernote `.enex` export into `dest_dir`: one markdown note per
+/// Evernote note, with created/updated dates, tags and source URL in the
+/// frontmatter and embedded resources saved under `attachments/`
+#[tauri::command(async)]
+pub fn import_enex(
+    file: String,
+    dest_dir: String,
+    settings: tauri::State<'_, Mutex<SettingsStore>>,
+) -> Result<ImportSummary, CommandError> {
+    let dest_dir = PathBuf::from(&dest_dir);
+    settings::check_writable(&settings, &dest_dir)?;
+    fs::create_dir_all(&dest_dir).map_err(|e| format!("Failed to create directory: {}", e))?;
+    let input = fs::File::open(&file).map_err(|e| format!("Failed to open file: {}", e))?;
+    let mut reader = Reader::from_reader(BufReader::new(input));
+
+    let mut summary = ImportSummary::default();
+    let mut buf = Vec::new();
+    // Element names from the root down to the current element
+    let mut path: Vec<String> = Vec::new();
+    let mut text = String::new();
+    let mut note = EnexNote::default();
+    let mut resource = EnexResource::default();
+
+    loop {
+        let event = reader
+            .read_event_into(&mut buf)
+            .map_err(|e| format!("Failed to parse {} at byte {}: {}", file, reader.buffer_position(), e))?;
+        match event {
+            Event::Start(start) => {
+                let name = String::from_utf8_lossy(start.local_name().as_ref()).to_string();
+                match name.as_str() {
+                    "note" => note = EnexNote::default(),
+                    "resource" => resource = EnexResource::default(),
+                    _ => {}
+                }
+                path.push(name);
+                text.clear();
+            }
+            Event::Text(t) => text.push_str(&t.decode().map_err(|e| format!("Failed to parse {}: {}", file, e))?),
+            Event::CData(t) => text.push_str(&String::from_utf8_lossy(&t)),
+            Event::GeneralRef(r) => {
+                let name = r.decode().map_err(|e| format!("Failed to parse {}: {}", file, e))?;
+                let entity = format!("&{};", name);
+                text.push_str(&quick_xml::escape::unescape(&entity).unwrap_or(entity.as_str().into()));
+            }
+            Event::End(_) => {
+                let name = path.pop().unwrap_or_default();
+                let parent = path.last().map(String::as_str).unwrap_or_default();
+                let value = std::mem::take(&mut text);
+                match (parent, name.as_str()) {
+                    ("note", "title") => note.title = value.trim().to_string(),
+                    ("note", "content") => note.content = value,
+                    ("note", "created") => note.created = Some(value),
+                    ("note", "updated") => note.updated = Some(value),
+                    ("note", "tag") => note.tags.push(value.trim().to_string()),
+                    ("note-attributes", "source-url") => note.source_url = Some(value.trim().to_string()),
+                    ("resource", "data") => {
+                        let encoded: String = value.chars().filter(|c| !c.is_whitespace()).collect();
+                        resource.data = base64::engine::general_purpose::STANDARD
+                            .decode(encoded)
+                            .unwrap_or_default();
+                    }
+                    ("resource", "mime") => resource.mime = value.trim().to_string(),
+                    ("resource-attributes", "file-name") => resource.file_name = Some(value.trim().to_string()),
+                    ("note", "resource") => note.resources.push(std::mem::take(&mut resource)),
+                    (_, "note") => {
+                        let note = std::mem::take(&mut note);
+                        match write_enex_note(&note, &dest_dir, &mut summary) {
+                            Ok(path) => summary.imported.push(path.to_string_lossy().to_string()),
+                            Err(error) => summary.failed.push(ImportFailure { item: note.title, error }),
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+        buf.clear();
+    }
+
+    Ok(summary)
+}
+
+/// Levels of zip files inside the export that are unpacked too; Notion
+/// splits large exports into `Part-N.zip` files inside the outer zip
+const MAX_NESTED_ZIPS: usize = 1;
+
+struct ArchiveEntry {
+    /// Path inside the export
+    path: PathBuf,
+    data: Vec<u8>,
+}
+
+/// Read every file in the zip, unpacking up to `depth` levels of nested
+/// zips. Entries that can't be read are recorded as failed.
+fn read_archive(
+    reader: impl Read + Seek,
+    depth: usize,
+    entries: &mut Vec<ArchiveEntry>,
+    summary: &mut ImportSummary,
+) -> Result<(), String> {
+    let mut archive = zip::ZipArchive::new(reader).map_err(|e| format!("Failed to open zip: {}", e))?;
+    for i in 0..archive.len() {
+        let mut file = match archive.by_index(i) {
+            Ok(file) => file,
+            Err(e) => {
+                summary.failed.push(ImportFailure {
+                    item: format!("entry {}", i),
+                    error: format!("Failed to read zip entry: {}", e),
+                });
+                continue;
+            }
+        };
+        if file.is_dir() {
+            continue;
+        }
+        // Names that would escape the destination are skipped
+        let Some(path) = file.enclosed_name() else {
+            summary.failed.push(ImportFailure {
+                item: file.name().to_string(),
+                error: "Unsafe path in zip".to_string(),
+            });
+            continue;
+        };
+        let mut data = Vec::new();
+        if let Err(e) = file.read_to_end(&mut data) {
+            summary.failed.push(ImportFailure {
+                item: path.to_string_lossy().to_string(),
+                error: format!("Failed to read zip entry: {}", e),
+            });
+            continue;
+        }
+
+        let is_zip = path.extension().is_some_and(|extension| extension.eq_ignore_ascii_case("zip"));
+        if is_zip && depth > 0 {
+            if let Err(error) = read_archive(Cursor::new(data), depth - 1, entries, summary) {
+                summary.failed.push(ImportFailure {
+                    item: path.to_string_lossy().to_string(),
+                    error,
+                });
+            }
+        } else {
+            entries.push(ArchiveEntry { path, data });
+        }
+    }
+    Ok(())
+}
+
+/// `name` without the 32-hex-digit page id Notion appends to file and
+/// folder names, and whether it was a database's `_all` CSV
+fn notion_name(name: &str) -> (String, bool) {
+    static ID: OnceLock<Regex> = OnceLock::new();
+    let id_regex = ID.get_or_init(|| Regex::new(r"^(.*?) ?[0-9a-f]{32}(_all)?$").expect("notion id regex is valid"));
+    match id_regex.captures(name) {
+        Some(captures) => (safe_file_name(&captures[1]), captures.get(2).is_some()),
+        None => (safe_file_name(name), false),
+    }
+}
+
+/// Lowercase extension of `path`
+fn extension_of(path: &Path) -> String {
+    path.extension().map(|e| e.to_string_lossy().to_lowercase()).unwrap_or_default()
+}
+
+/// Clean output path, relative to the destination, for the export file at
+/// `path`. Pages and databases become `.md` notes.
+fn notion_output_path(path: &Path) -> PathBuf {
+    let mut output = PathBuf::new();
+    if let Some(parent) = path.parent() {
+        for component in parent.components() {
+            output.push(notion_name(&component.as_os_str().to_string_lossy()).0);
+        }
+    }
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let extension = match extension_of(path).as_str() {
+        "md" | "markdown" | "html" | "htm" | "csv" => "md".to_string(),
+        extension => extension.to_string(),
+    };
+    let (name, _) = notion_name(&stem);
+    if extension.is_empty() {
+        output.join(name)
+    } else {
+        output.join(format!("{}.{}", name, extension))
+    }
+}
+
+/// Escape a CSV cell for a markdown table
+pub fn table_cell(cell: &str) -> String {
+    cell.trim().replace('|', "\\|").replace("\r\n", "<br>").replace('\n', "<br>")
+}
+
+/// A Notion database CSV as a note with a markdown table. Cells in the first
+/// column link to the row's page when it was exported too.
+fn csv_to_markdown(data: &[u8], title: &str, row_page: impl Fn(&str) -> Option<String>) -> Result<String, String> {
+    let text = String::from_utf8_lossy(data);
+    let mut reader = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .from_reader(text.trim_start_matches('\u{feff}').as_bytes());
+    let mut rows = Vec::new();
+    for record in reader.records() {
+        let record = record.map_err(|e| format!("Failed to parse CSV: {}", e))?;
+        rows.push(record.iter().map(table_cell).collect::<Vec<_>>());
+    }
+
+    let mut markdown = format!("# {}\n", title);
+    let Some(header) = rows.first() else {
+        return Ok(markdown);
+    };
+    let width = rows.iter().map(Vec::len).max().unwrap_or(0).max(1);
+    let line = |cells: &[String]| {
+        let padded: Vec<&str> = (0..width).map(|i| cells.get(i).map_or("", String::as_str)).collect();
+        format!("| {} |\n", padded.join(" | "))
+    };
+    markdown.push('\n');
+    markdown.push_str(&line(header));
+    markdown.push_str(&line(&vec!["---".to_string(); width]));
+    for row in &rows[1..] {
+        let mut row = row.clone();
+        if let Some(first) = row.first_mut() {
+            if let Some(href) = row_page(first) {
+                *first = format!("[{}]({})", first, href);
+            }
+        }
+        markdown.push_str(&line(&row));
+    }
+    Ok(markdown)
+}
+
+/// Rewrite the relative links in a page from the export's paths to the
+/// cleaned ones. `source` and `output` are the page's path in the export and
+/// its output path.
+fn rewrite_notion_links(
+    content: &str,
+    source: &Path,
+    output: &Path,
+    outputs: &HashMap<PathBuf, PathBuf>,
+) -> String {
+    let source_dir = source.parent().unwrap_or(Path::new(""));
+    let output_dir = output.parent().unwrap_or(Path::new(""));
+    let mut content = content.to_string();
+    for link in links::extract(&content).iter().rev() {
+        if link.kind != LinkKind::Markdown || link.target.is_empty() || links::is_external(&link.target) {
+            continue;
+        }
+        let target = links::normalize_path(&source_dir.join(links::percent_decode(&link.target)));
+        let (Some(new_target), Some(range)) = (outputs.get(&target), rename::target_range(&content, link)) else {
+            continue;
+        };
+        content.replace_range(range, &link_target(&rename::relative_path(output_dir, new_target)));
+    }
+    content
+}
+
+/// Convert one export file and write it to `output`. Returns whether it
+/// was a note, rather than an attachment.
+fn write_notion_entry(
+    entry: &ArchiveEntry,
+    output: &Path,
+    dest_dir: &Path,
+    outputs: &HashMap<PathBuf, PathBuf>,
+) -> Result<bool, String> {
+    let path = dest_dir.join(output);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("Failed to create directory: {}", e))?;
+    }
+    let content = match extension_of(&entry.path).as_str() {
+        "md" | "markdown" => String::from_utf8_lossy(&entry.data).to_string(),
+        "html" | "htm" => markdown_from_html(&String::from_utf8_lossy(&entry.data)),
+        "csv" => {
+            let title = output.file_stem().unwrap_or_default().to_string_lossy();
+            // Row pages are exported to a folder named after the database
+            let rows_dir = output.with_extension("");
+            let pages: HashSet<&PathBuf> = outputs.values().collect();
+            let row_page = |name: &str| {
+                let page = rows_dir.join(format!("{}.md", safe_file_name(name)));
+                pages.contains(&page).then(|| {
+                    link_target(&rename::relative_path(output.parent().unwrap_or(Path::new("")), &page))
+                })
+            };
+            csv_to_markdown(&entry.data, &title, row_page)?
+        }
+        _ => {
+            fs::write(&path, &entry.data).map_err(|e| format!("Failed to write file: {}", e))?;
+            return Ok(false);
+        }
+    };
+    let content = rewrite_notion_links(&content, &entry.path, output, outputs);
+    crate::write_atomic(&path, content.as_bytes()).map_err(|e| format!("Failed to write file: {}", e))?;
+    Ok(true)
+}
+
+/// Import a Notion "Markdown & CSV" (or HTML) export zip into `dest_dir`,
+/// dropping the page ids from file names, rewriting links between pages to
+/// the new paths and turning database CSVs into notes with markdown tables
+#[tauri::command(async)]
+pub fn import_notion_zip(
+    zip_path: String,
+    dest_dir: String,
+    settings: tauri::State<'_, Mutex<SettingsStore>>,
+) -> Result<ImportSummary, CommandError> {
+    let dest_dir = PathBuf::from(&dest_dir);
+    settings::check_writable(&settings, &dest_dir)?;
+    fs::create_dir_all(&dest_dir).map_err(|e| format!("Failed to create directory: {}", e))?;
+    let file = fs::File::open(&zip_path).map_err(|e| format!("Failed to open file: {}", e))?;
+    let mut summary = ImportSummary::default();
+    let mut entries = Vec::new();
+    read_archive(BufReader::new(file), MAX_NESTED_ZIPS, &mut entries, &mut summary)?;
+
+    // A database is exported as both `Name id.csv` (the current view) and
+    // `Name id_all.csv` (every row); only the latter is kept
+    let paths: HashSet<&Path> = entries.iter().map(|entry| entry.path.as_path()).collect();
+    let all_rows = |path: &Path| {
+        let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+        path.with_file_name(format!("{}_all.csv", stem))
+    };
+    let superseded = |path: &Path| extension_of(path) == "csv" && paths.contains(all_rows(path).as_path());
+
+    // Export path to output path, relative to `dest_dir`
+    let mut outputs: HashMap<PathBuf, PathBuf> = HashMap::new();
+    let mut taken: HashSet<PathBuf> = HashSet::new();
+    for entry in entries.iter().filter(|entry| !superseded(&entry.path)) {
+        let clean = notion_output_path(&entry.path);
+        let dir = clean.parent().unwrap_or(Path::new("")).to_path_buf();
+        let name = clean.file_name().unwrap_or_default().to_string_lossy();
+        let name = unique_name(&name, |candidate| {
+            let candidate = dir.join(candidate);
+            taken.contains(&candidate) || dest_dir.join(&candidate).exists()
+        });
+        let output = dir.join(name);
+        taken.insert(output.clone());
+        outputs.insert(entry.path.clone(), output);
+    }
+    for entry in entries.iter().filter(|entry| superseded(&entry.path)) {
+        if let Some(output) = outputs.get(&all_rows(&entry.path)).cloned() {
+            outputs.insert(entry.path.clone(), output);
+        }
+    }
+
+    for entry in entries.iter().filter(|entry| !superseded(&entry.path)) {
+        let output = &outputs[&entry.path];
+        match write_notion_entry(entry, output, &dest_dir, &outputs) {
+            Ok(true) => summary.imported.push(dest_dir.join(output).to_string_lossy().to_string()),
+            Ok(false) => summary.attachments += 1,
+            Err(error) => summary.failed.push(ImportFailure {
+                item: entry.path.to_string_lossy().to_string(),
+                error,
+            }),
+        }
+    }
+    summary.imported.sort();
+    Ok(summary)
+}
+
+/// Set `to`'s modification time to `from`'s
+fn copy_modified(from: &Path, to: &Path) -> Result<(), String> {
+    let modified = fs::metadata(from)
+        .and_then(|metadata| metadata.modified())
+        .map_err(|e| format!("Failed to read {}: {}", from.display(), e))?;
+    fs::File::options()
+        .write(true)
+        .open(to)
+        .and_then(|file| file.set_times(fs::FileTimes::new().set_modified(modified)))
+        .map_err(|e| format!("Failed to set modification time: {}", e))
+}
+
+/// A block of a Logseq page: one bullet of the outline, with its
+/// continuation lines
+#[derive(Debug, Default)]
+struct LogseqBlock {
+    /// Depth in the outline, 0 for top-level bullets
+    level: usize,
+    lines: Vec<String>,
+    /// `key:: value` properties other than those consumed below
+    properties: Vec<(String, String)>,
+    /// `id::` property, which block references point at
+    id: Option<String>,
+    scheduled: Option<String>,
+    deadline: Option<String>,
+}
+
+/// A Logseq property line, `key:: value`, or an entry of a `:PROPERTIES:`
+/// drawer, `:key: value`
+fn logseq_property(line: &str, in_drawer: bool) -> Option<(String, String)> {
+    static PROPERTY: OnceLock<Regex> = OnceLock::new();
+    static DRAWER_ENTRY: OnceLock<Regex> = OnceLock::new();
+    let regex = if in_drawer {
+        DRAWER_ENTRY.get_or_init(|| Regex::new(r"^:([\w\-/]+):(?:\s+(.*))?$").expect("drawer entry regex is valid"))
+    } else {
+        PROPERTY.get_or_init(|| Regex::new(r"^([\w\-/]+)::(?:\s+(.*))?$").expect("property regex is valid"))
+    };
+    let captures = regex.captures(line.trim())?;
+    let value = captures.get(2).map_or("", |value| value.as_str()).trim().to_string();
+    Some((captures[1].to_lowercase(), value))
+}
+
+/// Width of the indentation of `line`, a tab counting for two spaces, and
+/// the rest of it
+fn logseq_indent(line: &str) -> (usize, &str) {
+    let rest = line.trim_start_matches([' ', '\t']);
+    let indent = &line[..line.len() - rest.len()];
+    (indent.chars().map(|c| if c == '\t' { 2 } else { 1 }).sum(), rest)
+}
+
+/// `line` without up to `width` columns of indentation
+fn logseq_dedent(line: &str, width: usize) -> &str {
+    let mut removed = 0;
+    for (at, c) in line.char_indices() {
+        if removed >= width || !matches!(c, ' ' | '\t') {
+            return &line[at..];
+        }
+        removed += if c == '\t' { 2 } else { 1 };
+    }
+    ""
+}
+
+/// The date of a `SCHEDULED: <2024-06-01 Sat>` or `DEADLINE:` line
+fn logseq_planning(line: &str) -> Option<(bool, String)> {
+    static PLANNING: OnceLock<Regex> = OnceLock::new();
+    let regex = PLANNING.get_or_init(|| {
+        Regex::new(r"^(SCHEDULED|DEADLINE):\s*<(\d{4}-\d{2}-\d{2})[^>]*>$").expect("planning regex is valid")
+    });
+    let captures = regex.captures(line.trim())?;
+    Some((&captures[1] == "DEADLINE", captures[2].to_string()))
+}
+
+/// The page properties and blocks of a Logseq page
+fn logseq_blocks(content: &str) -> (Vec<(String, String)>, Vec<LogseqBlock>) {
+    let mut page_properties = Vec::new();
+    let mut blocks: Vec<LogseqBlock> = Vec::new();
+    // Indentation of the open blocks, outermost first
+    let mut open: Vec<usize> = Vec::new();
+    let mut in_fence = false;
+    // Inside a drawer, and whether it is `:PROPERTIES:`
+    let mut drawer: Option<bool> = None;
+
+    for line in content.lines() {
+        let (width, rest) = logseq_indent(line);
+        if !in_fence && (rest.starts_with("- ") || rest == "-") {
+            while open.last().is_some_and(|&open| open >= width) {
+                open.pop();
+            }
+            let mut block = LogseqBlock {
+                level: open.len(),
+                ..Default::default()
+            };
+            open.push(width);
+            drawer = None;
+            let text = rest[1..].trim_start();
+            match logseq_property(text, false) {
+                Some(property) => block.properties.push(property),
+                None => {
+                    in_fence = text.starts_with("```");
+                    block.lines.push(text.to_string());
+                }
+            }
+            blocks.push(block);
+            continue;
+        }
+
+        let Some(block) = blocks.last_mut() else {
+            // Text before the first bullet: page properties, or a page
+            // written outside Logseq
+            match logseq_property(line, false) {
+                Some(property) => page_properties.push(property),
+                None if line.trim().is_empty() => {}
+                None => blocks.push(LogseqBlock {
+                    lines: vec![line.to_string()],
+                    ..Default::default()
+                }),
+            }
+            continue;
+        };
+        let text = logseq_dedent(line, open.last().map_or(0, |width| width + 2));
+        if in_fence {
+            in_fence = !text.trim_start().starts_with("```");
+            block.lines.push(text.to_string());
+            continue;
+        }
+        match text.trim() {
+            ":END:" if drawer.is_some() => drawer = None,
+            ":PROPERTIES:" => drawer = Some(true),
+            trimmed if drawer.is_none() && is_drawer_start(trimmed) => drawer = Some(false),
+            _ if drawer == Some(false) => {}
+            trimmed => {
+                if let Some((key, value)) = logseq_property(trimmed, drawer == Some(true)) {
+                    match key.as_str() {
+                        "id" => block.id = Some(value),
+                        "collapsed" | "heading" => {}
+                        _ => block.properties.push((key, value)),
+                    }
+                } else if let Some((deadline, date)) = logseq_planning(trimmed) {
+                    *(if deadline { &mut block.deadline } else { &mut block.scheduled }) = Some(date);
+                } else {
+                    in_fence = trimmed.starts_with("```");
+                    block.lines.push(text.to_string());
+                }
+            }
+        }
+    }
+
+    // Page properties may also be written as a first block of nothing else
+    if page_properties.is_empty() && blocks.first().is_some_and(|block| block.lines.is_empty()) {
+        page_properties = blocks.remove(0).properties;
+    }
+    (page_properties, blocks)
+}
+
+/// `:LOGBOOK:` and other drawers Logseq keeps its own bookkeeping in
+fn is_drawer_start(line: &str) -> bool {
+    line.len() > 2
+        && line.starts_with(':')
+        && line.ends_with(':')
+        && line[1..line.len() - 1].chars().all(|c| c.is_ascii_uppercase())
+}
+
+/// The outline of a Logseq page as markdown: top-level blocks become
+/// paragraphs, their children list items, and `TODO`/`DONE` blocks tasks
+fn logseq_markdown(blocks: &[LogseqBlock]) -> String {
+    static MARKER: OnceLock<Regex> = OnceLock::new();
+    let marker_regex = MARKER.get_or_init(|| {
+        Regex::new(r"^(TODO|DOING|NOW|LATER|WAIT|WAITING|IN-PROGRESS|DONE|CANCELED|CANCELLED)\s+(.*)$")
+            .expect("task marker regex is valid")
+    });
+    let mut lines: Vec<String> = Vec::new();
+    // Levels a block's list items are indented by less than its level: 1
+    // under a paragraph, 0 under a task
+    let mut offset = 1;
+    let mut previous_paragraph = false;
+    for block in blocks {
+        let first = block.lines.first().map(String::as_str).unwrap_or_default();
+        let (mut first, is_task) = match marker_regex.captures(first) {
+            Some(captures) => match &captures[1] {
+                "DONE" => (format!("[x] {}", &captures[2]), true),
+                "CANCELED" | "CANCELLED" => (format!("~~{}~~", &captures[2]), false),
+                _ => (format!("[ ] {}", &captures[2]), true),
+            },
+            None => (first.to_string(), false),
+        };
+        if let Some(deadline) = &block.deadline {
+            first.push_str(&format!(" 📅 {}", deadline));
+        }
+        if let Some(scheduled) = &block.scheduled {
+            first.push_str(&format!(" ⏳ {}", scheduled));
+        }
+        if let Some(id) = &block.id {
+            first.push_str(&format!(" ^{}", id));
+        }
+        let rest = block.lines.iter().skip(1).cloned();
+        let properties = block.properties.iter().map(|(key, value)| format!("{}:: {}", key, value));
+        let rest: Vec<String> = rest.chain(properties).collect();
+
+        if block.level == 0 {
+            offset = if is_task { 0 } else { 1 };
+            if !lines.is_empty() && (previous_paragraph || !is_task) {
+                lines.push(String::new());
+            }
+            previous_paragraph = !is_task;
+        }
+        if block.level == 0 && !is_task {
+            lines.push(first);
+            lines.extend(rest);
+        } else {
+            let indent = "  ".repeat(block.level.saturating_sub(offset));
+            lines.push(format!("{}- {}", indent, first).trim_end().to_string());
+            lines.extend(rest.iter().map(|line| match line.is_empty() {
+                true => String::new(),
+                false => format!("{}  {}", indent, line),
+            }));
+        }
+    }
+    lines.join("\n")
+}
+
+/// `[[a]], #b, c` as `["a", "b", "c"]`
+fn logseq_values(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(|item| item.trim().trim_start_matches('#').trim_start_matches("[[").trim_end_matches("]]").trim())
+        .filter(|item| !item.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Title Logseq gives the journal page of `date`, for a date-fns `format`
+/// such as its default `MMM do, yyyy`
+fn logseq_journal_title(date: NaiveDate, format: &str) -> String {
+    const TOKENS: &[&str] = &["yyyy", "yy", "MMMM", "MMM", "MM", "M", "do", "dd", "d", "EEEE", "EEE", "EE", "E"];
+    let mut title = String::new();
+    let mut rest = format;
+    while let Some(c) = rest.chars().next() {
+        if c == '\'' {
+            let end = rest[1..].find('\'').map_or(rest.len(), |at| at + 1);
+            title.push_str(&rest[1..end]);
+            rest = rest.get(end + 1..).unwrap_or_default();
+            continue;
+        }
+        let Some(token) = TOKENS.iter().find(|token| rest.starts_with(**token)) else {
+            title.push(c);
+            rest = &rest[c.len_utf8()..];
+            continue;
+        };
+        let day = date.day();
+        title.push_str(&match *token {
+            "yyyy" => date.format("%Y").to_string(),
+            "yy" => date.format("%y").to_string(),
+            "MMMM" => date.format("%B").to_string(),
+            "MMM" => date.format("%b").to_string(),
+            "MM" => format!("{:02}", date.month()),
+            "M" => date.month().to_string(),
+            "do" => {
+                let suffix = match (day % 10, day % 100) {
+                    (_, 11..=13) => "th",
+                    (1, _) => "st",
+                    (2, _) => "nd",
+                    (3, _) => "rd",
+                    _ => "th",
+                };
+                format!("{}{}", day, suffix)
+            }
+            "dd" => format!("{:02}", day),
+            "d" => day.to_string(),
+            "EEEE" => date.format("%A").to_string(),
+            _ => date.format("%a").to_string(),
+        });
+        rest = &rest[token.len()..];
+    }
+    title
+}
+
+/// Logseq's page references and attachment links in `markdown` as readmark
+/// links: `((block))` references and embeds become `[[Page#^block]]`,
+/// `[text]([[Page]])` and `#[[Page]]` plain wiki links, page names are
+/// mapped to the imported notes' via `pages` (lowercase name to link
+/// target) and `../assets/` links point at `attachments` (relative to the
+/// note)
+fn logseq_links(
+    markdown: &str,
+    pages: &HashMap<String, String>,
+    blocks: &HashMap<String, String>,
+    attachments: &str,
+) -> String {
+    const UUID: &str = r"[0-9a-f]{8}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{12}";
+    static BLOCK_REF: OnceLock<Regex> = OnceLock::new();
+    static PAGE_REF: OnceLock<Regex> = OnceLock::new();
+    static WIKI: OnceLock<Regex> = OnceLock::new();
+    static ASSET: OnceLock<Regex> = OnceLock::new();
+    let block_regex = BLOCK_REF.get_or_init(|| {
+        Regex::new(&format!(r"\{{\{{embed \(\(({0})\)\)\}}\}}|\[([^\[\]]*)\]\(\(\(({0})\)\)\)|\(\(({0})\)\)", UUID))
+            .expect("block reference regex is valid")
+    });
+    let page_regex = PAGE_REF.get_or_init(|| {
+        Regex::new(r"\{\{embed \[\[([^\[\]]+)\]\]\}\}|\[([^\[\]]*)\]\(\[\[([^\[\]]+)\]\]\)|#\[\[([^\[\]]+)\]\]")
+            .expect("page reference regex is valid")
+    });
+    let wiki_regex = WIKI.get_or_init(|| {
+        Regex::new(r"(!?)\[\[([^\[\]|#]+)(#[^\[\]|]*)?(?:\|([^\[\]]*))?\]\]").expect("wiki link regex is valid")
+    });
+    let asset_regex = ASSET.get_or_init(|| {
+        Regex::new(r"\]\((?:\.\./|\./|/)*assets/([^()\s]+)\)(?:\{[^{}\n]*\})?").expect("asset link regex is valid")
+    });
+
+    let mut in_fence = false;
+    let mut lines = Vec::new();
+    for line in markdown.lines() {
+        if line.trim_start().starts_with("```") {
+            in_fence = !in_fence;
+        }
+        if in_fence || line.trim_start().starts_with("```") {
+            lines.push(line.to_string());
+            continue;
+        }
+        let line = block_regex.replace_all(line, |captures: &regex::Captures| {
+            let id = captures.get(1).or(captures.get(3)).or(captures.get(4)).map_or("", |id| id.as_str());
+            let Some(page) = blocks.get(id) else {
+                return captures[0].to_string();
+            };
+            match (captures.get(1), captures.get(2)) {
+                (Some(_), _) => format!("![[{}#^{}]]", page, id),
+                (_, Some(text)) => format!("[[{}#^{}|{}]]", page, id, text.as_str()),
+                _ => format!("[[{}#^{}]]", page, id),
+            }
+        });
+        let line = page_regex.replace_all(&line, |captures: &regex::Captures| {
+            match (captures.get(1), captures.get(2), captures.get(3), captures.get(4)) {
+                (Some(page), ..) => format!("![[{}]]", page.as_str()),
+                (_, Some(text), Some(page), _) => format!("[[{}|{}]]", page.as_str(), text.as_str()),
+                (.., Some(page)) => format!("[[{}]]", page.as_str()),
+                _ => captures[0].to_string(),
+            }
+        });
+        let line = wiki_regex.replace_all(&line, |captures: &regex::Captures| {
+            let name = captures[2].trim();
+            let Some(target) = pages.get(&name.to_lowercase()).filter(|target| *target != name) else {
+                return captures[0].to_string();
+            };
+            let anchor = captures.get(3).map_or("", |anchor| anchor.as_str());
+            match (&captures[1], captures.get(4)) {
+                ("!", _) => format!("![[{}{}]]", target, anchor),
+                (_, Some(text)) => format!("[[{}{}|{}]]", target, anchor, text.as_str()),
+                _ => format!("[[{}{}|{}]]", target, anchor, name),
+            }
+        });
+        let line = asset_regex.replace_all(&line, |captures: &regex::Captures| {
+            format!("]({}/{})", attachments, &captures[1])
+        });
+        lines.push(line.into_owned());
+    }
+    lines.join("\n")
+}
+
+/// A page or journal of a Logseq graph to import
+struct LogseqPage {
+    source: PathBuf,
+    /// Output path, relative to the destination
+    output: PathBuf,
+    /// What wiki links to the page name it by
+    target: String,
+    properties: Vec<(String, String)>,
+    blocks: Vec<LogseqBlock>,
+}
+
+/// Logseq's name for the page in `file`: its file name, with `___` (or
+/// the older `%2F` and `.`) standing for the `/` of namespaces
+fn logseq_page_name(file: &Path) -> String {
+    let stem = file.file_stem().unwrap_or_default().to_string_lossy();
+    let name = links::percent_decode(&stem);
+    if name.contains("___") {
+        name.replace("___", "/")
+    } else {
+        name
+    }
+}
+
+/// The `.md` files under `dir`, failing `.org` pages, which aren't
+/// supported
+fn logseq_files(dir: &Path, summary: &mut ImportSummary) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    let mut pending = vec![dir.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        let Ok(entries) = fs::read_dir(&dir) else {
+            continue;
+        };
+        for path in entries.flatten().map(|entry| entry.path()) {
+            match extension_of(&path).as_str() {
+                _ if path.is_dir() => pending.push(path),
+                "md" | "markdown" => files.push(path),
+                "org" => summary.failed.push(ImportFailure {
+                    item: path.to_string_lossy().to_string(),
+                    error: "Org-mode pages can't be imported".to_string(),
+                }),
+                _ => {}
+            }
+        }
+    }
+    files.sort();
+    files
+}
+
+/// Copy the graph's `assets` folder to `dest`
+fn copy_logseq_assets(assets: &Path, dest: &Path, summary: &mut ImportSummary) {
+    let mut pending = vec![assets.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        let Ok(entries) = fs::read_dir(&dir) else {
+            continue;
+        };
+        for path in entries.flatten().map(|entry| entry.path()) {
+            if path.is_dir() {
+                pending.push(path);
+                continue;
+            }
+            let output = dest.join(path.strip_prefix(assets).unwrap_or(&path));
+            let copied = output
+                .parent()
+                .map_or(Ok(()), fs::create_dir_all)
+                .and_then(|_| fs::copy(&path, &output))
+                .map_err(|e| format!("Failed to copy attachment: {}", e))
+                .and_then(|_| copy_modified(&path, &output));
+            match copied {
+                Ok(()) => summary.attachments += 1,
+                Err(error) => summary.failed.push(ImportFailure {
+                    item: path.to_string_lossy().to_string(),
+                    error,
+                }),
+            }
+        }
+    }
+}
+
+/// Write one converted Logseq page
+fn write_logseq_page(
+    page: &LogseqPage,
+    dest_dir: &Path,
+    pages: &HashMap<String, String>,
+    blocks: &HashMap<String, String>,
+) -> Result<PathBuf, String> {
+    let path = dest_dir.join(&page.output);
+    let note_dir = path.parent().unwrap_or(dest_dir);
+    let attachments = rename::relative_path(note_dir, &dest_dir.join(ATTACHMENTS_DIR));
+    let body = logseq_links(&logseq_markdown(&page.blocks), pages, blocks, &link_target(&attachments));
+
+    let mut fields = Map::new();
+    for (key, value) in &page.properties {
+        match key.as_str() {
+            "title" | "id" | "collapsed" => {}
+            "tags" => {
+                fields.insert("tags".to_string(), Value::from(logseq_values(value)));
+            }
+            "alias" => {
+                fields.insert("aliases".to_string(), Value::from(logseq_values(value)));
+            }
+            _ => {
+                fields.insert(key.clone(), Value::String(value.clone()));
+            }
+        }
+    }
+    let content = frontmatter::replace(&format!("{}\n", body.trim()), &fields)?;
+    fs::create_dir_all(note_dir).map_err(|e| format!("Failed to create directory: {}", e))?;
+    crate::write_atomic(&path, content.as_bytes()).map_err(|e| format!("Failed to write file: {}", e))?;
+    copy_modified(&page.source, &path)?;
+    Ok(path)
+}
+
+/// The journal title format set in the graph's `logseq/config.edn`
+fn logseq_journal_format(graph: &Path) -> String {
+    static FORMAT: OnceLock<Regex> = OnceLock::new();
+    let format_regex = FORMAT.get_or_init(|| {
+        Regex::new(r#"(?m)^[^;\n]*:journal/page-title-format\s+"([^"]+)""#).expect("journal format regex is valid")
+    });
+    fs::read_to_string(graph.join("logseq").join("config.edn"))
+        .ok()
+        .and_then(|config| format_regex.captures(&config).map(|captures| captures[1].to_string()))
+        .unwrap_or_else(|| "MMM do, yyyy".to_string())
+}
+
+/// Import the Logseq graph in `dir` into `dest_dir`. Pages become notes
+/// (namespaces folders) and journals dated notes under `journals/`; the
+/// outline's top-level bullets become paragraphs and nested ones list
+/// items, `TODO`/`DONE` blocks tasks, page properties frontmatter, and
+/// block references links to `^block` anchors. Assets are copied to
+/// `attachments/`, and the files keep their modification times.
+#[tauri::command(async)]
+pub fn import_logseq(
+    dir: String,
+    dest_dir: String,
+    settings: tauri::State<'_, Mutex<SettingsStore>>,
+) -> Result<ImportSummary, CommandError> {
+    let graph = PathBuf::from(&dir);
+    let (pages_dir, journals_dir) = (graph.join("pages"), graph.join("journals"));
+    if !pages_dir.is_dir() && !journals_dir.is_dir() {
+        return Err(format!("Not a Logseq graph, it has no pages or journals folder: {}", dir).into());
+    }
+    let dest_dir = PathBuf::from(&dest_dir);
+    settings::check_writable(&settings, &dest_dir)?;
+    fs::create_dir_all(&dest_dir).map_err(|e| format!("Failed to create directory: {}", e))?;
+    let mut summary = ImportSummary::default();
+    let journal_format = logseq_journal_format(&graph);
+
+    // Read every page first, so that links can point at the new names
+    let mut pages = Vec::new();
+    let mut names: HashMap<String, String> = HashMap::new();
+    let mut taken: HashSet<PathBuf> = HashSet::new();
+    let files = logseq_files(&pages_dir, &mut summary).into_iter().map(|file| (file, false));
+    let journals = logseq_files(&journals_dir, &mut summary).into_iter().map(|file| (file, true));
+    for (source, is_journal) in files.chain(journals) {
+        let content = match fs::read(&source) {
+            Ok(content) => String::from_utf8_lossy(&content).to_string(),
+            Err(e) => {
+                summary.failed.push(ImportFailure {
+                    item: source.to_string_lossy().to_string(),
+                    error: format!("Failed to read file: {}", e),
+                });
+                continue;
+            }
+        };
+        let (properties, blocks) = logseq_blocks(&content);
+        let stem = source.file_stem().unwrap_or_default().to_string_lossy().to_string();
+        let date = NaiveDate::parse_from_str(&stem, "%Y_%m_%d").ok().filter(|_| is_journal);
+        let (name, clean) = match date {
+            Some(date) => (
+                logseq_journal_title(date, &journal_format),
+                PathBuf::from("journals").join(date.format("%Y-%m-%d").to_string()),
+            ),
+            None => {
+                let title = properties.iter().find(|(key, _)| key == "title").map(|(_, title)| title.clone());
+                let name = title.unwrap_or_else(|| logseq_page_name(&source));
+                let clean: PathBuf = name.split('/').map(safe_file_name).collect();
+                (name, if is_journal { Path::new("journals").join(clean) } else { clean })
+            }
+        };
+        let dir = clean.parent().unwrap_or(Path::new("")).to_path_buf();
+        let file_name = format!("{}.md", clean.file_name().unwrap_or_default().to_string_lossy());
+        let file_name = unique_name(&file_name, |candidate| {
+            let candidate = dir.join(candidate);
+            taken.contains(&candidate) || dest_dir.join(&candidate).exists()
+        });
+        let output = dir.join(file_name);
+        taken.insert(output.clone());
+        // Journals by their date alone; pages by path, for namespaces
+        let target = match date {
+            Some(_) => output.file_stem().unwrap_or_default().to_string_lossy().to_string(),
+            None => rename::relative_path(Path::new(""), &output.with_extension("")),
+        };
+        names.insert(name.to_lowercase(), target.clone());
+        pages.push(LogseqPage {
+            source,
+            output,
+            target,
+            properties,
+            blocks,
+        });
+    }
+    let block_pages: HashMap<String, String> = pages
+        .iter()
+        .flat_map(|page| {
+            let blocks = page.blocks.iter();
+            blocks.filter_map(|block| block.id.clone().map(|id| (id, page.target.clone())))
+        })
+        .collect();
+
+    copy_logseq_assets(&graph.join("assets"), &dest_dir.join(ATTACHMENTS_DIR), &mut summary);
+    for page in &pages {
+        match write_logseq_page(page, &dest_dir, &names, &block_pages) {
+            Ok(path) => summary.imported.push(path.to_string_lossy().to_string()),
+            Err(error) => summary.failed.push(ImportFailure {
+                item: page.source.to_string_lossy().to_string(),
+                error,
+            }),
+        }
+    }
+    summary.imported.sort();
+    Ok(summary)
+}
+
+/// An item of a Joplin export: a note, folder, resource, tag or note-tag
+/// link, serialized as its title and body followed by `key: value` fields
+#[derive(Debug, Default)]
+struct JoplinItem {
+    title: String,
+    body: String,
+    fields: HashMap<String, String>,
+}
+
+impl JoplinItem {
+    fn field(&self, name: &str) -> &str {
+        self.fields.get(name).map_or("", String::as_str)
+    }
+}
+
+fn joplin_item(text: &str) -> JoplinItem {
+    static FIELD: OnceLock<Regex> = OnceLock::new();
+    let field_regex = FIELD.get_or_init(|| Regex::new(r"^([a-z_]+): ?(.*)$").expect("field regex is valid"));
+    let lines: Vec<&str> = text.lines().collect();
+    // The fields run from the last blank line to the end
+    let start = lines.iter().rposition(|line| !field_regex.is_match(line)).map_or(0, |at| at + 1);
+    let fields = lines[start..]
+        .iter()
+        .filter_map(|line| field_regex.captures(line))
+        .map(|captures| (captures[1].to_string(), captures[2].to_string()))
+        .collect();
+    let head = lines[..start].join("\n");
+    let head = head.trim_end();
+    let (title, body) = head.split_once('\n').unwrap_or((head, ""));
+    JoplinItem {
+        title: title.trim().to_string(),
+        body: body.trim_start_matches('\n').to_string(),
+        fields,
+    }
+}
+
+/// Joplin's item types, from the `type_` field
+const JOPLIN_NOTE: &str = "1";
+const JOPLIN_FOLDER: &str = "2";
+const JOPLIN_RESOURCE: &str = "4";
+const JOPLIN_TAG: &str = "5";
+const JOPLIN_NOTE_TAG: &str = "6";
+
+/// Folder of the notebook `id`, relative to the destination, following
+/// its parents. Notebooks nested in themselves are cut off.
+fn joplin_folder(id: &str, folders: &HashMap<&str, &JoplinItem>) -> PathBuf {
+    let mut names = Vec::new();
+    let mut current = id;
+    while let Some(folder) = folders.get(current) {
+        if names.len() >= folders.len() {
+            break;
+        }
+        names.push(safe_file_name(&folder.title));
+        current = folder.field("parent_id");
+    }
+    names.iter().rev().collect()
+}
+
+/// `body` with its `:/id` links to notes and resources pointing at their
+/// files. `targets` maps item ids to paths relative to the destination,
+/// `note_dir` is the note's folder.
+fn rewrite_joplin_links(body: &str, note_dir: &Path, targets: &HashMap<String, PathBuf>) -> String {
+    static ITEM_LINK: OnceLock<Regex> = OnceLock::new();
+    let link_regex = ITEM_LINK.get_or_init(|| {
+        Regex::new(r#"(\]\(|src="):/([0-9a-f]{32})(#[^)"\s]*)?"#).expect("item link regex is valid")
+    });
+    link_regex
+        .replace_all(body, |captures: &regex::Captures| match targets.get(&captures[2]) {
+            Some(target) => {
+                let anchor = captures.get(3).map_or("", |anchor| anchor.as_str());
+                format!("{}{}{}", &captures[1], link_target(&rename::relative_path(note_dir, target)), anchor)
+            }
+            None => captures[0].to_string(),
+        })
+        .into_owned()
+}
+
+/// Write one Joplin note to `output` (relative to `dest_dir`)
+fn write_joplin_note(
+    note: &JoplinItem,
+    output: &Path,
+    dest_dir: &Path,
+    tags: &[String],
+    targets: &HashMap<String, PathBuf>,
+) -> Result<PathBuf, String> {
+    let body = match note.field("markup_language") {
+        "2" => markdown_from_html(&note.body),
+        _ => note.body.clone(),
+    };
+    let body = rewrite_joplin_links(&body, output.parent().unwrap_or(Path::new("")), targets);
+
+    let mut fields = Map::new();
+    // The user-set dates win over those of the item itself
+    let date = |user: &str, item: &str| match note.field(user) {
+        "" => note.field(item),
+        date => date,
+    };
+    for (key, value) in [
+        ("created", date("user_created_time", "created_time")),
+        ("updated", date("user_updated_time", "updated_time")),
+        ("source", note.field("source_url")),
+        ("author", note.field("author")),
+    ] {
+        if !value.is_empty() {
+            fields.insert(key.to_string(), Value::String(value.to_string()));
+        }
+    }
+    if !tags.is_empty() {
+        fields.insert("tags".to_string(), Value::from(tags.to_vec()));
+    }
+
+    let path = dest_dir.join(output);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("Failed to create directory: {}", e))?;
+    }
+    let content = frontmatter::replace(&format!("{}\n", body.trim_end()), &fields)?;
+    crate::write_atomic(&path, content.as_bytes()).map_err(|e| format!("Failed to write file: {}", e))?;
+    Ok(path)
+}
+
+/// Import a Joplin `.jex` export into `dest_dir`: notebooks become folders
+/// and notes markdown notes with their created/updated dates, tags and
+/// source URL in the frontmatter. Resources are saved under `attachments/`
+/// and the `:/id` links to them and between notes rewritten to relative
+/// paths.
+#[tauri::command(async)]
+pub fn import_joplin_jex(
+    file: String,
+    dest_dir: String,
+    settings: tauri::State<'_, Mutex<SettingsStore>>,
+) -> Result<ImportSummary, CommandError> {
+    let dest_dir = PathBuf::from(&dest_dir);
+    settings::check_writable(&settings, &dest_dir)?;
+    fs::create_dir_all(&dest_dir).map_err(|e| format!("Failed to create directory: {}", e))?;
+    let input = fs::File::open(&file).map_err(|e| format!("Failed to open file: {}", e))?;
+    let mut archive = tar::Archive::new(BufReader::new(input));
+    let mut summary = ImportSummary::default();
+
+    let mut items = Vec::new();
+    // Resource files by id
+    let mut resource_data: HashMap<String, Vec<u8>> = HashMap::new();
+    let entries = archive.entries().map_err(|e| format!("Failed to read {}: {}", file, e))?;
+    for entry in entries {
+        let mut entry = entry.map_err(|e| format!("Failed to read {}: {}", file, e))?;
+        let path = entry.path().map(|path| path.to_path_buf()).unwrap_or_default();
+        let mut data = Vec::new();
+        if let Err(e) = entry.read_to_end(&mut data) {
+            summary.failed.push(ImportFailure {
+                item: path.to_string_lossy().to_string(),
+                error: format!("Failed to read archive entry: {}", e),
+            });
+            continue;
+        }
+        let stem = path.file_stem().unwrap_or_default().to_string_lossy().to_string();
+        if path.parent().is_some_and(|parent| parent.ends_with("resources")) {
+            resource_data.insert(stem, data);
+        } else if extension_of(&path) == "md" {
+            items.push(joplin_item(&String::from_utf8_lossy(&data)));
+        }
+    }
+    let of_type = |kind: &'static str| items.iter().filter(move |item| item.field("type_") == kind);
+
+    let folders: HashMap<&str, &JoplinItem> = of_type(JOPLIN_FOLDER).map(|item| (item.field("id"), item)).collect();
+    let tag_names: HashMap<&str, &str> =
+        of_type(JOPLIN_TAG).map(|tag| (tag.field("id"), tag.title.as_str())).collect();
+    let mut note_tags: HashMap<&str, Vec<String>> = HashMap::new();
+    for link in of_type(JOPLIN_NOTE_TAG) {
+        if let Some(tag) = tag_names.get(link.field("tag_id")) {
+            note_tags.entry(link.field("note_id")).or_default().push(tag.to_string());
+        }
+    }
+
+    // Item ids to paths relative to `dest_dir`
+    let mut targets: HashMap<String, PathBuf> = HashMap::new();
+    let attachments_dir = dest_dir.join(ATTACHMENTS_DIR);
+    for resource in of_type(JOPLIN_RESOURCE) {
+        let id = resource.field("id");
+        let Some(data) = resource_data.get(id) else {
+            summary.failed.push(ImportFailure {
+                item: resource.title.clone(),
+                error: "The resource's file is missing from the export".to_string(),
+            });
+            continue;
+        };
+        let name = [resource.field("filename"), resource.title.as_str()]
+            .into_iter()
+            .find(|name| !name.trim().is_empty())
+            .map(safe_file_name)
+            .unwrap_or_else(|| id.to_string());
+        let name = match (Path::new(&name).extension(), resource.field("file_extension")) {
+            (None, "") => format!("{}.{}", name, extension_for(resource.field("mime"))),
+            (None, extension) => format!("{}.{}", name, extension),
+            (Some(_), _) => name,
+        };
+        let saved = fs::create_dir_all(&attachments_dir).and_then(|_| {
+            let path = unique_path(&attachments_dir, &name);
+            fs::write(&path, data).map(|_| path)
+        });
+        match saved {
+            Ok(path) => {
+                let file_name = path.file_name().unwrap_or_default();
+                targets.insert(id.to_string(), Path::new(ATTACHMENTS_DIR).join(file_name));
+                summary.attachments += 1;
+            }
+            Err(e) => summary.failed.push(ImportFailure {
+                item: name,
+                error: format!("Failed to save attachment: {}", e),
+            }),
+        }
+    }
+
+    let mut taken: HashSet<PathBuf> = HashSet::new();
+    let notes: Vec<&JoplinItem> = of_type(JOPLIN_NOTE).collect();
+    for note in &notes {
+        let dir = joplin_folder(note.field("parent_id"), &folders);
+        let name = unique_name(&format!("{}.md", safe_file_name(&note.title)), |candidate| {
+            let candidate = dir.join(candidate);
+            taken.contains(&candidate) || dest_dir.join(&candidate).exists()
+        });
+        let output = dir.join(name);
+        taken.insert(output.clone());
+        targets.insert(note.field("id").to_string(), output);
+    }
+    for note in notes {
+        let output = &targets[note.field("id")];
+        let tags = note_tags.get(note.field("id")).map_or(&[][..], Vec::as_slice);
+        match write_joplin_note(note, output, &dest_dir, tags, &targets) {
+            Ok(path) => summary.imported.push(path.to_string_lossy().to_string()),
+            Err(error) => summary.failed.push(ImportFailure {
+                item: note.title.clone(),
+                error,
+            }),
+        }
+    }
+    summary.imported.sort();
+    Ok(summary)
+}
+
+/// A photo, video, audio recording or PDF of a Day One entry
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct DayOneMedia {
+    /// What the entry's `dayone-moment:` links name it by
+    identifier: String,
+    /// Stem of its file in the export
+    md5: String,
+    /// Extension of its file, e.g. `jpeg`
+    #[serde(rename = "type")]
+    kind: String,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+struct DayOneLocation {
+    place_name: Option<String>,
+    locality_name: Option<String>,
+    administrative_area: Option<String>,
+    country: Option<String>,
+    latitude: Option<f64>,
+    longitude: Option<f64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+struct DayOneWeather {
+    conditions_description: Option<String>,
+    temperature_celsius: Option<f64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+struct DayOneEntry {
+    uuid: String,
+    /// RFC 3339, in UTC
+    creation_date: String,
+    modified_date: Option<String>,
+    text: String,
+    tags: Vec<String>,
+    starred: bool,
+    location: Option<DayOneLocation>,
+    weather: Option<DayOneWeather>,
+    photos: Vec<DayOneMedia>,
+    videos: Vec<DayOneMedia>,
+    audios: Vec<DayOneMedia>,
+    pdf_attachments: Vec<DayOneMedia>,
+}
+
+/// Where the media files of a Day One export are: its zip's entries, or the
+/// folders next to its JSON file
+enum DayOneFiles {
+    Zip(HashMap<String, Vec<u8>>),
+    Dir(PathBuf),
+}
+
+/// Folders Day One exports media into
+const DAYONE_MEDIA_DIRS: &[&str] = &["photos", "videos", "audios", "pdfs"];
+
+impl DayOneFiles {
+    /// The file of `media`, as `(name, data)`
+    fn get(&self, media: &DayOneMedia) -> Option<(String, Vec<u8>)> {
+        let name = format!("{}.{}", media.md5, media.kind);
+        let data = match self {
+            DayOneFiles::Zip(files) => files.get(&name).cloned(),
+            DayOneFiles::Dir(dir) => {
+                DAYONE_MEDIA_DIRS.iter().find_map(|media_dir| fs::read(dir.join(media_dir).join(&name)).ok())
+            }
+        };
+        data.map(|data| (name, data))
+    }
+}
+
+/// Frontmatter of a Day One entry: dates, tags, location and weather
+fn dayone_fields(entry: &DayOneEntry) -> Map<String, Value> {
+    let mut fields = Map::new();
+    fields.insert("created".to_string(), Value::String(entry.creation_date.clone()));
+    if let Some(modified) = &entry.modified_date {
+        fields.insert("updated".to_string(), Value::String(modified.clone()));
+    }
+    if !entry.tags.is_empty() {
+        fields.insert("tags".to_string(), Value::from(entry.tags.clone()));
+    }
+    if entry.starred {
+        fields.insert("starred".to_string(), Value::Bool(true));
+    }
+    if let Some(location) = &entry.location {
+        let parts = [
+            &location.place_name,
+            &location.locality_name,
+            &location.administrative_area,
+            &location.country,
+        ];
+        let mut names: Vec<&str> = Vec::new();
+        for name in parts.into_iter().flatten().map(|name| name.trim()) {
+            if !name.is_empty() && !names.contains(&name) {
+                names.push(name);
+            }
+        }
+        if !names.is_empty() {
+            fields.insert("location".to_string(), Value::String(names.join(", ")));
+        }
+        if let (Some(latitude), Some(longitude)) = (location.latitude, location.longitude) {
+            fields.insert("coordinates".to_string(), Value::from(vec![latitude, longitude]));
+        }
+    }
+    if let Some(weather) = &entry.weather {
+        let temperature = weather.temperature_celsius.map(|celsius| format!("{:.0} °C", celsius));
+        let parts: Vec<String> = weather.conditions_description.clone().into_iter().chain(temperature).collect();
+        if !parts.is_empty() {
+            fields.insert("weather".to_string(), Value::String(parts.join(", ")));
+        }
+    }
+    fields
+}
+
+/// Save the media of `entry` under `dest_dir/attachments`, returning their
+/// paths relative to `dest_dir` by identifier
+fn save_dayone_media(
+    entry: &DayOneEntry,
+    files: &DayOneFiles,
+    dest_dir: &Path,
+    summary: &mut ImportSummary,
+) -> Result<HashMap<String, PathBuf>, String> {
+    let attachments_dir = dest_dir.join(ATTACHMENTS_DIR);
+    let mut saved = HashMap::new();
+    let media = entry.photos.iter().chain(&entry.videos).chain(&entry.audios).chain(&entry.pdf_attachments);
+    for media in media {
+        let Some((name, data)) = files.get(media) else {
+            continue;
+        };
+        fs::create_dir_all(&attachments_dir).map_err(|e| format!("Failed to create directory: {}", e))?;
+        // Reused when an export is imported again
+        let path = match attachments::find_identical(&attachments_dir, &data) {
+            Some(path) => path,
+            None => {
+                let path = unique_path(&attachments_dir, &name);
+                fs::write(&path, &data).map_err(|e| format!("Failed to save attachment {}: {}", name, e))?;
+                summary.attachments += 1;
+                path
+            }
+        };
+        let relative = Path::new(ATTACHMENTS_DIR).join(path.file_name().unwrap_or_default());
+        saved.insert(media.identifier.clone(), relative);
+    }
+    Ok(saved)
+}
+
+/// Write the note of one Day One entry, at the daily note path of its day
+fn write_dayone_entry(
+    entry: &DayOneEntry,
+    files: &DayOneFiles,
+    dest_dir: &Path,
+    daily_notes_format: &str,
+    summary: &mut ImportSummary,
+) -> Result<PathBuf, String> {
+    static MOMENT: OnceLock<Regex> = OnceLock::new();
+    let moment_regex = MOMENT.get_or_init(|| {
+        Regex::new(r"dayone-moment:(?://|/(?:video|audio|pdfattachment)/)([A-Za-z0-9]+)")
+            .expect("moment regex is valid")
+    });
+    let created = chrono::DateTime::parse_from_rfc3339(&entry.creation_date)
+        .map_err(|e| format!("Invalid creation date {}: {}", entry.creation_date, e))?
+        .with_timezone(&chrono::Local);
+    let relative = templates::render(daily_notes_format, &HashMap::new(), "", created.date_naive(), created.time());
+    let path = links::normalize_path(&dest_dir.join(relative));
+    let note_dir = path.parent().unwrap_or(dest_dir).to_path_buf();
+
+    let media = save_dayone_media(entry, files, dest_dir, summary)?;
+    let body = moment_regex.replace_all(&entry.text, |captures: &regex::Captures| match media.get(&captures[1]) {
+        Some(saved) => link_target(&rename::relative_path(&note_dir, &dest_dir.join(saved))),
+        None => captures[0].to_string(),
+    });
+    let content = frontmatter::replace(&format!("{}\n", body.trim()), &dayone_fields(entry))?;
+
+    // Days with several entries get a note per entry
+    fs::create_dir_all(&note_dir).map_err(|e| format!("Failed to create directory: {}", e))?;
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let path = unique_path(&note_dir, &name);
+    crate::write_atomic(&path, content.as_bytes()).map_err(|e| format!("Failed to write file: {}", e))?;
+    Ok(path)
+}
+
+/// Import a Day One JSON export, zipped or not, into `dest_dir`: one note
+/// per entry, placed as the vault's daily notes are (by the entry's date in
+/// the local time zone), with its dates, tags, location and weather in the
+/// frontmatter and its photos and other media saved under `attachments/`
+#[tauri::command(async)]
+pub fn import_dayone(
+    file: String,
+    dest_dir: String,
+    registry: tauri::State<'_, NoteIndexRegistry>,
+    settings: tauri::State<'_, Mutex<SettingsStore>>,
+) -> Result<ImportSummary, CommandError> {
+    let dest_dir = PathBuf::from(&dest_dir);
+    settings::check_writable(&settings, &dest_dir)?;
+    fs::create_dir_all(&dest_dir).map_err(|e| format!("Failed to create directory: {}", e))?;
+    let root = registry.for_path(&dest_dir).map_or(dest_dir.clone(), |index| index.root().to_path_buf());
+    let daily_notes_format = vault_config::resolve(&root, &settings)?.daily_notes_format;
+    let data = fs::read(&file).map_err(|e| format!("Failed to open file: {}", e))?;
+    let mut summary = ImportSummary::default();
+
+    // A zip has a JSON file per journal, and the media in folders
+    let (journals, files) = if data.starts_with(b"PK") {
+        let mut entries = Vec::new();
+        read_archive(Cursor::new(data), 0, &mut entries, &mut summary)?;
+        let (journals, media): (Vec<ArchiveEntry>, Vec<ArchiveEntry>) =
+            entries.into_iter().partition(|entry| extension_of(&entry.path) == "json");
+        let media = media.into_iter().filter_map(|entry| {
+            let name = entry.path.file_name()?.to_string_lossy().to_string();
+            Some((name, entry.data))
+        });
+        let journals = journals.into_iter().map(|entry| (entry.path.to_string_lossy().to_string(), entry.data));
+        (journals.collect::<Vec<_>>(), DayOneFiles::Zip(media.collect()))
+    } else {
+        let dir = Path::new(&file).parent().unwrap_or(Path::new("")).to_path_buf();
+        (vec![(file.clone(), data)], DayOneFiles::Dir(dir))
+    };
+
+    for (name, journal) in journals {
+        let journal: Value =
+            serde_json::from_slice(&journal).map_err(|e| format!("Invalid Day One export {}: {}", name, e))?;
+        let entries = journal
+            .get("entries")
+            .and_then(Value::as_array)
+            .ok_or_else(|| format!("Not a Day One export: {}", name))?;
+        for entry in entries {
+            let entry = match DayOneEntry::deserialize(entry) {
+                Ok(entry) => entry,
+                Err(e) => {
+                    summary.failed.push(ImportFailure {
+                        item: entry.get("uuid").and_then(Value::as_str).unwrap_or("entry").to_string(),
+                        error: format!("Invalid entry: {}", e),
+                    });
+                    continue;
+                }
+            };
+            match write_dayone_entry(&entry, &files, &dest_dir, &daily_notes_format, &mut summary) {
+                Ok(path) => summary.imported.push(path.to_string_lossy().to_string()),
+                Err(error) => summary.failed.push(ImportFailure { item: entry.uuid, error }),
+            }
+        }
+    }
+    summary.imported.sort();
+    Ok(summary)
+}
@@ -0,0 +1,227 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager};
+
+/// Least time between two `job-progress` events of a job
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Error message of a job stopped with `cancel_job`
+pub const CANCELLED: &str = "Cancelled";
+
+#[derive(Debug, Clone, Serialize)]
+pub struct JobProgress {
+    pub id: u64,
+    pub kind: String,
+    /// Files scanned or processed so far
+    pub completed: usize,
+    /// `None` while the total isn't known yet, e.g. during a directory walk
+    pub total: Option<usize>,
+    /// 0 to 100, when the total is known
+    pub percent: Option<f64>,
+    /// File being worked on
+    pub current: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct JobPartial {
+    pub id: u64,
+    pub kind: String,
+    /// Part of the result, e.g. the files found since the last batch
+    pub items: serde_json::Value,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct JobDone {
+    pub id: u64,
+    pub kind: String,
+    /// What the equivalent direct command would have returned
+    pub result: Option<serde_json::Value>,
+    pub error: Option<String>,
+    pub cancelled: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct JobInfo {
+    pub id: u64,
+    pub kind: String,
+    /// Milliseconds since the job started
+    pub elapsed_ms: u64,
+}
+
+/// Progress reporting and cancellation handed to long operations. Direct
+/// commands run them with `Job::detached()`, which reports nothing and is
+/// never cancelled.
+pub struct Job {
+    id: u64,
+    kind: String,
+    app: Option<AppHandle>,
+    cancelled: Arc<AtomicBool>,
+    last_progress: Mutex<Option<Instant>>,
+}
+
+impl Job {
+    pub fn detached() -> Self {
+        Job {
+            id: 0,
+            kind: String::new(),
+            app: None,
+            cancelled: Arc::new(AtomicBool::new(false)),
+            last_progress: Mutex::new(None),
+        }
+    }
+
+    /// `Err(CANCELLED)` once `cancel_job` was called for this job, to bail
+    /// out with `?`
+    pub fn check(&self) -> Result<(), String> {
+        if self.cancelled.load(Ordering::Relaxed) {
+            return Err(CANCELLED.to_string());
+        }
+        Ok(())
+    }
+
+    /// Report progress, at most every `PROGRESS_INTERVAL` unless the job is
+    /// complete
+    pub fn progress(&self, completed: usize, total: Option<usize>, current: Option<&str>) {
+        let Some(app) = &self.app else {
+            return;
+        };
+        let finished = total.is_some_and(|total| completed >= total);
+        if let Ok(mut last) = self.last_progress.lock() {
+            if !finished && last.is_some_and(|at| at.elapsed() < PROGRESS_INTERVAL) {
+                return;
+            }
+            *last = Some(Instant::now());
+        }
+        let percent = total.map(|total| if total == 0 { 100.0 } else { completed as f64 * 100.0 / total as f64 });
+        let _ = app.emit(
+            "job-progress",
+            JobProgress {
+                id: self.id,
+                kind: self.kind.clone(),
+                completed,
+                total,
+                percent,
+                current: current.map(str::to_string),
+            },
+        );
+    }
+
+    /// Send part of the result ahead of `job-done` as a `job-partial` event,
+    /// so the frontend can show it while the job runs
+    pub fn partial<T: Serialize>(&self, items: &T) {
+        let Some(app) = &self.app else {
+            return;
+        };
+        let Ok(items) = serde_json::to_value(items) else {
+            return;
+        };
+        let _ = app.emit(
+            "job-partial",
+            JobPartial {
+                id: self.id,
+                kind: self.kind.clone(),
+                items,
+            },
+        );
+    }
+}
+
+struct RunningJob {
+    kind: String,
+    started: Instant,
+    cancelled: Arc<AtomicBool>,
+}
+
+/// Jobs currently running
+#[derive(Default)]
+pub struct JobRegistry {
+    next_id: AtomicU64,
+    running: Mutex<HashMap<u64, RunningJob>>,
+}
+
+/// Run `work` on a background thread as a job of `kind`, returning its id.
+/// The outcome is emitted as `job-done`.
+pub fn spawn<T, F>(app: &AppHandle, kind: &str, work: F) -> Result<u64, String>
+where
+    T: Serialize,
+    F: FnOnce(&Job) -> Result<T, String> + Send + 'static,
+{
+    let registry = app.state::<JobRegistry>();
+    let id = registry.next_id.fetch_add(1, Ordering::Relaxed) + 1;
+    let cancelled = Arc::new(AtomicBool::new(false));
+    registry.running.lock().map_err(|e| format!("Lock error: {}", e))?.insert(
+        id,
+        RunningJob {
+            kind: kind.to_string(),
+            started: Instant::now(),
+            cancelled: Arc::clone(&cancelled),
+        },
+    );
+
+    let job = Job {
+        id,
+        kind: kind.to_string(),
+        app: Some(app.clone()),
+        cancelled,
+        last_progress: Mutex::new(None),
+    };
+    let app = app.clone();
+    thread::spawn(move || {
+        let outcome = work(&job).and_then(|value| {
+            serde_json::to_value(value).map_err(|e| format!("Failed to serialize result: {}", e))
+        });
+        if let Ok(mut running) = app.state::<JobRegistry>().running.lock() {
+            running.remove(&id);
+        }
+        let cancelled = job.cancelled.load(Ordering::Relaxed);
+        let (result, error) = match outcome {
+            Ok(value) => (Some(value), None),
+            Err(e) => (None, Some(e)),
+        };
+        let _ = app.emit(
+            "job-done",
+            JobDone {
+                id,
+                kind: job.kind.clone(),
+                result,
+                error,
+                cancelled,
+            },
+        );
+    });
+    Ok(id)
+}
+
+/// Ask the job `id` to stop. It finishes with a `job-done` event whose
+/// `cancelled` is set. Returns false if no such job is running.
+#[tauri::command]
+pub fn cancel_job(id: u64, registry: tauri::State<'_, JobRegistry>) -> Result<bool, String> {
+    let running = registry.running.lock().map_err(|e| format!("Lock error: {}", e))?;
+    match running.get(&id) {
+        Some(job) => {
+            job.cancelled.store(true, Ordering::Relaxed);
+            Ok(true)
+        }
+        None => Ok(false),
+    }
+}
+
+/// Jobs that are still running, oldest first
+#[tauri::command]
+pub fn list_jobs(registry: tauri::State<'_, JobRegistry>) -> Result<Vec<JobInfo>, String> {
+    let running = registry.running.lock().map_err(|e| format!("Lock error: {}", e))?;
+    let mut jobs: Vec<JobInfo> = running
+        .iter()
+        .map(|(id, job)| JobInfo {
+            id: *id,
+            kind: job.kind.clone(),
+            elapsed_ms: job.started.elapsed().as_millis() as u64,
+        })
+        .collect();
+    jobs.sort_by_key(|job| job.id);
+    Ok(jobs)
+}
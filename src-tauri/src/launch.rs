@@ -0,0 +1,102 @@
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::sandbox::Sandbox;
+
+/// A file or folder the OS asked the app to open: a double-clicked `.md`
+/// file, a path on the command line, or one dropped on the dock icon
+#[derive(Debug, Clone, Serialize)]
+pub struct ExternalFile {
+    pub path: String,
+    /// Folders are opened as vaults
+    pub is_dir: bool,
+}
+
+/// Paths received before the frontend was ready to handle them
+#[derive(Default)]
+pub struct ExternalOpens {
+    ready: bool,
+    pending: Vec<ExternalFile>,
+}
+
+/// The files and folders named in command-line `args`, resolved against
+/// `cwd`. The first argument is the program; flags are skipped.
+pub fn paths_from_args(args: &[String], cwd: &Path) -> Vec<PathBuf> {
+    args.iter()
+        .skip(1)
+        .filter(|arg| !arg.starts_with('-'))
+        .map(|arg| cwd.join(arg))
+        .filter(|path| path.exists())
+        .map(|path| path.canonicalize().unwrap_or(path))
+        .collect()
+}
+
+/// Bring the main window to the front, e.g. when a second launch handed
+/// its files over to this one
+pub fn focus_main_window(app: &AppHandle) {
+    if let Some(window) = app.get_webview_window("main") {
+        let _ = window.show();
+        let _ = window.unminimize();
+        let _ = window.set_focus();
+    }
+}
+
+/// Open `paths` in the frontend with an `open-external-file` event each, or
+/// queue them until it asks for them with `take_external_files`
+pub fn open_paths(app: &AppHandle, paths: Vec<PathBuf>) {
+    if paths.is_empty() {
+        return;
+    }
+    let state = app.state::<Mutex<ExternalOpens>>();
+    let Ok(mut opens) = state.lock() else {
+        return;
+    };
+    let sandbox = app.state::<Sandbox>();
+    for path in paths {
+        // Opened by the user through the OS, so readable wherever it is
+        if path.is_dir() {
+            sandbox.allow_vault(&path);
+        } else {
+            sandbox.allow_file(&path);
+        }
+        let file = ExternalFile {
+            is_dir: path.is_dir(),
+            path: path.to_string_lossy().to_string(),
+        };
+        if opens.ready {
+            let _ = app.emit("open-external-file", file);
+        } else {
+            opens.pending.push(file);
+        }
+    }
+}
+
+/// Files the app was asked to open before the frontend was listening. Call
+/// once the `open-external-file` listener is registered; later requests
+/// arrive as events.
+#[tauri::command]
+pub fn take_external_files(state: tauri::State<'_, Mutex<ExternalOpens>>) -> Result<Vec<ExternalFile>, String> {
+    let mut opens = state.lock().map_err(|e| format!("Lock error: {}", e))?;
+    opens.ready = true;
+    Ok(std::mem::take(&mut opens.pending))
+}
+
+/// Handle app lifecycle events. macOS hands over files opened from Finder
+/// or dropped on the dock icon as an event rather than as arguments.
+pub fn on_run_event(app: &AppHandle, event: tauri::RunEvent) {
+    #[cfg(any(target_os = "macos", target_os = "ios"))]
+    match event {
+        tauri::RunEvent::Opened { urls } => {
+            let paths = urls.into_iter().filter_map(|url| url.to_file_path().ok()).collect();
+            open_paths(app, paths);
+        }
+        // Clicking the dock icon brings back a window hidden to the tray
+        #[cfg(target_os = "macos")]
+        tauri::RunEvent::Reopen { .. } => focus_main_window(app),
+        _ => {}
+    }
+    #[cfg(not(any(target_os = "macos", target_os = "ios")))]
+    let _ = (app, event);
+}
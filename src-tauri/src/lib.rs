@@ -1,18 +1,119 @@
-use notify_debouncer_mini::{new_debouncer, DebouncedEventKind};
+mod attachments;
+mod autocommit;
+mod backup;
+mod blocks;
+mod bundle;
+mod capture_server;
+mod clipboard;
+mod clipper;
+mod completions;
+mod conflicts;
+mod daily;
+mod dates;
+mod deep_link;
+mod desktop;
+mod diff;
+mod encoding;
+mod encryption;
+mod error;
+mod export;
+mod file_range;
+mod format;
+mod frontmatter;
+mod git;
+mod graph;
+mod history;
+mod http_server;
+mod ics;
+mod ignore_rules;
+mod import;
+mod jobs;
+mod launch;
+mod link_style;
+mod links;
+mod lint;
+mod markdown;
+mod mentions;
+mod merge;
+mod metadata_cache;
+mod note_index;
+mod note_names;
+mod opml;
+mod pins;
+mod preview_server;
+mod query;
+mod recent;
+mod reminders;
+mod rename;
+mod render;
+mod sandbox;
+mod search;
+mod search_index;
+mod session;
+mod settings;
+mod spellcheck;
+mod split;
+mod stats;
+mod sync;
+mod tables;
+mod tags;
+mod tasks;
+mod templates;
+mod toc;
+mod tray;
+mod vault_config;
+mod vault_flavor;
+mod vault_index;
+mod watcher;
+
+use autocommit::AutocommitState;
+use backup::Backups;
+use capture_server::CaptureServer;
+use deep_link::DeepLinks;
+use encoding::{LineEnding, TextFile};
+use encryption::EncryptionKeys;
+use error::{CommandError, ConflictError, TooLargeError};
+use ignore::WalkState;
+use ignore_rules::IgnoreMatcher;
+use jobs::{Job, JobRegistry};
+use launch::ExternalOpens;
+use metadata_cache::MetadataCache;
+use note_index::NoteIndexRegistry;
+use preview_server::PreviewServer;
+use recent::RecentStore;
+use reminders::Reminders;
+use sandbox::Sandbox;
+use search::NotePathCache;
+use search_index::IndexRegistry;
 use serde::{Deserialize, Serialize};
+use session::SessionStore;
+use settings::SettingsStore;
+use spellcheck::Spellchecker;
+use sync::SyncRegistry;
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
 use std::fs;
-use std::path::PathBuf;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Mutex;
-use std::time::Duration;
-use tauri::{AppHandle, Emitter};
-use walkdir::WalkDir;
+use std::time::UNIX_EPOCH;
+use tauri::{Emitter, Manager};
+use tauri_plugin_deep_link::DeepLinkExt;
+use watcher::WatcherState;
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FileEntry {
     pub name: String,
     pub path: String,
     pub is_dir: bool,
     pub is_markdown: bool,
+    /// Size in bytes
+    pub size: u64,
+    /// Timestamps in milliseconds since the Unix epoch, when the platform reports them
+    pub modified: Option<u64>,
+    pub created: Option<u64>,
+    pub readonly: bool,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -21,235 +122,1067 @@ pub struct DirectoryContents {
     pub entries: Vec<FileEntry>,
 }
 
-#[derive(Debug, Serialize, Clone)]
-pub struct FileChangeEvent {
-    pub path: String,
-    pub kind: String,
+#[derive(Debug, Serialize)]
+pub struct TreeNode {
+    pub entry: FileEntry,
+    /// `None` for files and for directories beyond the requested depth
+    pub children: Option<Vec<TreeNode>>,
 }
 
-// Global state for the file watcher
-struct WatcherState {
-    watcher: Option<notify_debouncer_mini::Debouncer<notify::RecommendedWatcher>>,
-    watched_path: Option<String>,
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SortKey {
+    #[default]
+    Name,
+    Mtime,
+    Size,
 }
 
-/// Read the contents of a text file
-#[tauri::command]
-fn read_text_file(path: String) -> Result<String, String> {
-    fs::read_to_string(&path).map_err(|e| format!("Failed to read file: {}", e))
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SortDirection {
+    #[default]
+    Asc,
+    Desc,
 }
 
-/// Write content to a text file
-#[tauri::command]
-fn write_text_file(path: String, content: String) -> Result<(), String> {
-    // Ensure parent directory exists
-    if let Some(parent) = PathBuf::from(&path).parent() {
-        fs::create_dir_all(parent).map_err(|e| format!("Failed to create directory: {}", e))?;
+/// Options for `list_dir`; every field is optional on the frontend side
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct ListOptions {
+    pub sort_by: SortKey,
+    pub direction: SortDirection,
+    /// Keep directories grouped before files regardless of the sort key
+    pub dirs_first: bool,
+    pub show_hidden: bool,
+    /// Only list files with one of these extensions (case-insensitive, without
+    /// the leading dot). Directories are always listed.
+    pub extensions: Option<Vec<String>>,
+    /// Extra gitignore-style patterns to hide
+    pub ignore_globs: Vec<String>,
+    /// Hide entries matched by `.gitignore` files in this or parent directories
+    pub respect_gitignore: bool,
+}
+
+impl Default for ListOptions {
+    fn default() -> Self {
+        ListOptions {
+            sort_by: SortKey::Name,
+            direction: SortDirection::Asc,
+            dirs_first: true,
+            show_hidden: false,
+            extensions: None,
+            ignore_globs: Vec::new(),
+            respect_gitignore: true,
+        }
     }
-    fs::write(&path, content).map_err(|e| format!("Failed to write file: {}", e))
 }
 
-/// List contents of a directory (non-recursive, sorted)
-#[tauri::command]
-fn list_dir(path: String) -> Result<DirectoryContents, String> {
-    let path_buf = PathBuf::from(&path);
+/// Folder in each vault holding the app's own data, such as file history
+const DATA_DIR: &str = ".readmark";
+
+/// Notes per `job-partial` event of a `list_md_files` scan
+const SCAN_BATCH_SIZE: usize = 500;
+
+#[derive(Debug, Serialize)]
+pub struct WriteResult {
+    /// Modification time of the written file, in milliseconds since the Unix epoch
+    pub mtime: Option<u64>,
+    /// SHA-256 of the written content, hex encoded
+    pub hash: String,
+}
+
+/// Read the contents of a text file, detecting its encoding and line
+/// endings. Files over the `max_read_size_mb` setting are rejected with a
+/// `TooLargeError`. Encrypted notes are decrypted, failing with a
+/// `NoteLocked` error while their vault is locked.
+#[tauri::command(async)]
+fn read_text_file(
+    path: String,
+    settings: tauri::State<'_, Mutex<SettingsStore>>,
+    keys: tauri::State<'_, EncryptionKeys>,
+) -> Result<TextFile, CommandError> {
+    if let Some(limit) = settings::max_read_size(&settings)? {
+        let size = fs::metadata(&path).map_err(|e| format!("Failed to read file: {}", e))?.len();
+        if size > limit {
+            return Err(TooLargeError { path, size, limit }.into());
+        }
+    }
+    let mtime = fs::metadata(&path).ok().as_ref().and_then(mtime_millis);
+    let bytes = fs::read(&path).map_err(|e| format!("Failed to read file: {}", e))?;
+    let encrypted = encryption::is_encrypted(&bytes);
+    // Of the bytes as they are on disk, which is what conflicts are checked against
+    let hash = content_hash(&bytes);
+    let bytes = keys.decrypt(Path::new(&path), bytes)?;
+    Ok(TextFile {
+        encrypted,
+        hash: Some(hash),
+        mtime,
+        ..encoding::decode(&bytes)
+    })
+}
+
+/// Atomically replace `path` with `contents`.
+///
+/// The data is written to a temporary file in the same directory, synced to
+/// disk and renamed over the target, so readers never observe a partially
+/// written file. Permissions of an existing target are carried over, and
+/// symlinks are written through rather than replaced.
+fn write_atomic(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let resolved = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    let path = resolved.as_path();
+    let parent = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
     
-    if !path_buf.exists() {
-        return Err(format!("Directory does not exist: {}", path));
+    let mut temp = tempfile::Builder::new()
+        .prefix(".readmark-")
+        .suffix(".tmp")
+        .tempfile_in(parent)?;
+    temp.write_all(contents)?;
+    temp.as_file().sync_all()?;
+    
+    if let Ok(metadata) = fs::metadata(path) {
+        temp.as_file().set_permissions(metadata.permissions())?;
+    }
+    
+    temp.persist(path).map_err(|e| e.error)?;
+    
+    // Make the rename itself durable; not supported for directories on Windows
+    #[cfg(unix)]
+    fs::File::open(parent)?.sync_all()?;
+    
+    Ok(())
+}
+
+/// Hex-encoded SHA-256 of `bytes`
+fn content_hash(bytes: &[u8]) -> String {
+    Sha256::digest(bytes)
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// Modification time in milliseconds since the Unix epoch
+fn mtime_millis(metadata: &fs::Metadata) -> Option<u64> {
+    metadata
+        .modified()
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_millis() as u64)
+}
+
+/// Creation time in milliseconds since the Unix epoch
+fn ctime_millis(metadata: &fs::Metadata) -> Option<u64> {
+    metadata
+        .created()
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_millis() as u64)
+}
+
+/// Compare the file on disk against the state the frontend last saw
+fn check_for_conflict(
+    path: &Path,
+    expected_mtime: Option<u64>,
+    expected_hash: Option<&str>,
+) -> Result<(), ConflictError> {
+    if expected_mtime.is_none() && expected_hash.is_none() {
+        return Ok(());
     }
     
-    if !path_buf.is_dir() {
-        return Err(format!("Path is not a directory: {}", path));
+    let current = fs::read(path).ok();
+    let current_mtime = fs::metadata(path).ok().as_ref().and_then(mtime_millis);
+    let current_hash = current.as_deref().map(content_hash);
+    
+    let mtime_changed = expected_mtime.is_some() && expected_mtime != current_mtime;
+    let hash_changed = expected_hash.is_some() && expected_hash != current_hash.as_deref();
+    
+    // When a hash is given it is authoritative: sync clients often touch
+    // mtimes without changing content
+    let conflict = match expected_hash {
+        Some(_) => hash_changed,
+        None => mtime_changed,
+    };
+    
+    if !conflict {
+        return Ok(());
     }
     
-    let mut entries: Vec<FileEntry> = Vec::new();
+    Err(ConflictError {
+        path: path.to_string_lossy().to_string(),
+        current_mtime,
+        current_hash,
+        // Never sent in the clear for encrypted notes
+        current_content: current
+            .filter(|bytes| !encryption::is_encrypted(bytes))
+            .map(|bytes| encoding::decode(&bytes).content),
+    })
+}
+
+/// Write content to a text file.
+///
+/// The file keeps the encoding, byte order mark and line endings it has on
+/// disk unless `encoding` (a label such as `windows-1252`) or
+/// `line_ending` is given; new files are UTF-8 with `\n`. When
+/// `expected_mtime` or `expected_hash` is given, the write is refused
+/// with a conflict error if the file on disk no longer matches. Both the
+/// replaced and the new contents are kept in the file's history. An
+/// encrypted note stays encrypted, so its vault must be unlocked.
+#[tauri::command(async)]
+#[allow(clippy::too_many_arguments)] // Mostly state injected by tauri
+fn write_text_file(
+    path: String,
+    content: String,
+    expected_mtime: Option<u64>,
+    expected_hash: Option<String>,
+    encoding: Option<String>,
+    line_ending: Option<LineEnding>,
+    registry: tauri::State<'_, NoteIndexRegistry>,
+    settings: tauri::State<'_, Mutex<SettingsStore>>,
+    autocommit: tauri::State<'_, AutocommitState>,
+    cache: tauri::State<'_, MetadataCache>,
+    keys: tauri::State<'_, EncryptionKeys>,
+) -> Result<WriteResult, CommandError> {
+    let path_buf = PathBuf::from(&path);
+    settings::check_writable(&settings, &path_buf)?;
     
-    let read_dir = fs::read_dir(&path_buf).map_err(|e| format!("Failed to read directory: {}", e))?;
+    check_for_conflict(&path_buf, expected_mtime, expected_hash.as_deref())?;
+    let previous = fs::read(&path_buf).ok();
+    let encrypted = previous.as_deref().is_some_and(encryption::is_encrypted);
+    let plain_previous = match &previous {
+        Some(bytes) if encrypted => Some(keys.decrypt(&path_buf, bytes.clone())?),
+        _ => None,
+    };
+    let mut format = plain_previous
+        .as_deref()
+        .or(previous.as_deref())
+        .map(encoding::format_of)
+        .unwrap_or_default();
+    if let Some(label) = &encoding {
+        format.encoding = encoding::encoding_for_label(label)?;
+    }
+    if let Some(line_ending) = line_ending {
+        format.line_ending = line_ending;
+    }
+    let mut bytes = encoding::encode(&content, format)?;
+    if encrypted {
+        bytes = keys.encrypt(&path_buf, &bytes)?;
+    }
     
+    // Ensure parent directory exists
+    let created = first_missing_ancestor(&path_buf);
+    if let Some(parent) = path_buf.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("Failed to create directory: {}", e))?;
+    }
+    write_atomic(&path_buf, &bytes).map_err(|e| format!("Failed to write file: {}", e))?;
+    cache.invalidate(created.as_deref().unwrap_or(&path_buf));
+    // The write itself succeeded, so a history failure isn't reported as one
+    if let Err(e) = history::record_write(&path_buf, previous.as_deref(), &bytes, &registry, &settings) {
+        eprintln!("History error for {}: {}", path, e);
+    }
+    autocommit.note_write(&registry.root_for(&path_buf));
+    
+    Ok(WriteResult {
+        mtime: fs::metadata(&path_buf).ok().as_ref().and_then(mtime_millis),
+        hash: content_hash(&bytes),
+    })
+}
+
+/// Bring the file history, the vault's note index and the listing cache up
+/// to date after a command replaced `previous` with `content` at `path`
+fn note_written(
+    path: &Path,
+    previous: Option<&[u8]>,
+    content: &[u8],
+    registry: &NoteIndexRegistry,
+    settings: &Mutex<SettingsStore>,
+    cache: &MetadataCache,
+) {
+    // The write itself succeeded, so a history failure isn't reported as one
+    if let Err(e) = history::record_write(path, previous, content, registry, settings) {
+        eprintln!("History error for {}: {}", path.display(), e);
+    }
+    if let Some(index) = registry.for_path(path) {
+        index.update_path(path);
+    }
+    cache.invalidate(path);
+}
+
+/// Atomically write `content` (the bytes for disk) over `previous` at
+/// `path`, for commands that edit a note, then update what `note_written`
+/// does
+fn write_note(
+    path: &Path,
+    previous: Option<&[u8]>,
+    content: &[u8],
+    registry: &NoteIndexRegistry,
+    settings: &Mutex<SettingsStore>,
+    cache: &MetadataCache,
+) -> Result<WriteResult, String> {
+    write_atomic(path, content).map_err(|e| format!("Failed to write file: {}", e))?;
+    note_written(path, previous, content, registry, settings, cache);
+    Ok(WriteResult {
+        mtime: fs::metadata(path).ok().as_ref().and_then(mtime_millis),
+        hash: content_hash(content),
+    })
+}
+
+/// The bytes of the note at `path` and their text, decoded as
+/// `read_text_file` does, for commands that edit it in place and write it
+/// back with `encoding::format_of` the bytes. Encrypted notes are refused.
+fn read_note(path: &Path) -> Result<(Vec<u8>, String), String> {
+    let bytes = fs::read(path).map_err(|e| format!("Failed to read file: {}", e))?;
+    if encryption::is_encrypted(&bytes) {
+        return Err(format!("{} is encrypted; unlock it in the editor to change it", path.display()));
+    }
+    let content = encoding::decode(&bytes).content;
+    Ok((bytes, content))
+}
+
+/// The outermost folder above `path` that doesn't exist yet, i.e. the first
+/// one creating `path`'s parents would add
+fn first_missing_ancestor(path: &Path) -> Option<PathBuf> {
+    path.ancestors()
+        .skip(1)
+        .take_while(|ancestor| !ancestor.as_os_str().is_empty() && !ancestor.exists())
+        .last()
+        .map(Path::to_path_buf)
+}
+
+/// Every entry of a single directory, unfiltered and unsorted
+fn read_dir_raw(path: &Path, note_extensions: &[String]) -> Result<Vec<FileEntry>, String> {
+    let read_dir = fs::read_dir(path).map_err(|e| format!("Failed to read directory: {}", e))?;
+    let mut entries = Vec::new();
     for entry in read_dir {
         let entry = entry.map_err(|e| format!("Failed to read entry: {}", e))?;
-        let file_name = entry.file_name().to_string_lossy().to_string();
+        entries.push(file_entry_for(&entry.path(), note_extensions));
+    }
+    Ok(entries)
+}
+
+/// Read the entries of a single directory, filtered and sorted per `options`
+fn read_dir_entries(
+    path: &Path,
+    options: &ListOptions,
+    note_extensions: &[String],
+    cache: &MetadataCache,
+) -> Result<Vec<FileEntry>, String> {
+    let extensions: Option<Vec<String>> = options.extensions.as_ref().map(|exts| {
+        exts.iter()
+            .map(|e| e.trim_start_matches('.').to_lowercase())
+            .collect()
+    });
+    
+    let ignore = IgnoreMatcher::new(path, &options.ignore_globs, options.respect_gitignore)?;
+    
+    let mut entries: Vec<FileEntry> = Vec::new();
+    
+    let listing = cache.listing(path, note_extensions, || read_dir_raw(path, note_extensions))?;
+    
+    for file_entry in listing.iter() {
+        let file_name = &file_entry.name;
         
         // Skip hidden files
-        if file_name.starts_with('.') {
+        if !options.show_hidden && file_name.starts_with('.') {
             continue;
         }
         
-        let file_path = entry.path();
-        let is_dir = file_path.is_dir();
-        let is_markdown = !is_dir && file_name.to_lowercase().ends_with(".md");
+        if ignore.is_ignored(Path::new(&file_entry.path), file_entry.is_dir) {
+            continue;
+        }
         
-        entries.push(FileEntry {
-            name: file_name,
-            path: file_path.to_string_lossy().to_string(),
-            is_dir,
-            is_markdown,
-        });
+        if let Some(extensions) = &extensions {
+            let extension = Path::new(file_name)
+                .extension()
+                .map(|e| e.to_string_lossy().to_lowercase())
+                .unwrap_or_default();
+            if !file_entry.is_dir && !extensions.contains(&extension) {
+                continue;
+            }
+        }
+        
+        entries.push(file_entry.clone());
     }
     
-    // Sort: directories first, then files, alphabetically
+    // Sort: directories first (unless disabled), then by the chosen key
     entries.sort_by(|a, b| {
-        match (a.is_dir, b.is_dir) {
-            (true, false) => std::cmp::Ordering::Less,
-            (false, true) => std::cmp::Ordering::Greater,
-            _ => a.name.to_lowercase().cmp(&b.name.to_lowercase()),
+        if options.dirs_first && a.is_dir != b.is_dir {
+            return b.is_dir.cmp(&a.is_dir);
+        }
+        let ordering = match options.sort_by {
+            SortKey::Name => a.name.to_lowercase().cmp(&b.name.to_lowercase()),
+            SortKey::Mtime => a.modified.cmp(&b.modified),
+            SortKey::Size => a.size.cmp(&b.size),
+        }
+        .then_with(|| a.name.to_lowercase().cmp(&b.name.to_lowercase()));
+        match options.direction {
+            SortDirection::Asc => ordering,
+            SortDirection::Desc => ordering.reverse(),
         }
     });
     
+    Ok(entries)
+}
+
+/// Validate that `path` is an existing directory
+fn ensure_dir(path: &Path) -> Result<(), String> {
+    if !path.exists() {
+        return Err(format!("Directory does not exist: {}", path.display()));
+    }
+    
+    if !path.is_dir() {
+        return Err(format!("Path is not a directory: {}", path.display()));
+    }
+    
+    Ok(())
+}
+
+/// List contents of a directory (non-recursive, sorted)
+#[tauri::command(async)]
+fn list_dir(
+    path: String,
+    options: Option<ListOptions>,
+    settings: tauri::State<'_, Mutex<SettingsStore>>,
+    cache: tauri::State<'_, MetadataCache>,
+) -> Result<DirectoryContents, String> {
+    let mut options = options.unwrap_or_default();
+    options.ignore_globs = settings::ignore_globs(&settings, &options.ignore_globs)?;
+    let note_extensions = settings::note_extensions(&settings)?;
+    let path_buf = PathBuf::from(&path);
+    
+    ensure_dir(&path_buf)?;
+    
+    let entries = read_dir_entries(&path_buf, &options, &note_extensions, &cache)?;
+    
     Ok(DirectoryContents {
         path,
         entries,
     })
 }
 
-/// Recursively list all markdown files in a directory
-#[tauri::command]
-fn list_md_files(path: String) -> Result<Vec<FileEntry>, String> {
+fn build_tree(
+    path: &Path,
+    depth: usize,
+    max_depth: usize,
+    options: &ListOptions,
+    note_extensions: &[String],
+    cache: &MetadataCache,
+    visited: &mut HashSet<PathBuf>,
+) -> Result<Vec<TreeNode>, String> {
+    // Guard against symlink loops
+    let canonical = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    if !visited.insert(canonical) {
+        return Ok(Vec::new());
+    }
+    
+    let mut nodes = Vec::new();
+    for entry in read_dir_entries(path, options, note_extensions, cache)? {
+        let children = if entry.is_dir && depth < max_depth {
+            // Unreadable subdirectories show up empty rather than failing the whole tree
+            let child_path = Path::new(&entry.path);
+            let children = build_tree(child_path, depth + 1, max_depth, options, note_extensions, cache, visited);
+            Some(children.unwrap_or_default())
+        } else {
+            None
+        };
+        nodes.push(TreeNode { entry, children });
+    }
+    
+    Ok(nodes)
+}
+
+/// Recursively list a directory as a tree, down to `max_depth` levels below `path`
+#[tauri::command(async)]
+fn list_tree(
+    path: String,
+    max_depth: Option<usize>,
+    options: Option<ListOptions>,
+    settings: tauri::State<'_, Mutex<SettingsStore>>,
+    cache: tauri::State<'_, MetadataCache>,
+) -> Result<Vec<TreeNode>, String> {
+    let mut options = options.unwrap_or_default();
+    options.ignore_globs = settings::ignore_globs(&settings, &options.ignore_globs)?;
+    let note_extensions = settings::note_extensions(&settings)?;
     let path_buf = PathBuf::from(&path);
     
+    ensure_dir(&path_buf)?;
+    
+    let mut visited = HashSet::new();
+    build_tree(&path_buf, 1, max_depth.unwrap_or(usize::MAX), &options, &note_extensions, &cache, &mut visited)
+}
+
+
+/// Recursively list all notes (files with a configured note extension) in a directory.
+///
+/// Hidden entries, `.gitignore`d paths and `ignore_globs` are skipped.
+#[tauri::command(async)]
+fn list_md_files(
+    path: String,
+    ignore_globs: Option<Vec<String>>,
+    settings: tauri::State<'_, Mutex<SettingsStore>>,
+) -> Result<Vec<FileEntry>, String> {
+    let note_extensions = settings::note_extensions(&settings)?;
+    let ignore_globs = settings::ignore_globs(&settings, &ignore_globs.unwrap_or_default())?;
+    md_files(&path, &note_extensions, &ignore_globs, &Job::detached())
+}
+
+/// `list_md_files` as a job, reporting the files scanned. Notes are sent in
+/// batches as `job-partial` events while the scan runs; the sorted list is
+/// the result of its `job-done` event.
+#[tauri::command]
+fn start_list_md_files(
+    path: String,
+    ignore_globs: Option<Vec<String>>,
+    app: tauri::AppHandle,
+    settings: tauri::State<'_, Mutex<SettingsStore>>,
+) -> Result<u64, String> {
+    let note_extensions = settings::note_extensions(&settings)?;
+    let ignore_globs = settings::ignore_globs(&settings, &ignore_globs.unwrap_or_default())?;
+    jobs::spawn(&app, "list_md_files", move |job| {
+        md_files(&path, &note_extensions, &ignore_globs, job)
+    })
+}
+
+fn md_files(
+    path: &str,
+    note_extensions: &[String],
+    ignore_globs: &[String],
+    job: &Job,
+) -> Result<Vec<FileEntry>, String> {
+    let path_buf = PathBuf::from(path);
     if !path_buf.exists() {
         return Err(format!("Directory does not exist: {}", path));
     }
     
-    let mut entries: Vec<FileEntry> = Vec::new();
+    let found: Mutex<Vec<FileEntry>> = Mutex::new(Vec::new());
+    let scanned = AtomicUsize::new(0);
+    let walker = ignore_rules::walker(&path_buf, ignore_globs)?;
     
-    for entry in WalkDir::new(&path_buf)
-        .follow_links(true)
-        .into_iter()
-        .filter_map(|e| e.ok())
-    {
-        let file_path = entry.path();
-        let file_name = entry.file_name().to_string_lossy().to_string();
-        
-        // Skip hidden files and directories
-        if file_name.starts_with('.') {
-            continue;
-        }
-        
-        if file_path.is_file() && file_name.to_lowercase().ends_with(".md") {
-            entries.push(FileEntry {
-                name: file_name,
-                path: file_path.to_string_lossy().to_string(),
-                is_dir: false,
-                is_markdown: true,
-            });
-        }
-    }
+    // One visitor per walker thread, each collecting its own batch
+    walker.build_parallel().run(|| {
+        let mut batch = ScanBatch {
+            entries: Vec::new(),
+            found: &found,
+            job,
+        };
+        let scanned = &scanned;
+        Box::new(move |entry| {
+            if job.check().is_err() {
+                return WalkState::Quit;
+            }
+            let Ok(entry) = entry else {
+                return WalkState::Continue;
+            };
+            let file_path = entry.path();
+            let count = scanned.fetch_add(1, Ordering::Relaxed) + 1;
+            job.progress(count, None, Some(&file_path.to_string_lossy()));
+            
+            if file_path.is_file() && settings::has_note_extension(file_path, note_extensions) {
+                batch.entries.push(file_entry_for(file_path, note_extensions));
+                if batch.entries.len() >= SCAN_BATCH_SIZE {
+                    batch.flush();
+                }
+            }
+            WalkState::Continue
+        })
+    });
+    job.check()?;
     
+    let mut entries = found.into_inner().map_err(|e| format!("Lock error: {}", e))?;
     // Sort alphabetically by path
-    entries.sort_by(|a, b| a.path.to_lowercase().cmp(&b.path.to_lowercase()));
+    entries.sort_by_key(|a| a.path.to_lowercase());
     
     Ok(entries)
 }
 
+/// Notes found by one walker thread of `md_files` since its last flush
+struct ScanBatch<'a> {
+    entries: Vec<FileEntry>,
+    found: &'a Mutex<Vec<FileEntry>>,
+    job: &'a Job,
+}
+
+impl ScanBatch<'_> {
+    /// Send the batch as a `job-partial` event and add it to the result
+    fn flush(&mut self) {
+        if self.entries.is_empty() {
+            return;
+        }
+        self.job.partial(&self.entries);
+        if let Ok(mut found) = self.found.lock() {
+            found.append(&mut self.entries);
+        }
+    }
+}
+
+impl Drop for ScanBatch<'_> {
+    fn drop(&mut self) {
+        self.flush();
+    }
+}
+
 /// Check if a path exists
-#[tauri::command]
+#[tauri::command(async)]
 fn path_exists(path: String) -> bool {
     PathBuf::from(&path).exists()
 }
 
-/// Get file metadata
-#[tauri::command]
-fn get_file_metadata(path: String) -> Result<FileEntry, String> {
-    let path_buf = PathBuf::from(&path);
-    
-    if !path_buf.exists() {
-        return Err(format!("File does not exist: {}", path));
-    }
-    
-    let file_name = path_buf
+/// Build a `FileEntry` for an existing path
+fn file_entry_for(path: &Path, note_extensions: &[String]) -> FileEntry {
+    let file_name = path
         .file_name()
         .map(|n| n.to_string_lossy().to_string())
         .unwrap_or_default();
     
-    let is_dir = path_buf.is_dir();
-    let is_markdown = !is_dir && file_name.to_lowercase().ends_with(".md");
+    // Follows symlinks; a dangling link is reported with empty metadata
+    let metadata = fs::metadata(path).ok();
+    let is_dir = metadata.as_ref().is_some_and(|m| m.is_dir());
+    let is_markdown = !is_dir && settings::has_note_extension(path, note_extensions);
     
-    Ok(FileEntry {
+    FileEntry {
         name: file_name,
-        path,
+        path: path.to_string_lossy().to_string(),
         is_dir,
         is_markdown,
-    })
+        size: metadata.as_ref().map(|m| m.len()).unwrap_or(0),
+        modified: metadata.as_ref().and_then(mtime_millis),
+        created: metadata.as_ref().and_then(ctime_millis),
+        readonly: metadata.as_ref().is_some_and(|m| m.permissions().readonly()),
+    }
 }
 
-/// Start watching a directory for changes
-#[tauri::command]
-fn watch_directory(path: String, app: AppHandle, state: tauri::State<'_, Mutex<WatcherState>>) -> Result<(), String> {
-    let mut watcher_state = state.lock().map_err(|e| format!("Lock error: {}", e))?;
+/// Get file metadata
+#[tauri::command(async)]
+fn get_file_metadata(
+    path: String,
+    settings: tauri::State<'_, Mutex<SettingsStore>>,
+    cache: tauri::State<'_, MetadataCache>,
+) -> Result<FileEntry, String> {
+    let path_buf = PathBuf::from(&path);
+    let note_extensions = settings::note_extensions(&settings)?;
     
-    // Stop existing watcher if any
-    watcher_state.watcher = None;
-    watcher_state.watched_path = None;
+    let entry = cache.entry(&path_buf, &note_extensions, || file_entry_for(&path_buf, &note_extensions));
+    // A missing path comes back without metadata
+    if entry.modified.is_none() && !path_buf.exists() {
+        return Err(format!("File does not exist: {}", path));
+    }
     
+    Ok(FileEntry { path, ..entry })
+}
+
+/// Create a new directory (and any missing parents)
+#[tauri::command(async)]
+fn create_dir(
+    path: String,
+    settings: tauri::State<'_, Mutex<SettingsStore>>,
+    cache: tauri::State<'_, MetadataCache>,
+) -> Result<FileEntry, CommandError> {
     let path_buf = PathBuf::from(&path);
-    if !path_buf.exists() || !path_buf.is_dir() {
-        return Err("Invalid directory path".to_string());
-    }
-    
-    let app_handle = app.clone();
-    
-    let mut debouncer = new_debouncer(Duration::from_millis(500), move |res: Result<Vec<notify_debouncer_mini::DebouncedEvent>, notify::Error>| {
-        match res {
-            Ok(events) => {
-                for event in events {
-                    let kind = match event.kind {
-                        DebouncedEventKind::Any => "change",
-                        DebouncedEventKind::AnyContinuous => "change",
-                        _ => "change",
-                    };
-                    
-                    let change_event = FileChangeEvent {
-                        path: event.path.to_string_lossy().to_string(),
-                        kind: kind.to_string(),
-                    };
-                    
-                    let _ = app_handle.emit("file-change", change_event);
-                }
-            }
-            Err(e) => {
-                eprintln!("Watch error: {:?}", e);
-            }
+    settings::check_writable(&settings, &path_buf)?;
+    
+    if path_buf.exists() {
+        return Err(format!("Path already exists: {}", path).into());
+    }
+    
+    let created = first_missing_ancestor(&path_buf);
+    fs::create_dir_all(&path_buf).map_err(|e| format!("Failed to create directory: {}", e))?;
+    cache.invalidate(created.as_deref().unwrap_or(&path_buf));
+    
+    Ok(file_entry_for(&path_buf, &settings::note_extensions(&settings)?))
+}
+
+/// Create a new note, refusing to overwrite an existing file
+#[tauri::command(async)]
+fn create_note(
+    path: String,
+    initial_content: Option<String>,
+    settings: tauri::State<'_, Mutex<SettingsStore>>,
+    cache: tauri::State<'_, MetadataCache>,
+) -> Result<FileEntry, CommandError> {
+    let path_buf = PathBuf::from(&path);
+    settings::check_writable(&settings, &path_buf)?;
+    
+    let created = first_missing_ancestor(&path_buf);
+    if let Some(parent) = path_buf.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("Failed to create directory: {}", e))?;
+    }
+    
+    // create_new makes the existence check and the creation a single step
+    let mut file = fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&path_buf)
+        .map_err(|e| match e.kind() {
+            io::ErrorKind::AlreadyExists => format!("File already exists: {}", path),
+            _ => format!("Failed to create file: {}", e),
+        })?;
+    cache.invalidate(created.as_deref().unwrap_or(&path_buf));
+    
+    if let Some(content) = initial_content {
+        file.write_all(content.as_bytes())
+            .map_err(|e| format!("Failed to write file: {}", e))?;
+    }
+    
+    Ok(file_entry_for(&path_buf, &settings::note_extensions(&settings)?))
+}
+
+/// Copy a file or directory tree, keeping permissions and modification times
+fn copy_preserving(from: &Path, to: &Path) -> io::Result<()> {
+    if from.is_dir() {
+        fs::create_dir_all(to)?;
+        for entry in fs::read_dir(from)? {
+            let entry = entry?;
+            copy_preserving(&entry.path(), &to.join(entry.file_name()))?;
         }
-    }).map_err(|e| format!("Failed to create watcher: {}", e))?;
+        fs::set_permissions(to, fs::metadata(from)?.permissions())?;
+        return Ok(());
+    }
     
-    debouncer.watcher().watch(&path_buf, notify::RecursiveMode::Recursive)
-        .map_err(|e| format!("Failed to watch directory: {}", e))?;
+    // fs::copy carries over permission bits, but not timestamps
+    fs::copy(from, to)?;
+    let metadata = fs::metadata(from)?;
+    let mut times = fs::FileTimes::new();
+    if let Ok(modified) = metadata.modified() {
+        times = times.set_modified(modified);
+    }
+    if let Ok(accessed) = metadata.accessed() {
+        times = times.set_accessed(accessed);
+    }
+    fs::File::options().write(true).open(to)?.set_times(times)
+}
+
+/// Move `from` to `to`, falling back to copy + delete across filesystems
+fn move_path(from: &Path, to: &Path, overwrite: bool) -> Result<(), String> {
+    if !from.exists() {
+        return Err(format!("File does not exist: {}", from.display()));
+    }
     
-    watcher_state.watcher = Some(debouncer);
-    watcher_state.watched_path = Some(path);
+    if to.exists() && !overwrite {
+        return Err(format!("Destination already exists: {}", to.display()));
+    }
     
-    Ok(())
+    if let Some(parent) = to.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("Failed to create directory: {}", e))?;
+    }
+    
+    match fs::rename(from, to) {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == io::ErrorKind::CrossesDevices => {
+            if to.is_dir() {
+                fs::remove_dir_all(to).map_err(|e| format!("Failed to replace destination: {}", e))?;
+            }
+            copy_preserving(from, to).map_err(|e| format!("Failed to copy file: {}", e))?;
+            let removed = if from.is_dir() {
+                fs::remove_dir_all(from)
+            } else {
+                fs::remove_file(from)
+            };
+            removed.map_err(|e| format!("Failed to remove original: {}", e))
+        }
+        Err(e) => Err(format!("Failed to rename file: {}", e)),
+    }
 }
 
-/// Stop watching directory
-#[tauri::command]
-fn unwatch_directory(state: tauri::State<'_, Mutex<WatcherState>>) -> Result<(), String> {
-    let mut watcher_state = state.lock().map_err(|e| format!("Lock error: {}", e))?;
-    watcher_state.watcher = None;
-    watcher_state.watched_path = None;
-    Ok(())
+/// Rename or move a file, falling back to copy + delete across filesystems
+#[tauri::command(async)]
+fn rename_file(
+    old_path: String,
+    new_path: String,
+    overwrite: Option<bool>,
+    settings: tauri::State<'_, Mutex<SettingsStore>>,
+    registry: tauri::State<'_, NoteIndexRegistry>,
+    cache: tauri::State<'_, MetadataCache>,
+) -> Result<FileEntry, CommandError> {
+    let from = PathBuf::from(&old_path);
+    let to = PathBuf::from(&new_path);
+    settings::check_writable(&settings, &from)?;
+    settings::check_writable(&settings, &to)?;
+    move_path(&from, &to, overwrite.unwrap_or(false))?;
+    cache.invalidate(&from);
+    cache.invalidate(&to);
+    pins::record_moves(&registry.root_for(&from), &[(&from, &to)]);
+    
+    Ok(file_entry_for(&to, &settings::note_extensions(&settings)?))
+}
+
+/// Delete a file or directory, moving it to the OS trash unless `permanent` is set
+#[tauri::command(async)]
+fn delete_file(
+    path: String,
+    permanent: Option<bool>,
+    settings: tauri::State<'_, Mutex<SettingsStore>>,
+    cache: tauri::State<'_, MetadataCache>,
+) -> Result<(), CommandError> {
+    let path_buf = PathBuf::from(&path);
+    settings::check_writable(&settings, &path_buf)?;
+    
+    if !path_buf.exists() {
+        return Err(format!("File does not exist: {}", path).into());
+    }
+    
+    let removed = if !permanent.unwrap_or(false) {
+        trash::delete(&path_buf).map_err(|e| format!("Failed to move to trash: {}", e))
+    } else if path_buf.is_dir() {
+        fs::remove_dir_all(&path_buf).map_err(|e| format!("Failed to delete file: {}", e))
+    } else {
+        fs::remove_file(&path_buf).map_err(|e| format!("Failed to delete file: {}", e))
+    };
+    cache.invalidate(&path_buf);
+    Ok(removed?)
 }
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
+    let handler = tauri::generate_handler![
+        read_text_file,
+        file_range::read_file_range,
+        file_range::get_line_count,
+        write_text_file,
+        list_dir,
+        list_tree,
+        list_md_files,
+        start_list_md_files,
+        path_exists,
+        get_file_metadata,
+        create_dir,
+        create_note,
+        rename_file,
+        delete_file,
+        watcher::watch_directory,
+        watcher::unwatch_directory,
+        watcher::watch_path,
+        watcher::unwatch,
+        watcher::list_watchers,
+        watcher::watcher_status,
+        watcher::watch_file,
+        watcher::unwatch_file,
+        settings::get_note_extensions,
+        settings::set_note_extensions,
+        settings::get_attachments_dir,
+        settings::set_attachments_dir,
+        settings::set_vault_read_only,
+        settings::is_vault_read_only,
+        settings::get_history_settings,
+        settings::set_history_settings,
+        settings::get_autocommit_settings,
+        settings::set_autocommit_settings,
+        settings::get_settings,
+        settings::set_settings,
+        vault_config::get_vault_config,
+        vault_flavor::detect_vault_flavor,
+        daily::open_daily_note,
+        dates::parse_date,
+        templates::list_templates,
+        templates::create_from_template,
+        note_names::generate_note_id,
+        note_names::create_unique_note,
+        recent::get_recent,
+        recent::touch_recent,
+        session::save_session,
+        session::load_session,
+        pins::pin_note,
+        pins::unpin_note,
+        pins::list_pinned,
+        desktop::reveal_in_file_manager,
+        desktop::open_with_default_app,
+        launch::take_external_files,
+        deep_link::take_deep_links,
+        sandbox::set_vault_root,
+        sandbox::pick_vault_folder,
+        sandbox::pick_note_file,
+        sandbox::pick_file,
+        sandbox::pick_save_path,
+        sandbox::pick_folder,
+        encryption::unlock_vault,
+        encryption::lock_vault,
+        encryption::is_vault_unlocked,
+        encryption::encrypt_note,
+        encryption::decrypt_note,
+        jobs::cancel_job,
+        jobs::list_jobs,
+        search::start_search_regex,
+        search_index::start_index_vault,
+        export::start_export_site,
+        search_index::index_status,
+        search_index::rebuild_index,
+        search_index::query_index,
+        search::search_regex,
+        search::replace_in_files,
+        search::fuzzy_find_notes,
+        frontmatter::read_frontmatter,
+        frontmatter::update_frontmatter,
+        markdown::get_outline,
+        links::get_links,
+        links::get_backlinks,
+        mentions::find_unlinked_mentions,
+        mentions::link_mention,
+        links::find_broken_links,
+        links::find_orphan_notes,
+        blocks::ensure_block_id,
+        format::format_markdown,
+        tables::csv_to_markdown_table,
+        tables::markdown_table_to_csv,
+        link_style::convert_link_style,
+        toc::insert_toc,
+        lint::lint_markdown,
+        spellcheck::check_text,
+        spellcheck::add_to_dictionary,
+        spellcheck::list_dictionaries,
+        clipboard::copy_note_as,
+        links::resolve_link,
+        tags::list_tags,
+        tags::find_notes_by_tag,
+        tags::rename_tag,
+        tasks::list_tasks,
+        tasks::toggle_task,
+        tasks::complete_task,
+        tasks::set_task_due,
+        reminders::snooze_task,
+        stats::get_note_stats,
+        stats::get_vault_stats,
+        graph::get_graph,
+        render::render_markdown,
+        export::export_html,
+        export::export_pdf,
+        export::export_with_pandoc,
+        export::export_site,
+        rename::rename_note_with_links,
+        rename::move_note,
+        rename::rename_folder,
+        merge::merge_notes,
+        split::split_note,
+        vault_index::query_notes,
+        query::run_query,
+        completions::get_link_completions,
+        completions::search_headings,
+        import::import_enex,
+        import::import_notion_zip,
+        import::import_logseq,
+        import::import_joplin_jex,
+        import::import_dayone,
+        opml::import_opml,
+        opml::export_opml,
+        import::html_to_markdown,
+        clipper::clip_url,
+        clipper::fetch_url_title,
+        attachments::save_attachment,
+        attachments::localize_images,
+        attachments::find_orphan_attachments,
+        history::list_versions,
+        history::read_version,
+        history::restore_version,
+        diff::diff_text,
+        diff::diff_versions,
+        git::git_status,
+        git::git_log,
+        git::git_diff,
+        git::git_sync,
+        git::git_sync_continue,
+        git::git_sync_abort,
+        sync::configure_sync,
+        sync::sync_now,
+        sync::sync_status,
+        conflicts::find_sync_conflicts,
+        conflicts::merge_conflict,
+        autocommit::flush_autocommit,
+        capture_server::start_capture_server,
+        capture_server::stop_capture_server,
+        capture_server::get_capture_server_status,
+        capture_server::reset_capture_token,
+        preview_server::start_preview_server,
+        preview_server::stop_preview_server,
+        preview_server::get_preview_server_status,
+        settings::get_backup_settings,
+        settings::set_backup_settings,
+        backup::backup_vault,
+        backup::list_backups,
+        backup::restore_backup,
+        bundle::export_zip,
+        ics::export_ics,
+    ];
     tauri::Builder::default()
+        // Registered first, so a second launch hands its arguments to the
+        // running app and exits before setting anything else up
+        .plugin(tauri_plugin_single_instance::init(|app, args, cwd| {
+            launch::open_paths(app, launch::paths_from_args(&args, Path::new(&cwd)));
+            launch::focus_main_window(app);
+        }))
+        .plugin(tauri_plugin_deep_link::init())
         .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_dialog::init())
-        .manage(Mutex::new(WatcherState {
-            watcher: None,
-            watched_path: None,
-        }))
-        .invoke_handler(tauri::generate_handler![
-            read_text_file,
-            write_text_file,
-            list_dir,
-            list_md_files,
-            path_exists,
-            get_file_metadata,
-            watch_directory,
-            unwatch_directory,
-        ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .plugin(tauri_plugin_notification::init())
+        .setup(|app| {
+            let settings_path = app.path().app_config_dir()?.join("settings.json");
+            let mut settings = SettingsStore::load(settings_path);
+            let handle = app.handle().clone();
+            settings.set_listener(move |settings| {
+                let _ = handle.emit("settings-changed", settings);
+            });
+            app.manage(Mutex::new(settings));
+            app.manage(IndexRegistry::new(app.path().app_cache_dir()?));
+            app.manage(SyncRegistry::new(app.path().app_data_dir()?));
+            app.manage(Mutex::new(RecentStore::load(app.path().app_data_dir()?.join("recent.json"))));
+            app.manage(SessionStore::new(app.path().app_data_dir()?.join("sessions")));
+            app.manage(Spellchecker::new(app.path().app_data_dir()?.join("dictionaries")));
+            app.manage(Backups::new(app.path().app_data_dir()?.join("backups")));
+            autocommit::spawn(app.handle().clone());
+            backup::spawn(app.handle().clone());
+            reminders::spawn(app.handle().clone());
+            capture_server::restore(app.handle());
+            tray::create(app.handle())?;
+
+            let args: Vec<String> = std::env::args().collect();
+            let cwd = std::env::current_dir().unwrap_or_default();
+            launch::open_paths(app.handle(), launch::paths_from_args(&args, &cwd));
+
+            // Linux and Windows only know the scheme once it is registered;
+            // macOS and installed Windows builds get it from the bundle
+            #[cfg(any(target_os = "linux", all(debug_assertions, windows)))]
+            app.deep_link().register_all()?;
+            let handle = app.handle().clone();
+            app.deep_link().on_open_url(move |event| {
+                let urls = event.urls();
+                deep_link::handle_urls(&handle, urls.iter().map(|url| url.as_str()));
+            });
+            if let Some(urls) = app.deep_link().get_current()? {
+                deep_link::handle_urls(app.handle(), urls.iter().map(|url| url.as_str()));
+            }
+            Ok(())
+        })
+        .on_window_event(|window, event| {
+            tray::on_window_event(window, event);
+            sandbox::on_window_event(window, event);
+        })
+        .manage(NotePathCache::default())
+        .manage(NoteIndexRegistry::default())
+        .manage(AutocommitState::default())
+        .manage(Reminders::default())
+        .manage(Mutex::new(WatcherState::new()))
+        .manage(Mutex::new(ExternalOpens::default()))
+        .manage(Mutex::new(DeepLinks::default()))
+        .manage(JobRegistry::default())
+        .manage(MetadataCache::default())
+        .manage(Sandbox::default())
+        .manage(EncryptionKeys::default())
+        .manage(CaptureServer::default())
+        .manage(PreviewServer::default())
+        .invoke_handler(move |invoke| {
+            // Path arguments are confined to the open vault before any
+            // command sees them
+            if let Err(e) = sandbox::check_invoke(&invoke) {
+                invoke.resolver.reject(e);
+                return true;
+            }
+            handler(invoke)
+        })
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|app, event| {
+            if let tauri::RunEvent::Exit = event {
+                backup::on_exit(app);
+            }
+            launch::on_run_event(app, event);
+        });
 }
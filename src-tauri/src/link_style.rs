@@ -0,0 +1,267 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::error::CommandError;
+use crate::links::{self, Link, LinkKind, NameLookup, Resolver};
+use crate::markdown;
+use crate::metadata_cache::MetadataCache;
+use crate::note_index::NoteIndexRegistry;
+use crate::rename::{self, LinkUpdate};
+use crate::settings::{self, SettingsStore};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LinkStyle {
+    /// `[[Note|text]]`
+    Wiki,
+    /// `[text](Note.md)`
+    Markdown,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LinkChange {
+    /// 1-based line number
+    pub line: usize,
+    pub before: String,
+    pub after: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FileLinkChanges {
+    pub path: String,
+    pub changes: Vec<LinkChange>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LinkStyleResult {
+    /// Notes whose links were, or with `dry_run` would be, converted
+    pub files: Vec<FileLinkChanges>,
+    pub dry_run: bool,
+}
+
+/// Percent-encode what can't appear raw in a markdown link destination
+pub fn encode_target(target: &str) -> String {
+    let mut encoded = String::with_capacity(target.len());
+    for c in target.chars() {
+        match c {
+            ' ' => encoded.push_str("%20"),
+            '%' => encoded.push_str("%25"),
+            '(' => encoded.push_str("%28"),
+            ')' => encoded.push_str("%29"),
+            '<' => encoded.push_str("%3C"),
+            '>' => encoded.push_str("%3E"),
+            '#' => encoded.push_str("%23"),
+            c => encoded.push(c),
+        }
+    }
+    encoded
+}
+
+/// Escape brackets in link text
+fn escape_text(text: &str) -> String {
+    text.replace('[', "\\[").replace(']', "\\]")
+}
+
+/// The wiki link `link` in the note `content` at `source` as a markdown
+/// link: `[[Note#Heading|alias]]` as `[alias](Note.md#heading)`, the path
+/// relative to the note
+pub fn wiki_to_markdown(resolver: &Resolver, content: &str, source: &Path, link: &Link) -> Option<String> {
+    let span = &content[link.start..link.end];
+    let inner = span.trim_start_matches('!').strip_prefix("[[")?.strip_suffix("]]")?;
+    let (written, alias) = match inner.split_once('|') {
+        Some((written, alias)) => (written.trim(), Some(alias.trim())),
+        None => (inner.trim(), None),
+    };
+
+    let href = match resolver.resolve(source, link) {
+        _ if link.target.is_empty() => String::new(),
+        Some(path) => rename::relative_path(source.parent()?, &path),
+        None if Path::new(&link.target).extension().is_some() => link.target.clone(),
+        None => {
+            let extension = resolver.note_extensions.first().map_or("md", String::as_str);
+            format!("{}.{}", link.target, extension)
+        }
+    };
+    let fragment = match link.fragment.as_deref() {
+        Some(block) if block.starts_with('^') => format!("#{}", block),
+        Some(heading) => format!("#{}", markdown::slugify(heading.rsplit('#').next().unwrap_or_default())),
+        None => String::new(),
+    };
+    // `[[#Heading]]` reads as `Heading`
+    let text = alias.unwrap_or_else(|| written.strip_prefix('#').unwrap_or(written));
+    let embed = if link.embed { "!" } else { "" };
+    Some(format!("{}[{}]({}{})", embed, escape_text(text), encode_target(&href), fragment))
+}
+
+struct Converter<'a> {
+    root: &'a Path,
+    note_extensions: &'a [String],
+    names: &'a NameLookup,
+    resolver: Resolver<'a>,
+}
+
+impl Converter<'_> {
+
+    /// How a wiki link names `path`: its bare name unless another file
+    /// shares it, else its path from the vault root
+    fn wiki_name(&self, path: &Path) -> Option<String> {
+        let is_note = settings::has_note_extension(path, self.note_extensions);
+        let stem = path.file_stem()?.to_string_lossy().to_string();
+        let unique = !is_note || self.names.notes_named(&stem).len() <= 1;
+        let mut name = match unique {
+            true => path.file_name()?.to_string_lossy().to_string(),
+            false => rename::relative_path(self.root, path),
+        };
+        if is_note {
+            name.truncate(name.len() - path.extension().map_or(0, |e| e.len() + 1));
+        }
+        Some(name)
+    }
+
+    /// `[text](Note.md#heading)` as `[[Note#Heading|text]]`. Links to files
+    /// outside the vault and image embeds, whose alt text has no place in a
+    /// wiki link, stay as they are.
+    fn to_wiki(&self, content: &str, source: &Path, link: &Link) -> Option<String> {
+        let span = &content[link.start..link.end];
+        // Reference-style links have their destination elsewhere
+        let text_end = span.rfind("](")?;
+        let text = span[span.find('[')? + 1..text_end].trim();
+
+        let (name, target) = match link.target.is_empty() {
+            true => (String::new(), source.to_path_buf()),
+            false => {
+                let target = self.resolver.resolve(source, link)?;
+                if !target.starts_with(self.root) {
+                    return None;
+                }
+                if link.embed && !settings::has_note_extension(&target, self.note_extensions) {
+                    return None;
+                }
+                (self.wiki_name(&target)?, target)
+            }
+        };
+        let fragment = match link.fragment.as_deref() {
+            Some(block) if block.starts_with('^') => format!("#{}", block),
+            Some(fragment) => {
+                let content = fs::read_to_string(&target).unwrap_or_default();
+                let headings = markdown::headings(&content);
+                let heading = links::find_heading(&headings, fragment).map(|heading| heading.text.clone());
+                format!("#{}", heading.unwrap_or_else(|| links::percent_decode(fragment)))
+            }
+            None => String::new(),
+        };
+        let written = format!("{}{}", name, fragment);
+        if written.contains(['|', '[', ']']) || text.contains(['|', '[', ']']) {
+            return None;
+        }
+        let decoded = links::percent_decode(&link.target);
+        let alias = match text {
+            "" => String::new(),
+            text if text == written || text == name || text == decoded => String::new(),
+            text => format!("|{}", text),
+        };
+        let embed = if link.embed { "!" } else { "" };
+        Some(format!("{}[[{}{}]]", embed, written, alias))
+    }
+
+    /// `content` with its links converted to `style`, and what changed
+    fn convert(&self, content: &str, source: &Path, style: LinkStyle) -> (String, Vec<LinkChange>) {
+        let mut converted = content.to_string();
+        let mut changes = Vec::new();
+        for link in links::extract(content).iter().rev() {
+            if links::is_external(&link.target) {
+                continue;
+            }
+            let replacement = match (link.kind, style) {
+                (LinkKind::Wiki, LinkStyle::Markdown) => wiki_to_markdown(&self.resolver, content, source, link),
+                (LinkKind::Markdown, LinkStyle::Wiki) => self.to_wiki(content, source, link),
+                _ => None,
+            };
+            if let Some(replacement) = replacement {
+                changes.push(LinkChange {
+                    line: link.line,
+                    before: content[link.start..link.end].to_string(),
+                    after: replacement.clone(),
+                });
+                converted.replace_range(link.start..link.end, &replacement);
+            }
+        }
+        changes.reverse();
+        (converted, changes)
+    }
+}
+
+/// Rewrite the links of the note at `path`, or of every note under the
+/// folder `path`, to `target_style`: `[[Note|alias]]` becomes
+/// `[alias](Note.md)` with the path relative to the note and URL-encoded,
+/// and back. External links stay as they are. With `dry_run`, only reports
+/// what would change.
+#[tauri::command(async)]
+pub fn convert_link_style(
+    path: String,
+    target_style: LinkStyle,
+    dry_run: Option<bool>,
+    registry: tauri::State<'_, NoteIndexRegistry>,
+    settings: tauri::State<'_, Mutex<SettingsStore>>,
+    cache: tauri::State<'_, MetadataCache>,
+) -> Result<LinkStyleResult, CommandError> {
+    let dry_run = dry_run.unwrap_or(false);
+    let path_buf = links::normalize_path(Path::new(&path));
+    if !dry_run {
+        settings::check_writable(&settings, &path_buf)?;
+    }
+    let (root, note_extensions, names) = if path_buf.is_dir() {
+        // Stand-in note path; only its directory is used
+        links::lookup_for(&path_buf.join("_"), &registry, &settings)?
+    } else {
+        links::lookup_for(&path_buf, &registry, &settings)?
+    };
+    let index = registry.for_path(&path_buf);
+    let notes: Vec<PathBuf> = match (path_buf.is_dir(), &index) {
+        (true, Some(index)) => {
+            let mut notes: Vec<PathBuf> =
+                index.contents()?.notes.keys().filter(|p| p.starts_with(&path_buf)).cloned().collect();
+            notes.sort();
+            notes
+        }
+        (true, None) => crate::search::note_paths(&path_buf, &[], &note_extensions)?,
+        (false, _) => vec![path_buf.clone()],
+    };
+    let converter = Converter {
+        root: &root,
+        note_extensions: &note_extensions,
+        names: &names,
+        resolver: Resolver {
+            root: &root,
+            note_extensions: &note_extensions,
+            names: &names,
+        },
+    };
+
+    let mut files = Vec::new();
+    let mut updates = Vec::new();
+    for note in notes {
+        let (previous, content) = crate::read_note(&note)?;
+        let (converted, changes) = converter.convert(&content, &note, target_style);
+        if changes.is_empty() {
+            continue;
+        }
+        files.push(FileLinkChanges {
+            path: note.to_string_lossy().to_string(),
+            changes,
+        });
+        updates.push(LinkUpdate {
+            from: note.clone(),
+            path: note,
+            content: converted,
+            previous,
+        });
+    }
+
+    if !dry_run {
+        rename::write_all_or_none(&updates, &registry, &settings, &cache)?;
+    }
+    Ok(LinkStyleResult { files, dry_run })
+}
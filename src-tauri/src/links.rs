@@ -0,0 +1,678 @@
+use pulldown_cmark::{Event, LinkType, Parser, Tag, TagEnd};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::blocks;
+use crate::frontmatter;
+use crate::markdown::{self, Heading};
+use crate::note_index::{NoteData, NoteIndex, NoteIndexRegistry, VaultContents};
+use crate::search::{line_of, line_starts};
+use crate::settings::{self, SettingsStore};
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum LinkKind {
+    /// `[[Note]]`, `[[Note#Heading|alias]]`
+    Wiki,
+    /// `[text](path.md)`, including autolinks
+    Markdown,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Link {
+    pub kind: LinkKind,
+    /// Written as `![[...]]` or `![...](...)`
+    pub embed: bool,
+    /// Target as written, without the `#fragment`; empty for links within
+    /// the same note
+    pub target: String,
+    /// Heading, or `^block` id, after the `#`
+    pub fragment: Option<String>,
+    /// Alias or link text
+    pub text: String,
+    /// 1-based line number
+    pub line: usize,
+    /// Byte range of the whole link
+    pub start: usize,
+    pub end: usize,
+}
+
+/// All links in `content`, in document order. Links inside code are skipped.
+pub fn extract(content: &str) -> Vec<Link> {
+    let offset = markdown::body_start(content);
+    let starts = line_starts(content);
+    let mut links = Vec::new();
+    // Links being read; images can nest inside links
+    let mut open: Vec<Link> = Vec::new();
+
+    for (event, range) in Parser::new_ext(&content[offset..], markdown::parser_options()).into_offset_iter() {
+        match event {
+            Event::Start(Tag::Link { link_type, dest_url, .. })
+            | Event::Start(Tag::Image { link_type, dest_url, .. }) => {
+                let embed = content[offset + range.start..].starts_with('!');
+                let (target, fragment) = match dest_url.split_once('#') {
+                    Some((target, fragment)) => (target.to_string(), Some(fragment.to_string())),
+                    None => (dest_url.to_string(), None),
+                };
+                open.push(Link {
+                    kind: match link_type {
+                        LinkType::WikiLink { .. } => LinkKind::Wiki,
+                        _ => LinkKind::Markdown,
+                    },
+                    embed,
+                    target,
+                    fragment: fragment.filter(|f| !f.is_empty()),
+                    text: String::new(),
+                    line: line_of(&starts, offset + range.start),
+                    start: offset + range.start,
+                    end: offset + range.end,
+                });
+            }
+            Event::Text(text) | Event::Code(text) => {
+                if let Some(link) = open.last_mut() {
+                    link.text.push_str(&text);
+                }
+            }
+            Event::End(TagEnd::Link) | Event::End(TagEnd::Image) => {
+                if let Some(link) = open.pop() {
+                    links.push(link);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    links.sort_by_key(|link| link.start);
+    links
+}
+
+/// Whether a link target points outside the vault (`https:`, `mailto:`, ...).
+/// Single-letter schemes are Windows drive letters, not URLs.
+pub fn is_external(target: &str) -> bool {
+    if target.starts_with("//") {
+        return true;
+    }
+    match target.split_once(':') {
+        Some((scheme, _)) => {
+            scheme.len() > 1
+                && scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+                && scheme.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'))
+        }
+        None => false,
+    }
+}
+
+/// Decode `%XX` escapes, as used for spaces in markdown link targets
+pub fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            if let Some(byte) = text.get(i + 1..i + 3).and_then(|hex| u8::from_str_radix(hex, 16).ok()) {
+                decoded.push(byte);
+                i += 3;
+                continue;
+            }
+        }
+        decoded.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).to_string()
+}
+
+/// Resolve `.` and `..` without touching the filesystem
+pub fn normalize_path(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                if !normalized.pop() {
+                    normalized.push(component);
+                }
+            }
+            _ => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+/// Vault files by lowercase name, for resolving `[[Name]]` links: notes by
+/// file stem and attachments by full file name
+#[derive(Default)]
+pub struct NameLookup {
+    notes: HashMap<String, Vec<PathBuf>>,
+    attachments: HashMap<String, Vec<PathBuf>>,
+    /// Notes by the frontmatter `aliases` they can also be linked by
+    aliases: HashMap<String, Vec<PathBuf>>,
+}
+
+impl NameLookup {
+    pub fn new(contents: &VaultContents) -> Self {
+        let mut lookup = NameLookup::default();
+        for (path, note) in &contents.notes {
+            if let Some(stem) = path.file_stem() {
+                let key = stem.to_string_lossy().to_lowercase();
+                lookup.notes.entry(key).or_default().push(path.clone());
+            }
+            for alias in &note.aliases {
+                lookup.aliases.entry(alias.to_lowercase()).or_default().push(path.clone());
+            }
+        }
+        for path in &contents.attachments {
+            if let Some(name) = path.file_name() {
+                let key = name.to_string_lossy().to_lowercase();
+                lookup.attachments.entry(key).or_default().push(path.clone());
+            }
+        }
+        lookup
+    }
+
+    pub fn notes_named(&self, stem: &str) -> &[PathBuf] {
+        self.notes.get(&stem.to_lowercase()).map_or(&[], Vec::as_slice)
+    }
+
+    fn attachments_named(&self, name: &str) -> &[PathBuf] {
+        self.attachments.get(&name.to_lowercase()).map_or(&[], Vec::as_slice)
+    }
+
+    fn notes_aliased(&self, alias: &str) -> &[PathBuf] {
+        self.aliases.get(&alias.to_lowercase()).map_or(&[], Vec::as_slice)
+    }
+}
+
+/// Alternative names from a note's frontmatter `aliases` (or `alias`) field,
+/// either a list or a comma separated string
+pub fn aliases(content: &str) -> Vec<String> {
+    let fields = frontmatter::fields(content);
+    let mut aliases = Vec::new();
+    for value in ["aliases", "alias"].iter().filter_map(|key| fields.get(*key)) {
+        match value {
+            Value::Array(items) => aliases.extend(items.iter().filter_map(Value::as_str).map(str::to_string)),
+            Value::String(s) => aliases.extend(s.split(',').map(str::to_string)),
+            _ => {}
+        }
+    }
+    aliases.iter().map(|alias| alias.trim().to_string()).filter(|alias| !alias.is_empty()).collect()
+}
+
+/// Order of preference between files with the same name: in the same
+/// directory as `source` first, then shortest path
+fn closeness(path: &Path, source: &Path) -> (bool, usize, PathBuf) {
+    (path.parent() != source.parent(), path.components().count(), path.to_path_buf())
+}
+
+/// The candidate whose trailing path components match `target` (compared
+/// without `strip_extension`, case-insensitively), closest to `source` first
+fn closest_match(candidates: &[PathBuf], target: &str, source: &Path, strip_extension: bool) -> Option<PathBuf> {
+    let suffix: Vec<String> = target
+        .split('/')
+        .filter(|part| !part.is_empty())
+        .map(str::to_lowercase)
+        .collect();
+
+    candidates
+        .iter()
+        .filter(|path| {
+            let mut path = path.to_path_buf();
+            if strip_extension {
+                path.set_extension("");
+            }
+            let components: Vec<String> = path
+                .components()
+                .map(|c| c.as_os_str().to_string_lossy().to_lowercase())
+                .collect();
+            components.ends_with(&suffix)
+        })
+        .min_by_key(|path| closeness(path, source))
+        .cloned()
+}
+
+/// Resolves links from notes in one vault to files on disk
+pub struct Resolver<'a> {
+    pub root: &'a Path,
+    pub note_extensions: &'a [String],
+    pub names: &'a NameLookup,
+}
+
+impl Resolver<'_> {
+    /// The file `link` in `source` points at, if it exists
+    pub fn resolve(&self, source: &Path, link: &Link) -> Option<PathBuf> {
+        if is_external(&link.target) {
+            return None;
+        }
+        if link.target.is_empty() {
+            return Some(source.to_path_buf());
+        }
+        match link.kind {
+            LinkKind::Markdown => self.resolve_relative(source, &percent_decode(&link.target)),
+            LinkKind::Wiki => self.resolve_wiki(source, &link.target),
+        }
+    }
+
+    /// A path relative to the note, or to the vault root if it starts with `/`
+    fn resolve_relative(&self, source: &Path, target: &str) -> Option<PathBuf> {
+        let base = match target.strip_prefix('/') {
+            Some(target) => self.root.join(target),
+            None => source.parent().unwrap_or(self.root).join(target),
+        };
+        self.existing(&normalize_path(&base))
+    }
+
+    fn resolve_wiki(&self, source: &Path, target: &str) -> Option<PathBuf> {
+        let target = target.trim();
+        let name = Path::new(target);
+
+        // `[[Note.md]]` means the same as `[[Note]]`; `[[image.png]]` names
+        // an attachment, which isn't in the note list
+        let has_note_extension = settings::has_note_extension(name, self.note_extensions);
+        let stem_target = if has_note_extension {
+            target.rsplit_once('.').map_or(target, |(stem, _)| stem)
+        } else {
+            target
+        };
+        let file_name = |target: &'_ str| target.rsplit('/').next().unwrap_or_default().to_string();
+        if name.extension().is_some() && !has_note_extension {
+            let attachment = self
+                .resolve_relative(source, target)
+                .or_else(|| self.existing(&self.root.join(target)))
+                .or_else(|| closest_match(self.names.attachments_named(&file_name(target)), target, source, false));
+            if attachment.is_some() {
+                return attachment;
+            }
+        }
+
+        // File names win over aliases, as in Obsidian
+        closest_match(self.names.notes_named(&file_name(stem_target)), stem_target, source, true).or_else(|| {
+            self.names
+                .notes_aliased(target)
+                .iter()
+                .min_by_key(|path| closeness(path, source))
+                .cloned()
+        })
+    }
+
+    /// `path` itself if it is a file, else `path` with a note extension added
+    fn existing(&self, path: &Path) -> Option<PathBuf> {
+        if path.is_file() {
+            return Some(path.to_path_buf());
+        }
+        self.note_extensions.iter().find_map(|ext| {
+            let mut with_ext = path.as_os_str().to_owned();
+            with_ext.push(".");
+            with_ext.push(ext);
+            let with_ext = PathBuf::from(with_ext);
+            with_ext.is_file().then_some(with_ext)
+        })
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct ResolvedLink {
+    #[serde(flatten)]
+    pub link: Link,
+    /// Absolute path of the target, or `None` for external and broken links
+    pub resolved: Option<String>,
+    pub external: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Backlink {
+    /// The note containing the link
+    pub source: String,
+    pub link: Link,
+}
+
+/// Root, note extensions and names of the open vault containing `source`.
+/// Without an open vault, wiki links can only be resolved next to the note.
+pub fn lookup_for(
+    source: &Path,
+    registry: &NoteIndexRegistry,
+    settings: &Mutex<SettingsStore>,
+) -> Result<(PathBuf, Vec<String>, NameLookup), String> {
+    if let Some(index) = registry.for_path(source) {
+        let names = NameLookup::new(&*index.contents()?);
+        return Ok((index.root().to_path_buf(), index.note_extensions().to_vec(), names));
+    }
+
+    let root = source.parent().map(Path::to_path_buf).unwrap_or_default();
+    let note_extensions = settings::note_extensions(settings)?;
+    let mut notes = HashMap::new();
+    for path in crate::search::note_paths(&root, &[], &note_extensions)? {
+        let note = NoteData {
+            aliases: fs::read_to_string(&path).map(|content| aliases(&content)).unwrap_or_default(),
+            ..Default::default()
+        };
+        notes.insert(path, note);
+    }
+    let names = NameLookup::new(&VaultContents {
+        notes,
+        attachments: Default::default(),
+    });
+    Ok((root, note_extensions, names))
+}
+
+/// Outgoing links of a note, with their targets resolved within the open
+/// vault
+#[tauri::command(async)]
+pub fn get_links(
+    path: String,
+    registry: tauri::State<'_, NoteIndexRegistry>,
+    settings: tauri::State<'_, Mutex<SettingsStore>>,
+) -> Result<Vec<ResolvedLink>, String> {
+    let source = PathBuf::from(&path);
+    let content = fs::read_to_string(&source).map_err(|e| format!("Failed to read file: {}", e))?;
+    let (root, note_extensions, names) = lookup_for(&source, &registry, &settings)?;
+    let resolver = Resolver {
+        root: &root,
+        note_extensions: &note_extensions,
+        names: &names,
+    };
+
+    Ok(extract(&content)
+        .into_iter()
+        .map(|link| ResolvedLink {
+            resolved: resolver.resolve(&source, &link).map(|p| p.to_string_lossy().to_string()),
+            external: is_external(&link.target),
+            link,
+        })
+        .collect())
+}
+
+/// Links from other notes in the vault that point at `path`
+#[tauri::command(async)]
+pub fn get_backlinks(
+    path: String,
+    registry: tauri::State<'_, NoteIndexRegistry>,
+) -> Result<Vec<Backlink>, String> {
+    let target = normalize_path(Path::new(&path));
+    let index = registry
+        .for_path(&target)
+        .ok_or_else(|| format!("No open vault contains {}", path))?;
+    let contents = index.contents()?;
+    let names = NameLookup::new(&contents);
+    let resolver = Resolver {
+        root: index.root(),
+        note_extensions: index.note_extensions(),
+        names: &names,
+    };
+
+    let mut backlinks: Vec<Backlink> = contents
+        .notes
+        .iter()
+        .filter(|(source, _)| **source != target)
+        .flat_map(|(source, note)| {
+            note.links
+                .iter()
+                .filter(|link| resolver.resolve(source, link).as_deref() == Some(target.as_path()))
+                .map(move |link| Backlink {
+                    source: source.to_string_lossy().to_string(),
+                    link: link.clone(),
+                })
+        })
+        .collect();
+    backlinks.sort_by(|a, b| a.source.cmp(&b.source).then(a.link.start.cmp(&b.link.start)));
+    Ok(backlinks)
+}
+
+#[derive(Debug, Serialize)]
+pub struct LinkTarget {
+    /// Absolute path of the note or attachment, or `None` if nothing matches
+    pub path: Option<String>,
+    pub fragment: Option<String>,
+    /// 1-based line of the heading or block the fragment names, if found
+    pub line: Option<usize>,
+}
+
+/// Resolve a link written in the note at `source_path`, such as
+/// `[[Note#Heading|alias]]`, `[[folder/Note]]` or a bare `Note#^block`.
+/// Names are matched by file name, then by frontmatter `aliases`.
+#[tauri::command(async)]
+pub fn resolve_link(
+    source_path: String,
+    link_text: String,
+    registry: tauri::State<'_, NoteIndexRegistry>,
+    settings: tauri::State<'_, Mutex<SettingsStore>>,
+) -> Result<LinkTarget, String> {
+    let source = normalize_path(Path::new(&source_path));
+    let text = link_text.trim();
+    let written = if text.contains("[[") || text.contains("](") {
+        text.to_string()
+    } else {
+        format!("[[{}]]", text)
+    };
+    let link = extract(&written)
+        .into_iter()
+        .next()
+        .ok_or_else(|| format!("Not a link: {}", link_text))?;
+    if is_external(&link.target) {
+        return Ok(LinkTarget {
+            path: None,
+            fragment: link.fragment,
+            line: None,
+        });
+    }
+
+    let (root, note_extensions, names) = lookup_for(&source, &registry, &settings)?;
+    let resolver = Resolver {
+        root: &root,
+        note_extensions: &note_extensions,
+        names: &names,
+    };
+    let path = resolver.resolve(&source, &link);
+    let line = match (&path, &link.fragment) {
+        (Some(path), Some(fragment)) => match fragment.strip_prefix('^') {
+            Some(id) => {
+                // Block ids are indexed; files outside an open vault are read
+                let indexed = registry.for_path(path).and_then(|index| {
+                    let contents = index.contents().ok()?;
+                    Some(blocks::find(&contents.notes.get(path)?.blocks, id))
+                });
+                indexed.unwrap_or_else(|| find_block(&fs::read_to_string(path).unwrap_or_default(), id))
+            }
+            None => {
+                let content = fs::read_to_string(path).unwrap_or_default();
+                find_heading(&markdown::headings(&content), fragment).map(|heading| heading.line)
+            }
+        },
+        _ => None,
+    };
+
+    Ok(LinkTarget {
+        path: path.map(|p| p.to_string_lossy().to_string()),
+        fragment: link.fragment,
+        line,
+    })
+}
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Missing {
+    /// No file matches the link target
+    File,
+    /// The file exists but has no heading matching the `#fragment`
+    Heading,
+    /// The file exists but has no `^block` id matching the fragment
+    Block,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BrokenLink {
+    /// The note containing the link
+    pub source: String,
+    pub link: Link,
+    /// What the link points at that doesn't exist
+    pub missing: Missing,
+    /// The file the link resolved to, for missing headings and blocks
+    pub resolved: Option<String>,
+}
+
+/// The heading `fragment` names, by slug as in `#my-heading` or by text as
+/// in `[[Note#My Heading]]`. For nested `A#B` fragments only the last
+/// heading is matched.
+pub fn find_heading<'a>(headings: &'a [Heading], fragment: &str) -> Option<&'a Heading> {
+    let fragment = percent_decode(fragment);
+    let fragment = fragment.rsplit('#').next().unwrap_or_default().trim();
+    let slug = markdown::slugify(fragment);
+    headings
+        .iter()
+        .find(|h| h.slug == fragment || h.slug == slug || h.text.eq_ignore_ascii_case(fragment))
+}
+
+/// 1-based number of the line of `content` that ends with the block id `^id`
+fn find_block(content: &str, id: &str) -> Option<usize> {
+    blocks::find(&blocks::block_ids(content), id)
+}
+
+/// Links in notes under `root` whose target file, heading or block id
+/// doesn't exist, ordered by note and position
+fn broken_links(index: &NoteIndex, root: &Path) -> Result<Vec<BrokenLink>, String> {
+    let contents = index.contents()?;
+    let names = NameLookup::new(&contents);
+    let resolver = Resolver {
+        root: index.root(),
+        note_extensions: index.note_extensions(),
+        names: &names,
+    };
+
+    // Target notes are read at most once, and only if a link has a heading
+    let mut targets: HashMap<PathBuf, Option<(String, Vec<Heading>)>> = HashMap::new();
+    let mut broken = Vec::new();
+
+    for (source, note) in &contents.notes {
+        if !source.starts_with(root) {
+            continue;
+        }
+        for link in &note.links {
+            if is_external(&link.target) {
+                continue;
+            }
+            let resolved = resolver.resolve(source, link);
+            let missing = match (&resolved, &link.fragment) {
+                (None, _) => Some(Missing::File),
+                (Some(target), Some(fragment)) if settings::has_note_extension(target, index.note_extensions()) => {
+                    let block = fragment.strip_prefix('^');
+                    // Block ids are indexed
+                    if let (Some(id), Some(note)) = (block, contents.notes.get(target)) {
+                        blocks::find(&note.blocks, id).is_none().then_some(Missing::Block)
+                    } else {
+                        let parsed = targets.entry(target.clone()).or_insert_with(|| {
+                            let content = fs::read_to_string(target).ok()?;
+                            let headings = markdown::headings(&content);
+                            Some((content, headings))
+                        });
+                        match (parsed, block) {
+                            (Some((content, _)), Some(id)) if find_block(content, id).is_none() => {
+                                Some(Missing::Block)
+                            }
+                            (Some((_, headings)), None) if find_heading(headings, fragment).is_none() => {
+                                Some(Missing::Heading)
+                            }
+                            _ => None,
+                        }
+                    }
+                }
+                _ => None,
+            };
+
+            if let Some(missing) = missing {
+                broken.push(BrokenLink {
+                    source: source.to_string_lossy().to_string(),
+                    link: link.clone(),
+                    missing,
+                    resolved: resolved.map(|p| p.to_string_lossy().to_string()),
+                });
+            }
+        }
+    }
+
+    broken.sort_by(|a, b| a.source.cmp(&b.source).then(a.link.start.cmp(&b.link.start)));
+    Ok(broken)
+}
+
+/// Every link in the vault at `root` whose target file, heading or block id
+/// doesn't exist. External links aren't checked.
+#[tauri::command(async)]
+pub fn find_broken_links(
+    root: String,
+    registry: tauri::State<'_, NoteIndexRegistry>,
+    settings: tauri::State<'_, Mutex<SettingsStore>>,
+) -> Result<Vec<BrokenLink>, String> {
+    let root = PathBuf::from(&root);
+    broken_links(&*registry.for_vault(&root, &settings)?, &root)
+}
+
+/// Which notes count as orphans
+#[derive(Debug, Clone, Copy, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum OrphanCriteria {
+    /// Nothing links to the note and it links to nothing
+    #[default]
+    Isolated,
+    /// Nothing links to the note
+    NoInbound,
+    /// The note links to nothing
+    NoOutbound,
+}
+
+/// Notes under `root` matching `criteria`, by path. Only links to files in
+/// the vault count, not external ones or links within the note itself.
+fn orphan_notes(index: &NoteIndex, root: &Path, criteria: OrphanCriteria) -> Result<Vec<String>, String> {
+    let contents = index.contents()?;
+    let names = NameLookup::new(&contents);
+    let resolver = Resolver {
+        root: index.root(),
+        note_extensions: index.note_extensions(),
+        names: &names,
+    };
+
+    let mut inbound: HashSet<PathBuf> = HashSet::new();
+    let mut outbound: HashSet<&Path> = HashSet::new();
+    for (source, note) in &contents.notes {
+        for link in &note.links {
+            match resolver.resolve(source, link) {
+                Some(target) if target != *source => {
+                    inbound.insert(target);
+                    outbound.insert(source);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    let mut orphans: Vec<String> = contents
+        .notes
+        .keys()
+        .filter(|path| path.starts_with(root))
+        .filter(|path| {
+            let linked_to = inbound.contains(*path);
+            let links_out = outbound.contains(path.as_path());
+            match criteria {
+                OrphanCriteria::Isolated => !linked_to && !links_out,
+                OrphanCriteria::NoInbound => !linked_to,
+                OrphanCriteria::NoOutbound => !links_out,
+            }
+        })
+        .map(|path| path.to_string_lossy().to_string())
+        .collect();
+    orphans.sort();
+    Ok(orphans)
+}
+
+/// Notes in the vault at `root` that no note links to and that link to no
+/// other file, or per `criteria` only one of the two
+#[tauri::command(async)]
+pub fn find_orphan_notes(
+    root: String,
+    criteria: Option<OrphanCriteria>,
+    registry: tauri::State<'_, NoteIndexRegistry>,
+    settings: tauri::State<'_, Mutex<SettingsStore>>,
+) -> Result<Vec<String>, String> {
+    let root = PathBuf::from(&root);
+    orphan_notes(&*registry.for_vault(&root, &settings)?, &root, criteria.unwrap_or_default())
+}
@@ -0,0 +1,226 @@
+use pulldown_cmark::{Event, Parser, Tag};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::ops::Range;
+use std::path::Path;
+use std::sync::{Mutex, OnceLock};
+
+use crate::markdown;
+use crate::mentions::prose_ranges;
+use crate::note_index::NoteIndexRegistry;
+use crate::search::{line_of, line_starts};
+use crate::settings::SettingsStore;
+use crate::vault_config;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    /// The rule isn't checked
+    Off,
+    Info,
+    Warning,
+    Error,
+}
+
+/// How severe each rule's findings are; `off` turns a rule off. Vaults set
+/// these under `lint` in `.readmark/config.json`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LintRules {
+    /// A heading more than one level below the previous one
+    pub heading_increment: Severity,
+    /// A URL in the text that isn't a link
+    pub bare_urls: Severity,
+    /// Two headings with the same text
+    pub duplicate_headings: Severity,
+    /// A line over `max_line_length` characters
+    pub long_lines: Severity,
+    pub max_line_length: usize,
+    /// Whitespace at the end of a line, other than a two-space line break
+    pub trailing_spaces: Severity,
+}
+
+impl Default for LintRules {
+    fn default() -> Self {
+        LintRules {
+            heading_increment: Severity::Warning,
+            bare_urls: Severity::Warning,
+            duplicate_headings: Severity::Warning,
+            long_lines: Severity::Off,
+            max_line_length: 120,
+            trailing_spaces: Severity::Info,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct Diagnostic {
+    /// Name of the rule, as in `LintRules`
+    pub rule: &'static str,
+    pub severity: Severity,
+    pub message: String,
+    /// 1-based line number
+    pub line: usize,
+    /// Byte offsets into the file
+    pub start: usize,
+    pub end: usize,
+}
+
+fn url_regex() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    // Trailing punctuation is taken to end the sentence, not the URL
+    REGEX.get_or_init(|| {
+        Regex::new(r"\b(?:https?://|www\.)[^\s<>]*[^\s<>.,;:!?'\x22)\]]").expect("valid url regex")
+    })
+}
+
+/// Byte ranges of code blocks and tables, which line rules leave alone
+fn verbatim_ranges(content: &str, offset: usize) -> Vec<Range<usize>> {
+    Parser::new_ext(&content[offset..], markdown::parser_options())
+        .into_offset_iter()
+        .filter_map(|(event, range)| match event {
+            Event::Start(Tag::CodeBlock(_)) | Event::Start(Tag::Table(_)) => {
+                Some(offset + range.start..offset + range.end)
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+struct Linter<'a> {
+    content: &'a str,
+    starts: Vec<usize>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Linter<'_> {
+    fn report(&mut self, rule: &'static str, severity: Severity, range: Range<usize>, message: String) {
+        if severity == Severity::Off {
+            return;
+        }
+        self.diagnostics.push(Diagnostic {
+            rule,
+            severity,
+            message,
+            line: line_of(&self.starts, range.start),
+            start: range.start,
+            end: range.end,
+        });
+    }
+
+    fn check_headings(&mut self, rules: &LintRules) {
+        let mut previous: Option<u8> = None;
+        let mut seen: HashMap<String, usize> = HashMap::new();
+        for heading in markdown::headings(self.content) {
+            let range = heading.start..heading.end;
+            if let Some(previous) = previous.filter(|previous| heading.level > previous + 1) {
+                let message = format!("Heading level jumps from H{} to H{}", previous, heading.level);
+                self.report("heading_increment", rules.heading_increment, range.clone(), message);
+            }
+            previous = Some(heading.level);
+
+            if heading.text.is_empty() {
+                continue;
+            }
+            match seen.get(&heading.text.to_lowercase()) {
+                Some(line) => {
+                    let message = format!("Duplicate heading \"{}\", first on line {}", heading.text, line);
+                    self.report("duplicate_headings", rules.duplicate_headings, range, message);
+                }
+                None => {
+                    seen.insert(heading.text.to_lowercase(), heading.line);
+                }
+            }
+        }
+    }
+
+    fn check_bare_urls(&mut self, severity: Severity) {
+        for range in prose_ranges(self.content) {
+            let text = &self.content[range.clone()];
+            for found in url_regex().find_iter(text) {
+                let at = range.start + found.start()..range.start + found.end();
+                let message = format!("Bare URL {}; wrap it in <> or make it a link", found.as_str());
+                self.report("bare_urls", severity, at, message);
+            }
+        }
+    }
+
+    fn check_lines(&mut self, rules: &LintRules) {
+        let body_start = markdown::body_start(self.content);
+        let verbatim = verbatim_ranges(self.content, body_start);
+        let content = self.content;
+        let starts = self.starts.clone();
+        for (i, &start) in starts.iter().enumerate() {
+            if start < body_start {
+                continue;
+            }
+            let end = content[start..].find('\n').map_or(content.len(), |i| start + i);
+            let line = content[start..end].trim_end_matches('\r');
+            let in_verbatim = verbatim.iter().any(|range| range.contains(&start));
+
+            let trimmed = line.trim_end();
+            let trailing = &line[trimmed.len()..];
+            let next_line = starts.get(i + 1).map(|&next| {
+                let next_end = content[next..].find('\n').map_or(content.len(), |i| next + i);
+                content[next..next_end].trim()
+            });
+            // Two spaces before a line of the same paragraph are a hard break
+            let line_break =
+                trailing == "  " && !trimmed.is_empty() && next_line.is_some_and(|next| !next.is_empty());
+            if !trailing.is_empty() && !line_break && !in_verbatim {
+                let at = start + trimmed.len()..start + line.len();
+                let message = "Trailing whitespace".to_string();
+                self.report("trailing_spaces", rules.trailing_spaces, at, message);
+            }
+
+            let length = line.chars().count();
+            // A line without spaces, such as a long URL, can't be wrapped
+            if length > rules.max_line_length && !in_verbatim && line.trim().contains(char::is_whitespace) {
+                let cut = line.char_indices().nth(rules.max_line_length).map_or(line.len(), |(i, _)| i);
+                let message = format!("Line is {} characters long, over {}", length, rules.max_line_length);
+                self.report("long_lines", rules.long_lines, start + cut..start + line.len(), message);
+            }
+        }
+    }
+}
+
+/// Problems in `content` under `rules`, in document order
+pub fn lint(content: &str, rules: &LintRules) -> Vec<Diagnostic> {
+    let mut linter = Linter {
+        content,
+        starts: line_starts(content),
+        diagnostics: Vec::new(),
+    };
+    linter.check_headings(rules);
+    if rules.bare_urls != Severity::Off {
+        linter.check_bare_urls(rules.bare_urls);
+    }
+    linter.check_lines(rules);
+    let mut diagnostics = linter.diagnostics;
+    diagnostics.sort_by_key(|diagnostic| (diagnostic.start, diagnostic.end));
+    diagnostics
+}
+
+/// Diagnostics for the note at `path`: inconsistent heading levels, bare
+/// URLs, duplicate headings, long lines and trailing spaces. Without
+/// `rules`, those of the note's vault apply.
+#[tauri::command(async)]
+pub fn lint_markdown(
+    path: String,
+    rules: Option<LintRules>,
+    registry: tauri::State<'_, NoteIndexRegistry>,
+    settings: tauri::State<'_, Mutex<SettingsStore>>,
+) -> Result<Vec<Diagnostic>, String> {
+    let path = Path::new(&path);
+    let content = fs::read_to_string(path).map_err(|e| format!("Failed to read file: {}", e))?;
+    let rules = match rules {
+        Some(rules) => rules,
+        None => match registry.for_path(path) {
+            Some(index) => vault_config::resolve(index.root(), &settings)?.lint,
+            None => LintRules::default(),
+        },
+    };
+    Ok(lint(&content, &rules))
+}
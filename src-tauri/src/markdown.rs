@@ -0,0 +1,123 @@
+use pulldown_cmark::{Event, HeadingLevel, Options, Parser, Tag, TagEnd};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs;
+
+use crate::frontmatter;
+use crate::search::{line_of, line_starts};
+
+/// Markdown extensions enabled everywhere notes are parsed
+pub fn parser_options() -> Options {
+    Options::ENABLE_TABLES
+        | Options::ENABLE_FOOTNOTES
+        | Options::ENABLE_STRIKETHROUGH
+        | Options::ENABLE_TASKLISTS
+        | Options::ENABLE_HEADING_ATTRIBUTES
+        | Options::ENABLE_WIKILINKS
+}
+
+/// Byte offset where the markdown body starts, after any frontmatter.
+/// Parsing from here keeps a YAML block from being read as a heading.
+pub fn body_start(content: &str) -> usize {
+    frontmatter::split(content).map_or(0, |block| block.body_start)
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct Heading {
+    /// 1 for `#` through 6 for `######`
+    pub level: u8,
+    pub text: String,
+    /// Anchor for `#slug` links, unique within the note
+    pub slug: String,
+    /// 1-based line number
+    pub line: usize,
+    /// Byte range of the whole heading, including the `#` markers
+    pub start: usize,
+    pub end: usize,
+}
+
+/// GitHub-style anchor: lowercase, punctuation dropped, spaces turned into
+/// hyphens
+pub fn slugify(text: &str) -> String {
+    text.trim()
+        .to_lowercase()
+        .chars()
+        .filter_map(|c| match c {
+            ' ' => Some('-'),
+            c if c.is_alphanumeric() || c == '-' || c == '_' => Some(c),
+            _ => None,
+        })
+        .collect()
+}
+
+fn level_number(level: HeadingLevel) -> u8 {
+    match level {
+        HeadingLevel::H1 => 1,
+        HeadingLevel::H2 => 2,
+        HeadingLevel::H3 => 3,
+        HeadingLevel::H4 => 4,
+        HeadingLevel::H5 => 5,
+        HeadingLevel::H6 => 6,
+    }
+}
+
+/// All headings in `content`, in document order. Offsets are into `content`
+/// itself, frontmatter included.
+pub fn headings(content: &str) -> Vec<Heading> {
+    let offset = body_start(content);
+    let starts = line_starts(content);
+    let mut headings = Vec::new();
+    let mut seen: HashMap<String, usize> = HashMap::new();
+    // (level, explicit id, start, text so far) of the heading being read
+    let mut current: Option<(u8, Option<String>, usize, String)> = None;
+
+    for (event, range) in Parser::new_ext(&content[offset..], parser_options()).into_offset_iter() {
+        match event {
+            Event::Start(Tag::Heading { level, id, .. }) => {
+                current = Some((level_number(level), id.map(|id| id.to_string()), range.start, String::new()));
+            }
+            Event::Text(text) | Event::Code(text) => {
+                if let Some((_, _, _, heading_text)) = current.as_mut() {
+                    heading_text.push_str(&text);
+                }
+            }
+            Event::End(TagEnd::Heading(_)) => {
+                let Some((level, id, start, text)) = current.take() else {
+                    continue;
+                };
+                let text = text.trim().to_string();
+
+                // Repeated headings get -1, -2, ... like GitHub's anchors
+                let base = id.unwrap_or_else(|| slugify(&text));
+                let count = seen.entry(base.clone()).or_insert(0);
+                let slug = if *count == 0 {
+                    base
+                } else {
+                    format!("{}-{}", base, count)
+                };
+                *count += 1;
+
+                // Setext headings and `#` lines both end after the newline
+                let end = start + content[offset + start..offset + range.end].trim_end().len();
+                headings.push(Heading {
+                    level,
+                    text,
+                    slug,
+                    line: line_of(&starts, offset + start),
+                    start: offset + start,
+                    end: offset + end,
+                });
+            }
+            _ => {}
+        }
+    }
+
+    headings
+}
+
+/// Heading outline of a note, for the table of contents panel
+#[tauri::command(async)]
+pub fn get_outline(path: String) -> Result<Vec<Heading>, String> {
+    let content = fs::read_to_string(&path).map_err(|e| format!("Failed to read file: {}", e))?;
+    Ok(headings(&content))
+}
@@ -0,0 +1,185 @@
+use pulldown_cmark::{Event, Parser, Tag, TagEnd};
+use regex::RegexBuilder;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::error::CommandError;
+use crate::links::normalize_path;
+use crate::markdown;
+use crate::metadata_cache::MetadataCache;
+use crate::note_index::NoteIndexRegistry;
+use crate::search::{line_of, line_starts};
+use crate::settings::{self, SettingsStore};
+use crate::WriteResult;
+
+#[derive(Debug, Serialize)]
+pub struct Mention {
+    /// The note mentioning the target
+    pub file: String,
+    /// 1-based line number
+    pub line: usize,
+    /// Byte offsets into the file
+    pub start: usize,
+    pub end: usize,
+    /// The name as written
+    pub text: String,
+    /// The full line the mention is on
+    pub line_text: String,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct TextRange {
+    pub start: usize,
+    pub end: usize,
+}
+
+/// Names a note goes by: its file name, first `#` heading and aliases
+fn names_of(path: &Path, content: &str) -> Vec<String> {
+    let mut names: Vec<String> = path.file_stem().map(|s| s.to_string_lossy().to_string()).into_iter().collect();
+    if let Some(heading) = markdown::headings(content).into_iter().find(|h| h.level == 1) {
+        names.push(heading.text);
+    }
+    names.extend(crate::links::aliases(content));
+    names.retain(|name| !name.trim().is_empty());
+    names.sort_by_key(|name| std::cmp::Reverse(name.len()));
+    let mut seen: Vec<String> = Vec::new();
+    names.retain(|name| {
+        let lower = name.to_lowercase();
+        let new = !seen.contains(&lower);
+        seen.push(lower);
+        new
+    });
+    names
+}
+
+/// Byte ranges of `content` holding plain prose: outside the frontmatter,
+/// links, code and HTML
+pub fn prose_ranges(content: &str) -> Vec<Range<usize>> {
+    let offset = markdown::body_start(content);
+    let mut ranges: Vec<Range<usize>> = Vec::new();
+    let mut skip_depth = 0;
+    for (event, range) in Parser::new_ext(&content[offset..], markdown::parser_options()).into_offset_iter() {
+        match event {
+            Event::Start(Tag::Link { .. }) | Event::Start(Tag::Image { .. }) | Event::Start(Tag::CodeBlock(_)) => {
+                skip_depth += 1
+            }
+            Event::End(TagEnd::Link) | Event::End(TagEnd::Image) | Event::End(TagEnd::CodeBlock) => skip_depth -= 1,
+            Event::Text(_) if skip_depth == 0 => {
+                let range = offset + range.start..offset + range.end;
+                // Adjacent text events, e.g. split at a `[`, are one range
+                match ranges.last_mut() {
+                    Some(last) if last.end == range.start => last.end = range.end,
+                    _ => ranges.push(range),
+                }
+            }
+            _ => {}
+        }
+    }
+    ranges
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// Whole-word, case-insensitive occurrences of `names` in the prose of
+/// `content`
+fn mentions_in(file: &Path, content: &str, names: &[String]) -> Result<Vec<Mention>, String> {
+    let pattern = names.iter().map(|name| regex::escape(name)).collect::<Vec<_>>().join("|");
+    let regex = RegexBuilder::new(&pattern)
+        .case_insensitive(true)
+        .build()
+        .map_err(|e| format!("Invalid pattern: {}", e))?;
+    let starts = line_starts(content);
+    let mut mentions = Vec::new();
+    for range in prose_ranges(content) {
+        let text = &content[range.clone()];
+        for found in regex.find_iter(text) {
+            let before = text[..found.start()].chars().next_back();
+            let after = text[found.end()..].chars().next();
+            if before.is_some_and(is_word_char) || after.is_some_and(is_word_char) {
+                continue;
+            }
+            let start = range.start + found.start();
+            let line = line_of(&starts, start);
+            let line_start = starts[line - 1];
+            let line_end = content[line_start..].find('\n').map_or(content.len(), |i| line_start + i);
+            mentions.push(Mention {
+                file: file.to_string_lossy().to_string(),
+                line,
+                start,
+                end: range.start + found.end(),
+                text: found.as_str().to_string(),
+                line_text: content[line_start..line_end].trim_end_matches('\r').to_string(),
+            });
+        }
+    }
+    Ok(mentions)
+}
+
+/// Places in other notes of the vault where the note at `path` is named, by
+/// file name, title or alias, without being linked. Mentions inside links,
+/// code and frontmatter are skipped.
+#[tauri::command(async)]
+pub fn find_unlinked_mentions(
+    path: String,
+    registry: tauri::State<'_, NoteIndexRegistry>,
+) -> Result<Vec<Mention>, String> {
+    let target = normalize_path(Path::new(&path));
+    let index = registry
+        .for_path(&target)
+        .ok_or_else(|| format!("No open vault contains {}", path))?;
+    let content = fs::read_to_string(&target).map_err(|e| format!("Failed to read file: {}", e))?;
+    let names = names_of(&target, &content);
+    if names.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mut sources: Vec<PathBuf> = index.contents()?.notes.keys().filter(|p| **p != target).cloned().collect();
+    sources.sort();
+    let mut mentions = Vec::new();
+    for source in sources {
+        let Ok(text) = fs::read_to_string(&source) else {
+            continue;
+        };
+        mentions.extend(mentions_in(&source, &text, &names)?);
+    }
+    Ok(mentions)
+}
+
+/// Turn the mention at `range` of the note at `path` into a `[[link]]` to the
+/// note `target`, keeping the text as written as the alias if it differs
+/// from the note's name. Returns the new file state.
+#[tauri::command(async)]
+pub fn link_mention(
+    path: String,
+    range: TextRange,
+    target: String,
+    registry: tauri::State<'_, NoteIndexRegistry>,
+    settings: tauri::State<'_, Mutex<SettingsStore>>,
+    cache: tauri::State<'_, MetadataCache>,
+) -> Result<WriteResult, CommandError> {
+    let path_buf = PathBuf::from(&path);
+    settings::check_writable(&settings, &path_buf)?;
+    let previous = fs::read_to_string(&path_buf).map_err(|e| format!("Failed to read file: {}", e))?;
+    let text = previous
+        .get(range.start..range.end)
+        .filter(|text| !text.trim().is_empty() && !text.contains(['\n', '[', ']']))
+        .ok_or_else(|| format!("No mention at {}..{}", range.start, range.end))?;
+    let name = Path::new(&target)
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .ok_or_else(|| format!("Not a note: {}", target))?;
+    let link = if text == name {
+        format!("[[{}]]", name)
+    } else {
+        format!("[[{}|{}]]", name, text)
+    };
+    let mut content = previous.clone();
+    content.replace_range(range.start..range.end, &link);
+
+    Ok(crate::write_note(&path_buf, Some(previous.as_bytes()), content.as_bytes(), &registry, &settings, &cache)?)
+}
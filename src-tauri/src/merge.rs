@@ -0,0 +1,301 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Mutex;
+
+use crate::error::CommandError;
+use crate::frontmatter;
+use crate::links::normalize_path;
+use crate::markdown;
+use crate::metadata_cache::MetadataCache;
+use crate::note_index::NoteIndexRegistry;
+use crate::pins;
+use crate::rename::{self, LinkUpdate};
+use crate::settings::{self, SettingsStore};
+
+/// Folder, relative to the vault root, merged notes are archived to when
+/// none is given
+const DEFAULT_ARCHIVE_DIR: &str = "archive";
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MergeMode {
+    /// The source's body goes after the target's
+    #[default]
+    Append,
+    /// Sections of the source go under the target's sections with the same
+    /// heading; the rest is appended
+    Interleave,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SourceAction {
+    #[default]
+    Keep,
+    /// Move the source to the OS trash
+    Delete,
+    /// Move the source into the archive folder
+    Archive,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct MergeOptions {
+    pub mode: MergeMode,
+    pub source_action: SourceAction,
+    /// Relative to the vault root; `archive` by default
+    pub archive_dir: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MergeSummary {
+    pub target: String,
+    /// Where the source is now: `None` once deleted, its new path if archived
+    pub source: Option<String>,
+    /// Sections of the source merged under a heading of the target
+    pub sections_merged: usize,
+    /// Notes whose links to the source now point at the target
+    pub links_redirected: Vec<String>,
+}
+
+/// A heading and the text up to the next one, or the text before the first
+/// heading
+struct Section<'a> {
+    heading: Option<(u8, String)>,
+    text: &'a str,
+    /// Source text merged into this section
+    extra: Vec<&'a str>,
+}
+
+fn sections(body: &str) -> Vec<Section<'_>> {
+    let headings = markdown::headings(body);
+    let mut sections = Vec::with_capacity(headings.len() + 1);
+    let first = headings.first().map_or(body.len(), |h| h.start);
+    sections.push(Section {
+        heading: None,
+        text: &body[..first],
+        extra: Vec::new(),
+    });
+    for (i, heading) in headings.iter().enumerate() {
+        let end = headings.get(i + 1).map_or(body.len(), |next| next.start);
+        sections.push(Section {
+            heading: Some((heading.level, heading.text.to_lowercase())),
+            text: &body[heading.start..end],
+            extra: Vec::new(),
+        });
+    }
+    sections
+}
+
+/// `base` followed by `addition`, a blank line apart
+fn join_block(base: &str, addition: &str) -> String {
+    let addition = addition.trim_matches(['\r', '\n']);
+    let base = base.trim_end();
+    match (base.is_empty(), addition.trim().is_empty()) {
+        (_, true) => format!("{}\n", base),
+        (true, false) => format!("{}\n", addition),
+        (false, false) => format!("{}\n\n{}\n", base, addition),
+    }
+}
+
+/// The target body with the source's sections merged under matching
+/// headings, and the number of sections merged so
+fn interleave(target: &str, source: &str) -> (String, usize) {
+    let mut merged = sections(target);
+    let mut tail: Vec<&str> = Vec::new();
+    let mut matched = 0;
+    // Unmatched source sections follow the section they came after, as long
+    // as they are nested in it
+    let mut anchor: Option<(usize, u8)> = None;
+
+    for section in sections(source) {
+        let Some((level, text)) = &section.heading else {
+            merged[0].extra.push(section.text);
+            continue;
+        };
+        let found = merged.iter().position(|s| s.heading.as_ref() == Some(&(*level, text.clone())));
+        if let Some(i) = found {
+            let body = section.text.split_once('\n').map_or("", |(_, body)| body);
+            merged[i].extra.push(body);
+            matched += 1;
+            anchor = Some((i, *level));
+            continue;
+        }
+        match anchor {
+            Some((i, anchor_level)) if *level > anchor_level => merged[i].extra.push(section.text),
+            _ => {
+                anchor = None;
+                tail.push(section.text);
+            }
+        }
+    }
+
+    let count = merged.len();
+    let mut body = String::new();
+    for (i, section) in merged.into_iter().enumerate() {
+        if section.extra.is_empty() {
+            body.push_str(section.text);
+            continue;
+        }
+        let mut text = section.extra.iter().fold(section.text.to_string(), |text, extra| join_block(&text, extra));
+        if i + 1 < count && !text.trim().is_empty() {
+            text.push('\n');
+        }
+        body.push_str(&text);
+    }
+    let body = tail.iter().fold(body, |body, text| join_block(&body, text));
+    (body, matched)
+}
+
+/// The target's frontmatter with the source's added: missing keys are
+/// copied and lists such as `tags` combined
+fn merge_fields(target: &mut Map<String, Value>, source: Map<String, Value>) {
+    for (key, value) in source {
+        match (target.get_mut(&key), value) {
+            (None, value) => {
+                target.insert(key, value);
+            }
+            (Some(Value::Array(items)), Value::Array(more)) => {
+                for item in more {
+                    if !items.contains(&item) {
+                        items.push(item);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// `target` with `source` merged into it per `mode`, and the number of
+/// sections merged under a heading
+fn merge_content(target: &str, source: &str, mode: MergeMode) -> Result<(String, usize), String> {
+    let target_start = markdown::body_start(target);
+    let source_block = frontmatter::split(source);
+    let source_body = &source[source_block.as_ref().map_or(0, |block| block.body_start)..];
+    let (body, matched) = match mode {
+        MergeMode::Append => (join_block(&target[target_start..], source_body), 0),
+        MergeMode::Interleave => interleave(&target[target_start..], source_body),
+    };
+    let content = format!("{}{}", &target[..target_start], body);
+
+    let source_fields = source_block.and_then(|block| frontmatter::parse(&block).ok()).unwrap_or_default();
+    if source_fields.is_empty() {
+        return Ok((content, matched));
+    }
+    let mut fields = match frontmatter::split(target) {
+        Some(block) => frontmatter::parse(&block)?,
+        None => Map::new(),
+    };
+    merge_fields(&mut fields, source_fields);
+    Ok((frontmatter::replace(&content, &fields)?, matched))
+}
+
+/// Merge the note `source` into the note `target`: its body is appended or,
+/// with the `interleave` mode, merged section by section, and its
+/// frontmatter combined with the target's. Links to the source anywhere in
+/// the vault are redirected to the target. The source is then kept, moved
+/// to the trash or archived, per `source_action`.
+#[tauri::command(async)]
+pub fn merge_notes(
+    source: String,
+    target: String,
+    options: Option<MergeOptions>,
+    registry: tauri::State<'_, NoteIndexRegistry>,
+    settings: tauri::State<'_, Mutex<SettingsStore>>,
+    cache: tauri::State<'_, MetadataCache>,
+) -> Result<MergeSummary, CommandError> {
+    let options = options.unwrap_or_default();
+    let source_path = normalize_path(Path::new(&source));
+    let target_path = normalize_path(Path::new(&target));
+    settings::check_writable(&settings, &source_path)?;
+    settings::check_writable(&settings, &target_path)?;
+    if source_path == target_path {
+        return Err("Can't merge a note into itself".to_string().into());
+    }
+    for path in [&source_path, &target_path] {
+        if !path.is_file() {
+            return Err(format!("File does not exist: {}", path.display()).into());
+        }
+    }
+    let index = registry
+        .for_path(&source_path)
+        .ok_or_else(|| format!("No open vault contains {}", source))?;
+    // Where the source goes, checked before anything changes
+    let archive_path = match options.source_action {
+        SourceAction::Archive => {
+            let dir = settings::normalize_vault_path(
+                options.archive_dir.as_deref().unwrap_or(DEFAULT_ARCHIVE_DIR),
+                "Archive folder",
+            )?;
+            let name = source_path.file_name().ok_or_else(|| format!("Not a file: {}", source))?;
+            let path = normalize_path(&index.root().join(dir).join(name));
+            if path.exists() {
+                return Err(format!("Already in the archive: {}", path.display()).into());
+            }
+            Some(path)
+        }
+        _ => None,
+    };
+
+    // Planned as if the source moved onto the target: links to it are
+    // redirected, and its own relative links made to work from the target's
+    // folder
+    let mut updates = rename::plan_updates(&index, &HashMap::from([(source_path.clone(), target_path.clone())]))?;
+    let mut take = |from: &Path| -> Result<String, String> {
+        match updates.iter().position(|update| update.from == from) {
+            Some(i) => Ok(updates.remove(i).content),
+            None => crate::read_note(from).map(|(_, content)| content),
+        }
+    };
+    let source_content = take(&source_path)?;
+    let target_content = take(&target_path)?;
+    let (previous, _) = crate::read_note(&target_path)?;
+    let (merged, sections_merged) = merge_content(&target_content, &source_content, options.mode)?;
+
+    let mut links_redirected: Vec<String> = updates.iter().map(|u| u.path.to_string_lossy().to_string()).collect();
+    links_redirected.sort();
+    updates.push(LinkUpdate {
+        from: target_path.clone(),
+        path: target_path.clone(),
+        content: merged,
+        previous,
+    });
+    rename::write_all_or_none(&updates, &registry, &settings, &cache)?;
+
+    let source_now = match (options.source_action, archive_path) {
+        (SourceAction::Delete, _) => {
+            trash::delete(&source_path).map_err(|e| format!("Merged, but failed to move the source to trash: {}", e))?;
+            None
+        }
+        (SourceAction::Archive, Some(archive)) => {
+            let created = crate::first_missing_ancestor(&archive);
+            crate::move_path(&source_path, &archive, false).map_err(|e| format!("Merged, but {}", e))?;
+            cache.invalidate(created.as_deref().unwrap_or(&archive));
+            Some(archive)
+        }
+        _ => Some(source_path.clone()),
+    };
+    if source_now.as_ref() != Some(&source_path) {
+        let to = source_now.as_deref().unwrap_or(&target_path);
+        pins::record_moves(index.root(), &[(&source_path, to)]);
+        index.update_path(&source_path);
+        cache.invalidate(&source_path);
+    }
+
+    for update in &updates {
+        index.update_path(&update.path);
+    }
+    if let Some(path) = &source_now {
+        index.update_path(path);
+    }
+    cache.invalidate(&target_path);
+    Ok(MergeSummary {
+        target: target_path.to_string_lossy().to_string(),
+        source: source_now.map(|path| path.to_string_lossy().to_string()),
+        sections_merged,
+        links_redirected,
+    })
+}
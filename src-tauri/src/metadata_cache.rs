@@ -0,0 +1,157 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use crate::FileEntry;
+
+#[derive(Default)]
+struct Cached {
+    /// Recursively watched folders, with the number of watchers on each
+    roots: HashMap<PathBuf, usize>,
+    /// Note extensions the entries were built with; other extensions drop
+    /// everything
+    note_extensions: Vec<String>,
+    /// Every entry of a directory, unfiltered
+    dirs: HashMap<PathBuf, Arc<Vec<FileEntry>>>,
+    files: HashMap<PathBuf, FileEntry>,
+    /// Bumped on every invalidation, so a read that raced with a change
+    /// isn't stored
+    generation: u64,
+}
+
+impl Cached {
+    fn covers(&self, path: &Path) -> bool {
+        self.roots.keys().any(|root| path.starts_with(root))
+    }
+
+    fn check_extensions(&mut self, note_extensions: &[String]) {
+        if self.note_extensions != note_extensions {
+            self.dirs.clear();
+            self.files.clear();
+            self.note_extensions = note_extensions.to_vec();
+        }
+    }
+
+    fn forget(&mut self, path: &Path) {
+        self.dirs.retain(|dir, _| !dir.starts_with(path));
+        self.files.retain(|file, _| !file.starts_with(path));
+    }
+}
+
+/// Directory listings and file metadata under recursively watched folders,
+/// dropped as the watchers report changes. Paths outside every watch are
+/// always read from disk, as nothing would tell the cache they changed.
+#[derive(Default)]
+pub struct MetadataCache {
+    inner: Arc<Mutex<Cached>>,
+}
+
+/// Keeps a watched folder cacheable until dropped along with its watcher
+pub struct CacheWatch {
+    inner: Arc<Mutex<Cached>>,
+    root: PathBuf,
+}
+
+impl Drop for CacheWatch {
+    fn drop(&mut self) {
+        let Ok(mut cached) = self.inner.lock() else {
+            return;
+        };
+        let remaining = cached.roots.get_mut(&self.root).map(|count| {
+            *count -= 1;
+            *count
+        });
+        if remaining == Some(0) {
+            cached.roots.remove(&self.root);
+            if !cached.covers(&self.root) {
+                cached.forget(&self.root);
+            }
+        }
+    }
+}
+
+impl MetadataCache {
+    /// Cache paths under `root` while the returned guard lives. The watcher
+    /// of `root` holds it, so the cache never outlives the events that keep
+    /// it fresh.
+    pub fn watch(&self, root: &Path) -> CacheWatch {
+        if let Ok(mut cached) = self.inner.lock() {
+            *cached.roots.entry(root.to_path_buf()).or_insert(0) += 1;
+        }
+        CacheWatch {
+            inner: Arc::clone(&self.inner),
+            root: root.to_path_buf(),
+        }
+    }
+
+    /// Every entry of the directory `dir`, from the cache or `read`
+    pub fn listing(
+        &self,
+        dir: &Path,
+        note_extensions: &[String],
+        read: impl FnOnce() -> Result<Vec<FileEntry>, String>,
+    ) -> Result<Arc<Vec<FileEntry>>, String> {
+        let generation = {
+            let mut cached = self.inner.lock().map_err(|e| format!("Lock error: {}", e))?;
+            if !cached.covers(dir) {
+                return read().map(Arc::new);
+            }
+            cached.check_extensions(note_extensions);
+            if let Some(entries) = cached.dirs.get(dir) {
+                return Ok(Arc::clone(entries));
+            }
+            cached.generation
+        };
+
+        let entries = Arc::new(read()?);
+        let mut cached = self.inner.lock().map_err(|e| format!("Lock error: {}", e))?;
+        if cached.generation == generation {
+            cached.dirs.insert(dir.to_path_buf(), Arc::clone(&entries));
+        }
+        Ok(entries)
+    }
+
+    /// The entry for `path`, from the cache or `read`
+    pub fn entry(&self, path: &Path, note_extensions: &[String], read: impl FnOnce() -> FileEntry) -> FileEntry {
+        let generation = {
+            let Ok(mut cached) = self.inner.lock() else {
+                return read();
+            };
+            if !cached.covers(path) {
+                return read();
+            }
+            cached.check_extensions(note_extensions);
+            if let Some(entry) = cached.files.get(path) {
+                return entry.clone();
+            }
+            let listed = path.parent().and_then(|parent| cached.dirs.get(parent)).and_then(|entries| {
+                entries.iter().find(|entry| Path::new(&entry.path) == path).cloned()
+            });
+            if let Some(entry) = listed {
+                return entry;
+            }
+            cached.generation
+        };
+
+        let entry = read();
+        if let Ok(mut cached) = self.inner.lock() {
+            if cached.generation == generation {
+                cached.files.insert(path.to_path_buf(), entry.clone());
+            }
+        }
+        entry
+    }
+
+    /// Drop what a change at `path` may have made stale: its own entry, the
+    /// listing of its folder, and everything below it if it is a folder
+    pub fn invalidate(&self, path: &Path) {
+        let Ok(mut cached) = self.inner.lock() else {
+            return;
+        };
+        cached.generation += 1;
+        cached.forget(path);
+        if let Some(parent) = path.parent() {
+            cached.dirs.remove(parent);
+        }
+    }
+}
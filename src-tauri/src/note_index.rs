@@ -0,0 +1,220 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock, RwLockReadGuard};
+use std::thread;
+
+use crate::blocks::{self, BlockId};
+use crate::links::{self, Link};
+use crate::settings::{self, SettingsStore};
+use crate::stats::{self, NoteStats};
+use crate::tags;
+use crate::tasks::{self, Task};
+
+/// Number of notes parsed between taking the write lock during a full scan
+const BATCH_SIZE: usize = 200;
+
+/// What the backend knows about a single note, re-extracted when it changes
+#[derive(Debug, Clone, Default)]
+pub struct NoteData {
+    pub links: Vec<Link>,
+    /// Distinct tags from the frontmatter and body, without the `#`
+    pub tags: Vec<String>,
+    pub tasks: Vec<Task>,
+    pub stats: NoteStats,
+    /// Frontmatter `aliases`, which `[[wiki links]]` can use instead of the
+    /// file name
+    pub aliases: Vec<String>,
+    /// `^block-id` anchors that `[[Note#^id]]` links point at
+    pub blocks: Vec<BlockId>,
+}
+
+impl NoteData {
+    pub fn parse(content: &str) -> Self {
+        NoteData {
+            links: links::extract(content),
+            tags: tags::extract(content),
+            tasks: tasks::extract(content),
+            stats: stats::compute(content),
+            aliases: links::aliases(content),
+            blocks: blocks::block_ids(content),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct VaultContents {
+    pub notes: HashMap<PathBuf, NoteData>,
+    /// Every other file in the vault (images, PDFs, ...), for resolving
+    /// attachment links by name
+    pub attachments: HashSet<PathBuf>,
+}
+
+impl VaultContents {
+    /// Drop `path`, and everything under it if it was a directory
+    fn remove(&mut self, path: &Path) {
+        self.notes.retain(|p, _| !p.starts_with(path));
+        self.attachments.retain(|p| !p.starts_with(path));
+    }
+}
+
+/// Parsed structure of every note in a vault, kept in memory and updated from
+/// watcher events. Backs the queries that need to look at all notes at once,
+/// such as backlinks.
+pub struct NoteIndex {
+    root: PathBuf,
+    note_extensions: Vec<String>,
+    contents: RwLock<VaultContents>,
+}
+
+impl NoteIndex {
+    fn new(root: &Path, note_extensions: Vec<String>) -> Self {
+        NoteIndex {
+            root: root.to_path_buf(),
+            note_extensions,
+            contents: RwLock::new(VaultContents::default()),
+        }
+    }
+
+    /// Scan `root` on the current thread, for one-off queries on a vault
+    /// that isn't open
+    pub fn build(root: &Path, note_extensions: Vec<String>) -> Self {
+        let index = NoteIndex::new(root, note_extensions);
+        index.add_dir(root);
+        index
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    pub fn note_extensions(&self) -> &[String] {
+        &self.note_extensions
+    }
+
+    pub fn contents(&self) -> Result<RwLockReadGuard<'_, VaultContents>, String> {
+        self.contents.read().map_err(|e| format!("Lock error: {}", e))
+    }
+
+    fn is_note(&self, path: &Path) -> bool {
+        settings::has_note_extension(path, &self.note_extensions)
+    }
+
+    fn read_note(path: &Path) -> Option<NoteData> {
+        let bytes = fs::read(path).ok()?;
+        Some(NoteData::parse(&String::from_utf8_lossy(&bytes)))
+    }
+
+    /// Add every file under `dir`, parsing notes a batch at a time so
+    /// queries aren't blocked for the whole scan
+    fn add_dir(&self, dir: &Path) {
+        let walker = match crate::ignore_rules::walker(dir, &[]) {
+            Ok(walker) => walker,
+            Err(e) => return eprintln!("Note index error for {}: {}", dir.display(), e),
+        };
+        let (notes, attachments): (Vec<PathBuf>, Vec<PathBuf>) = walker
+            .build()
+            .filter_map(|e| e.ok())
+            .map(|e| e.into_path())
+            .filter(|p| p.is_file())
+            .partition(|p| self.is_note(p));
+
+        if let Ok(mut contents) = self.contents.write() {
+            contents.attachments.extend(attachments);
+        }
+        for batch in notes.chunks(BATCH_SIZE) {
+            let parsed: Vec<(PathBuf, NoteData)> = batch
+                .iter()
+                .filter_map(|path| Self::read_note(path).map(|note| (path.clone(), note)))
+                .collect();
+            if let Ok(mut contents) = self.contents.write() {
+                contents.notes.extend(parsed);
+            }
+        }
+    }
+
+    fn spawn_build(self: &Arc<Self>) {
+        let index = Arc::clone(self);
+        thread::spawn(move || index.add_dir(&index.root));
+    }
+
+    /// Bring the index up to date after `path` was created, changed or removed
+    pub fn update_path(&self, path: &Path) {
+        if path.is_dir() {
+            // A directory appeared (e.g. moved into the vault)
+            return self.add_dir(path);
+        }
+
+        let note = if self.is_note(path) { Self::read_note(path) } else { None };
+        let Ok(mut contents) = self.contents.write() else {
+            return;
+        };
+        match note {
+            Some(note) => {
+                contents.notes.insert(path.to_path_buf(), note);
+            }
+            None if path.is_file() => {
+                contents.attachments.insert(path.to_path_buf());
+            }
+            None => contents.remove(path),
+        }
+    }
+}
+
+/// Note indexes of the vaults opened in this session
+#[derive(Default)]
+pub struct NoteIndexRegistry {
+    indexes: Mutex<HashMap<PathBuf, Arc<NoteIndex>>>,
+}
+
+impl NoteIndexRegistry {
+    /// Get the index for `root`, building it in the background the first time
+    pub fn open(&self, root: &Path, note_extensions: Vec<String>) -> Result<Arc<NoteIndex>, String> {
+        let mut indexes = self.indexes.lock().map_err(|e| format!("Lock error: {}", e))?;
+        if let Some(index) = indexes.get(root) {
+            return Ok(Arc::clone(index));
+        }
+
+        let index = Arc::new(NoteIndex::new(root, note_extensions));
+        index.spawn_build();
+        indexes.insert(root.to_path_buf(), Arc::clone(&index));
+        Ok(index)
+    }
+
+    /// The index of the open vault at or around `root`, or a one-off scan of
+    /// `root` if no such vault is open
+    pub fn for_vault(&self, root: &Path, settings: &Mutex<SettingsStore>) -> Result<Arc<NoteIndex>, String> {
+        crate::ensure_dir(root)?;
+        match self.for_path(root) {
+            Some(index) => Ok(index),
+            None => Ok(Arc::new(NoteIndex::build(root, settings::note_extensions(settings)?))),
+        }
+    }
+
+    /// Root of the innermost open vault containing `path`, or the folder
+    /// `path` is in when no vault is open around it
+    pub fn root_for(&self, path: &Path) -> PathBuf {
+        match self.for_path(path) {
+            Some(index) => index.root().to_path_buf(),
+            None => path.parent().map(Path::to_path_buf).unwrap_or_default(),
+        }
+    }
+
+    /// The indexes of every open vault
+    pub fn indexes(&self) -> Vec<Arc<NoteIndex>> {
+        match self.indexes.lock() {
+            Ok(indexes) => indexes.values().cloned().collect(),
+            Err(_) => Vec::new(),
+        }
+    }
+
+    /// The index of the innermost open vault containing `path`
+    pub fn for_path(&self, path: &Path) -> Option<Arc<NoteIndex>> {
+        let indexes = self.indexes.lock().ok()?;
+        indexes
+            .iter()
+            .filter(|(root, _)| path.starts_with(root))
+            .max_by_key(|(root, _)| root.components().count())
+            .map(|(_, index)| Arc::clone(index))
+    }
+}
@@ -0,0 +1,84 @@
+use chrono::Local;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::error::CommandError;
+use crate::metadata_cache::MetadataCache;
+use crate::settings::{self, SettingsStore};
+use crate::templates;
+use crate::FileEntry;
+
+/// Date pattern of note ids when none is given, e.g. `202406151230`
+const DEFAULT_ID_FORMAT: &str = "YYYYMMDDHHmm";
+
+/// Names tried by `create_unique_note` before giving up
+const MAX_UNIQUE_ATTEMPTS: usize = 10_000;
+
+/// A timestamp id for a new note, formatted with a date pattern such as
+/// `YYYYMMDDHHmmss`; `YYYYMMDDHHmm` by default
+#[tauri::command]
+pub fn generate_note_id(format: Option<String>) -> Result<String, String> {
+    let format = format.unwrap_or_else(|| DEFAULT_ID_FORMAT.to_string());
+    let now = Local::now();
+    templates::format_date_pattern(&format, now.date_naive(), now.time())
+        .ok_or_else(|| format!("Not a date pattern: {}", format))
+}
+
+/// Create an empty note named `base_name` in `folder`, or `base_name 2`,
+/// `base_name 3` and so on if that is taken. Each name is claimed with an
+/// exclusive create, so a file appearing at the same time, e.g. from a sync
+/// client, is never overwritten. The first note extension is added unless
+/// `base_name` has one.
+#[tauri::command(async)]
+pub fn create_unique_note(
+    folder: String,
+    base_name: String,
+    settings: tauri::State<'_, Mutex<SettingsStore>>,
+    cache: tauri::State<'_, MetadataCache>,
+) -> Result<FileEntry, CommandError> {
+    let dir = PathBuf::from(&folder);
+    settings::check_writable(&settings, &dir)?;
+    let base_name = base_name.trim();
+    if base_name.is_empty() || base_name.contains(['/', '\\']) || base_name == "." || base_name == ".." {
+        return Err(format!("Invalid note name: {}", base_name).into());
+    }
+    let note_extensions = settings::note_extensions(&settings)?;
+    let (stem, extension) = if settings::has_note_extension(&dir.join(base_name), &note_extensions) {
+        let (stem, extension) = base_name.rsplit_once('.').unwrap_or((base_name, ""));
+        (stem.to_string(), extension.to_string())
+    } else {
+        let extension = note_extensions.first().map(String::as_str).unwrap_or("md");
+        (base_name.to_string(), extension.to_string())
+    };
+
+    let created = crate::first_missing_ancestor(&dir.join(&stem));
+    let path = create_unique(&dir, &stem, &extension, "")?;
+    cache.invalidate(created.as_deref().unwrap_or(&path));
+    Ok(crate::file_entry_for(&path, &note_extensions))
+}
+
+/// Create the file `stem.extension` in `dir` with `content`, or
+/// `stem 2.extension` and so on if that is taken, and return its path.
+/// Names are claimed with an exclusive create, so no file is overwritten.
+pub fn create_unique(dir: &Path, stem: &str, extension: &str, content: &str) -> Result<PathBuf, String> {
+    fs::create_dir_all(dir).map_err(|e| format!("Failed to create directory: {}", e))?;
+    for attempt in 1..=MAX_UNIQUE_ATTEMPTS {
+        let name = match attempt {
+            1 => format!("{}.{}", stem, extension),
+            n => format!("{} {}.{}", stem, n, extension),
+        };
+        let path = dir.join(name);
+        match fs::OpenOptions::new().write(true).create_new(true).open(&path) {
+            Ok(mut file) => {
+                file.write_all(content.as_bytes())
+                    .map_err(|e| format!("Failed to write file: {}", e))?;
+                return Ok(path);
+            }
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(format!("Failed to create file: {}", e)),
+        }
+    }
+    Err(format!("No free name for {} in {}", stem, dir.display()))
+}
@@ -0,0 +1,351 @@
+use pulldown_cmark::{Event, Parser, Tag, TagEnd};
+use quick_xml::events::{BytesStart, Event as XmlEvent};
+use quick_xml::Reader;
+use std::fs;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::encoding;
+use crate::encryption;
+use crate::error::CommandError;
+use crate::export::{self, escape_html};
+use crate::import::{self, ImportFailure, ImportSummary};
+use crate::links;
+use crate::markdown;
+use crate::settings::{self, SettingsStore};
+
+/// An `<outline>` element and the ones nested in it
+#[derive(Debug, Default)]
+struct Outline {
+    text: String,
+    /// `_note`, the outline's description
+    note: String,
+    /// `htmlUrl` of a feed, or `url` of a link
+    url: Option<String>,
+    /// `xmlUrl` of a feed
+    feed: Option<String>,
+    /// `_complete`, for the outline of a task
+    complete: Option<bool>,
+    children: Vec<Outline>,
+}
+
+fn outline_from(start: &BytesStart) -> Outline {
+    let mut outline = Outline::default();
+    for attribute in start.attributes().flatten() {
+        let value = attribute.unescape_value().map(|value| value.to_string()).unwrap_or_default();
+        match attribute.key.as_ref() {
+            b"text" => outline.text = value,
+            // Some apps write only a title
+            b"title" if outline.text.is_empty() => outline.text = value,
+            b"_note" => outline.note = value,
+            b"htmlUrl" | b"url" => outline.url = Some(value).filter(|url| !url.is_empty()),
+            b"xmlUrl" => outline.feed = Some(value).filter(|url| !url.is_empty()),
+            b"_complete" => outline.complete = Some(value == "true"),
+            _ => {}
+        }
+    }
+    outline
+}
+
+/// The title in the head of an OPML file and its top-level outlines
+fn read_opml(file: &str) -> Result<(String, Vec<Outline>), String> {
+    let input = fs::File::open(file).map_err(|e| format!("Failed to open file: {}", e))?;
+    let mut reader = Reader::from_reader(BufReader::new(input));
+    let mut buf = Vec::new();
+    let mut title = String::new();
+    let mut in_title = false;
+    // The open outlines, outermost first, under a root for the body
+    let mut open = vec![Outline::default()];
+    loop {
+        let event = reader
+            .read_event_into(&mut buf)
+            .map_err(|e| format!("Failed to parse {} at byte {}: {}", file, reader.buffer_position(), e))?;
+        match event {
+            XmlEvent::Start(start) if start.local_name().as_ref() == b"outline" => open.push(outline_from(&start)),
+            XmlEvent::Empty(start) if start.local_name().as_ref() == b"outline" => {
+                let outline = outline_from(&start);
+                if let Some(parent) = open.last_mut() {
+                    parent.children.push(outline);
+                }
+            }
+            XmlEvent::End(end) if end.local_name().as_ref() == b"outline" && open.len() > 1 => {
+                if let Some(outline) = open.pop() {
+                    if let Some(parent) = open.last_mut() {
+                        parent.children.push(outline);
+                    }
+                }
+            }
+            XmlEvent::Start(start) if start.local_name().as_ref() == b"title" => in_title = true,
+            XmlEvent::End(end) if end.local_name().as_ref() == b"title" => in_title = false,
+            XmlEvent::Text(text) if in_title => {
+                title.push_str(&text.decode().map_err(|e| format!("Failed to parse {}: {}", file, e))?)
+            }
+            XmlEvent::Eof => break,
+            _ => {}
+        }
+        buf.clear();
+    }
+    // Unclosed outlines still count
+    while open.len() > 1 {
+        if let Some(outline) = open.pop() {
+            if let Some(parent) = open.last_mut() {
+                parent.children.push(outline);
+            }
+        }
+    }
+    Ok((title.trim().to_string(), open.pop().map(|root| root.children).unwrap_or_default()))
+}
+
+/// Markdown for the text of an outline, which some apps write as HTML
+fn outline_text(outline: &Outline) -> String {
+    let text = outline.text.trim();
+    let text = match text.contains('<') && text.contains('>') {
+        true => import::markdown_from_html(text).trim().to_string(),
+        false => text.to_string(),
+    };
+    let text = match &outline.url {
+        Some(url) => format!("[{}]({})", text, url),
+        None => text,
+    };
+    match &outline.feed {
+        Some(feed) => format!("{} ([feed]({}))", text, feed),
+        None => text,
+    }
+}
+
+/// `outlines` as list items indented by `depth` levels
+fn push_list(lines: &mut Vec<String>, outlines: &[Outline], depth: usize) {
+    let indent = "  ".repeat(depth);
+    for outline in outlines {
+        let checkbox = match outline.complete {
+            Some(true) => "[x] ",
+            Some(false) => "[ ] ",
+            None => "",
+        };
+        lines.push(format!("{}- {}{}", indent, checkbox, outline_text(outline)).trim_end().to_string());
+        if !outline.note.trim().is_empty() {
+            lines.push(String::new());
+            let note = outline.note.trim().lines();
+            lines.extend(note.map(|line| format!("{}  {}", indent, line).trim_end().to_string()));
+            lines.push(String::new());
+        }
+        push_list(lines, &outline.children, depth + 1);
+    }
+}
+
+/// A note with `note` as its first paragraph and `outlines` as a nested list
+fn outline_note(note: &str, outlines: &[Outline]) -> String {
+    let mut lines = Vec::new();
+    if !note.trim().is_empty() {
+        lines.extend(note.trim().lines().map(str::to_string));
+        lines.push(String::new());
+    }
+    push_list(&mut lines, outlines, 0);
+    format!("{}\n", lines.join("\n").trim_end())
+}
+
+fn write_note(dest_dir: &Path, title: &str, content: &str) -> Result<PathBuf, String> {
+    let path = import::unique_path(dest_dir, &format!("{}.md", import::safe_file_name(title)));
+    crate::write_atomic(&path, content.as_bytes()).map_err(|e| format!("Failed to write file: {}", e))?;
+    Ok(path)
+}
+
+/// Import an OPML outline, e.g. from an outliner or the subscriptions of a
+/// feed reader, into `dest_dir` as a note with a nested list, the outlines'
+/// notes as paragraphs under their items and feeds as links. With `split`,
+/// each top-level outline becomes a note of its own.
+#[tauri::command(async)]
+pub fn import_opml(
+    file: String,
+    dest_dir: String,
+    split: Option<bool>,
+    settings: tauri::State<'_, Mutex<SettingsStore>>,
+) -> Result<ImportSummary, CommandError> {
+    let dest_dir = PathBuf::from(&dest_dir);
+    settings::check_writable(&settings, &dest_dir)?;
+    fs::create_dir_all(&dest_dir).map_err(|e| format!("Failed to create directory: {}", e))?;
+    let (title, outlines) = read_opml(&file)?;
+    let mut summary = ImportSummary::default();
+
+    let notes: Vec<(String, String)> = if split.unwrap_or(false) {
+        outlines
+            .iter()
+            .map(|outline| (outline.text.clone(), outline_note(&outline.note, &outline.children)))
+            .collect()
+    } else {
+        let title = match title.is_empty() {
+            true => Path::new(&file).file_stem().unwrap_or_default().to_string_lossy().to_string(),
+            false => title,
+        };
+        vec![(title, outline_note("", &outlines))]
+    };
+    for (title, content) in notes {
+        match write_note(&dest_dir, &title, &content) {
+            Ok(path) => summary.imported.push(path.to_string_lossy().to_string()),
+            Err(error) => summary.failed.push(ImportFailure { item: title, error }),
+        }
+    }
+    Ok(summary)
+}
+
+/// An outline being built from a note
+#[derive(Debug, Default)]
+struct Node {
+    /// 1-6 for headings, 7 and up for list items by depth; nodes nest
+    /// under those of a lower rank
+    rank: usize,
+    outline: Outline,
+}
+
+/// Close the open nodes of `rank` and above, attaching each to its parent
+fn close(open: &mut Vec<Node>, rank: usize) {
+    while open.len() > 1 && open.last().is_some_and(|node| node.rank >= rank) {
+        if let Some(node) = open.pop() {
+            if let Some(parent) = open.last_mut() {
+                parent.outline.children.push(node.outline);
+            }
+        }
+    }
+}
+
+/// The outline of a note: its headings, nested by level, with the list
+/// items and paragraphs under them
+fn note_outlines(content: &str) -> Vec<Outline> {
+    let body = &content[markdown::body_start(content)..];
+    let mut open = vec![Node::default()];
+    let mut lists = 0;
+    // Text is going to the top node, or to a paragraph of it
+    let mut capture = false;
+    let mut paragraph: Option<String> = None;
+    for event in Parser::new_ext(body, markdown::parser_options()) {
+        match event {
+            Event::Start(Tag::Heading { level, .. }) => {
+                close(&mut open, level as usize);
+                open.push(Node {
+                    rank: level as usize,
+                    ..Default::default()
+                });
+                capture = true;
+            }
+            Event::End(TagEnd::Heading(_)) => capture = false,
+            Event::Start(Tag::List(_)) => {
+                lists += 1;
+                capture = false;
+            }
+            Event::End(TagEnd::List(_)) => lists -= 1,
+            Event::Start(Tag::Item) => {
+                close(&mut open, 6 + lists);
+                open.push(Node {
+                    rank: 6 + lists,
+                    ..Default::default()
+                });
+                capture = true;
+            }
+            Event::End(TagEnd::Item) => {
+                close(&mut open, 6 + lists);
+                capture = false;
+            }
+            Event::TaskListMarker(done) => {
+                if let Some(node) = open.last_mut() {
+                    node.outline.complete = Some(done);
+                }
+            }
+            Event::Start(Tag::Paragraph) => {
+                // The first paragraph of an item is its text
+                let item_text = open.last().is_some_and(|node| {
+                    node.rank > 6 && node.outline.text.is_empty() && node.outline.children.is_empty()
+                });
+                if !item_text {
+                    paragraph = Some(String::new());
+                }
+            }
+            Event::End(TagEnd::Paragraph) => {
+                let Some(text) = paragraph.take() else {
+                    continue;
+                };
+                let text = text.trim().to_string();
+                match open.last_mut() {
+                    Some(node) if node.rank > 0 => {
+                        if !node.outline.note.is_empty() {
+                            node.outline.note.push_str("\n\n");
+                        }
+                        node.outline.note.push_str(&text);
+                    }
+                    // Paragraphs before the first heading are outlines of
+                    // their own
+                    Some(node) if !text.is_empty() => node.outline.children.push(Outline {
+                        text,
+                        ..Default::default()
+                    }),
+                    _ => {}
+                }
+            }
+            Event::Start(Tag::Link { dest_url, .. }) if links::is_external(&dest_url) => {
+                if let Some(node) = open.last_mut().filter(|_| capture && paragraph.is_none()) {
+                    node.outline.url.get_or_insert_with(|| dest_url.to_string());
+                }
+            }
+            Event::Text(text) | Event::Code(text) => match (&mut paragraph, open.last_mut()) {
+                (Some(paragraph), _) => paragraph.push_str(&text),
+                (None, Some(node)) if capture => node.outline.text.push_str(&text),
+                _ => {}
+            },
+            Event::SoftBreak | Event::HardBreak => match (&mut paragraph, open.last_mut()) {
+                (Some(paragraph), _) => paragraph.push('\n'),
+                (None, Some(node)) if capture => node.outline.text.push(' '),
+                _ => {}
+            },
+            _ => {}
+        }
+    }
+    close(&mut open, 0);
+    open.pop().map(|root| root.outline.children).unwrap_or_default()
+}
+
+/// `text` for an XML attribute, line breaks included
+fn attribute(text: &str) -> String {
+    escape_html(text).replace('\n', "&#10;")
+}
+
+fn push_outlines(xml: &mut String, outlines: &[Outline], depth: usize) {
+    for outline in outlines {
+        let indent = "  ".repeat(depth + 2);
+        xml.push_str(&format!("{}<outline text=\"{}\"", indent, attribute(outline.text.trim())));
+        if !outline.note.is_empty() {
+            xml.push_str(&format!(" _note=\"{}\"", attribute(&outline.note)));
+        }
+        if let Some(url) = &outline.url {
+            xml.push_str(&format!(" type=\"link\" url=\"{}\"", attribute(url)));
+        }
+        if let Some(complete) = outline.complete {
+            xml.push_str(&format!(" _complete=\"{}\"", complete));
+        }
+        if outline.children.is_empty() {
+            xml.push_str("/>\n");
+        } else {
+            xml.push_str(">\n");
+            push_outlines(xml, &outline.children, depth + 1);
+            xml.push_str(&format!("{}</outline>\n", indent));
+        }
+    }
+}
+
+/// Export the note at `path` as an OPML outline at `out_path`, for
+/// outliners: headings nest by level, list items by indentation, and the
+/// paragraphs under a heading or item become its note
+#[tauri::command(async)]
+pub fn export_opml(path: String, out_path: String) -> Result<(), String> {
+    let bytes = fs::read(&path).map_err(|e| format!("Failed to read file: {}", e))?;
+    if encryption::is_encrypted(&bytes) {
+        return Err(format!("Can't export an encrypted note: {}", path));
+    }
+    let content = encoding::decode(&bytes).content;
+    let title = export::note_title(Path::new(&path), &markdown::headings(&content));
+
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<opml version=\"2.0\">\n");
+    xml.push_str(&format!("  <head>\n    <title>{}</title>\n  </head>\n  <body>\n", escape_html(&title)));
+    push_outlines(&mut xml, &note_outlines(&content), 0);
+    xml.push_str("  </body>\n</opml>\n");
+    export::write_output(Path::new(&out_path), xml.as_bytes())
+}
@@ -0,0 +1,219 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::note_index::NoteIndexRegistry;
+use crate::rename;
+use crate::settings::{self, SettingsStore};
+
+const PINS_FILE: &str = "pins.json";
+
+/// A pinned note as stored in `.readmark/pins.json`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Pin {
+    /// Stays the same when the note is renamed or moved
+    id: String,
+    /// Path within the vault, with `/` separators
+    path: String,
+    /// Content hash, to find the note again after it was moved outside the
+    /// app
+    hash: String,
+    /// Milliseconds since the Unix epoch
+    pinned_at: u64,
+    /// Set once the note couldn't be found by its content either, so it
+    /// isn't searched for on every listing; cleared when it is back at `path`
+    #[serde(default)]
+    lost: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PinnedNote {
+    pub id: String,
+    pub path: String,
+    pub name: String,
+    /// False if the note was deleted, or moved and changed outside the app
+    pub exists: bool,
+    /// Milliseconds since the Unix epoch
+    pub pinned_at: u64,
+}
+
+fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+fn pins_path(root: &Path) -> PathBuf {
+    root.join(crate::DATA_DIR).join(PINS_FILE)
+}
+
+fn read_pins(root: &Path) -> Result<Vec<Pin>, String> {
+    match fs::read_to_string(pins_path(root)) {
+        Ok(raw) => serde_json::from_str(&raw).map_err(|e| format!("Invalid {}: {}", PINS_FILE, e)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(format!("Failed to read {}: {}", PINS_FILE, e)),
+    }
+}
+
+fn write_pins(root: &Path, pins: &[Pin]) -> Result<(), String> {
+    let path = pins_path(root);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+    }
+    let json = serde_json::to_string_pretty(pins).map_err(|e| format!("Failed to serialize pins: {}", e))?;
+    crate::write_atomic(&path, json.as_bytes()).map_err(|e| format!("Failed to save pins: {}", e))
+}
+
+fn file_hash(path: &Path) -> Option<String> {
+    fs::read(path).ok().map(|data| crate::content_hash(&data))
+}
+
+/// Update the pins of the vault at `root` for files and folders that were
+/// moved from the first path of each pair to the second
+pub fn record_moves(root: &Path, moves: &[(&Path, &Path)]) {
+    let Ok(mut pins) = read_pins(root) else {
+        return;
+    };
+    let mut changed = false;
+    for pin in &mut pins {
+        let path = root.join(&pin.path);
+        for (from, to) in moves {
+            if let Ok(rest) = path.strip_prefix(from) {
+                let moved = if rest.as_os_str().is_empty() { to.to_path_buf() } else { to.join(rest) };
+                pin.path = rename::relative_path(root, &moved);
+                pin.lost = false;
+                changed = true;
+                break;
+            }
+        }
+    }
+    if changed {
+        let _ = write_pins(root, &pins);
+    }
+}
+
+/// Pin the note at `path` in the vault it belongs to
+#[tauri::command(async)]
+pub fn pin_note(
+    path: String,
+    registry: tauri::State<'_, NoteIndexRegistry>,
+    settings: tauri::State<'_, Mutex<SettingsStore>>,
+) -> Result<PinnedNote, String> {
+    let path_buf = PathBuf::from(&path);
+    settings::check_writable(&settings, &path_buf).map_err(|e| e.to_string())?;
+    if !path_buf.is_file() {
+        return Err(format!("File does not exist: {}", path));
+    }
+    let root = registry.root_for(&path_buf);
+    let relative = rename::relative_path(&root, &path_buf);
+
+    let mut pins = read_pins(&root)?;
+    let pin = match pins.iter().find(|pin| pin.path == relative) {
+        Some(pin) => pin.clone(),
+        None => {
+            let pinned_at = now_millis();
+            let pin = Pin {
+                id: crate::content_hash(format!("{}:{}", relative, pinned_at).as_bytes())[..12].to_string(),
+                path: relative,
+                hash: file_hash(&path_buf).unwrap_or_default(),
+                pinned_at,
+                lost: false,
+            };
+            pins.push(pin.clone());
+            write_pins(&root, &pins)?;
+            pin
+        }
+    };
+    Ok(pinned_note(&root, &pin, true))
+}
+
+/// Unpin the note at `path`
+#[tauri::command(async)]
+pub fn unpin_note(
+    path: String,
+    registry: tauri::State<'_, NoteIndexRegistry>,
+    settings: tauri::State<'_, Mutex<SettingsStore>>,
+) -> Result<(), String> {
+    let path_buf = PathBuf::from(&path);
+    settings::check_writable(&settings, &path_buf).map_err(|e| e.to_string())?;
+    let root = registry.root_for(&path_buf);
+    let relative = rename::relative_path(&root, &path_buf);
+
+    let mut pins = read_pins(&root)?;
+    let count = pins.len();
+    pins.retain(|pin| pin.path != relative);
+    if pins.len() == count {
+        return Err(format!("Note is not pinned: {}", path));
+    }
+    write_pins(&root, &pins)
+}
+
+fn pinned_note(root: &Path, pin: &Pin, exists: bool) -> PinnedNote {
+    let path = root.join(&pin.path);
+    PinnedNote {
+        id: pin.id.clone(),
+        name: path.file_stem().map(|n| n.to_string_lossy().to_string()).unwrap_or_default(),
+        path: path.to_string_lossy().to_string(),
+        exists,
+        pinned_at: pin.pinned_at,
+    }
+}
+
+/// Pinned notes of the vault at `root`, in the order they were pinned.
+/// Notes moved outside the app are found again by their content, once.
+#[tauri::command(async)]
+pub fn list_pinned(
+    root: String,
+    registry: tauri::State<'_, NoteIndexRegistry>,
+    settings: tauri::State<'_, Mutex<SettingsStore>>,
+) -> Result<Vec<PinnedNote>, String> {
+    let root = PathBuf::from(&root);
+    crate::ensure_dir(&root)?;
+    let mut pins = read_pins(&root)?;
+
+    let mut changed = false;
+    let mut missing = Vec::new();
+    for (i, pin) in pins.iter_mut().enumerate() {
+        match file_hash(&root.join(&pin.path)) {
+            Some(hash) if hash != pin.hash || pin.lost => {
+                pin.hash = hash;
+                pin.lost = false;
+                changed = true;
+            }
+            Some(_) => {}
+            None if !pin.lost => missing.push(i),
+            None => {}
+        }
+    }
+
+    if !missing.is_empty() {
+        let index = registry.for_vault(&root, &settings)?;
+        let contents = index.contents()?;
+        let pinned: Vec<PathBuf> = pins.iter().map(|pin| root.join(&pin.path)).collect();
+        let mut by_hash: HashMap<String, PathBuf> = HashMap::new();
+        for path in contents.notes.keys().filter(|path| !pinned.contains(path)) {
+            if let Some(hash) = file_hash(path) {
+                by_hash.entry(hash).or_insert_with(|| path.clone());
+            }
+        }
+        for i in missing {
+            match by_hash.remove(&pins[i].hash) {
+                Some(path) => pins[i].path = rename::relative_path(&root, &path),
+                None => pins[i].lost = true,
+            }
+            changed = true;
+        }
+    }
+    if changed {
+        write_pins(&root, &pins)?;
+    }
+
+    Ok(pins
+        .iter()
+        .map(|pin| pinned_note(&root, pin, root.join(&pin.path).is_file()))
+        .collect())
+}
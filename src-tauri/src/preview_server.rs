@@ -0,0 +1,448 @@
+use serde::Serialize;
+use serde_json::json;
+use std::collections::VecDeque;
+use std::fs;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, TcpListener, UdpSocket};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
+use tauri::AppHandle;
+
+use crate::encryption;
+use crate::export::{self, NoteExporter, WikiLinkStyle};
+use crate::http_server::{self, Request, Response, ServerHandle};
+use crate::links::{self, NameLookup, Resolver};
+use crate::markdown;
+use crate::note_index::{NoteIndex, NoteIndexRegistry};
+use crate::settings::{self, SettingsStore};
+use crate::watcher;
+
+/// Port used when none is given
+const DEFAULT_PORT: u16 = 27184;
+/// Cookie the access key is kept in once a page was opened with it
+const KEY_COOKIE: &str = "readmark_preview";
+/// How long a page's request for changes is held open
+const POLL_TIMEOUT: Duration = Duration::from_secs(25);
+/// Changes remembered for pages catching up
+const MAX_RECENT_CHANGES: usize = 256;
+
+/// Reloads the page when its note, or a file it may show, changes
+const RELOAD_SCRIPT: &str = r#"<script>
+(() => {
+  let version = VERSION;
+  const note = NOTE;
+  const poll = () =>
+    fetch(`/_preview/changes?since=${version}&note=${encodeURIComponent(note)}`)
+      .then((response) => response.json())
+      .then((change) => {
+        if (change.reload) return location.reload();
+        version = change.version;
+        poll();
+      })
+      .catch(() => setTimeout(poll, 2000));
+  poll();
+})();
+</script>
+"#;
+
+#[derive(Default)]
+struct ChangeLog {
+    version: u64,
+    recent: VecDeque<(u64, PathBuf)>,
+}
+
+/// Files changed in the vault, numbered, for pages waiting to reload
+#[derive(Default)]
+struct Changes {
+    log: Mutex<ChangeLog>,
+    changed: Condvar,
+}
+
+impl Changes {
+    fn record(&self, path: PathBuf) {
+        if let Ok(mut log) = self.log.lock() {
+            log.version += 1;
+            let version = log.version;
+            log.recent.push_back((version, path));
+            if log.recent.len() > MAX_RECENT_CHANGES {
+                log.recent.pop_front();
+            }
+        }
+        self.changed.notify_all();
+    }
+
+    fn version(&self) -> u64 {
+        self.log.lock().map_or(0, |log| log.version)
+    }
+
+    /// Wait until a file `relevant` to a page changes after `since`, or the
+    /// poll times out. Returns the latest version and whether to reload.
+    fn wait(&self, since: u64, relevant: impl Fn(&Path) -> bool) -> (u64, bool) {
+        let deadline = Instant::now() + POLL_TIMEOUT;
+        let Ok(mut log) = self.log.lock() else {
+            return (since, false);
+        };
+        loop {
+            let oldest = log.recent.front().map_or(log.version + 1, |(version, _)| *version);
+            // Changes the log no longer has might have been relevant
+            let missed = since + 1 < oldest;
+            let changed = log.recent.iter().any(|(version, path)| *version > since && relevant(path));
+            if since < log.version && (missed || changed) {
+                return (log.version, true);
+            }
+            let now = Instant::now();
+            if now >= deadline {
+                return (log.version, false);
+            }
+            log = match self.changed.wait_timeout(log, deadline - now) {
+                Ok((log, _)) => log,
+                Err(_) => return (since, false),
+            };
+        }
+    }
+}
+
+/// What a running preview server serves, shared with its connections
+struct Preview {
+    root: PathBuf,
+    index: Arc<NoteIndex>,
+    port: u16,
+    lan: bool,
+    key: String,
+    title: String,
+    changes: Changes,
+}
+
+fn html_page(status: u16, title: &str, body: &str) -> Response {
+    Response::new(status, "text/html; charset=utf-8", export::html_document(title, body, None))
+}
+
+impl Preview {
+    fn handle(&self, request: &Request) -> Response {
+        if !self.lan && !request.is_local_host(self.port) {
+            return Response::error(403, "Unexpected Host header");
+        }
+        let query_key = request.query_param("key");
+        let key = query_key.as_deref().or_else(|| request.cookie(KEY_COOKIE));
+        if !key.is_some_and(|key| http_server::tokens_match(key, &self.key)) {
+            let body = "<p>Open the preview with the link shown in Readmark.</p>\n";
+            return html_page(401, "Preview", body);
+        }
+        if request.method != "GET" {
+            return Response::error(405, "Use GET");
+        }
+
+        let response = match request.path.as_str() {
+            "/_preview/changes" => self.changes(request),
+            path => self.file(&links::percent_decode(path.trim_start_matches('/'))),
+        };
+        match query_key {
+            // Pages linked from here don't carry the key
+            Some(key) => response.with_header(
+                "Set-Cookie",
+                format!("{}={}; Path=/; HttpOnly; SameSite=Strict", KEY_COOKIE, key),
+            ),
+            None => response,
+        }
+    }
+
+    fn changes(&self, request: &Request) -> Response {
+        let since = request.query_param("since").and_then(|since| since.parse().ok()).unwrap_or(0);
+        // The index page lists every note, so any change is relevant to it
+        let note = request.query_param("note").filter(|note| !note.is_empty()).map(|note| self.root.join(note));
+        // Besides the note itself, attachments it shows may have changed
+        let relevant = |path: &Path| match &note {
+            Some(note) => note == path || !settings::has_note_extension(path, self.index.note_extensions()),
+            None => true,
+        };
+        let (version, reload) = self.changes.wait(since, relevant);
+        Response::json(200, json!({ "version": version, "reload": reload }))
+    }
+
+    /// The page or attachment at the vault-relative `relative`: `Note.html`
+    /// for `Note.md`, other files as they are
+    fn file(&self, relative: &str) -> Response {
+        let not_found = || html_page(404, "Not found", "<p>There is no such note.</p>\n");
+        let Ok(relative) = settings::normalize_vault_path(relative, "Path") else {
+            return not_found();
+        };
+        let path = self.root.join(&relative);
+        if relative == "." || relative == "index.html" {
+            return match self.note_with_stem(&self.root.join("index")) {
+                Some(note) => self.note_page(&note),
+                None => self.index_page(),
+            };
+        }
+        if path.extension().is_some_and(|extension| extension == "html") {
+            return match self.note_with_stem(&path.with_extension("")) {
+                Some(note) => self.note_page(&note),
+                None => not_found(),
+            };
+        }
+
+        let known = self.index.contents().is_ok_and(|contents| contents.attachments.contains(&path));
+        match (known, export::image_mime(&path).or_else(|| other_mime(&path))) {
+            (true, Some(mime)) => match fs::read(&path) {
+                Ok(bytes) if !encryption::is_encrypted(&bytes) => Response::new(200, mime, bytes),
+                _ => not_found(),
+            },
+            _ => not_found(),
+        }
+    }
+
+    /// The note at `stem` with one of the note extensions, if there is one
+    fn note_with_stem(&self, stem: &Path) -> Option<PathBuf> {
+        let contents = self.index.contents().ok()?;
+        self.index.note_extensions().iter().find_map(|extension| {
+            let mut name = stem.as_os_str().to_os_string();
+            name.push(format!(".{}", extension));
+            let path = PathBuf::from(name);
+            contents.notes.contains_key(&path).then_some(path)
+        })
+    }
+
+    fn nav(&self, relative_dir: &Path) -> String {
+        format!(
+            "<nav><a href=\"{}\">{}</a></nav>\n",
+            export::relative_href(&self.root.join(relative_dir), &self.root.join("index.html")),
+            export::escape_html(&self.title)
+        )
+    }
+
+    /// The reload script for the page of `note`, or of the index page
+    fn reload_script(&self, note: Option<&Path>) -> String {
+        let relative = note.map_or_else(String::new, |note| {
+            note.strip_prefix(&self.root).unwrap_or(note).to_string_lossy().replace('\\', "/")
+        });
+        // Kept out of the way of a `</script>` in a file name
+        let note = serde_json::to_string(&relative).unwrap_or_default().replace("</", "<\\/");
+        RELOAD_SCRIPT
+            .replace("VERSION", &self.changes.version().to_string())
+            .replace("NOTE", &note)
+    }
+
+    fn note_page(&self, source: &Path) -> Response {
+        let bytes = match fs::read(source) {
+            Ok(bytes) => bytes,
+            Err(e) => return Response::error(500, &format!("Failed to read file: {}", e)),
+        };
+        let note_dir = source.parent().unwrap_or(&self.root);
+        let nav = self.nav(note_dir.strip_prefix(&self.root).unwrap_or(Path::new("")));
+        let script = self.reload_script(Some(source));
+        if encryption::is_encrypted(&bytes) {
+            let body = format!("{}<p>This note is encrypted.</p>\n{}", nav, script);
+            return html_page(403, &self.title, &body);
+        }
+
+        let content = String::from_utf8_lossy(&bytes);
+        let body = {
+            let Ok(contents) = self.index.contents() else {
+                return Response::error(500, "The note index is unavailable");
+            };
+            let names = NameLookup::new(&contents);
+            let resolver = Resolver {
+                root: self.index.root(),
+                note_extensions: self.index.note_extensions(),
+                names: &names,
+            };
+            // Only attachments of the vault are served; anything else shows
+            // as a broken image rather than being read from disk
+            let image_src = |image: &Path| -> Option<String> {
+                match image.starts_with(&self.root) && contents.attachments.contains(image) {
+                    true => Some(export::relative_href(note_dir, image)),
+                    false => Some(String::new()),
+                }
+            };
+            let exporter = NoteExporter {
+                resolver: &resolver,
+                source,
+                wiki_links: WikiLinkStyle::Anchor,
+                image_src: &image_src,
+            };
+            exporter.render(&content)
+        };
+        let title = export::note_title(source, &markdown::headings(&content));
+        html_page(200, &title, &format!("{}{}{}", nav, body, script))
+    }
+
+    /// Every note in the vault, for vaults without an index note
+    fn index_page(&self) -> Response {
+        let mut notes: Vec<PathBuf> = match self.index.contents() {
+            Ok(contents) => contents.notes.keys().cloned().collect(),
+            Err(e) => return Response::error(500, &e),
+        };
+        notes.sort();
+        let mut list = String::new();
+        for note in &notes {
+            let relative = note.strip_prefix(&self.root).unwrap_or(note);
+            let folder = match relative.parent().map(|dir| dir.to_string_lossy()) {
+                Some(folder) if !folder.is_empty() => format!(" <small>{}</small>", export::escape_html(&folder)),
+                _ => String::new(),
+            };
+            list.push_str(&format!(
+                "<li><a href=\"{}\">{}</a>{}</li>\n",
+                export::relative_href(&self.root, &note.with_extension("html")),
+                export::escape_html(&note.file_stem().unwrap_or_default().to_string_lossy()),
+                folder
+            ));
+        }
+        let body = format!(
+            "<h1>{}</h1>\n<ul>\n{}</ul>\n{}",
+            export::escape_html(&self.title),
+            list,
+            self.reload_script(None)
+        );
+        html_page(200, &self.title, &body)
+    }
+}
+
+/// MIME type of attachments other than images that browsers show
+fn other_mime(path: &Path) -> Option<&'static str> {
+    let extension = path.extension()?.to_string_lossy().to_lowercase();
+    Some(match extension.as_str() {
+        "pdf" => "application/pdf",
+        "mp3" => "audio/mpeg",
+        "mp4" => "video/mp4",
+        "webm" => "video/webm",
+        "txt" => "text/plain; charset=utf-8",
+        _ => return None,
+    })
+}
+
+/// This machine's address on the local network, if it has one
+fn lan_address() -> Option<IpAddr> {
+    // Connecting a UDP socket sends nothing; it only picks the interface
+    let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)).ok()?;
+    socket.connect((Ipv4Addr::new(192, 0, 2, 1), 80)).ok()?;
+    Some(socket.local_addr().ok()?.ip()).filter(|ip| !ip.is_loopback() && !ip.is_unspecified())
+}
+
+struct Running {
+    server: ServerHandle,
+    watcher: u64,
+    preview: Arc<Preview>,
+}
+
+/// The preview server, if it is running
+#[derive(Default)]
+pub struct PreviewServer {
+    running: Mutex<Option<Running>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PreviewServerStatus {
+    pub running: bool,
+    pub port: Option<u16>,
+    /// Root of the vault served
+    pub vault: Option<String>,
+    /// Whether other devices on the network can connect
+    pub lan: bool,
+    /// Address to open the preview at, including its access key
+    pub url: Option<String>,
+}
+
+fn status(server: &PreviewServer) -> Result<PreviewServerStatus, String> {
+    let running = server.running.lock().map_err(|e| format!("Lock error: {}", e))?;
+    let Some(Running { preview, .. }) = running.as_ref() else {
+        return Ok(PreviewServerStatus {
+            running: false,
+            port: None,
+            vault: None,
+            lan: false,
+            url: None,
+        });
+    };
+    let host = match preview.lan.then(lan_address).flatten() {
+        Some(address) => address.to_string(),
+        None => "127.0.0.1".to_string(),
+    };
+    Ok(PreviewServerStatus {
+        running: true,
+        port: Some(preview.port),
+        vault: Some(preview.root.to_string_lossy().to_string()),
+        lan: preview.lan,
+        url: Some(format!("http://{}:{}/?key={}", host, preview.port, preview.key)),
+    })
+}
+
+fn stop(app: &AppHandle, server: &PreviewServer) -> Result<(), String> {
+    if let Some(running) = server.running.lock().map_err(|e| format!("Lock error: {}", e))?.take() {
+        running.server.stop();
+        watcher::stop_watching(app, running.watcher);
+    }
+    Ok(())
+}
+
+/// Serve the vault at `root` as rendered pages on `port` (27184 by default),
+/// for previewing notes in a browser. Pages reload when their note changes.
+/// With `lan`, other devices on the network can connect too. Every page
+/// needs the access key in the returned URL, which changes on each start.
+#[tauri::command(async)]
+pub fn start_preview_server(
+    root: String,
+    port: Option<u16>,
+    lan: Option<bool>,
+    app: AppHandle,
+    server: tauri::State<'_, PreviewServer>,
+    registry: tauri::State<'_, NoteIndexRegistry>,
+    settings: tauri::State<'_, Mutex<SettingsStore>>,
+) -> Result<PreviewServerStatus, String> {
+    let root = links::normalize_path(Path::new(&root));
+    crate::ensure_dir(&root)?;
+    let lan = lan.unwrap_or(false);
+    stop(&app, &server)?;
+
+    let address = if lan { Ipv4Addr::UNSPECIFIED } else { Ipv4Addr::LOCALHOST };
+    let port = port.unwrap_or(DEFAULT_PORT);
+    let listener = TcpListener::bind(SocketAddr::from((address, port)))
+        .map_err(|e| format!("Failed to listen on port {}: {}", port, e))?;
+    let preview = Arc::new(Preview {
+        index: registry.for_vault(&root, &settings)?,
+        title: root
+            .file_name()
+            .map_or_else(|| root.to_string_lossy().to_string(), |name| name.to_string_lossy().to_string()),
+        root: root.clone(),
+        port: listener.local_addr().map_err(|e| format!("Failed to start server: {}", e))?.port(),
+        lan,
+        key: http_server::random_token(),
+        changes: Changes::default(),
+    });
+
+    let watched = preview.clone();
+    let watcher = watcher::watch_vault(&app, &root, move |change| {
+        let path = PathBuf::from(&change.path);
+        if !path.is_dir() {
+            watched.changes.record(path);
+        }
+    })?;
+    let served = preview.clone();
+    let handle = match http_server::serve(listener, move |request| served.handle(request)) {
+        Ok(handle) => handle,
+        Err(e) => {
+            watcher::stop_watching(&app, watcher);
+            return Err(e);
+        }
+    };
+    *server.running.lock().map_err(|e| format!("Lock error: {}", e))? = Some(Running {
+        server: handle,
+        watcher,
+        preview,
+    });
+    status(&server)
+}
+
+/// Stop the preview server
+#[tauri::command]
+pub fn stop_preview_server(
+    app: AppHandle,
+    server: tauri::State<'_, PreviewServer>,
+) -> Result<PreviewServerStatus, String> {
+    stop(&app, &server)?;
+    status(&server)
+}
+
+/// Whether the preview server is running, and where
+#[tauri::command]
+pub fn get_preview_server_status(server: tauri::State<'_, PreviewServer>) -> Result<PreviewServerStatus, String> {
+    status(&server)
+}
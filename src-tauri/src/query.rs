@@ -0,0 +1,559 @@
+use serde::Serialize;
+use serde_json::Value;
+use std::path::Path;
+
+use crate::search_index::IndexRegistry;
+use crate::vault_index::{self, CompareOp, NoteFilter, NoteRecord, NoteSort};
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    /// A field name or keyword
+    Word(String),
+    Text(String),
+    Number(f64),
+    Tag(String),
+    Link(String),
+    Op(CompareOp),
+    Open,
+    Close,
+    Comma,
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || matches!(c, '_' | '-' | '.' | '/')
+}
+
+fn tokenize(query: &str) -> Result<Vec<Token>, String> {
+    let chars: Vec<char> = query.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    let take_while = |start: usize, f: &dyn Fn(char) -> bool| {
+        let end = (start..chars.len()).find(|&j| !f(chars[j])).unwrap_or(chars.len());
+        (chars[start..end].iter().collect::<String>(), end)
+    };
+    while i < chars.len() {
+        let c = chars[i];
+        let next = chars.get(i + 1).copied();
+        match c {
+            c if c.is_whitespace() => i += 1,
+            '"' | '\'' => {
+                let mut text = String::new();
+                i += 1;
+                loop {
+                    match chars.get(i) {
+                        None => return Err("Unterminated string".to_string()),
+                        Some(&q) if q == c => break,
+                        Some('\\') if i + 1 < chars.len() => {
+                            text.push(chars[i + 1]);
+                            i += 2;
+                        }
+                        Some(&other) => {
+                            text.push(other);
+                            i += 1;
+                        }
+                    }
+                }
+                tokens.push(Token::Text(text));
+                i += 1;
+            }
+            '#' => {
+                let (tag, end) = take_while(i + 1, &|c| c.is_alphanumeric() || matches!(c, '_' | '-' | '/'));
+                if tag.is_empty() {
+                    return Err("Expected a tag after #".to_string());
+                }
+                tokens.push(Token::Tag(tag));
+                i = end;
+            }
+            '[' if next == Some('[') => {
+                let rest: String = chars[i + 2..].iter().collect();
+                let target = rest.split_once("]]").ok_or("Unterminated [[link]]")?.0;
+                tokens.push(Token::Link(target.split('|').next().unwrap_or(target).trim().to_string()));
+                i += target.chars().count() + 4;
+            }
+            '(' | ')' | ',' => {
+                tokens.push(match c {
+                    '(' => Token::Open,
+                    ')' => Token::Close,
+                    _ => Token::Comma,
+                });
+                i += 1;
+            }
+            '=' | '!' | '<' | '>' => {
+                let (op, len) = match (c, next) {
+                    ('=', Some('=')) => (CompareOp::Eq, 2),
+                    ('=', _) => (CompareOp::Eq, 1),
+                    ('!', Some('=')) => (CompareOp::Ne, 2),
+                    ('<', Some('=')) => (CompareOp::Lte, 2),
+                    ('<', _) => (CompareOp::Lt, 1),
+                    ('>', Some('=')) => (CompareOp::Gte, 2),
+                    ('>', _) => (CompareOp::Gt, 1),
+                    _ => return Err("Expected != after !".to_string()),
+                };
+                tokens.push(Token::Op(op));
+                i += len;
+            }
+            // Numbers, and values like 2024-05-01 that compare as text
+            c if c.is_ascii_digit() || (c == '-' && next.is_some_and(|n| n.is_ascii_digit())) => {
+                let (text, end) = take_while(i + 1, &|c| c.is_alphanumeric() || matches!(c, '.' | '-' | ':'));
+                let text = format!("{}{}", c, text);
+                tokens.push(text.parse().map_or(Token::Text(text), Token::Number));
+                i = end;
+            }
+            c if c.is_alphabetic() || c == '_' => {
+                let (word, end) = take_while(i, &is_word_char);
+                tokens.push(Token::Word(word));
+                i = end;
+            }
+            other => return Err(format!("Unexpected character: {}", other)),
+        }
+    }
+    Ok(tokens)
+}
+
+const KEYWORDS: &[&str] = &[
+    "TABLE", "LIST", "FROM", "WHERE", "SORT", "LIMIT", "AND", "OR", "NOT", "ASC", "DESC", "AS", "CONTAINS", "TRUE",
+    "FALSE", "NULL",
+];
+
+#[derive(Debug)]
+enum Expr {
+    Compare(String, CompareOp, Value),
+    /// The field is set and not `false`, empty or null
+    Truthy(String),
+    Not(Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+}
+
+fn is_truthy(value: Option<&Value>) -> bool {
+    match value {
+        None | Some(Value::Null) | Some(Value::Bool(false)) => false,
+        Some(Value::String(s)) => !s.is_empty(),
+        Some(Value::Array(items)) => !items.is_empty(),
+        Some(_) => true,
+    }
+}
+
+impl Expr {
+    fn eval(&self, record: &NoteRecord) -> bool {
+        match self {
+            Expr::Compare(key, op, value) => vault_index::matches(record.value(key).as_ref(), *op, value),
+            Expr::Truthy(key) => is_truthy(record.value(key).as_ref()),
+            Expr::Not(inner) => !inner.eval(record),
+            Expr::And(a, b) => a.eval(record) && b.eval(record),
+            Expr::Or(a, b) => a.eval(record) || b.eval(record),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum QueryKind {
+    List,
+    Table,
+}
+
+struct Column {
+    key: String,
+    name: String,
+}
+
+struct Query {
+    kind: QueryKind,
+    columns: Vec<Column>,
+    from: NoteFilter,
+    condition: Option<Expr>,
+    sort: Vec<NoteSort>,
+    limit: Option<usize>,
+}
+
+struct Parser<'a> {
+    tokens: Vec<Token>,
+    pos: usize,
+    root: &'a Path,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn at_keyword(&self, keyword: &str) -> bool {
+        matches!(self.peek(), Some(Token::Word(word)) if word.eq_ignore_ascii_case(keyword))
+    }
+
+    fn eat_keyword(&mut self, keyword: &str) -> bool {
+        let found = self.at_keyword(keyword);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn at_clause(&self) -> bool {
+        ["FROM", "WHERE", "SORT", "LIMIT"].iter().any(|keyword| self.at_keyword(keyword))
+    }
+
+    fn field(&mut self) -> Result<String, String> {
+        match self.next() {
+            Some(Token::Word(word)) if !KEYWORDS.iter().any(|k| word.eq_ignore_ascii_case(k)) => Ok(word),
+            Some(other) => Err(format!("Expected a field name, found {}", describe(&other))),
+            None => Err("Expected a field name".to_string()),
+        }
+    }
+
+    fn value(&mut self) -> Result<Value, String> {
+        match self.next() {
+            Some(Token::Text(text)) | Some(Token::Tag(text)) => Ok(Value::String(text)),
+            Some(Token::Number(n)) => Ok(serde_json::Number::from_f64(n).map_or(Value::Null, Value::Number)),
+            Some(Token::Word(word)) if word.eq_ignore_ascii_case("true") => Ok(Value::Bool(true)),
+            Some(Token::Word(word)) if word.eq_ignore_ascii_case("false") => Ok(Value::Bool(false)),
+            Some(Token::Word(word)) if word.eq_ignore_ascii_case("null") => Ok(Value::Null),
+            Some(other) => Err(format!("Expected a value, found {}", describe(&other))),
+            None => Err("Expected a value".to_string()),
+        }
+    }
+
+    fn columns(&mut self) -> Result<Vec<Column>, String> {
+        let mut columns = Vec::new();
+        if self.peek().is_none() || self.at_clause() {
+            return Ok(columns);
+        }
+        loop {
+            let key = self.field()?;
+            let name = match self.eat_keyword("AS") {
+                true => match self.next() {
+                    Some(Token::Text(name)) | Some(Token::Word(name)) => name,
+                    _ => return Err("Expected a column name after AS".to_string()),
+                },
+                false => key.clone(),
+            };
+            columns.push(Column { key, name });
+            if self.peek() != Some(&Token::Comma) {
+                return Ok(columns);
+            }
+            self.pos += 1;
+        }
+    }
+
+    fn from(&mut self, filter: &mut NoteFilter) -> Result<(), String> {
+        loop {
+            match self.next() {
+                Some(Token::Tag(tag)) => filter.tags.push(tag),
+                Some(Token::Text(folder)) if filter.folder.is_none() => {
+                    filter.folder = Some(self.root.join(folder.trim_matches('/')).to_string_lossy().to_string());
+                }
+                Some(Token::Link(target)) if filter.links_to.is_none() => filter.links_to = Some(target),
+                Some(Token::Text(_)) => return Err("FROM takes one folder".to_string()),
+                Some(Token::Link(_)) => return Err("FROM takes one [[link]]".to_string()),
+                Some(other) => return Err(format!("Expected #tag, \"folder\" or [[link]], found {}", describe(&other))),
+                None => return Err("Expected #tag, \"folder\" or [[link]] after FROM".to_string()),
+            }
+            if !self.eat_keyword("AND") {
+                return Ok(());
+            }
+        }
+    }
+
+    fn or(&mut self) -> Result<Expr, String> {
+        let mut expr = self.and()?;
+        while self.eat_keyword("OR") {
+            expr = Expr::Or(Box::new(expr), Box::new(self.and()?));
+        }
+        Ok(expr)
+    }
+
+    fn and(&mut self) -> Result<Expr, String> {
+        let mut expr = self.unary()?;
+        while self.eat_keyword("AND") {
+            expr = Expr::And(Box::new(expr), Box::new(self.unary()?));
+        }
+        Ok(expr)
+    }
+
+    fn unary(&mut self) -> Result<Expr, String> {
+        if self.eat_keyword("NOT") {
+            return Ok(Expr::Not(Box::new(self.unary()?)));
+        }
+        if self.peek() == Some(&Token::Open) {
+            self.pos += 1;
+            let expr = self.or()?;
+            if self.next() != Some(Token::Close) {
+                return Err("Expected )".to_string());
+            }
+            return Ok(expr);
+        }
+        let key = self.field()?;
+        if self.eat_keyword("CONTAINS") {
+            return Ok(Expr::Compare(key, CompareOp::Contains, self.value()?));
+        }
+        match self.peek() {
+            Some(Token::Op(op)) => {
+                let op = *op;
+                self.pos += 1;
+                Ok(Expr::Compare(key, op, self.value()?))
+            }
+            _ => Ok(Expr::Truthy(key)),
+        }
+    }
+
+    fn sort(&mut self) -> Result<Vec<NoteSort>, String> {
+        let mut sort = Vec::new();
+        loop {
+            let key = self.field()?;
+            let descending = self.eat_keyword("DESC");
+            if !descending {
+                self.eat_keyword("ASC");
+            }
+            sort.push(NoteSort { key, descending });
+            if self.peek() != Some(&Token::Comma) {
+                return Ok(sort);
+            }
+            self.pos += 1;
+        }
+    }
+
+    fn query(&mut self) -> Result<Query, String> {
+        let (kind, columns) = if self.eat_keyword("TABLE") {
+            (QueryKind::Table, self.columns()?)
+        } else {
+            self.eat_keyword("LIST");
+            let columns = match self.peek() {
+                Some(Token::Word(_)) if !self.at_clause() => self.columns()?,
+                _ => Vec::new(),
+            };
+            if columns.len() > 1 {
+                return Err("LIST takes one field".to_string());
+            }
+            (QueryKind::List, columns)
+        };
+        let mut query = Query {
+            kind,
+            columns,
+            from: NoteFilter::default(),
+            condition: None,
+            sort: Vec::new(),
+            limit: None,
+        };
+        let mut seen: Vec<String> = Vec::new();
+        while let Some(token) = self.next() {
+            let clause = match &token {
+                Token::Word(word) => word.to_uppercase(),
+                other => return Err(format!("Unexpected {}", describe(other))),
+            };
+            if seen.contains(&clause) {
+                return Err(format!("{} is given twice", clause));
+            }
+            match clause.as_str() {
+                "FROM" => self.from(&mut query.from)?,
+                "WHERE" => query.condition = Some(self.or()?),
+                "SORT" => query.sort = self.sort()?,
+                "LIMIT" => match self.next() {
+                    Some(Token::Number(n)) if n >= 0.0 && n.fract() == 0.0 => query.limit = Some(n as usize),
+                    _ => return Err("LIMIT takes a whole number".to_string()),
+                },
+                _ => return Err(format!("Unexpected {}", describe(&token))),
+            }
+            seen.push(clause);
+        }
+        Ok(query)
+    }
+}
+
+fn describe(token: &Token) -> String {
+    match token {
+        Token::Word(word) => word.clone(),
+        Token::Text(text) => format!("\"{}\"", text),
+        Token::Number(n) => n.to_string(),
+        Token::Tag(tag) => format!("#{}", tag),
+        Token::Link(target) => format!("[[{}]]", target),
+        Token::Op(_) => "an operator".to_string(),
+        Token::Open => "(".to_string(),
+        Token::Close => ")".to_string(),
+        Token::Comma => ",".to_string(),
+    }
+}
+
+fn parse(query: &str, root: &Path) -> Result<Query, String> {
+    let mut parser = Parser {
+        tokens: tokenize(query)?,
+        pos: 0,
+        root,
+    };
+    parser.query()
+}
+
+#[derive(Debug, Serialize)]
+pub struct QueryRow {
+    pub path: String,
+    pub title: String,
+    /// One per column, null where the note doesn't have the field
+    pub values: Vec<Value>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct QueryResult {
+    pub kind: QueryKind,
+    pub columns: Vec<String>,
+    pub rows: Vec<QueryRow>,
+}
+
+/// Run a query over the notes of the vault at `root`
+pub fn run(registry: &IndexRegistry, root: &Path, query: &str) -> Result<QueryResult, String> {
+    let query = parse(query, root).map_err(|e| format!("Invalid query: {}", e))?;
+    let mut records = vault_index::records(registry, root, &query.from)?;
+    if let Some(condition) = &query.condition {
+        records.retain(|record| condition.eval(record));
+    }
+    vault_index::sort(&mut records, &query.sort);
+    if let Some(limit) = query.limit {
+        records.truncate(limit);
+    }
+    let rows = records
+        .into_iter()
+        .map(|record| QueryRow {
+            values: query
+                .columns
+                .iter()
+                .map(|column| record.value(&column.key).unwrap_or(Value::Null))
+                .collect(),
+            path: record.path,
+            title: record.title,
+        })
+        .collect();
+    Ok(QueryResult {
+        kind: query.kind,
+        columns: query.columns.into_iter().map(|column| column.name).collect(),
+        rows,
+    })
+}
+
+/// Run a Dataview-style query over a vault's notes, e.g.
+/// `TABLE rating, finished FROM #book WHERE rating >= 4 SORT finished DESC`.
+/// `LIST` or `TABLE` with fields to show, then optionally `FROM` #tags, a
+/// "folder" or a [[note]] linked to, `WHERE` conditions on fields joined
+/// with AND, OR and NOT, `SORT` fields with ASC or DESC, and `LIMIT`.
+/// Fields are frontmatter keys or `file.` properties such as `file.mtime`.
+#[tauri::command(async)]
+pub fn run_query(
+    root: String,
+    query: String,
+    registry: tauri::State<'_, IndexRegistry>,
+) -> Result<QueryResult, String> {
+    run(&registry, Path::new(&root), &query)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parsed(query: &str) -> Query {
+        parse(query, Path::new("/vault")).unwrap()
+    }
+
+    fn error(query: &str) -> String {
+        match parse(query, Path::new("/vault")) {
+            Ok(_) => panic!("{} parsed", query),
+            Err(e) => e,
+        }
+    }
+
+    #[test]
+    fn tokenizes_values() {
+        let tokens = tokenize(r#"due >= 2024-05-01 AND rating != -1.5 AND title = "say \"hi\"""#).unwrap();
+        assert_eq!(
+            tokens,
+            vec![
+                Token::Word("due".into()),
+                Token::Op(CompareOp::Gte),
+                Token::Text("2024-05-01".into()),
+                Token::Word("AND".into()),
+                Token::Word("rating".into()),
+                Token::Op(CompareOp::Ne),
+                Token::Number(-1.5),
+                Token::Word("AND".into()),
+                Token::Word("title".into()),
+                Token::Op(CompareOp::Eq),
+                Token::Text("say \"hi\"".into()),
+            ]
+        );
+        assert_eq!(
+            tokenize("#project/alpha [[Note|alias]]").unwrap(),
+            vec![Token::Tag("project/alpha".into()), Token::Link("Note".into())]
+        );
+    }
+
+    #[test]
+    fn parses_a_table_query() {
+        let query = parsed(
+            r#"TABLE due AS "Due", status FROM #project AND "work/" WHERE done = false SORT due DESC LIMIT 10"#,
+        );
+        assert_eq!(query.kind, QueryKind::Table);
+        let columns: Vec<(&str, &str)> = query.columns.iter().map(|c| (c.key.as_str(), c.name.as_str())).collect();
+        assert_eq!(columns, [("due", "Due"), ("status", "status")]);
+        assert_eq!(query.from.tags, ["project"]);
+        assert_eq!(query.from.folder.as_deref(), Some(Path::new("/vault/work").to_str().unwrap()));
+        assert!(matches!(
+            query.condition,
+            Some(Expr::Compare(ref key, CompareOp::Eq, Value::Bool(false))) if key == "done"
+        ));
+        assert_eq!(query.sort.len(), 1);
+        assert!(query.sort[0].key == "due" && query.sort[0].descending);
+        assert_eq!(query.limit, Some(10));
+    }
+
+    #[test]
+    fn parses_a_list_query() {
+        let query = parsed("list from [[Ideas]]");
+        assert_eq!(query.kind, QueryKind::List);
+        assert!(query.columns.is_empty());
+        assert_eq!(query.from.links_to.as_deref(), Some("Ideas"));
+
+        let query = parsed("LIST status WHERE status");
+        assert_eq!(query.columns.len(), 1);
+        assert!(matches!(query.condition, Some(Expr::Truthy(ref key)) if key == "status"));
+    }
+
+    #[test]
+    fn binds_and_tighter_than_or() {
+        let query = parsed("LIST WHERE a OR b AND NOT (c OR d)");
+        let Some(Expr::Or(left, right)) = query.condition else {
+            panic!("expected OR at the top");
+        };
+        assert!(matches!(*left, Expr::Truthy(ref key) if key == "a"));
+        let Expr::And(b, not) = *right else {
+            panic!("expected AND on the right");
+        };
+        assert!(matches!(*b, Expr::Truthy(ref key) if key == "b"));
+        assert!(matches!(*not, Expr::Not(ref inner) if matches!(**inner, Expr::Or(_, _))));
+    }
+
+    #[test]
+    fn parses_contains() {
+        let query = parsed("LIST WHERE tags CONTAINS #urgent");
+        let Some(Expr::Compare(key, CompareOp::Contains, Value::String(tag))) = query.condition else {
+            panic!("expected CONTAINS");
+        };
+        assert_eq!((key.as_str(), tag.as_str()), ("tags", "urgent"));
+    }
+
+    #[test]
+    fn rejects_malformed_queries() {
+        assert_eq!(error(r#"LIST WHERE title = "open"#), "Unterminated string");
+        assert_eq!(error("LIST FROM [[Note"), "Unterminated [[link]]");
+        assert_eq!(error("LIST a, b"), "LIST takes one field");
+        assert_eq!(error("LIST LIMIT 2 LIMIT 3"), "LIMIT is given twice");
+        assert_eq!(error("LIST LIMIT 1.5"), "LIMIT takes a whole number");
+        assert_eq!(error(r#"LIST FROM "a" AND "b""#), "FROM takes one folder");
+        assert_eq!(error("LIST WHERE (a OR b"), "Expected )");
+        assert_eq!(error("LIST WHERE = 1"), "Expected a field name, found an operator");
+        assert_eq!(error("LIST WHERE a = "), "Expected a value");
+        assert_eq!(error("LIST @"), "Unexpected character: @");
+    }
+}
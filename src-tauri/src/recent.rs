@@ -0,0 +1,135 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::AppHandle;
+
+use crate::tray;
+
+/// Entries kept per kind; older ones are dropped
+const MAX_ENTRIES: usize = 50;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum RecentKind {
+    Note,
+    Vault,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecentEntry {
+    pub path: String,
+    pub kind: RecentKind,
+    /// Milliseconds since the Unix epoch
+    pub opened_at: u64,
+    /// Whether the path is still there, e.g. the drive a vault is on may not
+    /// be mounted
+    #[serde(skip_deserializing)]
+    pub exists: bool,
+}
+
+/// Recently opened notes and vaults, most recent first, backed by
+/// `recent.json` in the app data directory
+pub struct RecentStore {
+    path: PathBuf,
+    entries: Vec<RecentEntry>,
+}
+
+fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+impl RecentStore {
+    /// Load entries from `path`, starting empty if the file is missing or
+    /// unreadable
+    pub fn load(path: PathBuf) -> Self {
+        let entries = fs::read_to_string(&path)
+            .ok()
+            .and_then(|raw| serde_json::from_str(&raw).ok())
+            .unwrap_or_default();
+        RecentStore { path, entries }
+    }
+
+    /// Up to `limit` entries of `kind`, most recent first
+    pub fn entries(&self, kind: RecentKind, limit: usize) -> Vec<RecentEntry> {
+        self.entries
+            .iter()
+            .filter(|entry| entry.kind == kind)
+            .take(limit)
+            .map(|entry| RecentEntry {
+                exists: Path::new(&entry.path).exists(),
+                ..entry.clone()
+            })
+            .collect()
+    }
+
+    /// Move `path` to the front of the list for `kind` and persist
+    fn touch(&mut self, path: &str, kind: RecentKind) -> Result<(), String> {
+        self.entries.retain(|entry| entry.path != path);
+        self.entries.insert(
+            0,
+            RecentEntry {
+                path: path.to_string(),
+                kind,
+                opened_at: now_millis(),
+                exists: true,
+            },
+        );
+        let mut kept = 0;
+        self.entries.retain(|entry| {
+            if entry.kind != kind {
+                return true;
+            }
+            kept += 1;
+            kept <= MAX_ENTRIES
+        });
+
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent).map_err(|e| format!("Failed to create data directory: {}", e))?;
+        }
+        let json = serde_json::to_string_pretty(&self.entries)
+            .map_err(|e| format!("Failed to serialize recent files: {}", e))?;
+        crate::write_atomic(&self.path, json.as_bytes())
+            .map_err(|e| format!("Failed to save recent files: {}", e))
+    }
+}
+
+/// Recently opened notes or vaults, most recent first. The most recent
+/// vault is the one to reopen on launch.
+#[tauri::command]
+pub fn get_recent(
+    kind: RecentKind,
+    limit: Option<usize>,
+    state: tauri::State<'_, Mutex<RecentStore>>,
+) -> Result<Vec<RecentEntry>, String> {
+    let store = state.lock().map_err(|e| format!("Lock error: {}", e))?;
+    Ok(store.entries(kind, limit.unwrap_or(MAX_ENTRIES)))
+}
+
+/// Record that a note (a file) or vault (a folder) was just opened
+#[tauri::command]
+pub fn touch_recent(
+    path: String,
+    app: AppHandle,
+    state: tauri::State<'_, Mutex<RecentStore>>,
+) -> Result<RecentEntry, String> {
+    let path_buf = PathBuf::from(&path);
+    if !path_buf.exists() {
+        return Err(format!("Path does not exist: {}", path));
+    }
+    let kind = if path_buf.is_dir() { RecentKind::Vault } else { RecentKind::Note };
+
+    let entry = {
+        let mut store = state.lock().map_err(|e| format!("Lock error: {}", e))?;
+        store.touch(&path, kind)?;
+        store.entries[0].clone()
+    };
+    if kind == RecentKind::Note {
+        tray::refresh(&app);
+    }
+    Ok(entry)
+}
@@ -0,0 +1,253 @@
+use chrono::{Local, NaiveDate, NaiveDateTime, NaiveTime, TimeDelta};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Listener, Manager};
+use tauri_plugin_notification::NotificationExt;
+
+use crate::encoding;
+use crate::metadata_cache::MetadataCache;
+use crate::note_index::NoteIndexRegistry;
+use crate::settings::SettingsStore;
+use crate::tasks::{self, Task};
+
+/// How often open vaults are checked for tasks coming due
+const TICK: Duration = Duration::from_secs(30);
+
+/// Event the window gets for each reminder, to offer snoozing and
+/// completing the task
+pub const REMINDER_EVENT: &str = "task-reminder";
+
+/// Event the window sends a `ReminderAction` back in, e.g. from the buttons
+/// of its own reminder or of a notification
+pub const ACTION_EVENT: &str = "task-reminder-action";
+
+/// Notifications group their actions under this id
+const ACTION_TYPE: &str = "task-reminder";
+
+/// A task is told apart by its note and text, which stay put when lines are
+/// added above it
+type TaskKey = (PathBuf, String);
+
+#[derive(Default)]
+pub struct Reminders {
+    state: Mutex<ReminderState>,
+}
+
+#[derive(Default)]
+struct ReminderState {
+    /// When open vaults were last checked; reminders due after it are next
+    checked: Option<NaiveDateTime>,
+    /// Snoozed tasks and when to remind of them again. Kept in memory only,
+    /// so snoozes don't outlive the app.
+    snoozed: HashMap<TaskKey, NaiveDateTime>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TaskReminder {
+    pub path: String,
+    pub line: usize,
+    /// The task's text without its due date
+    pub text: String,
+    /// `YYYY-MM-DD`
+    pub due: String,
+    pub due_time: Option<String>,
+    /// Whether this reminder is one that was snoozed
+    pub snoozed: bool,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "action", rename_all = "lowercase")]
+pub enum ReminderAction {
+    Snooze {
+        path: String,
+        line: usize,
+        minutes: Option<u32>,
+    },
+    Complete {
+        path: String,
+        line: usize,
+    },
+}
+
+/// When to remind of `task`: at its due time, or at `default_time` on its
+/// due date. `None` for tasks that are done or have no due date.
+fn reminder_time(task: &Task, default_time: NaiveTime) -> Option<NaiveDateTime> {
+    if task.completed {
+        return None;
+    }
+    let date = NaiveDate::parse_from_str(task.due.as_deref()?, "%Y-%m-%d").ok()?;
+    let time = match task.due_time.as_deref() {
+        Some(time) => NaiveTime::parse_from_str(time, "%H:%M").ok()?,
+        None => default_time,
+    };
+    Some(date.and_time(time))
+}
+
+fn reminder(path: &Path, task: &Task, snoozed: bool) -> TaskReminder {
+    TaskReminder {
+        path: path.to_string_lossy().to_string(),
+        line: task.line,
+        text: tasks::strip_due(&task.text),
+        due: task.due.clone().unwrap_or_default(),
+        due_time: task.due_time.clone(),
+        snoozed,
+    }
+}
+
+/// Reminders of the open tasks of open vaults that came due after the last
+/// check, and of snoozed ones whose snooze is over
+fn due_reminders(app: &AppHandle, now: NaiveDateTime) -> Vec<TaskReminder> {
+    let settings = app.state::<Mutex<SettingsStore>>();
+    let Ok(reminders) = settings.lock().map(|store| store.settings().reminders.clone()) else {
+        return Vec::new();
+    };
+    let state = app.state::<Reminders>();
+    let Ok(mut state) = state.state.lock() else {
+        return Vec::new();
+    };
+    // The first check only catches what is due from then on, so reopening
+    // the app doesn't repeat reminders
+    let since = state.checked.replace(now).unwrap_or(now);
+    if !reminders.enabled {
+        return Vec::new();
+    }
+    let default_time = NaiveTime::parse_from_str(&reminders.time, "%H:%M").unwrap_or(NaiveTime::MIN);
+
+    let mut due = Vec::new();
+    let mut seen = HashSet::new();
+    for index in app.state::<NoteIndexRegistry>().indexes() {
+        let Ok(contents) = index.contents() else {
+            continue;
+        };
+        for (path, note) in &contents.notes {
+            for task in &note.tasks {
+                let key = (path.clone(), task.text.clone());
+                // Nested vaults index the same notes
+                if seen.contains(&key) {
+                    continue;
+                }
+                let snoozed = state.snoozed.get(&key).copied();
+                let at = match snoozed {
+                    Some(_) if task.completed => {
+                        state.snoozed.remove(&key);
+                        continue;
+                    }
+                    Some(until) => Some(until),
+                    None => reminder_time(task, default_time),
+                };
+                if at.is_some_and(|at| at <= now && (snoozed.is_some() || at > since)) {
+                    state.snoozed.remove(&key);
+                    due.push(reminder(path, task, snoozed.is_some()));
+                    seen.insert(key);
+                }
+            }
+        }
+    }
+    // Snoozes that are over without a reminder are of tasks that are gone,
+    // e.g. edited or deleted
+    state.snoozed.retain(|_, until| *until > now);
+    due
+}
+
+/// Show `reminder` as a system notification and pass it to the window
+fn notify(app: &AppHandle, reminder: &TaskReminder) {
+    let note = Path::new(&reminder.path).file_stem().unwrap_or_default().to_string_lossy().to_string();
+    let when = match &reminder.due_time {
+        Some(time) => format!("Due {} at {}", reminder.due, time),
+        None => format!("Due {}", reminder.due),
+    };
+    let shown = app
+        .notification()
+        .builder()
+        .title(&reminder.text)
+        .body(format!("{} · {}", when, note))
+        .action_type_id(ACTION_TYPE)
+        .extra("path", &reminder.path)
+        .extra("line", reminder.line)
+        .show();
+    if let Err(e) = shown {
+        eprintln!("Failed to show reminder for {}: {}", reminder.path, e);
+    }
+    let _ = app.emit(REMINDER_EVENT, reminder);
+}
+
+/// Remind of the task on `line` of the note at `path` again in `minutes`,
+/// or the snooze time in the settings. Returns when, as local
+/// `YYYY-MM-DDTHH:MM:SS`.
+fn snooze(
+    path: &str,
+    line: usize,
+    minutes: Option<u32>,
+    reminders: &Reminders,
+    settings: &Mutex<SettingsStore>,
+) -> Result<String, String> {
+    let minutes = match minutes {
+        Some(minutes) => minutes,
+        None => settings.lock().map_err(|e| format!("Lock error: {}", e))?.settings().reminders.snooze_minutes,
+    };
+    let content = fs::read(path).map_err(|e| format!("Failed to read file: {}", e))?;
+    let task = tasks::extract(&encoding::decode(&content).content)
+        .into_iter()
+        .find(|task| task.line == line)
+        .ok_or_else(|| format!("Line {} is not a task", line))?;
+    let until = Local::now().naive_local() + TimeDelta::minutes(minutes.max(1) as i64);
+    let mut state = reminders.state.lock().map_err(|e| format!("Lock error: {}", e))?;
+    state.snoozed.insert((PathBuf::from(path), task.text), until);
+    Ok(until.format("%Y-%m-%dT%H:%M:%S").to_string())
+}
+
+fn handle_action(app: &AppHandle, payload: &str) {
+    let action = match serde_json::from_str::<ReminderAction>(payload) {
+        Ok(action) => action,
+        Err(e) => return eprintln!("Invalid reminder action {}: {}", payload, e),
+    };
+    let settings = app.state::<Mutex<SettingsStore>>();
+    let (path, result) = match action {
+        ReminderAction::Snooze { path, line, minutes } => {
+            let result = snooze(&path, line, minutes, &app.state::<Reminders>(), &settings).map(|_| ());
+            (path, result)
+        }
+        ReminderAction::Complete { path, line } => {
+            let registry = app.state::<NoteIndexRegistry>();
+            let cache = app.state::<MetadataCache>();
+            let result =
+                tasks::set_checkbox(&path, line, Some(true), None, None, &registry, &settings, &cache).map(|_| ());
+            (path, result.map_err(|e| e.to_string()))
+        }
+    };
+    if let Err(e) = result {
+        eprintln!("Reminder action on {} failed: {}", path, e);
+    }
+}
+
+/// Start the thread that reminds of tasks in open vaults as they come due,
+/// and route the snooze and complete actions the window sends back
+pub fn spawn(app: AppHandle) {
+    let handle = app.clone();
+    app.listen(ACTION_EVENT, move |event| handle_action(&handle, event.payload()));
+    thread::spawn(move || loop {
+        for reminder in due_reminders(&app, Local::now().naive_local()) {
+            notify(&app, &reminder);
+        }
+        thread::sleep(TICK);
+    });
+}
+
+/// Put off the reminder of the task on `line` (1-based) of the note at
+/// `path` by `minutes`, or the snooze time in the settings. Returns when it
+/// comes back, as local `YYYY-MM-DDTHH:MM:SS`.
+#[tauri::command]
+pub fn snooze_task(
+    path: String,
+    line: usize,
+    minutes: Option<u32>,
+    reminders: tauri::State<'_, Reminders>,
+    settings: tauri::State<'_, Mutex<SettingsStore>>,
+) -> Result<String, String> {
+    snooze(&path, line, minutes, &reminders, &settings)
+}
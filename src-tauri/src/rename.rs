@@ -0,0 +1,477 @@
+use std::collections::HashMap;
+use std::ffi::OsStr;
+use std::fs;
+use std::ops::Range;
+use std::path::{Component, Path, PathBuf};
+use serde::Serialize;
+use std::sync::Mutex;
+
+use crate::encoding;
+use crate::encryption;
+use crate::error::CommandError;
+use crate::links::{self, normalize_path, Link, LinkKind, NameLookup, Resolver};
+use crate::metadata_cache::MetadataCache;
+use crate::note_index::{NoteIndex, NoteIndexRegistry};
+use crate::pins;
+use crate::settings::{self, SettingsStore};
+
+#[derive(Debug, Serialize)]
+pub struct MoveResult {
+    /// Where the note is now
+    pub new_path: String,
+    /// Notes whose links were rewritten, including the moved one at its new
+    /// path
+    pub modified: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FolderRename {
+    pub new_path: String,
+    /// Files inside the folder
+    pub moved: usize,
+    /// Notes whose links were, or with `dry_run` would be, rewritten, at
+    /// their paths after the rename
+    pub modified: Vec<String>,
+    pub dry_run: bool,
+}
+
+/// A note whose links change because of a move
+pub struct LinkUpdate {
+    /// Where the note is now
+    pub from: PathBuf,
+    /// Where the note will be once the moves are done
+    pub path: PathBuf,
+    /// The new text, with `\n` line breaks
+    pub content: String,
+    /// The note's bytes before the update, whose encoding and line endings
+    /// `content` is written in
+    pub previous: Vec<u8>,
+}
+
+impl LinkUpdate {
+    /// `content` as the bytes to write
+    fn encoded(&self) -> Result<Vec<u8>, String> {
+        encoding::encode(&self.content, encoding::format_of(&self.previous))
+            .map_err(|e| format!("{}: {}", self.path.display(), e))
+    }
+}
+
+/// `target` relative to the directory `from_dir`, with `/` separators
+pub fn relative_path(from_dir: &Path, target: &Path) -> String {
+    let from: Vec<Component> = from_dir.components().collect();
+    let to: Vec<Component> = target.components().collect();
+    let common = from.iter().zip(&to).take_while(|(a, b)| a == b).count();
+
+    let mut parts: Vec<String> = vec!["..".to_string(); from.len() - common];
+    parts.extend(to[common..].iter().map(|c| c.as_os_str().to_string_lossy().to_string()));
+    parts.join("/")
+}
+
+/// `path` without its extension, if the link it came from was written
+/// without one
+fn match_extension_style(path: String, written: &str) -> String {
+    if Path::new(written).extension().is_some() {
+        return path;
+    }
+    match Path::new(&path).extension() {
+        Some(extension) => path[..path.len() - extension.len() - 1].to_string(),
+        None => path,
+    }
+}
+
+/// Byte range of the target text within `link`'s source, e.g. `Note` in
+/// `[[Note#Heading|alias]]`. `None` for reference-style links, whose target
+/// is defined elsewhere.
+pub fn target_range(content: &str, link: &Link) -> Option<Range<usize>> {
+    let span = &content[link.start..link.end];
+    let at = match link.kind {
+        LinkKind::Wiki => span.find("[[")? + 2,
+        LinkKind::Markdown => {
+            let at = span.rfind("](")? + 2;
+            if span[at..].starts_with('<') {
+                at + 1
+            } else {
+                at
+            }
+        }
+    };
+    span[at..]
+        .starts_with(&link.target)
+        .then(|| link.start + at..link.start + at + link.target.len())
+}
+
+/// Computes the rewritten links for a set of moves within one vault
+struct Rewriter<'a> {
+    root: &'a Path,
+    names: &'a NameLookup,
+    /// Old path to new path
+    moves: &'a HashMap<PathBuf, PathBuf>,
+}
+
+impl Rewriter<'_> {
+    /// New target text for a wiki link whose file moved from `old_target` to
+    /// `new_target`. Stays a bare name where that is unambiguous.
+    fn wiki_target(&self, link: &Link, old_target: &Path, new_target: &Path) -> Option<String> {
+        let keep_extension = Path::new(&link.target).extension().is_some();
+
+        if !link.target.contains('/') {
+            // A link by one of the note's aliases rather than its name keeps
+            // working wherever the note goes
+            let written = link.target.to_lowercase();
+            let named = |name: Option<&OsStr>| name.is_some_and(|name| name.to_string_lossy().to_lowercase() == written);
+            if !named(old_target.file_stem()) && !named(old_target.file_name()) {
+                return None;
+            }
+            let name = if keep_extension {
+                new_target.file_name()
+            } else {
+                new_target.file_stem()
+            }?
+            .to_string_lossy()
+            .to_string();
+            // A bare name is enough unless another note with that name would
+            // take precedence
+            let stem = new_target.file_stem()?.to_string_lossy();
+            let clash = self
+                .names
+                .notes_named(&stem)
+                .iter()
+                .any(|p| p != old_target && p != new_target && !self.moves.contains_key(p));
+            if !clash {
+                return Some(name);
+            }
+        }
+
+        let relative = relative_path(self.root, new_target);
+        Some(match_extension_style(relative, &link.target))
+    }
+
+    /// New target text for a markdown link in the note at `source` (after any
+    /// move) to the file now at `new_target`
+    fn markdown_target(&self, link: &Link, source: &Path, new_target: &Path) -> Option<String> {
+        let path = if link.target.starts_with('/') {
+            format!("/{}", relative_path(self.root, new_target))
+        } else {
+            relative_path(source.parent()?, new_target)
+        };
+        let path = match_extension_style(path, &links::percent_decode(&link.target));
+        // Keep spaces raw only where they were written raw, as in `<my note.md>`
+        Some(if link.target.contains(' ') {
+            path
+        } else {
+            path.replace(' ', "%20")
+        })
+    }
+}
+
+/// Work out the link changes needed across the vault when files move per
+/// `moves`. Encrypted notes and notes that can't be read keep their links.
+pub fn plan_updates(index: &NoteIndex, moves: &HashMap<PathBuf, PathBuf>) -> Result<Vec<LinkUpdate>, String> {
+    let contents = index.contents()?;
+    let names = NameLookup::new(&contents);
+    let resolver = Resolver {
+        root: index.root(),
+        note_extensions: index.note_extensions(),
+        names: &names,
+    };
+    let rewriter = Rewriter {
+        root: index.root(),
+        names: &names,
+        moves,
+    };
+
+    let mut updates = Vec::new();
+    for (source, note) in &contents.notes {
+        let source_moved = moves.contains_key(source);
+        let links_to_moved = note
+            .links
+            .iter()
+            .any(|link| resolver.resolve(source, link).is_some_and(|t| moves.contains_key(&t)));
+        if !source_moved && !links_to_moved {
+            continue;
+        }
+
+        // Offsets in the index may be stale, so work from the file on disk
+        let previous = match fs::read(source) {
+            Ok(bytes) if !encryption::is_encrypted(&bytes) => bytes,
+            Ok(_) => continue,
+            Err(e) => {
+                eprintln!("Skipping links in {}: {}", source.display(), e);
+                continue;
+            }
+        };
+        let content = encoding::decode(&previous).content;
+        let new_source = moves.get(source).unwrap_or(source);
+
+        let mut edits: Vec<(Range<usize>, String)> = Vec::new();
+        for link in links::extract(&content) {
+            if link.target.is_empty() {
+                continue;
+            }
+            let Some(old_target) = resolver.resolve(source, &link) else {
+                continue;
+            };
+            let new_target = moves.get(&old_target).unwrap_or(&old_target);
+
+            let text = match link.kind {
+                LinkKind::Wiki if moves.contains_key(&old_target) => {
+                    rewriter.wiki_target(&link, &old_target, new_target)
+                }
+                // Relative paths change when either end moves
+                LinkKind::Markdown if source_moved || moves.contains_key(&old_target) => {
+                    rewriter.markdown_target(&link, new_source, new_target)
+                }
+                _ => None,
+            };
+            let Some(text) = text.filter(|text| *text != link.target) else {
+                continue;
+            };
+            if let Some(range) = target_range(&content, &link) {
+                edits.push((range, text));
+            }
+        }
+
+        if edits.is_empty() {
+            continue;
+        }
+        let mut updated = content;
+        for (range, text) in edits.into_iter().rev() {
+            updated.replace_range(range, &text);
+        }
+        updates.push(LinkUpdate {
+            from: source.clone(),
+            path: new_source.clone(),
+            content: updated,
+            previous,
+        });
+    }
+
+    Ok(updates)
+}
+
+/// Move files per `moves`, then save the notes whose links changed. Returns
+/// the paths of the notes that were rewritten.
+fn apply_moves(
+    index: &NoteIndex,
+    moves: &HashMap<PathBuf, PathBuf>,
+    registry: &NoteIndexRegistry,
+    settings: &Mutex<SettingsStore>,
+    cache: &MetadataCache,
+) -> Result<Vec<String>, String> {
+    let updates = plan_updates(index, moves)?;
+    for (from, to) in moves {
+        crate::move_path(from, to, false)?;
+    }
+    let moved: Vec<(&Path, &Path)> = moves.iter().map(|(from, to)| (from.as_path(), to.as_path())).collect();
+    pins::record_moves(index.root(), &moved);
+
+    let mut modified = Vec::new();
+    let mut failed = Vec::new();
+    for update in &updates {
+        let written = update.encoded().and_then(|bytes| {
+            crate::write_atomic(&update.path, &bytes).map_err(|e| e.to_string())?;
+            Ok(bytes)
+        });
+        match written {
+            Ok(bytes) => {
+                crate::note_written(&update.path, Some(&update.previous), &bytes, registry, settings, cache);
+                modified.push(update.path.to_string_lossy().to_string());
+            }
+            Err(e) => failed.push(format!("{} ({})", update.path.display(), e)),
+        }
+    }
+
+    // Don't leave queries to wait for the watcher to catch up
+    for path in moves.keys().chain(moves.values()).chain(updates.iter().map(|u| &u.path)) {
+        index.update_path(path);
+    }
+
+    if !failed.is_empty() {
+        return Err(format!("Moved, but failed to update links in {}", failed.join(", ")));
+    }
+    modified.sort();
+    Ok(modified)
+}
+
+/// Rename or move a note and rewrite the `[[wiki]]` and relative markdown
+/// links that pointed at it, as well as the note's own relative links.
+/// Returns the notes that were modified.
+#[tauri::command(async)]
+pub fn rename_note_with_links(
+    old_path: String,
+    new_path: String,
+    registry: tauri::State<'_, NoteIndexRegistry>,
+    settings: tauri::State<'_, Mutex<SettingsStore>>,
+    cache: tauri::State<'_, MetadataCache>,
+) -> Result<Vec<String>, CommandError> {
+    let from = normalize_path(Path::new(&old_path));
+    let to = normalize_path(Path::new(&new_path));
+    settings::check_writable(&settings, &from)?;
+    settings::check_writable(&settings, &to)?;
+    if !from.is_file() {
+        return Err(format!("File does not exist: {}", old_path).into());
+    }
+    if to.exists() {
+        return Err(format!("Destination already exists: {}", new_path).into());
+    }
+
+    let index = registry
+        .for_path(&from)
+        .ok_or_else(|| format!("No open vault contains {}", old_path))?;
+    Ok(apply_moves(&index, &HashMap::from([(from, to)]), &registry, &settings, &cache)?)
+}
+
+/// Move a note into the folder `dest_dir`, keeping its name. With
+/// `update_links`, the note's own relative links are rewritten to still
+/// resolve from its new folder, and links to it from other notes to point
+/// at its new path.
+#[tauri::command(async)]
+pub fn move_note(
+    path: String,
+    dest_dir: String,
+    update_links: bool,
+    registry: tauri::State<'_, NoteIndexRegistry>,
+    settings: tauri::State<'_, Mutex<SettingsStore>>,
+    cache: tauri::State<'_, MetadataCache>,
+) -> Result<MoveResult, CommandError> {
+    let from = normalize_path(Path::new(&path));
+    let name = from.file_name().ok_or_else(|| format!("Not a file: {}", path))?;
+    let to = normalize_path(&Path::new(&dest_dir).join(name));
+    settings::check_writable(&settings, &from)?;
+    settings::check_writable(&settings, &to)?;
+    if !from.is_file() {
+        return Err(format!("File does not exist: {}", path).into());
+    }
+    if to == from {
+        return Err(format!("{} is already in {}", path, dest_dir).into());
+    }
+    if to.exists() {
+        return Err(format!("Destination already exists: {}", to.display()).into());
+    }
+
+    let created = crate::first_missing_ancestor(&to);
+    let modified = if update_links {
+        let index = registry
+            .for_path(&from)
+            .ok_or_else(|| format!("No open vault contains {}", path))?;
+        apply_moves(&index, &HashMap::from([(from.clone(), to.clone())]), &registry, &settings, &cache)
+    } else {
+        crate::move_path(&from, &to, false).map(|()| {
+            pins::record_moves(&registry.root_for(&from), &[(&from, &to)]);
+            Vec::new()
+        })
+    };
+    cache.invalidate(&from);
+    cache.invalidate(created.as_deref().unwrap_or(&to));
+    Ok(MoveResult {
+        new_path: to.to_string_lossy().to_string(),
+        modified: modified?,
+    })
+}
+
+/// Write `updates`, restoring the notes already written if one fails, and
+/// record them in the file history once all are
+pub fn write_all_or_none(
+    updates: &[LinkUpdate],
+    registry: &NoteIndexRegistry,
+    settings: &Mutex<SettingsStore>,
+    cache: &MetadataCache,
+) -> Result<(), String> {
+    let encoded = updates.iter().map(LinkUpdate::encoded).collect::<Result<Vec<_>, String>>()?;
+    let mut written: Vec<(&Path, Vec<u8>)> = Vec::new();
+    for (update, bytes) in updates.iter().zip(&encoded) {
+        let result = fs::read(&update.path).and_then(|original| {
+            crate::write_atomic(&update.path, bytes)?;
+            written.push((&update.path, original));
+            Ok(())
+        });
+        if let Err(e) = result {
+            for (path, original) in &written {
+                let _ = crate::write_atomic(path, original);
+            }
+            return Err(format!("Failed to update links in {}: {}", update.path.display(), e));
+        }
+    }
+    for (update, bytes) in updates.iter().zip(&encoded) {
+        crate::note_written(&update.path, Some(&update.previous), bytes, registry, settings, cache);
+    }
+    Ok(())
+}
+
+/// Rename or move the folder `old_path` to `new_path` and rewrite every link
+/// in the vault to a file inside it, as well as the relative links of the
+/// notes it holds. Either all of it happens or none does: if a note can't be
+/// rewritten, the folder is moved back. With `dry_run`, only reports what
+/// would change.
+#[tauri::command(async)]
+pub fn rename_folder(
+    old_path: String,
+    new_path: String,
+    dry_run: bool,
+    registry: tauri::State<'_, NoteIndexRegistry>,
+    settings: tauri::State<'_, Mutex<SettingsStore>>,
+    cache: tauri::State<'_, MetadataCache>,
+) -> Result<FolderRename, CommandError> {
+    let from = normalize_path(Path::new(&old_path));
+    let to = normalize_path(Path::new(&new_path));
+    if !from.is_dir() {
+        return Err(format!("Folder does not exist: {}", old_path).into());
+    }
+    if to.exists() {
+        return Err(format!("Destination already exists: {}", new_path).into());
+    }
+    if to.starts_with(&from) {
+        return Err(format!("Can't move a folder into itself: {}", new_path).into());
+    }
+    let index = registry
+        .for_path(&from)
+        .ok_or_else(|| format!("No open vault contains {}", old_path))?;
+    if !to.starts_with(index.root()) {
+        return Err(format!("Destination is outside the vault: {}", new_path).into());
+    }
+
+    let moves: HashMap<PathBuf, PathBuf> = {
+        let contents = index.contents()?;
+        contents
+            .notes
+            .keys()
+            .chain(&contents.attachments)
+            .filter_map(|path| Some((path.clone(), to.join(path.strip_prefix(&from).ok()?))))
+            .collect()
+    };
+    let updates = plan_updates(&index, &moves)?;
+    let mut modified: Vec<String> = updates.iter().map(|u| u.path.to_string_lossy().to_string()).collect();
+    modified.sort();
+    let result = FolderRename {
+        new_path: to.to_string_lossy().to_string(),
+        moved: moves.len(),
+        modified,
+        dry_run,
+    };
+    if dry_run {
+        return Ok(result);
+    }
+
+    settings::check_writable(&settings, &from)?;
+    settings::check_writable(&settings, &to)?;
+    let created = crate::first_missing_ancestor(&to);
+    crate::move_path(&from, &to, false)?;
+    if let Err(e) = write_all_or_none(&updates, &registry, &settings, &cache) {
+        let rolled_back = crate::move_path(&to, &from, false);
+        return Err(match rolled_back {
+            Ok(()) => format!("{}; the folder was not renamed", e),
+            Err(back) => format!("{}; moving the folder back failed too: {}", e, back),
+        }
+        .into());
+    }
+    pins::record_moves(index.root(), &[(&from, &to)]);
+
+    index.update_path(&from);
+    index.update_path(&to);
+    for update in &updates {
+        index.update_path(&update.path);
+    }
+    cache.invalidate(&from);
+    cache.invalidate(created.as_deref().unwrap_or(&to));
+    Ok(result)
+}
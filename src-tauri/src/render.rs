@@ -0,0 +1,127 @@
+use pulldown_cmark::{html, CowStr, Event, LinkType, Parser, Tag};
+use serde::Deserialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::links::{self, Link, LinkKind, Resolver};
+use crate::markdown;
+use crate::note_index::NoteIndexRegistry;
+use crate::settings::SettingsStore;
+
+/// What to render: `{ "content": "..." }` or `{ "path": "..." }`
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MarkdownSource {
+    Content(String),
+    Path(String),
+}
+
+#[derive(Debug, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct RenderOptions {
+    /// Point local images at the asset protocol so the webview can load them
+    pub asset_urls: bool,
+    /// Directory that relative image paths in `content` are resolved
+    /// against. Notes rendered by path use their own directory.
+    pub base_dir: Option<String>,
+}
+
+/// HTML for the body of `content`, frontmatter excluded, with every parser
+/// event passed through `map` first
+pub fn render<'a>(content: &'a str, map: impl FnMut(Event<'a>) -> Event<'a>) -> String {
+    let body = &content[markdown::body_start(content)..];
+    let mut output = String::new();
+    html::push_html(&mut output, Parser::new_ext(body, markdown::parser_options()).map(map));
+    output
+}
+
+/// Percent-encode everything but the characters JavaScript's
+/// `encodeURIComponent` leaves alone
+fn encode_uri_component(text: &str) -> String {
+    let mut encoded = String::with_capacity(text.len());
+    for byte in text.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'!' | b'~' | b'*' | b'\'' | b'(' | b')' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+/// URL the webview loads a local file from, as `convertFileSrc` builds it
+pub fn asset_url(path: &Path) -> String {
+    let encoded = encode_uri_component(&path.to_string_lossy());
+    if cfg!(windows) {
+        format!("http://asset.localhost/{}", encoded)
+    } else {
+        format!("asset://localhost/{}", encoded)
+    }
+}
+
+/// The local file an image (`![](dest)` or `![[dest]]`) in the note at
+/// `source` shows, if it exists
+pub fn local_image(resolver: &Resolver, source: &Path, link_type: LinkType, dest: &str) -> Option<PathBuf> {
+    if links::is_external(dest) {
+        return None;
+    }
+    let link = Link {
+        kind: match link_type {
+            LinkType::WikiLink { .. } => LinkKind::Wiki,
+            _ => LinkKind::Markdown,
+        },
+        embed: true,
+        target: dest.split('#').next().unwrap_or_default().to_string(),
+        fragment: None,
+        text: String::new(),
+        line: 0,
+        start: 0,
+        end: 0,
+    };
+    resolver.resolve(source, &link).filter(|path| path.is_file())
+}
+
+/// Render markdown to HTML with the same extensions as the rest of the app,
+/// for the preview pane and exports
+#[tauri::command(async)]
+pub fn render_markdown(
+    content_or_path: MarkdownSource,
+    options: Option<RenderOptions>,
+    registry: tauri::State<'_, NoteIndexRegistry>,
+    settings: tauri::State<'_, Mutex<SettingsStore>>,
+) -> Result<String, String> {
+    let options = options.unwrap_or_default();
+    let (content, source) = match content_or_path {
+        MarkdownSource::Path(path) => {
+            let content = fs::read_to_string(&path).map_err(|e| format!("Failed to read file: {}", e))?;
+            (content, links::normalize_path(Path::new(&path)))
+        }
+        MarkdownSource::Content(content) => {
+            // Stand-in note path; only its directory is used
+            let base_dir = options.base_dir.clone().unwrap_or_default();
+            (content, Path::new(&base_dir).join("_"))
+        }
+    };
+    if !options.asset_urls {
+        return Ok(render(&content, |event| event));
+    }
+
+    let (root, note_extensions, names) = links::lookup_for(&source, &registry, &settings)?;
+    let resolver = Resolver {
+        root: &root,
+        note_extensions: &note_extensions,
+        names: &names,
+    };
+    Ok(render(&content, |event| match event {
+        Event::Start(Tag::Image { link_type, dest_url, title, id }) => {
+            let dest_url = match local_image(&resolver, &source, link_type, &dest_url) {
+                Some(path) => CowStr::from(asset_url(&path)),
+                None => dest_url,
+            };
+            Event::Start(Tag::Image { link_type, dest_url, title, id })
+        }
+        event => event,
+    }))
+}
@@ -0,0 +1,478 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use serde_json::Value;
+use std::sync::Mutex;
+use tauri::ipc::{Invoke, InvokeBody};
+use tauri::{AppHandle, DragDropEvent, Manager, Runtime, Window, WindowEvent};
+use tauri_plugin_dialog::DialogExt;
+
+use crate::settings::{self, SettingsStore};
+
+/// Command arguments holding a path, or a list of paths, that must be inside
+/// the open vault or allowed. Export destinations and import sources outside
+/// the vault are allowed by picking them with `pick_save_path`, `pick_file`
+/// or `pick_folder`.
+const PATH_ARGS: &[&str] = &[
+    "path", "paths", "root", "oldPath", "newPath", "notePath", "sourcePath", "destDir", "template", "dest",
+    "folder", "source", "target", "original", "conflict", "outPath", "outDir", "file", "zipPath",
+];
+
+/// Paths in the arguments of particular commands, as `/`-separated keys with
+/// `*` for each item of a list or value of a map
+const COMMAND_PATH_ARGS: &[(&str, &str)] = &[
+    ("import_logseq", "dir"),
+    ("render_markdown", "contentOrPath/path"),
+    ("render_markdown", "options/base_dir"),
+    ("format_markdown", "contentOrPath/path"),
+    ("replace_in_files", "options/only_files"),
+    ("git_sync_continue", "resolved/*/path"),
+    // Backups are written there on a schedule, so the folder must be picked
+    ("set_backup_settings", "backup/dir"),
+    ("set_settings", "patch/vaults/*/backup/dir"),
+];
+
+/// Commands that check their path arguments themselves
+const UNCHECKED_COMMANDS: &[&str] = &["set_vault_root"];
+
+struct VaultRoot {
+    /// As given, cleaned up
+    path: PathBuf,
+    /// With symlinks resolved
+    canonical: PathBuf,
+}
+
+#[derive(Default)]
+struct Allowed {
+    root: Option<VaultRoot>,
+    /// Folders the user picked as a vault or opened the app with, which
+    /// `set_vault_root` may switch to
+    vaults: HashSet<PathBuf>,
+    /// Files outside the vault the user opened through the OS, the file
+    /// dialog or drag and drop, or picked to save to
+    files: HashSet<PathBuf>,
+    /// Folders outside the vault the user picked in the dialog, e.g. to
+    /// export to, with everything in them
+    folders: HashSet<PathBuf>,
+}
+
+/// The folder commands may touch, so a compromised webview can't reach the
+/// rest of the disk
+#[derive(Default)]
+pub struct Sandbox {
+    allowed: Mutex<Allowed>,
+}
+
+/// `path` without `.` components, or an error if it is relative or climbs
+/// out with `..`
+fn clean(path: &Path) -> Result<PathBuf, String> {
+    if !path.is_absolute() {
+        return Err(format!("Path must be absolute: {}", path.display()));
+    }
+    let mut cleaned = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::ParentDir => return Err(format!("Path must not contain '..': {}", path.display())),
+            Component::CurDir => {}
+            other => cleaned.push(other),
+        }
+    }
+    Ok(cleaned)
+}
+
+/// `path` with symlinks resolved as far as it exists
+fn canonicalize_existing(path: &Path) -> PathBuf {
+    let mut missing = Vec::new();
+    let mut existing = path;
+    loop {
+        if let Ok(canonical) = fs::canonicalize(existing) {
+            return missing.iter().rev().fold(canonical, |path, name| path.join(name));
+        }
+        match (existing.parent(), existing.file_name()) {
+            (Some(parent), Some(name)) => {
+                missing.push(name.to_os_string());
+                existing = parent;
+            }
+            _ => return path.to_path_buf(),
+        }
+    }
+}
+
+impl Sandbox {
+    /// Confine commands to the folder `path`, returning it canonicalized
+    fn open_root(&self, path: &Path) -> Result<String, String> {
+        let cleaned = clean(path)?;
+        let canonical = fs::canonicalize(&cleaned).map_err(|e| format!("Failed to open vault: {}", e))?;
+        if !canonical.is_dir() {
+            return Err(format!("Not a folder: {}", path.display()));
+        }
+        // Confining to these would confine nothing
+        let home = std::env::var_os("HOME").or_else(|| std::env::var_os("USERPROFILE")).map(PathBuf::from);
+        if canonical.parent().is_none() || home.is_some_and(|home| canonical == canonicalize_existing(&home)) {
+            return Err(format!("Too broad to open as a vault: {}", path.display()));
+        }
+
+        let mut allowed = self.allowed.lock().map_err(|e| format!("Lock error: {}", e))?;
+        allowed.vaults.insert(canonical.clone());
+        allowed.root = Some(VaultRoot {
+            path: cleaned,
+            canonical: canonical.clone(),
+        });
+        Ok(canonical.to_string_lossy().to_string())
+    }
+
+    /// The open vault, if any
+    pub fn root(&self) -> Option<PathBuf> {
+        let allowed = self.allowed.lock().ok()?;
+        allowed.root.as_ref().map(|root| root.path.clone())
+    }
+
+    /// Let `set_vault_root` open the folder `path`, for folders the app was
+    /// opened with
+    pub fn allow_vault(&self, path: &Path) {
+        let Ok(canonical) = clean(path).and_then(|path| fs::canonicalize(path).map_err(|e| e.to_string())) else {
+            return;
+        };
+        if let Ok(mut allowed) = self.allowed.lock() {
+            allowed.vaults.insert(canonical);
+        }
+    }
+
+    /// Let commands reach `file` although it is outside the vault, for files
+    /// the user opened themselves
+    pub fn allow_file(&self, file: &Path) {
+        let Ok(file) = clean(file) else {
+            return;
+        };
+        if let Ok(mut allowed) = self.allowed.lock() {
+            allowed.files.insert(canonicalize_existing(&file));
+            allowed.files.insert(file);
+        }
+    }
+
+    /// Let commands reach `folder` and everything in it although it is
+    /// outside the vault, for folders the user picked themselves
+    fn allow_folder(&self, folder: &Path) {
+        let Ok(folder) = clean(folder) else {
+            return;
+        };
+        if let Ok(mut allowed) = self.allowed.lock() {
+            allowed.folders.insert(canonicalize_existing(&folder));
+            allowed.folders.insert(folder);
+        }
+    }
+
+    /// Ok if `path` is inside the open vault or an allowed folder, or is an
+    /// allowed file. Symlinks within the vault are followed wherever they
+    /// lead, as the file tree shows them.
+    pub fn check(&self, path: &str) -> Result<(), String> {
+        let path = clean(Path::new(path))?;
+        let allowed = self.allowed.lock().map_err(|e| format!("Lock error: {}", e))?;
+        let canonical = canonicalize_existing(&path);
+        if allowed.files.contains(&path) || allowed.files.contains(&canonical) {
+            return Ok(());
+        }
+        if allowed.folders.iter().any(|folder| path.starts_with(folder) || canonical.starts_with(folder)) {
+            return Ok(());
+        }
+        let Some(root) = &allowed.root else {
+            return Err(format!("No vault is open to access {}", path.display()));
+        };
+        if path.starts_with(&root.path) || canonical.starts_with(&root.canonical) {
+            return Ok(());
+        }
+        Err(format!("Path is outside the open vault: {}", path.display()))
+    }
+}
+
+/// Check the path, or each path of the list, at `keys` in `value`
+fn check_value(sandbox: &Sandbox, value: &Value, keys: &[&str]) -> Result<(), String> {
+    match (keys, value) {
+        ([], Value::String(path)) => sandbox.check(path),
+        ([] | ["*", ..], Value::Array(items)) => {
+            let rest = keys.get(1..).unwrap_or_default();
+            items.iter().try_for_each(|item| check_value(sandbox, item, rest))
+        }
+        (["*", rest @ ..], Value::Object(map)) => {
+            map.values().try_for_each(|item| check_value(sandbox, item, rest))
+        }
+        ([key, rest @ ..], Value::Object(map)) => match map.get(*key) {
+            Some(value) => check_value(sandbox, value, rest),
+            None => Ok(()),
+        },
+        _ => Ok(()),
+    }
+}
+
+/// Check the path arguments of a command call against the sandbox
+pub fn check_invoke<R: Runtime>(invoke: &Invoke<R>) -> Result<(), String> {
+    let command = invoke.message.command();
+    if UNCHECKED_COMMANDS.contains(&command) {
+        return Ok(());
+    }
+    let InvokeBody::Json(args) = invoke.message.payload() else {
+        return Ok(());
+    };
+    let webview = invoke.message.webview();
+    let Some(sandbox) = webview.try_state::<Sandbox>() else {
+        return Ok(());
+    };
+    for arg in PATH_ARGS {
+        check_value(&sandbox, args, &[arg])?;
+    }
+    for (_, keys) in COMMAND_PATH_ARGS.iter().filter(|(name, _)| *name == command) {
+        check_value(&sandbox, args, &keys.split('/').collect::<Vec<_>>())?;
+    }
+    Ok(())
+}
+
+/// Allow files dropped on the window, which the frontend opens next
+pub fn on_window_event(window: &Window, event: &WindowEvent) {
+    if let WindowEvent::DragDrop(DragDropEvent::Drop { paths, .. }) = event {
+        let sandbox = window.state::<Sandbox>();
+        for path in paths.iter().filter(|path| path.is_file()) {
+            sandbox.allow_file(path);
+        }
+    }
+}
+
+/// Make `path` the vault commands are confined to, replacing the previous
+/// one. Only folders the user picked with `pick_vault_folder` or opened the
+/// app with can be opened this way.
+#[tauri::command]
+pub fn set_vault_root(path: String, sandbox: tauri::State<'_, Sandbox>) -> Result<String, String> {
+    let canonical = fs::canonicalize(clean(Path::new(&path))?).map_err(|e| format!("Failed to open vault: {}", e))?;
+    let picked = sandbox.allowed.lock().map_err(|e| format!("Lock error: {}", e))?.vaults.contains(&canonical);
+    if !picked {
+        return Err(format!("Open the folder from the dialog first: {}", path));
+    }
+    sandbox.open_root(&canonical)
+}
+
+/// Let the user pick a folder in the system dialog and open it as the vault.
+/// `None` if the dialog was cancelled.
+#[tauri::command(async)]
+pub fn pick_vault_folder(app: AppHandle, sandbox: tauri::State<'_, Sandbox>) -> Result<Option<String>, String> {
+    let Some(picked) = app.dialog().file().set_title("Open Folder").blocking_pick_folder() else {
+        return Ok(None);
+    };
+    let path = picked.into_path().map_err(|e| format!("Failed to open folder: {}", e))?;
+    sandbox.open_root(&path).map(Some)
+}
+
+/// Let the user pick a note in the system dialog, and allow it even if it is
+/// outside the vault. `None` if the dialog was cancelled.
+#[tauri::command(async)]
+pub fn pick_note_file(
+    app: AppHandle,
+    sandbox: tauri::State<'_, Sandbox>,
+    settings: tauri::State<'_, Mutex<SettingsStore>>,
+) -> Result<Option<String>, String> {
+    let note_extensions = settings::note_extensions(&settings)?;
+    let extensions: Vec<&str> = note_extensions.iter().map(String::as_str).collect();
+    let picked = app
+        .dialog()
+        .file()
+        .set_title("Open Markdown File")
+        .add_filter("Markdown", &extensions)
+        .blocking_pick_file();
+    let Some(picked) = picked else {
+        return Ok(None);
+    };
+    let path = picked.into_path().map_err(|e| format!("Failed to open file: {}", e))?;
+    sandbox.allow_file(&path);
+    Ok(Some(path.to_string_lossy().to_string()))
+}
+
+/// Let the user pick a file to open in the system dialog, e.g. to import, and
+/// allow it even if it is outside the vault. `extensions` (without the dot)
+/// limit what can be picked. `None` if the dialog was cancelled.
+#[tauri::command(async)]
+pub fn pick_file(
+    title: Option<String>,
+    extensions: Option<Vec<String>>,
+    app: AppHandle,
+    sandbox: tauri::State<'_, Sandbox>,
+) -> Result<Option<String>, String> {
+    let mut dialog = app.dialog().file().set_title(title.unwrap_or_else(|| "Open File".to_string()));
+    if let Some(extensions) = &extensions {
+        let extensions: Vec<&str> = extensions.iter().map(String::as_str).collect();
+        dialog = dialog.add_filter("Files", &extensions);
+    }
+    let Some(picked) = dialog.blocking_pick_file() else {
+        return Ok(None);
+    };
+    let path = picked.into_path().map_err(|e| format!("Failed to open file: {}", e))?;
+    sandbox.allow_file(&path);
+    Ok(Some(path.to_string_lossy().to_string()))
+}
+
+/// Let the user pick where to save a file in the system dialog, e.g. to
+/// export to, and allow it even if it is outside the vault. `None` if the
+/// dialog was cancelled.
+#[tauri::command(async)]
+pub fn pick_save_path(
+    title: Option<String>,
+    default_name: Option<String>,
+    extensions: Option<Vec<String>>,
+    app: AppHandle,
+    sandbox: tauri::State<'_, Sandbox>,
+) -> Result<Option<String>, String> {
+    let mut dialog = app.dialog().file().set_title(title.unwrap_or_else(|| "Save As".to_string()));
+    if let Some(name) = default_name {
+        dialog = dialog.set_file_name(name);
+    }
+    if let Some(extensions) = &extensions {
+        let extensions: Vec<&str> = extensions.iter().map(String::as_str).collect();
+        dialog = dialog.add_filter("Files", &extensions);
+    }
+    let Some(picked) = dialog.blocking_save_file() else {
+        return Ok(None);
+    };
+    let path = picked.into_path().map_err(|e| format!("Failed to save file: {}", e))?;
+    sandbox.allow_file(&path);
+    Ok(Some(path.to_string_lossy().to_string()))
+}
+
+/// Let the user pick a folder in the system dialog, e.g. to export a site
+/// to or import from, and allow everything in it even if it is outside the
+/// vault. `None` if the dialog was cancelled.
+#[tauri::command(async)]
+pub fn pick_folder(
+    title: Option<String>,
+    app: AppHandle,
+    sandbox: tauri::State<'_, Sandbox>,
+) -> Result<Option<String>, String> {
+    let title = title.unwrap_or_else(|| "Choose Folder".to_string());
+    let Some(picked) = app.dialog().file().set_title(title).blocking_pick_folder() else {
+        return Ok(None);
+    };
+    let path = picked.into_path().map_err(|e| format!("Failed to open folder: {}", e))?;
+    sandbox.allow_folder(&path);
+    Ok(Some(path.to_string_lossy().to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    /// A fresh folder holding `vault` and `outside`, removed when dropped
+    struct Scratch(PathBuf);
+
+    impl Scratch {
+        fn new(name: &str) -> Self {
+            let dir = std::env::temp_dir().join(format!("readmark-sandbox-{}-{}", name, std::process::id()));
+            let _ = fs::remove_dir_all(&dir);
+            fs::create_dir_all(dir.join("vault/notes")).unwrap();
+            fs::create_dir_all(dir.join("outside")).unwrap();
+            Scratch(fs::canonicalize(dir).unwrap())
+        }
+
+        fn path(&self, relative: &str) -> String {
+            self.0.join(relative).to_string_lossy().to_string()
+        }
+
+        fn sandbox(&self) -> Sandbox {
+            let sandbox = Sandbox::default();
+            sandbox.open_root(&self.0.join("vault")).unwrap();
+            sandbox
+        }
+    }
+
+    impl Drop for Scratch {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[test]
+    fn allows_paths_inside_the_vault() {
+        let scratch = Scratch::new("inside");
+        let sandbox = scratch.sandbox();
+        assert!(sandbox.check(&scratch.path("vault")).is_ok());
+        assert!(sandbox.check(&scratch.path("vault/notes/a.md")).is_ok());
+        assert!(sandbox.check(&scratch.path("vault/./notes/new/b.md")).is_ok());
+        assert!(sandbox.check(&scratch.path("outside/a.md")).is_err());
+        // Path prefixes go by whole components
+        assert!(sandbox.check(&scratch.path("vault-2/a.md")).is_err());
+    }
+
+    #[test]
+    fn rejects_relative_paths_and_traversal() {
+        let scratch = Scratch::new("traversal");
+        let sandbox = scratch.sandbox();
+        assert!(sandbox.check("notes/a.md").is_err());
+        assert!(sandbox.check("../outside/a.md").is_err());
+        let error = sandbox.check(&scratch.path("vault/../outside/a.md")).unwrap_err();
+        assert!(error.contains(".."), "{}", error);
+        assert!(sandbox.check(&scratch.path("vault/notes/../../outside")).is_err());
+    }
+
+    #[test]
+    fn rejects_everything_without_a_vault() {
+        let scratch = Scratch::new("no-vault");
+        let error = Sandbox::default().check(&scratch.path("vault/a.md")).unwrap_err();
+        assert!(error.contains("No vault is open"), "{}", error);
+    }
+
+    #[test]
+    fn refuses_too_broad_vaults() {
+        assert!(Sandbox::default().open_root(Path::new("/")).is_err());
+        if let Some(home) = std::env::var_os("HOME") {
+            assert!(Sandbox::default().open_root(Path::new(&home)).is_err());
+        }
+    }
+
+    #[test]
+    fn allows_picked_files_and_folders() {
+        let scratch = Scratch::new("picked");
+        let sandbox = scratch.sandbox();
+        sandbox.allow_file(&scratch.0.join("outside/export.pdf"));
+        assert!(sandbox.check(&scratch.path("outside/export.pdf")).is_ok());
+        assert!(sandbox.check(&scratch.path("outside/other.pdf")).is_err());
+
+        sandbox.allow_folder(&scratch.0.join("outside"));
+        assert!(sandbox.check(&scratch.path("outside/site/index.html")).is_ok());
+        assert!(sandbox.check(&scratch.path("outside/../elsewhere")).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn follows_symlinks() {
+        use std::os::unix::fs::symlink;
+
+        let scratch = Scratch::new("symlinks");
+        let sandbox = scratch.sandbox();
+        // Into the vault from outside: where it leads counts
+        symlink(scratch.0.join("vault/notes"), scratch.0.join("outside/to-notes")).unwrap();
+        assert!(sandbox.check(&scratch.path("outside/to-notes/a.md")).is_ok());
+        // Out of the vault from outside: still outside
+        symlink(scratch.0.join("outside"), scratch.0.join("escape")).unwrap();
+        assert!(sandbox.check(&scratch.path("escape/a.md")).is_err());
+        // A symlink in the vault is followed, as the file tree shows it
+        symlink(scratch.0.join("outside"), scratch.0.join("vault/linked")).unwrap();
+        assert!(sandbox.check(&scratch.path("vault/linked/a.md")).is_ok());
+    }
+
+    #[test]
+    fn checks_nested_path_arguments() {
+        let scratch = Scratch::new("nested");
+        let sandbox = scratch.sandbox();
+        let (inside, outside) = (scratch.path("vault/a.md"), scratch.path("outside/a.md"));
+
+        let args = json!({ "path": inside, "paths": [inside, outside] });
+        assert!(check_value(&sandbox, &args, &["path"]).is_ok());
+        assert!(check_value(&sandbox, &args, &["paths"]).is_err());
+        assert!(check_value(&sandbox, &args, &["missing"]).is_ok());
+
+        let args = json!({ "resolved": [{ "path": inside }, { "path": outside }] });
+        assert!(check_value(&sandbox, &args, &["resolved", "*", "path"]).is_err());
+
+        let vaults = json!({ "/a": { "backup": { "dir": null } }, "/b": { "backup": { "dir": outside } } });
+        let args = json!({ "patch": { "vaults": vaults } });
+        assert!(check_value(&sandbox, &args, &["patch", "vaults", "*", "backup", "dir"]).is_err());
+        let args = json!({ "patch": { "vaults": { "/a": { "backup": { "dir": inside } } } } });
+        assert!(check_value(&sandbox, &args, &["patch", "vaults", "*", "backup", "dir"]).is_ok());
+    }
+}
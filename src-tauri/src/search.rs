@@ -0,0 +1,553 @@
+use nucleo_matcher::pattern::{CaseMatching, Normalization, Pattern};
+use nucleo_matcher::{Config, Matcher, Utf32Str};
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tauri::AppHandle;
+
+use crate::error::CommandError;
+use crate::jobs::{self, Job};
+use crate::settings::{self, SettingsStore};
+
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct RegexOptions {
+    pub case_insensitive: bool,
+    /// `^` and `$` match at line boundaries (default true)
+    pub multi_line: bool,
+    /// `.` also matches newlines
+    pub dot_matches_newline: bool,
+    /// Stop after this many matches across the vault
+    pub max_results: usize,
+    pub ignore_globs: Vec<String>,
+}
+
+impl Default for RegexOptions {
+    fn default() -> Self {
+        RegexOptions {
+            case_insensitive: false,
+            multi_line: true,
+            dot_matches_newline: false,
+            max_results: 1000,
+            ignore_globs: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct CaptureRange {
+    /// Group name for named groups (`(?P<name>...)`)
+    pub name: Option<String>,
+    pub start: usize,
+    pub end: usize,
+    pub text: String,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct RegexMatch {
+    /// 1-based line number of the match start
+    pub line: usize,
+    /// Byte offsets into the file
+    pub start: usize,
+    pub end: usize,
+    pub text: String,
+    /// The full line the match starts on
+    pub line_text: String,
+    /// Capture groups 1.., `None` where a group did not participate
+    pub captures: Vec<Option<CaptureRange>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FileMatches {
+    pub path: String,
+    pub matches: Vec<RegexMatch>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RegexSearchResult {
+    pub files: Vec<FileMatches>,
+    pub total_matches: usize,
+    /// True when `max_results` cut the search short
+    pub truncated: bool,
+}
+
+pub fn build_regex(pattern: &str, options: &RegexOptions) -> Result<Regex, String> {
+    RegexBuilder::new(pattern)
+        .case_insensitive(options.case_insensitive)
+        .multi_line(options.multi_line)
+        .dot_matches_new_line(options.dot_matches_newline)
+        .build()
+        .map_err(|e| format!("Invalid regex: {}", e))
+}
+
+/// Byte offset of the start of every line in `text`
+pub fn line_starts(text: &str) -> Vec<usize> {
+    std::iter::once(0)
+        .chain(text.match_indices('\n').map(|(i, _)| i + 1))
+        .collect()
+}
+
+/// 1-based line number containing byte `offset`
+pub fn line_of(starts: &[usize], offset: usize) -> usize {
+    match starts.binary_search(&offset) {
+        Ok(index) => index + 1,
+        Err(index) => index,
+    }
+}
+
+/// All matches of `regex` in `text`, at most `limit`
+pub fn find_matches(regex: &Regex, text: &str, limit: usize) -> Vec<RegexMatch> {
+    let starts = line_starts(text);
+    let names: Vec<Option<String>> = regex
+        .capture_names()
+        .map(|n| n.map(str::to_string))
+        .collect();
+
+    regex
+        .captures_iter(text)
+        .take(limit)
+        .map(|caps| {
+            let whole = caps.get(0).expect("group 0 always participates");
+            let line = line_of(&starts, whole.start());
+            let line_start = starts[line - 1];
+            let line_end = text[line_start..]
+                .find('\n')
+                .map(|i| line_start + i)
+                .unwrap_or(text.len());
+            let captures = (1..caps.len())
+                .map(|i| {
+                    caps.get(i).map(|m| CaptureRange {
+                        name: names.get(i).cloned().flatten(),
+                        start: m.start(),
+                        end: m.end(),
+                        text: m.as_str().to_string(),
+                    })
+                })
+                .collect();
+            RegexMatch {
+                line,
+                start: whole.start(),
+                end: whole.end(),
+                text: whole.as_str().to_string(),
+                line_text: text[line_start..line_end].trim_end_matches('\r').to_string(),
+                captures,
+            }
+        })
+        .collect()
+}
+
+/// Every note under `root`, honoring ignore rules, sorted by path
+pub fn note_paths(root: &Path, ignore_globs: &[String], note_extensions: &[String]) -> Result<Vec<PathBuf>, String> {
+    let walker = crate::ignore_rules::walker(root, ignore_globs)?;
+    let mut paths: Vec<PathBuf> = walker
+        .build()
+        .filter_map(|e| e.ok())
+        .map(|e| e.into_path())
+        .filter(|p| p.is_file() && settings::has_note_extension(p, note_extensions))
+        .collect();
+    paths.sort();
+    Ok(paths)
+}
+
+/// Search every note under `root` with a regular expression
+#[tauri::command(async)]
+pub fn search_regex(
+    root: String,
+    pattern: String,
+    options: Option<RegexOptions>,
+    settings: tauri::State<'_, Mutex<SettingsStore>>,
+) -> Result<RegexSearchResult, String> {
+    let options = options.unwrap_or_default();
+    let note_extensions = settings::note_extensions(&settings)?;
+    let ignore_globs = settings::ignore_globs(&settings, &options.ignore_globs)?;
+    regex_search(Path::new(&root), &pattern, &options, &note_extensions, &ignore_globs, &Job::detached())
+}
+
+/// `search_regex` as a job, reporting the notes searched. The matches are
+/// the result of its `job-done` event.
+#[tauri::command]
+pub fn start_search_regex(
+    root: String,
+    pattern: String,
+    options: Option<RegexOptions>,
+    app: AppHandle,
+    settings: tauri::State<'_, Mutex<SettingsStore>>,
+) -> Result<u64, String> {
+    let options = options.unwrap_or_default();
+    let note_extensions = settings::note_extensions(&settings)?;
+    let ignore_globs = settings::ignore_globs(&settings, &options.ignore_globs)?;
+    // Fail before starting a job if the pattern is invalid
+    build_regex(&pattern, &options)?;
+    jobs::spawn(&app, "search", move |job| {
+        regex_search(Path::new(&root), &pattern, &options, &note_extensions, &ignore_globs, job)
+    })
+}
+
+fn regex_search(
+    root: &Path,
+    pattern: &str,
+    options: &RegexOptions,
+    note_extensions: &[String],
+    ignore_globs: &[String],
+    job: &Job,
+) -> Result<RegexSearchResult, String> {
+    crate::ensure_dir(root)?;
+    let regex = build_regex(pattern, options)?;
+
+    let mut result = RegexSearchResult {
+        files: Vec::new(),
+        total_matches: 0,
+        truncated: false,
+    };
+
+    let paths = note_paths(root, ignore_globs, note_extensions)?;
+    for (searched, path) in paths.iter().enumerate() {
+        job.check()?;
+        job.progress(searched, Some(paths.len()), Some(&path.to_string_lossy()));
+        if result.truncated {
+            break;
+        }
+        let remaining = options.max_results.saturating_sub(result.total_matches);
+
+        // Binary or non-UTF-8 files are skipped rather than failing the search
+        let Ok(text) = fs::read_to_string(path) else {
+            continue;
+        };
+
+        // Ask for one extra match to know whether the limit cut anything off
+        let mut matches = find_matches(&regex, &text, remaining + 1);
+        if matches.len() > remaining {
+            matches.truncate(remaining);
+            result.truncated = true;
+        }
+        if matches.is_empty() {
+            continue;
+        }
+        result.total_matches += matches.len();
+        result.files.push(FileMatches {
+            path: path.to_string_lossy().to_string(),
+            matches,
+        });
+    }
+    job.progress(paths.len(), Some(paths.len()), None);
+
+    Ok(result)
+}
+
+#[derive(Debug, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct ReplaceOptions {
+    /// Treat the pattern as a regular expression; `$1`/`${name}` then work in
+    /// the replacement. Otherwise both are literal text.
+    pub regex: bool,
+    pub case_insensitive: bool,
+    pub ignore_globs: Vec<String>,
+    /// Write the changes. Without it only a preview is returned.
+    pub apply: bool,
+    /// Restrict the operation to these files (e.g. the ones left checked in the preview)
+    pub only_files: Option<Vec<String>>,
+    /// Content hashes from the preview; files that changed since are refused
+    pub expected_hashes: Option<HashMap<String, String>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ReplacePreviewLine {
+    pub line: usize,
+    pub before: String,
+    pub after: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ReplaceFileResult {
+    pub path: String,
+    /// Hash of the file content the preview was computed from
+    pub hash: String,
+    pub replacements: usize,
+    pub lines: Vec<ReplacePreviewLine>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ReplaceResult {
+    pub files: Vec<ReplaceFileResult>,
+    pub total_replacements: usize,
+    pub applied: bool,
+}
+
+struct PendingReplacement {
+    path: PathBuf,
+    original: String,
+    replaced: String,
+}
+
+/// Write every replacement or none of them.
+///
+/// All new contents are first staged as temp files next to their targets;
+/// only once every file is staged are they renamed into place. If a rename
+/// fails, the files already replaced are restored from their original content,
+/// and the error names any that couldn't be.
+fn apply_all_or_nothing(pending: &[PendingReplacement]) -> Result<(), String> {
+    let mut staged = Vec::with_capacity(pending.len());
+    for item in pending {
+        let parent = item.path.parent().unwrap_or(Path::new("."));
+        let mut temp = tempfile::Builder::new()
+            .prefix(".readmark-")
+            .suffix(".tmp")
+            .tempfile_in(parent)
+            .map_err(|e| format!("Failed to stage {}: {}", item.path.display(), e))?;
+        temp.write_all(item.replaced.as_bytes())
+            .and_then(|_| temp.as_file().sync_all())
+            .map_err(|e| format!("Failed to stage {}: {}", item.path.display(), e))?;
+        if let Ok(metadata) = fs::metadata(&item.path) {
+            let _ = temp.as_file().set_permissions(metadata.permissions());
+        }
+        staged.push(temp);
+    }
+
+    for (done, (temp, item)) in staged.into_iter().zip(pending).enumerate() {
+        if let Err(e) = temp.persist(&item.path) {
+            let mut unrestored = Vec::new();
+            for restored in &pending[..done] {
+                if let Err(restore_error) = crate::write_atomic(&restored.path, restored.original.as_bytes()) {
+                    eprintln!("Failed to restore {}: {}", restored.path.display(), restore_error);
+                    unrestored.push(restored.path.display().to_string());
+                }
+            }
+            let outcome = match unrestored.is_empty() {
+                true => "no files were changed".to_string(),
+                false => format!("these files were replaced and could not be restored: {}", unrestored.join(", ")),
+            };
+            return Err(format!("Failed to write {}: {} ({})", item.path.display(), e.error, outcome));
+        }
+    }
+
+    Ok(())
+}
+
+/// Find and replace across every note in a vault.
+///
+/// Returns a preview of affected lines; with `options.apply` the changes are
+/// also written, transactionally across all files.
+#[tauri::command(async)]
+pub fn replace_in_files(
+    root: String,
+    pattern: String,
+    replacement: String,
+    options: Option<ReplaceOptions>,
+    settings: tauri::State<'_, Mutex<SettingsStore>>,
+) -> Result<ReplaceResult, CommandError> {
+    let options = options.unwrap_or_default();
+    let root = PathBuf::from(&root);
+    crate::ensure_dir(&root)?;
+    if options.apply {
+        settings::check_writable(&settings, &root)?;
+    }
+
+    if pattern.is_empty() {
+        return Err("Search pattern is empty".to_string().into());
+    }
+    let source = if options.regex { pattern } else { regex::escape(&pattern) };
+    let regex = RegexBuilder::new(&source)
+        .case_insensitive(options.case_insensitive)
+        .multi_line(true)
+        .build()
+        .map_err(|e| format!("Invalid regex: {}", e))?;
+    let replace_with = |text: &str| -> String {
+        if options.regex {
+            regex.replace_all(text, replacement.as_str()).into_owned()
+        } else {
+            regex.replace_all(text, regex::NoExpand(&replacement)).into_owned()
+        }
+    };
+
+    let note_extensions = settings::note_extensions(&settings)?;
+    let mut files = Vec::new();
+    let mut pending = Vec::new();
+
+    let ignore_globs = settings::ignore_globs(&settings, &options.ignore_globs)?;
+    for path in note_paths(&root, &ignore_globs, &note_extensions)? {
+        let path_str = path.to_string_lossy().to_string();
+        if let Some(only) = &options.only_files {
+            if !only.contains(&path_str) {
+                continue;
+            }
+        }
+
+        let Ok(original) = fs::read_to_string(&path) else {
+            continue;
+        };
+        let replacements = regex.find_iter(&original).count();
+        if replacements == 0 {
+            continue;
+        }
+
+        let hash = crate::content_hash(original.as_bytes());
+        if let Some(expected) = options.expected_hashes.as_ref().and_then(|h| h.get(&path_str)) {
+            if *expected != hash {
+                return Err(format!("File changed since preview: {}", path_str).into());
+            }
+        }
+
+        // Preview line by line; a match spanning lines shows on its first line
+        let starts = line_starts(&original);
+        let mut lines: Vec<ReplacePreviewLine> = Vec::new();
+        for m in regex.find_iter(&original) {
+            let line = line_of(&starts, m.start());
+            if lines.last().is_some_and(|l| l.line == line) {
+                continue;
+            }
+            let line_end = starts.get(line).map(|next| next - 1).unwrap_or(original.len());
+            let before = original[starts[line - 1]..line_end].trim_end_matches('\r').to_string();
+            let after = replace_with(&before);
+            lines.push(ReplacePreviewLine { line, before, after });
+        }
+
+        let replaced = replace_with(&original);
+        files.push(ReplaceFileResult {
+            path: path_str,
+            hash,
+            replacements,
+            lines,
+        });
+        pending.push(PendingReplacement { path, original, replaced });
+    }
+
+    if options.apply {
+        apply_all_or_nothing(&pending)?;
+    }
+
+    Ok(ReplaceResult {
+        total_replacements: files.iter().map(|f| f.replacements).sum(),
+        files,
+        applied: options.apply,
+    })
+}
+
+/// Per-vault list of note paths (relative to the vault root) for the quick
+/// switcher. Built on first use and dropped whenever the watcher reports a
+/// file that was added or removed, or the note extensions or ignore globs
+/// change.
+#[derive(Default)]
+pub struct NotePathCache {
+    lists: Mutex<HashMap<PathBuf, NoteList>>,
+}
+
+/// The notes of a vault, relative to its root
+struct NoteList {
+    /// The note extensions the list was built with
+    note_extensions: Vec<String>,
+    /// The ignore globs the list was built with
+    ignore_globs: Vec<String>,
+    paths: Arc<Vec<String>>,
+}
+
+impl NotePathCache {
+    fn get_or_build(
+        &self,
+        root: &Path,
+        ignore_globs: &[String],
+        note_extensions: &[String],
+    ) -> Result<Arc<Vec<String>>, String> {
+        let lists = self.lists.lock().map_err(|e| format!("Lock error: {}", e))?;
+        // A list built before the settings changed is rebuilt
+        let current = |list: &&NoteList| list.note_extensions == note_extensions && list.ignore_globs == ignore_globs;
+        if let Some(list) = lists.get(root).filter(current) {
+            return Ok(Arc::clone(&list.paths));
+        }
+        drop(lists);
+
+        let list: Vec<String> = note_paths(root, ignore_globs, note_extensions)?
+            .iter()
+            .filter_map(|p| p.strip_prefix(root).ok())
+            .map(|p| p.to_string_lossy().to_string())
+            .collect();
+        let list = Arc::new(list);
+        self.lists
+            .lock()
+            .map_err(|e| format!("Lock error: {}", e))?
+            .insert(
+                root.to_path_buf(),
+                NoteList {
+                    note_extensions: note_extensions.to_vec(),
+                    ignore_globs: ignore_globs.to_vec(),
+                    paths: Arc::clone(&list),
+                },
+            );
+        Ok(list)
+    }
+
+    /// Forget cached lists affected by a change at `path`. Edits to files that
+    /// are already listed don't change the list and keep the cache.
+    pub fn invalidate(&self, path: &Path) {
+        let Ok(mut lists) = self.lists.lock() else {
+            return;
+        };
+        lists.retain(|root, list| {
+            let Ok(relative) = path.strip_prefix(root) else {
+                return true;
+            };
+            let relative = relative.to_string_lossy();
+            path.is_file() && list.paths.iter().any(|p| *p == relative)
+        });
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct FuzzyMatch {
+    pub path: String,
+    pub relative_path: String,
+    pub score: u32,
+    /// Character (not byte) positions in `relative_path` that matched, sorted
+    pub indices: Vec<u32>,
+}
+
+/// Fuzzy-match note paths in a vault for a quick switcher, best first
+#[tauri::command(async)]
+pub fn fuzzy_find_notes(
+    root: String,
+    query: String,
+    limit: Option<usize>,
+    cache: tauri::State<'_, NotePathCache>,
+    settings: tauri::State<'_, Mutex<SettingsStore>>,
+) -> Result<Vec<FuzzyMatch>, String> {
+    let root_path = PathBuf::from(&root);
+    crate::ensure_dir(&root_path)?;
+
+    let note_extensions = settings::note_extensions(&settings)?;
+    let ignore_globs = settings::ignore_globs(&settings, &[])?;
+    let list = cache.get_or_build(&root_path, &ignore_globs, &note_extensions)?;
+    let limit = limit.unwrap_or(50);
+
+    let mut matcher = Matcher::new(Config::DEFAULT.match_paths());
+    let pattern = Pattern::parse(&query, CaseMatching::Smart, Normalization::Smart);
+    let mut buf = Vec::new();
+
+    let mut results: Vec<FuzzyMatch> = list
+        .iter()
+        .filter_map(|relative| {
+            let mut indices = Vec::new();
+            let score = pattern.indices(Utf32Str::new(relative, &mut buf), &mut matcher, &mut indices)?;
+            indices.sort_unstable();
+            indices.dedup();
+            Some(FuzzyMatch {
+                path: root_path.join(relative).to_string_lossy().to_string(),
+                relative_path: relative.clone(),
+                score,
+                indices,
+            })
+        })
+        .collect();
+
+    // Best score first; shorter paths win ties
+    results.sort_by(|a, b| {
+        b.score
+            .cmp(&a.score)
+            .then_with(|| a.relative_path.len().cmp(&b.relative_path.len()))
+            .then_with(|| a.relative_path.cmp(&b.relative_path))
+    });
+    results.truncate(limit);
+    Ok(results)
+}
@@ -0,0 +1,489 @@
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf, MAIN_SEPARATOR};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::AppHandle;
+
+use crate::jobs::{self, Job};
+use crate::settings::{self, SettingsStore};
+use crate::vault_index;
+
+/// Bump when the schema changes; older databases are dropped and rebuilt
+const SCHEMA_VERSION: i32 = 4;
+
+/// Number of files indexed per transaction during a full scan. Keeps the
+/// connection lock short so queries stay responsive while building.
+const BATCH_SIZE: usize = 200;
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum IndexState {
+    Building,
+    Ready,
+    Error,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct IndexStatus {
+    pub root: String,
+    pub state: IndexState,
+    pub indexed_files: usize,
+    /// Milliseconds since the Unix epoch of the last completed scan or update
+    pub last_updated: Option<u64>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SearchHit {
+    pub path: String,
+    pub title: String,
+    /// Excerpt around the match, with hits wrapped in `<mark>` tags
+    pub snippet: String,
+    /// Relevance, higher is better
+    pub score: f64,
+}
+
+/// Full-text index of one vault, stored as an SQLite FTS5 database
+pub struct VaultIndex {
+    root: PathBuf,
+    note_extensions: Vec<String>,
+    conn: Mutex<Connection>,
+    status: Mutex<IndexStatus>,
+    building: AtomicBool,
+}
+
+/// Open indexes keyed by vault root. Databases live in the app cache
+/// directory so they never end up in synced vault folders.
+pub struct IndexRegistry {
+    cache_dir: PathBuf,
+    indexes: Mutex<HashMap<PathBuf, Arc<VaultIndex>>>,
+}
+
+fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+fn init_schema(conn: &Connection) -> rusqlite::Result<()> {
+    let version: i32 = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+    if version != SCHEMA_VERSION {
+        conn.execute_batch(
+            "DROP TABLE IF EXISTS notes;
+             DROP TABLE IF EXISTS files;",
+        )?;
+        for table in vault_index::TABLES {
+            conn.execute_batch(&format!("DROP TABLE IF EXISTS {};", table))?;
+        }
+    }
+    conn.execute_batch(&format!(
+        "PRAGMA journal_mode = WAL;
+         CREATE TABLE IF NOT EXISTS files (
+             id INTEGER PRIMARY KEY,
+             path TEXT NOT NULL UNIQUE,
+             mtime INTEGER NOT NULL,
+             size INTEGER NOT NULL
+         );
+         CREATE VIRTUAL TABLE IF NOT EXISTS notes USING fts5(title, body);
+         PRAGMA user_version = {};",
+        SCHEMA_VERSION
+    ))?;
+    vault_index::init_schema(conn)
+}
+
+/// Title of a note: its first level-one heading, or the file stem
+fn note_title(path: &Path, body: &str) -> String {
+    body.lines()
+        .find_map(|line| line.strip_prefix("# "))
+        .map(|title| title.trim().to_string())
+        .unwrap_or_else(|| {
+            path.file_stem()
+                .map(|s| s.to_string_lossy().to_string())
+                .unwrap_or_default()
+        })
+}
+
+/// Turn free text into an FTS5 query: every word must match, the last one as a prefix
+fn fts_query(input: &str) -> Option<String> {
+    let terms: Vec<String> = input
+        .split_whitespace()
+        .map(|term| format!("\"{}\"", term.replace('"', "\"\"")))
+        .collect();
+    if terms.is_empty() {
+        return None;
+    }
+    Some(format!("{}*", terms.join(" ")))
+}
+
+fn index_file(conn: &Connection, path: &Path, metadata: &fs::Metadata) -> rusqlite::Result<()> {
+    let bytes = match fs::read(path) {
+        Ok(bytes) => bytes,
+        Err(_) => return remove_path(conn, path),
+    };
+    let body = String::from_utf8_lossy(&bytes);
+    let path_str = path.to_string_lossy();
+    let mtime = crate::mtime_millis(metadata).unwrap_or(0) as i64;
+    let size = metadata.len() as i64;
+
+    let existing: Option<i64> = conn
+        .query_row("SELECT id FROM files WHERE path = ?1", [&path_str], |row| row.get(0))
+        .optional()?;
+    let id = match existing {
+        Some(id) => {
+            conn.execute("UPDATE files SET mtime = ?2, size = ?3 WHERE id = ?1", params![id, mtime, size])?;
+            conn.execute("DELETE FROM notes WHERE rowid = ?1", [id])?;
+            id
+        }
+        None => {
+            conn.execute(
+                "INSERT INTO files (path, mtime, size) VALUES (?1, ?2, ?3)",
+                params![path_str, mtime, size],
+            )?;
+            conn.last_insert_rowid()
+        }
+    };
+    let title = note_title(path, &body);
+    conn.execute(
+        "INSERT INTO notes (rowid, title, body) VALUES (?1, ?2, ?3)",
+        params![id, title, body],
+    )?;
+    vault_index::index_note(conn, id, path, &title, &body, crate::ctime_millis(metadata))
+}
+
+/// Remove a file, or everything below a directory, from the index
+fn remove_path(conn: &Connection, path: &Path) -> rusqlite::Result<()> {
+    let path_str = path.to_string_lossy().to_string();
+    let prefix = format!("{}{}", path_str.trim_end_matches(MAIN_SEPARATOR), MAIN_SEPARATOR);
+    let matches = "path = ?1 OR substr(path, 1, length(?2)) = ?2";
+    vault_index::remove(conn, &format!("SELECT id FROM files WHERE {}", matches), &[&path_str, &prefix])?;
+    conn.execute(
+        &format!("DELETE FROM notes WHERE rowid IN (SELECT id FROM files WHERE {})", matches),
+        params![path_str, prefix],
+    )?;
+    conn.execute(&format!("DELETE FROM files WHERE {}", matches), params![path_str, prefix])?;
+    Ok(())
+}
+
+impl VaultIndex {
+    pub fn lock_conn(&self) -> Result<std::sync::MutexGuard<'_, Connection>, String> {
+        self.conn.lock().map_err(|e| format!("Lock error: {}", e))
+    }
+
+    fn set_status(&self, state: IndexState, error: Option<String>) {
+        let count = self
+            .lock_conn()
+            .ok()
+            .and_then(|conn| conn.query_row("SELECT COUNT(*) FROM files", [], |row| row.get::<_, i64>(0)).ok())
+            .unwrap_or(0) as usize;
+        if let Ok(mut status) = self.status.lock() {
+            status.state = state;
+            status.indexed_files = count;
+            status.error = error;
+            if state != IndexState::Building {
+                status.last_updated = Some(now_millis());
+            }
+        }
+    }
+
+    pub fn status(&self) -> IndexStatus {
+        self.status
+            .lock()
+            .map(|status| status.clone())
+            .unwrap_or_else(|e| e.into_inner().clone())
+    }
+
+    fn is_note(&self, path: &Path) -> bool {
+        path.is_file() && settings::has_note_extension(path, &self.note_extensions)
+    }
+
+    /// Bring the index in line with the files on disk, reindexing only files
+    /// whose mtime or size changed. With `from_scratch`, everything is dropped first.
+    fn sync(&self, from_scratch: bool, job: &Job) -> Result<(), String> {
+        if from_scratch {
+            let conn = self.lock_conn()?;
+            vault_index::clear(&conn)
+                .and_then(|_| conn.execute_batch("DELETE FROM notes; DELETE FROM files;"))
+                .map_err(|e| format!("Failed to clear index: {}", e))?;
+        }
+
+        let known: HashMap<String, (i64, i64)> = {
+            let conn = self.lock_conn()?;
+            let mut stmt = conn
+                .prepare("SELECT path, mtime, size FROM files")
+                .map_err(|e| e.to_string())?;
+            let rows = stmt
+                .query_map([], |row| Ok((row.get(0)?, (row.get(1)?, row.get(2)?))))
+                .map_err(|e| e.to_string())?;
+            rows.filter_map(|r| r.ok()).collect()
+        };
+
+        let walker = crate::ignore_rules::walker(&self.root, &[])?;
+        let mut seen: HashSet<String> = HashSet::new();
+        let mut pending: Vec<(PathBuf, fs::Metadata)> = Vec::new();
+
+        for (scanned, entry) in walker.build().filter_map(|e| e.ok()).enumerate() {
+            job.check()?;
+            let path = entry.path();
+            job.progress(scanned, None, Some(&path.to_string_lossy()));
+            if !self.is_note(path) {
+                continue;
+            }
+            let Ok(metadata) = fs::metadata(path) else {
+                continue;
+            };
+            let path_str = path.to_string_lossy().to_string();
+            let current = (crate::mtime_millis(&metadata).unwrap_or(0) as i64, metadata.len() as i64);
+            if known.get(&path_str) != Some(&current) {
+                pending.push((path.to_path_buf(), metadata));
+            }
+            seen.insert(path_str);
+
+            if pending.len() >= BATCH_SIZE {
+                self.index_batch(&mut pending)?;
+            }
+        }
+        self.index_batch(&mut pending)?;
+
+        let conn = self.lock_conn()?;
+        for path in known.keys().filter(|p| !seen.contains(*p)) {
+            remove_path(&conn, Path::new(path)).map_err(|e| e.to_string())?;
+        }
+        Ok(())
+    }
+
+    fn index_batch(&self, pending: &mut Vec<(PathBuf, fs::Metadata)>) -> Result<(), String> {
+        if pending.is_empty() {
+            return Ok(());
+        }
+        let mut conn = self.lock_conn()?;
+        let tx = conn.transaction().map_err(|e| e.to_string())?;
+        for (path, metadata) in pending.drain(..) {
+            index_file(&tx, &path, &metadata).map_err(|e| format!("Failed to index {}: {}", path.display(), e))?;
+        }
+        tx.commit().map_err(|e| e.to_string())
+    }
+
+    /// Run a scan, keeping the status up to date. Fails if one is already
+    /// running.
+    fn run_sync(&self, from_scratch: bool, job: &Job) -> Result<(), String> {
+        if self.building.swap(true, Ordering::SeqCst) {
+            return Err(format!("{} is already being indexed", self.root.display()));
+        }
+        self.set_status(IndexState::Building, None);
+        let result = self.sync(from_scratch, job);
+        match &result {
+            Ok(()) => self.set_status(IndexState::Ready, None),
+            Err(e) => self.set_status(IndexState::Error, Some(e.clone())),
+        }
+        self.building.store(false, Ordering::SeqCst);
+        result
+    }
+
+    /// Run a scan on a background thread, unless one is already running
+    fn spawn_sync(self: &Arc<Self>, from_scratch: bool) {
+        if self.building.load(Ordering::SeqCst) {
+            return;
+        }
+        let index = Arc::clone(self);
+        thread::spawn(move || {
+            let _ = index.run_sync(from_scratch, &Job::detached());
+        });
+    }
+
+    /// Apply a watcher event for `path`: reindex notes that exist, drop ones that don't
+    pub fn update_path(&self, path: &Path) {
+        let result = (|| -> Result<(), String> {
+            if path.is_dir() {
+                // A directory appeared (e.g. moved into the vault); index its notes
+                let walker = crate::ignore_rules::walker(path, &[])?;
+                let mut pending: Vec<(PathBuf, fs::Metadata)> = walker
+                    .build()
+                    .filter_map(|e| e.ok())
+                    .filter(|e| self.is_note(e.path()))
+                    .filter_map(|e| fs::metadata(e.path()).ok().map(|m| (e.path().to_path_buf(), m)))
+                    .collect();
+                return self.index_batch(&mut pending);
+            }
+
+            let conn = self.lock_conn()?;
+            match fs::metadata(path) {
+                Ok(metadata) if self.is_note(path) => index_file(&conn, path, &metadata),
+                _ => remove_path(&conn, path),
+            }
+            .map_err(|e| e.to_string())
+        })();
+
+        if let Err(e) = result {
+            eprintln!("Index update error for {}: {}", path.display(), e);
+        } else if !self.building.load(Ordering::SeqCst) {
+            self.set_status(IndexState::Ready, None);
+        }
+    }
+
+    pub fn query(&self, query: &str, limit: usize) -> Result<Vec<SearchHit>, String> {
+        let Some(fts) = fts_query(query) else {
+            return Ok(Vec::new());
+        };
+        let conn = self.lock_conn()?;
+        let mut stmt = conn
+            .prepare(
+                "SELECT files.path, notes.title,
+                        snippet(notes, -1, '<mark>', '</mark>', '…', 16),
+                        bm25(notes, 5.0, 1.0)
+                 FROM notes JOIN files ON files.id = notes.rowid
+                 WHERE notes MATCH ?1
+                 ORDER BY bm25(notes, 5.0, 1.0)
+                 LIMIT ?2",
+            )
+            .map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map(params![fts, limit as i64], |row| {
+                Ok(SearchHit {
+                    path: row.get(0)?,
+                    title: row.get(1)?,
+                    snippet: row.get(2)?,
+                    score: -row.get::<_, f64>(3)?,
+                })
+            })
+            .map_err(|e| format!("Search failed: {}", e))?;
+        rows.collect::<Result<_, _>>().map_err(|e| format!("Search failed: {}", e))
+    }
+}
+
+impl IndexRegistry {
+    pub fn new(cache_dir: PathBuf) -> Self {
+        IndexRegistry {
+            cache_dir,
+            indexes: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Get the index for `root`, opening it and starting an incremental scan
+    /// the first time the vault is seen in this session
+    pub fn open(&self, root: &Path, note_extensions: Vec<String>) -> Result<Arc<VaultIndex>, String> {
+        let mut indexes = self.indexes.lock().map_err(|e| format!("Lock error: {}", e))?;
+        if let Some(index) = indexes.get(root) {
+            return Ok(Arc::clone(index));
+        }
+
+        let index = self.create(root, note_extensions)?;
+        index.spawn_sync(false);
+        indexes.insert(root.to_path_buf(), Arc::clone(&index));
+        Ok(index)
+    }
+
+    /// The index for `root`, opened without starting a scan if it isn't yet
+    fn get_or_create(&self, root: &Path, note_extensions: Vec<String>) -> Result<Arc<VaultIndex>, String> {
+        let mut indexes = self.indexes.lock().map_err(|e| format!("Lock error: {}", e))?;
+        if let Some(index) = indexes.get(root) {
+            return Ok(Arc::clone(index));
+        }
+        let index = self.create(root, note_extensions)?;
+        indexes.insert(root.to_path_buf(), Arc::clone(&index));
+        Ok(index)
+    }
+
+    /// Replace the index for `root` with a fresh one built from scratch
+    fn rebuild(&self, root: &Path, note_extensions: Vec<String>) -> Result<Arc<VaultIndex>, String> {
+        let mut indexes = self.indexes.lock().map_err(|e| format!("Lock error: {}", e))?;
+        let index = self.create(root, note_extensions)?;
+        index.spawn_sync(true);
+        indexes.insert(root.to_path_buf(), Arc::clone(&index));
+        Ok(index)
+    }
+
+    fn create(&self, root: &Path, note_extensions: Vec<String>) -> Result<Arc<VaultIndex>, String> {
+        fs::create_dir_all(&self.cache_dir).map_err(|e| format!("Failed to create cache directory: {}", e))?;
+        let key = crate::content_hash(root.to_string_lossy().as_bytes());
+        let db_path = self.cache_dir.join(format!("search-{}.db", &key[..16]));
+        let conn = Connection::open(&db_path).map_err(|e| format!("Failed to open index: {}", e))?;
+        // A previous index for the same vault may still be finishing a scan
+        conn.busy_timeout(std::time::Duration::from_secs(5))
+            .map_err(|e| format!("Failed to open index: {}", e))?;
+        init_schema(&conn).map_err(|e| format!("Failed to initialize index: {}", e))?;
+
+        Ok(Arc::new(VaultIndex {
+            root: root.to_path_buf(),
+            note_extensions,
+            conn: Mutex::new(conn),
+            status: Mutex::new(IndexStatus {
+                root: root.to_string_lossy().to_string(),
+                state: IndexState::Building,
+                indexed_files: 0,
+                last_updated: None,
+                error: None,
+            }),
+            building: AtomicBool::new(false),
+        }))
+    }
+
+    pub fn get(&self, root: &Path) -> Result<Arc<VaultIndex>, String> {
+        self.indexes
+            .lock()
+            .map_err(|e| format!("Lock error: {}", e))?
+            .get(root)
+            .cloned()
+            .ok_or_else(|| format!("No index open for {}", root.display()))
+    }
+}
+
+/// Status of the search index for a vault
+#[tauri::command]
+pub fn index_status(root: String, registry: tauri::State<'_, IndexRegistry>) -> Result<IndexStatus, String> {
+    Ok(registry.get(Path::new(&root))?.status())
+}
+
+/// Drop and rebuild the search index for a vault in the background
+#[tauri::command(async)]
+pub fn rebuild_index(
+    root: String,
+    registry: tauri::State<'_, IndexRegistry>,
+    settings: tauri::State<'_, Mutex<SettingsStore>>,
+) -> Result<IndexStatus, String> {
+    let root = PathBuf::from(&root);
+    crate::ensure_dir(&root)?;
+
+    // A fresh index also picks up changed note extensions
+    let index = registry.rebuild(&root, settings::note_extensions(&settings)?)?;
+    Ok(index.status())
+}
+
+/// Bring the search index of a vault up to date as a job, reporting the
+/// files scanned; with `from_scratch`, it is rebuilt. The result of its
+/// `job-done` event is the index status.
+#[tauri::command]
+pub fn start_index_vault(
+    root: String,
+    from_scratch: Option<bool>,
+    app: AppHandle,
+    registry: tauri::State<'_, IndexRegistry>,
+    settings: tauri::State<'_, Mutex<SettingsStore>>,
+) -> Result<u64, String> {
+    let root = PathBuf::from(&root);
+    crate::ensure_dir(&root)?;
+    let index = registry.get_or_create(&root, settings::note_extensions(&settings)?)?;
+    if index.building.load(Ordering::SeqCst) {
+        return Err(format!("{} is already being indexed", root.display()));
+    }
+    jobs::spawn(&app, "index", move |job| {
+        index.run_sync(from_scratch.unwrap_or(false), job)?;
+        Ok(index.status())
+    })
+}
+
+/// Full-text search across a vault, best matches first
+#[tauri::command(async)]
+pub fn query_index(
+    root: String,
+    query: String,
+    limit: Option<usize>,
+    registry: tauri::State<'_, IndexRegistry>,
+) -> Result<Vec<SearchHit>, String> {
+    registry.get(Path::new(&root))?.query(&query, limit.unwrap_or(50))
+}
@@ -0,0 +1,107 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// An open editor tab
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct SessionTab {
+    pub path: String,
+    /// Scroll offset of the editor in pixels
+    pub scroll_top: f64,
+    /// Byte offset of the cursor
+    pub cursor: Option<usize>,
+    /// Whether the tab was showing the rendered preview
+    pub preview: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SidebarState {
+    pub visible: bool,
+    pub width: Option<f64>,
+    /// Selected sidebar panel, e.g. "files", "search" or "tags"
+    pub panel: Option<String>,
+    /// Folders expanded in the file tree
+    pub expanded: Vec<String>,
+}
+
+impl Default for SidebarState {
+    fn default() -> Self {
+        SidebarState {
+            visible: true,
+            width: None,
+            panel: None,
+            expanded: Vec::new(),
+        }
+    }
+}
+
+/// Where the user left off in a vault
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct SessionState {
+    pub tabs: Vec<SessionTab>,
+    /// Path of the focused tab
+    pub active: Option<String>,
+    pub sidebar: SidebarState,
+}
+
+/// Per-vault session files in the app data directory
+pub struct SessionStore {
+    dir: PathBuf,
+}
+
+impl SessionStore {
+    pub fn new(dir: PathBuf) -> Self {
+        SessionStore { dir }
+    }
+
+    fn path(&self, root: &Path) -> PathBuf {
+        let key = crate::content_hash(root.to_string_lossy().as_bytes());
+        self.dir.join(format!("{}.json", &key[..16]))
+    }
+}
+
+/// Save the open tabs, active file, scroll positions and sidebar state of
+/// the vault at `root`
+#[tauri::command]
+pub fn save_session(
+    root: String,
+    state: SessionState,
+    store: tauri::State<'_, SessionStore>,
+) -> Result<(), String> {
+    let root = PathBuf::from(&root);
+    crate::ensure_dir(&root)?;
+
+    fs::create_dir_all(&store.dir).map_err(|e| format!("Failed to create session directory: {}", e))?;
+    let json = serde_json::to_string_pretty(&state).map_err(|e| format!("Failed to serialize session: {}", e))?;
+    crate::write_atomic(&store.path(&root), json.as_bytes()).map_err(|e| format!("Failed to save session: {}", e))
+}
+
+/// The saved session of the vault at `root`, without tabs and folders that
+/// have since been deleted. `None` if there is no saved session.
+#[tauri::command]
+pub fn load_session(root: String, store: tauri::State<'_, SessionStore>) -> Result<Option<SessionState>, String> {
+    let root = PathBuf::from(&root);
+    let raw = match fs::read_to_string(store.path(&root)) {
+        Ok(raw) => raw,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(format!("Failed to read session: {}", e)),
+    };
+    // A session that no longer parses is as good as none
+    let Ok(mut state) = serde_json::from_str::<SessionState>(&raw) else {
+        return Ok(None);
+    };
+
+    state.tabs.retain(|tab| Path::new(&tab.path).is_file());
+    let active_open = state
+        .active
+        .as_ref()
+        .is_some_and(|active| state.tabs.iter().any(|tab| &tab.path == active));
+    if !active_open {
+        state.active = state.tabs.first().map(|tab| tab.path.clone());
+    }
+    state.sidebar.expanded.retain(|folder| Path::new(folder).is_dir());
+    Ok(Some(state))
+}
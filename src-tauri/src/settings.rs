@@ -0,0 +1,667 @@
+use chrono::NaiveTime;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::error::{CommandError, ReadOnlyVault};
+use crate::frontmatter;
+use crate::ignore_rules::IgnoreMatcher;
+use crate::vault_config;
+
+/// Extensions treated as notes when no setting has been saved yet
+pub const DEFAULT_NOTE_EXTENSIONS: &[&str] = &["md", "markdown", "mdx", "txt"];
+
+/// Attachments folder of vaults without a setting saved
+pub const DEFAULT_ATTACHMENTS_DIR: &str = "attachments";
+
+/// Autosave delay in the editor when none has been saved
+const DEFAULT_AUTOSAVE_INTERVAL_MS: u64 = 1000;
+
+/// Templates folder of vaults that don't set their own
+pub const DEFAULT_TEMPLATES_DIR: &str = "templates";
+
+/// Path of a day's note within the vault, for vaults that don't set their
+/// own. `{{...}}` holds a date pattern such as `YYYY-MM-DD`.
+pub const DEFAULT_DAILY_NOTES_FORMAT: &str = "{{YYYY-MM-DD}}.md";
+
+/// Largest file `read_text_file` loads whole when none has been saved, in
+/// megabytes
+const DEFAULT_MAX_READ_SIZE_MB: u64 = 50;
+
+/// Longest autosave delay accepted, ten minutes
+const MAX_AUTOSAVE_INTERVAL_MS: u64 = 10 * 60 * 1000;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    /// File extensions treated as notes, lowercase and without the leading dot
+    pub note_extensions: Vec<String>,
+    /// Gitignore-style patterns hidden from listings, searches and watchers
+    /// in every vault, on top of those passed to a command
+    pub ignore_globs: Vec<String>,
+    /// Attachments folder of vaults that don't set their own
+    pub attachments_dir: String,
+    /// Templates folder of vaults that don't set their own, relative to the
+    /// vault root
+    pub templates_dir: String,
+    /// Path of a day's note in vaults that don't set their own, relative to
+    /// the vault root, e.g. `Journal/{{YYYY}}/{{YYYY-MM-DD}}.md`
+    pub daily_notes_format: String,
+    /// Note new daily notes are created from in vaults that don't set their
+    /// own, relative to the vault root; empty for none
+    pub daily_notes_template: String,
+    /// Delay after the last keystroke before the editor saves; 0 turns
+    /// autosave off
+    pub autosave_interval_ms: u64,
+    /// Hide the window to the tray when it is closed, instead of quitting
+    pub close_to_tray: bool,
+    /// Largest file `read_text_file` loads whole, in megabytes; bigger ones
+    /// are read with `read_file_range`. 0 for no limit.
+    pub max_read_size_mb: u64,
+    /// Settings of individual vaults, by root path
+    pub vaults: BTreeMap<String, VaultSettings>,
+    pub history: HistorySettings,
+    pub capture_server: CaptureServerSettings,
+    pub reminders: ReminderSettings,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            note_extensions: DEFAULT_NOTE_EXTENSIONS.iter().map(|e| e.to_string()).collect(),
+            ignore_globs: Vec::new(),
+            attachments_dir: DEFAULT_ATTACHMENTS_DIR.to_string(),
+            templates_dir: DEFAULT_TEMPLATES_DIR.to_string(),
+            daily_notes_format: DEFAULT_DAILY_NOTES_FORMAT.to_string(),
+            daily_notes_template: String::new(),
+            autosave_interval_ms: DEFAULT_AUTOSAVE_INTERVAL_MS,
+            close_to_tray: true,
+            max_read_size_mb: DEFAULT_MAX_READ_SIZE_MB,
+            vaults: BTreeMap::new(),
+            history: HistorySettings::default(),
+            capture_server: CaptureServerSettings::default(),
+            reminders: ReminderSettings::default(),
+        }
+    }
+}
+
+/// Retention of the snapshots `write_text_file` takes of notes
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HistorySettings {
+    pub enabled: bool,
+    /// Snapshots kept per file; 0 for no limit
+    pub max_versions: usize,
+    /// Snapshots older than this are removed, except a file's newest; 0 for
+    /// no limit
+    pub max_age_days: u64,
+}
+
+impl Default for HistorySettings {
+    fn default() -> Self {
+        HistorySettings {
+            enabled: true,
+            max_versions: 50,
+            max_age_days: 90,
+        }
+    }
+}
+
+/// The local HTTP server browser extensions and automation tools capture
+/// notes through
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CaptureServerSettings {
+    /// Started with the app
+    pub enabled: bool,
+    /// Root of the vault it serves
+    pub vault: Option<String>,
+    /// Port on 127.0.0.1
+    pub port: u16,
+    /// Note `POST /capture` appends to, relative to the vault root
+    pub inbox: String,
+    /// Bearer token clients must send; generated on first start
+    pub token: String,
+}
+
+impl Default for CaptureServerSettings {
+    fn default() -> Self {
+        CaptureServerSettings {
+            enabled: false,
+            vault: None,
+            port: 27183,
+            inbox: "Inbox.md".to_string(),
+            token: String::new(),
+        }
+    }
+}
+
+/// Notifications for tasks of open vaults as they come due
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ReminderSettings {
+    pub enabled: bool,
+    /// `HH:MM` on their due date that tasks without a due time are reminded
+    /// of
+    pub time: String,
+    /// How long `snooze_task` puts a reminder off by default
+    pub snooze_minutes: u32,
+}
+
+impl Default for ReminderSettings {
+    fn default() -> Self {
+        ReminderSettings {
+            enabled: true,
+            time: "09:00".to_string(),
+            snooze_minutes: 10,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VaultSettings {
+    /// Folder pasted and downloaded files are saved in, relative to the vault
+    /// root, or to the note's folder when it starts with `./` (`.` for the
+    /// note's folder itself, `/` for the root itself). `None` for the
+    /// app-wide setting.
+    pub attachments_dir: Option<String>,
+    pub autocommit: AutocommitSettings,
+    pub backup: BackupSettings,
+    /// WebDAV folder the vault syncs with, if any
+    pub sync: Option<SyncConfig>,
+    /// Refuse every change to the vault's files, e.g. for a published
+    /// archive or a vault on a shared drive
+    pub read_only: bool,
+}
+
+/// Committing a vault's changes to its git repository automatically
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AutocommitSettings {
+    pub enabled: bool,
+    /// Minutes without saves before the changes are committed
+    pub interval_minutes: u64,
+    /// Commit message; `{date}`, `{count}` and `{files}` are filled in
+    pub message: String,
+}
+
+impl Default for AutocommitSettings {
+    fn default() -> Self {
+        AutocommitSettings {
+            enabled: false,
+            interval_minutes: 5,
+            message: "Vault backup: {date}".to_string(),
+        }
+    }
+}
+
+/// When a vault is backed up to a zip archive automatically
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BackupSchedule {
+    #[default]
+    Off,
+    /// Once a day while the app runs
+    Daily,
+    /// When the app quits
+    OnClose,
+}
+
+/// Zip backups of a vault
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BackupSettings {
+    pub schedule: BackupSchedule,
+    /// Absolute path of the folder backups are written to; `None` for one in
+    /// the app's data folder
+    pub dir: Option<String>,
+    /// Backups kept, oldest removed first; 0 for no limit
+    pub keep: usize,
+}
+
+impl Default for BackupSettings {
+    fn default() -> Self {
+        BackupSettings {
+            schedule: BackupSchedule::Off,
+            dir: None,
+            keep: 10,
+        }
+    }
+}
+
+/// Two-way sync of a vault with a folder on a WebDAV server
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SyncConfig {
+    /// URL of the remote folder, e.g.
+    /// `https://cloud.example.com/remote.php/dav/files/me/Notes/` on Nextcloud
+    pub url: String,
+    pub username: String,
+    /// Stored in the settings file as is; prefer an app password where the
+    /// server supports them
+    pub password: String,
+}
+
+/// Normalize user-entered extensions: trim, drop leading dots, lowercase, dedupe
+pub fn normalize_extensions(extensions: &[String]) -> Vec<String> {
+    let mut normalized: Vec<String> = Vec::new();
+    for extension in extensions {
+        let extension = extension.trim().trim_start_matches('.').to_lowercase();
+        if !extension.is_empty() && !normalized.contains(&extension) {
+            normalized.push(extension);
+        }
+    }
+    normalized
+}
+
+/// Normalize a user-entered path within the vault: forward slashes, no
+/// trailing slash, and `.` for an empty one. Paths leaving the vault are
+/// rejected, naming the setting as `what`.
+pub fn normalize_vault_path(path: &str, what: &str) -> Result<String, String> {
+    let path = path.trim().replace('\\', "/");
+    let path = path.trim_end_matches('/');
+    if path.is_empty() || path == "." {
+        return Ok(".".to_string());
+    }
+    let inside = Path::new(path).components().all(|c| matches!(c, Component::Normal(_) | Component::CurDir));
+    if !inside {
+        return Err(format!("{} must be inside the vault: {}", what, path));
+    }
+    Ok(path.to_string())
+}
+
+/// Normalize a user-entered attachments folder, where `.` stands for the
+/// note's own folder and `/` for the vault root
+pub fn normalize_attachments_dir(dir: &str) -> Result<String, String> {
+    if dir.trim() == "/" {
+        return Ok("/".to_string());
+    }
+    normalize_vault_path(dir, "Attachments folder")
+}
+
+/// Normalize a daily note path template, which must name a file
+pub fn normalize_daily_notes_format(format: &str) -> Result<String, String> {
+    let format = normalize_vault_path(format, "Daily note path")?;
+    if format == "." {
+        return Err("A daily note path is required".to_string());
+    }
+    Ok(format)
+}
+
+/// Normalize a daily note template path, keeping an empty one, which stands
+/// for no template
+pub fn normalize_daily_notes_template(template: &str) -> Result<String, String> {
+    if template.trim().is_empty() {
+        return Ok(String::new());
+    }
+    normalize_vault_path(template, "Daily note template")
+}
+
+/// Whether `path` has one of the configured note extensions
+pub fn has_note_extension(path: &Path, extensions: &[String]) -> bool {
+    path.extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .is_some_and(|e| extensions.contains(&e))
+}
+
+/// Callback for saved settings changes
+type Listener = Box<dyn Fn(&Settings) + Send>;
+
+/// Settings backed by `settings.json` in the app config directory
+pub struct SettingsStore {
+    path: PathBuf,
+    settings: Settings,
+    /// Called with the new settings after each saved change
+    listener: Option<Listener>,
+}
+
+impl SettingsStore {
+    /// Load settings from `path`, falling back to defaults if the file is
+    /// missing or unreadable
+    pub fn load(path: PathBuf) -> Self {
+        let settings = fs::read_to_string(&path)
+            .ok()
+            .and_then(|raw| serde_json::from_str(&raw).ok())
+            .unwrap_or_default();
+        SettingsStore {
+            path,
+            settings,
+            listener: None,
+        }
+    }
+
+    /// Call `listener` with the new settings whenever a change is saved
+    pub fn set_listener(&mut self, listener: impl Fn(&Settings) + Send + 'static) {
+        self.listener = Some(Box::new(listener));
+    }
+
+    pub fn settings(&self) -> &Settings {
+        &self.settings
+    }
+
+    /// Apply `change` and persist the result
+    pub fn update(&mut self, change: impl FnOnce(&mut Settings)) -> Result<&Settings, String> {
+        let mut updated = self.settings.clone();
+        change(&mut updated);
+
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent).map_err(|e| format!("Failed to create config directory: {}", e))?;
+        }
+        let json = serde_json::to_string_pretty(&updated)
+            .map_err(|e| format!("Failed to serialize settings: {}", e))?;
+        crate::write_atomic(&self.path, json.as_bytes())
+            .map_err(|e| format!("Failed to save settings: {}", e))?;
+
+        self.settings = updated;
+        if let Some(listener) = &self.listener {
+            listener(&self.settings);
+        }
+        Ok(&self.settings)
+    }
+
+    /// Apply `change` and persist the result if it passes `validate`
+    fn update_checked(&mut self, change: impl FnOnce(&mut Settings)) -> Result<&Settings, String> {
+        let mut updated = self.settings.clone();
+        change(&mut updated);
+        let updated = validate(updated)?;
+        self.update(|settings| *settings = updated)
+    }
+}
+
+/// Current note extensions, for commands that classify files
+pub fn note_extensions(state: &Mutex<SettingsStore>) -> Result<Vec<String>, String> {
+    let store = state.lock().map_err(|e| format!("Lock error: {}", e))?;
+    Ok(store.settings().note_extensions.clone())
+}
+
+/// Largest file `read_text_file` loads whole, in bytes, or `None` for no
+/// limit
+pub fn max_read_size(state: &Mutex<SettingsStore>) -> Result<Option<u64>, String> {
+    let store = state.lock().map_err(|e| format!("Lock error: {}", e))?;
+    let megabytes = store.settings().max_read_size_mb;
+    Ok((megabytes > 0).then(|| megabytes.saturating_mul(1024 * 1024)))
+}
+
+/// Ignore patterns that apply everywhere, followed by `extra`
+pub fn ignore_globs(state: &Mutex<SettingsStore>, extra: &[String]) -> Result<Vec<String>, String> {
+    let store = state.lock().map_err(|e| format!("Lock error: {}", e))?;
+    Ok(store.settings().ignore_globs.iter().chain(extra).cloned().collect())
+}
+
+/// Err if `path` is in a vault set to read-only, for commands about to
+/// change it
+pub fn check_writable(state: &Mutex<SettingsStore>, path: &Path) -> Result<(), CommandError> {
+    let store = state.lock().map_err(|e| format!("Lock error: {}", e))?;
+    let read_only = store
+        .settings()
+        .vaults
+        .iter()
+        .find(|(root, vault)| vault.read_only && path.starts_with(root));
+    match read_only {
+        Some((root, _)) => Err(ReadOnlyVault {
+            root: root.clone(),
+            path: path.to_string_lossy().to_string(),
+        }
+        .into()),
+        None => Ok(()),
+    }
+}
+
+/// Settings of the vault at `root`, or the defaults if none are saved
+pub fn vault_settings(state: &Mutex<SettingsStore>, root: &Path) -> Result<VaultSettings, String> {
+    let store = state.lock().map_err(|e| format!("Lock error: {}", e))?;
+    Ok(store
+        .settings()
+        .vaults
+        .get(root.to_string_lossy().as_ref())
+        .cloned()
+        .unwrap_or_default())
+}
+
+/// Get the file extensions treated as notes
+#[tauri::command]
+pub fn get_note_extensions(state: tauri::State<'_, Mutex<SettingsStore>>) -> Result<Vec<String>, String> {
+    note_extensions(&state)
+}
+
+/// Set the file extensions treated as notes
+#[tauri::command]
+pub fn set_note_extensions(
+    extensions: Vec<String>,
+    state: tauri::State<'_, Mutex<SettingsStore>>,
+) -> Result<Vec<String>, String> {
+    let extensions = normalize_extensions(&extensions);
+    if extensions.is_empty() {
+        return Err("At least one note extension is required".to_string());
+    }
+
+    let mut store = state.lock().map_err(|e| format!("Lock error: {}", e))?;
+    let settings = store.update(|settings| settings.note_extensions = extensions)?;
+    Ok(settings.note_extensions.clone())
+}
+
+/// Get the attachments folder of the vault at `root`, as set in the vault's
+/// config file, the app or the app-wide default
+#[tauri::command]
+pub fn get_attachments_dir(root: String, state: tauri::State<'_, Mutex<SettingsStore>>) -> Result<String, String> {
+    Ok(vault_config::resolve(Path::new(&root), &state)?.attachments_dir)
+}
+
+/// Set the attachments folder of the vault at `root`. One set in the vault's
+/// config file takes precedence.
+#[tauri::command]
+pub fn set_attachments_dir(
+    root: String,
+    dir: String,
+    state: tauri::State<'_, Mutex<SettingsStore>>,
+) -> Result<String, String> {
+    let dir = normalize_attachments_dir(&dir)?;
+    let mut store = state.lock().map_err(|e| format!("Lock error: {}", e))?;
+    store.update(|settings| settings.vaults.entry(root).or_default().attachments_dir = Some(dir.clone()))?;
+    Ok(dir)
+}
+
+/// Make the vault at `root` read-only, or writable again. Returns the new
+/// state.
+#[tauri::command]
+pub fn set_vault_read_only(
+    root: String,
+    read_only: bool,
+    state: tauri::State<'_, Mutex<SettingsStore>>,
+) -> Result<bool, String> {
+    let mut store = state.lock().map_err(|e| format!("Lock error: {}", e))?;
+    let settings = store.update(|settings| settings.vaults.entry(root.clone()).or_default().read_only = read_only)?;
+    Ok(settings.vaults[&root].read_only)
+}
+
+/// Whether the vault at `root` is read-only
+#[tauri::command]
+pub fn is_vault_read_only(root: String, state: tauri::State<'_, Mutex<SettingsStore>>) -> Result<bool, String> {
+    Ok(vault_settings(&state, Path::new(&root))?.read_only)
+}
+
+/// Get the file history retention settings
+#[tauri::command]
+pub fn get_history_settings(state: tauri::State<'_, Mutex<SettingsStore>>) -> Result<HistorySettings, String> {
+    let store = state.lock().map_err(|e| format!("Lock error: {}", e))?;
+    Ok(store.settings().history.clone())
+}
+
+/// Set the file history retention settings
+#[tauri::command]
+pub fn set_history_settings(
+    history: HistorySettings,
+    state: tauri::State<'_, Mutex<SettingsStore>>,
+) -> Result<HistorySettings, String> {
+    let mut store = state.lock().map_err(|e| format!("Lock error: {}", e))?;
+    let settings = store.update(|settings| settings.history = history)?;
+    Ok(settings.history.clone())
+}
+
+/// Get the auto-commit settings of the vault at `root`
+#[tauri::command]
+pub fn get_autocommit_settings(
+    root: String,
+    state: tauri::State<'_, Mutex<SettingsStore>>,
+) -> Result<AutocommitSettings, String> {
+    Ok(vault_settings(&state, Path::new(&root))?.autocommit)
+}
+
+/// Set the auto-commit settings of the vault at `root`
+#[tauri::command]
+pub fn set_autocommit_settings(
+    root: String,
+    autocommit: AutocommitSettings,
+    state: tauri::State<'_, Mutex<SettingsStore>>,
+) -> Result<AutocommitSettings, String> {
+    let mut store = state.lock().map_err(|e| format!("Lock error: {}", e))?;
+    let settings =
+        store.update_checked(|settings| settings.vaults.entry(root.clone()).or_default().autocommit = autocommit)?;
+    Ok(settings.vaults[&root].autocommit.clone())
+}
+
+/// Get the backup settings of the vault at `root`
+#[tauri::command]
+pub fn get_backup_settings(
+    root: String,
+    state: tauri::State<'_, Mutex<SettingsStore>>,
+) -> Result<BackupSettings, String> {
+    Ok(vault_settings(&state, Path::new(&root))?.backup)
+}
+
+/// Set the backup settings of the vault at `root`. The sandbox only lets
+/// `dir` be a folder the user picked with `pick_folder` or one in the vault.
+#[tauri::command]
+pub fn set_backup_settings(
+    root: String,
+    backup: BackupSettings,
+    state: tauri::State<'_, Mutex<SettingsStore>>,
+) -> Result<BackupSettings, String> {
+    let mut store = state.lock().map_err(|e| format!("Lock error: {}", e))?;
+    let settings =
+        store.update_checked(|settings| settings.vaults.entry(root.clone()).or_default().backup = backup)?;
+    Ok(settings.vaults[&root].backup.clone())
+}
+
+/// The settings with every field present, for checking patches against.
+/// Keys of maps are stood in for by `*`.
+fn settings_schema() -> Value {
+    let vault = VaultSettings {
+        sync: Some(SyncConfig::default()),
+        ..Default::default()
+    };
+    let mut schema = serde_json::to_value(Settings::default()).unwrap_or_default();
+    schema["vaults"] = serde_json::json!({ "*": vault });
+    schema
+}
+
+/// Reject keys in `patch` that aren't settings, so typos don't silently do
+/// nothing
+fn check_keys(patch: &Value, schema: &Value, at: &str) -> Result<(), String> {
+    let (Value::Object(patch), Value::Object(schema)) = (patch, schema) else {
+        return Ok(());
+    };
+    for (key, value) in patch {
+        let path = if at.is_empty() { key.clone() } else { format!("{}.{}", at, key) };
+        let expected = schema
+            .get(key)
+            .or_else(|| schema.get("*"))
+            .ok_or_else(|| format!("Unknown setting: {}", path))?;
+        check_keys(value, expected, &path)?;
+    }
+    Ok(())
+}
+
+/// Check the values of `settings`, normalizing those that have a canonical
+/// form
+fn validate(mut settings: Settings) -> Result<Settings, String> {
+    settings.note_extensions = normalize_extensions(&settings.note_extensions);
+    if settings.note_extensions.is_empty() {
+        return Err("At least one note extension is required".to_string());
+    }
+
+    settings.ignore_globs = settings
+        .ignore_globs
+        .iter()
+        .map(|glob| glob.trim().to_string())
+        .filter(|glob| !glob.is_empty())
+        .collect();
+    IgnoreMatcher::new(Path::new("/"), &settings.ignore_globs, false)?;
+
+    settings.attachments_dir = normalize_attachments_dir(&settings.attachments_dir)?;
+    settings.templates_dir = normalize_vault_path(&settings.templates_dir, "Templates folder")?;
+    settings.daily_notes_format = normalize_daily_notes_format(&settings.daily_notes_format)?;
+    settings.daily_notes_template = normalize_daily_notes_template(&settings.daily_notes_template)?;
+    settings.capture_server.inbox = normalize_vault_path(&settings.capture_server.inbox, "Inbox note")?;
+    if settings.capture_server.inbox == "." {
+        return Err("An inbox note is required".to_string());
+    }
+    if settings.capture_server.port == 0 {
+        return Err("Capture server port must be between 1 and 65535".to_string());
+    }
+    if settings.autosave_interval_ms > MAX_AUTOSAVE_INTERVAL_MS
+        || (settings.autosave_interval_ms > 0 && settings.autosave_interval_ms < 100)
+    {
+        return Err("Autosave interval must be between 100 ms and 10 minutes, or 0 for no autosave".to_string());
+    }
+    settings.reminders.time = NaiveTime::parse_from_str(settings.reminders.time.trim(), "%H:%M")
+        .map_err(|_| format!("Invalid reminder time, expected HH:MM: {}", settings.reminders.time))?
+        .format("%H:%M")
+        .to_string();
+    if settings.reminders.snooze_minutes == 0 {
+        return Err("Snooze time must be at least a minute".to_string());
+    }
+
+    for (root, vault) in settings.vaults.iter_mut() {
+        if let Some(dir) = &vault.attachments_dir {
+            vault.attachments_dir = Some(normalize_attachments_dir(dir)?);
+        }
+        if vault.autocommit.message.trim().is_empty() {
+            return Err(format!("A commit message is required for {}", root));
+        }
+        vault.autocommit.interval_minutes = vault.autocommit.interval_minutes.max(1);
+        let dir = vault.backup.dir.take().filter(|dir| !dir.trim().is_empty());
+        if let Some(dir) = dir {
+            if !Path::new(&dir).is_absolute() {
+                return Err(format!("Backup folder must be an absolute path: {}", dir));
+            }
+            vault.backup.dir = Some(dir);
+        }
+        if let Some(sync) = &vault.sync {
+            if !sync.url.starts_with("http://") && !sync.url.starts_with("https://") {
+                return Err(format!("Not a WebDAV URL: {}", sync.url));
+            }
+        }
+    }
+    Ok(settings)
+}
+
+/// Get all settings
+#[tauri::command]
+pub fn get_settings(state: tauri::State<'_, Mutex<SettingsStore>>) -> Result<Settings, String> {
+    let store = state.lock().map_err(|e| format!("Lock error: {}", e))?;
+    Ok(store.settings().clone())
+}
+
+/// Change settings by a JSON merge patch of `get_settings`' result, e.g.
+/// `{ "history": { "max_versions": 20 } }`. `null` resets a setting to its
+/// default. Nothing is saved if any value is invalid. A `settings-changed`
+/// event carries the new settings.
+#[tauri::command]
+pub fn set_settings(patch: Value, state: tauri::State<'_, Mutex<SettingsStore>>) -> Result<Settings, String> {
+    if !patch.is_object() {
+        return Err("Settings patch must be an object".to_string());
+    }
+    check_keys(&patch, &settings_schema(), "")?;
+
+    let mut store = state.lock().map_err(|e| format!("Lock error: {}", e))?;
+    let mut merged = serde_json::to_value(store.settings()).map_err(|e| format!("Failed to read settings: {}", e))?;
+    frontmatter::merge_patch(&mut merged, &patch);
+    let settings = serde_json::from_value(merged).map_err(|e| format!("Invalid settings: {}", e))?;
+    let settings = validate(settings)?;
+    Ok(store.update(|current| *current = settings)?.clone())
+}
@@ -0,0 +1,542 @@
+use regex::Regex;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
+
+use crate::error::CommandError;
+use crate::mentions::prose_ranges;
+use crate::settings::{self, SettingsStore};
+
+/// The vault's own words, one per line, in its `.readmark` folder
+const DICTIONARY_FILE: &str = "dictionary.txt";
+/// Suggestions returned per misspelled word
+const MAX_SUGGESTIONS: usize = 5;
+
+type Flag = u32;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FlagMode {
+    /// One character per flag, the default
+    Char,
+    /// `FLAG long`: two characters per flag
+    Long,
+    /// `FLAG num`: comma-separated numbers
+    Num,
+}
+
+fn parse_flags(flags: &str, mode: FlagMode) -> Vec<Flag> {
+    match mode {
+        FlagMode::Char => flags.chars().map(|c| c as Flag).collect(),
+        FlagMode::Long => {
+            let chars: Vec<char> = flags.chars().collect();
+            chars.chunks(2).map(|pair| pair.iter().fold(0, |flag, c| (flag << 16) | *c as Flag)).collect()
+        }
+        FlagMode::Num => flags.split(',').filter_map(|n| n.trim().parse().ok()).collect(),
+    }
+}
+
+/// One `PFX` or `SFX` rule of an affix file
+#[derive(Debug)]
+struct Affix {
+    flag: Flag,
+    /// Combines with affixes of the other kind
+    cross: bool,
+    /// Removed from the stem before `add` is added
+    strip: String,
+    add: String,
+    /// What the stem must start (prefixes) or end (suffixes) with
+    condition: Option<Regex>,
+    /// Flags for affixes that may be added on top of this one
+    continuation: Vec<Flag>,
+}
+
+/// A hunspell condition as a regex anchored to the stem's `start` or end
+fn condition_regex(condition: &str, prefix: bool) -> Option<Regex> {
+    if condition == "." {
+        return None;
+    }
+    let mut pattern = String::new();
+    let mut in_class = false;
+    for c in condition.chars() {
+        match c {
+            '[' => in_class = true,
+            ']' => in_class = false,
+            _ => {}
+        }
+        match c {
+            '[' | ']' | '.' => pattern.push(c),
+            '^' if in_class => pattern.push(c),
+            c => pattern.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    let pattern = if prefix { format!("^(?:{})", pattern) } else { format!("(?:{})$", pattern) };
+    Regex::new(&pattern).ok()
+}
+
+/// A hunspell dictionary: its word list and the affix rules that inflect
+/// them. Compounding and morphology are not supported.
+#[derive(Debug, Default)]
+pub struct Dictionary {
+    words: HashMap<String, Vec<Flag>>,
+    prefixes: Vec<Affix>,
+    suffixes: Vec<Affix>,
+    /// Characters to try in suggestions, most frequent first
+    try_chars: Vec<char>,
+    /// Common misspellings and their corrections, from `REP`
+    replacements: Vec<(String, String)>,
+    forbidden: Option<Flag>,
+    need_affix: Option<Flag>,
+    no_suggest: Option<Flag>,
+    only_in_compound: Option<Flag>,
+}
+
+impl Dictionary {
+    /// Parse an `.aff` and `.dic` pair, decoded with the affix file's `SET`
+    pub fn parse(aff: &[u8], dic: &[u8]) -> Dictionary {
+        let (aff_text, _, _) = encoding_rs::UTF_8.decode(aff);
+        let encoding = aff_text
+            .lines()
+            .find_map(|line| line.strip_prefix("SET "))
+            .and_then(|label| encoding_rs::Encoding::for_label(label.trim().as_bytes()))
+            .unwrap_or(encoding_rs::UTF_8);
+        let (aff_text, _, _) = encoding.decode(aff);
+        let (dic_text, _, _) = encoding.decode(dic);
+
+        let mut dictionary = Dictionary::default();
+        let mut mode = FlagMode::Char;
+        // Affix headers (`SFX A Y 3`) say whether their rules combine
+        let mut cross: HashMap<(bool, Flag), bool> = HashMap::new();
+        for line in aff_text.lines() {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let flag = |i: usize| fields.get(i).and_then(|f| parse_flags(f, mode).first().copied());
+            match fields.as_slice() {
+                ["FLAG", "long", ..] => mode = FlagMode::Long,
+                ["FLAG", "num", ..] => mode = FlagMode::Num,
+                ["TRY", chars, ..] => dictionary.try_chars = chars.chars().collect(),
+                ["REP", from, to, ..] => {
+                    // `_` stands for a space
+                    dictionary.replacements.push((from.replace('_', " "), to.replace('_', " ")))
+                }
+                ["FORBIDDENWORD", ..] => dictionary.forbidden = flag(1),
+                ["NEEDAFFIX", ..] | ["PSEUDOROOT", ..] => dictionary.need_affix = flag(1),
+                ["NOSUGGEST", ..] => dictionary.no_suggest = flag(1),
+                ["ONLYINCOMPOUND", ..] => dictionary.only_in_compound = flag(1),
+                [kind @ ("PFX" | "SFX"), _, combines, count] if count.parse::<usize>().is_ok() => {
+                    if let Some(flag) = flag(1) {
+                        cross.insert((*kind == "PFX", flag), *combines == "Y");
+                    }
+                }
+                [kind @ ("PFX" | "SFX"), _, strip, add, rest @ ..] => {
+                    let prefix = *kind == "PFX";
+                    let Some(flag) = flag(1) else {
+                        continue;
+                    };
+                    let (add, continuation) = match add.split_once('/') {
+                        Some((add, flags)) => (add, parse_flags(flags, mode)),
+                        None => (*add, Vec::new()),
+                    };
+                    let affix = Affix {
+                        flag,
+                        cross: cross.get(&(prefix, flag)).copied().unwrap_or(false),
+                        strip: if *strip == "0" { String::new() } else { strip.to_string() },
+                        add: if add == "0" { String::new() } else { add.to_string() },
+                        condition: condition_regex(rest.first().copied().unwrap_or("."), prefix),
+                        continuation,
+                    };
+                    if prefix {
+                        dictionary.prefixes.push(affix);
+                    } else {
+                        dictionary.suffixes.push(affix);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        // The first line is the word count
+        for line in dic_text.lines().skip(1) {
+            let Some(entry) = line.split(['\t', ' ']).next().filter(|entry| !entry.is_empty()) else {
+                continue;
+            };
+            // `/` separates the flags unless escaped
+            let split = entry.char_indices().find(|&(i, c)| c == '/' && !entry[..i].ends_with('\\'));
+            let (word, flags) = match split {
+                Some((i, _)) => (&entry[..i], parse_flags(&entry[i + 1..], mode)),
+                None => (entry, Vec::new()),
+            };
+            dictionary.words.entry(word.replace("\\/", "/")).or_default().extend(flags);
+        }
+        dictionary
+    }
+
+    fn has_flag(&self, flags: &[Flag], flag: Option<Flag>) -> bool {
+        flag.is_some_and(|flag| flags.contains(&flag))
+    }
+
+    /// `stem` is in the word list and may take an affix with `flag`
+    fn stem_takes(&self, stem: &str, flag: Flag) -> bool {
+        self.words
+            .get(stem)
+            .is_some_and(|flags| flags.contains(&flag) && !self.has_flag(flags, self.forbidden))
+    }
+
+    /// Stems `word` could be made of with `suffix`
+    fn strip_suffix(&self, word: &str, suffix: &Affix) -> Option<String> {
+        let base = word.strip_suffix(suffix.add.as_str())?;
+        if base.is_empty() && suffix.strip.is_empty() {
+            return None;
+        }
+        let stem = format!("{}{}", base, suffix.strip);
+        suffix.condition.as_ref().is_none_or(|condition| condition.is_match(&stem)).then_some(stem)
+    }
+
+    fn strip_prefix(&self, word: &str, prefix: &Affix) -> Option<String> {
+        let base = word.strip_prefix(prefix.add.as_str())?;
+        if base.is_empty() && prefix.strip.is_empty() {
+            return None;
+        }
+        let stem = format!("{}{}", prefix.strip, base);
+        prefix.condition.as_ref().is_none_or(|condition| condition.is_match(&stem)).then_some(stem)
+    }
+
+    /// `word` is a stem with one suffix, or two where the inner one allows
+    /// the outer. With `prefix_flag`, the stem must also take that prefix.
+    fn check_suffixed(&self, word: &str, prefix_flag: Option<Flag>) -> bool {
+        let takes = |stem: &str, flag: Flag| {
+            self.stem_takes(stem, flag) && prefix_flag.is_none_or(|prefix| self.stem_takes(stem, prefix))
+        };
+        self.suffixes.iter().filter(|suffix| prefix_flag.is_none() || suffix.cross).any(|suffix| {
+            let Some(stem) = self.strip_suffix(word, suffix) else {
+                return false;
+            };
+            takes(&stem, suffix.flag)
+                || self
+                    .suffixes
+                    .iter()
+                    .filter(|inner| inner.continuation.contains(&suffix.flag))
+                    .any(|inner| self.strip_suffix(&stem, inner).is_some_and(|root| takes(&root, inner.flag)))
+        })
+    }
+
+    /// `word` as written, without trying other capitalizations
+    fn check_exact(&self, word: &str) -> Option<bool> {
+        if let Some(flags) = self.words.get(word) {
+            if self.has_flag(flags, self.forbidden) {
+                return Some(false);
+            }
+            if !self.has_flag(flags, self.need_affix) && !self.has_flag(flags, self.only_in_compound) {
+                return Some(true);
+            }
+        }
+        let prefixed = self.prefixes.iter().any(|prefix| {
+            self.strip_prefix(word, prefix).is_some_and(|stem| {
+                self.stem_takes(&stem, prefix.flag)
+                    || (prefix.cross && self.check_suffixed(&stem, Some(prefix.flag)))
+            })
+        });
+        (prefixed || self.check_suffixed(word, None)).then_some(true)
+    }
+
+    /// `word` is spelled correctly. Capitalized and all-caps words also
+    /// match their lowercase forms.
+    pub fn check(&self, word: &str) -> bool {
+        let word = word.replace('’', "'");
+        if let Some(correct) = self.check_exact(&word) {
+            return correct;
+        }
+        let lower = word.to_lowercase();
+        if lower != word && self.check_exact(&lower) == Some(true) {
+            return true;
+        }
+        // `NASA`-style words listed as `Nasa`, or names in caps
+        let title = capitalize(&lower);
+        word.chars().all(|c| !c.is_lowercase()) && title != word && self.check_exact(&title) == Some(true)
+    }
+
+    /// Valid words one edit away from `word`, likeliest first
+    pub fn suggest(&self, word: &str) -> Vec<String> {
+        if word.chars().count() > 40 {
+            return Vec::new();
+        }
+        let lower = word.to_lowercase();
+        let chars: Vec<char> = lower.chars().collect();
+        let try_chars: Vec<char> = if self.try_chars.is_empty() {
+            "esianrtolcdugmphbyfvkwzxjq".chars().collect()
+        } else {
+            self.try_chars.clone()
+        };
+
+        let mut candidates: Vec<String> = Vec::new();
+        for (from, to) in &self.replacements {
+            for (i, _) in lower.match_indices(from.as_str()) {
+                candidates.push(format!("{}{}{}", &lower[..i], to, &lower[i + from.len()..]));
+            }
+        }
+        let joined = |parts: &[char]| parts.iter().collect::<String>();
+        for i in 0..chars.len().saturating_sub(1) {
+            let mut swapped = chars.clone();
+            swapped.swap(i, i + 1);
+            candidates.push(joined(&swapped));
+        }
+        for i in 0..chars.len() {
+            for &c in &try_chars {
+                if c != chars[i] {
+                    let mut replaced = chars.clone();
+                    replaced[i] = c;
+                    candidates.push(joined(&replaced));
+                }
+            }
+        }
+        for i in 0..chars.len() {
+            let mut removed = chars.clone();
+            removed.remove(i);
+            candidates.push(joined(&removed));
+        }
+        for i in 0..=chars.len() {
+            for &c in &try_chars {
+                let mut inserted = chars.clone();
+                inserted.insert(i, c);
+                candidates.push(joined(&inserted));
+            }
+        }
+        for i in 1..chars.len() {
+            candidates.push(format!("{} {}", joined(&chars[..i]), joined(&chars[i..])));
+        }
+
+        let capitalized = word.chars().next().is_some_and(char::is_uppercase);
+        let mut seen = HashSet::new();
+        let mut suggestions = Vec::new();
+        for candidate in candidates {
+            if candidate == lower || !seen.insert(candidate.clone()) {
+                continue;
+            }
+            let valid = candidate.split(' ').all(|part| {
+                !part.is_empty()
+                    && self.check(part)
+                    && !self.words.get(part).is_some_and(|flags| self.has_flag(flags, self.no_suggest))
+            });
+            if valid {
+                suggestions.push(if capitalized { capitalize(&candidate) } else { candidate });
+                if suggestions.len() == MAX_SUGGESTIONS {
+                    break;
+                }
+            }
+        }
+        suggestions
+    }
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+/// Loaded dictionaries, by language
+pub struct Spellchecker {
+    /// Dictionaries installed for the app, searched before the system's
+    dir: PathBuf,
+    loaded: Mutex<HashMap<String, Arc<Dictionary>>>,
+}
+
+impl Spellchecker {
+    pub fn new(dir: PathBuf) -> Self {
+        Spellchecker {
+            dir,
+            loaded: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Folders hunspell dictionaries are looked for in
+    fn search_dirs(&self) -> Vec<PathBuf> {
+        let mut dirs = vec![self.dir.clone()];
+        if cfg!(target_os = "macos") {
+            if let Some(home) = std::env::var_os("HOME") {
+                dirs.push(Path::new(&home).join("Library/Spelling"));
+            }
+            dirs.push(PathBuf::from("/Library/Spelling"));
+        } else if cfg!(unix) {
+            for dir in ["/usr/share/hunspell", "/usr/share/myspell", "/usr/share/myspell/dicts"] {
+                dirs.push(PathBuf::from(dir));
+            }
+        }
+        dirs
+    }
+
+    /// Languages with both an `.aff` and a `.dic` file, such as `en_US`
+    pub fn languages(&self) -> Vec<String> {
+        let mut languages: Vec<String> = self
+            .search_dirs()
+            .iter()
+            .filter_map(|dir| fs::read_dir(dir).ok())
+            .flatten()
+            .flatten()
+            .filter_map(|entry| {
+                let path = entry.path();
+                let lang = path.file_stem()?.to_string_lossy().to_string();
+                let paired = path.extension()? == "dic" && path.with_extension("aff").is_file();
+                paired.then_some(lang)
+            })
+            .collect();
+        languages.sort();
+        languages.dedup();
+        languages
+    }
+
+    /// The dictionary for `lang`, loading it on first use. `en-US` means
+    /// `en_US`, and a bare `en` the first English dictionary found.
+    pub fn dictionary(&self, lang: &str) -> Result<Arc<Dictionary>, String> {
+        let lang = lang.replace('-', "_");
+        if let Some(dictionary) = self.loaded.lock().map_err(|e| format!("Lock error: {}", e))?.get(&lang) {
+            return Ok(dictionary.clone());
+        }
+        let languages = self.languages();
+        let found = languages
+            .iter()
+            .find(|found| **found == lang)
+            .or_else(|| languages.iter().find(|found| found.starts_with(&format!("{}_", lang))))
+            .ok_or_else(|| format!("No dictionary for {}", lang))?;
+        let dic = self
+            .search_dirs()
+            .into_iter()
+            .map(|dir| dir.join(format!("{}.dic", found)))
+            .find(|dic| dic.is_file())
+            .ok_or_else(|| format!("No dictionary for {}", lang))?;
+        let aff = fs::read(dic.with_extension("aff")).map_err(|e| format!("Failed to read dictionary: {}", e))?;
+        let words = fs::read(&dic).map_err(|e| format!("Failed to read dictionary: {}", e))?;
+        let dictionary = Arc::new(Dictionary::parse(&aff, &words));
+        self.loaded
+            .lock()
+            .map_err(|e| format!("Lock error: {}", e))?
+            .insert(lang, dictionary.clone());
+        Ok(dictionary)
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct Misspelling {
+    /// Byte offsets into the text
+    pub start: usize,
+    pub end: usize,
+    pub word: String,
+    pub suggestions: Vec<String>,
+}
+
+fn word_regex() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| Regex::new(r"\p{L}[\p{L}\p{M}'’]*").expect("valid word regex"))
+}
+
+fn url_regex() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| Regex::new(r"\b(?:[a-z][a-z0-9+.-]*://|www\.)\S+|\S+@\S+\.\w+").expect("valid url regex"))
+}
+
+fn dictionary_path(root: &Path) -> PathBuf {
+    root.join(crate::DATA_DIR).join(DICTIONARY_FILE)
+}
+
+/// Words added to the vault at `root`'s dictionary
+fn vault_words(root: &Path) -> Result<Vec<String>, String> {
+    match fs::read_to_string(dictionary_path(root)) {
+        Ok(raw) => Ok(raw.lines().map(str::trim).filter(|w| !w.is_empty()).map(String::from).collect()),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(format!("Failed to read {}: {}", DICTIONARY_FILE, e)),
+    }
+}
+
+/// Misspelled words in the prose of the markdown `text`. Code, links, URLs,
+/// tags and words with digits or underscores are skipped.
+pub fn check(text: &str, dictionary: &Dictionary, extra_words: &HashSet<String>) -> Vec<Misspelling> {
+    let mut misspellings = Vec::new();
+    let mut suggested: HashMap<String, Vec<String>> = HashMap::new();
+    for range in prose_ranges(text) {
+        let prose = &text[range.clone()];
+        let urls: Vec<_> = url_regex().find_iter(prose).map(|url| url.range()).collect();
+        for found in word_regex().find_iter(prose) {
+            let word = found.as_str().trim_end_matches(['\'', '’']);
+            let before = prose[..found.start()].chars().next_back();
+            let after = prose[found.end()..].chars().next();
+            let identifier = |c: char| c.is_ascii_digit() || c == '_';
+            if before.is_some_and(|c| identifier(c) || c == '#' || c == '@')
+                || after.is_some_and(identifier)
+                || urls.iter().any(|url| url.contains(&found.start()))
+            {
+                continue;
+            }
+            if extra_words.contains(&word.to_lowercase()) || dictionary.check(word) {
+                continue;
+            }
+            let suggestions = suggested
+                .entry(word.to_string())
+                .or_insert_with(|| dictionary.suggest(word))
+                .clone();
+            misspellings.push(Misspelling {
+                start: range.start + found.start(),
+                end: range.start + found.start() + word.len(),
+                word: word.to_string(),
+                suggestions,
+            });
+        }
+    }
+    misspellings
+}
+
+/// Misspelled words in the markdown `text` with suggestions, checked against
+/// the hunspell dictionary for `lang` and, with `root`, the vault's own
+/// words
+#[tauri::command(async)]
+pub fn check_text(
+    text: String,
+    lang: String,
+    root: Option<String>,
+    spellchecker: tauri::State<'_, Spellchecker>,
+) -> Result<Vec<Misspelling>, String> {
+    let dictionary = spellchecker.dictionary(&lang)?;
+    let extra_words = match &root {
+        Some(root) => vault_words(Path::new(root))?.iter().map(|w| w.to_lowercase()).collect(),
+        None => HashSet::new(),
+    };
+    Ok(check(&text, &dictionary, &extra_words))
+}
+
+/// Add `word` to the dictionary of the vault at `root`, kept in
+/// `.readmark/dictionary.txt`
+#[tauri::command]
+pub fn add_to_dictionary(
+    root: String,
+    word: String,
+    settings: tauri::State<'_, Mutex<SettingsStore>>,
+) -> Result<(), CommandError> {
+    let root = PathBuf::from(&root);
+    crate::ensure_dir(&root)?;
+    settings::check_writable(&settings, &root)?;
+    let word = word.trim();
+    if word.is_empty() || word.contains(char::is_whitespace) {
+        return Err(format!("Not a word: {:?}", word).into());
+    }
+    let mut words = vault_words(&root)?;
+    if words.iter().any(|w| w.to_lowercase() == word.to_lowercase()) {
+        return Ok(());
+    }
+    words.push(word.to_string());
+
+    let path = dictionary_path(&root);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+    }
+    let content: String = words.iter().map(|w| format!("{}\n", w)).collect();
+    crate::write_atomic(&path, content.as_bytes()).map_err(|e| format!("Failed to save dictionary: {}", e))?;
+    Ok(())
+}
+
+/// Languages with a hunspell dictionary installed, such as `en_US`
+#[tauri::command]
+pub fn list_dictionaries(spellchecker: tauri::State<'_, Spellchecker>) -> Vec<String> {
+    spellchecker.languages()
+}
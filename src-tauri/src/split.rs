@@ -0,0 +1,109 @@
+use std::fs;
+use std::ops::Range;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use crate::error::CommandError;
+use crate::import;
+use crate::markdown;
+use crate::metadata_cache::MetadataCache;
+use crate::note_index::NoteIndexRegistry;
+use crate::note_names;
+use crate::settings::{self, SettingsStore};
+
+/// A heading of the level split at, with everything up to the next heading
+/// of that level or above
+struct Part {
+    title: String,
+    range: Range<usize>,
+}
+
+fn parts(content: &str, level: u8) -> Vec<Part> {
+    let headings = markdown::headings(content);
+    headings
+        .iter()
+        .enumerate()
+        .filter(|(_, heading)| heading.level == level)
+        .map(|(i, heading)| {
+            let end = headings[i + 1..]
+                .iter()
+                .find(|next| next.level <= level)
+                .map_or(content.len(), |next| next.start);
+            Part {
+                title: heading.text.clone(),
+                range: heading.start..end,
+            }
+        })
+        .collect()
+}
+
+/// Remove files created before a failure
+fn remove_all(paths: &[PathBuf]) {
+    for path in paths {
+        let _ = fs::remove_file(path);
+    }
+}
+
+/// Split the note at `path` into one note per heading of `level` (1 for
+/// `#`), each holding its section and a copy of the note's frontmatter.
+/// The new notes are created next to the original, named after their
+/// headings, and each section in the original is replaced with a
+/// `[[link]]` to its note. Returns the paths of the new notes.
+#[tauri::command(async)]
+pub fn split_note(
+    path: String,
+    level: u8,
+    registry: tauri::State<'_, NoteIndexRegistry>,
+    settings: tauri::State<'_, Mutex<SettingsStore>>,
+    cache: tauri::State<'_, MetadataCache>,
+) -> Result<Vec<String>, CommandError> {
+    let path_buf = PathBuf::from(&path);
+    settings::check_writable(&settings, &path_buf)?;
+    if !(1..=6).contains(&level) {
+        return Err(format!("Heading level must be 1 to 6, not {}", level).into());
+    }
+    let content = fs::read_to_string(&path_buf).map_err(|e| format!("Failed to read file: {}", e))?;
+    let parts = parts(&content, level);
+    if parts.is_empty() {
+        return Err(format!("The note has no headings of level {}", level).into());
+    }
+    let dir = path_buf.parent().ok_or_else(|| format!("Not a file: {}", path))?;
+    let extension = path_buf.extension().map_or("md".into(), |e| e.to_string_lossy());
+    let frontmatter = &content[..markdown::body_start(&content)];
+
+    let mut created: Vec<PathBuf> = Vec::new();
+    for part in &parts {
+        let section = content[part.range.clone()].trim_end();
+        let note = format!("{}{}\n", frontmatter, section);
+        let new_path = match note_names::create_unique(dir, &import::safe_file_name(&part.title), &extension, &note) {
+            Ok(new_path) => new_path,
+            Err(e) => {
+                remove_all(&created);
+                return Err(e.into());
+            }
+        };
+        created.push(new_path);
+    }
+
+    let mut remaining = content.clone();
+    for (part, new_path) in parts.iter().zip(&created).rev() {
+        let name = new_path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+        remaining.replace_range(part.range.clone(), &format!("[[{}]]\n\n", name));
+    }
+
+    let remaining = format!("{}\n", remaining.trim_end());
+    if let Err(e) = crate::write_atomic(&path_buf, remaining.as_bytes()) {
+        remove_all(&created);
+        return Err(format!("Failed to write file: {}", e).into());
+    }
+    crate::note_written(&path_buf, Some(content.as_bytes()), remaining.as_bytes(), &registry, &settings, &cache);
+
+    let index = registry.for_path(&path_buf);
+    for path in &created {
+        if let Some(index) = &index {
+            index.update_path(path);
+        }
+        cache.invalidate(path);
+    }
+    Ok(created.iter().map(|p| p.to_string_lossy().to_string()).collect())
+}
@@ -0,0 +1,134 @@
+use pulldown_cmark::{Event, Parser, Tag, TagEnd};
+use serde::Serialize;
+use std::collections::HashSet;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use crate::links;
+use crate::markdown;
+use crate::note_index::NoteIndexRegistry;
+use crate::settings::SettingsStore;
+
+/// Average silent reading speed used for the reading time estimate
+const WORDS_PER_MINUTE: usize = 200;
+
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct NoteStats {
+    pub words: usize,
+    /// Characters of readable text, including spaces but not markup
+    pub characters: usize,
+    pub headings: usize,
+    pub links: usize,
+    pub reading_time_minutes: usize,
+}
+
+/// Chinese, Japanese and Korean text isn't separated by spaces, so each
+/// character counts as a word
+fn is_cjk(c: char) -> bool {
+    matches!(c,
+        '\u{3040}'..='\u{30FF}' | '\u{3400}'..='\u{4DBF}' | '\u{4E00}'..='\u{9FFF}' | '\u{AC00}'..='\u{D7AF}')
+}
+
+fn count_words(text: &str) -> usize {
+    text.split_whitespace()
+        .map(|token| {
+            let cjk = token.chars().filter(|c| is_cjk(*c)).count();
+            let other_runs = token.split(is_cjk).filter(|run| run.chars().any(char::is_alphanumeric)).count();
+            cjk + other_runs
+        })
+        .sum()
+}
+
+fn reading_time(words: usize) -> usize {
+    words.div_ceil(WORDS_PER_MINUTE)
+}
+
+/// The readable text of a note's body, with markup and code blocks dropped
+fn plain_text(content: &str) -> String {
+    let offset = markdown::body_start(content);
+    let mut text = String::new();
+    let mut in_code_block = false;
+    for event in Parser::new_ext(&content[offset..], markdown::parser_options()) {
+        match event {
+            Event::Start(Tag::CodeBlock(_)) => in_code_block = true,
+            Event::End(TagEnd::CodeBlock) => in_code_block = false,
+            Event::Text(t) | Event::Code(t) if !in_code_block => text.push_str(&t),
+            // Keep words in separate blocks and lines apart
+            Event::SoftBreak
+            | Event::HardBreak
+            | Event::End(TagEnd::Paragraph | TagEnd::Heading(_) | TagEnd::Item | TagEnd::TableCell)
+                if !text.ends_with(char::is_whitespace) =>
+            {
+                text.push(' ');
+            }
+            _ => {}
+        }
+    }
+    text
+}
+
+pub fn compute(content: &str) -> NoteStats {
+    let text = plain_text(content);
+    let words = count_words(&text);
+    NoteStats {
+        words,
+        characters: text.trim().chars().count(),
+        headings: markdown::headings(content).len(),
+        links: links::extract(content).len(),
+        reading_time_minutes: reading_time(words),
+    }
+}
+
+/// Word count, reading time and other figures for a single note
+#[tauri::command(async)]
+pub fn get_note_stats(path: String) -> Result<NoteStats, String> {
+    let content = fs::read_to_string(&path).map_err(|e| format!("Failed to read file: {}", e))?;
+    Ok(compute(&content))
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct VaultStats {
+    pub notes: usize,
+    pub attachments: usize,
+    pub words: usize,
+    pub characters: usize,
+    pub headings: usize,
+    pub links: usize,
+    /// Distinct tags, ignoring case
+    pub tags: usize,
+    pub tasks: usize,
+    pub completed_tasks: usize,
+    pub reading_time_minutes: usize,
+}
+
+/// Totals across every note in the vault at `root`
+#[tauri::command(async)]
+pub fn get_vault_stats(
+    root: String,
+    registry: tauri::State<'_, NoteIndexRegistry>,
+    settings: tauri::State<'_, Mutex<SettingsStore>>,
+) -> Result<VaultStats, String> {
+    let root = PathBuf::from(&root);
+    let index = registry.for_vault(&root, &settings)?;
+    let contents = index.contents()?;
+
+    let mut stats = VaultStats {
+        attachments: contents.attachments.iter().filter(|p| p.starts_with(&root)).count(),
+        ..VaultStats::default()
+    };
+    let mut tags = HashSet::new();
+    for (_, note) in contents.notes.iter().filter(|(path, _)| path.starts_with(&root)) {
+        stats.notes += 1;
+        stats.words += note.stats.words;
+        stats.characters += note.stats.characters;
+        stats.headings += note.stats.headings;
+        stats.links += note.stats.links;
+        stats.tasks += note.tasks.len();
+        stats.completed_tasks += note.tasks.iter().filter(|task| task.completed).count();
+        tags.extend(note.tags.iter().map(|tag| tag.to_lowercase()));
+    }
+    stats.tags = tags.len();
+    stats.reading_time_minutes = reading_time(stats.words);
+    Ok(stats)
+}
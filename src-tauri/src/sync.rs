@@ -0,0 +1,846 @@
+use base64::Engine;
+use chrono::Local;
+use quick_xml::events::Event;
+use quick_xml::Reader;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use ureq::http::{Method, Request, Response};
+
+use crate::error::CommandError;
+use crate::ignore_rules::{self, IgnoreMatcher};
+use crate::import::{self, ImportFailure};
+use crate::links;
+use crate::rename;
+use crate::settings::{self, SettingsStore, SyncConfig};
+
+/// Bump when the schema changes; older databases are dropped, which makes
+/// the next sync compare every file afresh
+const SCHEMA_VERSION: i32 = 1;
+
+/// Largest file transferred in either direction
+const MAX_FILE_BYTES: u64 = 256 * 1024 * 1024;
+
+const PROPFIND_BODY: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<d:propfind xmlns:d="DAV:"><d:prop><d:resourcetype/><d:getetag/><d:getlastmodified/><d:getcontentlength/></d:prop></d:propfind>"#;
+
+#[derive(Debug, Serialize)]
+pub struct SyncStatus {
+    pub root: String,
+    pub configured: bool,
+    pub url: Option<String>,
+    pub username: Option<String>,
+    pub syncing: bool,
+    /// Milliseconds since the Unix epoch of the last completed sync
+    pub last_sync: Option<u64>,
+    /// Why the last sync failed, if it did
+    pub last_error: Option<String>,
+    /// Files known to be in sync as of the last sync
+    pub tracked_files: usize,
+}
+
+/// A file changed on both sides since the last sync
+#[derive(Debug, Serialize)]
+pub struct SyncConflict {
+    /// The file, which now has the remote's version
+    pub path: String,
+    /// Where this device's version was kept
+    pub conflict_copy: String,
+}
+
+/// What a sync changed. Paths are local, absolute paths.
+#[derive(Debug, Default, Serialize)]
+pub struct SyncReport {
+    pub uploaded: Vec<String>,
+    pub downloaded: Vec<String>,
+    /// Files deleted here because they were deleted on the remote; they are
+    /// moved to the trash
+    pub deleted_local: Vec<String>,
+    /// Files deleted on the remote because they were deleted here
+    pub deleted_remote: Vec<String>,
+    pub conflicts: Vec<SyncConflict>,
+    /// Files that couldn't be synced; they are tried again next time
+    pub failed: Vec<ImportFailure>,
+}
+
+/// A file or folder in a WebDAV `multistatus` response
+#[derive(Debug, Default)]
+struct DavEntry {
+    /// Percent-decoded path part of the `href`
+    path: String,
+    is_dir: bool,
+    etag: Option<String>,
+    modified: Option<String>,
+    size: Option<String>,
+}
+
+impl DavEntry {
+    /// Identifies the content: the ETag, or the modification time and size
+    /// on servers without them
+    fn version(&self) -> String {
+        match &self.etag {
+            Some(etag) => etag.trim_start_matches("W/").trim_matches('"').to_string(),
+            None => format!(
+                "{}|{}",
+                self.modified.as_deref().unwrap_or(""),
+                self.size.as_deref().unwrap_or("")
+            ),
+        }
+    }
+}
+
+/// Files and folders of the remote, by path relative to the sync folder
+struct RemoteListing {
+    /// Path to content version
+    files: BTreeMap<String, String>,
+    dirs: HashSet<String>,
+}
+
+/// Path part of an `href`, which may be a full URL, percent-decoded
+fn href_path(href: &str) -> String {
+    let path = match href.split_once("://") {
+        Some((_, rest)) => rest.find('/').map_or("/", |at| &rest[at..]),
+        None => href,
+    };
+    links::percent_decode(path)
+}
+
+/// `path` with each segment percent-encoded for use in a URL
+pub fn encode_path(path: &str) -> String {
+    let mut encoded = String::with_capacity(path.len());
+    for byte in path.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+/// The entries of a PROPFIND `multistatus` response
+fn parse_multistatus(xml: &str) -> Result<Vec<DavEntry>, String> {
+    let mut reader = Reader::from_str(xml);
+    let mut entries = Vec::new();
+    let mut entry: Option<DavEntry> = None;
+    let mut text = String::new();
+
+    loop {
+        let event = reader.read_event().map_err(|e| format!("Invalid WebDAV response: {}", e))?;
+        match event {
+            Event::Start(start) => {
+                text.clear();
+                if start.local_name().as_ref() == b"response" {
+                    entry = Some(DavEntry::default());
+                }
+            }
+            Event::Empty(empty) if empty.local_name().as_ref() == b"collection" => {
+                if let Some(entry) = entry.as_mut() {
+                    entry.is_dir = true;
+                }
+            }
+            Event::Text(t) => text.push_str(&t.decode().map_err(|e| format!("Invalid WebDAV response: {}", e))?),
+            Event::CData(t) => text.push_str(&String::from_utf8_lossy(&t)),
+            Event::GeneralRef(r) => {
+                let name = r.decode().map_err(|e| format!("Invalid WebDAV response: {}", e))?;
+                let entity = format!("&{};", name);
+                text.push_str(&quick_xml::escape::unescape(&entity).unwrap_or(entity.as_str().into()));
+            }
+            Event::End(end) => {
+                let name = end.local_name();
+                if let Some(current) = entry.as_mut() {
+                    let value = Some(text.trim().to_string()).filter(|value| !value.is_empty());
+                    match name.as_ref() {
+                        b"href" => current.path = href_path(text.trim()),
+                        b"getetag" => current.etag = value,
+                        b"getlastmodified" => current.modified = value,
+                        b"getcontentlength" => current.size = value,
+                        b"collection" => current.is_dir = true,
+                        b"response" => entries.extend(entry.take()),
+                        _ => {}
+                    }
+                }
+                text.clear();
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    Ok(entries)
+}
+
+/// Minimal WebDAV client for one remote folder
+struct WebDav {
+    agent: ureq::Agent,
+    /// URL of the folder, ending in `/`
+    base: String,
+    /// Percent-decoded path part of `base`
+    base_path: String,
+    auth: Option<String>,
+}
+
+impl WebDav {
+    fn new(config: &SyncConfig) -> Result<Self, String> {
+        let url = config.url.trim();
+        if !url.starts_with("http://") && !url.starts_with("https://") {
+            return Err(format!("Not a WebDAV URL: {}", url));
+        }
+        // Re-encoded, since URLs are often pasted with raw spaces
+        let scheme_end = url.find("://").map_or(0, |at| at + 3);
+        let origin = &url[..url[scheme_end..].find('/').map_or(url.len(), |at| scheme_end + at)];
+        let base_path = format!("{}/", href_path(url).trim_end_matches('/'));
+        let base = format!("{}{}", origin, encode_path(&base_path));
+        let auth = (!config.username.is_empty()).then(|| {
+            let credentials = format!("{}:{}", config.username, config.password);
+            format!("Basic {}", base64::engine::general_purpose::STANDARD.encode(credentials))
+        });
+        let agent = ureq::Agent::config_builder()
+            .timeout_global(Some(Duration::from_secs(120)))
+            .allow_non_standard_methods(true)
+            .http_status_as_error(false)
+            .build()
+            .into();
+        Ok(WebDav {
+            agent,
+            base,
+            base_path,
+            auth,
+        })
+    }
+
+    /// Send a request for `path`, relative to the folder. Responses with an
+    /// error status are turned into errors.
+    fn send<B: ureq::AsSendBody>(
+        &self,
+        method: &str,
+        path: &str,
+        headers: &[(&str, &str)],
+        body: B,
+    ) -> Result<Response<ureq::Body>, String> {
+        let url = format!("{}{}", self.base, encode_path(path));
+        let method = Method::from_bytes(method.as_bytes()).map_err(|e| format!("Invalid method: {}", e))?;
+        let mut request = Request::builder().method(method.clone()).uri(&url);
+        if let Some(auth) = &self.auth {
+            request = request.header("Authorization", auth);
+        }
+        for (name, value) in headers {
+            request = request.header(*name, *value);
+        }
+        let request = request.body(body).map_err(|e| format!("Invalid request: {}", e))?;
+        let response = self
+            .agent
+            .run(request)
+            .map_err(|e| format!("{} {} failed: {}", method, url, e))?;
+
+        let status = response.status();
+        if status.is_client_error() || status.is_server_error() {
+            let reason = match status.as_u16() {
+                401 | 403 => "check the username and password".to_string(),
+                _ => status.canonical_reason().unwrap_or("error").to_lowercase(),
+            };
+            return Err(format!("{} {} failed: {} ({})", method, url, status.as_u16(), reason));
+        }
+        Ok(response)
+    }
+
+    /// PROPFIND `path` to the given depth
+    fn propfind(&self, path: &str, depth: &str) -> Result<Vec<DavEntry>, String> {
+        let headers = [("Depth", depth), ("Content-Type", "application/xml; charset=utf-8")];
+        let mut response = self.send("PROPFIND", path, &headers, PROPFIND_BODY)?;
+        let xml = response
+            .body_mut()
+            .with_config()
+            .limit(MAX_FILE_BYTES)
+            .read_to_string()
+            .map_err(|e| format!("Failed to read WebDAV response: {}", e))?;
+        parse_multistatus(&xml)
+    }
+
+    /// `entry`'s path relative to the folder. `None` for the folder itself,
+    /// and for paths that would lead out of the vault, such as `..%2Fx`.
+    fn relative(&self, entry: &DavEntry) -> Option<String> {
+        let path = entry.path.strip_prefix(&self.base_path)?.trim_matches('/');
+        settings::normalize_vault_path(path, "Remote file").ok().filter(|path| path != ".")
+    }
+
+    /// Every file in the folder and its subfolders, minus what `excluded`
+    /// rejects. Folders are listed one level at a time, since some servers
+    /// refuse `Depth: infinity`.
+    fn list(&self, excluded: impl Fn(&str, bool) -> bool) -> Result<RemoteListing, String> {
+        let mut listing = RemoteListing {
+            files: BTreeMap::new(),
+            dirs: HashSet::new(),
+        };
+        let mut pending = VecDeque::from([String::new()]);
+        while let Some(dir) = pending.pop_front() {
+            let prefix = if dir.is_empty() { String::new() } else { format!("{}/", dir) };
+            for entry in self.propfind(&prefix, "1")? {
+                let Some(path) = self.relative(&entry) else {
+                    continue;
+                };
+                if path == dir || excluded(&path, entry.is_dir) {
+                    continue;
+                }
+                if entry.is_dir {
+                    if listing.dirs.insert(path.clone()) {
+                        pending.push_back(path);
+                    }
+                } else {
+                    listing.files.insert(path, entry.version());
+                }
+            }
+        }
+        Ok(listing)
+    }
+
+    fn download(&self, path: &str) -> Result<Vec<u8>, String> {
+        let mut response = self.send("GET", path, &[], ())?;
+        response
+            .body_mut()
+            .with_config()
+            .limit(MAX_FILE_BYTES)
+            .read_to_vec()
+            .map_err(|e| format!("Failed to download {}: {}", path, e))
+    }
+
+    /// Upload `data` to `path`, creating missing folders. Returns the
+    /// version of the uploaded file.
+    fn upload(&self, path: &str, data: &[u8], dirs: &mut HashSet<String>) -> Result<String, String> {
+        let segments: Vec<&str> = path.split('/').collect();
+        for end in 1..segments.len() {
+            let parent = segments[..end].join("/");
+            if !dirs.contains(&parent) {
+                self.send("MKCOL", &format!("{}/", parent), &[], ())?;
+                dirs.insert(parent);
+            }
+        }
+
+        let response = self.send("PUT", path, &[("Content-Type", "application/octet-stream")], data)?;
+        let etag = ["oc-etag", "etag"]
+            .iter()
+            .find_map(|name| response.headers().get(*name)?.to_str().ok());
+        if let Some(etag) = etag {
+            return Ok(DavEntry {
+                etag: Some(etag.to_string()),
+                ..Default::default()
+            }
+            .version());
+        }
+        // Not every server reports the new ETag, so ask for it
+        self.propfind(path, "0")?
+            .first()
+            .map(DavEntry::version)
+            .ok_or_else(|| format!("No properties returned for {}", path))
+    }
+
+    fn delete(&self, path: &str) -> Result<(), String> {
+        match self.send("DELETE", path, &[], ()) {
+            Err(e) if e.contains("failed: 404") => Ok(()),
+            result => result.map(|_| ()),
+        }
+    }
+}
+
+/// A file's state as of the last sync
+struct Synced {
+    mtime: i64,
+    size: i64,
+    hash: String,
+    remote_version: String,
+}
+
+/// A local file as found by the scan
+struct LocalFile {
+    mtime: i64,
+    size: i64,
+}
+
+/// How one side of a file differs from its state at the last sync
+#[derive(Clone, Copy, PartialEq)]
+enum Change {
+    Unchanged,
+    /// Modified or created
+    Changed,
+    /// Deleted, or never there
+    Gone,
+}
+
+fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+fn init_schema(conn: &Connection) -> rusqlite::Result<()> {
+    let version: i32 = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+    if version != SCHEMA_VERSION {
+        conn.execute_batch(
+            "DROP TABLE IF EXISTS files;
+             DROP TABLE IF EXISTS meta;",
+        )?;
+    }
+    conn.execute_batch(&format!(
+        "CREATE TABLE IF NOT EXISTS files (
+             path TEXT PRIMARY KEY,
+             mtime INTEGER NOT NULL,
+             size INTEGER NOT NULL,
+             hash TEXT NOT NULL,
+             remote_version TEXT NOT NULL
+         );
+         CREATE TABLE IF NOT EXISTS meta (key TEXT PRIMARY KEY, value TEXT NOT NULL);
+         PRAGMA user_version = {};",
+        SCHEMA_VERSION
+    ))
+}
+
+fn meta(conn: &Connection, key: &str) -> rusqlite::Result<Option<String>> {
+    conn.query_row("SELECT value FROM meta WHERE key = ?1", [key], |row| row.get(0))
+        .optional()
+}
+
+fn set_meta(conn: &Connection, key: &str, value: &str) -> rusqlite::Result<()> {
+    conn.execute(
+        "INSERT INTO meta (key, value) VALUES (?1, ?2) ON CONFLICT(key) DO UPDATE SET value = ?2",
+        [key, value],
+    )
+    .map(|_| ())
+}
+
+fn load_synced(conn: &Connection) -> rusqlite::Result<BTreeMap<String, Synced>> {
+    let mut statement = conn.prepare("SELECT path, mtime, size, hash, remote_version FROM files")?;
+    let rows = statement.query_map([], |row| {
+        Ok((
+            row.get(0)?,
+            Synced {
+                mtime: row.get(1)?,
+                size: row.get(2)?,
+                hash: row.get(3)?,
+                remote_version: row.get(4)?,
+            },
+        ))
+    })?;
+    rows.collect()
+}
+
+/// Record that `path` is in sync: its local metadata and content hash, and
+/// the remote version
+fn record(conn: &Connection, root: &Path, path: &str, hash: &str, remote_version: &str) -> Result<(), String> {
+    let metadata = fs::metadata(root.join(path)).map_err(|e| format!("Failed to read metadata: {}", e))?;
+    conn.execute(
+        "INSERT OR REPLACE INTO files (path, mtime, size, hash, remote_version) VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![
+            path,
+            crate::mtime_millis(&metadata).unwrap_or(0) as i64,
+            metadata.len() as i64,
+            hash,
+            remote_version
+        ],
+    )
+    .map_err(|e| format!("Failed to update sync state: {}", e))?;
+    Ok(())
+}
+
+fn forget(conn: &Connection, path: &str) -> Result<(), String> {
+    conn.execute("DELETE FROM files WHERE path = ?1", [path])
+        .map_err(|e| format!("Failed to update sync state: {}", e))?;
+    Ok(())
+}
+
+/// Files in the vault that take part in sync, by path relative to `root`
+fn scan_local(root: &Path, ignore_globs: &[String]) -> Result<BTreeMap<String, LocalFile>, String> {
+    let mut files = BTreeMap::new();
+    for entry in ignore_rules::walker(root, ignore_globs)?.build().filter_map(|entry| entry.ok()) {
+        if !entry.file_type().is_some_and(|t| t.is_file()) {
+            continue;
+        }
+        let Ok(metadata) = entry.metadata() else {
+            continue;
+        };
+        files.insert(
+            rename::relative_path(root, entry.path()),
+            LocalFile {
+                mtime: crate::mtime_millis(&metadata).unwrap_or(0) as i64,
+                size: metadata.len() as i64,
+            },
+        );
+    }
+    Ok(files)
+}
+
+/// Where to keep this device's version of `path` when both sides changed
+/// it, named like the conflicted copies of other sync clients
+fn conflict_copy_path(path: &Path) -> PathBuf {
+    let stem = path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+    let suffix = Local::now().format("%Y-%m-%d %H%M%S");
+    let name = match path.extension() {
+        Some(extension) => format!("{} (conflicted copy {}).{}", stem, suffix, extension.to_string_lossy()),
+        None => format!("{} (conflicted copy {})", stem, suffix),
+    };
+    import::unique_path(path.parent().unwrap_or(Path::new("")), &name)
+}
+
+/// Save downloaded `data` to `path` in the vault
+fn write_local(root: &Path, path: &str, data: &[u8]) -> Result<(), String> {
+    let target = root.join(settings::normalize_vault_path(path, "Synced file")?);
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("Failed to create directory: {}", e))?;
+    }
+    crate::write_atomic(&target, data).map_err(|e| format!("Failed to write {}: {}", target.display(), e))
+}
+
+/// One sync of the vault at `root` with the remote in `config`
+struct SyncRun<'a> {
+    root: &'a Path,
+    dav: WebDav,
+    conn: Connection,
+    remote_dirs: HashSet<String>,
+    report: SyncReport,
+}
+
+impl SyncRun<'_> {
+    fn local_path(&self, path: &str) -> String {
+        self.root.join(path).to_string_lossy().to_string()
+    }
+
+    fn upload(&mut self, path: &str) -> Result<(), String> {
+        let data = fs::read(self.root.join(path)).map_err(|e| format!("Failed to read file: {}", e))?;
+        let version = self.dav.upload(path, &data, &mut self.remote_dirs)?;
+        record(&self.conn, self.root, path, &crate::content_hash(&data), &version)?;
+        self.report.uploaded.push(self.local_path(path));
+        Ok(())
+    }
+
+    fn download(&mut self, path: &str, version: &str) -> Result<(), String> {
+        let data = self.dav.download(path)?;
+        write_local(self.root, path, &data)?;
+        record(&self.conn, self.root, path, &crate::content_hash(&data), version)?;
+        self.report.downloaded.push(self.local_path(path));
+        Ok(())
+    }
+
+    /// Both sides changed `path`: take the remote's version unless the two
+    /// are the same, keeping this device's as a conflicted copy that is
+    /// uploaded too
+    fn reconcile(&mut self, path: &str, version: &str, local_hash: &str) -> Result<(), String> {
+        let data = self.dav.download(path)?;
+        let hash = crate::content_hash(&data);
+        if hash == local_hash {
+            return record(&self.conn, self.root, path, &hash, version);
+        }
+
+        let target = self.root.join(path);
+        let copy = conflict_copy_path(&target);
+        fs::rename(&target, &copy).map_err(|e| format!("Failed to keep conflicted copy: {}", e))?;
+        write_local(self.root, path, &data)?;
+        record(&self.conn, self.root, path, &hash, version)?;
+        self.report.conflicts.push(SyncConflict {
+            path: target.to_string_lossy().to_string(),
+            conflict_copy: copy.to_string_lossy().to_string(),
+        });
+
+        let copy_path = rename::relative_path(self.root, &copy);
+        self.upload(&copy_path)
+    }
+
+    fn sync_path(
+        &mut self,
+        path: &str,
+        local: Option<&LocalFile>,
+        remote: Option<&String>,
+        synced: Option<&Synced>,
+    ) -> Result<(), String> {
+        let mut local_hash = None;
+        let local_change = match (local, synced) {
+            (None, _) => Change::Gone,
+            (Some(file), Some(synced)) if file.mtime == synced.mtime && file.size == synced.size => {
+                Change::Unchanged
+            }
+            (Some(_), synced) => {
+                let data = fs::read(self.root.join(path)).map_err(|e| format!("Failed to read file: {}", e))?;
+                let hash = crate::content_hash(&data);
+                let change = match synced {
+                    Some(synced) if synced.hash == hash => Change::Unchanged,
+                    _ => Change::Changed,
+                };
+                local_hash = Some(hash);
+                change
+            }
+        };
+        let remote_change = match (remote, synced) {
+            (None, _) => Change::Gone,
+            (Some(version), Some(synced)) if *version == synced.remote_version => Change::Unchanged,
+            (Some(_), _) => Change::Changed,
+        };
+
+        match (local_change, remote_change) {
+            (Change::Unchanged, Change::Unchanged) => {
+                // Touched but not modified: remember the new mtime to skip
+                // hashing next time
+                match (local_hash, synced) {
+                    (Some(hash), Some(synced)) => {
+                        record(&self.conn, self.root, path, &hash, &synced.remote_version)
+                    }
+                    _ => Ok(()),
+                }
+            }
+            (Change::Changed, Change::Unchanged | Change::Gone) => self.upload(path),
+            (Change::Unchanged | Change::Gone, Change::Changed) => self.download(path, remote.map_or("", |v| v)),
+            (Change::Gone, Change::Unchanged) => {
+                self.dav.delete(path)?;
+                forget(&self.conn, path)?;
+                self.report.deleted_remote.push(self.local_path(path));
+                Ok(())
+            }
+            (Change::Unchanged, Change::Gone) => {
+                trash::delete(self.root.join(path)).map_err(|e| format!("Failed to move to trash: {}", e))?;
+                forget(&self.conn, path)?;
+                self.report.deleted_local.push(self.local_path(path));
+                Ok(())
+            }
+            (Change::Gone, Change::Gone) => forget(&self.conn, path),
+            (Change::Changed, Change::Changed) => {
+                self.reconcile(path, remote.map_or("", |v| v), local_hash.as_deref().unwrap_or(""))
+            }
+        }
+    }
+}
+
+/// Open the sync database at `db_path` for the remote at `url`. The state
+/// is dropped if it was tracking another remote.
+fn open_db(db_path: &Path, url: &str) -> Result<Connection, String> {
+    if let Some(parent) = db_path.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("Failed to create data directory: {}", e))?;
+    }
+    let conn = Connection::open(db_path).map_err(|e| format!("Failed to open sync state: {}", e))?;
+    init_schema(&conn).map_err(|e| format!("Failed to initialize sync state: {}", e))?;
+    let previous = meta(&conn, "url").map_err(|e| format!("Failed to read sync state: {}", e))?;
+    if previous.as_deref() != Some(url) {
+        conn.execute_batch("DELETE FROM files; DELETE FROM meta;")
+            .and_then(|_| set_meta(&conn, "url", url))
+            .map_err(|e| format!("Failed to reset sync state: {}", e))?;
+    }
+    Ok(conn)
+}
+
+/// Two-way sync of the vault at `root` with the WebDAV folder in `config`,
+/// leaving out files `ignore_globs` match on either side
+fn sync_vault(
+    root: &Path,
+    config: &SyncConfig,
+    ignore_globs: &[String],
+    db_path: &Path,
+) -> Result<SyncReport, String> {
+    let dav = WebDav::new(config)?;
+    let conn = open_db(db_path, &dav.base)?;
+
+    // Skip what the vault listing hides, on the remote too, so `.git` or
+    // `.readmark` folders are never synced
+    let matcher = IgnoreMatcher::new(root, ignore_globs, true)?;
+    let remote = dav.list(|path, is_dir| {
+        path.split('/').any(|segment| segment.starts_with('.')) || matcher.is_ignored(&root.join(path), is_dir)
+    })?;
+    let local = scan_local(root, ignore_globs)?;
+    let mut synced = load_synced(&conn).map_err(|e| format!("Failed to read sync state: {}", e))?;
+    // Rows saved before remote paths were checked may lead out of the vault
+    synced.retain(|path, _| settings::normalize_vault_path(path, "Synced file").is_ok_and(|p| p != "."));
+
+    let paths: BTreeSet<&String> = local.keys().chain(remote.files.keys()).chain(synced.keys()).collect();
+    let mut run = SyncRun {
+        root,
+        dav,
+        conn,
+        remote_dirs: remote.dirs,
+        report: SyncReport::default(),
+    };
+    for path in paths {
+        if let Err(error) = run.sync_path(path, local.get(path), remote.files.get(path), synced.get(path)) {
+            run.report.failed.push(ImportFailure {
+                item: run.local_path(path),
+                error,
+            });
+        }
+    }
+
+    set_meta(&run.conn, "last_sync", &now_millis().to_string())
+        .map_err(|e| format!("Failed to update sync state: {}", e))?;
+    Ok(run.report)
+}
+
+/// Sync state databases and the vaults being synced. Databases live in the
+/// app data directory, so they never end up in a synced folder themselves.
+pub struct SyncRegistry {
+    data_dir: PathBuf,
+    running: Mutex<HashSet<PathBuf>>,
+    /// Why the last sync of a vault failed
+    errors: Mutex<HashMap<PathBuf, String>>,
+}
+
+/// Marks a vault as syncing until dropped
+struct Running<'a> {
+    registry: &'a SyncRegistry,
+    root: PathBuf,
+}
+
+impl Drop for Running<'_> {
+    fn drop(&mut self) {
+        if let Ok(mut running) = self.registry.running.lock() {
+            running.remove(&self.root);
+        }
+    }
+}
+
+impl SyncRegistry {
+    pub fn new(data_dir: PathBuf) -> Self {
+        SyncRegistry {
+            data_dir,
+            running: Mutex::new(HashSet::new()),
+            errors: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn db_path(&self, root: &Path) -> PathBuf {
+        let key = crate::content_hash(root.to_string_lossy().as_bytes());
+        self.data_dir.join(format!("sync-{}.db", &key[..16]))
+    }
+
+    fn start(&self, root: &Path) -> Result<Running<'_>, String> {
+        let mut running = self.running.lock().map_err(|e| format!("Lock error: {}", e))?;
+        if !running.insert(root.to_path_buf()) {
+            return Err(format!("{} is already syncing", root.display()));
+        }
+        Ok(Running {
+            registry: self,
+            root: root.to_path_buf(),
+        })
+    }
+
+    fn set_error(&self, root: &Path, error: Option<String>) {
+        if let Ok(mut errors) = self.errors.lock() {
+            match error {
+                Some(error) => errors.insert(root.to_path_buf(), error),
+                None => errors.remove(root),
+            };
+        }
+    }
+
+    /// Forget what was synced with the previous remote of `root`
+    fn reset(&self, root: &Path) -> Result<(), String> {
+        let db_path = self.db_path(root);
+        if db_path.exists() {
+            fs::remove_file(&db_path).map_err(|e| format!("Failed to reset sync state: {}", e))?;
+        }
+        self.set_error(root, None);
+        Ok(())
+    }
+
+    fn status(&self, root: &Path, config: Option<&SyncConfig>) -> SyncStatus {
+        let (last_sync, tracked_files) = Connection::open_with_flags(
+            self.db_path(root),
+            rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY,
+        )
+        .ok()
+        .map(|conn| {
+            let last_sync = meta(&conn, "last_sync").ok().flatten().and_then(|v| v.parse().ok());
+            let tracked = conn
+                .query_row("SELECT COUNT(*) FROM files", [], |row| row.get::<_, i64>(0))
+                .unwrap_or(0);
+            (last_sync, tracked as usize)
+        })
+        .unwrap_or((None, 0));
+
+        SyncStatus {
+            root: root.to_string_lossy().to_string(),
+            configured: config.is_some(),
+            url: config.map(|c| c.url.clone()),
+            username: config.map(|c| c.username.clone()).filter(|u| !u.is_empty()),
+            syncing: self.running.lock().is_ok_and(|running| running.contains(root)),
+            last_sync,
+            last_error: self.errors.lock().ok().and_then(|errors| errors.get(root).cloned()),
+            tracked_files,
+        }
+    }
+}
+
+/// Set up (or, with no config, turn off) sync of the vault at `root` with a
+/// WebDAV folder. The folder is created if missing, which also checks the
+/// URL and credentials.
+#[tauri::command(async)]
+pub fn configure_sync(
+    root: String,
+    config: Option<SyncConfig>,
+    registry: tauri::State<'_, SyncRegistry>,
+    settings: tauri::State<'_, Mutex<SettingsStore>>,
+) -> Result<SyncStatus, String> {
+    let root_path = PathBuf::from(&root);
+    crate::ensure_dir(&root_path)?;
+
+    let config = match config {
+        Some(config) => {
+            let config = SyncConfig {
+                url: format!("{}/", config.url.trim().trim_end_matches('/')),
+                ..config
+            };
+            let dav = WebDav::new(&config)?;
+            if let Err(e) = dav.propfind("", "0") {
+                if !e.contains("failed: 404") {
+                    return Err(e);
+                }
+                dav.send("MKCOL", "", &[], ())?;
+            }
+            Some(config)
+        }
+        None => None,
+    };
+
+    let previous = settings::vault_settings(&settings, &root_path)?.sync;
+    if previous.as_ref().map(|c| &c.url) != config.as_ref().map(|c| &c.url) {
+        registry.reset(&root_path)?;
+    }
+    {
+        let mut store = settings.lock().map_err(|e| format!("Lock error: {}", e))?;
+        store.update(|settings| settings.vaults.entry(root).or_default().sync = config.clone())?;
+    }
+    Ok(registry.status(&root_path, config.as_ref()))
+}
+
+/// Sync the vault at `root` with its WebDAV folder now: changes on either
+/// side are copied over, and files changed on both are kept as conflicted
+/// copies
+#[tauri::command(async)]
+pub fn sync_now(
+    root: String,
+    registry: tauri::State<'_, SyncRegistry>,
+    settings: tauri::State<'_, Mutex<SettingsStore>>,
+) -> Result<SyncReport, CommandError> {
+    let root = PathBuf::from(&root);
+    crate::ensure_dir(&root)?;
+    settings::check_writable(&settings, &root)?;
+    let config = settings::vault_settings(&settings, &root)?
+        .sync
+        .ok_or_else(|| format!("Sync is not set up for {}", root.display()))?;
+
+    let ignore_globs = settings::ignore_globs(&settings, &[])?;
+
+    let _running = registry.start(&root)?;
+    let result = sync_vault(&root, &config, &ignore_globs, &registry.db_path(&root));
+    registry.set_error(&root, result.as_ref().err().cloned());
+    Ok(result?)
+}
+
+/// Whether the vault at `root` syncs, and how its last sync went
+#[tauri::command(async)]
+pub fn sync_status(
+    root: String,
+    registry: tauri::State<'_, SyncRegistry>,
+    settings: tauri::State<'_, Mutex<SettingsStore>>,
+) -> Result<SyncStatus, String> {
+    let root = PathBuf::from(&root);
+    let config = settings::vault_settings(&settings, &root)?.sync;
+    Ok(registry.status(&root, config.as_ref()))
+}
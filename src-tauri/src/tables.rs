@@ -0,0 +1,176 @@
+use pulldown_cmark::{Alignment, Event, Parser, Tag, TagEnd};
+use serde::Deserialize;
+use std::fs;
+
+use crate::encoding;
+use crate::encryption;
+use crate::format;
+use crate::import;
+use crate::markdown;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ColumnAlignment {
+    None,
+    Left,
+    Center,
+    Right,
+}
+
+impl From<ColumnAlignment> for Alignment {
+    fn from(alignment: ColumnAlignment) -> Self {
+        match alignment {
+            ColumnAlignment::None => Alignment::None,
+            ColumnAlignment::Left => Alignment::Left,
+            ColumnAlignment::Center => Alignment::Center,
+            ColumnAlignment::Right => Alignment::Right,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct CsvTableOptions {
+    /// Field separator; guessed from the first line when not given
+    pub delimiter: Option<char>,
+    /// Whether the first row holds the column names. Without it the table
+    /// gets an empty header row.
+    pub has_header: bool,
+    /// Alignment of each column. Columns not listed are right-aligned if
+    /// all their cells are numbers.
+    pub alignments: Vec<ColumnAlignment>,
+}
+
+impl Default for CsvTableOptions {
+    fn default() -> Self {
+        CsvTableOptions {
+            delimiter: None,
+            has_header: true,
+            alignments: Vec::new(),
+        }
+    }
+}
+
+/// The most frequent of `,`, `;`, tab and `|` in the first line of `csv`
+fn guess_delimiter(csv: &str) -> u8 {
+    let first = csv.lines().next().unwrap_or_default();
+    [b',', b';', b'\t', b'|']
+        .into_iter()
+        .max_by_key(|delimiter| (first.bytes().filter(|b| b == delimiter).count(), *delimiter == b','))
+        .unwrap_or(b',')
+}
+
+fn delimiter_byte(delimiter: char) -> Result<u8, String> {
+    match delimiter.is_ascii() {
+        true => Ok(delimiter as u8),
+        false => Err(format!("The delimiter must be an ASCII character: {}", delimiter)),
+    }
+}
+
+fn is_number(cell: &str) -> bool {
+    let cell = cell.trim().trim_start_matches(['-', '+']).trim_end_matches('%');
+    let cell = cell.trim_start_matches(['$', '€', '£']).replace(',', "");
+    !cell.is_empty() && cell.parse::<f64>().is_ok()
+}
+
+/// `csv` as a markdown table with its columns padded to line up. Quoted
+/// fields may hold delimiters, quotes and line breaks; pipes are escaped
+/// and line breaks become `<br>`.
+#[tauri::command]
+pub fn csv_to_markdown_table(csv: String, options: Option<CsvTableOptions>) -> Result<String, String> {
+    let options = options.unwrap_or_default();
+    let csv = csv.trim_start_matches('\u{feff}');
+    let delimiter = match options.delimiter {
+        Some(delimiter) => delimiter_byte(delimiter)?,
+        None => guess_delimiter(csv),
+    };
+    let mut reader = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .delimiter(delimiter)
+        .from_reader(csv.as_bytes());
+    let mut rows = Vec::new();
+    for record in reader.records() {
+        let record = record.map_err(|e| format!("Failed to parse CSV: {}", e))?;
+        rows.push(record.iter().map(import::table_cell).collect::<Vec<_>>());
+    }
+    if rows.is_empty() {
+        return Err("The CSV has no rows".to_string());
+    }
+
+    let width = rows.iter().map(Vec::len).max().unwrap_or(0).max(1);
+    if !options.has_header {
+        rows.insert(0, vec![String::new(); width]);
+    }
+    let alignments: Vec<Alignment> = (0..width)
+        .map(|i| match options.alignments.get(i) {
+            Some(alignment) => (*alignment).into(),
+            None => {
+                let cells: Vec<&String> =
+                    rows[1..].iter().filter_map(|row| row.get(i)).filter(|cell| !cell.is_empty()).collect();
+                match !cells.is_empty() && cells.iter().all(|cell| is_number(cell)) {
+                    true => Alignment::Right,
+                    false => Alignment::None,
+                }
+            }
+        })
+        .collect();
+
+    let line = |cells: &[String]| {
+        let padded: Vec<&str> = (0..width).map(|i| cells.get(i).map_or("", String::as_str)).collect();
+        format!("| {} |", padded.join(" | "))
+    };
+    let mut source = vec![line(&rows[0]), line(&vec!["---".to_string(); width])];
+    source.extend(rows[1..].iter().map(|row| line(row)));
+    Ok(format::align_table(&source.join("\n"), &alignments))
+}
+
+/// A markdown table cell's text as a CSV field: escaped pipes unescaped and
+/// `<br>` back to line breaks
+fn csv_field(cell: &str) -> String {
+    cell.trim().replace("\\|", "|").replace("<br>", "\n").replace("<br/>", "\n").replace("<br />", "\n")
+}
+
+/// The table at `table_index` (0-based, in document order) of the note at
+/// `path` as CSV, with its header row first. Cells keep their markdown.
+#[tauri::command(async)]
+pub fn markdown_table_to_csv(path: String, table_index: usize, delimiter: Option<char>) -> Result<String, String> {
+    let bytes = fs::read(&path).map_err(|e| format!("Failed to read file: {}", e))?;
+    if encryption::is_encrypted(&bytes) {
+        return Err(format!("Can't read tables of an encrypted note: {}", path));
+    }
+    let content = encoding::decode(&bytes).content;
+    let offset = markdown::body_start(&content);
+    let body = &content[offset..];
+
+    let mut tables = 0;
+    let mut in_table = false;
+    let mut rows: Vec<Vec<String>> = Vec::new();
+    for (event, range) in Parser::new_ext(body, markdown::parser_options()).into_offset_iter() {
+        match event {
+            Event::Start(Tag::Table(_)) => {
+                in_table = tables == table_index;
+                tables += 1;
+            }
+            Event::End(TagEnd::Table) if in_table => break,
+            Event::Start(Tag::TableHead | Tag::TableRow) if in_table => rows.push(Vec::new()),
+            Event::Start(Tag::TableCell) if in_table => {
+                if let Some(row) = rows.last_mut() {
+                    row.push(csv_field(&body[range]));
+                }
+            }
+            _ => {}
+        }
+    }
+    if rows.is_empty() {
+        return Err(format!("The note has {} tables, there is no table {}", tables, table_index));
+    }
+
+    let delimiter = delimiter_byte(delimiter.unwrap_or(','))?;
+    let mut writer = csv::WriterBuilder::new().flexible(true).delimiter(delimiter).from_writer(Vec::new());
+    for row in &rows {
+        writer.write_record(row).map_err(|e| format!("Failed to write CSV: {}", e))?;
+    }
+    let csv = writer.into_inner().map_err(|e| format!("Failed to write CSV: {}", e))?;
+    String::from_utf8(csv).map_err(|e| format!("Failed to write CSV: {}", e))
+}
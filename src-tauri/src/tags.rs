@@ -0,0 +1,323 @@
+use pulldown_cmark::{Event, Parser, Tag, TagEnd};
+use regex::Regex;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fs;
+use std::ops::Range;
+use std::path::PathBuf;
+use std::sync::{Mutex, OnceLock};
+
+use crate::error::CommandError;
+use crate::frontmatter;
+use crate::markdown;
+use crate::note_index::NoteIndexRegistry;
+use crate::settings::{self, SettingsStore};
+
+/// `#tag` or `#nested/tag`, at the start of the text or after whitespace or
+/// an opening bracket
+fn tag_regex() -> &'static Regex {
+    static TAG: OnceLock<Regex> = OnceLock::new();
+    TAG.get_or_init(|| Regex::new(r"(?:^|[\s(\[{,;])#([\p{L}\p{N}_\-/]+)").expect("tag regex is valid"))
+}
+
+/// Tags need at least one non-digit, so `#123` stays an issue number
+fn is_valid_tag(tag: &str) -> bool {
+    !tag.is_empty() && !tag.chars().all(|c| c.is_ascii_digit() || c == '/') && !tag.ends_with('/')
+}
+
+/// `#tags` in the body of `content`, with the byte range of each (without
+/// the `#`). Code spans and blocks are skipped.
+pub fn body_tags(content: &str) -> Vec<(Range<usize>, String)> {
+    let offset = markdown::body_start(content);
+    let mut tags = Vec::new();
+    let mut in_code_block = false;
+    for (event, range) in Parser::new_ext(&content[offset..], markdown::parser_options()).into_offset_iter() {
+        let text = match event {
+            Event::Start(Tag::CodeBlock(_)) => {
+                in_code_block = true;
+                continue;
+            }
+            Event::End(TagEnd::CodeBlock) => {
+                in_code_block = false;
+                continue;
+            }
+            Event::Text(text) if !in_code_block => text,
+            _ => continue,
+        };
+        // Text with escapes or entities doesn't map back onto the source
+        let start = offset + range.start;
+        if content.get(start..offset + range.end) != Some(&*text) {
+            continue;
+        }
+        for captures in tag_regex().captures_iter(&text) {
+            let Some(tag) = captures.get(1) else { continue };
+            // `\#word` is escaped in the source
+            let hash = start + tag.start() - 1;
+            if content[..hash].ends_with('\\') {
+                continue;
+            }
+            if is_valid_tag(tag.as_str()) {
+                tags.push((start + tag.start()..start + tag.end(), tag.as_str().to_string()));
+            }
+        }
+    }
+    tags
+}
+
+/// Tags listed in a frontmatter `tags` (or `tag`) field, either as a list or
+/// as a comma or space separated string. A leading `#` is dropped.
+pub fn frontmatter_tags(fields: &serde_json::Map<String, Value>) -> Vec<String> {
+    let values = ["tags", "tag"].iter().filter_map(|key| fields.get(*key));
+    let mut tags = Vec::new();
+    for value in values {
+        let items: Vec<String> = match value {
+            Value::Array(items) => items
+                .iter()
+                .filter_map(|item| match item {
+                    Value::String(s) => Some(s.clone()),
+                    Value::Number(n) => Some(n.to_string()),
+                    _ => None,
+                })
+                .collect(),
+            Value::String(s) => s.split([',', ' ']).map(str::to_string).collect(),
+            _ => Vec::new(),
+        };
+        tags.extend(
+            items
+                .iter()
+                .map(|tag| tag.trim().trim_start_matches('#').to_string())
+                .filter(|tag| !tag.is_empty()),
+        );
+    }
+    tags
+}
+
+/// All distinct tags of a note, frontmatter first, compared case-insensitively
+pub fn extract(content: &str) -> Vec<String> {
+    let mut tags: Vec<String> = Vec::new();
+    let all = frontmatter_tags(&frontmatter::fields(content))
+        .into_iter()
+        .chain(body_tags(content).into_iter().map(|(_, tag)| tag));
+    for tag in all {
+        if !tags.iter().any(|t| t.to_lowercase() == tag.to_lowercase()) {
+            tags.push(tag);
+        }
+    }
+    tags
+}
+
+/// Whether `tag` is `query` or nested under it, ignoring case
+fn tag_matches(tag: &str, query: &str) -> bool {
+    let tag = tag.to_lowercase();
+    let query = query.trim_start_matches('#').to_lowercase();
+    tag == query || tag.strip_prefix(&query).is_some_and(|rest| rest.starts_with('/'))
+}
+
+/// `tag` with `old` swapped for `new`, keeping any nested part
+/// (`project/alpha` becomes `work/alpha`). `None` if `tag` isn't affected.
+fn renamed(tag: &str, old: &str, new: &str) -> Option<String> {
+    if !tag_matches(tag, old) {
+        return None;
+    }
+    Some(format!("{}{}", new, &tag[old.len()..]))
+}
+
+/// Rename tags in a frontmatter `tags`/`tag` value, keeping its shape.
+/// Returns how many were renamed.
+fn rename_in_value(value: &mut Value, old: &str, new: &str) -> usize {
+    let mut count = 0;
+    let mut rename = |item: &str| {
+        let hash = if item.starts_with('#') { "#" } else { "" };
+        let renamed = renamed(item.trim_start_matches('#'), old, new)?;
+        count += 1;
+        Some(format!("{}{}", hash, renamed))
+    };
+    match value {
+        Value::Array(items) => {
+            for item in items {
+                if let Some(renamed) = item.as_str().and_then(&mut rename) {
+                    *item = Value::String(renamed);
+                }
+            }
+        }
+        Value::String(s) => {
+            // Keep the original separators of `tags: a, b c`
+            static ITEM: OnceLock<Regex> = OnceLock::new();
+            let item = ITEM.get_or_init(|| Regex::new(r"[^,\s]+").expect("tag item regex is valid"));
+            *s = item
+                .replace_all(s, |captures: &regex::Captures| {
+                    rename(&captures[0]).unwrap_or_else(|| captures[0].to_string())
+                })
+                .into_owned();
+        }
+        _ => {}
+    }
+    count
+}
+
+/// `content` with `old` (and tags nested under it) renamed to `new`, and the
+/// number of occurrences changed
+fn rename_in_note(content: &str, old: &str, new: &str) -> Result<(String, usize), String> {
+    let mut updated = content.to_string();
+    let mut count = 0;
+    for (range, tag) in body_tags(content).into_iter().rev() {
+        if let Some(renamed) = renamed(&tag, old, new) {
+            updated.replace_range(range, &renamed);
+            count += 1;
+        }
+    }
+
+    // The body edits come after the frontmatter, so the block is unchanged
+    if let Some(block) = frontmatter::split(&updated) {
+        let mut fields = frontmatter::parse(&block)?;
+        let mut in_fields = 0;
+        for key in ["tags", "tag"] {
+            if let Some(value) = fields.get_mut(key) {
+                in_fields += rename_in_value(value, old, new);
+            }
+        }
+        if in_fields > 0 {
+            updated = frontmatter::replace(&updated, &fields)?;
+            count += in_fields;
+        }
+    }
+
+    Ok((updated, count))
+}
+
+#[derive(Debug, Serialize)]
+pub struct TagCount {
+    pub tag: String,
+    /// Number of notes with the tag
+    pub count: usize,
+}
+
+/// Tags used in the vault at `root` with the number of notes for each, most
+/// used first
+#[tauri::command(async)]
+pub fn list_tags(
+    root: String,
+    registry: tauri::State<'_, NoteIndexRegistry>,
+    settings: tauri::State<'_, Mutex<SettingsStore>>,
+) -> Result<Vec<TagCount>, String> {
+    let root = PathBuf::from(&root);
+    let index = registry.for_vault(&root, &settings)?;
+    let contents = index.contents()?;
+
+    // Spelling of the first note seen with each tag, and the note count
+    let mut counts: HashMap<String, TagCount> = HashMap::new();
+    for (path, note) in &contents.notes {
+        if !path.starts_with(&root) {
+            continue;
+        }
+        for tag in &note.tags {
+            counts
+                .entry(tag.to_lowercase())
+                .or_insert_with(|| TagCount {
+                    tag: tag.clone(),
+                    count: 0,
+                })
+                .count += 1;
+        }
+    }
+
+    let mut tags: Vec<TagCount> = counts.into_values().collect();
+    tags.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.tag.to_lowercase().cmp(&b.tag.to_lowercase())));
+    Ok(tags)
+}
+
+#[derive(Debug, Serialize)]
+pub struct TagRename {
+    pub path: String,
+    /// Number of tags renamed in the note
+    pub occurrences: usize,
+}
+
+/// Rename `old` to `new` in the bodies and frontmatter of every note in the
+/// vault at `root`. Tags nested under `old` move along with it. With
+/// `dry_run`, nothing is written and the result previews the changes.
+#[tauri::command(async)]
+pub fn rename_tag(
+    root: String,
+    old: String,
+    new: String,
+    dry_run: Option<bool>,
+    registry: tauri::State<'_, NoteIndexRegistry>,
+    settings: tauri::State<'_, Mutex<SettingsStore>>,
+) -> Result<Vec<TagRename>, CommandError> {
+    let old = old.trim().trim_start_matches('#');
+    let new = new.trim().trim_start_matches('#');
+    if !is_valid_tag(old) {
+        return Err(format!("Invalid tag: {}", old).into());
+    }
+    let tag_chars = new.chars().all(|c| c.is_alphanumeric() || "_-/".contains(c));
+    if !is_valid_tag(new) || !tag_chars {
+        return Err(format!("Invalid tag: {}", new).into());
+    }
+
+    let root = PathBuf::from(&root);
+    if !dry_run.unwrap_or(false) {
+        settings::check_writable(&settings, &root)?;
+    }
+    let index = registry.for_vault(&root, &settings)?;
+    let mut paths: Vec<PathBuf> = index
+        .contents()?
+        .notes
+        .iter()
+        .filter(|(path, note)| path.starts_with(&root) && note.tags.iter().any(|t| tag_matches(t, old)))
+        .map(|(path, _)| path.clone())
+        .collect();
+    paths.sort();
+
+    let mut changes = Vec::new();
+    let mut failed = Vec::new();
+    for path in paths {
+        // The index may be behind the file on disk
+        let content =
+            fs::read_to_string(&path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        let (updated, occurrences) = rename_in_note(&content, old, new)?;
+        if occurrences == 0 {
+            continue;
+        }
+        if !dry_run.unwrap_or(false) {
+            if let Err(e) = crate::write_atomic(&path, updated.as_bytes()) {
+                failed.push(format!("{} ({})", path.display(), e));
+                continue;
+            }
+            index.update_path(&path);
+        }
+        changes.push(TagRename {
+            path: path.to_string_lossy().to_string(),
+            occurrences,
+        });
+    }
+
+    if !failed.is_empty() {
+        return Err(format!("Failed to rename the tag in {}", failed.join(", ")).into());
+    }
+    Ok(changes)
+}
+
+/// Notes in the vault at `root` tagged with `tag` or a tag nested under it
+/// (`#project` also finds `#project/alpha`)
+#[tauri::command(async)]
+pub fn find_notes_by_tag(
+    root: String,
+    tag: String,
+    registry: tauri::State<'_, NoteIndexRegistry>,
+    settings: tauri::State<'_, Mutex<SettingsStore>>,
+) -> Result<Vec<String>, String> {
+    let root = PathBuf::from(&root);
+    let index = registry.for_vault(&root, &settings)?;
+    let contents = index.contents()?;
+
+    let mut paths: Vec<String> = contents
+        .notes
+        .iter()
+        .filter(|(path, note)| path.starts_with(&root) && note.tags.iter().any(|t| tag_matches(t, &tag)))
+        .map(|(path, _)| path.to_string_lossy().to_string())
+        .collect();
+    paths.sort();
+    Ok(paths)
+}
@@ -0,0 +1,329 @@
+use chrono::{Local, NaiveDate, NaiveTime};
+use pulldown_cmark::{Event, Parser};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::ops::Range;
+use std::path::PathBuf;
+use std::sync::{Mutex, OnceLock};
+
+use crate::blocks;
+use crate::dates;
+use crate::error::CommandError;
+use crate::encoding;
+use crate::markdown;
+use crate::metadata_cache::MetadataCache;
+use crate::note_index::NoteIndexRegistry;
+use crate::search::{line_of, line_starts};
+use crate::settings::{self, SettingsStore};
+use crate::WriteResult;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Task {
+    /// 1-based line number of the checkbox
+    pub line: usize,
+    /// Text after the checkbox
+    pub text: String,
+    pub completed: bool,
+    /// `YYYY-MM-DD` from `📅 2024-05-01`, `due:2024-05-01` or `@due(2024-05-01)`
+    pub due: Option<String>,
+    /// `HH:MM` from a time after the date, as in `📅 2024-05-01 14:30` or
+    /// `due:2024-05-01T14:30`
+    pub due_time: Option<String>,
+}
+
+fn due_regex() -> &'static Regex {
+    static DUE: OnceLock<Regex> = OnceLock::new();
+    DUE.get_or_init(|| {
+        Regex::new(r"(?:📅\s*|\bdue:\s*|@due\()(\d{4}-\d{2}-\d{2})(?:[T ]([01]\d|2[0-3]):([0-5]\d)\b)?")
+            .expect("due date regex is valid")
+    })
+}
+
+/// A task's text without its due date
+pub fn strip_due(text: &str) -> String {
+    let mut stripped = String::new();
+    let mut last = 0;
+    for due in due_regex().find_iter(text) {
+        stripped.push_str(&text[last..due.start()]);
+        last = due.end();
+        if due.as_str().starts_with("@due(") && text[last..].starts_with(')') {
+            last += 1;
+        }
+    }
+    stripped.push_str(&text[last..]);
+    stripped.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// The checkbox at the start of a task list item line, such as `  - [ ]` or
+/// `> 1. [x]`, capturing the mark
+fn checkbox_regex() -> &'static Regex {
+    static CHECKBOX: OnceLock<Regex> = OnceLock::new();
+    CHECKBOX.get_or_init(|| {
+        Regex::new(r"^[ \t>]*(?:[-*+]|\d+[.)])[ \t]+\[([ xX])\]").expect("checkbox regex is valid")
+    })
+}
+
+/// Every `- [ ]` / `- [x]` item in `content`. Checkboxes in code blocks
+/// aren't tasks.
+pub fn extract(content: &str) -> Vec<Task> {
+    let offset = markdown::body_start(content);
+    let starts = line_starts(content);
+    let mut tasks = Vec::new();
+    for (event, range) in Parser::new_ext(&content[offset..], markdown::parser_options()).into_offset_iter() {
+        let Event::TaskListMarker(completed) = event else {
+            continue;
+        };
+        let line = line_of(&starts, offset + range.start);
+        let line_end = content[offset + range.end..]
+            .find('\n')
+            .map_or(content.len(), |i| offset + range.end + i);
+        let text = content[offset + range.end..line_end].trim().to_string();
+        let captures = due_regex().captures(&text);
+        let due = captures.as_ref().map(|captures| captures[1].to_string());
+        let due_time = captures
+            .as_ref()
+            .and_then(|captures| Some(format!("{}:{}", captures.get(2)?.as_str(), captures.get(3)?.as_str())));
+        tasks.push(Task {
+            line,
+            text,
+            completed,
+            due,
+            due_time,
+        });
+    }
+    tasks
+}
+
+#[derive(Debug, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct TaskFilter {
+    /// Only open (`false`) or only completed (`true`) tasks
+    pub completed: Option<bool>,
+    /// Only tasks due on or before this `YYYY-MM-DD` date
+    pub due_before: Option<String>,
+    /// Only tasks whose text contains this, ignoring case
+    pub query: Option<String>,
+}
+
+impl TaskFilter {
+    fn matches(&self, task: &Task) -> bool {
+        if self.completed.is_some_and(|completed| completed != task.completed) {
+            return false;
+        }
+        if let Some(before) = &self.due_before {
+            // Dates are zero-padded, so they compare as strings
+            if task.due.as_ref().is_none_or(|due| due > before) {
+                return false;
+            }
+        }
+        match &self.query {
+            Some(query) => task.text.to_lowercase().contains(&query.to_lowercase()),
+            None => true,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct VaultTask {
+    pub path: String,
+    #[serde(flatten)]
+    pub task: Task,
+}
+
+/// Tasks from every note in the vault at `root`, by file and line
+#[tauri::command(async)]
+pub fn list_tasks(
+    root: String,
+    filter: Option<TaskFilter>,
+    registry: tauri::State<'_, NoteIndexRegistry>,
+    settings: tauri::State<'_, Mutex<SettingsStore>>,
+) -> Result<Vec<VaultTask>, String> {
+    let filter = filter.unwrap_or_default();
+    let root = PathBuf::from(&root);
+    let index = registry.for_vault(&root, &settings)?;
+    let contents = index.contents()?;
+
+    let mut tasks: Vec<VaultTask> = contents
+        .notes
+        .iter()
+        .filter(|(path, _)| path.starts_with(&root))
+        .flat_map(|(path, note)| {
+            note.tasks.iter().filter(|task| filter.matches(task)).map(|task| VaultTask {
+                path: path.to_string_lossy().to_string(),
+                task: task.clone(),
+            })
+        })
+        .collect();
+    tasks.sort_by(|a, b| a.path.cmp(&b.path).then(a.task.line.cmp(&b.task.line)));
+    Ok(tasks)
+}
+
+/// Replace the task on `line` (1-based) of the note at `path` with what
+/// `edit` makes of it, given the line and the range of its checkbox mark,
+/// leaving the rest of the file as it was. Takes the `expected_mtime` /
+/// `expected_hash` conflict guard of `write_text_file`, so a line number
+/// from a stale listing can't edit another task.
+#[allow(clippy::too_many_arguments)]
+fn edit_task_line(
+    path: &str,
+    line: usize,
+    expected_mtime: Option<u64>,
+    expected_hash: Option<&str>,
+    registry: &NoteIndexRegistry,
+    settings: &Mutex<SettingsStore>,
+    cache: &MetadataCache,
+    edit: impl FnOnce(&str, Range<usize>) -> Result<String, String>,
+) -> Result<WriteResult, CommandError> {
+    let path_buf = PathBuf::from(path);
+    settings::check_writable(settings, &path_buf)?;
+    crate::check_for_conflict(&path_buf, expected_mtime, expected_hash)?;
+    let (previous, mut content) = crate::read_note(&path_buf)?;
+
+    let starts = line_starts(&content);
+    let start = *line
+        .checked_sub(1)
+        .and_then(|i| starts.get(i))
+        .ok_or_else(|| format!("Line {} is out of range", line))?;
+    let end = content[start..].find('\n').map_or(content.len(), |i| start + i);
+
+    let mark = checkbox_regex()
+        .captures(&content[start..end])
+        .and_then(|captures| captures.get(1))
+        .ok_or_else(|| format!("Line {} is not a task", line))?
+        .range();
+    let edited = edit(&content[start..end], mark)?;
+    content.replace_range(start..end, &edited);
+
+    let bytes = encoding::encode(&content, encoding::format_of(&previous))?;
+    Ok(crate::write_note(&path_buf, Some(&previous), &bytes, registry, settings, cache)?)
+}
+
+/// Check (`Some(true)`), uncheck or flip (`None`) the checkbox on `line`
+/// (1-based) of the note at `path`, with `edit_task_line`'s conflict guard
+#[allow(clippy::too_many_arguments)]
+pub fn set_checkbox(
+    path: &str,
+    line: usize,
+    completed: Option<bool>,
+    expected_mtime: Option<u64>,
+    expected_hash: Option<&str>,
+    registry: &NoteIndexRegistry,
+    settings: &Mutex<SettingsStore>,
+    cache: &MetadataCache,
+) -> Result<WriteResult, CommandError> {
+    edit_task_line(path, line, expected_mtime, expected_hash, registry, settings, cache, |text, mark| {
+        let checked = completed.unwrap_or(&text[mark.clone()] == " ");
+        let mut text = text.to_string();
+        text.replace_range(mark, if checked { "x" } else { " " });
+        Ok(text)
+    })
+}
+
+/// `text`, a task's line after its checkbox at `after`, with its due date
+/// set to `due`, or removed if `None`. A due date it has keeps its syntax; a
+/// new one is added as `📅 YYYY-MM-DD` before a trailing `^block-id`.
+fn with_due(text: &str, after: usize, due: Option<(NaiveDate, Option<NaiveTime>)>) -> String {
+    let date = due.map(|(date, time)| {
+        let time = time.map(|time| time.format("%H:%M").to_string());
+        (date.format("%Y-%m-%d").to_string(), time)
+    });
+    let Some(found) = due_regex().find_at(text, after) else {
+        let Some((date, time)) = date else {
+            return text.to_string();
+        };
+        let trimmed = text.trim_end();
+        let at = blocks::block_id_regex().find(trimmed).map_or(trimmed.len(), |id| id.start());
+        let at = trimmed[..at].trim_end().len();
+        let time = time.map(|time| format!(" {}", time)).unwrap_or_default();
+        return format!("{} 📅 {}{}{}", &text[..at], date, time, &text[at..]);
+    };
+
+    let old = found.as_str();
+    let (mut start, mut end) = (found.start(), found.end());
+    let replacement = match date {
+        Some((date, time)) => {
+            let separator = if old.starts_with("due:") { "T" } else { " " };
+            let time = time.map(|time| format!("{}{}", separator, time)).unwrap_or_default();
+            if old.starts_with("due:") {
+                format!("due:{}{}", date, time)
+            } else if old.starts_with("@due(") {
+                format!("@due({}{}", date, time)
+            } else {
+                format!("📅 {}{}", date, time)
+            }
+        }
+        None => {
+            if old.starts_with("@due(") && text[end..].starts_with(')') {
+                end += 1;
+            }
+            // The space before it goes too
+            start = text[..start].trim_end().len().max(after);
+            String::new()
+        }
+    };
+    format!("{}{}{}", &text[..start], replacement, &text[end..])
+}
+
+/// Set the due date of the task on `line` (1-based) of the note at `path` to
+/// `when`, read like `parse_date`: `2024-06-01`, `friday`, `in 2 weeks` or
+/// `tomorrow at 9am`. An empty `when` removes the due date. Takes the same
+/// `expected_mtime` / `expected_hash` conflict guard as `write_text_file`.
+#[tauri::command(async)]
+#[allow(clippy::too_many_arguments)] // Mostly state injected by tauri
+pub fn set_task_due(
+    path: String,
+    line: usize,
+    when: String,
+    expected_mtime: Option<u64>,
+    expected_hash: Option<String>,
+    registry: tauri::State<'_, NoteIndexRegistry>,
+    settings: tauri::State<'_, Mutex<SettingsStore>>,
+    cache: tauri::State<'_, MetadataCache>,
+) -> Result<WriteResult, CommandError> {
+    let due = match when.trim() {
+        "" => None,
+        when => Some(dates::parse(when, Local::now().date_naive())?),
+    };
+    edit_task_line(
+        &path,
+        line,
+        expected_mtime,
+        expected_hash.as_deref(),
+        &registry,
+        &settings,
+        &cache,
+        |text, mark| Ok(with_due(text, mark.end + 1, due)),
+    )
+}
+
+/// Flip the checkbox on `line` (1-based) of the note at `path`, leaving the
+/// rest of the file as it was. Takes the same conflict guard as
+/// `write_text_file`.
+#[tauri::command(async)]
+pub fn toggle_task(
+    path: String,
+    line: usize,
+    expected_mtime: Option<u64>,
+    expected_hash: Option<String>,
+    registry: tauri::State<'_, NoteIndexRegistry>,
+    settings: tauri::State<'_, Mutex<SettingsStore>>,
+    cache: tauri::State<'_, MetadataCache>,
+) -> Result<WriteResult, CommandError> {
+    set_checkbox(&path, line, None, expected_mtime, expected_hash.as_deref(), &registry, &settings, &cache)
+}
+
+/// Check the checkbox on `line` (1-based) of the note at `path`; a task
+/// that is already done stays done. Takes the same conflict guard as
+/// `write_text_file`.
+#[tauri::command(async)]
+pub fn complete_task(
+    path: String,
+    line: usize,
+    expected_mtime: Option<u64>,
+    expected_hash: Option<String>,
+    registry: tauri::State<'_, NoteIndexRegistry>,
+    settings: tauri::State<'_, Mutex<SettingsStore>>,
+    cache: tauri::State<'_, MetadataCache>,
+) -> Result<WriteResult, CommandError> {
+    set_checkbox(&path, line, Some(true), expected_mtime, expected_hash.as_deref(), &registry, &settings, &cache)
+}
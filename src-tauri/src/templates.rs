@@ -0,0 +1,282 @@
+use chrono::{Datelike, Local, NaiveDate, NaiveTime, Timelike};
+use regex::Regex;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+
+use crate::error::CommandError;
+use crate::metadata_cache::MetadataCache;
+use crate::settings::{self, SettingsStore};
+use crate::vault_config;
+use crate::FileEntry;
+
+/// Tokens of a date pattern, longest first so `YYYY` wins over `YY`
+const DATE_TOKENS: &[&str] = &[
+    "YYYY", "YY", "MMMM", "MMM", "MM", "M", "DDDD", "DD", "D", "dddd", "ddd", "WW", "W", "E", "Q", "HH", "H", "mm",
+    "ss",
+];
+
+#[derive(Debug, Serialize)]
+pub struct TemplateInfo {
+    /// Path within the templates folder without the extension, e.g. `Meeting`
+    /// or `Work/Standup`
+    pub name: String,
+    pub path: String,
+}
+
+/// `{{...}}` placeholders
+fn placeholder_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r"\{\{\s*([^{}]*?)\s*\}\}").unwrap())
+}
+
+fn format_token(token: &str, date: NaiveDate, time: NaiveTime) -> String {
+    match token {
+        "YYYY" => format!("{:04}", date.year()),
+        "YY" => format!("{:02}", date.year().rem_euclid(100)),
+        "MMMM" => date.format("%B").to_string(),
+        "MMM" => date.format("%b").to_string(),
+        "MM" => format!("{:02}", date.month()),
+        "M" => date.month().to_string(),
+        "DDDD" => format!("{:03}", date.ordinal()),
+        "DD" => format!("{:02}", date.day()),
+        "D" => date.day().to_string(),
+        "dddd" => date.format("%A").to_string(),
+        "ddd" => date.format("%a").to_string(),
+        "WW" => format!("{:02}", date.iso_week().week()),
+        "W" => date.iso_week().week().to_string(),
+        "E" => date.weekday().number_from_monday().to_string(),
+        "Q" => date.month().div_ceil(3).to_string(),
+        "HH" => format!("{:02}", time.hour()),
+        "H" => time.hour().to_string(),
+        "mm" => format!("{:02}", time.minute()),
+        "ss" => format!("{:02}", time.second()),
+        _ => String::new(),
+    }
+}
+
+/// `pattern`, such as `YYYY-MM-DD` or `dddd, MMMM D`, filled in for `date`
+/// and `time`. `None` if it has letters that aren't a date token, i.e. it
+/// isn't a date pattern.
+pub fn format_date_pattern(pattern: &str, date: NaiveDate, time: NaiveTime) -> Option<String> {
+    let mut formatted = String::new();
+    let mut rest = pattern;
+    let mut any_token = false;
+    while let Some(c) = rest.chars().next() {
+        if let Some(token) = DATE_TOKENS.iter().find(|token| rest.starts_with(**token)) {
+            formatted.push_str(&format_token(token, date, time));
+            rest = &rest[token.len()..];
+            any_token = true;
+        } else if c.is_alphabetic() {
+            return None;
+        } else {
+            formatted.push(c);
+            rest = &rest[c.len_utf8()..];
+        }
+    }
+    any_token.then_some(formatted)
+}
+
+/// `text` with its placeholders filled in:
+///
+/// - `{{date}}` and `{{time}}` as `YYYY-MM-DD` and `HH:mm`, or in a pattern
+///   of their own with `{{date:dddd, MMMM D}}`
+/// - `{{title}}` as `title`
+/// - a name in `vars` as its value; these take precedence over the above
+/// - a bare date pattern such as `{{YYYY-MM-DD}}`
+///
+/// Other placeholders are left as they are.
+pub fn render(
+    text: &str,
+    vars: &HashMap<String, String>,
+    title: &str,
+    date: NaiveDate,
+    time: NaiveTime,
+) -> String {
+    placeholder_regex()
+        .replace_all(text, |caps: &regex::Captures| {
+            let placeholder = &caps[1];
+            if let Some(value) = vars.get(placeholder) {
+                return value.clone();
+            }
+            let (name, pattern) = match placeholder.split_once(':') {
+                Some((name, pattern)) => (name.trim(), Some(pattern.trim())),
+                None => (placeholder, None),
+            };
+            let filled = match (name, pattern) {
+                ("date", None) => Some(date.format("%Y-%m-%d").to_string()),
+                ("time", None) => Some(time.format("%H:%M").to_string()),
+                ("date" | "time", Some(pattern)) => format_date_pattern(pattern, date, time),
+                ("title", None) => Some(title.to_string()),
+                _ => format_date_pattern(placeholder, date, time),
+            };
+            filled.unwrap_or_else(|| caps[0].to_string())
+        })
+        .into_owned()
+}
+
+/// A regex for the text `pattern` fills in, with a group for each of its
+/// year, month (`m`, or `n` for its name) and day tokens; `parts` gets which
+/// of them each group is
+fn date_pattern_regex(pattern: &str, parts: &mut Vec<char>) -> Option<String> {
+    let mut regex = String::new();
+    let mut rest = pattern;
+    while let Some(c) = rest.chars().next() {
+        if let Some(token) = DATE_TOKENS.iter().find(|token| rest.starts_with(**token)) {
+            let part = match *token {
+                "YYYY" => Some('y'),
+                "MM" | "M" => Some('m'),
+                "MMMM" | "MMM" if !parts.contains(&'m') => Some('n'),
+                "DD" | "D" => Some('d'),
+                _ => None,
+            };
+            match part {
+                Some(part) if !parts.contains(&part) => {
+                    parts.push(part);
+                    regex.push_str(match part {
+                        'y' => r"(\d{4})",
+                        'n' => r"(\p{L}+)\.?",
+                        _ => r"(\d{1,2})",
+                    });
+                }
+                _ if token.starts_with(['M', 'd']) && token.len() > 2 => regex.push_str(r"\p{L}+\.?"),
+                _ => regex.push_str(r"\d+"),
+            }
+            rest = &rest[token.len()..];
+        } else if c.is_alphabetic() {
+            return None;
+        } else {
+            regex.push_str(&regex::escape(&c.to_string()));
+            rest = &rest[c.len_utf8()..];
+        }
+    }
+    Some(regex)
+}
+
+/// The month `name`, in full or short, is
+fn month_of_name(name: &str) -> Option<u32> {
+    let name = name.to_lowercase();
+    (1..=12).find(|&month| {
+        NaiveDate::from_ymd_opt(2000, month, 1)
+            .is_some_and(|date| ["%B", "%b"].iter().any(|f| date.format(f).to_string().to_lowercase() == name))
+    })
+}
+
+/// The date `template`, a path template like `daily_notes_format`, renders
+/// to `path` for; `None` if `path` isn't one of its paths or the template
+/// has no year, month and day in it
+pub fn path_date(template: &str, path: &str) -> Option<NaiveDate> {
+    let mut regex = String::from("^");
+    let mut parts = Vec::new();
+    let mut last = 0;
+    for caps in placeholder_regex().captures_iter(template) {
+        let whole = caps.get(0)?;
+        regex.push_str(&regex::escape(&template[last..whole.start()]));
+        last = whole.end();
+        let placeholder = &caps[1];
+        let pattern = match placeholder.split_once(':') {
+            Some((name, pattern)) if name.trim() == "date" => pattern.trim(),
+            _ if placeholder == "date" => "YYYY-MM-DD",
+            Some(_) => return None,
+            None => placeholder,
+        };
+        regex.push_str(&date_pattern_regex(pattern, &mut parts)?);
+    }
+    regex.push_str(&regex::escape(&template[last..]));
+    regex.push('$');
+
+    let caps = Regex::new(&regex).ok()?.captures(path)?;
+    let part = |part: char| -> Option<&str> {
+        let group = parts.iter().position(|p| *p == part)?;
+        Some(caps.get(group + 1)?.as_str())
+    };
+    let number = |name: char| part(name)?.parse::<u32>().ok();
+    let month = number('m').or_else(|| month_of_name(part('n')?))?;
+    let date = NaiveDate::from_ymd_opt(number('y')? as i32, month, number('d')?)?;
+    // Tokens such as weekdays have to agree with the date too
+    (render(template, &HashMap::new(), "", date, NaiveTime::MIN) == path).then_some(date)
+}
+
+/// The templates folder of the vault at `root`
+fn templates_dir(root: &Path, settings: &Mutex<SettingsStore>) -> Result<PathBuf, String> {
+    let config = vault_config::resolve(root, settings)?;
+    Ok(root.join(config.templates_dir))
+}
+
+/// Notes in the vault's templates folder, by name
+#[tauri::command(async)]
+pub fn list_templates(
+    root: String,
+    settings: tauri::State<'_, Mutex<SettingsStore>>,
+) -> Result<Vec<TemplateInfo>, String> {
+    let root = PathBuf::from(&root);
+    crate::ensure_dir(&root)?;
+    let dir = templates_dir(&root, &settings)?;
+    if !dir.is_dir() {
+        return Ok(Vec::new());
+    }
+    let note_extensions = settings::note_extensions(&settings)?;
+
+    let mut templates = Vec::new();
+    for entry in crate::ignore_rules::walker(&dir, &[])?.build().filter_map(|entry| entry.ok()) {
+        let path = entry.path();
+        if !entry.file_type().is_some_and(|t| t.is_file()) || !settings::has_note_extension(path, &note_extensions) {
+            continue;
+        }
+        let relative = path.strip_prefix(&dir).unwrap_or(path).with_extension("");
+        templates.push(TemplateInfo {
+            name: relative.to_string_lossy().replace('\\', "/"),
+            path: path.to_string_lossy().to_string(),
+        });
+    }
+    templates.sort_by_key(|template| template.name.to_lowercase());
+    Ok(templates)
+}
+
+/// Write `content` to the new file `path`, creating its folders. Fails if
+/// the file exists, unless `allow_existing`.
+pub fn create_file(path: &Path, content: &str, allow_existing: bool, cache: &MetadataCache) -> Result<(), String> {
+    let created = crate::first_missing_ancestor(path);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("Failed to create directory: {}", e))?;
+    }
+    // create_new makes the existence check and the creation a single step
+    match fs::OpenOptions::new().write(true).create_new(true).open(path) {
+        Ok(mut file) => {
+            file.write_all(content.as_bytes())
+                .map_err(|e| format!("Failed to write file: {}", e))?;
+        }
+        Err(e) if e.kind() == io::ErrorKind::AlreadyExists && allow_existing => {}
+        Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {
+            return Err(format!("File already exists: {}", path.display()));
+        }
+        Err(e) => return Err(format!("Failed to create file: {}", e)),
+    }
+    cache.invalidate(created.as_deref().unwrap_or(path));
+    Ok(())
+}
+
+/// Create the note `dest` from the note `template`, refusing to overwrite
+/// an existing file. Placeholders are filled in as described in `render`,
+/// with `{{title}}` standing for `dest`'s name and `vars` for custom ones.
+#[tauri::command(async)]
+pub fn create_from_template(
+    template: String,
+    dest: String,
+    vars: Option<HashMap<String, String>>,
+    settings: tauri::State<'_, Mutex<SettingsStore>>,
+    cache: tauri::State<'_, MetadataCache>,
+) -> Result<FileEntry, CommandError> {
+    let dest_path = PathBuf::from(&dest);
+    settings::check_writable(&settings, &dest_path)?;
+    let text = fs::read_to_string(&template).map_err(|e| format!("Failed to read template: {}", e))?;
+    let title = dest_path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+    let now = Local::now();
+    let content = render(&text, &vars.unwrap_or_default(), &title, now.date_naive(), now.time());
+
+    create_file(&dest_path, &content, false, &cache)?;
+    Ok(crate::file_entry_for(&dest_path, &settings::note_extensions(&settings)?))
+}
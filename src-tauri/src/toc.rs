@@ -0,0 +1,208 @@
+use pulldown_cmark::{Event, Parser, Tag};
+use regex::Regex;
+use serde::Deserialize;
+use std::fs;
+use std::ops::Range;
+use std::path::PathBuf;
+use std::sync::{Mutex, OnceLock};
+
+use crate::error::CommandError;
+use crate::link_style::LinkStyle;
+use crate::markdown::{self, Heading};
+use crate::metadata_cache::MetadataCache;
+use crate::note_index::NoteIndexRegistry;
+use crate::search::line_starts;
+use crate::settings::{self, SettingsStore};
+use crate::WriteResult;
+
+const START_MARKER: &str = "<!-- toc -->";
+const END_MARKER: &str = "<!-- tocstop -->";
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct TocOptions {
+    /// Shallowest heading level listed. By default 2 when the note has a
+    /// single `#` title at the top, else 1.
+    pub min_level: Option<u8>,
+    /// Deepest heading level listed
+    pub max_level: u8,
+    /// `1.` items instead of `-`
+    pub ordered: bool,
+    /// `[Heading](#heading)` or `[[#Heading]]` entries
+    pub link_style: LinkStyle,
+}
+
+impl Default for TocOptions {
+    fn default() -> Self {
+        TocOptions {
+            min_level: None,
+            max_level: 6,
+            ordered: false,
+            link_style: LinkStyle::Markdown,
+        }
+    }
+}
+
+fn start_marker_regex() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| Regex::new(r"(?i)^\s*<!--\s*toc\s*-->\s*$").expect("valid toc marker regex"))
+}
+
+/// `<!-- tocstop -->`, or `<!-- /toc -->` as some other tools write it
+fn end_marker_regex() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| Regex::new(r"(?i)^\s*<!--\s*(?:tocstop|/toc)\s*-->\s*$").expect("valid toc marker regex"))
+}
+
+/// Byte ranges of the code blocks in `content`, where markers don't count
+fn code_ranges(content: &str) -> Vec<Range<usize>> {
+    let offset = markdown::body_start(content);
+    let mut ranges = Vec::new();
+    for (event, range) in Parser::new_ext(&content[offset..], markdown::parser_options()).into_offset_iter() {
+        if let Event::Start(Tag::CodeBlock(_)) = event {
+            ranges.push(offset + range.start..offset + range.end);
+        }
+    }
+    ranges
+}
+
+/// Byte ranges of the lines of the TOC markers, start and end, if present
+fn find_markers(content: &str) -> (Option<Range<usize>>, Option<Range<usize>>) {
+    let body_start = markdown::body_start(content);
+    let code = code_ranges(content);
+    let mut start: Option<Range<usize>> = None;
+    for line_start in line_starts(content) {
+        if line_start < body_start || code.iter().any(|range| range.contains(&line_start)) {
+            continue;
+        }
+        let line_end = content[line_start..].find('\n').map_or(content.len(), |i| line_start + i + 1);
+        let line = &content[line_start..line_end];
+        match &start {
+            None if start_marker_regex().is_match(line) => start = Some(line_start..line_end),
+            Some(_) if end_marker_regex().is_match(line) => return (start, Some(line_start..line_end)),
+            _ => {}
+        }
+    }
+    (start, None)
+}
+
+/// One TOC entry per heading, nested by level
+fn toc_lines(headings: &[&Heading], options: &TocOptions, newline: &str) -> String {
+    let base = headings.iter().map(|h| h.level).min().unwrap_or(1);
+    let mut lines = String::new();
+    let mut depth = 0;
+    let mut counters: Vec<usize> = Vec::new();
+    for (i, heading) in headings.iter().enumerate() {
+        // A level skipped below the previous heading still nests one deeper
+        let wanted = (heading.level - base) as usize;
+        depth = if i == 0 { 0 } else { wanted.min(depth + 1) };
+        counters.truncate(depth + 1);
+        counters.resize(depth + 1, 0);
+        counters[depth] += 1;
+
+        let indent = if options.ordered { 3 } else { 2 };
+        let marker = if options.ordered {
+            format!("{}.", counters[depth])
+        } else {
+            "-".to_string()
+        };
+        let entry = match options.link_style {
+            LinkStyle::Wiki if !heading.text.contains(['|', '#', '[', ']']) => format!("[[#{}]]", heading.text),
+            _ => format!("[{}](#{})", heading.text.replace('[', "\\[").replace(']', "\\]"), heading.slug),
+        };
+        lines.push_str(&format!("{}{} {}{}", " ".repeat(depth * indent), marker, entry, newline));
+    }
+    lines
+}
+
+/// `content` with its table of contents inserted or brought up to date
+pub fn with_toc(content: &str, options: &TocOptions) -> String {
+    let newline = if content.contains("\r\n") { "\r\n" } else { "\n" };
+    let (start, end) = find_markers(content);
+    let all = markdown::headings(content);
+    let inside = |heading: &Heading| match (&start, &end) {
+        (Some(start), Some(end)) => heading.start >= start.end && heading.start < end.start,
+        _ => false,
+    };
+    let headings: Vec<&Heading> = all.iter().filter(|h| !inside(h)).collect();
+
+    let titled = matches!(headings.as_slice(), [first, ..] if first.level == 1)
+        && headings.iter().filter(|h| h.level == 1).count() == 1;
+    let min_level = options.min_level.unwrap_or(if titled { 2 } else { 1 });
+    let listed: Vec<&Heading> = headings
+        .iter()
+        .filter(|h| h.level >= min_level && h.level <= options.max_level)
+        .copied()
+        .collect();
+    let lines = toc_lines(&listed, options, newline);
+
+    let mut updated = content.to_string();
+    match (start, end) {
+        (Some(start), Some(end)) => updated.replace_range(start.end..end.start, &lines),
+        (Some(start), None) => {
+            let mut block = lines;
+            if !content[..start.end].ends_with('\n') {
+                block.insert_str(0, newline);
+            }
+            block.push_str(END_MARKER);
+            block.push_str(newline);
+            updated.insert_str(start.end, &block);
+        }
+        (None, _) => {
+            // Below a `#` title that opens the note, else at the top of the body
+            let body_start = markdown::body_start(content);
+            let title_end = headings
+                .first()
+                .filter(|h| h.level == 1 && content[body_start..h.start].trim().is_empty())
+                .map(|h| content[h.end..].find('\n').map_or(content.len(), |i| h.end + i + 1));
+            let at = title_end.unwrap_or(body_start);
+            let mut block = String::new();
+            if title_end.is_some() {
+                if !content[..at].ends_with('\n') {
+                    block.push_str(newline);
+                }
+                block.push_str(newline);
+            }
+            block.push_str(&format!("{}{}{}{}{}", START_MARKER, newline, lines, END_MARKER, newline));
+            if at < content.len() && !content[at..].starts_with(['\n', '\r']) {
+                block.push_str(newline);
+            }
+            updated.insert_str(at, &block);
+        }
+    }
+    updated
+}
+
+/// Generate a table of contents from the headings of the note at `path` and
+/// write it between its `<!-- toc -->` and `<!-- tocstop -->` markers,
+/// adding them below the title if missing. The rest of the file is left
+/// byte-for-byte as it was.
+#[tauri::command(async)]
+pub fn insert_toc(
+    path: String,
+    options: Option<TocOptions>,
+    registry: tauri::State<'_, NoteIndexRegistry>,
+    settings: tauri::State<'_, Mutex<SettingsStore>>,
+    cache: tauri::State<'_, MetadataCache>,
+) -> Result<WriteResult, CommandError> {
+    let path_buf = PathBuf::from(&path);
+    let previous = fs::read_to_string(&path_buf).map_err(|e| format!("Failed to read file: {}", e))?;
+    let content = with_toc(&previous, &options.unwrap_or_default());
+
+    if content != previous {
+        settings::check_writable(&settings, &path_buf)?;
+        return Ok(crate::write_note(
+            &path_buf,
+            Some(previous.as_bytes()),
+            content.as_bytes(),
+            &registry,
+            &settings,
+            &cache,
+        )?);
+    }
+
+    Ok(WriteResult {
+        mtime: fs::metadata(&path_buf).ok().as_ref().and_then(crate::mtime_millis),
+        hash: crate::content_hash(content.as_bytes()),
+    })
+}
@@ -0,0 +1,155 @@
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::menu::{IsMenuItem, Menu, MenuEvent, MenuItem, PredefinedMenuItem, Submenu};
+use tauri::tray::{MouseButton, MouseButtonState, TrayIconBuilder, TrayIconEvent};
+use tauri::{AppHandle, Emitter, Manager, Window, WindowEvent};
+
+use crate::daily;
+use crate::launch;
+use crate::metadata_cache::MetadataCache;
+use crate::recent::{RecentKind, RecentStore};
+use crate::sandbox::Sandbox;
+use crate::settings::SettingsStore;
+
+const TRAY_ID: &str = "main";
+
+/// Recent notes listed in the tray menu
+const MAX_RECENT_ITEMS: usize = 10;
+
+/// Prefix of the menu ids of recent notes, followed by the path
+const RECENT_PREFIX: &str = "recent:";
+
+/// The tray menu: recent notes, "New note", "Today's note", "Quick capture",
+/// and the items to bring the window back or quit
+fn build_menu(app: &AppHandle) -> tauri::Result<Menu<tauri::Wry>> {
+    let recent = app
+        .state::<Mutex<RecentStore>>()
+        .lock()
+        .map(|store| store.entries(RecentKind::Note, MAX_RECENT_ITEMS))
+        .unwrap_or_default();
+    let mut recent_items = Vec::new();
+    for entry in recent.iter().filter(|entry| entry.exists) {
+        let name = Path::new(&entry.path)
+            .file_stem()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_else(|| entry.path.clone());
+        let id = format!("{}{}", RECENT_PREFIX, entry.path);
+        recent_items.push(MenuItem::with_id(app, id, name, true, None::<&str>)?);
+    }
+    let recent_refs: Vec<&dyn IsMenuItem<tauri::Wry>> =
+        recent_items.iter().map(|item| item as &dyn IsMenuItem<tauri::Wry>).collect();
+    let recent_menu = Submenu::with_id_and_items(app, "recent", "Recent notes", !recent_items.is_empty(), &recent_refs)?;
+
+    let show = MenuItem::with_id(app, "show", "Show Readmark", true, None::<&str>)?;
+    let new_note = MenuItem::with_id(app, "new-note", "New note", true, None::<&str>)?;
+    let today = MenuItem::with_id(app, "daily-note", "Today's note", true, None::<&str>)?;
+    let capture = MenuItem::with_id(app, "quick-capture", "Quick capture", true, None::<&str>)?;
+    let quit = MenuItem::with_id(app, "quit", "Quit Readmark", true, None::<&str>)?;
+    Menu::with_items(
+        app,
+        &[
+            &show,
+            &PredefinedMenuItem::separator(app)?,
+            &new_note,
+            &today,
+            &capture,
+            &recent_menu,
+            &PredefinedMenuItem::separator(app)?,
+            &quit,
+        ],
+    )
+}
+
+/// Show the window and tell the frontend which tray item was picked:
+/// `tray-new-note` and `tray-quick-capture`, while recent notes and today's
+/// note of the open vault are opened like files from the OS, with
+/// `open-external-file`
+fn on_menu_event(app: &AppHandle, event: MenuEvent) {
+    let id = event.id.as_ref();
+    if id == "quit" {
+        app.exit(0);
+        return;
+    }
+    launch::focus_main_window(app);
+    match id {
+        "new-note" => {
+            let _ = app.emit("tray-new-note", ());
+        }
+        "quick-capture" => {
+            let _ = app.emit("tray-quick-capture", ());
+        }
+        "daily-note" => open_todays_note(app),
+        _ => {
+            if let Some(path) = id.strip_prefix(RECENT_PREFIX) {
+                launch::open_paths(app, vec![PathBuf::from(path)]);
+            }
+        }
+    }
+}
+
+/// Open today's note of the open vault, creating it if needed
+fn open_todays_note(app: &AppHandle) {
+    let Some(root) = app.state::<Sandbox>().root() else {
+        return;
+    };
+    let today = chrono::Local::now().date_naive();
+    let settings = app.state::<Mutex<SettingsStore>>();
+    match daily::daily_note(&root, today, &settings, &app.state::<MetadataCache>()) {
+        Ok(path) => launch::open_paths(app, vec![path]),
+        Err(e) => eprintln!("Failed to open today's note: {}", e),
+    }
+}
+
+/// Add the tray icon. A left click brings the window back; the menu opens
+/// on right click.
+pub fn create(app: &AppHandle) -> tauri::Result<()> {
+    let mut builder = TrayIconBuilder::with_id(TRAY_ID)
+        .tooltip("Readmark")
+        .menu(&build_menu(app)?)
+        .show_menu_on_left_click(false)
+        .on_menu_event(on_menu_event)
+        .on_tray_icon_event(|tray, event| {
+            if let TrayIconEvent::Click {
+                button: MouseButton::Left,
+                button_state: MouseButtonState::Up,
+                ..
+            } = event
+            {
+                launch::focus_main_window(tray.app_handle());
+            }
+        });
+    if let Some(icon) = app.default_window_icon() {
+        builder = builder.icon(icon.clone());
+    }
+    builder.build(app)?;
+    Ok(())
+}
+
+/// Rebuild the tray menu, e.g. after the recent notes changed
+pub fn refresh(app: &AppHandle) {
+    let Some(tray) = app.tray_by_id(TRAY_ID) else {
+        return;
+    };
+    match build_menu(app) {
+        Ok(menu) => {
+            let _ = tray.set_menu(Some(menu));
+        }
+        Err(e) => eprintln!("Failed to update tray menu: {}", e),
+    }
+}
+
+/// Hide the window instead of closing it when `close_to_tray` is on, so
+/// the app stays reachable from the tray
+pub fn on_window_event(window: &Window, event: &WindowEvent) {
+    let WindowEvent::CloseRequested { api, .. } = event else {
+        return;
+    };
+    let close_to_tray = window
+        .state::<Mutex<SettingsStore>>()
+        .lock()
+        .is_ok_and(|store| store.settings().close_to_tray);
+    if close_to_tray && window.app_handle().tray_by_id(TRAY_ID).is_some() {
+        api.prevent_close();
+        let _ = window.hide();
+    }
+}
@@ -0,0 +1,135 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::link_style::LinkStyle;
+use crate::lint::LintRules;
+use crate::settings::{self, SettingsStore};
+use crate::vault_flavor;
+
+/// Name of the config file in a vault's `.readmark` folder
+const CONFIG_FILE: &str = "config.json";
+
+/// Conventions a vault sets for itself in `.readmark/config.json`, so they
+/// travel with it. Anything left out falls back to the app's settings.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VaultConfigFile {
+    pub templates_dir: Option<String>,
+    pub attachments_dir: Option<String>,
+    pub daily_notes_format: Option<String>,
+    pub daily_notes_template: Option<String>,
+    pub new_notes_dir: Option<String>,
+    pub link_style: Option<LinkStyle>,
+    pub lint: Option<LintRules>,
+}
+
+/// The conventions in effect for a vault
+#[derive(Debug, Clone, Serialize)]
+pub struct VaultConfig {
+    /// Templates folder, relative to the vault root
+    pub templates_dir: String,
+    /// Attachments folder, relative to the vault root or, starting with
+    /// `./`, to the note's folder; `/` for the root itself
+    pub attachments_dir: String,
+    /// Path of a day's note, relative to the vault root
+    pub daily_notes_format: String,
+    /// Note new daily notes are created from, relative to the vault root
+    pub daily_notes_template: Option<String>,
+    /// Folder new notes go in, relative to the vault root; `None` for the
+    /// folder of the open note
+    pub new_notes_dir: Option<String>,
+    /// Style of the links to insert; `None` for no preference
+    pub link_style: Option<LinkStyle>,
+    /// Rules `lint_markdown` checks notes against
+    pub lint: LintRules,
+    /// The vault's config file, if it has one
+    pub config_file: Option<String>,
+}
+
+fn config_path(root: &Path) -> PathBuf {
+    root.join(crate::DATA_DIR).join(CONFIG_FILE)
+}
+
+/// The config file of the vault at `root`; `None` if it has none
+fn read_file(root: &Path) -> Result<Option<VaultConfigFile>, String> {
+    let path = config_path(root);
+    let raw = match fs::read_to_string(&path) {
+        Ok(raw) => raw,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(format!("Failed to read {}: {}", path.display(), e)),
+    };
+    serde_json::from_str(&raw)
+        .map(Some)
+        .map_err(|e| format!("Invalid vault config {}: {}", path.display(), e))
+}
+
+/// The conventions in effect for the vault at `root`: its config file, then
+/// the app's settings for the vault, then its Obsidian settings if it is an
+/// Obsidian vault, then the app-wide settings
+pub fn resolve(root: &Path, settings: &Mutex<SettingsStore>) -> Result<VaultConfig, String> {
+    let file = read_file(root)?;
+    let overrides = file.clone().unwrap_or_default();
+    let obsidian = vault_flavor::obsidian_config(root);
+    let store = settings.lock().map_err(|e| format!("Lock error: {}", e))?;
+    let global = store.settings();
+    let vault = global.vaults.get(root.to_string_lossy().as_ref());
+
+    let attachments_dir = match overrides.attachments_dir {
+        Some(dir) => settings::normalize_attachments_dir(&dir)?,
+        None => vault
+            .and_then(|vault| vault.attachments_dir.clone())
+            .or_else(|| obsidian.as_ref().map(|obsidian| obsidian.attachments_dir.clone()))
+            .unwrap_or_else(|| global.attachments_dir.clone()),
+    };
+    let templates_dir = match overrides.templates_dir {
+        Some(dir) => settings::normalize_vault_path(&dir, "Templates folder")?,
+        None => obsidian
+            .as_ref()
+            .and_then(|obsidian| obsidian.templates_dir.clone())
+            .unwrap_or_else(|| global.templates_dir.clone()),
+    };
+    let daily_notes_format = match overrides.daily_notes_format {
+        Some(format) => settings::normalize_daily_notes_format(&format)?,
+        None => obsidian
+            .as_ref()
+            .and_then(|obsidian| obsidian.daily_notes_format.clone())
+            .unwrap_or_else(|| global.daily_notes_format.clone()),
+    };
+    let daily_notes_template = match overrides.daily_notes_template {
+        Some(template) => settings::normalize_daily_notes_template(&template)?,
+        None => obsidian
+            .as_ref()
+            .and_then(|obsidian| obsidian.daily_notes_template.clone())
+            .unwrap_or_else(|| global.daily_notes_template.clone()),
+    };
+    let new_notes_dir = match overrides.new_notes_dir {
+        Some(dir) => Some(settings::normalize_vault_path(&dir, "New notes folder")?),
+        None => obsidian.as_ref().and_then(|obsidian| obsidian.new_notes_dir.clone()),
+    };
+    let link_style = overrides.link_style.or(obsidian.as_ref().map(|obsidian| obsidian.link_style));
+
+    Ok(VaultConfig {
+        templates_dir,
+        attachments_dir,
+        daily_notes_format,
+        daily_notes_template: Some(daily_notes_template).filter(|template| !template.is_empty()),
+        new_notes_dir,
+        link_style,
+        lint: overrides.lint.unwrap_or_default(),
+        config_file: file.map(|_| config_path(root).to_string_lossy().to_string()),
+    })
+}
+
+/// The conventions in effect for the vault at `root`, with those its
+/// `.readmark/config.json` sets taking precedence over the app's settings,
+/// and those of an Obsidian vault's `.obsidian` folder filling in for the
+/// app-wide ones
+#[tauri::command]
+pub fn get_vault_config(root: String, settings: tauri::State<'_, Mutex<SettingsStore>>) -> Result<VaultConfig, String> {
+    let root = PathBuf::from(&root);
+    crate::ensure_dir(&root)?;
+    resolve(&root, &settings)
+}
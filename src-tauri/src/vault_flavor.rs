@@ -0,0 +1,232 @@
+use chrono::{NaiveDate, NaiveTime};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+use crate::link_style::LinkStyle;
+use crate::settings;
+use crate::templates;
+
+/// Folder Obsidian keeps a vault's settings in
+const OBSIDIAN_DIR: &str = ".obsidian";
+
+/// Whose conventions a vault follows
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Flavor {
+    /// A plain folder of notes, or one set up by readmark
+    Readmark,
+    /// A vault with a `.obsidian` folder
+    Obsidian,
+}
+
+/// A vault's Obsidian settings, in readmark's terms
+#[derive(Debug, Clone, Serialize)]
+pub struct ObsidianConfig {
+    /// Where pasted files go, as in `attachments_dir`: `/` for the vault
+    /// root, `.` or `./sub` for the note's folder
+    pub attachments_dir: String,
+    /// Folder new notes go in, relative to the vault root; `None` for the
+    /// folder of the open note
+    pub new_notes_dir: Option<String>,
+    /// Path of a day's note, as in `daily_notes_format`; `None` if the daily
+    /// notes plugin isn't set up or its format uses tokens readmark doesn't
+    /// know
+    pub daily_notes_format: Option<String>,
+    pub daily_notes_template: Option<String>,
+    pub templates_dir: Option<String>,
+    /// Style of the links Obsidian inserts
+    pub link_style: LinkStyle,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct VaultFlavor {
+    pub flavor: Flavor,
+    /// Settings read from the `.obsidian` folder, for Obsidian vaults
+    pub obsidian: Option<ObsidianConfig>,
+}
+
+/// `.obsidian/app.json`, the keys readmark follows
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+struct AppJson {
+    attachment_folder_path: Option<String>,
+    new_file_location: Option<String>,
+    new_file_folder_path: Option<String>,
+    use_markdown_links: bool,
+}
+
+/// `.obsidian/daily-notes.json`
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct DailyNotesJson {
+    folder: String,
+    format: String,
+    template: String,
+}
+
+/// `.obsidian/templates.json`
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct TemplatesJson {
+    folder: String,
+}
+
+/// `file` in the `.obsidian` folder at `dir`; `None` if it is missing or
+/// can't be read. Obsidian writes these as it pleases, so a broken one
+/// counts as missing rather than failing every lookup of the vault's
+/// conventions.
+fn read_json<T: for<'de> Deserialize<'de>>(dir: &Path, file: &str) -> Option<T> {
+    let path = dir.join(file);
+    let raw = match fs::read_to_string(&path) {
+        Ok(raw) => raw,
+        Err(e) if e.kind() == ErrorKind::NotFound => return None,
+        Err(e) => {
+            eprintln!("Failed to read {}: {}", path.display(), e);
+            return None;
+        }
+    };
+    serde_json::from_str(&raw)
+        .map_err(|e| eprintln!("Invalid Obsidian settings {}: {}", path.display(), e))
+        .ok()
+}
+
+/// A vault-relative folder as Obsidian writes it, e.g. `/Journal/`
+fn vault_folder(folder: &str, what: &str) -> Option<String> {
+    settings::normalize_vault_path(folder.trim().trim_matches('/'), what).ok()
+}
+
+/// Obsidian's `attachmentFolderPath` as an `attachments_dir`
+fn attachments_dir(folder: &str) -> Option<String> {
+    match folder.trim() {
+        "" | "/" => Some("/".to_string()),
+        "./" | "." => Some(".".to_string()),
+        dir => settings::normalize_attachments_dir(dir.trim_end_matches('/')).ok(),
+    }
+}
+
+/// Move `pattern` onto `template`, in `{{...}}` if it has tokens. `None` if
+/// it has some readmark doesn't fill in.
+fn flush(pattern: &mut String, template: &mut String) -> Option<()> {
+    if pattern.chars().any(char::is_alphabetic) {
+        let sample = NaiveDate::from_ymd_opt(2000, 1, 1)?;
+        templates::format_date_pattern(pattern, sample, NaiveTime::MIN)?;
+        template.push_str(&format!("{{{{{}}}}}", pattern));
+    } else {
+        template.push_str(pattern);
+    }
+    pattern.clear();
+    Some(())
+}
+
+/// A moment.js date format, such as `YYYY/MM/[Week] WW`, as a readmark
+/// path template: tokens in `{{...}}` and `[...]` escapes as plain text.
+/// `None` if it uses tokens readmark doesn't fill in.
+fn path_template(format: &str) -> Option<String> {
+    let mut template = String::new();
+    let mut pattern = String::new();
+    let mut rest = format;
+    while let Some(c) = rest.chars().next() {
+        if c == '[' {
+            let end = rest.find(']')?;
+            flush(&mut pattern, &mut template)?;
+            template.push_str(&rest[1..end]);
+            rest = &rest[end + 1..];
+        } else {
+            pattern.push(c);
+            rest = &rest[c.len_utf8()..];
+        }
+    }
+    flush(&mut pattern, &mut template)?;
+    Some(template)
+}
+
+/// A template path as Obsidian stores it, usually without its extension
+fn template_path(template: &str) -> Option<String> {
+    let template = template.trim().trim_start_matches('/');
+    if template.is_empty() {
+        return None;
+    }
+    let template = match Path::new(template).extension() {
+        Some(_) => template.to_string(),
+        None => format!("{}.md", template),
+    };
+    settings::normalize_daily_notes_template(&template).ok()
+}
+
+fn daily_notes(daily: &DailyNotesJson) -> (Option<String>, Option<String>) {
+    let format = match daily.format.trim() {
+        "" => "YYYY-MM-DD",
+        format => format,
+    };
+    let path = path_template(format).and_then(|name| {
+        let folder = vault_folder(&daily.folder, "Daily notes folder")?;
+        let path = match folder.as_str() {
+            "." => format!("{}.md", name),
+            folder => format!("{}/{}.md", folder, name),
+        };
+        settings::normalize_daily_notes_format(&path).ok()
+    });
+    (path, template_path(&daily.template))
+}
+
+/// The Obsidian settings of the vault at `root`; `None` if it isn't an
+/// Obsidian vault. Settings Obsidian leaves out take its defaults.
+pub fn obsidian_config(root: &Path) -> Option<ObsidianConfig> {
+    let dir = root.join(OBSIDIAN_DIR);
+    if !dir.is_dir() {
+        return None;
+    }
+    let app: AppJson = read_json(&dir, "app.json").unwrap_or_default();
+    let new_notes_dir = match app.new_file_location.as_deref() {
+        Some("current") => None,
+        Some("folder") => app
+            .new_file_folder_path
+            .as_deref()
+            .and_then(|folder| vault_folder(folder, "New notes folder")),
+        _ => Some(".".to_string()),
+    };
+    let (daily_notes_format, daily_notes_template) = match read_json::<DailyNotesJson>(&dir, "daily-notes.json") {
+        Some(daily) => daily_notes(&daily),
+        None => (None, None),
+    };
+    let templates_dir = read_json::<TemplatesJson>(&dir, "templates.json")
+        .filter(|templates| !templates.folder.trim().is_empty())
+        .and_then(|templates| vault_folder(&templates.folder, "Templates folder"));
+
+    Some(ObsidianConfig {
+        attachments_dir: app
+            .attachment_folder_path
+            .as_deref()
+            .and_then(attachments_dir)
+            .unwrap_or_else(|| "/".to_string()),
+        new_notes_dir,
+        daily_notes_format,
+        daily_notes_template,
+        templates_dir,
+        link_style: if app.use_markdown_links {
+            LinkStyle::Markdown
+        } else {
+            LinkStyle::Wiki
+        },
+    })
+}
+
+/// Whether the vault at `root` is an Obsidian vault and, if so, the
+/// settings of it readmark follows: attachments folder, new note location,
+/// daily notes and link style
+#[tauri::command]
+pub fn detect_vault_flavor(root: String) -> Result<VaultFlavor, String> {
+    let root = PathBuf::from(&root);
+    crate::ensure_dir(&root)?;
+    let obsidian = obsidian_config(&root);
+    Ok(VaultFlavor {
+        flavor: if obsidian.is_some() {
+            Flavor::Obsidian
+        } else {
+            Flavor::Readmark
+        },
+        obsidian,
+    })
+}
@@ -0,0 +1,456 @@
+use rusqlite::types::Value as SqlValue;
+use rusqlite::{params, params_from_iter, Connection};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::path::{Path, MAIN_SEPARATOR};
+
+use crate::frontmatter;
+use crate::links;
+use crate::markdown;
+use crate::search_index::IndexRegistry;
+use crate::stats;
+use crate::tags;
+
+/// Notes returned by `query_notes` when no limit is given
+const DEFAULT_LIMIT: usize = 1000;
+
+/// Tables of note metadata, next to the full-text tables of the search
+/// index and keyed by its `files.id`
+pub const TABLES: &[&str] = &["note_meta", "note_tags", "note_aliases", "note_links", "note_fields", "note_headings"];
+
+pub fn init_schema(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS note_meta (
+             file_id INTEGER PRIMARY KEY,
+             name TEXT NOT NULL,
+             title TEXT NOT NULL,
+             word_count INTEGER NOT NULL,
+             ctime INTEGER
+         );
+         CREATE TABLE IF NOT EXISTS note_tags (
+             file_id INTEGER NOT NULL,
+             tag TEXT NOT NULL,
+             tag_lower TEXT NOT NULL
+         );
+         CREATE INDEX IF NOT EXISTS note_tags_tag ON note_tags (tag_lower);
+         CREATE INDEX IF NOT EXISTS note_tags_file ON note_tags (file_id);
+         CREATE TABLE IF NOT EXISTS note_aliases (
+             file_id INTEGER NOT NULL,
+             alias TEXT NOT NULL
+         );
+         CREATE INDEX IF NOT EXISTS note_aliases_file ON note_aliases (file_id);
+         CREATE TABLE IF NOT EXISTS note_links (
+             file_id INTEGER NOT NULL,
+             target TEXT NOT NULL,
+             target_name TEXT NOT NULL
+         );
+         CREATE INDEX IF NOT EXISTS note_links_name ON note_links (target_name);
+         CREATE INDEX IF NOT EXISTS note_links_file ON note_links (file_id);
+         CREATE TABLE IF NOT EXISTS note_fields (
+             file_id INTEGER NOT NULL,
+             key TEXT NOT NULL,
+             value TEXT NOT NULL
+         );
+         CREATE INDEX IF NOT EXISTS note_fields_file ON note_fields (file_id);
+         CREATE TABLE IF NOT EXISTS note_headings (
+             file_id INTEGER NOT NULL,
+             level INTEGER NOT NULL,
+             text TEXT NOT NULL,
+             slug TEXT NOT NULL,
+             line INTEGER NOT NULL
+         );
+         CREATE INDEX IF NOT EXISTS note_headings_file ON note_headings (file_id);",
+    )
+}
+
+/// Drop the metadata of the files `file_ids` selects, an SQL query taking
+/// `params`
+pub fn remove(conn: &Connection, file_ids: &str, params: &[&dyn rusqlite::ToSql]) -> rusqlite::Result<()> {
+    for table in TABLES {
+        conn.execute(&format!("DELETE FROM {} WHERE file_id IN ({})", table, file_ids), params)?;
+    }
+    Ok(())
+}
+
+/// Lowercased name a link target refers to: its last path segment without
+/// the extension, e.g. `note` for `../Notes/Note.md`
+fn target_name(target: &str) -> String {
+    let decoded = links::percent_decode(target);
+    let last = decoded.rsplit('/').next().unwrap_or(&decoded);
+    let path = Path::new(last);
+    let name = match path.extension() {
+        Some(_) => path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default(),
+        None => last.to_string(),
+    };
+    name.to_lowercase()
+}
+
+/// Drop the metadata of every note
+pub fn clear(conn: &Connection) -> rusqlite::Result<()> {
+    for table in TABLES {
+        conn.execute(&format!("DELETE FROM {}", table), [])?;
+    }
+    Ok(())
+}
+
+/// Store the metadata of the note `file_id`, replacing what was there
+pub fn index_note(
+    conn: &Connection,
+    file_id: i64,
+    path: &Path,
+    title: &str,
+    content: &str,
+    ctime: Option<u64>,
+) -> rusqlite::Result<()> {
+    remove(conn, "?1", &[&file_id])?;
+    let name = path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+    conn.execute(
+        "INSERT INTO note_meta (file_id, name, title, word_count, ctime) VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![file_id, name, title, stats::compute(content).words as i64, ctime.map(|t| t as i64)],
+    )?;
+    for tag in tags::extract(content) {
+        conn.execute(
+            "INSERT INTO note_tags (file_id, tag, tag_lower) VALUES (?1, ?2, ?3)",
+            params![file_id, tag, tag.to_lowercase()],
+        )?;
+    }
+    for alias in links::aliases(content) {
+        conn.execute("INSERT INTO note_aliases (file_id, alias) VALUES (?1, ?2)", params![file_id, alias])?;
+    }
+    let mut targets: Vec<String> = links::extract(content)
+        .into_iter()
+        .filter(|link| !link.target.is_empty() && !links::is_external(&link.target))
+        .map(|link| link.target)
+        .collect();
+    targets.sort();
+    targets.dedup();
+    for target in targets {
+        conn.execute(
+            "INSERT INTO note_links (file_id, target, target_name) VALUES (?1, ?2, ?3)",
+            params![file_id, target, target_name(&target)],
+        )?;
+    }
+    for (key, value) in frontmatter::fields(content) {
+        conn.execute(
+            "INSERT INTO note_fields (file_id, key, value) VALUES (?1, ?2, ?3)",
+            params![file_id, key, value.to_string()],
+        )?;
+    }
+    for heading in markdown::headings(content) {
+        conn.execute(
+            "INSERT INTO note_headings (file_id, level, text, slug, line) VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![file_id, heading.level, heading.text, heading.slug, heading.line as i64],
+        )?;
+    }
+    Ok(())
+}
+
+/// A note as the index knows it
+#[derive(Debug, Clone, Serialize)]
+pub struct NoteRecord {
+    pub path: String,
+    pub title: String,
+    pub aliases: Vec<String>,
+    pub tags: Vec<String>,
+    /// Link targets as written, without `#fragment`s
+    pub links: Vec<String>,
+    /// Frontmatter
+    pub fields: Map<String, Value>,
+    pub word_count: u64,
+    /// Milliseconds since the Unix epoch
+    pub mtime: u64,
+    pub ctime: Option<u64>,
+    pub size: u64,
+}
+
+impl NoteRecord {
+    /// The frontmatter field `key`, or with a `file.` prefix one of the
+    /// note's own properties: `file.path`, `file.name`, `file.folder`,
+    /// `file.title`, `file.tags`, `file.aliases`, `file.links`, `file.words`,
+    /// `file.mtime`, `file.ctime` and `file.size`
+    pub fn value(&self, key: &str) -> Option<Value> {
+        let Some(property) = key.strip_prefix("file.") else {
+            return self.fields.get(key).cloned();
+        };
+        let path = Path::new(&self.path);
+        let strings = |items: &[String]| Value::from(items.to_vec());
+        Some(match property {
+            "path" => Value::from(self.path.clone()),
+            "name" => Value::from(path.file_stem()?.to_string_lossy().to_string()),
+            "folder" => Value::from(path.parent()?.to_string_lossy().to_string()),
+            "title" => Value::from(self.title.clone()),
+            "tags" => strings(&self.tags),
+            "aliases" => strings(&self.aliases),
+            "links" => strings(&self.links),
+            "words" => Value::from(self.word_count),
+            "mtime" => Value::from(self.mtime),
+            "ctime" => Value::from(self.ctime?),
+            "size" => Value::from(self.size),
+            _ => return None,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CompareOp {
+    Eq,
+    Ne,
+    Lt,
+    Lte,
+    Gt,
+    Gte,
+    /// A list holding the value, or text holding it as a substring
+    Contains,
+    /// The field is set, whatever its value
+    Exists,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct FieldFilter {
+    /// Frontmatter key, or a `file.` property as in `NoteRecord::value`
+    pub key: String,
+    pub op: CompareOp,
+    #[serde(default)]
+    pub value: Value,
+}
+
+/// What notes `query_notes` returns; every condition given must hold
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct NoteFilter {
+    /// Only notes inside this folder
+    pub folder: Option<String>,
+    /// Tags the note must all have; `project` also matches `project/alpha`
+    pub tags: Vec<String>,
+    /// Only notes with a link whose target names this note, e.g. `Ideas` or
+    /// `/vault/Ideas.md`
+    pub links_to: Option<String>,
+    pub fields: Vec<FieldFilter>,
+    /// Milliseconds since the Unix epoch
+    pub modified_after: Option<u64>,
+    pub modified_before: Option<u64>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct NoteSort {
+    /// Frontmatter key, or a `file.` property as in `NoteRecord::value`
+    pub key: String,
+    #[serde(default)]
+    pub descending: bool,
+}
+
+/// A number from a number or numeric text
+fn as_number(value: &Value) -> Option<f64> {
+    match value {
+        Value::Number(n) => n.as_f64(),
+        Value::String(s) => s.trim().parse().ok(),
+        Value::Bool(_) | Value::Null | Value::Array(_) | Value::Object(_) => None,
+    }
+}
+
+/// Order of two field values: numerically if both are numbers, otherwise as
+/// case-insensitive text, so that dates like `2024-05-01` sort correctly.
+/// Missing values sort first.
+pub fn compare_values(a: Option<&Value>, b: Option<&Value>) -> Ordering {
+    match (a.filter(|v| !v.is_null()), b.filter(|v| !v.is_null())) {
+        (None, None) => Ordering::Equal,
+        (None, Some(_)) => Ordering::Less,
+        (Some(_), None) => Ordering::Greater,
+        (Some(a), Some(b)) => match (as_number(a), as_number(b)) {
+            (Some(x), Some(y)) => x.partial_cmp(&y).unwrap_or(Ordering::Equal),
+            _ => text_of(a).to_lowercase().cmp(&text_of(b).to_lowercase()),
+        },
+    }
+}
+
+fn text_of(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+/// Whether `actual` stands in relation `op` to `expected`
+pub fn matches(actual: Option<&Value>, op: CompareOp, expected: &Value) -> bool {
+    let actual = actual.filter(|v| !v.is_null());
+    match op {
+        CompareOp::Exists => actual.is_some(),
+        CompareOp::Contains => match actual {
+            Some(Value::Array(items)) => items.iter().any(|item| compare_values(Some(item), Some(expected)).is_eq()),
+            Some(value) => text_of(value).to_lowercase().contains(&text_of(expected).to_lowercase()),
+            None => false,
+        },
+        // A list equals a value it holds, so `tags = "book"` works too
+        CompareOp::Eq | CompareOp::Ne if matches!(actual, Some(Value::Array(_))) && !expected.is_array() => {
+            matches(actual, CompareOp::Contains, expected) == (op == CompareOp::Eq)
+        }
+        _ => {
+            let Some(actual) = actual else {
+                return op == CompareOp::Ne;
+            };
+            let order = compare_values(Some(actual), Some(expected));
+            match op {
+                CompareOp::Eq => order.is_eq(),
+                CompareOp::Ne => order.is_ne(),
+                CompareOp::Lt => order.is_lt(),
+                CompareOp::Lte => order.is_le(),
+                CompareOp::Gt => order.is_gt(),
+                CompareOp::Gte => order.is_ge(),
+                CompareOp::Contains | CompareOp::Exists => unreachable!(),
+            }
+        }
+    }
+}
+
+/// SQL condition on `files` and the parameters it takes, for the parts of
+/// `filter` the database answers itself
+fn sql_filter(filter: &NoteFilter) -> (String, Vec<SqlValue>) {
+    let mut conditions = vec!["1 = 1".to_string()];
+    let mut params: Vec<SqlValue> = Vec::new();
+    if let Some(folder) = &filter.folder {
+        let prefix = format!("{}{}", folder.trim_end_matches(MAIN_SEPARATOR), MAIN_SEPARATOR);
+        params.push(SqlValue::Text(prefix));
+        conditions.push(format!("substr(files.path, 1, length(?{n})) = ?{n}", n = params.len()));
+    }
+    for tag in &filter.tags {
+        params.push(SqlValue::Text(tag.trim_start_matches('#').to_lowercase()));
+        conditions.push(format!(
+            "EXISTS (SELECT 1 FROM note_tags t WHERE t.file_id = files.id
+                     AND (t.tag_lower = ?{n} OR substr(t.tag_lower, 1, length(?{n}) + 1) = ?{n} || '/'))",
+            n = params.len()
+        ));
+    }
+    if let Some(target) = &filter.links_to {
+        params.push(SqlValue::Text(target_name(target)));
+        conditions.push(format!(
+            "EXISTS (SELECT 1 FROM note_links l WHERE l.file_id = files.id AND l.target_name = ?{})",
+            params.len()
+        ));
+    }
+    if let Some(after) = filter.modified_after {
+        params.push(SqlValue::Integer(after as i64));
+        conditions.push(format!("files.mtime > ?{}", params.len()));
+    }
+    if let Some(before) = filter.modified_before {
+        params.push(SqlValue::Integer(before as i64));
+        conditions.push(format!("files.mtime < ?{}", params.len()));
+    }
+    (conditions.join(" AND "), params)
+}
+
+/// One of the lists of a `NoteRecord`
+type ListField = fn(&mut NoteRecord) -> &mut Vec<String>;
+
+/// The notes matching the SQL part of `filter`
+fn load_records(conn: &Connection, filter: &NoteFilter) -> rusqlite::Result<Vec<NoteRecord>> {
+    let (condition, params) = sql_filter(filter);
+    let mut stmt = conn.prepare(&format!(
+        "SELECT files.id, files.path, files.mtime, files.size, m.title, m.word_count, m.ctime
+         FROM files JOIN note_meta m ON m.file_id = files.id
+         WHERE {}",
+        condition
+    ))?;
+    let rows = stmt.query_map(params_from_iter(params.iter()), |row| {
+        Ok((
+            row.get::<_, i64>(0)?,
+            NoteRecord {
+                path: row.get(1)?,
+                mtime: row.get::<_, i64>(2)? as u64,
+                size: row.get::<_, i64>(3)? as u64,
+                title: row.get(4)?,
+                word_count: row.get::<_, i64>(5)? as u64,
+                ctime: row.get::<_, Option<i64>>(6)?.map(|t| t as u64),
+                aliases: Vec::new(),
+                tags: Vec::new(),
+                links: Vec::new(),
+                fields: Map::new(),
+            },
+        ))
+    })?;
+    let mut records: HashMap<i64, NoteRecord> = rows.collect::<Result<_, _>>()?;
+
+    let ids = format!("SELECT files.id FROM files WHERE {}", condition);
+    let lists: [(&str, &str, ListField); 3] = [
+        ("note_tags", "tag", |r| &mut r.tags),
+        ("note_aliases", "alias", |r| &mut r.aliases),
+        ("note_links", "target", |r| &mut r.links),
+    ];
+    for (table, column, list) in lists {
+        let mut stmt = conn.prepare(&format!(
+            "SELECT file_id, {} FROM {} WHERE file_id IN ({}) ORDER BY rowid",
+            column, table, ids
+        ))?;
+        let mut rows = stmt.query(params_from_iter(params.iter()))?;
+        while let Some(row) = rows.next()? {
+            if let Some(record) = records.get_mut(&row.get(0)?) {
+                list(record).push(row.get(1)?);
+            }
+        }
+    }
+    let mut stmt = conn.prepare(&format!(
+        "SELECT file_id, key, value FROM note_fields WHERE file_id IN ({}) ORDER BY rowid",
+        ids
+    ))?;
+    let mut rows = stmt.query(params_from_iter(params.iter()))?;
+    while let Some(row) = rows.next()? {
+        if let Some(record) = records.get_mut(&row.get(0)?) {
+            let raw: String = row.get(2)?;
+            record.fields.insert(row.get(1)?, serde_json::from_str(&raw).unwrap_or(Value::String(raw)));
+        }
+    }
+
+    Ok(records.into_values().collect())
+}
+
+/// Notes of the vault at `root` matching `filter`, in no particular order
+pub fn records(registry: &IndexRegistry, root: &Path, filter: &NoteFilter) -> Result<Vec<NoteRecord>, String> {
+    let index = registry.get(root)?;
+    let mut records = {
+        let conn = index.lock_conn()?;
+        load_records(&conn, filter).map_err(|e| format!("Query failed: {}", e))?
+    };
+    records.retain(|record| {
+        filter
+            .fields
+            .iter()
+            .all(|field| matches(record.value(&field.key).as_ref(), field.op, &field.value))
+    });
+    Ok(records)
+}
+
+/// Sort `records` by each of `sort` in turn, then by path
+pub fn sort(records: &mut [NoteRecord], sort: &[NoteSort]) {
+    records.sort_by(|a, b| {
+        sort.iter()
+            .map(|sort| {
+                let order = compare_values(a.value(&sort.key).as_ref(), b.value(&sort.key).as_ref());
+                if sort.descending {
+                    order.reverse()
+                } else {
+                    order
+                }
+            })
+            .find(|order| order.is_ne())
+            .unwrap_or_else(|| a.path.cmp(&b.path))
+    });
+}
+
+/// Notes of a vault matching `filter`, from the index kept up to date by
+/// the watcher. Sorted by `sort` (a frontmatter key or a `file.` property
+/// such as `file.mtime`), then by path.
+#[tauri::command(async)]
+pub fn query_notes(
+    root: String,
+    filter: Option<NoteFilter>,
+    sort: Option<NoteSort>,
+    limit: Option<usize>,
+    registry: tauri::State<'_, IndexRegistry>,
+) -> Result<Vec<NoteRecord>, String> {
+    let mut records = records(&registry, Path::new(&root), &filter.unwrap_or_default())?;
+    self::sort(&mut records, &sort.into_iter().collect::<Vec<_>>());
+    records.truncate(limit.unwrap_or(DEFAULT_LIMIT));
+    Ok(records)
+}
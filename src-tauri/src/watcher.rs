@@ -0,0 +1,653 @@
+use notify::event::{ModifyKind, RenameMode};
+use notify::{EventKind, RecursiveMode, Watcher};
+use notify_debouncer_full::{new_debouncer, DebounceEventResult, Debouncer, FileIdMap};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::ignore_rules::IgnoreMatcher;
+use crate::metadata_cache::MetadataCache;
+use crate::note_index::{NoteIndex, NoteIndexRegistry};
+use crate::search::NotePathCache;
+use crate::search_index::{IndexRegistry, VaultIndex};
+use crate::settings::{self, SettingsStore};
+
+/// Quiet period before a burst of events is delivered, if not overridden
+pub const DEFAULT_DEBOUNCE_MS: u64 = 500;
+
+/// Noise from sync clients, office apps and atomic saves (including the
+/// `.readmark-*.tmp` files from `write_atomic`), ignored on top of
+/// `ignore_rules::DEFAULT_IGNORES`
+pub const WATCHER_IGNORES: &[&str] = &[
+    "*.tmp",
+    ".tmp*",
+    "~$*",
+    "*.swp",
+    "*.crdownload",
+    ".cache/",
+    ".trash/",
+];
+
+/// Restart attempts after a watcher error, waiting 1s, 2s, 4s, ... between them
+const MAX_RESTART_ATTEMPTS: u32 = 5;
+
+type WatcherHandle = Debouncer<notify::RecommendedWatcher, FileIdMap>;
+
+#[derive(Debug, Serialize, Clone)]
+pub struct FileChangeEvent {
+    /// The affected path; for renames, the new location
+    pub path: String,
+    /// One of "created", "modified", "removed" or "renamed"
+    pub kind: String,
+    /// The previous location, for "renamed" events
+    pub from: Option<String>,
+    /// ID of the watcher that saw the change
+    pub watcher_id: u64,
+}
+
+impl FileChangeEvent {
+    fn new(watcher_id: u64, kind: &str, path: &Path) -> Self {
+        FileChangeEvent {
+            path: path.to_string_lossy().to_string(),
+            kind: kind.to_string(),
+            from: None,
+            watcher_id,
+        }
+    }
+}
+
+/// What a watcher was started with, kept so it can be restarted after an error
+#[derive(Clone)]
+struct WatchSpec {
+    path: PathBuf,
+    recursive: bool,
+    kind: WatchKind,
+}
+
+#[derive(Clone)]
+enum WatchKind {
+    /// Changes anywhere under the path, sent as `file-change`
+    Tree(TreeWatch),
+    /// Content changes to a single file, sent as `open-file-changed`
+    File,
+}
+
+#[derive(Clone)]
+struct TreeWatch {
+    debounce_ms: u64,
+    ignore_globs: Vec<String>,
+    /// Vault indexes kept up to date from this watcher's events
+    search_index: Option<Arc<VaultIndex>>,
+    note_index: Option<Arc<NoteIndex>>,
+    /// Called with each change after it is sent to the frontend
+    on_change: Option<ChangeListener>,
+}
+
+type ChangeListener = Arc<dyn Fn(&FileChangeEvent) + Send + Sync>;
+
+impl TreeWatch {
+    fn new(debounce_ms: Option<u64>, ignore_globs: Option<Vec<String>>) -> Self {
+        TreeWatch {
+            debounce_ms: debounce_ms.unwrap_or(DEFAULT_DEBOUNCE_MS),
+            ignore_globs: ignore_globs.unwrap_or_default(),
+            search_index: None,
+            note_index: None,
+            on_change: None,
+        }
+    }
+}
+
+impl WatchSpec {
+    fn start(&self, app: &AppHandle, id: u64) -> Result<WatcherHandle, String> {
+        match &self.kind {
+            WatchKind::Tree(tree) => start_watcher(app, id, &self.path, self.recursive, tree.clone()),
+            WatchKind::File => start_file_watcher(app, id, &self.path),
+        }
+    }
+}
+
+struct WatchEntry {
+    spec: WatchSpec,
+    // Dropping the debouncer stops the watch; None while a restart is pending
+    // or after giving up
+    debouncer: Option<WatcherHandle>,
+    restarts: u32,
+    last_error: Option<String>,
+}
+
+// Global state for the file watchers, keyed by watcher ID
+pub struct WatcherState {
+    next_id: u64,
+    watchers: HashMap<u64, WatchEntry>,
+    /// The watcher started by `watch_directory` for the open vault
+    vault_watcher: Option<u64>,
+    /// The watcher started by `watch_file` for the note open in the editor
+    open_file_watcher: Option<u64>,
+}
+
+impl WatcherState {
+    pub fn new() -> Self {
+        WatcherState {
+            next_id: 1,
+            watchers: HashMap::new(),
+            vault_watcher: None,
+            open_file_watcher: None,
+        }
+    }
+
+    /// Start a watcher for `spec` and return its ID
+    fn start(&mut self, app: &AppHandle, spec: WatchSpec) -> Result<u64, String> {
+        let id = self.next_id;
+        let debouncer = spec.start(app, id)?;
+        self.next_id += 1;
+        self.watchers.insert(
+            id,
+            WatchEntry {
+                spec,
+                debouncer: Some(debouncer),
+                restarts: 0,
+                last_error: None,
+            },
+        );
+        Ok(id)
+    }
+}
+
+/// Sent as `open-file-changed` when the note open in the editor changes on disk
+#[derive(Debug, Serialize, Clone)]
+pub struct OpenFileChangedEvent {
+    pub path: String,
+    /// False if the file was deleted or moved away
+    pub exists: bool,
+    /// SHA-256 of the new contents, comparable with `WriteResult::hash`
+    pub hash: Option<String>,
+    pub mtime: Option<u64>,
+    pub watcher_id: u64,
+}
+
+/// Sent as `watcher-error` when notify reports an error for a watcher
+#[derive(Debug, Serialize, Clone)]
+pub struct WatcherErrorEvent {
+    pub watcher_id: u64,
+    pub path: String,
+    pub message: String,
+    /// False once restart attempts have been given up
+    pub restarting: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct WatchInfo {
+    pub id: u64,
+    pub path: String,
+    pub recursive: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct WatcherStatus {
+    pub id: u64,
+    pub path: String,
+    pub recursive: bool,
+    /// Whether the watcher is running and its path still exists
+    pub alive: bool,
+    /// Successful restarts after errors
+    pub restarts: u32,
+    pub last_error: Option<String>,
+}
+
+/// Translate a debounced notify event into the events sent to the frontend
+fn change_events(watcher_id: u64, event: &notify::Event) -> Vec<FileChangeEvent> {
+    let paths = &event.paths;
+    let all = |kind: &str| -> Vec<FileChangeEvent> {
+        paths.iter().map(|p| FileChangeEvent::new(watcher_id, kind, p)).collect()
+    };
+    match event.kind {
+        EventKind::Create(_) => all("created"),
+        EventKind::Remove(_) => all("removed"),
+        EventKind::Modify(ModifyKind::Name(RenameMode::Both)) if paths.len() == 2 => {
+            vec![FileChangeEvent {
+                from: Some(paths[0].to_string_lossy().to_string()),
+                ..FileChangeEvent::new(watcher_id, "renamed", &paths[1])
+            }]
+        }
+        EventKind::Modify(ModifyKind::Name(RenameMode::From)) => all("removed"),
+        EventKind::Modify(ModifyKind::Name(RenameMode::To)) => all("created"),
+        // Rename with only one side known: tell by whether the path still exists
+        EventKind::Modify(ModifyKind::Name(_)) => paths
+            .iter()
+            .map(|p| {
+                let kind = if p.exists() { "created" } else { "removed" };
+                FileChangeEvent::new(watcher_id, kind, p)
+            })
+            .collect(),
+        EventKind::Modify(_) | EventKind::Any => all("modified"),
+        EventKind::Access(_) | EventKind::Other => Vec::new(),
+    }
+}
+
+/// Create a debounced watcher for `path` that forwards changes to the
+/// vault indexes, the note path cache and the frontend
+fn start_watcher(app: &AppHandle, id: u64, path: &Path, recursive: bool, tree: TreeWatch) -> Result<WatcherHandle, String> {
+    let app_handle = app.clone();
+    let root = if path.is_dir() { path } else { path.parent().unwrap_or(path) };
+
+    let globs: Vec<String> = WATCHER_IGNORES
+        .iter()
+        .map(|g| g.to_string())
+        .chain(tree.ignore_globs.iter().cloned())
+        .collect();
+    // Matched as the listings are, nested .gitignore files included
+    let matcher = move |root: &Path| match recursive {
+        true => IgnoreMatcher::for_tree(root, &globs),
+        false => IgnoreMatcher::new(root, &globs, true),
+    };
+    let mut ignore = matcher(root)?;
+    let watched_root = root.to_path_buf();
+
+    let mode = if recursive {
+        RecursiveMode::Recursive
+    } else {
+        RecursiveMode::NonRecursive
+    };
+    // Listings under the root are cached for as long as this watcher runs
+    let cache_watch = recursive.then(|| app.state::<MetadataCache>().watch(root));
+    debounce(path, mode, tree.debounce_ms, move |res: DebounceEventResult| {
+        let _ = &cache_watch;
+        match res {
+            Ok(events) => {
+                for mut change in events.iter().flat_map(|e| change_events(id, &e.event)) {
+                    let path = PathBuf::from(&change.path);
+                    if path.file_name().is_some_and(|name| name == ".gitignore") {
+                        match matcher(&watched_root) {
+                            Ok(rebuilt) => ignore = rebuilt,
+                            Err(e) => eprintln!("Failed to reload ignore rules: {}", e),
+                        }
+                    }
+                    let mut from = change.from.as_ref().map(PathBuf::from);
+                    // An atomic save renames an ignored temp file over the note,
+                    // which is the note being modified rather than renamed
+                    let ignored = |p: &Path| ignore.is_ignored(p, p.is_dir());
+                    if from.as_deref().is_some_and(ignored) && !ignored(&path) {
+                        change.kind = "modified".to_string();
+                        change.from = None;
+                        from = None;
+                    }
+
+                    let touched: Vec<&PathBuf> = std::iter::once(&path).chain(from.as_ref()).collect();
+                    if touched.iter().all(|p| ignore.is_ignored(p, p.is_dir())) {
+                        continue;
+                    }
+
+                    for p in &touched {
+                        if let Some(index) = &tree.search_index {
+                            index.update_path(p);
+                        }
+                        if let Some(index) = &tree.note_index {
+                            index.update_path(p);
+                        }
+                        app_handle.state::<NotePathCache>().invalidate(p);
+                        app_handle.state::<MetadataCache>().invalidate(p);
+                    }
+
+                    let _ = app_handle.emit("file-change", &change);
+                    if let Some(on_change) = &tree.on_change {
+                        on_change(&change);
+                    }
+                }
+            }
+            Err(errors) => handle_errors(&app_handle, id, &errors),
+        }
+    })
+}
+
+/// Create a watcher for the single file at `path`.
+///
+/// Editors that save by writing a temp file and renaming it over the original
+/// replace the inode, so the parent directory is watched and events are
+/// filtered by file name. Only changes to the contents are reported.
+fn start_file_watcher(
+    app: &AppHandle,
+    id: u64,
+    path: &Path,
+) -> Result<WatcherHandle, String> {
+    let app_handle = app.clone();
+    let target = path.to_path_buf();
+    let parent = path.parent().ok_or_else(|| format!("No parent directory: {}", path.display()))?;
+    let file_name = path.file_name().map(|n| n.to_os_string());
+
+    let current_hash = |p: &Path| fs::read(p).ok().map(|bytes| crate::content_hash(&bytes));
+    let mut last_hash = current_hash(&target);
+
+    debounce(parent, RecursiveMode::NonRecursive, DEFAULT_DEBOUNCE_MS, move |res: DebounceEventResult| {
+        let events = match res {
+            Ok(events) => events,
+            Err(errors) => return handle_errors(&app_handle, id, &errors),
+        };
+        let touched = events
+            .iter()
+            .flat_map(|e| &e.event.paths)
+            .any(|p| p.file_name().map(|n| n.to_os_string()) == file_name);
+        if !touched {
+            return;
+        }
+
+        let hash = current_hash(&target);
+        if hash == last_hash {
+            return;
+        }
+        last_hash = hash.clone();
+
+        let _ = app_handle.emit(
+            "open-file-changed",
+            OpenFileChangedEvent {
+                path: target.to_string_lossy().to_string(),
+                exists: hash.is_some(),
+                hash,
+                mtime: fs::metadata(&target).ok().as_ref().and_then(crate::mtime_millis),
+                watcher_id: id,
+            },
+        );
+    })
+}
+
+/// Create a debouncer running `handler` and start watching `path` with it
+fn debounce(
+    path: &Path,
+    mode: RecursiveMode,
+    debounce_ms: u64,
+    handler: impl FnMut(DebounceEventResult) + Send + 'static,
+) -> Result<WatcherHandle, String> {
+    let mut debouncer = new_debouncer(Duration::from_millis(debounce_ms), None, handler)
+        .map_err(|e| format!("Failed to create watcher: {}", e))?;
+    debouncer
+        .watcher()
+        .watch(path, mode)
+        .map_err(|e| format!("Failed to watch {}: {}", path.display(), e))?;
+    // Lets the debouncer pair up rename events by file ID
+    debouncer.cache().add_root(path, mode);
+
+    Ok(debouncer)
+}
+
+/// Report watcher errors to the frontend and restart the watcher.
+///
+/// Runs on the debouncer's own thread, so the restart happens on another one.
+fn handle_errors(app: &AppHandle, id: u64, errors: &[notify::Error]) {
+    let message = errors.iter().map(|e| e.to_string()).collect::<Vec<_>>().join("; ");
+    eprintln!("Watch error: {}", message);
+
+    let app = app.clone();
+    thread::spawn(move || restart(&app, id, message));
+}
+
+/// Stop watcher `id` and try to start it again with its original spec,
+/// backing off between attempts
+fn restart(app: &AppHandle, id: u64, message: String) {
+    let state = app.state::<Mutex<WatcherState>>();
+    let emit_error = |path: &Path, message: String, restarting: bool| {
+        let _ = app.emit(
+            "watcher-error",
+            WatcherErrorEvent {
+                watcher_id: id,
+                path: path.to_string_lossy().to_string(),
+                message,
+                restarting,
+            },
+        );
+    };
+
+    {
+        let Ok(mut watcher_state) = state.lock() else { return };
+        let Some(entry) = watcher_state.watchers.get_mut(&id) else { return };
+        // A batch of errors arrives together; the first one handles the restart
+        if entry.debouncer.take().is_none() {
+            return;
+        }
+        entry.last_error = Some(message.clone());
+        emit_error(&entry.spec.path, message, true);
+    }
+
+    for attempt in 0..MAX_RESTART_ATTEMPTS {
+        thread::sleep(Duration::from_secs(1 << attempt));
+
+        let Ok(mut watcher_state) = state.lock() else { return };
+        // Unwatched in the meantime
+        let Some(entry) = watcher_state.watchers.get_mut(&id) else { return };
+        match entry.spec.start(app, id) {
+            Ok(debouncer) => {
+                entry.debouncer = Some(debouncer);
+                entry.restarts += 1;
+                return;
+            }
+            Err(e) => entry.last_error = Some(e),
+        }
+    }
+
+    let Ok(watcher_state) = state.lock() else { return };
+    if let Some(entry) = watcher_state.watchers.get(&id) {
+        let message = format!(
+            "Gave up restarting the watcher: {}",
+            entry.last_error.as_deref().unwrap_or("unknown error")
+        );
+        emit_error(&entry.spec.path, message, false);
+    }
+}
+
+fn lock_state<'a>(
+    state: &'a tauri::State<'_, Mutex<WatcherState>>,
+) -> Result<std::sync::MutexGuard<'a, WatcherState>, String> {
+    state.lock().map_err(|e| format!("Lock error: {}", e))
+}
+
+/// Watch a file or directory alongside any other watchers, returning its ID.
+/// Directories are watched recursively unless `recursive` is false.
+#[tauri::command(async)]
+pub fn watch_path(
+    path: String,
+    recursive: Option<bool>,
+    debounce_ms: Option<u64>,
+    ignore_globs: Option<Vec<String>>,
+    app: AppHandle,
+    state: tauri::State<'_, Mutex<WatcherState>>,
+    settings: tauri::State<'_, Mutex<SettingsStore>>,
+) -> Result<u64, String> {
+    let path_buf = PathBuf::from(&path);
+    if !path_buf.exists() {
+        return Err(format!("Path does not exist: {}", path));
+    }
+    let recursive = path_buf.is_dir() && recursive.unwrap_or(true);
+    let ignore_globs = settings::ignore_globs(&settings, &ignore_globs.unwrap_or_default())?;
+
+    let spec = WatchSpec {
+        path: path_buf,
+        recursive,
+        kind: WatchKind::Tree(TreeWatch::new(debounce_ms, Some(ignore_globs))),
+    };
+    lock_state(&state)?.start(&app, spec)
+}
+
+/// Watch the vault at `root` for the backend's own use, keeping its note
+/// index up to date and calling `on_change` with every change. Returns the
+/// watcher ID, for `stop_watching`.
+pub fn watch_vault(
+    app: &AppHandle,
+    root: &Path,
+    on_change: impl Fn(&FileChangeEvent) + Send + Sync + 'static,
+) -> Result<u64, String> {
+    let settings = app.state::<Mutex<SettingsStore>>();
+    let mut tree = TreeWatch::new(None, Some(settings::ignore_globs(&settings, &[])?));
+    tree.note_index = Some(app.state::<NoteIndexRegistry>().for_vault(root, &settings)?);
+    tree.on_change = Some(Arc::new(on_change));
+    let spec = WatchSpec {
+        path: root.to_path_buf(),
+        recursive: true,
+        kind: WatchKind::Tree(tree),
+    };
+    let state = app.state::<Mutex<WatcherState>>();
+    let id = lock_state(&state)?.start(app, spec)?;
+    Ok(id)
+}
+
+/// Stop a watcher started by `watch_vault`
+pub fn stop_watching(app: &AppHandle, id: u64) {
+    if let Ok(mut state) = app.state::<Mutex<WatcherState>>().lock() {
+        state.watchers.remove(&id);
+    }
+}
+
+/// Stop the watcher with the given ID
+#[tauri::command]
+pub fn unwatch(id: u64, state: tauri::State<'_, Mutex<WatcherState>>) -> Result<(), String> {
+    let mut watcher_state = lock_state(&state)?;
+    if watcher_state.vault_watcher == Some(id) {
+        watcher_state.vault_watcher = None;
+    }
+    if watcher_state.open_file_watcher == Some(id) {
+        watcher_state.open_file_watcher = None;
+    }
+    watcher_state
+        .watchers
+        .remove(&id)
+        .map(|_| ())
+        .ok_or_else(|| format!("No watcher with id {}", id))
+}
+
+/// List active watchers
+#[tauri::command]
+pub fn list_watchers(state: tauri::State<'_, Mutex<WatcherState>>) -> Result<Vec<WatchInfo>, String> {
+    let watcher_state = lock_state(&state)?;
+    let mut watchers: Vec<WatchInfo> = watcher_state
+        .watchers
+        .iter()
+        .map(|(id, entry)| WatchInfo {
+            id: *id,
+            path: entry.spec.path.to_string_lossy().to_string(),
+            recursive: entry.spec.recursive,
+        })
+        .collect();
+    watchers.sort_by_key(|w| w.id);
+    Ok(watchers)
+}
+
+/// Health of each watcher, including errors and restarts
+#[tauri::command]
+pub fn watcher_status(state: tauri::State<'_, Mutex<WatcherState>>) -> Result<Vec<WatcherStatus>, String> {
+    let watcher_state = lock_state(&state)?;
+    let mut statuses: Vec<WatcherStatus> = watcher_state
+        .watchers
+        .iter()
+        .map(|(id, entry)| WatcherStatus {
+            id: *id,
+            path: entry.spec.path.to_string_lossy().to_string(),
+            recursive: entry.spec.recursive,
+            alive: entry.debouncer.is_some() && entry.spec.path.exists(),
+            restarts: entry.restarts,
+            last_error: entry.last_error.clone(),
+        })
+        .collect();
+    statuses.sort_by_key(|s| s.id);
+    Ok(statuses)
+}
+
+/// Start watching the open vault, replacing the previous vault watcher.
+/// Other watchers are left running. Returns the watcher ID.
+#[tauri::command(async)]
+pub fn watch_directory(
+    path: String,
+    debounce_ms: Option<u64>,
+    ignore_globs: Option<Vec<String>>,
+    app: AppHandle,
+    state: tauri::State<'_, Mutex<WatcherState>>,
+    settings: tauri::State<'_, Mutex<SettingsStore>>,
+) -> Result<u64, String> {
+    let path_buf = PathBuf::from(&path);
+    if !path_buf.exists() || !path_buf.is_dir() {
+        return Err("Invalid directory path".to_string());
+    }
+
+    // Opening a vault opens (or creates) its indexes; watching works without
+    // them. Done before locking, as building them can take a while.
+    let note_extensions = settings::note_extensions(&settings)?;
+    let ignore_globs = settings::ignore_globs(&settings, &ignore_globs.unwrap_or_default())?;
+    let mut tree = TreeWatch::new(debounce_ms, Some(ignore_globs));
+    tree.search_index = app
+        .state::<IndexRegistry>()
+        .open(&path_buf, note_extensions.clone())
+        .map_err(|e| eprintln!("Search index unavailable: {}", e))
+        .ok();
+    tree.note_index = app
+        .state::<NoteIndexRegistry>()
+        .open(&path_buf, note_extensions)
+        .map_err(|e| eprintln!("Note index unavailable: {}", e))
+        .ok();
+
+    let mut watcher_state = lock_state(&state)?;
+    // Stop the previous vault watcher if any
+    if let Some(previous) = watcher_state.vault_watcher.take() {
+        watcher_state.watchers.remove(&previous);
+    }
+
+    let spec = WatchSpec {
+        path: path_buf,
+        recursive: true,
+        kind: WatchKind::Tree(tree),
+    };
+    let id = watcher_state.start(&app, spec)?;
+    watcher_state.vault_watcher = Some(id);
+
+    Ok(id)
+}
+
+/// Stop watching the open vault
+#[tauri::command]
+pub fn unwatch_directory(state: tauri::State<'_, Mutex<WatcherState>>) -> Result<(), String> {
+    let mut watcher_state = lock_state(&state)?;
+    if let Some(id) = watcher_state.vault_watcher.take() {
+        watcher_state.watchers.remove(&id);
+    }
+    Ok(())
+}
+
+/// Watch the note open in the editor, replacing the previous open-file
+/// watcher. Emits `open-file-changed` with the new hash when the file is
+/// changed, replaced or removed by another program. Returns the watcher ID.
+#[tauri::command(async)]
+pub fn watch_file(
+    path: String,
+    app: AppHandle,
+    state: tauri::State<'_, Mutex<WatcherState>>,
+) -> Result<u64, String> {
+    let path_buf = PathBuf::from(&path);
+    if !path_buf.is_file() {
+        return Err(format!("Not a file: {}", path));
+    }
+
+    let mut watcher_state = lock_state(&state)?;
+    if let Some(previous) = watcher_state.open_file_watcher.take() {
+        watcher_state.watchers.remove(&previous);
+    }
+
+    let spec = WatchSpec {
+        path: path_buf,
+        recursive: false,
+        kind: WatchKind::File,
+    };
+    let id = watcher_state.start(&app, spec)?;
+    watcher_state.open_file_watcher = Some(id);
+
+    Ok(id)
+}
+
+/// Stop watching the open note
+#[tauri::command]
+pub fn unwatch_file(state: tauri::State<'_, Mutex<WatcherState>>) -> Result<(), String> {
+    let mut watcher_state = lock_state(&state)?;
+    if let Some(id) = watcher_state.open_file_watcher.take() {
+        watcher_state.watchers.remove(&id);
+    }
+    Ok(())
+}
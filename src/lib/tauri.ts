@@ -1,11 +1,15 @@
 import { invoke } from "@tauri-apps/api/core";
-import { listen, UnlistenFn } from "@tauri-apps/api/event";
-import { open } from "@tauri-apps/plugin-dialog";
+import { emit, listen, UnlistenFn } from "@tauri-apps/api/event";
 import { openUrl as tauriOpenUrl } from "@tauri-apps/plugin-opener";
 
 export interface FileChangeEvent {
+  /** The affected path; for renames, the new location */
   path: string;
-  kind: string;
+  kind: "created" | "modified" | "removed" | "renamed";
+  /** The previous location, for renames */
+  from: string | null;
+  /** ID of the watcher that reported the change */
+  watcher_id: number;
 }
 
 export interface FileEntry {
@@ -13,6 +17,12 @@ export interface FileEntry {
   path: string;
   is_dir: boolean;
   is_markdown: boolean;
+  /** Size in bytes */
+  size: number;
+  /** Milliseconds since the Unix epoch, null when the platform doesn't report it */
+  modified: number | null;
+  created: number | null;
+  readonly: boolean;
 }
 
 export interface DirectoryContents {
@@ -20,32 +30,162 @@ export interface DirectoryContents {
   entries: FileEntry[];
 }
 
+export type LineEnding = "lf" | "crlf" | "cr";
+
+export interface TextFile {
+  /** The text, with line endings turned into \n */
+  content: string;
+  /** e.g. "UTF-8", "windows-1252" or "UTF-16LE" */
+  encoding: string;
+  /** Whether the file starts with a byte order mark */
+  bom: boolean;
+  /** The file's most common line ending */
+  line_ending: LineEnding;
+  /** Whether the file is an encrypted note, decrypted for reading */
+  encrypted: boolean;
+  /** SHA-256 of the file's bytes on disk, to pass back to writeFile */
+  hash: string | null;
+  /** Modification time when read, in ms since the Unix epoch, for writeFile */
+  mtime: number | null;
+}
+
+/**
+ * Read a text file, detecting its encoding and line endings. Rejects with a
+ * TooLargeError for files over the max_read_size_mb setting, and with a
+ * NoteLockedError for encrypted notes while their vault is locked.
+ */
+export async function readTextFile(path: string): Promise<TextFile> {
+  return invoke<TextFile>("read_text_file", { path });
+}
+
 /**
  * Read the contents of a text file
  */
 export async function readFile(path: string): Promise<string> {
-  return invoke<string>("read_text_file", { path });
+  return (await readTextFile(path)).content;
+}
+
+/**
+ * Returned (as the rejection value) when a file is too large to read whole
+ */
+export interface TooLargeError {
+  kind: "too_large";
+  path: string;
+  /** Sizes in bytes */
+  size: number;
+  limit: number;
+}
+
+export function isTooLargeError(error: unknown): error is TooLargeError {
+  return typeof error === "object" && error !== null && (error as { kind?: string }).kind === "too_large";
+}
+
+export interface FileRange {
+  content: string;
+  /** Byte offset the content starts at, moved past a split character */
+  offset: number;
+  /** Byte offset where the next range starts */
+  end: number;
+  /** Size of the whole file in bytes */
+  size: number;
+}
+
+/**
+ * Read up to `len` bytes of a text file from byte `offset`, trimmed to whole
+ * characters, for viewing large files a window at a time
+ */
+export async function readFileRange(path: string, offset: number, len: number): Promise<FileRange> {
+  return invoke<FileRange>("read_file_range", { path, offset, len });
+}
+
+/**
+ * Number of lines in a file, counted without loading it whole
+ */
+export async function getLineCount(path: string): Promise<number> {
+  return invoke<number>("get_line_count", { path });
+}
+
+export interface WriteResult {
+  mtime: number | null;
+  hash: string;
+}
+
+/**
+ * Returned (as the rejection value) when the file changed on disk since it was read
+ */
+export interface ConflictError {
+  kind: "conflict";
+  path: string;
+  current_mtime: number | null;
+  current_hash: string | null;
+  current_content: string | null;
+}
+
+export function isConflictError(error: unknown): error is ConflictError {
+  return typeof error === "object" && error !== null && (error as { kind?: string }).kind === "conflict";
 }
 
 /**
- * Write content to a text file
+ * Write content to a text file. Pass the `mtime` or `hash` of the TextFile
+ * from the last readTextFile, or of the WriteResult from the last write, to
+ * have the write rejected with a ConflictError if the file changed on disk.
+ * The file keeps its encoding and line endings unless `format` changes them.
  */
-export async function writeFile(path: string, content: string): Promise<void> {
-  return invoke("write_text_file", { path, content });
+export async function writeFile(
+  path: string,
+  content: string,
+  expected?: { mtime?: number; hash?: string },
+  format?: { encoding?: string; lineEnding?: LineEnding }
+): Promise<WriteResult> {
+  return invoke<WriteResult>("write_text_file", {
+    path,
+    content,
+    expectedMtime: expected?.mtime,
+    expectedHash: expected?.hash,
+    encoding: format?.encoding,
+    lineEnding: format?.lineEnding,
+  });
+}
+
+export interface ListOptions {
+  sort_by?: "name" | "mtime" | "size";
+  direction?: "asc" | "desc";
+  /** Group directories before files (default true) */
+  dirs_first?: boolean;
+  show_hidden?: boolean;
+  /** Only list files with these extensions, e.g. ["md", "txt"] */
+  extensions?: string[];
+  /** Extra gitignore-style patterns to hide */
+  ignore_globs?: string[];
+  /** Honor .gitignore files (default true) */
+  respect_gitignore?: boolean;
 }
 
 /**
  * List contents of a directory (non-recursive)
  */
-export async function listDirectory(path: string): Promise<DirectoryContents> {
-  return invoke<DirectoryContents>("list_dir", { path });
+export async function listDirectory(path: string, options?: ListOptions): Promise<DirectoryContents> {
+  return invoke<DirectoryContents>("list_dir", { path, options });
+}
+
+export interface TreeNode {
+  entry: FileEntry;
+  /** null for files and for directories beyond maxDepth */
+  children: TreeNode[] | null;
+}
+
+/**
+ * List a directory tree in one call, down to `maxDepth` levels
+ */
+export async function listTree(path: string, maxDepth?: number, options?: ListOptions): Promise<TreeNode[]> {
+  return invoke<TreeNode[]>("list_tree", { path, maxDepth, options });
 }
 
 /**
  * Recursively list all markdown files in a directory
  */
-export async function listMarkdownFiles(path: string): Promise<FileEntry[]> {
-  return invoke<FileEntry[]>("list_md_files", { path });
+export async function listMarkdownFiles(path: string, ignoreGlobs?: string[]): Promise<FileEntry[]> {
+  return invoke<FileEntry[]>("list_md_files", { path, ignoreGlobs });
 }
 
 /**
@@ -63,51 +203,2516 @@ export async function getFileInfo(path: string): Promise<FileEntry> {
 }
 
 /**
- * Open a folder picker dialog
+ * Create a new directory (including missing parents)
  */
-export async function openFolderDialog(): Promise<string | null> {
-  const result = await open({
-    directory: true,
-    multiple: false,
-    title: "Open Folder",
+export async function createDirectory(path: string): Promise<FileEntry> {
+  return invoke<FileEntry>("create_dir", { path });
+}
+
+/**
+ * Create a new note. Fails if the file already exists.
+ */
+export async function createNote(path: string, initialContent = ""): Promise<FileEntry> {
+  return invoke<FileEntry>("create_note", { path, initialContent });
+}
+
+/**
+ * Rename or move a file. Fails if the destination exists unless `overwrite` is set.
+ */
+export async function renameFile(oldPath: string, newPath: string, overwrite = false): Promise<FileEntry> {
+  return invoke<FileEntry>("rename_file", { oldPath, newPath, overwrite });
+}
+
+/**
+ * Delete a file, moving it to the system trash unless `permanent` is set
+ */
+export async function deleteFile(path: string, permanent = false): Promise<void> {
+  return invoke("delete_file", { path, permanent });
+}
+
+/**
+ * Get the file extensions treated as notes (e.g. ["md", "markdown", "mdx", "txt"])
+ */
+export async function getNoteExtensions(): Promise<string[]> {
+  return invoke<string[]>("get_note_extensions");
+}
+
+/**
+ * Set the file extensions treated as notes. Returns the normalized list.
+ */
+export async function setNoteExtensions(extensions: string[]): Promise<string[]> {
+  return invoke<string[]>("set_note_extensions", { extensions });
+}
+
+/**
+ * Get a vault's attachments folder: relative to the vault root, or to the
+ * note's folder when it starts with "./"
+ */
+export async function getAttachmentsDir(root: string): Promise<string> {
+  return invoke<string>("get_attachments_dir", { root });
+}
+
+/**
+ * Set a vault's attachments folder. Returns the normalized folder.
+ */
+export async function setAttachmentsDir(root: string, dir: string): Promise<string> {
+  return invoke<string>("set_attachments_dir", { root, dir });
+}
+
+/**
+ * Make a vault read-only, or writable again. Returns the new state.
+ */
+export async function setVaultReadOnly(root: string, readOnly: boolean): Promise<boolean> {
+  return invoke<boolean>("set_vault_read_only", { root, readOnly });
+}
+
+export async function isVaultReadOnly(root: string): Promise<boolean> {
+  return invoke<boolean>("is_vault_read_only", { root });
+}
+
+/**
+ * Returned (as the rejection value) by commands that would change a read-only vault
+ */
+export interface ReadOnlyVaultError {
+  kind: "read_only_vault";
+  root: string;
+  path: string;
+}
+
+export function isReadOnlyVaultError(error: unknown): error is ReadOnlyVaultError {
+  return typeof error === "object" && error !== null && (error as { kind?: string }).kind === "read_only_vault";
+}
+
+export interface HistorySettings {
+  enabled: boolean;
+  /** Snapshots kept per file; 0 for no limit */
+  max_versions: number;
+  /** Snapshots older than this are removed, except a file's newest; 0 for no limit */
+  max_age_days: number;
+}
+
+/**
+ * Get the file history retention settings
+ */
+export async function getHistorySettings(): Promise<HistorySettings> {
+  return invoke<HistorySettings>("get_history_settings");
+}
+
+/**
+ * Set the file history retention settings
+ */
+export async function setHistorySettings(history: HistorySettings): Promise<HistorySettings> {
+  return invoke<HistorySettings>("set_history_settings", { history });
+}
+
+export interface CaptureServerSettings {
+  /** Started with the app */
+  enabled: boolean;
+  /** Root of the vault it serves */
+  vault: string | null;
+  /** Port on 127.0.0.1 */
+  port: number;
+  /** Note `POST /capture` appends to, relative to the vault root */
+  inbox: string;
+  /** Bearer token clients must send; generated on first start */
+  token: string;
+}
+
+/** Notifications for tasks of open vaults as they come due */
+export interface ReminderSettings {
+  enabled: boolean;
+  /** HH:MM on their due date that tasks without a due time are reminded of */
+  time: string;
+  /** How long snoozeTask puts a reminder off by default */
+  snooze_minutes: number;
+}
+
+export interface AutocommitSettings {
+  enabled: boolean;
+  /** Minutes without saves before the changes are committed */
+  interval_minutes: number;
+  /** Commit message; {date}, {count} and {files} are filled in */
+  message: string;
+}
+
+/**
+ * Get a vault's auto-commit settings
+ */
+export async function getAutocommitSettings(root: string): Promise<AutocommitSettings> {
+  return invoke<AutocommitSettings>("get_autocommit_settings", { root });
+}
+
+/**
+ * Set a vault's auto-commit settings
+ */
+export async function setAutocommitSettings(root: string, autocommit: AutocommitSettings): Promise<AutocommitSettings> {
+  return invoke<AutocommitSettings>("set_autocommit_settings", { root, autocommit });
+}
+
+export interface BackupSettings {
+  /** "daily" while the app runs, or "on_close" when it quits */
+  schedule: "off" | "daily" | "on_close";
+  /**
+   * Absolute path of the backup folder, picked with pickFolder() unless it
+   * is in the vault; null for one in the app's data folder
+   */
+  dir: string | null;
+  /** Backups kept, oldest removed first; 0 for no limit */
+  keep: number;
+}
+
+/**
+ * Get a vault's backup settings
+ */
+export async function getBackupSettings(root: string): Promise<BackupSettings> {
+  return invoke<BackupSettings>("get_backup_settings", { root });
+}
+
+/**
+ * Set a vault's backup settings
+ */
+export async function setBackupSettings(root: string, backup: BackupSettings): Promise<BackupSettings> {
+  return invoke<BackupSettings>("set_backup_settings", { root, backup });
+}
+
+export interface VaultSettings {
+  /** Overrides the app-wide attachments folder; null to use it */
+  attachments_dir: string | null;
+  autocommit: AutocommitSettings;
+  backup: BackupSettings;
+  sync: SyncConfig | null;
+  /** Refuse every change to the vault's files */
+  read_only: boolean;
+}
+
+export interface Settings {
+  note_extensions: string[];
+  /** Gitignore-style patterns hidden from listings, searches and watchers everywhere */
+  ignore_globs: string[];
+  /** Attachments folder of vaults that don't set their own */
+  attachments_dir: string;
+  /** Templates folder of vaults that don't set their own */
+  templates_dir: string;
+  /** Path of a day's note in vaults that don't set their own, e.g. Journal/{{YYYY}}/{{YYYY-MM-DD}}.md */
+  daily_notes_format: string;
+  /** Note new daily notes are created from in vaults that don't set their own; empty for none */
+  daily_notes_template: string;
+  /** Editor autosave delay; 0 turns autosave off */
+  autosave_interval_ms: number;
+  /** Hide the window to the tray when it is closed, instead of quitting */
+  close_to_tray: boolean;
+  /** Largest file readFile loads whole, in megabytes; 0 for no limit */
+  max_read_size_mb: number;
+  /** By vault root path */
+  vaults: Record<string, VaultSettings>;
+  history: HistorySettings;
+  capture_server: CaptureServerSettings;
+  reminders: ReminderSettings;
+}
+
+/** A partial settings object; null resets a setting to its default */
+export type SettingsPatch = {
+  [K in keyof Settings]?: Settings[K] extends object
+    ? { [P in keyof Settings[K]]?: unknown } | null
+    : Settings[K] | null;
+};
+
+/**
+ * Get all settings
+ */
+export async function getSettings(): Promise<Settings> {
+  return invoke<Settings>("get_settings");
+}
+
+/**
+ * Change settings with a JSON merge patch, e.g. `{ history: { max_versions: 20 } }`.
+ * Rejects, saving nothing, if a key is unknown or a value invalid.
+ */
+export async function setSettings(patch: SettingsPatch): Promise<Settings> {
+  return invoke<Settings>("set_settings", { patch });
+}
+
+/** Conventions in effect for a vault */
+export interface VaultConfig {
+  templates_dir: string;
+  /** Relative to the vault root, or to the note's folder when starting with ./; / for the root */
+  attachments_dir: string;
+  daily_notes_format: string;
+  /** Note new daily notes are created from, if any */
+  daily_notes_template: string | null;
+  /** Folder new notes go in; null for the open note's folder */
+  new_notes_dir: string | null;
+  /** Style of links to insert, if the vault prefers one */
+  link_style: "wiki" | "markdown" | null;
+  /** Rules lintMarkdown checks the vault's notes against */
+  lint: LintRules;
+  /** The vault's .readmark/config.json, if it has one */
+  config_file: string | null;
+}
+
+/**
+ * Conventions in effect for a vault: its .readmark/config.json overrides
+ * the app's settings
+ */
+export async function getVaultConfig(root: string): Promise<VaultConfig> {
+  return invoke<VaultConfig>("get_vault_config", { root });
+}
+
+/** A vault's Obsidian settings, in readmark's terms */
+export interface ObsidianConfig {
+  attachments_dir: string;
+  new_notes_dir: string | null;
+  /** null without the daily notes plugin, or with a format readmark can't follow */
+  daily_notes_format: string | null;
+  daily_notes_template: string | null;
+  templates_dir: string | null;
+  link_style: "wiki" | "markdown";
+}
+
+export interface VaultFlavor {
+  flavor: "readmark" | "obsidian";
+  obsidian: ObsidianConfig | null;
+}
+
+/**
+ * Whether a vault is an Obsidian vault, and the settings of its .obsidian
+ * folder that getVaultConfig follows
+ */
+export async function detectVaultFlavor(root: string): Promise<VaultFlavor> {
+  return invoke<VaultFlavor>("detect_vault_flavor", { root });
+}
+
+/**
+ * Path of a day's note (date as YYYY-MM-DD or words read like parseDate,
+ * today if omitted), creating it from the vault's daily note template if it
+ * doesn't exist yet
+ */
+export async function openDailyNote(root: string, date?: string): Promise<string> {
+  return invoke<string>("open_daily_note", { root, date });
+}
+
+export interface ParsedDate {
+  /** YYYY-MM-DD */
+  date: string;
+  /** HH:MM, if the text has a time of day */
+  time: string | null;
+}
+
+/**
+ * Read a date such as "next friday", "in 2 weeks", "june 1st" or "tomorrow
+ * at 9am", counting from `reference` (YYYY-MM-DD, today if omitted)
+ */
+export async function parseDate(text: string, reference?: string): Promise<ParsedDate> {
+  return invoke<ParsedDate>("parse_date", { text, reference });
+}
+
+export interface TemplateInfo {
+  /** Path within the templates folder without the extension, e.g. "Work/Standup" */
+  name: string;
+  path: string;
+}
+
+/**
+ * Notes in the vault's templates folder, by name
+ */
+export async function listTemplates(root: string): Promise<TemplateInfo[]> {
+  return invoke<TemplateInfo[]>("list_templates", { root });
+}
+
+/**
+ * Create a note from a template, refusing to overwrite an existing file.
+ * Fills in {{date}}, {{time}}, {{title}} (the new note's name), patterns
+ * such as {{date:dddd, MMMM D}} or {{YYYY-MM-DD}}, and the custom `vars`.
+ */
+export async function createFromTemplate(
+  template: string,
+  dest: string,
+  vars?: Record<string, string>
+): Promise<FileEntry> {
+  return invoke<FileEntry>("create_from_template", { template, dest, vars });
+}
+
+/**
+ * A timestamp id for a new note from a date pattern, by default
+ * YYYYMMDDHHmm (e.g. 202406151230)
+ */
+export async function generateNoteId(format?: string): Promise<string> {
+  return invoke<string>("generate_note_id", { format });
+}
+
+/**
+ * Create an empty note named `baseName` in `folder`, or "baseName 2",
+ * "baseName 3" and so on if the name is taken, never overwriting a file
+ */
+export async function createUniqueNote(folder: string, baseName: string): Promise<FileEntry> {
+  return invoke<FileEntry>("create_unique_note", { folder, baseName });
+}
+
+export type RecentKind = "note" | "vault";
+
+export interface RecentEntry {
+  path: string;
+  kind: RecentKind;
+  /** Milliseconds since the Unix epoch */
+  opened_at: number;
+  /** False if the path is gone, e.g. its drive isn't mounted */
+  exists: boolean;
+}
+
+/**
+ * Recently opened notes or vaults, most recent first. `getRecent("vault", 1)`
+ * gives the vault to reopen on launch.
+ */
+export async function getRecent(kind: RecentKind, limit?: number): Promise<RecentEntry[]> {
+  return invoke<RecentEntry[]>("get_recent", { kind, limit });
+}
+
+/**
+ * Record that a note (file) or vault (folder) was just opened
+ */
+export async function touchRecent(path: string): Promise<RecentEntry> {
+  return invoke<RecentEntry>("touch_recent", { path });
+}
+
+export interface SessionTab {
+  path: string;
+  /** Editor scroll offset in pixels */
+  scroll_top: number;
+  /** Byte offset of the cursor */
+  cursor?: number | null;
+  preview: boolean;
+}
+
+export interface SidebarState {
+  visible: boolean;
+  width?: number | null;
+  /** Selected panel, e.g. "files", "search" or "tags" */
+  panel?: string | null;
+  /** Folders expanded in the file tree */
+  expanded: string[];
+}
+
+export interface SessionState {
+  tabs: SessionTab[];
+  /** Path of the focused tab */
+  active?: string | null;
+  sidebar: SidebarState;
+}
+
+/**
+ * Save the open tabs, active file, scroll positions and sidebar state of a
+ * vault
+ */
+export async function saveSession(root: string, state: SessionState): Promise<void> {
+  return invoke<void>("save_session", { root, state });
+}
+
+/**
+ * The saved session of a vault, without tabs for deleted files, or null if
+ * none was saved
+ */
+export async function loadSession(root: string): Promise<SessionState | null> {
+  return invoke<SessionState | null>("load_session", { root });
+}
+
+export interface PinnedNote {
+  /** Stays the same when the note is renamed or moved */
+  id: string;
+  path: string;
+  name: string;
+  /** False if the note was deleted, or moved and changed outside the app */
+  exists: boolean;
+  /** Milliseconds since the Unix epoch */
+  pinned_at: number;
+}
+
+/**
+ * Pin a note to the favorites of the vault it is in
+ */
+export async function pinNote(path: string): Promise<PinnedNote> {
+  return invoke<PinnedNote>("pin_note", { path });
+}
+
+/**
+ * Unpin a note
+ */
+export async function unpinNote(path: string): Promise<void> {
+  return invoke<void>("unpin_note", { path });
+}
+
+/**
+ * Pinned notes of a vault, in the order they were pinned. Notes moved outside
+ * the app are found again by their content.
+ */
+export async function listPinned(root: string): Promise<PinnedNote[]> {
+  return invoke<PinnedNote[]>("list_pinned", { root });
+}
+
+/**
+ * Show a file selected in Finder, Explorer or the desktop's file manager
+ */
+export async function revealInFileManager(path: string): Promise<void> {
+  return invoke<void>("reveal_in_file_manager", { path });
+}
+
+/**
+ * Open a file in the app the OS associates with it
+ */
+export async function openWithDefaultApp(path: string): Promise<void> {
+  return invoke<void>("open_with_default_app", { path });
+}
+
+export interface ExternalFile {
+  path: string;
+  /** Folders are opened as vaults */
+  is_dir: boolean;
+}
+
+/**
+ * Files the OS asked the app to open (double-clicked `.md` files, command
+ * line arguments) before the frontend was ready. Call once after
+ * `onOpenExternalFile` is registered.
+ */
+export async function takeExternalFiles(): Promise<ExternalFile[]> {
+  return invoke<ExternalFile[]>("take_external_files");
+}
+
+/**
+ * Listen for files the OS asks the running app to open
+ */
+export function onOpenExternalFile(callback: (file: ExternalFile) => void): Promise<UnlistenFn> {
+  return listen<ExternalFile>("open-external-file", (event) => {
+    callback(event.payload);
   });
-  return result as string | null;
 }
 
+/** What a `readmark://` link asks the app to do */
+export type DeepLink =
+  | { action: "open"; path: string }
+  | { action: "new"; title?: string | null; content?: string | null }
+  | { action: "search"; query: string };
+
 /**
- * Open a file picker dialog for markdown files
+ * `readmark://` links the app was launched with before the frontend was
+ * ready. Call once after `onDeepLink` is registered.
  */
-export async function openFileDialog(): Promise<string | null> {
-  const result = await open({
-    directory: false,
-    multiple: false,
-    title: "Open Markdown File",
-    filters: [
-      {
-        name: "Markdown",
-        extensions: ["md", "markdown"],
-      },
-    ],
+export async function takeDeepLinks(): Promise<DeepLink[]> {
+  return invoke<DeepLink[]>("take_deep_links");
+}
+
+/**
+ * Listen for `readmark://open?path=`, `readmark://new?title=&content=` and
+ * `readmark://search?q=` links opened while the app is running
+ */
+export function onDeepLink(callback: (link: DeepLink) => void): Promise<UnlistenFn> {
+  return listen<DeepLink>("deep-link", (event) => {
+    callback(event.payload);
   });
-  return result as string | null;
 }
 
 /**
- * Open a URL in the default browser
+ * Listen for "New note" picked from the tray menu
  */
-export async function openUrl(url: string): Promise<void> {
-  try {
-    await tauriOpenUrl(url);
-  } catch (error) {
-    console.error("Failed to open URL:", error);
-  }
+export function onTrayNewNote(callback: () => void): Promise<UnlistenFn> {
+  return listen("tray-new-note", () => {
+    callback();
+  });
+}
+
+/**
+ * Listen for "Quick capture" picked from the tray menu. Recent notes picked
+ * from the tray arrive through `onOpenExternalFile`.
+ */
+export function onTrayQuickCapture(callback: () => void): Promise<UnlistenFn> {
+  return listen("tray-quick-capture", () => {
+    callback();
+  });
+}
+
+/**
+ * Listen for saved settings changes, from any command
+ */
+export function onSettingsChanged(callback: (settings: Settings) => void): Promise<UnlistenFn> {
+  return listen<Settings>("settings-changed", (event) => {
+    callback(event.payload);
+  });
+}
+
+export interface IndexStatus {
+  root: string;
+  state: "building" | "ready" | "error";
+  indexed_files: number;
+  last_updated: number | null;
+  error: string | null;
+}
+
+export interface SearchHit {
+  path: string;
+  title: string;
+  /** Excerpt with matches wrapped in <mark> tags */
+  snippet: string;
+  score: number;
+}
+
+/**
+ * Get the status of a vault's search index (opened when the vault is watched)
+ */
+export async function getIndexStatus(root: string): Promise<IndexStatus> {
+  return invoke<IndexStatus>("index_status", { root });
+}
+
+/**
+ * Rebuild a vault's search index from scratch in the background
+ */
+export async function rebuildIndex(root: string): Promise<IndexStatus> {
+  return invoke<IndexStatus>("rebuild_index", { root });
+}
+
+/**
+ * Full-text search across a vault, best matches first
+ */
+export async function queryIndex(root: string, query: string, limit?: number): Promise<SearchHit[]> {
+  return invoke<SearchHit[]>("query_index", { root, query, limit });
+}
+
+/** How a note's field compares with a value */
+export type CompareOp = "eq" | "ne" | "lt" | "lte" | "gt" | "gte" | "contains" | "exists";
+
+export interface FieldFilter {
+  /** Frontmatter key, or a file property such as "file.mtime" or "file.words" */
+  key: string;
+  op: CompareOp;
+  value?: unknown;
+}
+
+/** Every condition given must hold */
+export interface NoteFilter {
+  /** Only notes inside this folder */
+  folder?: string;
+  /** Tags the note must all have; "project" also matches "project/alpha" */
+  tags?: string[];
+  /** Only notes linking to this note, by name or path */
+  links_to?: string;
+  fields?: FieldFilter[];
+  /** Milliseconds since the Unix epoch */
+  modified_after?: number;
+  modified_before?: number;
+}
+
+export interface NoteSort {
+  /** Frontmatter key, or a file property such as "file.mtime" */
+  key: string;
+  descending?: boolean;
+}
+
+export interface NoteRecord {
+  path: string;
+  title: string;
+  aliases: string[];
+  tags: string[];
+  links: string[];
+  fields: Record<string, unknown>;
+  word_count: number;
+  mtime: number;
+  ctime: number | null;
+  size: number;
+}
+
+/**
+ * Notes of a vault matching a filter, from its index (opened when the vault
+ * is watched). Sorted by `sort`, then by path; 1000 notes at most unless a
+ * limit is given.
+ */
+export async function queryNotes(
+  root: string,
+  filter?: NoteFilter,
+  sort?: NoteSort,
+  limit?: number,
+): Promise<NoteRecord[]> {
+  return invoke<NoteRecord[]>("query_notes", { root, filter, sort, limit });
+}
+
+export interface QueryRow {
+  path: string;
+  title: string;
+  /** One per column, null where the note doesn't have the field */
+  values: unknown[];
+}
+
+export interface QueryResult {
+  kind: "list" | "table";
+  columns: string[];
+  rows: QueryRow[];
+}
+
+/**
+ * Run a Dataview-style query over a vault's notes, e.g.
+ * `TABLE rating FROM #book WHERE rating >= 4 SORT finished DESC LIMIT 10`.
+ * FROM takes #tags, a "folder" or a [[note]] linked to; WHERE compares
+ * fields with = != < <= > >= and CONTAINS, joined by AND, OR and NOT.
+ * Fields are frontmatter keys or file properties such as "file.mtime".
+ */
+export async function runQuery(root: string, query: string): Promise<QueryResult> {
+  return invoke<QueryResult>("run_query", { root, query });
+}
+
+export interface LinkCompletion {
+  kind: "note" | "alias" | "heading";
+  /** The note name, alias or heading that matched */
+  label: string;
+  /** What goes between [[ and ]], e.g. "Note", "Note|Alias" or "Note#Heading" */
+  target: string;
+  path: string;
+  title: string;
+}
+
+/**
+ * Note names, aliases and headings matching what was typed after `[[`,
+ * best first: matches at the start, then notes linked to often and changed
+ * recently. "Note#Sec" completes the headings of that note.
+ */
+export async function getLinkCompletions(root: string, prefix: string, limit?: number): Promise<LinkCompletion[]> {
+  return invoke<LinkCompletion[]>("get_link_completions", { root, prefix, limit });
+}
+
+export interface HeadingMatch {
+  path: string;
+  /** Title of the note */
+  title: string;
+  heading: string;
+  /** 1 for # through 6 for ###### */
+  level: number;
+  /** Anchor for #slug links */
+  slug: string;
+  /** 1-based line number */
+  line: number;
+  /** What goes between [[ and ]], e.g. "Note#Heading" */
+  target: string;
+}
+
+/**
+ * Headings across a vault containing the words of `query`, best first, for
+ * [[Note#Heading]] completion and jumping to a heading in another note
+ */
+export async function searchHeadings(root: string, query: string, limit?: number): Promise<HeadingMatch[]> {
+  return invoke<HeadingMatch[]>("search_headings", { root, query, limit });
+}
+
+export interface RegexOptions {
+  case_insensitive?: boolean;
+  /** ^ and $ match at line boundaries (default true) */
+  multi_line?: boolean;
+  dot_matches_newline?: boolean;
+  /** Maximum matches across the vault (default 1000) */
+  max_results?: number;
+  ignore_globs?: string[];
+}
+
+export interface CaptureRange {
+  name: string | null;
+  start: number;
+  end: number;
+  text: string;
+}
+
+export interface RegexMatch {
+  line: number;
+  /** Byte offsets into the file */
+  start: number;
+  end: number;
+  text: string;
+  line_text: string;
+  captures: (CaptureRange | null)[];
+}
+
+export interface RegexSearchResult {
+  files: { path: string; matches: RegexMatch[] }[];
+  total_matches: number;
+  truncated: boolean;
+}
+
+/**
+ * Search every note in a vault with a regular expression
+ */
+export async function searchRegex(root: string, pattern: string, options?: RegexOptions): Promise<RegexSearchResult> {
+  return invoke<RegexSearchResult>("search_regex", { root, pattern, options });
+}
+
+export interface ReplaceOptions {
+  /** Pattern is a regex and the replacement may use $1 / ${name} */
+  regex?: boolean;
+  case_insensitive?: boolean;
+  ignore_globs?: string[];
+  /** Write the changes; otherwise only a preview is returned */
+  apply?: boolean;
+  only_files?: string[];
+  /** Hashes from the preview, to refuse files that changed since */
+  expected_hashes?: Record<string, string>;
+}
+
+export interface ReplaceResult {
+  files: {
+    path: string;
+    hash: string;
+    replacements: number;
+    lines: { line: number; before: string; after: string }[];
+  }[];
+  total_replacements: number;
+  applied: boolean;
+}
+
+/**
+ * Find and replace across a vault. Preview first, then call again with
+ * `apply: true` (and the preview's hashes) to write all files or none.
+ */
+export async function replaceInFiles(
+  root: string,
+  pattern: string,
+  replacement: string,
+  options?: ReplaceOptions
+): Promise<ReplaceResult> {
+  return invoke<ReplaceResult>("replace_in_files", { root, pattern, replacement, options });
+}
+
+export interface FuzzyMatch {
+  path: string;
+  relative_path: string;
+  score: number;
+  /** Character positions in relative_path to highlight */
+  indices: number[];
+}
+
+/**
+ * Fuzzy-match note paths for the quick switcher, best first
+ */
+export async function fuzzyFindNotes(root: string, query: string, limit?: number): Promise<FuzzyMatch[]> {
+  return invoke<FuzzyMatch[]>("fuzzy_find_notes", { root, query, limit });
+}
+
+export interface Frontmatter {
+  /** null when the note has no frontmatter */
+  format: "yaml" | "toml" | null;
+  data: Record<string, unknown>;
+  /** Byte offset where the body starts */
+  body_offset: number;
+}
+
+/**
+ * Read a note's YAML or TOML frontmatter
+ */
+export async function readFrontmatter(path: string): Promise<Frontmatter> {
+  return invoke<Frontmatter>("read_frontmatter", { path });
+}
+
+/**
+ * Apply a JSON merge patch to a note's frontmatter (null removes a key).
+ * Accepts the same conflict guard as writeFile().
+ */
+export async function updateFrontmatter(
+  path: string,
+  patch: Record<string, unknown>,
+  expected?: { mtime?: number; hash?: string }
+): Promise<WriteResult> {
+  return invoke<WriteResult>("update_frontmatter", {
+    path,
+    patch,
+    expectedMtime: expected?.mtime,
+    expectedHash: expected?.hash,
+  });
+}
+
+export interface Heading {
+  level: number;
+  text: string;
+  /** Anchor for #slug links, unique within the note */
+  slug: string;
+  /** 1-based line number */
+  line: number;
+  /** Byte range of the heading line(s) */
+  start: number;
+  end: number;
+}
+
+/**
+ * Get the heading outline of a note
+ */
+export async function getOutline(path: string): Promise<Heading[]> {
+  return invoke<Heading[]>("get_outline", { path });
+}
+
+export interface Link {
+  kind: "wiki" | "markdown";
+  /** Written as ![[...]] or ![...](...) */
+  embed: boolean;
+  /** Target as written, without the #fragment; empty for links within the note */
+  target: string;
+  /** Heading or ^block id after the # */
+  fragment: string | null;
+  /** Alias or link text */
+  text: string;
+  /** 1-based line number */
+  line: number;
+  start: number;
+  end: number;
+}
+
+export interface ResolvedLink extends Link {
+  /** Absolute path of the target; null for external and broken links */
+  resolved: string | null;
+  external: boolean;
+}
+
+export interface Backlink {
+  /** The note containing the link */
+  source: string;
+  link: Link;
+}
+
+/**
+ * Get the outgoing links of a note
+ */
+export async function getLinks(path: string): Promise<ResolvedLink[]> {
+  return invoke<ResolvedLink[]>("get_links", { path });
+}
+
+/**
+ * Get the links from other notes in the open vault that point at a note
+ */
+export async function getBacklinks(path: string): Promise<Backlink[]> {
+  return invoke<Backlink[]>("get_backlinks", { path });
+}
+
+export interface Mention {
+  /** The note mentioning the target */
+  file: string;
+  /** 1-based line number */
+  line: number;
+  /** Byte offsets into the file */
+  start: number;
+  end: number;
+  /** The name as written */
+  text: string;
+  /** The full line the mention is on */
+  line_text: string;
+}
+
+/**
+ * Places in other notes where a note is named, by file name, title or
+ * alias, without being linked. Mentions in links, code and frontmatter are
+ * skipped.
+ */
+export async function findUnlinkedMentions(path: string): Promise<Mention[]> {
+  return invoke<Mention[]>("find_unlinked_mentions", { path });
+}
+
+/**
+ * Turn a mention (byte range of `file`) into a [[link]] to the note at
+ * `target`, keeping the text as written as the alias
+ */
+export async function linkMention(
+  file: string,
+  range: { start: number; end: number },
+  target: string,
+): Promise<WriteResult> {
+  return invoke<WriteResult>("link_mention", { path: file, range, target });
+}
+
+/**
+ * Rename or move a note and update every link that pointed at it.
+ * Returns the notes whose content was rewritten.
+ */
+export async function renameNoteWithLinks(oldPath: string, newPath: string): Promise<string[]> {
+  return invoke<string[]>("rename_note_with_links", { oldPath, newPath });
+}
+
+export interface MoveResult {
+  /** Where the note is now */
+  new_path: string;
+  /** Notes whose links were rewritten, including the moved one at its new path */
+  modified: string[];
+}
+
+/**
+ * Move a note into another folder, keeping its name. With `updateLinks`,
+ * links in and to it are rewritten so they still resolve.
+ */
+export async function moveNote(path: string, destDir: string, updateLinks: boolean): Promise<MoveResult> {
+  return invoke<MoveResult>("move_note", { path, destDir, updateLinks });
+}
+
+export interface FolderRename {
+  new_path: string;
+  /** Files inside the folder */
+  moved: number;
+  /** Notes whose links were (or with dryRun would be) rewritten, at their new paths */
+  modified: string[];
+  dry_run: boolean;
+}
+
+/**
+ * Rename or move a folder and rewrite every link to a file inside it; if
+ * any note can't be rewritten nothing changes. With `dryRun`, only reports
+ * what would change.
+ */
+export async function renameFolder(oldPath: string, newPath: string, dryRun = false): Promise<FolderRename> {
+  return invoke<FolderRename>("rename_folder", { oldPath, newPath, dryRun });
+}
+
+export interface MergeOptions {
+  /** "append" puts the source after the target; "interleave" merges sections with the same heading */
+  mode?: "append" | "interleave";
+  /** What becomes of the source afterwards; "keep" by default */
+  source_action?: "keep" | "delete" | "archive";
+  /** Relative to the vault root; "archive" by default */
+  archive_dir?: string;
+}
+
+export interface MergeSummary {
+  target: string;
+  /** Where the source is now: null once deleted */
+  source: string | null;
+  /** Sections of the source merged under a heading of the target */
+  sections_merged: number;
+  /** Notes whose links to the source now point at the target */
+  links_redirected: string[];
+}
+
+/**
+ * Merge one note into another, combining frontmatter and redirecting links
+ * to the source at the target
+ */
+export async function mergeNotes(source: string, target: string, options?: MergeOptions): Promise<MergeSummary> {
+  return invoke<MergeSummary>("merge_notes", { source, target, options });
+}
+
+/**
+ * Split a note into one note per heading of `level` (1 for #), created next
+ * to it with its frontmatter; each section in the original becomes a link
+ * to its note. Returns the new notes.
+ */
+export async function splitNote(path: string, level: number): Promise<string[]> {
+  return invoke<string[]>("split_note", { path, level });
+}
+
+export interface BrokenLink {
+  /** The note containing the link */
+  source: string;
+  link: Link;
+  /** What the link points at that doesn't exist */
+  missing: "file" | "heading" | "block";
+  /** The file the link resolved to, for missing headings and blocks */
+  resolved: string | null;
+}
+
+/**
+ * Find every link in a vault whose target file, heading or block doesn't exist
+ */
+export async function findBrokenLinks(root: string): Promise<BrokenLink[]> {
+  return invoke<BrokenLink[]>("find_broken_links", { root });
+}
+
+/**
+ * Give a line (1-based) of a note a ^block-id unless it has one, and return
+ * a link to it such as "[[Note#^f3k9x2]]"
+ */
+export async function ensureBlockId(path: string, line: number): Promise<string> {
+  return invoke<string>("ensure_block_id", { path, line });
+}
+
+/**
+ * Notes in a vault that nothing links to and that link to nothing, to find
+ * forgotten notes. "no_inbound" or "no_outbound" check only one direction.
+ */
+export async function findOrphanNotes(
+  root: string,
+  criteria?: "isolated" | "no_inbound" | "no_outbound",
+): Promise<string[]> {
+  return invoke<string[]>("find_orphan_notes", { root, criteria });
+}
+
+export interface TagCount {
+  tag: string;
+  count: number;
+}
+
+/**
+ * List the tags used in a vault, most used first
+ */
+export async function listTags(root: string): Promise<TagCount[]> {
+  return invoke<TagCount[]>("list_tags", { root });
+}
+
+/**
+ * Find notes tagged with a tag or a tag nested under it
+ */
+export async function findNotesByTag(root: string, tag: string): Promise<string[]> {
+  return invoke<string[]>("find_notes_by_tag", { root, tag });
+}
+
+export interface TagRename {
+  path: string;
+  /** Number of tags renamed in the note */
+  occurrences: number;
+}
+
+/**
+ * Rename a tag (and the tags nested under it) in every note of a vault.
+ * With dryRun, nothing is written and the result previews the changes.
+ */
+export async function renameTag(
+  root: string,
+  oldTag: string,
+  newTag: string,
+  dryRun?: boolean
+): Promise<TagRename[]> {
+  return invoke<TagRename[]>("rename_tag", { root, old: oldTag, new: newTag, dryRun });
+}
+
+export interface VaultTask {
+  path: string;
+  /** 1-based line number of the checkbox */
+  line: number;
+  text: string;
+  completed: boolean;
+  /** YYYY-MM-DD, from `📅 date`, `due:date` or `@due(date)` */
+  due: string | null;
+  /** HH:MM, from a time after the date, e.g. `📅 2024-05-01 14:30` */
+  due_time: string | null;
+}
+
+export interface TaskFilter {
+  completed?: boolean;
+  /** Only tasks due on or before this YYYY-MM-DD date */
+  due_before?: string;
+  /** Only tasks whose text contains this, ignoring case */
+  query?: string;
+}
+
+/**
+ * List the `- [ ]` / `- [x]` tasks of every note in a vault
+ */
+export async function listTasks(root: string, filter?: TaskFilter): Promise<VaultTask[]> {
+  return invoke<VaultTask[]>("list_tasks", { root, filter });
+}
+
+/**
+ * Flip the checkbox on a line (1-based) of a note. Accepts the same conflict
+ * guard as writeFile(), so a stale line number can't flip another task.
+ */
+export async function toggleTask(
+  path: string,
+  line: number,
+  expected?: { mtime?: number; hash?: string }
+): Promise<WriteResult> {
+  return invoke<WriteResult>("toggle_task", {
+    path,
+    line,
+    expectedMtime: expected?.mtime,
+    expectedHash: expected?.hash,
+  });
+}
+
+/**
+ * Check the checkbox on a line (1-based) of a note; a done task stays done.
+ * Accepts the same conflict guard as writeFile().
+ */
+export async function completeTask(
+  path: string,
+  line: number,
+  expected?: { mtime?: number; hash?: string }
+): Promise<WriteResult> {
+  return invoke<WriteResult>("complete_task", {
+    path,
+    line,
+    expectedMtime: expected?.mtime,
+    expectedHash: expected?.hash,
+  });
+}
+
+/**
+ * Set the due date of the task on a line (1-based) of a note from text read
+ * like parseDate; an empty `when` removes it. Accepts the same conflict
+ * guard as writeFile().
+ */
+export async function setTaskDue(
+  path: string,
+  line: number,
+  when: string,
+  expected?: { mtime?: number; hash?: string }
+): Promise<WriteResult> {
+  return invoke<WriteResult>("set_task_due", {
+    path,
+    line,
+    when,
+    expectedMtime: expected?.mtime,
+    expectedHash: expected?.hash,
+  });
+}
+
+/**
+ * Put off the reminder of the task on a line (1-based) of a note by
+ * `minutes`, or the snooze time in the settings. Resolves to when it comes
+ * back, as local YYYY-MM-DDTHH:MM:SS.
+ */
+export async function snoozeTask(path: string, line: number, minutes?: number): Promise<string> {
+  return invoke<string>("snooze_task", { path, line, minutes });
+}
+
+/** A task of an open vault that came due */
+export interface TaskReminder {
+  path: string;
+  line: number;
+  /** The task's text without its due date */
+  text: string;
+  /** YYYY-MM-DD */
+  due: string;
+  due_time: string | null;
+  /** Whether this reminder is one that was snoozed */
+  snoozed: boolean;
+}
+
+export type ReminderAction =
+  | { action: "snooze"; path: string; line: number; minutes?: number | null }
+  | { action: "complete"; path: string; line: number };
+
+/**
+ * Listen for task reminders, sent along with their system notifications
+ */
+export function onTaskReminder(callback: (reminder: TaskReminder) => void): Promise<UnlistenFn> {
+  return listen<TaskReminder>("task-reminder", (event) => {
+    callback(event.payload);
+  });
+}
+
+/**
+ * Snooze or complete the task of a reminder, e.g. from a notification's
+ * buttons
+ */
+export async function sendReminderAction(action: ReminderAction): Promise<void> {
+  return emit("task-reminder-action", action);
+}
+
+export interface NoteStats {
+  words: number;
+  /** Characters of readable text, including spaces but not markup */
+  characters: number;
+  headings: number;
+  links: number;
+  reading_time_minutes: number;
+}
+
+export interface VaultStats {
+  notes: number;
+  attachments: number;
+  words: number;
+  characters: number;
+  headings: number;
+  links: number;
+  tags: number;
+  tasks: number;
+  completed_tasks: number;
+  reading_time_minutes: number;
+}
+
+/**
+ * Get the word count, reading time and other figures for a note
+ */
+export async function getNoteStats(path: string): Promise<NoteStats> {
+  return invoke<NoteStats>("get_note_stats", { path });
+}
+
+/**
+ * Get totals across every note in a vault
+ */
+export async function getVaultStats(root: string): Promise<VaultStats> {
+  return invoke<VaultStats>("get_vault_stats", { root });
+}
+
+export interface GraphOptions {
+  /** Add a node per tag, connected to the notes that use it */
+  include_tags?: boolean;
+  /** Keep notes without any edges (default true) */
+  include_orphans?: boolean;
+  /** Only include nodes within `depth` edges of this note */
+  focus?: string;
+  /** Maximum distance from `focus` (default 1) */
+  depth?: number;
+}
+
+export interface GraphNode {
+  /** The note's path, or `#tag` for tags */
+  id: string;
+  kind: "note" | "tag";
+  label: string;
+  degree: number;
+}
+
+export interface GraphEdge {
+  source: string;
+  target: string;
+  weight: number;
+}
+
+export interface Graph {
+  nodes: GraphNode[];
+  edges: GraphEdge[];
+}
+
+/**
+ * Get the notes and links of a vault for the graph view
+ */
+export async function getGraph(root: string, options?: GraphOptions): Promise<Graph> {
+  return invoke<Graph>("get_graph", { root, options });
+}
+
+export interface LinkTarget {
+  /** Absolute path of the note or attachment, or null if nothing matches */
+  path: string | null;
+  fragment: string | null;
+  /** 1-based line of the heading or block the fragment names, if found */
+  line: number | null;
+}
+
+/**
+ * Resolve a link such as `[[Note#Heading|alias]]` written in a note,
+ * matching file names first and then frontmatter aliases
+ */
+export async function resolveLink(sourcePath: string, linkText: string): Promise<LinkTarget> {
+  return invoke<LinkTarget>("resolve_link", { sourcePath, linkText });
+}
+
+export type MarkdownSource = { content: string } | { path: string };
+
+export interface RenderOptions {
+  /** Point local images at the asset protocol so the webview can load them */
+  asset_urls?: boolean;
+  /** Directory relative image paths in `content` are resolved against */
+  base_dir?: string;
+}
+
+/**
+ * Render markdown (given directly or read from a note) to HTML
+ */
+export async function renderMarkdown(source: MarkdownSource, options?: RenderOptions): Promise<string> {
+  return invoke<string>("render_markdown", { contentOrPath: source, options });
+}
+
+export interface FormatStyle {
+  /** Marker of bullet list items; "-" by default */
+  bullet?: "-" | "*" | "+";
+  /** Pad table cells so the columns line up (default true) */
+  align_tables?: boolean;
+  /** Drop spaces at line ends, except two-space line breaks (default true) */
+  trim_trailing_whitespace?: boolean;
+  /** Blank lines kept in a row (default 1) */
+  max_blank_lines?: number;
+  /** Move [label]: url definitions to the end of the note (default true) */
+  references_at_end?: boolean;
+}
+
+export interface FormatResult {
+  content: string;
+  changed: boolean;
+}
+
+/**
+ * Format markdown (given directly or read from a note) the same way every
+ * time: ATX headings, one bullet marker, aligned tables, no trailing
+ * whitespace, normalized reference links. With `write`, a note is saved back.
+ */
+export async function formatMarkdown(
+  source: MarkdownSource,
+  style?: FormatStyle,
+  write = false,
+): Promise<FormatResult> {
+  return invoke<FormatResult>("format_markdown", { contentOrPath: source, style, write });
+}
+
+export type ColumnAlignment = "none" | "left" | "center" | "right";
+
+export interface CsvTableOptions {
+  /** Guessed from the first line if omitted */
+  delimiter?: string;
+  /** Defaults to true; without a header row the table gets an empty one */
+  has_header?: boolean;
+  /** Per column; unlisted columns of numbers are right-aligned */
+  alignments?: ColumnAlignment[];
+}
+
+/** CSV text as an aligned markdown table */
+export async function csvToMarkdownTable(csv: string, options?: CsvTableOptions): Promise<string> {
+  return invoke<string>("csv_to_markdown_table", { csv, options });
+}
+
+/** A note's table (0-based, in document order) as CSV, header row first */
+export async function markdownTableToCsv(path: string, tableIndex: number, delimiter?: string): Promise<string> {
+  return invoke<string>("markdown_table_to_csv", { path, tableIndex, delimiter });
+}
+
+export interface LinkChange {
+  /** 1-based line number */
+  line: number;
+  before: string;
+  after: string;
+}
+
+export interface LinkStyleResult {
+  files: { path: string; changes: LinkChange[] }[];
+  dry_run: boolean;
+}
+
+/**
+ * Rewrite the links of a note, or of every note in a folder, between
+ * `[[Note|alias]]` and `[alias](Note.md)`. With `dryRun`, only reports the
+ * changes.
+ */
+export async function convertLinkStyle(
+  rootOrPath: string,
+  targetStyle: "wiki" | "markdown",
+  dryRun = false,
+): Promise<LinkStyleResult> {
+  return invoke<LinkStyleResult>("convert_link_style", { path: rootOrPath, targetStyle, dryRun });
+}
+
+export interface TocOptions {
+  /** Shallowest heading level listed; by default 2 below a single `#` title, else 1 */
+  min_level?: number;
+  /** Deepest heading level listed (default 6) */
+  max_level?: number;
+  /** Numbered instead of bulleted entries */
+  ordered?: boolean;
+  /** `[Heading](#heading)` (default) or `[[#Heading]]` entries */
+  link_style?: "markdown" | "wiki";
+}
+
+/**
+ * Generate a table of contents from a note's headings and write it between
+ * its `<!-- toc -->` and `<!-- tocstop -->` markers, adding them if missing
+ */
+export async function insertToc(path: string, options?: TocOptions): Promise<WriteResult> {
+  return invoke<WriteResult>("insert_toc", { path, options });
+}
+
+export type LintSeverity = "off" | "info" | "warning" | "error";
+
+/** Severity of each rule's findings; "off" turns a rule off */
+export interface LintRules {
+  /** A heading more than one level below the previous one (default warning) */
+  heading_increment?: LintSeverity;
+  /** A URL in the text that isn't a link (default warning) */
+  bare_urls?: LintSeverity;
+  /** Two headings with the same text (default warning) */
+  duplicate_headings?: LintSeverity;
+  /** A line over max_line_length characters (default off) */
+  long_lines?: LintSeverity;
+  /** Default 120 */
+  max_line_length?: number;
+  /** Whitespace at the end of a line, other than a two-space line break (default info) */
+  trailing_spaces?: LintSeverity;
+}
+
+export interface Diagnostic {
+  rule: keyof LintRules;
+  severity: Exclude<LintSeverity, "off">;
+  message: string;
+  /** 1-based line number */
+  line: number;
+  /** Byte offsets into the file */
+  start: number;
+  end: number;
+}
+
+/**
+ * Lint a note for the editor's diagnostics. Without `rules`, those of the
+ * note's vault (`lint` in .readmark/config.json) apply.
+ */
+export async function lintMarkdown(path: string, rules?: LintRules): Promise<Diagnostic[]> {
+  return invoke<Diagnostic[]>("lint_markdown", { path, rules });
+}
+
+export interface Misspelling {
+  /** Byte offsets into the text */
+  start: number;
+  end: number;
+  word: string;
+  suggestions: string[];
+}
+
+/**
+ * Misspelled words in markdown text, checked against the hunspell dictionary
+ * for `lang` (e.g. "en_US") and, with `root`, the vault's own words
+ */
+export async function checkText(text: string, lang: string, root?: string): Promise<Misspelling[]> {
+  return invoke<Misspelling[]>("check_text", { text, lang, root });
+}
+
+/** Add a word to the vault's dictionary, kept in .readmark/dictionary.txt */
+export async function addToDictionary(root: string, word: string): Promise<void> {
+  return invoke<void>("add_to_dictionary", { root, word });
+}
+
+/** Languages with a hunspell dictionary installed */
+export async function listDictionaries(): Promise<string[]> {
+  return invoke<string[]>("list_dictionaries");
+}
+
+/**
+ * Put a rendered note on the system clipboard as HTML, plus RTF for
+ * "rich_text" on macOS and Windows, so pasting into mail, chat or a word
+ * processor keeps the formatting
+ */
+export async function copyNoteAs(path: string, format: "html" | "rich_text"): Promise<void> {
+  return invoke<void>("copy_note_as", { path, format });
+}
+
+export interface HtmlExportOptions {
+  /** How wiki links to other notes appear: as plain text (default) or as links to their .html export */
+  wiki_links?: "text" | "anchor";
+  /** Page title; defaults to the note's first heading, then its file name */
+  title?: string;
+  /** Extra CSS added after the built-in stylesheet */
+  css?: string;
+}
+
+/**
+ * Export a note as a self-contained HTML file with styles and images embedded
+ */
+export async function exportHtml(path: string, outPath: string, options?: HtmlExportOptions): Promise<void> {
+  return invoke<void>("export_html", { path, outPath, options });
+}
+
+export interface PdfExportOptions extends HtmlExportOptions {
+  /** CSS page size such as "A4", "Letter" or "210mm 297mm" (default A4) */
+  page_size?: string;
+  landscape?: boolean;
+  /** CSS page margin, e.g. "20mm" (default) or "1in 0.75in" */
+  margin?: string;
+}
+
+export interface ExportProgress {
+  out_path: string;
+  /** What the export is doing, e.g. "rendering" or "printing" */
+  stage: string;
+  completed: number;
+  total: number;
+}
+
+/**
+ * Export a note to PDF with a headless Chrome, Chromium or Edge.
+ * Progress is reported through the "export-progress" event.
+ */
+export async function exportPdf(path: string, outPath: string, options?: PdfExportOptions): Promise<void> {
+  return invoke<void>("export_pdf", { path, outPath, options });
+}
+
+/**
+ * Returned (as the rejection value) when an external program is missing or fails
+ */
+export type ToolError =
+  | { kind: "tool_missing"; tool: string; install_url: string }
+  | { kind: "tool_failed"; tool: string; status: number | null; stderr: string };
+
+export function isToolError(error: unknown): error is ToolError {
+  const kind = typeof error === "object" && error !== null ? (error as { kind?: string }).kind : undefined;
+  return kind === "tool_missing" || kind === "tool_failed";
+}
+
+/**
+ * Convert a note to DOCX, ODT, EPUB or RTF with pandoc, using its frontmatter as document metadata
+ */
+export async function exportWithPandoc(
+  path: string,
+  format: "docx" | "odt" | "epub" | "rtf",
+  outPath: string
+): Promise<void> {
+  return invoke<void>("export_with_pandoc", { path, format, outPath });
+}
+
+export interface SiteExportOptions {
+  /** Site name for the index page and navigation; defaults to the vault's folder name */
+  title?: string;
+  /** Extra CSS added to every page */
+  css?: string;
+}
+
+export interface SiteExport {
+  pages: number;
+  attachments: number;
+}
+
+/**
+ * Export a whole vault as a static HTML site with an index page.
+ * Progress is reported through the "export-progress" event.
+ */
+export async function exportSite(root: string, outDir: string, options?: SiteExportOptions): Promise<SiteExport> {
+  return invoke<SiteExport>("export_site", { root, outDir, options });
+}
+
+export interface JobProgress {
+  id: number;
+  kind: string;
+  /** Files scanned or processed so far */
+  completed: number;
+  /** null while the total isn't known yet, e.g. during a directory walk */
+  total: number | null;
+  /** 0 to 100, when the total is known */
+  percent: number | null;
+  current: string | null;
+}
+
+export interface JobPartial<T = unknown> {
+  id: number;
+  kind: string;
+  /** Part of the result, e.g. the notes found since the last batch */
+  items: T;
+}
+
+export interface JobDone<T = unknown> {
+  id: number;
+  kind: string;
+  /** What the equivalent direct command would have returned */
+  result: T | null;
+  error: string | null;
+  cancelled: boolean;
+}
+
+export interface JobInfo {
+  id: number;
+  kind: string;
+  elapsed_ms: number;
+}
+
+/**
+ * List notes as a cancellable job. Notes arrive in batches as "job-partial"
+ * events while the scan runs, then sorted in its "job-done" event.
+ */
+export async function startListMdFiles(path: string, ignoreGlobs?: string[]): Promise<number> {
+  return invoke<number>("start_list_md_files", { path, ignoreGlobs });
+}
+
+/**
+ * Regex search as a cancellable job; a RegexSearchResult arrives in its
+ * "job-done" event
+ */
+export async function startSearchRegex(root: string, pattern: string, options?: RegexOptions): Promise<number> {
+  return invoke<number>("start_search_regex", { root, pattern, options });
+}
+
+/**
+ * Bring a vault's search index up to date as a cancellable job, or rebuild
+ * it with `fromScratch`; the IndexStatus arrives in its "job-done" event
+ */
+export async function startIndexVault(root: string, fromScratch?: boolean): Promise<number> {
+  return invoke<number>("start_index_vault", { root, fromScratch });
+}
+
+/**
+ * Static site export as a cancellable job; the SiteExport arrives in its
+ * "job-done" event
+ */
+export async function startExportSite(root: string, outDir: string, options?: SiteExportOptions): Promise<number> {
+  return invoke<number>("start_export_site", { root, outDir, options });
+}
+
+export interface ZipExportOptions {
+  /** Also bundle the notes the selection links to, and so on (default false) */
+  include_linked_notes?: boolean;
+  /** Bundle the images and other files the notes embed or link to (default true) */
+  include_attachments?: boolean;
+}
+
+export interface ZipExport {
+  notes: number;
+  attachments: number;
+}
+
+/**
+ * Package notes, and every note under the folders among `paths`, into a zip
+ * archive with the attachments they embed or link to. Files keep their place
+ * relative to the vault root, and links between bundled files become
+ * relative markdown links.
+ */
+export async function exportZip(paths: string[], outPath: string, options?: ZipExportOptions): Promise<ZipExport> {
+  return invoke<ZipExport>("export_zip", { paths, outPath, options });
+}
+
+export interface IcsExport {
+  /** Events for daily notes */
+  daily_notes: number;
+  /** To-dos for tasks with a due date */
+  tasks: number;
+}
+
+/**
+ * Export a vault's daily notes as all-day events and its tasks with due
+ * dates as to-dos in an iCalendar file, for calendar apps
+ */
+export async function exportIcs(root: string, outPath: string): Promise<IcsExport> {
+  return invoke<IcsExport>("export_ics", { root, outPath });
+}
+
+/**
+ * Ask a job to stop. Returns false if it isn't running anymore.
+ */
+export async function cancelJob(id: number): Promise<boolean> {
+  return invoke<boolean>("cancel_job", { id });
+}
+
+/**
+ * Jobs that are still running
+ */
+export async function listJobs(): Promise<JobInfo[]> {
+  return invoke<JobInfo[]>("list_jobs");
+}
+
+/**
+ * Listen for progress of running jobs
+ */
+export function onJobProgress(callback: (progress: JobProgress) => void): Promise<UnlistenFn> {
+  return listen<JobProgress>("job-progress", (event) => {
+    callback(event.payload);
+  });
+}
+
+/**
+ * Listen for partial results of running jobs
+ */
+export function onJobPartial(callback: (partial: JobPartial) => void): Promise<UnlistenFn> {
+  return listen<JobPartial>("job-partial", (event) => {
+    callback(event.payload);
+  });
+}
+
+/**
+ * Listen for jobs finishing, failing or being cancelled
+ */
+export function onJobDone(callback: (done: JobDone) => void): Promise<UnlistenFn> {
+  return listen<JobDone>("job-done", (event) => {
+    callback(event.payload);
+  });
+}
+
+export interface ImportFailure {
+  /** Title or path of the item that couldn't be imported */
+  item: string;
+  error: string;
+}
+
+export interface ImportSummary {
+  /** Paths of the notes written */
+  imported: string[];
+  /** Number of attachments saved */
+  attachments: number;
+  failed: ImportFailure[];
+}
+
+/**
+ * Import an Evernote .enex export into a folder as markdown notes
+ */
+export async function importEnex(file: string, destDir: string): Promise<ImportSummary> {
+  return invoke<ImportSummary>("import_enex", { file, destDir });
+}
+
+/**
+ * Import a Notion export zip into a folder, cleaning up file names and links
+ * and turning databases into markdown tables
+ */
+export async function importNotionZip(zipPath: string, destDir: string): Promise<ImportSummary> {
+  return invoke<ImportSummary>("import_notion_zip", { zipPath, destDir });
+}
+
+/**
+ * Import a Logseq graph folder: pages and journals become notes, the
+ * outline paragraphs and lists, and assets attachments
+ */
+export async function importLogseq(dir: string, destDir: string): Promise<ImportSummary> {
+  return invoke<ImportSummary>("import_logseq", { dir, destDir });
+}
+
+/**
+ * Import a Joplin .jex export: notebooks become folders, resources
+ * attachments, and the links between them relative paths
+ */
+export async function importJoplinJex(file: string, destDir: string): Promise<ImportSummary> {
+  return invoke<ImportSummary>("import_joplin_jex", { file, destDir });
+}
+
+/**
+ * Import a Day One JSON export (or its zip) into a folder, one note per
+ * entry laid out like the vault's daily notes, with location and weather in
+ * the frontmatter and photos saved as attachments
+ */
+export async function importDayone(file: string, destDir: string): Promise<ImportSummary> {
+  return invoke<ImportSummary>("import_dayone", { file, destDir });
+}
+
+/**
+ * Import an OPML outline or feed list as a note with a nested list, or with
+ * `split` one note per top-level outline
+ */
+export async function importOpml(file: string, destDir: string, split?: boolean): Promise<ImportSummary> {
+  return invoke<ImportSummary>("import_opml", { file, destDir, split });
+}
+
+/**
+ * Export a note's headings and lists as an OPML outline
+ */
+export async function exportOpml(path: string, outPath: string): Promise<void> {
+  return invoke<void>("export_opml", { path, outPath });
+}
+
+/**
+ * Convert HTML, such as pasted rich text, to markdown
+ */
+export async function htmlToMarkdown(html: string): Promise<string> {
+  return invoke<string>("html_to_markdown", { html });
+}
+
+export interface ClippedNote {
+  /** Path of the note written */
+  path: string;
+  title: string;
+  /** Number of images downloaded */
+  images: number;
+  /** Images that couldn't be downloaded; the note keeps linking to them */
+  failed: ImportFailure[];
+}
+
+/**
+ * Save the article on a web page as a markdown note, downloading its images
+ */
+export async function clipUrl(url: string, destDir: string): Promise<ClippedNote> {
+  return invoke<ClippedNote>("clip_url", { url, destDir });
+}
+
+export interface UrlTitle {
+  /** URL after redirects */
+  url: string;
+  /** OpenGraph title, else the page's <title>; null if it has neither */
+  title: string | null;
+  description: string | null;
+  site_name: string | null;
+  /** Preview image URL */
+  image: string | null;
+}
+
+/**
+ * Fetch a page's title and OpenGraph data from the backend, which isn't
+ * subject to CORS, e.g. to turn a pasted URL into a `[Title](url)` link
+ */
+export async function fetchUrlTitle(url: string): Promise<UrlTitle> {
+  return invoke<UrlTitle>("fetch_url_title", { url });
+}
+
+export interface CaptureServerStatus {
+  running: boolean;
+  port: number;
+  /** Root of the vault served */
+  vault: string | null;
+  /** Bearer token clients must send */
+  token: string;
+  /** Base URL, e.g. http://127.0.0.1:27183 */
+  url: string;
+}
+
+/**
+ * Start the local capture server for a vault and keep it on across launches.
+ * It listens on 127.0.0.1 only and every request needs the token, as
+ * `Authorization: Bearer <token>`: `POST /capture` appends the markdown body
+ * (or JSON `{ text, note? }`) to the inbox note, and `GET /notes/<path>`
+ * returns a note.
+ */
+export async function startCaptureServer(root: string, port?: number): Promise<CaptureServerStatus> {
+  return invoke<CaptureServerStatus>("start_capture_server", { root, port });
+}
+
+/**
+ * Stop the capture server and keep it off across launches
+ */
+export async function stopCaptureServer(): Promise<CaptureServerStatus> {
+  return invoke<CaptureServerStatus>("stop_capture_server");
+}
+
+/**
+ * Get whether the capture server is running, where, and its token
+ */
+export async function getCaptureServerStatus(): Promise<CaptureServerStatus> {
+  return invoke<CaptureServerStatus>("get_capture_server_status");
+}
+
+/**
+ * Replace the capture server's token, locking out clients using the old one
+ */
+export async function resetCaptureToken(): Promise<CaptureServerStatus> {
+  return invoke<CaptureServerStatus>("reset_capture_token");
+}
+
+export interface PreviewServerStatus {
+  running: boolean;
+  port: number | null;
+  /** Root of the vault served */
+  vault: string | null;
+  /** Whether other devices on the network can connect */
+  lan: boolean;
+  /** Address to open the preview at, including its access key */
+  url: string | null;
+}
+
+/**
+ * Serve a vault as rendered pages (27184 by default) for previewing notes in
+ * a browser. Pages reload when their note changes. With `lan`, other devices
+ * on the network can connect too. Every page needs the access key in the
+ * returned URL, which changes on each start.
+ */
+export async function startPreviewServer(root: string, port?: number, lan = false): Promise<PreviewServerStatus> {
+  return invoke<PreviewServerStatus>("start_preview_server", { root, port, lan });
+}
+
+/**
+ * Stop the preview server
+ */
+export async function stopPreviewServer(): Promise<PreviewServerStatus> {
+  return invoke<PreviewServerStatus>("stop_preview_server");
+}
+
+/**
+ * Get whether the preview server is running, and where
+ */
+export async function getPreviewServerStatus(): Promise<PreviewServerStatus> {
+  return invoke<PreviewServerStatus>("get_preview_server_status");
+}
+
+export interface SavedAttachment {
+  /** Path of the attachment */
+  path: string;
+  /** Link target for the attachment, relative to the note */
+  link: string;
+  /** An identical file was already in the attachments folder and was reused */
+  existing: boolean;
+}
+
+/**
+ * Save pasted or dropped file data (bytes, base64 or a data: URL) to the
+ * vault's attachments folder and get the link to insert into the note
+ */
+export async function saveAttachment(
+  data: Uint8Array | number[] | string,
+  suggestedName: string | null,
+  notePath: string
+): Promise<SavedAttachment> {
+  const payload = data instanceof Uint8Array ? Array.from(data) : data;
+  return invoke<SavedAttachment>("save_attachment", { data: payload, suggestedName, notePath });
+}
+
+export interface LocalizedImage {
+  /** Remote URL the image was linked by */
+  url: string;
+  /** Path of the downloaded copy */
+  path: string;
+}
+
+export interface LocalizeReport {
+  /** Images downloaded, in the order they appear in the note */
+  localized: LocalizedImage[];
+  /** Images that couldn't be downloaded; their links are left as they were */
+  failed: ImportFailure[];
+  /** The note as written, or null if no link changed */
+  written: WriteResult | null;
+}
+
+/**
+ * Download a note's remote images into the attachments folder and rewrite
+ * their links to the local copies
+ */
+export async function localizeImages(path: string): Promise<LocalizeReport> {
+  return invoke<LocalizeReport>("localize_images", { path });
+}
+
+export interface OrphanAttachment {
+  path: string;
+  /** Size in bytes */
+  size: number;
+}
+
+/**
+ * List attachments in the vault's attachments folder that no note links to.
+ * With `trash` set they are moved to the system trash as well.
+ */
+export async function findOrphanAttachments(root: string, trash = false): Promise<OrphanAttachment[]> {
+  return invoke<OrphanAttachment[]>("find_orphan_attachments", { root, trash });
+}
+
+export interface Version {
+  id: string;
+  /** When the snapshot was taken, in milliseconds since the Unix epoch */
+  timestamp: number;
+  /** Size in bytes */
+  size: number;
+}
+
+/**
+ * List the saved versions of a file, newest first
+ */
+export async function listVersions(path: string): Promise<Version[]> {
+  return invoke<Version[]>("list_versions", { path });
+}
+
+/**
+ * Read a saved version of a file
+ */
+export async function readVersion(path: string, id: string): Promise<string> {
+  return invoke<string>("read_version", { path, id });
+}
+
+/**
+ * Replace a file with a saved version. The current contents stay in the
+ * history, so the restore can be undone.
+ */
+export async function restoreVersion(path: string, id: string): Promise<WriteResult> {
+  return invoke<WriteResult>("restore_version", { path, id });
+}
+
+export interface DiffLine {
+  kind: "context" | "added" | "removed";
+  /** The line without its line ending */
+  text: string;
+  /** 1-based line number in the old text; null for added lines */
+  old_line: number | null;
+  /** 1-based line number in the new text; null for removed lines */
+  new_line: number | null;
+}
+
+export interface Hunk {
+  old_start: number;
+  old_lines: number;
+  new_start: number;
+  new_lines: number;
+  lines: DiffLine[];
+}
+
+export interface TextDiff {
+  hunks: Hunk[];
+  added: number;
+  removed: number;
+}
+
+/**
+ * Line diff of two texts, with `context` unchanged lines around each change
+ */
+export async function diffText(a: string, b: string, context?: number): Promise<TextDiff> {
+  return invoke<TextDiff>("diff_text", { a, b, context });
+}
+
+/**
+ * Line diff between two saved versions of a file; either id can be
+ * "current" for the file as it is now. Encrypted notes need their vault
+ * unlocked.
+ */
+export async function diffVersions(path: string, idA: string, idB: string, context?: number): Promise<TextDiff> {
+  return invoke<TextDiff>("diff_versions", { path, idA, idB, context });
+}
+
+export type GitFileState =
+  | "unmodified"
+  | "modified"
+  | "added"
+  | "deleted"
+  | "renamed"
+  | "copied"
+  | "type_changed"
+  | "untracked"
+  | "conflicted";
+
+export interface GitFileStatus {
+  path: string;
+  /** Previous path, for renames and copies */
+  from: string | null;
+  /** Change staged in the index */
+  index: GitFileState;
+  /** Change in the working tree that isn't staged */
+  worktree: GitFileState;
+}
+
+export interface GitStatus {
+  /** Checked-out branch; null for a detached HEAD */
+  branch: string | null;
+  upstream: string | null;
+  ahead: number;
+  behind: number;
+  /** Changed files under the vault root */
+  files: GitFileStatus[];
+}
+
+export interface GitCommit {
+  hash: string;
+  short_hash: string;
+  author: string;
+  email: string;
+  /** Author date, in milliseconds since the Unix epoch */
+  timestamp: number;
+  subject: string;
+}
+
+/**
+ * Branch and changed files of the git repository a vault is in.
+ * Rejects with a ToolError if git is missing or fails.
+ */
+export async function gitStatus(root: string): Promise<GitStatus> {
+  return invoke<GitStatus>("git_status", { root });
+}
+
+/**
+ * Commits that touched a note (following renames) or a folder, newest first
+ */
+export async function gitLog(path: string, limit?: number): Promise<GitCommit[]> {
+  return invoke<GitCommit[]>("git_log", { path, limit });
+}
+
+/**
+ * Line diff of a note between a commit (HEAD by default) and the working copy
+ */
+export async function gitDiff(path: string, rev?: string): Promise<TextDiff> {
+  return invoke<TextDiff>("git_diff", { path, rev });
+}
+
+/** Payload of the "git-autocommit" event */
+export interface AutocommitEvent {
+  root: string;
+  /** Hash of the new commit; null if there was nothing to commit or it failed */
+  commit: string | null;
+  /** Number of files committed */
+  files: number;
+  message: string | null;
+  error: string | null;
+}
+
+/**
+ * Commit pending changes of every vault with auto-commit on right away,
+ * e.g. when the window loses focus. Results are also sent as
+ * "git-autocommit" events.
+ */
+export async function flushAutocommit(): Promise<AutocommitEvent[]> {
+  return invoke<AutocommitEvent[]>("flush_autocommit");
+}
+
+export interface BackupInfo {
+  path: string;
+  name: string;
+  /** When the backup was made, in milliseconds since the Unix epoch */
+  created: number;
+  /** Size of the archive in bytes */
+  size: number;
+}
+
+/** Payload of the "vault-backup" event sent after scheduled backups */
+export interface BackupEvent {
+  root: string;
+  /** null if nothing changed since the last backup, or it failed */
+  backup: BackupInfo | null;
+  error: string | null;
+}
+
+export interface RestoreSummary {
+  /** Files written from the backup */
+  restored: number;
+  /** Files already as they are in the backup */
+  unchanged: number;
+}
+
+/**
+ * Back up a vault to a timestamped zip archive in `outDir`, or the vault's
+ * backup folder, leaving out ignored files. Old backups beyond the vault's
+ * limit are removed.
+ */
+export async function backupVault(root: string, outDir?: string): Promise<BackupInfo> {
+  return invoke<BackupInfo>("backup_vault", { root, outDir });
+}
+
+/**
+ * List a vault's backups in `outDir`, or its backup folder, newest first
+ */
+export async function listBackups(root: string, outDir?: string): Promise<BackupInfo[]> {
+  return invoke<BackupInfo[]>("list_backups", { root, outDir });
+}
+
+/**
+ * Restore the files in a backup into the vault. Files that differ are
+ * overwritten, with their current contents kept in the file history; files
+ * the backup doesn't have are left alone.
+ */
+export async function restoreBackup(zipPath: string, root: string): Promise<RestoreSummary> {
+  return invoke<RestoreSummary>("restore_backup", { zipPath, root });
+}
+
+/** Payload of the "git-sync-progress" event */
+export interface SyncProgress {
+  root: string;
+  stage: "fetching" | "integrating" | "pushing" | "done";
+  completed: number;
+  total: number;
+}
+
+export interface SyncOptions {
+  /** Merge upstream changes instead of rebasing local commits onto them */
+  merge?: boolean;
+}
+
+/** A file both sides changed, left with conflict markers in the working copy */
+export interface GitConflict {
+  path: string;
+  /** The common ancestor's version; null if the file didn't exist yet */
+  base: string | null;
+  /** This device's version; null if it was deleted here */
+  local: string | null;
+  /** The remote's version; null if it was deleted there */
+  remote: string | null;
+}
+
+export interface GitSyncResult {
+  /** Commits brought in from the remote */
+  pulled: number;
+  /** Commits pushed to the remote */
+  pushed: number;
+  /** Files to resolve before gitSyncContinue; nothing was pushed if non-empty */
+  conflicts: GitConflict[];
+}
+
+export interface ResolvedFile {
+  path: string;
+  /** The merged contents to keep */
+  content: string;
+}
+
+/**
+ * Fetch, rebase (or merge) onto the upstream branch and push. Progress is
+ * sent as "git-sync-progress" events.
+ */
+export async function gitSync(root: string, options?: SyncOptions): Promise<GitSyncResult> {
+  return invoke<GitSyncResult>("git_sync", { root, options });
+}
+
+/**
+ * Carry on with a sync that stopped on conflicts, keeping the resolved
+ * contents given
+ */
+export async function gitSyncContinue(root: string, resolved: ResolvedFile[]): Promise<GitSyncResult> {
+  return invoke<GitSyncResult>("git_sync_continue", { root, resolved });
+}
+
+/**
+ * Abandon a sync that stopped on conflicts, restoring the state before it
+ */
+export async function gitSyncAbort(root: string): Promise<void> {
+  return invoke<void>("git_sync_abort", { root });
+}
+
+/** A vault's WebDAV sync target */
+export interface SyncConfig {
+  /** Remote folder, e.g. https://cloud.example.com/remote.php/dav/files/me/Notes/ */
+  url: string;
+  username: string;
+  /** Kept in the settings file; prefer an app password */
+  password: string;
+}
+
+export interface SyncStatus {
+  root: string;
+  configured: boolean;
+  url: string | null;
+  username: string | null;
+  syncing: boolean;
+  /** Milliseconds since the Unix epoch of the last completed sync */
+  last_sync: number | null;
+  last_error: string | null;
+  /** Files known to be in sync as of the last sync */
+  tracked_files: number;
+}
+
+/** A file changed on both sides; it now has the remote's version */
+export interface SyncConflict {
+  path: string;
+  /** Where this device's version was kept */
+  conflict_copy: string;
+}
+
+export interface SyncReport {
+  uploaded: string[];
+  downloaded: string[];
+  /** Deleted on the remote, so moved to the trash here */
+  deleted_local: string[];
+  /** Deleted here, so deleted on the remote */
+  deleted_remote: string[];
+  conflicts: SyncConflict[];
+  /** Retried on the next sync */
+  failed: ImportFailure[];
+}
+
+/**
+ * Set up WebDAV sync for a vault, or turn it off with null. Creates the
+ * remote folder if needed, which also checks the URL and credentials.
+ */
+export async function configureSync(root: string, config: SyncConfig | null): Promise<SyncStatus> {
+  return invoke<SyncStatus>("configure_sync", { root, config });
+}
+
+/**
+ * Two-way sync of a vault with its WebDAV folder
+ */
+export async function syncNow(root: string): Promise<SyncReport> {
+  return invoke<SyncReport>("sync_now", { root });
+}
+
+/**
+ * Whether a vault syncs and how its last sync went
+ */
+export async function syncStatus(root: string): Promise<SyncStatus> {
+  return invoke<SyncStatus>("sync_status", { root });
+}
+
+/** Naming scheme of a sync conflict file */
+export type ConflictKind = "conflicted_copy" | "syncthing" | "owncloud";
+
+/** A copy a sync client made of a file that changed on two devices */
+export interface ConflictFile {
+  path: string;
+  /** The file it is a conflicting version of */
+  original: string;
+  original_exists: boolean;
+  kind: ConflictKind;
+  size: number;
+  /** Milliseconds since the Unix epoch */
+  modified: number | null;
+}
+
+export interface ConflictMerge {
+  /** Merged text, with conflict markers where both versions changed the same lines */
+  merged: string;
+  /** Regions left with markers; 0 if the merge is clean */
+  conflicts: number;
+  /** History version used as the common ancestor; null if none was found */
+  base: string | null;
+  /** Line diff from the original to the conflict file */
+  diff: TextDiff;
+}
+
+/**
+ * Conflict files ("conflicted copy", ".sync-conflict-") that sync clients
+ * left in a vault
+ */
+export async function findSyncConflicts(root: string): Promise<ConflictFile[]> {
+  return invoke<ConflictFile[]>("find_sync_conflicts", { root });
+}
+
+/**
+ * Three-way merge of a conflict file into its original. The common ancestor
+ * is history version `base`, or the likeliest one if omitted. Nothing is
+ * written.
+ */
+export async function mergeConflict(original: string, conflict: string, base?: string): Promise<ConflictMerge> {
+  return invoke<ConflictMerge>("merge_conflict", { original, conflict, base });
+}
+
+/**
+ * Confine file commands to the vault at `path`, replacing the previous
+ * one. Returns the canonical path. Only folders picked with
+ * `openFolderDialog` or opened with the app are accepted.
+ */
+export async function setVaultRoot(path: string): Promise<string> {
+  return invoke<string>("set_vault_root", { path });
+}
+
+/**
+ * Open a folder picker dialog; the picked folder becomes the open vault
+ */
+export async function openFolderDialog(): Promise<string | null> {
+  return invoke<string | null>("pick_vault_folder");
+}
+
+/**
+ * Open a file picker dialog for notes; the picked file can be read and
+ * saved even outside the vault
+ */
+export async function openFileDialog(): Promise<string | null> {
+  return invoke<string | null>("pick_note_file");
+}
+
+/**
+ * Open a file picker dialog, e.g. for a file to import; the picked file can
+ * be read even outside the vault. `extensions` are without the dot.
+ */
+export async function pickFile(title?: string, extensions?: string[]): Promise<string | null> {
+  return invoke<string | null>("pick_file", { title, extensions });
+}
+
+/**
+ * Open a save dialog, e.g. for where to export to; the picked path can be
+ * written even outside the vault
+ */
+export async function pickSavePath(
+  title?: string,
+  defaultName?: string,
+  extensions?: string[],
+): Promise<string | null> {
+  return invoke<string | null>("pick_save_path", { title, defaultName, extensions });
+}
+
+/**
+ * Open a folder picker dialog, e.g. for where to export a site to; files in
+ * the picked folder can be read and written even outside the vault
+ */
+export async function pickFolder(title?: string): Promise<string | null> {
+  return invoke<string | null>("pick_folder", { title });
+}
+
+/**
+ * Unlock the encrypted notes of a vault. The first unlock sets the vault's
+ * password; later ones reject with "Wrong password" if it doesn't match.
+ */
+export async function unlockVault(root: string, password: string): Promise<void> {
+  return invoke("unlock_vault", { root, password });
+}
+
+/**
+ * Forget a vault's key, so its encrypted notes can't be read until it is unlocked again
+ */
+export async function lockVault(root: string): Promise<void> {
+  return invoke("lock_vault", { root });
+}
+
+export async function isVaultUnlocked(root: string): Promise<boolean> {
+  return invoke<boolean>("is_vault_unlocked", { root });
+}
+
+/**
+ * Encrypt a note in place; its vault must be unlocked. Its history is deleted.
+ */
+export async function encryptNote(path: string): Promise<WriteResult> {
+  return invoke<WriteResult>("encrypt_note", { path });
+}
+
+/**
+ * Store an encrypted note as plaintext again
+ */
+export async function decryptNote(path: string): Promise<WriteResult> {
+  return invoke<WriteResult>("decrypt_note", { path });
+}
+
+/**
+ * Returned (as the rejection value) when reading or saving an encrypted
+ * note whose vault is locked
+ */
+export interface NoteLockedError {
+  kind: "note_locked";
+  path: string;
+}
+
+export function isNoteLockedError(error: unknown): error is NoteLockedError {
+  return typeof error === "object" && error !== null && (error as { kind?: string }).kind === "note_locked";
+}
+
+/**
+ * Open a URL in the default browser
+ */
+export async function openUrl(url: string): Promise<void> {
+  try {
+    await tauriOpenUrl(url);
+  } catch (error) {
+    console.error("Failed to open URL:", error);
+  }
+}
+
+/**
+ * Start watching the open vault, replacing the previous vault watcher.
+ * Returns the watcher ID.
+ */
+export async function watchDirectory(
+  path: string,
+  ignoreGlobs?: string[],
+  debounceMs?: number
+): Promise<number> {
+  return invoke<number>("watch_directory", { path, ignoreGlobs, debounceMs });
+}
+
+export interface WatchInfo {
+  id: number;
+  path: string;
+  recursive: boolean;
+}
+
+/**
+ * Watch an additional file or directory; returns an ID for unwatch()
+ */
+export async function watchPath(
+  path: string,
+  recursive = true,
+  ignoreGlobs?: string[],
+  debounceMs?: number
+): Promise<number> {
+  return invoke<number>("watch_path", { path, recursive, ignoreGlobs, debounceMs });
+}
+
+/**
+ * Stop a watcher started with watchPath() or watchDirectory()
+ */
+export async function unwatch(id: number): Promise<void> {
+  return invoke("unwatch", { id });
+}
+
+/** Payload of the `open-file-changed` event */
+export interface OpenFileChangedEvent {
+  path: string;
+  /** False if the file was deleted or moved away */
+  exists: boolean;
+  /** Hash of the new contents; compare with WriteResult.hash to skip own saves */
+  hash: string | null;
+  mtime: number | null;
+  watcher_id: number;
+}
+
+/**
+ * Watch the note open in the editor, replacing the previous one.
+ * Emits `open-file-changed` when it changes on disk. Returns the watcher ID.
+ */
+export async function watchFile(path: string): Promise<number> {
+  return invoke<number>("watch_file", { path });
+}
+
+/**
+ * Stop watching the open note
+ */
+export async function unwatchFile(): Promise<void> {
+  return invoke("unwatch_file");
+}
+
+/**
+ * List the active watchers
+ */
+export async function listWatchers(): Promise<WatchInfo[]> {
+  return invoke<WatchInfo[]>("list_watchers");
+}
+
+export interface WatcherStatus extends WatchInfo {
+  /** Whether the watcher is running and its path still exists */
+  alive: boolean;
+  /** Successful restarts after errors */
+  restarts: number;
+  last_error: string | null;
+}
+
+/** Payload of the `watcher-error` event */
+export interface WatcherErrorEvent {
+  watcher_id: number;
+  path: string;
+  message: string;
+  /** False once the backend has given up restarting the watcher */
+  restarting: boolean;
 }
 
 /**
- * Start watching a directory for file changes
+ * Get the health of each watcher
  */
-export async function watchDirectory(path: string): Promise<void> {
-  return invoke("watch_directory", { path });
+export async function getWatcherStatus(): Promise<WatcherStatus[]> {
+  return invoke<WatcherStatus[]>("watcher_status");
 }
 
 /**